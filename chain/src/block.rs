@@ -3,9 +3,12 @@ use alloc::{vec, vec::Vec};
 use core::str;
 
 use light_bitcoin_primitives::H256;
-use light_bitcoin_serialization::{deserialize, Deserializable, Serializable};
+use light_bitcoin_serialization::{
+    deserialize, Deserializable, Serializable, SERIALIZE_TRANSACTION_WITNESS,
+};
 
 use crate::block_header::BlockHeader;
+use crate::constants::WITNESS_SCALE_FACTOR;
 use crate::merkle_root::merkle_root;
 use crate::transaction::Transaction;
 
@@ -67,6 +70,13 @@ impl Block {
         merkle_root(&hashes)
     }
 
+    /// Returns the weight of the block: three times its size without witness plus its size
+    /// with witness (BIP141).
+    pub fn weight(&self) -> usize {
+        self.serialized_size() * (WITNESS_SCALE_FACTOR - 1)
+            + self.serialized_size_with_flags(SERIALIZE_TRANSACTION_WITNESS)
+    }
+
     /// Return the block header.
     pub fn header(&self) -> &BlockHeader {
         &self.header
@@ -82,7 +92,8 @@ impl Block {
 mod tests {
     use light_bitcoin_primitives::h256_rev;
 
-    use super::Block;
+    use super::{Block, Transaction};
+    use crate::IndexedBlock;
 
     // Block 80000
     // https://blockchain.info/rawblock/000000000043a8c0fd1d6f726790caa2a406010d19efd2780db27bdbbd93baf6
@@ -95,4 +106,25 @@ mod tests {
         let hash = h256_rev("000000000043a8c0fd1d6f726790caa2a406010d19efd2780db27bdbbd93baf6");
         assert_eq!(block.hash(), hash);
     }
+
+    #[test]
+    fn test_block_weight() {
+        // Block 80000, without witness
+        let raw = "01000000ba8b9cda965dd8e536670f9ddec10e53aab14b20bacad27b9137190000000000190760b278fe7b8565fda3b968b918d5fd997f993b23674c0af3b6fde300b38f33a5914ce6ed5b1b01e32f570201000000010000000000000000000000000000000000000000000000000000000000000000ffffffff0704e6ed5b1b014effffffff0100f2052a01000000434104b68a50eaa0287eff855189f949c1c6e5f58b37c88231373d8a59809cbae83059cc6469d65c665ccfd1cfeb75c6e8e19413bba7fbff9bc762419a76d87b16086eac000000000100000001a6b97044d03da79c005b20ea9c0e1a6d9dc12d9f7b91a5911c9030a439eed8f5000000004948304502206e21798a42fae0e854281abd38bacd1aeed3ee3738d9e1446618c4571d1090db022100e2ac980643b0b82c0e88ffdfec6b64e3e6ba35e7ba5fdd7d5d6cc8d25c6b241501ffffffff0100f2052a010000001976a914404371705fa9bd789a2fcd52d2c580b65d35549d88ac00000000";
+        let block: Block = raw.parse().unwrap();
+        assert_eq!(block.weight(), raw.len() / 2 * 4);
+        assert_eq!(IndexedBlock::from(block).weight(), raw.len() / 2 * 4);
+
+        // A block of a segwit transaction
+        let block: Block = "0000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000010000000000010100000000000000000000000000000000000000000000000000000000000000000000000000000000000001010000000000".parse().unwrap();
+        assert_eq!(
+            block.weight(),
+            block
+                .transactions
+                .iter()
+                .map(Transaction::weight)
+                .sum::<usize>()
+                + 81 * 4
+        );
+    }
 }
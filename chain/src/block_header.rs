@@ -3,12 +3,14 @@ use alloc::vec::Vec;
 use core::{fmt, str};
 
 use light_bitcoin_crypto::dhash256;
-use light_bitcoin_primitives::{hash_rev, Compact, H256};
+use light_bitcoin_primitives::{hash_rev, Compact, H256, U256};
 use light_bitcoin_serialization::{deserialize, serialize, Deserializable, Reader, Serializable};
 
 #[cfg(feature = "std")]
 use serde::{Deserialize, Serialize};
 
+use crate::error::Error;
+
 /// A block header, which contains all the block's information except
 /// the actual transactions
 #[derive(Ord, PartialOrd, Eq, PartialEq, Copy, Clone, Default)]
@@ -65,6 +67,27 @@ impl BlockHeader {
     pub fn hash(&self) -> H256 {
         dhash256(&serialize(self))
     }
+
+    /// Returns the target encoded by `bits`, which the header hash must not exceed.
+    pub fn target(&self) -> Result<U256, Error> {
+        self.bits.target().ok_or(Error::InvalidTarget)
+    }
+
+    /// Returns the work of the header, which is added to the chainwork of its chain.
+    pub fn work(&self) -> U256 {
+        self.bits.work()
+    }
+
+    /// Checks that the header hash, as a little-endian number, meets the target of `bits`.
+    ///
+    /// The target is not compared to the proof of work limit of the network.
+    pub fn validate_pow(&self) -> Result<(), Error> {
+        let target = self.target()?;
+        if U256::from_little_endian(self.hash().as_bytes()) > target {
+            return Err(Error::HighHash);
+        }
+        Ok(())
+    }
 }
 
 impl codec::Encode for BlockHeader {
@@ -204,4 +227,40 @@ mod tests {
         let got = reader.read::<BlockHeader>().unwrap_err();
         assert_eq!(got, io::Error::UnexpectedEof);
     }
+
+    #[test]
+    fn test_block_header_pow() {
+        // Block 80000
+        let mut block_header = BlockHeader {
+            version: 1,
+            previous_header_hash: h256_rev(
+                "00000000001937917bd2caba204bb1aa530ec1de9d0f6736e5d85d96da9c8bba",
+            ),
+            merkle_root_hash: h256_rev(
+                "8fb300e3fdb6f30a4c67233b997f99fdd518b968b9a3fd65857bfe78b2600719",
+            ),
+            time: 1284613427,
+            bits: 459009510.into(),
+            nonce: 1462756097,
+        };
+        assert_eq!(
+            block_header.hash(),
+            h256_rev("000000000043a8c0fd1d6f726790caa2a406010d19efd2780db27bdbbd93baf6")
+        );
+        assert_eq!(block_header.validate_pow(), Ok(()));
+        assert_eq!(
+            block_header.target(),
+            Ok(U256::from(0x5bede6) << (8 * (0x1b - 3)))
+        );
+        assert_eq!(block_header.work(), U256::from(3_061_863_899_400u64));
+
+        block_header.nonce += 1;
+        assert_eq!(block_header.validate_pow(), Err(Error::HighHash));
+
+        // Negative target
+        block_header.bits = 0x1b_dbede6.into();
+        assert_eq!(block_header.target(), Err(Error::InvalidTarget));
+        assert_eq!(block_header.validate_pow(), Err(Error::InvalidTarget));
+        assert_eq!(block_header.work(), U256::zero());
+    }
 }
@@ -0,0 +1,455 @@
+//! BIP158 compact block filters
+//!
+//! A basic filter is a Golomb-Coded Set of the output scripts of a block and of the scripts spent
+//! by its inputs. Clients download the filters, check them against the scripts they watch and
+//! only download the blocks that match.
+//!
+//! https://github.com/bitcoin/bips/blob/master/bip-0158.mediawiki
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use light_bitcoin_crypto::{dhash256, siphash24};
+use light_bitcoin_primitives::{Bytes, H256};
+use light_bitcoin_serialization::{CompactInteger, Reader, Serializable, Stream};
+
+use crate::error::Error;
+use crate::indexed_block::IndexedBlock;
+use crate::transaction::OutPoint;
+
+/// Golomb-Rice coding parameter of basic filters.
+pub const BASIC_FILTER_P: u8 = 19;
+/// Inverse of the false positive rate of basic filters.
+pub const BASIC_FILTER_M: u64 = 784_931;
+
+/// First byte of the scripts excluded from basic filters (`OP_RETURN`).
+const OP_RETURN: u8 = 0x6a;
+
+/// Block filter, the content of the `cfilter` message.
+#[derive(Eq, PartialEq, Clone, Debug, Default)]
+pub struct BlockFilter {
+    /// Number of elements followed by the Golomb-Rice coded set
+    pub content: Vec<u8>,
+}
+
+impl BlockFilter {
+    /// Creates the filter from its serialized content.
+    pub fn new(content: Vec<u8>) -> Self {
+        BlockFilter { content }
+    }
+
+    /// Builds the basic filter of the block.
+    ///
+    /// `spent_script` returns the script of the output spent by an input, the scripts of every
+    /// input except the coinbase one are needed.
+    pub fn new_basic<F>(block: &IndexedBlock, mut spent_script: F) -> Result<Self, Error>
+    where
+        F: FnMut(&OutPoint) -> Option<Bytes>,
+    {
+        let mut elements = Vec::new();
+        for tx in &block.transactions {
+            elements.extend(
+                tx.raw
+                    .outputs
+                    .iter()
+                    .map(|output| output.script_pubkey.clone())
+                    .filter(|script| !script.is_empty() && script[0] != OP_RETURN),
+            );
+            if tx.raw.is_coinbase() {
+                continue;
+            }
+            for input in &tx.raw.inputs {
+                let script = spent_script(&input.previous_output).ok_or(Error::MissingPrevout)?;
+                if !script.is_empty() {
+                    elements.push(script);
+                }
+            }
+        }
+        elements.sort_unstable();
+        elements.dedup();
+
+        let filter = GcsFilter::basic(block.hash(), elements.len() as u64);
+        let mut values = elements
+            .iter()
+            .map(|element| filter.hash(element))
+            .collect::<Vec<_>>();
+        values.sort_unstable();
+
+        let mut stream = Stream::new();
+        stream.append(&CompactInteger::from(values.len()));
+        let mut content: Vec<u8> = stream.out().into();
+        content.extend(filter.encode(&values));
+        Ok(BlockFilter { content })
+    }
+
+    /// Returns the hash of the filter.
+    pub fn filter_hash(&self) -> H256 {
+        dhash256(&self.content)
+    }
+
+    /// Returns the header of the filter, which commits to the headers of the filters of all the
+    /// previous blocks (the previous header of the genesis filter is zero).
+    pub fn filter_header(&self, previous_header: &H256) -> H256 {
+        let mut data = Vec::with_capacity(64);
+        data.extend_from_slice(self.filter_hash().as_bytes());
+        data.extend_from_slice(previous_header.as_bytes());
+        dhash256(&data)
+    }
+
+    /// Returns true if the filter of the block matches at least one of the queried scripts.
+    pub fn match_any<'a, I>(&self, block_hash: &H256, query: I) -> Result<bool, Error>
+    where
+        I: IntoIterator<Item = &'a [u8]>,
+    {
+        let (filter, mut values) = self.values(block_hash)?;
+        let mut queries = filter.hash_sorted(query).into_iter().peekable();
+        let mut value = match values.next() {
+            Some(value) => value?,
+            None => return Ok(false),
+        };
+        while let Some(query) = queries.peek() {
+            if *query == value {
+                return Ok(true);
+            } else if *query < value {
+                queries.next();
+            } else {
+                value = match values.next() {
+                    Some(value) => value?,
+                    None => return Ok(false),
+                };
+            }
+        }
+        Ok(false)
+    }
+
+    /// Returns true if the filter of the block matches all the queried scripts.
+    pub fn match_all<'a, I>(&self, block_hash: &H256, query: I) -> Result<bool, Error>
+    where
+        I: IntoIterator<Item = &'a [u8]>,
+    {
+        let (filter, mut values) = self.values(block_hash)?;
+        let mut value = None;
+        for query in filter.hash_sorted(query) {
+            while value.is_none_or(|value| value < query) {
+                value = match values.next() {
+                    Some(value) => Some(value?),
+                    None => return Ok(false),
+                };
+            }
+            if value != Some(query) {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Returns the parameters of the filter and the iterator of its sorted values.
+    fn values(
+        &self,
+        block_hash: &H256,
+    ) -> Result<(GcsFilter, impl Iterator<Item = Result<u64, Error>> + '_), Error> {
+        let mut reader = Reader::new(&self.content);
+        let n: CompactInteger = reader.read().map_err(|_| Error::InvalidFilter)?;
+        let offset = n.serialized_size();
+        let n = u64::from(n);
+        // Every element takes at least P + 1 bits
+        let data = &self.content[offset..];
+        if n > data.len() as u64 * 8 / (u64::from(BASIC_FILTER_P) + 1) {
+            return Err(Error::InvalidFilter);
+        }
+        let filter = GcsFilter::basic(block_hash, n);
+
+        let mut bits = BitReader::new(data);
+        let mut value = 0u64;
+        let values = (0..n).map(move |_| {
+            value = value
+                .checked_add(filter.golomb_rice_decode(&mut bits)?)
+                .ok_or(Error::InvalidFilter)?;
+            Ok(value)
+        });
+        Ok((filter, values))
+    }
+}
+
+/// Golomb-Coded Set parameters.
+#[derive(Copy, Clone, Debug)]
+struct GcsFilter {
+    k0: u64,
+    k1: u64,
+    p: u8,
+    /// Range of the hashed values, the number of elements times M
+    range: u64,
+}
+
+impl GcsFilter {
+    /// Parameters of the basic filter of the block with `n` elements.
+    fn basic(block_hash: &H256, n: u64) -> Self {
+        let key = block_hash.as_bytes();
+        let mut k0 = [0u8; 8];
+        let mut k1 = [0u8; 8];
+        k0.copy_from_slice(&key[0..8]);
+        k1.copy_from_slice(&key[8..16]);
+        GcsFilter {
+            k0: u64::from_le_bytes(k0),
+            k1: u64::from_le_bytes(k1),
+            p: BASIC_FILTER_P,
+            range: n * BASIC_FILTER_M,
+        }
+    }
+
+    /// Maps the element uniformly to `[0, range)`.
+    fn hash(&self, element: &[u8]) -> u64 {
+        let hash = siphash24(self.k0, self.k1, element);
+        ((u128::from(hash) * u128::from(self.range)) >> 64) as u64
+    }
+
+    fn hash_sorted<'a, I>(&self, elements: I) -> Vec<u64>
+    where
+        I: IntoIterator<Item = &'a [u8]>,
+    {
+        let mut values = elements
+            .into_iter()
+            .map(|element| self.hash(element))
+            .collect::<Vec<_>>();
+        values.sort_unstable();
+        values.dedup();
+        values
+    }
+
+    /// Encodes the differences between the sorted values.
+    fn encode(&self, values: &[u64]) -> Vec<u8> {
+        let mut writer = BitWriter::default();
+        let mut last = 0;
+        for value in values {
+            self.golomb_rice_encode(&mut writer, value - last);
+            last = *value;
+        }
+        writer.data
+    }
+
+    fn golomb_rice_encode(&self, writer: &mut BitWriter, value: u64) {
+        for _ in 0..(value >> self.p) {
+            writer.write_bit(true);
+        }
+        writer.write_bit(false);
+        writer.write(value, self.p);
+    }
+
+    fn golomb_rice_decode(&self, reader: &mut BitReader) -> Result<u64, Error> {
+        let mut quotient = 0u64;
+        while reader.read_bit()? {
+            quotient += 1;
+        }
+        let remainder = reader.read(self.p)?;
+        quotient
+            .checked_shl(u32::from(self.p))
+            .filter(|value| value >> self.p == quotient)
+            .map(|value| value + remainder)
+            .ok_or(Error::InvalidFilter)
+    }
+}
+
+/// Writer of bits, most significant bit first.
+#[derive(Default)]
+struct BitWriter {
+    data: Vec<u8>,
+    bits: usize,
+}
+
+impl BitWriter {
+    fn write_bit(&mut self, bit: bool) {
+        if self.bits.is_multiple_of(8) {
+            self.data.push(0);
+        }
+        if bit {
+            let last = self.data.len() - 1;
+            self.data[last] |= 0x80 >> (self.bits % 8);
+        }
+        self.bits += 1;
+    }
+
+    /// Writes the `nbits` lowest bits of the value.
+    fn write(&mut self, value: u64, nbits: u8) {
+        for i in (0..nbits).rev() {
+            self.write_bit((value >> i) & 1 == 1);
+        }
+    }
+}
+
+/// Reader of bits, most significant bit first.
+struct BitReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> BitReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        BitReader { data, position: 0 }
+    }
+
+    fn read_bit(&mut self) -> Result<bool, Error> {
+        let byte = self
+            .data
+            .get(self.position / 8)
+            .ok_or(Error::InvalidFilter)?;
+        let bit = byte & (0x80 >> (self.position % 8)) != 0;
+        self.position += 1;
+        Ok(bit)
+    }
+
+    fn read(&mut self, nbits: u8) -> Result<u64, Error> {
+        let mut value = 0;
+        for _ in 0..nbits {
+            value = (value << 1) | u64::from(self.read_bit()?);
+        }
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use light_bitcoin_primitives::h256_rev;
+
+    use super::*;
+    use crate::block::Block;
+    use crate::block_header::BlockHeader;
+    use crate::transaction::{Transaction, TransactionInput, TransactionOutput};
+
+    fn testnet_genesis() -> IndexedBlock {
+        let coinbase: Transaction = "01000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000"
+            .parse()
+            .unwrap();
+        let header = BlockHeader {
+            version: 1,
+            previous_header_hash: H256::zero(),
+            merkle_root_hash: coinbase.hash(),
+            time: 1_296_688_602,
+            bits: 0x1d00ffff.into(),
+            nonce: 414_098_458,
+        };
+        Block::new(header, vec![coinbase]).into()
+    }
+
+    // https://github.com/bitcoin/bips/blob/master/bip-0158/testnet-19.json
+    #[test]
+    fn test_genesis_filter() {
+        let block = testnet_genesis();
+        assert_eq!(
+            *block.hash(),
+            h256_rev("000000000933ea01ad0ee984209779baaec3ced90fa3f408719526f8d77f4943")
+        );
+        let filter = BlockFilter::new_basic(&block, |_| None).unwrap();
+        assert_eq!(filter.content, hex::decode("019dfca8").unwrap());
+        assert_eq!(
+            filter.filter_header(&H256::zero()),
+            h256_rev("21584579b7eb08997773e5aeff3a7f932700042d0ed2a6129012b7d7ae81b750")
+        );
+
+        let script = &block.transactions[0].raw.outputs[0].script_pubkey;
+        assert!(filter
+            .match_any(block.hash(), vec![&b"other"[..], script])
+            .unwrap());
+        assert!(filter.match_all(block.hash(), vec![&script[..]]).unwrap());
+        assert!(!filter
+            .match_all(block.hash(), vec![&b"other"[..], script])
+            .unwrap());
+    }
+
+    #[test]
+    fn test_spent_scripts() {
+        let genesis = testnet_genesis();
+        let spent = genesis.transactions[0].raw.outputs[0].script_pubkey.clone();
+        let spending = Transaction {
+            version: 1,
+            inputs: vec![TransactionInput {
+                previous_output: OutPoint::new(genesis.transactions[0].hash, 0),
+                ..Default::default()
+            }],
+            outputs: vec![
+                TransactionOutput {
+                    value: 1,
+                    script_pubkey: "6a0131".parse().unwrap(),
+                },
+                TransactionOutput {
+                    value: 1,
+                    script_pubkey: "51".parse().unwrap(),
+                },
+            ],
+            lock_time: 0,
+        };
+        let mut header = genesis.header.raw;
+        header.previous_header_hash = *genesis.hash();
+        let block: IndexedBlock =
+            Block::new(header, vec![genesis.transactions[0].raw.clone(), spending]).into();
+
+        assert_eq!(
+            BlockFilter::new_basic(&block, |_| None),
+            Err(Error::MissingPrevout)
+        );
+        let filter = BlockFilter::new_basic(&block, |outpoint| {
+            assert_eq!(outpoint.txid, genesis.transactions[0].hash);
+            Some(spent.clone())
+        })
+        .unwrap();
+        // The coinbase output and the spent output have the same script, the null data
+        // output is skipped
+        assert_eq!(filter.content[0], 2);
+        let queries = [&spent[..], &[0x51][..]];
+        assert!(filter
+            .match_all(block.hash(), queries.iter().cloned())
+            .unwrap());
+        assert!(!filter
+            .match_any(block.hash(), vec![&[0x6a, 0x01, 0x31][..]])
+            .unwrap());
+
+        // Filter headers chain the filters
+        let genesis_filter = BlockFilter::new_basic(&genesis, |_| None).unwrap();
+        let genesis_header = genesis_filter.filter_header(&H256::zero());
+        assert_ne!(
+            filter.filter_header(&genesis_header),
+            filter.filter_header(&H256::zero())
+        );
+
+        let empty = BlockFilter::new(vec![0]);
+        assert!(!empty
+            .match_any(block.hash(), queries.iter().cloned())
+            .unwrap());
+        assert!(empty.match_all(block.hash(), vec![]).unwrap());
+        let truncated = BlockFilter::new(filter.content[..4].to_vec());
+        assert_eq!(
+            truncated.match_all(block.hash(), queries.iter().cloned()),
+            Err(Error::InvalidFilter)
+        );
+        let oversized = BlockFilter::new(vec![0xfe, 0xff, 0xff, 0xff, 0xff, 0]);
+        assert_eq!(
+            oversized.match_any(block.hash(), queries.iter().cloned()),
+            Err(Error::InvalidFilter)
+        );
+    }
+
+    #[test]
+    fn test_golomb_coded_set() {
+        let patterns = (0..16u8).map(|i| vec![i * 0x11; 3]).collect::<Vec<_>>();
+        let filter = GcsFilter {
+            k0: 0,
+            k1: 0,
+            p: BASIC_FILTER_P,
+            range: patterns.len() as u64 * BASIC_FILTER_M,
+        };
+        let values = filter.hash_sorted(patterns.iter().map(|p| &p[..]));
+        let encoded = filter.encode(&values);
+
+        let mut reader = BitReader::new(&encoded);
+        let mut value = 0;
+        for expected in values {
+            value += filter.golomb_rice_decode(&mut reader).unwrap();
+            assert_eq!(value, expected);
+        }
+
+        let mut writer = BitWriter::default();
+        for (value, nbits) in [(0, 1), (2, 2), (6, 3), (11, 4), (1, 5), (32, 6), (7, 7)].iter() {
+            writer.write(*value, *nbits);
+        }
+        assert_eq!(writer.data, hex::decode("5ac30070").unwrap());
+    }
+}
@@ -22,3 +22,15 @@ pub const LOCKTIME_THRESHOLD: u32 = 500_000_000; // Tue Nov  5 00:53:20 1985 UTC
 
 /// Number of Satoshis in single coin
 pub const SATOSHIS_IN_COIN: u64 = 100_000_000;
+
+/// Weight of a byte of the transaction data without witness (BIP141).
+pub const WITNESS_SCALE_FACTOR: usize = 4;
+
+/// Maximum weight of a block (BIP141).
+pub const MAX_BLOCK_WEIGHT: usize = 4_000_000;
+
+/// Maximum sigop cost of a block (BIP141).
+pub const MAX_BLOCK_SIGOPS_COST: usize = 80_000;
+
+/// Maximum amount of Satoshis, 21 million coins.
+pub const MAX_MONEY: u64 = 21_000_000 * SATOSHIS_IN_COIN;
@@ -0,0 +1,46 @@
+//! Chain validation errors
+
+/// Chain validation errors
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Error {
+    /// The `bits` of the header encode a negative, overflowing or zero target.
+    InvalidTarget,
+    /// The header hash is above its target.
+    HighHash,
+    /// The header is already stored.
+    DuplicateHeader,
+    /// The parent of the header is unknown.
+    UnknownParent,
+    /// A header needed to compute the required target is missing.
+    MissingAncestor,
+    /// The `bits` of the header differ from the required target.
+    UnexpectedBits,
+    /// The header time is not above the median time past.
+    TimeTooOld,
+    /// The header time is too far in the future.
+    TimeTooNew,
+    /// The script of an output spent by the block is missing.
+    MissingPrevout,
+    /// The block filter is not a valid Golomb-Coded Set.
+    InvalidFilter,
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Error::InvalidTarget => "Invalid proof of work target".fmt(f),
+            Error::HighHash => "Block hash does not meet its target".fmt(f),
+            Error::DuplicateHeader => "Duplicate block header".fmt(f),
+            Error::UnknownParent => "Unknown previous block header".fmt(f),
+            Error::MissingAncestor => "Missing ancestor of the block header".fmt(f),
+            Error::UnexpectedBits => "Incorrect proof of work bits".fmt(f),
+            Error::TimeTooOld => "Block time is not above the median time past".fmt(f),
+            Error::TimeTooNew => "Block time is too far in the future".fmt(f),
+            Error::MissingPrevout => "Missing script of a spent output".fmt(f),
+            Error::InvalidFilter => "Invalid block filter encoding".fmt(f),
+        }
+    }
+}
@@ -0,0 +1,521 @@
+//! Header chain of a light client.
+//!
+//! Headers are accepted in order on top of a trusted checkpoint. Each header must link to a
+//! known header, meet its proof-of-work target and the retarget rules, and have a time above the
+//! median time past and not too far in the future. The chain with the most work is the best
+//! chain, switching to a heavier fork reorganizes it.
+
+mod params;
+mod store;
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use light_bitcoin_primitives::{Compact, H256};
+
+use crate::error::Error;
+use crate::indexed_header::IndexedBlockHeader;
+
+pub use self::params::{ConsensusParams, MAX_FUTURE_BLOCK_TIME, MEDIAN_TIME_SPAN};
+pub use self::store::{HeaderStore, MemoryHeaderStore, StoredHeader};
+
+/// Effect of an accepted header on the best chain.
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub enum HeaderStatus {
+    /// The header extends the best chain.
+    Extended,
+    /// The header is stored on a side chain with no more work than the best chain.
+    SideChain,
+    /// The header makes its chain the best chain.
+    Reorganized {
+        /// Height of the last header shared by both chains.
+        fork_height: u32,
+        /// Headers of the previous best chain above the fork, from the lowest.
+        disconnected: Vec<H256>,
+    },
+}
+
+/// Header chain validating and storing headers.
+#[derive(Clone, Debug)]
+pub struct HeaderChain<S> {
+    params: ConsensusParams,
+    store: S,
+}
+
+impl<S: HeaderStore> HeaderChain<S> {
+    /// Creates the header chain of the store, which starts with the checkpoint if it is empty.
+    ///
+    /// Retargeting requires the headers of whole retarget periods, so the checkpoint should be
+    /// the first header of a period (or the genesis header). The median time past of the first
+    /// headers above the checkpoint is computed from the available headers.
+    pub fn new(params: ConsensusParams, mut store: S, checkpoint: StoredHeader) -> Self {
+        if store.best_hash().is_none() {
+            let hash = checkpoint.header.hash;
+            store.insert_header(checkpoint);
+            store.set_best_hash_at(checkpoint.height, Some(hash));
+            store.set_best_hash(hash);
+        }
+        HeaderChain { params, store }
+    }
+
+    /// Returns the consensus parameters of the chain.
+    pub fn params(&self) -> &ConsensusParams {
+        &self.params
+    }
+
+    /// Returns the header storage.
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    /// Returns the header storage, consuming the chain.
+    pub fn into_store(self) -> S {
+        self.store
+    }
+
+    /// Returns the tip of the best chain.
+    pub fn best_header(&self) -> StoredHeader {
+        self.store
+            .best_hash()
+            .and_then(|hash| self.store.header(&hash))
+            .expect("the store is initialized with the checkpoint; qed")
+    }
+
+    /// Returns the stored header of the hash, on any chain.
+    pub fn header(&self, hash: &H256) -> Option<StoredHeader> {
+        self.store.header(hash)
+    }
+
+    /// Returns the header of the best chain at the height.
+    pub fn best_header_at(&self, height: u32) -> Option<StoredHeader> {
+        self.store
+            .best_hash_at(height)
+            .and_then(|hash| self.store.header(&hash))
+    }
+
+    /// Returns the ancestor of the header at the height, which is the header itself at its own
+    /// height.
+    pub fn ancestor(&self, header: &StoredHeader, height: u32) -> Option<StoredHeader> {
+        if height > header.height {
+            return None;
+        }
+        let mut current = *header;
+        while current.height > height {
+            // Ancestors of best chain headers are found in the index
+            if self.store.best_hash_at(current.height) == Some(current.header.hash) {
+                return self.best_header_at(height);
+            }
+            current = self
+                .store
+                .header(&current.header.raw.previous_header_hash)?;
+        }
+        Some(current)
+    }
+
+    /// Returns the median time of the header and its `MEDIAN_TIME_SPAN - 1` ancestors.
+    pub fn median_time_past(&self, header: &StoredHeader) -> u32 {
+        let mut times = Vec::with_capacity(MEDIAN_TIME_SPAN);
+        let mut current = Some(*header);
+        while let Some(header) = current.filter(|_| times.len() < MEDIAN_TIME_SPAN) {
+            times.push(header.header.raw.time);
+            current = self.store.header(&header.header.raw.previous_header_hash);
+        }
+        times.sort_unstable();
+        times[times.len() / 2]
+    }
+
+    /// Returns the `bits` required for a child of the parent with the given time.
+    pub fn next_work_required(&self, parent: &StoredHeader, time: u32) -> Result<Compact, Error> {
+        let params = &self.params;
+        let interval = params.difficulty_adjustment_interval();
+        let parent_bits = parent.header.raw.bits;
+
+        if !(parent.height + 1).is_multiple_of(interval) {
+            if !params.pow_allow_min_difficulty_blocks {
+                return Ok(parent_bits);
+            }
+            // Headers more than 20 minutes after their parent may use the minimum difficulty
+            let pow_limit_bits = params.pow_limit_bits();
+            if time > parent.header.raw.time + params.pow_target_spacing * 2 {
+                return Ok(pow_limit_bits);
+            }
+            // Otherwise they use the difficulty of the last header which doesn't
+            let mut current = *parent;
+            while !current.height.is_multiple_of(interval)
+                && current.header.raw.bits == pow_limit_bits
+            {
+                match self.store.header(&current.header.raw.previous_header_hash) {
+                    Some(previous) => current = previous,
+                    None => break,
+                }
+            }
+            return Ok(current.header.raw.bits);
+        }
+
+        let first = self
+            .ancestor(parent, parent.height + 1 - interval)
+            .ok_or(Error::MissingAncestor)?;
+        Ok(params.calculate_next_work_required(
+            parent_bits,
+            first.header.raw.time,
+            parent.header.raw.time,
+        ))
+    }
+
+    /// Validates and stores the header, switching to its chain if it has the most work.
+    ///
+    /// `now` is the current UNIX time, the header time can't be more than
+    /// `MAX_FUTURE_BLOCK_TIME` after it.
+    pub fn accept_header(
+        &mut self,
+        header: IndexedBlockHeader,
+        now: u32,
+    ) -> Result<HeaderStatus, Error> {
+        if self.store.header(&header.hash).is_some() {
+            return Err(Error::DuplicateHeader);
+        }
+        let parent = self
+            .store
+            .header(&header.raw.previous_header_hash)
+            .ok_or(Error::UnknownParent)?;
+
+        header.raw.validate_pow()?;
+        if header.raw.bits != self.next_work_required(&parent, header.raw.time)? {
+            return Err(Error::UnexpectedBits);
+        }
+        if header.raw.time <= self.median_time_past(&parent) {
+            return Err(Error::TimeTooOld);
+        }
+        if header.raw.time > now.saturating_add(MAX_FUTURE_BLOCK_TIME) {
+            return Err(Error::TimeTooNew);
+        }
+
+        let stored = StoredHeader {
+            header,
+            height: parent.height + 1,
+            chainwork: parent.chainwork + header.raw.work(),
+        };
+        self.store.insert_header(stored);
+
+        let best = self.best_header();
+        if stored.chainwork <= best.chainwork {
+            return Ok(HeaderStatus::SideChain);
+        }
+        if parent.header.hash == best.header.hash {
+            self.store
+                .set_best_hash_at(stored.height, Some(header.hash));
+            self.store.set_best_hash(header.hash);
+            return Ok(HeaderStatus::Extended);
+        }
+        Ok(self.reorganize(stored, best))
+    }
+
+    /// Makes the chain of the header the best chain.
+    fn reorganize(&mut self, tip: StoredHeader, best: StoredHeader) -> HeaderStatus {
+        // Find the new chain down to the fork
+        let mut connected = Vec::new();
+        let mut current = tip;
+        while self.store.best_hash_at(current.height) != Some(current.header.hash) {
+            connected.push(current.header.hash);
+            current = self.parent(&current);
+        }
+        let fork_height = current.height;
+
+        // Find the old chain down to the fork, before the index is rewritten
+        let mut disconnected = Vec::new();
+        let mut current = best;
+        while current.height > fork_height {
+            disconnected.push(current.header.hash);
+            current = self.parent(&current);
+        }
+        disconnected.reverse();
+
+        for (height, hash) in (fork_height + 1..).zip(connected.into_iter().rev()) {
+            self.store.set_best_hash_at(height, Some(hash));
+        }
+        for height in tip.height + 1..=best.height {
+            self.store.set_best_hash_at(height, None);
+        }
+        self.store.set_best_hash(tip.header.hash);
+        HeaderStatus::Reorganized {
+            fork_height,
+            disconnected,
+        }
+    }
+
+    /// Returns the parent of a stored header.
+    fn parent(&self, header: &StoredHeader) -> StoredHeader {
+        self.store
+            .header(&header.header.raw.previous_header_hash)
+            .expect("headers are only stored with their parent; qed")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use light_bitcoin_primitives::{h256_rev, U256};
+
+    use super::*;
+    use crate::block_header::BlockHeader;
+
+    /// Regtest-like parameters with retargets every 4 headers.
+    const PARAMS: ConsensusParams = ConsensusParams {
+        pow_target_timespan: 4 * 600,
+        pow_no_retargeting: false,
+        pow_allow_min_difficulty_blocks: false,
+        ..ConsensusParams::REGTEST
+    };
+
+    fn genesis(bits: u32) -> StoredHeader {
+        let header = mine(BlockHeader {
+            version: 1,
+            time: 1_600_000_000,
+            bits: bits.into(),
+            ..Default::default()
+        });
+        StoredHeader {
+            header,
+            height: 0,
+            chainwork: header.raw.work(),
+        }
+    }
+
+    fn mine(mut header: BlockHeader) -> IndexedBlockHeader {
+        while header.validate_pow().is_err() {
+            header.nonce += 1;
+        }
+        header.into()
+    }
+
+    fn child(parent: &IndexedBlockHeader, spacing: u32, bits: u32) -> IndexedBlockHeader {
+        mine(BlockHeader {
+            version: 1,
+            previous_header_hash: parent.hash,
+            time: parent.raw.time + spacing,
+            bits: bits.into(),
+            ..Default::default()
+        })
+    }
+
+    const NOW: u32 = 2_000_000_000;
+
+    #[test]
+    fn test_accept_headers_and_retarget() {
+        let genesis = genesis(0x1f03ffff);
+        let mut chain = HeaderChain::new(PARAMS, MemoryHeaderStore::default(), genesis);
+        let mut tip = genesis.header;
+        for _ in 0..3 {
+            tip = child(&tip, 300, 0x1f03ffff);
+            assert_eq!(chain.accept_header(tip, NOW), Ok(HeaderStatus::Extended));
+        }
+        assert_eq!(chain.best_header().height, 3);
+        assert_eq!(chain.accept_header(tip, NOW), Err(Error::DuplicateHeader));
+
+        // Headers came twice as fast as expected, the target is halved
+        let next =
+            PARAMS.calculate_next_work_required(0x1f03ffff.into(), 1_600_000_000, 1_600_000_900);
+        assert_eq!(
+            next,
+            Compact::from_u256((U256::from(0x03ffff) << 224) * 900 / 2400)
+        );
+        let unexpected = child(&tip, 300, 0x1f03ffff);
+        assert_eq!(
+            chain.accept_header(unexpected, NOW),
+            Err(Error::UnexpectedBits)
+        );
+        tip = child(&tip, 300, next.into());
+        assert_eq!(chain.accept_header(tip, NOW), Ok(HeaderStatus::Extended));
+        assert_eq!(
+            chain.best_header().chainwork,
+            genesis.chainwork * 4 + next.work()
+        );
+    }
+
+    #[test]
+    fn test_header_rules() {
+        let genesis = genesis(0x207fffff);
+        let mut chain = HeaderChain::new(
+            ConsensusParams::REGTEST,
+            MemoryHeaderStore::default(),
+            genesis,
+        );
+
+        let orphan = child(&child(&genesis.header, 600, 0x207fffff), 600, 0x207fffff);
+        assert_eq!(chain.accept_header(orphan, NOW), Err(Error::UnknownParent));
+
+        let mut high_hash = child(&genesis.header, 600, 0x207fffff);
+        while high_hash.raw.validate_pow().is_ok() {
+            high_hash.raw.nonce += 1;
+        }
+        let high_hash = IndexedBlockHeader::from_raw(high_hash.raw);
+        assert_eq!(chain.accept_header(high_hash, NOW), Err(Error::HighHash));
+
+        // Header times can be up to two hours after the current time
+        let at_limit = child(&genesis.header, 600, 0x207fffff);
+        let now = at_limit.raw.time - MAX_FUTURE_BLOCK_TIME;
+        assert_eq!(
+            chain.accept_header(at_limit, now),
+            Ok(HeaderStatus::Extended)
+        );
+        let too_new = child(&at_limit, 1, 0x207fffff);
+        assert_eq!(chain.accept_header(too_new, now), Err(Error::TimeTooNew));
+
+        // The median of the 11 previous times is the 6th one
+        let mut tip = chain.best_header().header;
+        for _ in 0..10 {
+            tip = child(&tip, 600, 0x207fffff);
+            chain.accept_header(tip, NOW).unwrap();
+        }
+        let median = chain.median_time_past(&chain.best_header());
+        assert_eq!(median, tip.raw.time - 5 * 600);
+        let too_old = mine(BlockHeader {
+            time: median,
+            ..child(&tip, 0, 0x207fffff).raw
+        });
+        assert_eq!(chain.accept_header(too_old, NOW), Err(Error::TimeTooOld));
+    }
+
+    #[test]
+    fn test_min_difficulty_blocks() {
+        let params = ConsensusParams {
+            pow_allow_min_difficulty_blocks: true,
+            pow_limit: U256::from(0xffff) << 240,
+            ..PARAMS
+        };
+        let genesis = genesis(0x2000ffff);
+        let mut chain = HeaderChain::new(params, MemoryHeaderStore::default(), genesis);
+
+        // More than 20 minutes after the parent, the proof-of-work limit can be used
+        let slow = child(&genesis.header, 1201, 0x2100ffff);
+        assert_eq!(chain.accept_header(slow, NOW), Ok(HeaderStatus::Extended));
+        // Otherwise the difficulty of the last regular header applies
+        let fast = child(&slow, 600, 0x2100ffff);
+        assert_eq!(chain.accept_header(fast, NOW), Err(Error::UnexpectedBits));
+        let fast = child(&slow, 600, 0x2000ffff);
+        assert_eq!(chain.accept_header(fast, NOW), Ok(HeaderStatus::Extended));
+    }
+
+    #[test]
+    fn test_reorganization() {
+        let genesis = genesis(0x207fffff);
+        let mut chain = HeaderChain::new(
+            ConsensusParams::REGTEST,
+            MemoryHeaderStore::default(),
+            genesis,
+        );
+        let a1 = child(&genesis.header, 600, 0x207fffff);
+        let a2 = child(&a1, 600, 0x207fffff);
+        let a3 = child(&a2, 600, 0x207fffff);
+        for header in [a1, a2, a3].iter() {
+            assert_eq!(
+                chain.accept_header(*header, NOW),
+                Ok(HeaderStatus::Extended)
+            );
+        }
+
+        // The fork has the same work as the best chain until it gets longer
+        let b2 = child(&a1, 601, 0x207fffff);
+        let b3 = child(&b2, 600, 0x207fffff);
+        let b4 = child(&b3, 600, 0x207fffff);
+        assert_eq!(chain.accept_header(b2, NOW), Ok(HeaderStatus::SideChain));
+        assert_eq!(chain.accept_header(b3, NOW), Ok(HeaderStatus::SideChain));
+        assert_eq!(
+            chain.accept_header(b4, NOW),
+            Ok(HeaderStatus::Reorganized {
+                fork_height: 1,
+                disconnected: vec![a2.hash, a3.hash],
+            })
+        );
+        assert_eq!(chain.best_header().header, b4);
+        assert_eq!(
+            chain.store().best_chain(),
+            vec![genesis.header.hash, a1.hash, b2.hash, b3.hash, b4.hash]
+        );
+        assert_eq!(
+            chain
+                .ancestor(&chain.header(&a3.hash).unwrap(), 1)
+                .unwrap()
+                .header,
+            a1
+        );
+
+        // Extending the old chain switches back to it
+        let a4 = child(&a3, 600, 0x207fffff);
+        let a5 = child(&a4, 600, 0x207fffff);
+        assert_eq!(chain.accept_header(a4, NOW), Ok(HeaderStatus::SideChain));
+        assert_eq!(
+            chain.accept_header(a5, NOW),
+            Ok(HeaderStatus::Reorganized {
+                fork_height: 1,
+                disconnected: vec![b2.hash, b3.hash, b4.hash],
+            })
+        );
+        assert_eq!(chain.best_header_at(5).unwrap().header, a5);
+    }
+
+    #[test]
+    fn test_reorganization_to_shorter_chain() {
+        let genesis = genesis(0x1f03ffff);
+        let mut chain = HeaderChain::new(PARAMS, MemoryHeaderStore::default(), genesis);
+        let mut next = |chain: &mut HeaderChain<_>, parent: &IndexedBlockHeader, spacing| {
+            let stored = chain.header(&parent.hash).unwrap();
+            let time = parent.raw.time + spacing;
+            let bits = chain.next_work_required(&stored, time).unwrap();
+            let header = child(parent, spacing, bits.into());
+            (header, chain.accept_header(header, NOW).unwrap())
+        };
+
+        let mut old = vec![genesis.header];
+        for _ in 0..5 {
+            let (header, status) = next(&mut chain, old.last().unwrap(), 600);
+            assert_eq!(status, HeaderStatus::Extended);
+            old.push(header);
+        }
+
+        // The fork retargets to a 4 times harder target, so it has more work with fewer headers
+        let mut new = vec![genesis.header];
+        for _ in 0..3 {
+            let (header, status) = next(&mut chain, new.last().unwrap(), 1);
+            assert_eq!(status, HeaderStatus::SideChain);
+            new.push(header);
+        }
+        let (header, status) = next(&mut chain, new.last().unwrap(), 1);
+        new.push(header);
+        assert_eq!(
+            status,
+            HeaderStatus::Reorganized {
+                fork_height: 0,
+                disconnected: old[1..].iter().map(|header| header.hash).collect(),
+            }
+        );
+        assert_eq!(chain.best_header().height, 4);
+        assert_eq!(
+            chain.store().best_chain(),
+            new.iter().map(|header| header.hash).collect::<Vec<_>>()
+        );
+        assert!(chain.best_header_at(5).is_none());
+    }
+
+    #[test]
+    fn test_mainnet_checkpoint() {
+        // Block 80000
+        let header: BlockHeader = "01000000ba8b9cda965dd8e536670f9ddec10e53aab14b20bacad27b9137190000000000190760b278fe7b8565fda3b968b918d5fd997f993b23674c0af3b6fde300b38f33a5914ce6ed5b1b01e32f57"
+            .parse()
+            .unwrap();
+        let checkpoint = StoredHeader {
+            header: header.into(),
+            height: 80_000,
+            chainwork: U256::zero(),
+        };
+        let chain = HeaderChain::new(
+            ConsensusParams::MAINNET,
+            MemoryHeaderStore::default(),
+            checkpoint,
+        );
+        assert_eq!(
+            chain.best_header().header.hash,
+            h256_rev("000000000043a8c0fd1d6f726790caa2a406010d19efd2780db27bdbbd93baf6")
+        );
+        assert_eq!(chain.median_time_past(&checkpoint), header.time);
+    }
+}
@@ -0,0 +1,144 @@
+//! Proof-of-work consensus parameters
+
+use light_bitcoin_keys::NetworkParams;
+use light_bitcoin_primitives::{Compact, U256};
+
+/// Maximum number of seconds a header time can be ahead of the current time.
+pub const MAX_FUTURE_BLOCK_TIME: u32 = 2 * 60 * 60;
+
+/// Number of previous headers whose median time a header time must exceed.
+pub const MEDIAN_TIME_SPAN: usize = 11;
+
+/// Proof-of-work consensus parameters of a network.
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub struct ConsensusParams {
+    /// Maximum proof-of-work target.
+    pub pow_limit: U256,
+    /// Expected number of seconds between two blocks.
+    pub pow_target_spacing: u32,
+    /// Expected number of seconds of a retarget period.
+    pub pow_target_timespan: u32,
+    /// Allows headers more than twice the target spacing after their parent to use the
+    /// proof-of-work limit (testnet 20-minute rule).
+    pub pow_allow_min_difficulty_blocks: bool,
+    /// Keeps the target of the parent at retarget boundaries (regtest).
+    pub pow_no_retargeting: bool,
+}
+
+impl ConsensusParams {
+    /// Bitcoin mainnet
+    pub const MAINNET: ConsensusParams = ConsensusParams {
+        pow_limit: NetworkParams::MAINNET.pow_limit,
+        pow_target_spacing: 10 * 60,
+        pow_target_timespan: 14 * 24 * 60 * 60,
+        pow_allow_min_difficulty_blocks: false,
+        pow_no_retargeting: false,
+    };
+
+    /// Bitcoin testnet (version 3)
+    pub const TESTNET: ConsensusParams = ConsensusParams {
+        pow_limit: NetworkParams::TESTNET.pow_limit,
+        pow_allow_min_difficulty_blocks: true,
+        ..ConsensusParams::MAINNET
+    };
+
+    /// Bitcoin regression test network
+    pub const REGTEST: ConsensusParams = ConsensusParams {
+        pow_limit: NetworkParams::REGTEST.pow_limit,
+        pow_allow_min_difficulty_blocks: true,
+        pow_no_retargeting: true,
+        ..ConsensusParams::MAINNET
+    };
+
+    /// Bitcoin default signet (BIP325)
+    pub const SIGNET: ConsensusParams = ConsensusParams {
+        pow_limit: NetworkParams::SIGNET.pow_limit,
+        ..ConsensusParams::MAINNET
+    };
+
+    /// Number of headers between two retargets (2016 on all networks).
+    pub fn difficulty_adjustment_interval(&self) -> u32 {
+        self.pow_target_timespan / self.pow_target_spacing
+    }
+
+    /// Returns the compact encoding of the proof-of-work limit.
+    pub fn pow_limit_bits(&self) -> Compact {
+        Compact::from_u256(self.pow_limit)
+    }
+
+    /// Computes the target of a retarget period from the target of the last header of the
+    /// previous period and the time it took.
+    ///
+    /// The time is clamped to [timespan / 4, timespan * 4] and the target to the limit.
+    pub fn calculate_next_work_required(
+        &self,
+        last_bits: Compact,
+        first_time: u32,
+        last_time: u32,
+    ) -> Compact {
+        if self.pow_no_retargeting {
+            return last_bits;
+        }
+        let timespan = i64::from(self.pow_target_timespan);
+        let actual =
+            (i64::from(last_time) - i64::from(first_time)).clamp(timespan / 4, timespan * 4) as u64;
+
+        let target = U256::from(last_bits)
+            .checked_mul(actual.into())
+            .map(|target| target / timespan as u64)
+            .filter(|target| *target <= self.pow_limit)
+            .unwrap_or(self.pow_limit);
+        Compact::from_u256(target)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // https://github.com/bitcoin/bitcoin/blob/master/src/test/pow_tests.cpp
+    #[test]
+    fn test_calculate_next_work_required() {
+        let params = ConsensusParams::MAINNET;
+        let vectors = [
+            // Block 32255 to 34271
+            (0x1d00ffff, 1261130161, 1262152739, 0x1d00d86a),
+            // Capped at the proof-of-work limit
+            (0x1d00ffff, 1231006505, 1233061996, 0x1d00ffff),
+            // Actual timespan below the lower limit
+            (0x1c05a3f4, 1279008237, 1279297671, 0x1c0168fd),
+            // Actual timespan above the upper limit
+            (0x1c387f6f, 1263163443, 1269211443, 0x1d00e1fd),
+        ];
+        for (bits, first_time, last_time, expected) in vectors.iter() {
+            assert_eq!(
+                params.calculate_next_work_required((*bits).into(), *first_time, *last_time),
+                Compact::new(*expected)
+            );
+        }
+        assert_eq!(
+            ConsensusParams::REGTEST.calculate_next_work_required(
+                0x207fffff.into(),
+                0,
+                1_000_000_000
+            ),
+            Compact::new(0x207fffff)
+        );
+    }
+
+    #[test]
+    fn test_pow_limit_bits() {
+        assert_eq!(
+            ConsensusParams::MAINNET.pow_limit_bits(),
+            Compact::new(0x1d00ffff)
+        );
+        assert_eq!(
+            ConsensusParams::REGTEST.pow_limit_bits(),
+            Compact::new(0x207fffff)
+        );
+        assert_eq!(
+            ConsensusParams::MAINNET.difficulty_adjustment_interval(),
+            2016
+        );
+    }
+}
@@ -0,0 +1,99 @@
+//! Storage of the header chain
+
+#[cfg(not(feature = "std"))]
+use alloc::{collections::BTreeMap, vec::Vec};
+#[cfg(feature = "std")]
+use std::collections::BTreeMap;
+
+use light_bitcoin_primitives::{H256, U256};
+
+use crate::indexed_header::IndexedBlockHeader;
+
+/// Header with its position in the chain.
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub struct StoredHeader {
+    /// The header and its hash
+    pub header: IndexedBlockHeader,
+    /// Height of the header
+    pub height: u32,
+    /// Total work of the chain ending with the header
+    pub chainwork: U256,
+}
+
+/// Storage of the headers of all the known chains, and of the index of the best chain.
+///
+/// Runtimes implement it on top of their storage maps; `MemoryHeaderStore` keeps everything in
+/// memory.
+pub trait HeaderStore {
+    /// Returns the stored header of the hash.
+    fn header(&self, hash: &H256) -> Option<StoredHeader>;
+
+    /// Stores a header.
+    fn insert_header(&mut self, header: StoredHeader);
+
+    /// Returns the hash of the best chain header at the height.
+    fn best_hash_at(&self, height: u32) -> Option<H256>;
+
+    /// Sets the hash of the best chain header at the height, `None` removing it.
+    fn set_best_hash_at(&mut self, height: u32, hash: Option<H256>);
+
+    /// Returns the hash of the tip of the best chain.
+    fn best_hash(&self) -> Option<H256>;
+
+    /// Sets the tip of the best chain.
+    fn set_best_hash(&mut self, hash: H256);
+}
+
+/// In-memory header storage.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryHeaderStore {
+    headers: BTreeMap<H256, StoredHeader>,
+    best_chain: BTreeMap<u32, H256>,
+    best_hash: Option<H256>,
+}
+
+impl MemoryHeaderStore {
+    /// Returns the number of stored headers, including side chains.
+    pub fn len(&self) -> usize {
+        self.headers.len()
+    }
+
+    /// Returns true if no header is stored.
+    pub fn is_empty(&self) -> bool {
+        self.headers.is_empty()
+    }
+
+    /// Returns the hashes of the best chain, from the lowest stored height.
+    pub fn best_chain(&self) -> Vec<H256> {
+        self.best_chain.values().cloned().collect()
+    }
+}
+
+impl HeaderStore for MemoryHeaderStore {
+    fn header(&self, hash: &H256) -> Option<StoredHeader> {
+        self.headers.get(hash).cloned()
+    }
+
+    fn insert_header(&mut self, header: StoredHeader) {
+        self.headers.insert(header.header.hash, header);
+    }
+
+    fn best_hash_at(&self, height: u32) -> Option<H256> {
+        self.best_chain.get(&height).cloned()
+    }
+
+    fn set_best_hash_at(&mut self, height: u32, hash: Option<H256>) {
+        match hash {
+            Some(hash) => self.best_chain.insert(height, hash),
+            None => self.best_chain.remove(&height),
+        };
+    }
+
+    fn best_hash(&self) -> Option<H256> {
+        self.best_hash
+    }
+
+    fn set_best_hash(&mut self, hash: H256) {
+        self.best_hash = Some(hash);
+    }
+}
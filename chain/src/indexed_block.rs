@@ -9,6 +9,7 @@ use light_bitcoin_serialization::{
 };
 
 use crate::block::Block;
+use crate::constants::WITNESS_SCALE_FACTOR;
 use crate::indexed_header::IndexedBlockHeader;
 use crate::indexed_transaction::IndexedTransaction;
 use crate::merkle_root::merkle_root;
@@ -103,6 +104,11 @@ impl IndexedBlock {
         header_size + txs_size
     }
 
+    /// Weight of the block (BIP141).
+    pub fn weight(&self) -> usize {
+        self.size() * (WITNESS_SCALE_FACTOR - 1) + self.size_with_witness()
+    }
+
     pub fn merkle_root(&self) -> H256 {
         merkle_root(
             self.transactions
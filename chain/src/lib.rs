@@ -7,6 +7,9 @@ pub mod constants;
 
 mod block;
 mod block_header;
+mod blockfilter;
+mod error;
+mod header_chain;
 mod merkle_root;
 mod transaction;
 
@@ -21,6 +24,12 @@ pub use light_bitcoin_primitives::*;
 
 pub use self::block::Block;
 pub use self::block_header::BlockHeader;
+pub use self::blockfilter::{BlockFilter, BASIC_FILTER_M, BASIC_FILTER_P};
+pub use self::error::Error;
+pub use self::header_chain::{
+    ConsensusParams, HeaderChain, HeaderStatus, HeaderStore, MemoryHeaderStore, StoredHeader,
+    MAX_FUTURE_BLOCK_TIME, MEDIAN_TIME_SPAN,
+};
 pub use self::merkle_root::{merkle_node_hash, merkle_root};
 pub use self::transaction::{OutPoint, Transaction, TransactionInput, TransactionOutput};
 
@@ -8,14 +8,14 @@ use core::{fmt, str};
 use light_bitcoin_crypto::dhash256;
 use light_bitcoin_primitives::{hash_rev, io, Bytes, H256};
 use light_bitcoin_serialization::{
-    deserialize, serialize, serialize_with_flags, Deserializable, Reader, Serializable, Stream,
-    SERIALIZE_TRANSACTION_WITNESS,
+    deserialize, serialize, serialize_with_flags, serialized_list_size, Deserializable, Reader,
+    Serializable, Stream, SERIALIZE_TRANSACTION_WITNESS,
 };
 
 #[cfg(feature = "std")]
 use serde::{Deserialize, Serialize};
 
-use crate::constants::{LOCKTIME_THRESHOLD, SEQUENCE_FINAL};
+use crate::constants::{LOCKTIME_THRESHOLD, SEQUENCE_FINAL, WITNESS_SCALE_FACTOR};
 
 /// Must be zero.
 const WITNESS_MARKER: u8 = 0;
@@ -112,6 +112,11 @@ impl TransactionInput {
     pub fn has_witness(&self) -> bool {
         !self.script_witness.is_empty()
     }
+
+    /// Size of the serialized witness of the input.
+    pub fn witness_size(&self) -> usize {
+        serialized_list_size::<Bytes, Bytes>(&self.script_witness)
+    }
 }
 
 impl Serializable for TransactionInput {
@@ -255,6 +260,26 @@ impl Transaction {
         self.inputs.iter().any(TransactionInput::has_witness)
     }
 
+    /// Size of the transaction serialized without witness.
+    pub fn base_size(&self) -> usize {
+        self.serialized_size()
+    }
+
+    /// Size of the transaction serialized with witness (BIP144).
+    pub fn total_size(&self) -> usize {
+        self.serialized_size_with_flags(SERIALIZE_TRANSACTION_WITNESS)
+    }
+
+    /// Weight of the transaction: three times its base size plus its total size (BIP141).
+    pub fn weight(&self) -> usize {
+        self.base_size() * (WITNESS_SCALE_FACTOR - 1) + self.total_size()
+    }
+
+    /// Virtual size of the transaction: its weight divided by 4, rounded up (BIP141).
+    pub fn vsize(&self) -> usize {
+        self.weight().div_ceil(WITNESS_SCALE_FACTOR)
+    }
+
     pub fn total_spends(&self) -> u64 {
         let mut result = 0u64;
         for output in self.outputs.iter() {
@@ -387,6 +412,23 @@ mod tests {
         assert_eq!(tx.serialized_size(), raw_tx.len() / 2);
     }
 
+    // https://github.com/rust-bitcoin/rust-bitcoin/blob/master/bitcoin/src/blockdata/transaction.rs
+    #[test]
+    fn test_transaction_weight() {
+        let raw_tx = "010000000001010000000000000000000000000000000000000000000000000000000000000000ffffffff3603da1b0e00045503bd5704c7dd8a0d0ced13bb5785010800000000000a636b706f6f6c122f4e696e6a61506f6f6c2f5345475749542fffffffff02b4e5a212000000001976a914876fbb82ec05caa6af7a3b5e5a983aae6c6cc6d688ac0000000000000000266a24aa21a9edf91c46b49eb8a29089980f02ee6b57e7d63d33b18b4fddac2bcd7db2a39837040120000000000000000000000000000000000000000000000000000000000000000000000000";
+        let tx: Transaction = raw_tx.parse().unwrap();
+        assert_eq!(tx.total_size(), raw_tx.len() / 2);
+        assert_eq!(tx.base_size(), 186);
+        assert_eq!(tx.inputs[0].witness_size(), 34);
+        assert_eq!(tx.weight(), 780);
+        assert_eq!(tx.vsize(), 195);
+
+        let legacy: Transaction = "0100000001a6b97044d03da79c005b20ea9c0e1a6d9dc12d9f7b91a5911c9030a439eed8f5000000004948304502206e21798a42fae0e854281abd38bacd1aeed3ee3738d9e1446618c4571d1090db022100e2ac980643b0b82c0e88ffdfec6b64e3e6ba35e7ba5fdd7d5d6cc8d25c6b241501ffffffff0100f2052a010000001976a914404371705fa9bd789a2fcd52d2c580b65d35549d88ac00000000".parse().unwrap();
+        assert_eq!(legacy.total_size(), legacy.base_size());
+        assert_eq!(legacy.weight(), legacy.base_size() * 4);
+        assert_eq!(legacy.vsize(), legacy.base_size());
+    }
+
     // test case from https://github.com/bitcoin/bips/blob/master/bip-0143.mediawiki#native-p2wpkh
     #[test]
     fn test_transaction_reader_with_witness() {
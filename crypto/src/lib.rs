@@ -2,7 +2,7 @@
 
 use core::hash::Hasher;
 
-use light_bitcoin_primitives::{H160, H256, H32};
+use light_bitcoin_primitives::{H160, H256, H32, H512};
 
 pub use digest::Digest;
 use digest::{
@@ -12,9 +12,10 @@ use digest::{
     },
     Reset,
 };
+use hmac::{Hmac, Mac, NewMac};
 use ripemd160::Ripemd160;
 use sha1::Sha1;
-use sha2::Sha256;
+use sha2::{Sha256, Sha512};
 use siphasher::sip::SipHasher24;
 
 #[derive(Clone, Default)]
@@ -170,6 +171,33 @@ pub fn dhash256(input: &[u8]) -> H256 {
     H256::from_slice(&hasher.finalize())
 }
 
+/// Tagged SHA-256 (BIP340): `SHA256(SHA256(tag) || SHA256(tag) || input)`
+#[inline]
+pub fn tagged_hash(tag: &[u8], input: &[u8]) -> H256 {
+    let tag_hash = sha256(tag);
+    let mut hasher = Sha256::new();
+    hasher.update(tag_hash.as_bytes());
+    hasher.update(tag_hash.as_bytes());
+    hasher.update(input);
+    H256::from_slice(&hasher.finalize())
+}
+
+/// HMAC-SHA512
+#[inline]
+pub fn hmac_sha512(key: &[u8], input: &[u8]) -> H512 {
+    let mut mac = Hmac::<Sha512>::new_varkey(key).expect("HMAC accepts keys of any size; qed");
+    mac.update(input);
+    H512::from_slice(&mac.finalize().into_bytes())
+}
+
+/// PBKDF2 with HMAC-SHA512 as the pseudorandom function
+#[inline]
+pub fn pbkdf2_hmac_sha512(password: &[u8], salt: &[u8], rounds: u32) -> H512 {
+    let mut result = H512::default();
+    pbkdf2::pbkdf2::<Hmac<Sha512>>(password, salt, rounds, result.as_bytes_mut());
+    result
+}
+
 /// SipHash-2-4
 #[inline]
 pub fn siphash24(key0: u64, key1: u64, input: &[u8]) -> u64 {
@@ -178,6 +206,37 @@ pub fn siphash24(key0: u64, key1: u64, input: &[u8]) -> u64 {
     hasher.finish()
 }
 
+/// MurmurHash3 (x86, 32 bits), used by BIP37 bloom filters
+pub fn murmur3(seed: u32, input: &[u8]) -> u32 {
+    const C1: u32 = 0xcc9e_2d51;
+    const C2: u32 = 0x1b87_3593;
+
+    let mut h1 = seed;
+    let mut blocks = input.chunks_exact(4);
+    for block in &mut blocks {
+        let mut k1 = u32::from_le_bytes([block[0], block[1], block[2], block[3]]);
+        k1 = k1.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2);
+        h1 ^= k1;
+        h1 = h1.rotate_left(13).wrapping_mul(5).wrapping_add(0xe654_6b64);
+    }
+
+    let tail = blocks.remainder();
+    if !tail.is_empty() {
+        let k1 = tail
+            .iter()
+            .rev()
+            .fold(0u32, |k1, byte| (k1 << 8) | u32::from(*byte));
+        h1 ^= k1.wrapping_mul(C1).rotate_left(15).wrapping_mul(C2);
+    }
+
+    h1 ^= input.len() as u32;
+    h1 ^= h1 >> 16;
+    h1 = h1.wrapping_mul(0x85eb_ca6b);
+    h1 ^= h1 >> 13;
+    h1 = h1.wrapping_mul(0xc2b2_ae35);
+    h1 ^ (h1 >> 16)
+}
+
 /// Data checksum
 #[inline]
 pub fn checksum(data: &[u8]) -> H32 {
@@ -186,7 +245,7 @@ pub fn checksum(data: &[u8]) -> H32 {
 
 #[cfg(test)]
 mod tests {
-    use light_bitcoin_primitives::{h160, h256, h32, Bytes};
+    use light_bitcoin_primitives::{h160, h256, h32, h512, Bytes};
 
     use super::*;
 
@@ -211,6 +270,48 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_tagged_hash() {
+        let result = tagged_hash(b"TapLeaf", b"hello");
+        assert_eq!(
+            result,
+            h256("cc0d501f9c9c9610e44edb939ad98d59a1a98739d0e26dbe932660ae33a8196e")
+        );
+    }
+
+    #[test]
+    fn test_murmur3() {
+        assert_eq!(murmur3(0, b""), 0);
+        assert_eq!(murmur3(1, b""), 0x514e_28b7);
+        assert_eq!(murmur3(0xffff_ffff, b""), 0x81f1_6f39);
+        assert_eq!(murmur3(0, &[0, 0, 0, 0]), 0x2362_f9de);
+        assert_eq!(murmur3(0, b"test"), 0xba6b_d213);
+        assert_eq!(murmur3(0x9747_b28c, b"Hello, world!"), 0x2488_4cba);
+        assert_eq!(
+            murmur3(0x9747_b28c, b"The quick brown fox jumps over the lazy dog"),
+            0x2fa8_26cd
+        );
+    }
+
+    #[test]
+    fn test_hmac_sha512() {
+        // RFC 4231 test case 2
+        let result = hmac_sha512(b"Jefe", b"what do ya want for nothing?");
+        assert_eq!(
+            result,
+            h512("164b7a7bfcf819e2e395fbe73b56e0a387bd64222e831fd610270cd7ea2505549758bf75c05a994a6d034f65f8f0e6fdcaeab1a34d4a6b4b636e070a38bce737")
+        );
+    }
+
+    #[test]
+    fn test_pbkdf2_hmac_sha512() {
+        let result = pbkdf2_hmac_sha512(b"password", b"salt", 1);
+        assert_eq!(
+            result,
+            h512("867f70cf1ade02cff3752599a3a53dc4af34c7a669815ae5d513554e1c8cf252c02d470a285a0501bad999bfe943c08f050235d7d68b1da55e63f73b60a57fce")
+        );
+    }
+
     #[test]
     fn test_dhash160() {
         let result = dhash160(b"hello");
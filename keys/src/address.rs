@@ -2,24 +2,27 @@
 //!
 //! A Bitcoin address, or simply address, is an identifier of 26-35 alphanumeric characters, beginning with the number 1
 //! or 3, that represents a possible destination for a bitcoin payment.
+//! Segregated witness addresses are encoded with Bech32 (version 0) or Bech32m (version 1+) instead,
+//! eg: bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4.
 //!
 //! https://en.bitcoin.it/wiki/Address
 
 use core::{fmt, ops, str};
 
 use light_bitcoin_crypto::checksum;
-use light_bitcoin_primitives::io;
+use light_bitcoin_primitives::{io, H256};
 use light_bitcoin_serialization::{Deserializable, Reader, Serializable, Stream};
 
 use codec::{Decode, Encode};
 #[cfg(feature = "std")]
 use serde::{Deserialize, Serialize};
 
+use crate::bech32;
 use crate::display::DisplayLayout;
 use crate::error::Error;
 use crate::AddressHash;
 
-/// There are two address formats currently in use.
+/// There are two base58 address formats and three segregated witness address formats currently in use.
 /// https://bitcoin.org/en/developer-reference#address-conversion
 #[derive(Ord, PartialOrd, Eq, PartialEq, Copy, Clone, Debug)]
 #[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
@@ -33,6 +36,18 @@ pub enum Type {
     /// Newer P2SH type starting with the number 3, eg: 3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy.
     /// https://bitcoin.org/en/glossary/p2sh-address
     P2SH,
+    /// Pay to Witness PubKey Hash
+    /// Native segwit v0 key hash, encoded with Bech32, eg: bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4.
+    /// https://github.com/bitcoin/bips/blob/master/bip-0173.mediawiki
+    P2WPKH,
+    /// Pay to Witness Script Hash
+    /// Native segwit v0 script hash, encoded with Bech32.
+    /// https://github.com/bitcoin/bips/blob/master/bip-0173.mediawiki
+    P2WSH,
+    /// Pay to Taproot
+    /// Native segwit v1 output key, encoded with Bech32m.
+    /// https://github.com/bitcoin/bips/blob/master/bip-0350.mediawiki
+    P2TR,
 }
 
 impl Default for Type {
@@ -46,6 +61,9 @@ impl Type {
         match v {
             0 => Some(Type::P2PKH),
             1 => Some(Type::P2SH),
+            2 => Some(Type::P2WPKH),
+            3 => Some(Type::P2WSH),
+            4 => Some(Type::P2TR),
             _ => None,
         }
     }
@@ -53,10 +71,7 @@ impl Type {
 
 impl Serializable for Type {
     fn serialize(&self, s: &mut Stream) {
-        let _stream = match *self {
-            Type::P2PKH => s.append(&Type::P2PKH),
-            Type::P2SH => s.append(&Type::P2SH),
-        };
+        s.append(&(*self as u32));
     }
 }
 
@@ -77,6 +92,8 @@ impl Deserializable for Type {
 pub enum Network {
     Mainnet,
     Testnet,
+    Regtest,
+    Signet,
 }
 
 impl Default for Network {
@@ -90,6 +107,8 @@ impl Network {
         match v {
             0 => Some(Network::Mainnet),
             1 => Some(Network::Testnet),
+            2 => Some(Network::Regtest),
+            3 => Some(Network::Signet),
             _ => None,
         }
     }
@@ -97,10 +116,7 @@ impl Network {
 
 impl Serializable for Network {
     fn serialize(&self, s: &mut Stream) {
-        let _stream = match *self {
-            Network::Mainnet => s.append(&Network::Mainnet),
-            Network::Testnet => s.append(&Network::Testnet),
-        };
+        s.append(&(*self as u32));
     }
 }
 
@@ -115,23 +131,194 @@ impl Deserializable for Network {
     }
 }
 
+/// Hash or witness program committed to by an address.
+#[derive(Ord, PartialOrd, Eq, PartialEq, Copy, Clone, Debug)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub enum AddressTypes {
+    /// Public key hash or script hash of a base58 (P2PKH or P2SH) address
+    Legacy(AddressHash),
+    /// Public key hash of a P2WPKH address
+    WitnessV0KeyHash(AddressHash),
+    /// Script hash of a P2WSH address
+    WitnessV0ScriptHash(H256),
+    /// X-only output key of a P2TR address
+    WitnessV1Taproot(H256),
+}
+
+impl Default for AddressTypes {
+    fn default() -> AddressTypes {
+        AddressTypes::Legacy(AddressHash::default())
+    }
+}
+
+impl ops::Deref for AddressTypes {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            AddressTypes::Legacy(hash) => hash.as_bytes(),
+            AddressTypes::WitnessV0KeyHash(hash) => hash.as_bytes(),
+            AddressTypes::WitnessV0ScriptHash(hash) => hash.as_bytes(),
+            AddressTypes::WitnessV1Taproot(key) => key.as_bytes(),
+        }
+    }
+}
+
+impl AddressTypes {
+    /// Returns the witness version of segwit addresses.
+    pub fn witness_version(&self) -> Option<u8> {
+        match self {
+            AddressTypes::Legacy(_) => None,
+            AddressTypes::WitnessV0KeyHash(_) | AddressTypes::WitnessV0ScriptHash(_) => Some(0),
+            AddressTypes::WitnessV1Taproot(_) => Some(1),
+        }
+    }
+
+    /// Builds the hash of a segwit address from its witness version and program.
+    pub fn from_witness_program(version: u8, program: &[u8]) -> Result<Self, Error> {
+        match (version, program.len()) {
+            (0, 20) => Ok(AddressTypes::WitnessV0KeyHash(AddressHash::from_slice(
+                program,
+            ))),
+            (0, 32) => Ok(AddressTypes::WitnessV0ScriptHash(H256::from_slice(program))),
+            (1, 32) => Ok(AddressTypes::WitnessV1Taproot(H256::from_slice(program))),
+            _ => Err(Error::InvalidAddress),
+        }
+    }
+
+    fn kind(&self) -> Option<Type> {
+        match self {
+            AddressTypes::Legacy(_) => None,
+            AddressTypes::WitnessV0KeyHash(_) => Some(Type::P2WPKH),
+            AddressTypes::WitnessV0ScriptHash(_) => Some(Type::P2WSH),
+            AddressTypes::WitnessV1Taproot(_) => Some(Type::P2TR),
+        }
+    }
+}
+
 /// `AddressHash` with network identifier and format type
+///
+/// Both the serialization and the SCALE encoding are the type, the network and the bytes of the
+/// hash, the type telling the size of the hash. Legacy addresses keep their encoding from before
+/// segwit addresses.
 #[derive(Ord, PartialOrd, Eq, PartialEq, Copy, Clone, Debug, Default)]
 #[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
-#[derive(Serializable, Deserializable)]
-#[derive(Encode, Decode)]
 pub struct Address {
     /// The type of the address.
     pub kind: Type,
     /// The network of the address.
     pub network: Network,
-    /// Public key hash.
-    pub hash: AddressHash,
+    /// Public key hash, script hash or witness program.
+    pub hash: AddressTypes,
+}
+
+impl Address {
+    /// Parses an address of the given network.
+    ///
+    /// Testnet, Regtest and Signet share their base58 prefixes, and Signet shares its bech32
+    /// human-readable part with Testnet, so `FromStr` resolves such addresses to the first
+    /// matching network (see `Network::ALL`). Use this method when the network is known.
+    pub fn from_str_with_network(s: &str, network: Network) -> Result<Self, Error> {
+        let mut address: Address = s.parse()?;
+        let (expected, parsed) = (network.params(), address.network.params());
+        let matches = match address.hash.witness_version() {
+            Some(_) => expected.bech32_hrp == parsed.bech32_hrp,
+            None => {
+                expected.p2pkh_prefix == parsed.p2pkh_prefix
+                    && expected.p2sh_prefix == parsed.p2sh_prefix
+            }
+        };
+        if !matches {
+            return Err(Error::InvalidAddress);
+        }
+        address.network = network;
+        Ok(address)
+    }
+
+    fn network_from_bech32_hrp(hrp: &str) -> Option<Network> {
+        Network::find(|params| params.bech32_hrp == hrp)
+    }
+
+    /// Parses a Bech32/Bech32m encoded segwit address.
+    fn from_bech32(s: &str) -> Result<Self, Error> {
+        let (hrp, version, program) = bech32::decode(s)?;
+        let network = Address::network_from_bech32_hrp(&hrp).ok_or(Error::InvalidAddress)?;
+        let hash = AddressTypes::from_witness_program(version, &program)?;
+        let kind = hash.kind().ok_or(Error::InvalidAddress)?;
+        Ok(Address {
+            kind,
+            network,
+            hash,
+        })
+    }
+}
+
+impl Serializable for Address {
+    fn serialize(&self, s: &mut Stream) {
+        s.append(&self.kind)
+            .append(&self.network)
+            .append_slice(&self.hash);
+    }
+}
+
+impl Deserializable for Address {
+    fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, io::Error>
+    where
+        Self: Sized,
+        T: io::Read,
+    {
+        let kind = reader.read()?;
+        let network = reader.read()?;
+        let hash = match kind {
+            Type::P2PKH | Type::P2SH => AddressTypes::Legacy(reader.read()?),
+            Type::P2WPKH => AddressTypes::WitnessV0KeyHash(reader.read()?),
+            Type::P2WSH => AddressTypes::WitnessV0ScriptHash(reader.read()?),
+            Type::P2TR => AddressTypes::WitnessV1Taproot(reader.read()?),
+        };
+        Ok(Address {
+            kind,
+            network,
+            hash,
+        })
+    }
+}
+
+impl Encode for Address {
+    fn encode_to<W: codec::Output + ?Sized>(&self, dest: &mut W) {
+        self.kind.encode_to(dest);
+        self.network.encode_to(dest);
+        dest.write(&self.hash);
+    }
+}
+
+impl codec::EncodeLike for Address {}
+
+impl Decode for Address {
+    fn decode<I: codec::Input>(value: &mut I) -> Result<Self, codec::Error> {
+        let kind = Type::decode(value)?;
+        let network = Network::decode(value)?;
+        let hash = match kind {
+            Type::P2PKH | Type::P2SH => AddressTypes::Legacy(Decode::decode(value)?),
+            Type::P2WPKH => AddressTypes::WitnessV0KeyHash(Decode::decode(value)?),
+            Type::P2WSH => AddressTypes::WitnessV0ScriptHash(Decode::decode(value)?),
+            Type::P2TR => AddressTypes::WitnessV1Taproot(Decode::decode(value)?),
+        };
+        Ok(Address {
+            kind,
+            network,
+            hash,
+        })
+    }
 }
 
 impl fmt::Display for Address {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        bs58::encode(self.layout().0).into_string().fmt(f)
+        match self.hash.witness_version() {
+            Some(version) => bech32::encode(self.network.params().bech32_hrp, version, &self.hash)
+                .map_err(|_| fmt::Error)?
+                .fmt(f),
+            None => bs58::encode(self.layout().0).into_string().fmt(f),
+        }
     }
 }
 
@@ -139,6 +326,16 @@ impl str::FromStr for Address {
     type Err = Error;
 
     fn from_str(s: &str) -> Result<Self, Error> {
+        // Segwit addresses start with a known human-readable part followed by the separator `1`
+        let is_bech32 = s
+            .to_ascii_lowercase()
+            .rsplit_once('1')
+            .and_then(|(hrp, _)| Address::network_from_bech32_hrp(hrp))
+            .is_some();
+        if is_bech32 {
+            return Address::from_bech32(s);
+        }
+
         let hex = bs58::decode(s)
             .into_vec()
             .map_err(|_| Error::InvalidAddress)?;
@@ -157,20 +354,27 @@ impl ops::Deref for AddressDisplayLayout {
     }
 }
 
+/// Base58Check layout of P2PKH and P2SH addresses.
+///
+/// Segwit addresses have no base58 layout, their layout is all zeros.
 impl DisplayLayout for Address {
     type Target = AddressDisplayLayout;
 
     fn layout(&self) -> Self::Target {
         let mut result = [0u8; 25];
 
-        result[0] = match (self.network, self.kind) {
-            (Network::Mainnet, Type::P2PKH) => 0,
-            (Network::Mainnet, Type::P2SH) => 5,
-            (Network::Testnet, Type::P2PKH) => 111,
-            (Network::Testnet, Type::P2SH) => 196,
+        let hash = match self.hash {
+            AddressTypes::Legacy(hash) => hash,
+            _ => return AddressDisplayLayout(result),
         };
 
-        result[1..21].copy_from_slice(self.hash.as_bytes());
+        let params = self.network.params();
+        result[0] = match self.kind {
+            Type::P2SH => params.p2sh_prefix,
+            _ => params.p2pkh_prefix,
+        };
+
+        result[1..21].copy_from_slice(hash.as_bytes());
         let cs = checksum(&result[0..21]);
         result[21..25].copy_from_slice(cs.as_bytes());
         AddressDisplayLayout(result)
@@ -189,15 +393,15 @@ impl DisplayLayout for Address {
             return Err(Error::InvalidChecksum);
         }
 
-        let (network, kind) = match data[0] {
-            0 => (Network::Mainnet, Type::P2PKH),
-            5 => (Network::Mainnet, Type::P2SH),
-            111 => (Network::Testnet, Type::P2PKH),
-            196 => (Network::Testnet, Type::P2SH),
-            _ => return Err(Error::InvalidAddress),
+        let (network, kind) = if let Some(network) = Network::find(|p| p.p2pkh_prefix == data[0]) {
+            (network, Type::P2PKH)
+        } else if let Some(network) = Network::find(|p| p.p2sh_prefix == data[0]) {
+            (network, Type::P2SH)
+        } else {
+            return Err(Error::InvalidAddress);
         };
 
-        let hash = AddressHash::from_slice(&data[1..21]);
+        let hash = AddressTypes::Legacy(AddressHash::from_slice(&data[1..21]));
         Ok(Address {
             kind,
             network,
@@ -208,7 +412,7 @@ impl DisplayLayout for Address {
 
 #[cfg(test)]
 mod tests {
-    use light_bitcoin_primitives::h160;
+    use light_bitcoin_primitives::{h160, h256};
 
     use super::*;
 
@@ -217,7 +421,7 @@ mod tests {
         let address = Address {
             kind: Type::P2PKH,
             network: Network::Mainnet,
-            hash: h160("3f4aa1fedf1f54eeb03b759deadb36676b184911"),
+            hash: AddressTypes::Legacy(h160("3f4aa1fedf1f54eeb03b759deadb36676b184911")),
         };
         assert_eq!(
             address.to_string(),
@@ -227,7 +431,7 @@ mod tests {
         let address = Address {
             kind: Type::P2SH,
             network: Network::Mainnet,
-            hash: h160("d246f700f4969106291a75ba85ad863cae68d667"),
+            hash: AddressTypes::Legacy(h160("d246f700f4969106291a75ba85ad863cae68d667")),
         };
         assert_eq!(
             address.to_string(),
@@ -240,7 +444,7 @@ mod tests {
         let address = Address {
             kind: Type::P2PKH,
             network: Network::Mainnet,
-            hash: h160("3f4aa1fedf1f54eeb03b759deadb36676b184911"),
+            hash: AddressTypes::Legacy(h160("3f4aa1fedf1f54eeb03b759deadb36676b184911")),
         };
         assert_eq!(
             address,
@@ -250,11 +454,183 @@ mod tests {
         let address = Address {
             kind: Type::P2SH,
             network: Network::Mainnet,
-            hash: h160("d246f700f4969106291a75ba85ad863cae68d667"),
+            hash: AddressTypes::Legacy(h160("d246f700f4969106291a75ba85ad863cae68d667")),
         };
         assert_eq!(
             address,
             "3LrrqZ2LtZxAcroVaYKgM6yDeRszV2sY1r".parse().unwrap()
         );
     }
+
+    #[test]
+    fn test_segwit_address_to_string() {
+        let address = Address {
+            kind: Type::P2WPKH,
+            network: Network::Mainnet,
+            hash: AddressTypes::WitnessV0KeyHash(h160("751e76e8199196d454941c45d1b3a323f1433bd6")),
+        };
+        assert_eq!(
+            address.to_string(),
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4".to_string(),
+        );
+
+        let address = Address {
+            kind: Type::P2WSH,
+            network: Network::Testnet,
+            hash: AddressTypes::WitnessV0ScriptHash(h256(
+                "1863143c14c5166804bd19203356da136c985678cd4d27a1b8c6329604903262",
+            )),
+        };
+        assert_eq!(
+            address.to_string(),
+            "tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7".to_string(),
+        );
+
+        let address = Address {
+            kind: Type::P2TR,
+            network: Network::Mainnet,
+            hash: AddressTypes::WitnessV1Taproot(h256(
+                "79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+            )),
+        };
+        assert_eq!(
+            address.to_string(),
+            "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0".to_string(),
+        );
+    }
+
+    #[test]
+    fn test_segwit_address_from_str() {
+        let address = Address {
+            kind: Type::P2WPKH,
+            network: Network::Mainnet,
+            hash: AddressTypes::WitnessV0KeyHash(h160("751e76e8199196d454941c45d1b3a323f1433bd6")),
+        };
+        assert_eq!(
+            address,
+            "BC1QW508D6QEJXTDG4Y5R3ZARVARY0C5XW7KV8F3T4"
+                .parse()
+                .unwrap()
+        );
+
+        let address = Address {
+            kind: Type::P2TR,
+            network: Network::Mainnet,
+            hash: AddressTypes::WitnessV1Taproot(h256(
+                "79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+            )),
+        };
+        assert_eq!(
+            address,
+            "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0"
+                .parse()
+                .unwrap()
+        );
+
+        // witness v1 programs must use Bech32m
+        assert_eq!(
+            "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqh2y7hd".parse::<Address>(),
+            Err(Error::InvalidAddress)
+        );
+        // unknown human-readable part
+        assert_eq!(
+            "tc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vq5zuyut".parse::<Address>(),
+            Err(Error::InvalidAddress)
+        );
+        // witness versions without an address type
+        assert_eq!(
+            "bc1zw508d6qejxtdg4y5r3zarvaryvaxxpcs".parse::<Address>(),
+            Err(Error::InvalidAddress)
+        );
+    }
+
+    #[test]
+    fn test_regtest_address() {
+        let address = Address {
+            kind: Type::P2WPKH,
+            network: Network::Regtest,
+            hash: AddressTypes::WitnessV0KeyHash(h160("751e76e8199196d454941c45d1b3a323f1433bd6")),
+        };
+        assert_eq!(
+            address.to_string(),
+            "bcrt1qw508d6qejxtdg4y5r3zarvary0c5xw7kygt080".to_string(),
+        );
+        assert_eq!(
+            address,
+            "bcrt1qw508d6qejxtdg4y5r3zarvary0c5xw7kygt080"
+                .parse()
+                .unwrap()
+        );
+
+        // base58 regtest addresses share their prefix with testnet
+        let address = Address {
+            kind: Type::P2PKH,
+            network: Network::Regtest,
+            hash: AddressTypes::Legacy(h160("3f4aa1fedf1f54eeb03b759deadb36676b184911")),
+        };
+        let s = address.to_string();
+        assert_eq!(s.parse::<Address>().unwrap().network, Network::Testnet);
+        assert_eq!(
+            Address::from_str_with_network(&s, Network::Regtest),
+            Ok(address)
+        );
+        assert_eq!(
+            Address::from_str_with_network(&s, Network::Mainnet),
+            Err(Error::InvalidAddress)
+        );
+        assert_eq!(
+            Address::from_str_with_network(
+                "bcrt1qw508d6qejxtdg4y5r3zarvary0c5xw7kygt080",
+                Network::Signet
+            ),
+            Err(Error::InvalidAddress)
+        );
+    }
+
+    #[test]
+    fn test_address_encoding() {
+        use light_bitcoin_serialization::{deserialize, serialize};
+
+        // Legacy addresses are encoded as before segwit addresses: type, network and hash
+        let address = Address {
+            kind: Type::P2SH,
+            network: Network::Testnet,
+            hash: AddressTypes::Legacy(h160("d246f700f4969106291a75ba85ad863cae68d667")),
+        };
+        let encoded = hex::decode("0101d246f700f4969106291a75ba85ad863cae68d667").unwrap();
+        assert_eq!(address.encode(), encoded);
+        assert_eq!(Address::decode(&mut encoded.as_slice()), Ok(address));
+        let serialized =
+            hex::decode("0100000001000000d246f700f4969106291a75ba85ad863cae68d667").unwrap();
+        assert_eq!(&*serialize(&address), serialized.as_slice());
+        assert_eq!(deserialize(serialized.as_slice()), Ok(address));
+
+        // The type of segwit addresses tells the size of the witness program
+        let address = Address {
+            kind: Type::P2WPKH,
+            network: Network::Mainnet,
+            hash: AddressTypes::WitnessV0KeyHash(h160("751e76e8199196d454941c45d1b3a323f1433bd6")),
+        };
+        let encoded = hex::decode("0200751e76e8199196d454941c45d1b3a323f1433bd6").unwrap();
+        assert_eq!(address.encode(), encoded);
+        assert_eq!(Address::decode(&mut encoded.as_slice()), Ok(address));
+
+        let address = Address {
+            kind: Type::P2TR,
+            network: Network::Signet,
+            hash: AddressTypes::WitnessV1Taproot(h256(
+                "79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+            )),
+        };
+        let encoded =
+            hex::decode("040379be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798")
+                .unwrap();
+        assert_eq!(address.encode(), encoded);
+        assert_eq!(Address::decode(&mut encoded.as_slice()), Ok(address));
+        let serialized = serialize(&address);
+        assert_eq!(serialized.len(), 4 + 4 + 32);
+        assert_eq!(deserialize(serialized.as_ref()), Ok(address));
+
+        assert!(Address::decode(&mut &encoded[..encoded.len() - 12]).is_err());
+    }
 }
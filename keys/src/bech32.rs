@@ -0,0 +1,263 @@
+//! Bech32 and Bech32m encoding of segregated witness addresses.
+//!
+//! https://github.com/bitcoin/bips/blob/master/bip-0173.mediawiki
+//! https://github.com/bitcoin/bips/blob/master/bip-0350.mediawiki
+
+#[cfg(not(feature = "std"))]
+use alloc::{string::String, vec::Vec};
+
+use crate::error::Error;
+
+const CHARSET: &[u8; 32] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+const GENERATOR: [u32; 5] = [
+    0x3b6a_57b2,
+    0x2650_8e6d,
+    0x1ea1_19fa,
+    0x3d42_33dd,
+    0x2a14_62b3,
+];
+
+/// Maximum length of an encoded address.
+const MAX_LENGTH: usize = 90;
+
+/// Length of the checksum in 5-bit groups.
+const CHECKSUM_LENGTH: usize = 6;
+
+/// Checksum variant, which depends on the witness version.
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+enum Variant {
+    /// BIP173, used by version 0 witness programs
+    Bech32,
+    /// BIP350, used by version 1+ witness programs
+    Bech32m,
+}
+
+impl Variant {
+    fn for_version(version: u8) -> Self {
+        if version == 0 {
+            Variant::Bech32
+        } else {
+            Variant::Bech32m
+        }
+    }
+
+    fn constant(self) -> u32 {
+        match self {
+            Variant::Bech32 => 1,
+            Variant::Bech32m => 0x2bc8_30a3,
+        }
+    }
+}
+
+fn polymod(values: &[u8]) -> u32 {
+    values.iter().fold(1u32, |chk, &value| {
+        let top = chk >> 25;
+        let chk = ((chk & 0x01ff_ffff) << 5) ^ u32::from(value);
+        GENERATOR
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| (top >> i) & 1 == 1)
+            .fold(chk, |chk, (_, g)| chk ^ g)
+    })
+}
+
+fn hrp_expand(hrp: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(hrp.len() * 2 + 1);
+    result.extend(hrp.iter().map(|c| c >> 5));
+    result.push(0);
+    result.extend(hrp.iter().map(|c| c & 0x1f));
+    result
+}
+
+fn create_checksum(hrp: &[u8], data: &[u8], variant: Variant) -> [u8; CHECKSUM_LENGTH] {
+    let mut values = hrp_expand(hrp);
+    values.extend_from_slice(data);
+    values.extend_from_slice(&[0; CHECKSUM_LENGTH]);
+    let polymod = polymod(&values) ^ variant.constant();
+    let mut checksum = [0u8; CHECKSUM_LENGTH];
+    for (i, c) in checksum.iter_mut().enumerate() {
+        *c = ((polymod >> (5 * (5 - i))) & 0x1f) as u8;
+    }
+    checksum
+}
+
+fn verify_checksum(hrp: &[u8], data: &[u8]) -> Option<Variant> {
+    let mut values = hrp_expand(hrp);
+    values.extend_from_slice(data);
+    match polymod(&values) {
+        c if c == Variant::Bech32.constant() => Some(Variant::Bech32),
+        c if c == Variant::Bech32m.constant() => Some(Variant::Bech32m),
+        _ => None,
+    }
+}
+
+/// Regroups `data` of `from` bits per element into elements of `to` bits.
+fn convert_bits(data: &[u8], from: u32, to: u32, pad: bool) -> Result<Vec<u8>, Error> {
+    let mut acc = 0u32;
+    let mut bits = 0u32;
+    let max = (1u32 << to) - 1;
+    let mut result = Vec::with_capacity(data.len() * from as usize / to as usize + 1);
+    for &value in data {
+        let value = u32::from(value);
+        if value >> from != 0 {
+            return Err(Error::InvalidAddress);
+        }
+        acc = (acc << from) | value;
+        bits += from;
+        while bits >= to {
+            bits -= to;
+            result.push(((acc >> bits) & max) as u8);
+        }
+    }
+    if pad {
+        if bits > 0 {
+            result.push(((acc << (to - bits)) & max) as u8);
+        }
+    } else if bits >= from || ((acc << (to - bits)) & max) != 0 {
+        return Err(Error::InvalidAddress);
+    }
+    Ok(result)
+}
+
+/// Encodes the witness program as a segwit address with the given human-readable part.
+pub fn encode(hrp: &str, version: u8, program: &[u8]) -> Result<String, Error> {
+    if version > 16 || program.len() < 2 || program.len() > 40 {
+        return Err(Error::InvalidAddress);
+    }
+
+    let hrp = hrp.as_bytes();
+    let mut data = Vec::with_capacity(1 + (program.len() * 8).div_ceil(5) + CHECKSUM_LENGTH);
+    data.push(version);
+    data.extend(convert_bits(program, 8, 5, true)?);
+    let checksum = create_checksum(hrp, &data, Variant::for_version(version));
+    data.extend_from_slice(&checksum);
+
+    let mut result = String::with_capacity(hrp.len() + 1 + data.len());
+    result.extend(hrp.iter().map(|&c| char::from(c.to_ascii_lowercase())));
+    result.push('1');
+    result.extend(data.iter().map(|&d| char::from(CHARSET[d as usize])));
+    Ok(result)
+}
+
+/// Decodes a segwit address into its (lowercase) human-readable part, witness version and
+/// witness program.
+pub fn decode(s: &str) -> Result<(String, u8, Vec<u8>), Error> {
+    if s.len() > MAX_LENGTH {
+        return Err(Error::InvalidAddress);
+    }
+    let has_lower = s.bytes().any(|c| c.is_ascii_lowercase());
+    let has_upper = s.bytes().any(|c| c.is_ascii_uppercase());
+    if has_lower && has_upper {
+        return Err(Error::InvalidAddress);
+    }
+    let s = s.to_ascii_lowercase();
+
+    let separator = s.rfind('1').ok_or(Error::InvalidAddress)?;
+    let (hrp, data) = (&s.as_bytes()[..separator], &s.as_bytes()[separator + 1..]);
+    if hrp.is_empty() || data.len() < CHECKSUM_LENGTH + 1 {
+        return Err(Error::InvalidAddress);
+    }
+    if hrp.iter().any(|&c| !(33..=126).contains(&c)) {
+        return Err(Error::InvalidAddress);
+    }
+    let data = data
+        .iter()
+        .map(|c| CHARSET.iter().position(|x| x == c).map(|p| p as u8))
+        .collect::<Option<Vec<u8>>>()
+        .ok_or(Error::InvalidAddress)?;
+
+    let variant = verify_checksum(hrp, &data).ok_or(Error::InvalidChecksum)?;
+    let version = data[0];
+    if version > 16 || variant != Variant::for_version(version) {
+        return Err(Error::InvalidAddress);
+    }
+    let program = convert_bits(&data[1..data.len() - CHECKSUM_LENGTH], 5, 8, false)?;
+    if program.len() < 2 || program.len() > 40 {
+        return Err(Error::InvalidAddress);
+    }
+    if version == 0 && program.len() != 20 && program.len() != 32 {
+        return Err(Error::InvalidAddress);
+    }
+
+    let hrp = String::from_utf8(hrp.to_vec()).map_err(|_| Error::InvalidAddress)?;
+    Ok((hrp, version, program))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // https://github.com/bitcoin/bips/blob/master/bip-0350.mediawiki#test-vectors-for-v0-v16-native-segregated-witness-addresses
+    #[test]
+    fn test_valid_addresses() {
+        let vectors = [
+            (
+                "BC1QW508D6QEJXTDG4Y5R3ZARVARY0C5XW7KV8F3T4",
+                "bc",
+                0,
+                "751e76e8199196d454941c45d1b3a323f1433bd6",
+            ),
+            (
+                "tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sl5k7",
+                "tb",
+                0,
+                "1863143c14c5166804bd19203356da136c985678cd4d27a1b8c6329604903262",
+            ),
+            (
+                "bc1pw508d6qejxtdg4y5r3zarvary0c5xw7kw508d6qejxtdg4y5r3zarvary0c5xw7kt5nd6y",
+                "bc",
+                1,
+                "751e76e8199196d454941c45d1b3a323f1433bd6751e76e8199196d454941c45d1b3a323f1433bd6",
+            ),
+            ("BC1SW50QGDZ25J", "bc", 16, "751e"),
+            (
+                "bc1zw508d6qejxtdg4y5r3zarvaryvaxxpcs",
+                "bc",
+                2,
+                "751e76e8199196d454941c45d1b3a323",
+            ),
+            (
+                "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0",
+                "bc",
+                1,
+                "79be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798",
+            ),
+        ];
+        for (address, hrp, version, program) in vectors.iter() {
+            let program = hex::decode(program).unwrap();
+            assert_eq!(
+                decode(address),
+                Ok((hrp.to_string(), *version, program.clone()))
+            );
+            assert_eq!(
+                encode(hrp, *version, &program),
+                Ok(address.to_ascii_lowercase())
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_addresses() {
+        let vectors = [
+            // Invalid checksum (Bech32 instead of Bech32m)
+            "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqh2y7hd",
+            // Invalid checksum (Bech32m instead of Bech32)
+            "BC1S0XLXVLHEMJA6C4DQV22UAPCTQUPFHLXM9H8Z3K2E72Q4K9HCZ7VQ54WELL",
+            "bc1zw508d6qejxtdg4y5r3zarvaryvaxxpcs2", // invalid checksum
+            "bc1pw5dgrnzv",                          // invalid program length
+            // Invalid program length for witness version 0
+            "BC1QR508D6QEJXTDG4Y5R3ZARVARYV98GJ9P",
+            // Mixed case
+            "tb1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3q0sL5k7",
+            // Zero padding of more than 4 bits
+            "bc1zw508d6qejxtdg4y5r3zarvaryvqyzf3du",
+            // Non-zero padding in 8-to-5 conversion
+            "tb1pw508d6qejxtdg4y5r3zarqfsj6c3",
+            "bc1gmk9yu", // empty data section
+        ];
+        for address in vectors.iter() {
+            assert!(decode(address).is_err(), "{}", address);
+        }
+    }
+}
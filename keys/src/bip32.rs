@@ -0,0 +1,848 @@
+//! BIP32 hierarchical deterministic keys.
+//!
+//! https://github.com/bitcoin/bips/blob/master/bip-0032.mediawiki
+//! https://github.com/satoshilabs/slips/blob/master/slip-0132.md
+
+#[cfg(not(feature = "std"))]
+use alloc::{vec, vec::Vec};
+use core::{fmt, ops, str};
+
+use light_bitcoin_crypto::{checksum, dhash160, hmac_sha512};
+use light_bitcoin_primitives::{H256, H264, H32};
+
+use crate::address::{Address, AddressTypes, Network, Type};
+use crate::display::DisplayLayout;
+use crate::error::Error;
+use crate::private::Private;
+use crate::public::Public;
+use crate::{AddressHash, Secret};
+
+/// 32 bytes long chain code of extended keys
+pub type ChainCode = H256;
+/// First 4 bytes of the identifier (`hash160` of the public key) of an extended key
+pub type Fingerprint = H32;
+
+/// Size of a serialized extended key, including the checksum.
+const EXTENDED_KEY_SIZE: usize = 82;
+
+/// Index of a child key.
+#[derive(Ord, PartialOrd, Eq, PartialEq, Copy, Clone, Debug, Hash)]
+pub enum ChildNumber {
+    /// Non-hardened key, index in range `[0, 2^31)`
+    Normal(u32),
+    /// Hardened key, index in range `[0, 2^31)`
+    Hardened(u32),
+}
+
+impl Default for ChildNumber {
+    fn default() -> ChildNumber {
+        ChildNumber::Normal(0)
+    }
+}
+
+impl ChildNumber {
+    /// Bit set on the serialized index of hardened keys.
+    pub const HARDENED_BIT: u32 = 1 << 31;
+
+    /// Creates a non-hardened child number.
+    pub fn normal(index: u32) -> Result<Self, Error> {
+        if index & Self::HARDENED_BIT != 0 {
+            return Err(Error::InvalidDerivationPath);
+        }
+        Ok(ChildNumber::Normal(index))
+    }
+
+    /// Creates a hardened child number.
+    pub fn hardened(index: u32) -> Result<Self, Error> {
+        if index & Self::HARDENED_BIT != 0 {
+            return Err(Error::InvalidDerivationPath);
+        }
+        Ok(ChildNumber::Hardened(index))
+    }
+
+    pub fn is_hardened(&self) -> bool {
+        matches!(self, ChildNumber::Hardened(_))
+    }
+
+    /// Returns the serialized index, with `HARDENED_BIT` set for hardened keys.
+    pub fn to_u32(&self) -> u32 {
+        match self {
+            ChildNumber::Normal(index) => *index,
+            ChildNumber::Hardened(index) => index | Self::HARDENED_BIT,
+        }
+    }
+}
+
+impl From<u32> for ChildNumber {
+    fn from(n: u32) -> Self {
+        if n & Self::HARDENED_BIT != 0 {
+            ChildNumber::Hardened(n ^ Self::HARDENED_BIT)
+        } else {
+            ChildNumber::Normal(n)
+        }
+    }
+}
+
+impl fmt::Display for ChildNumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChildNumber::Normal(index) => write!(f, "{}", index),
+            ChildNumber::Hardened(index) => write!(f, "{}'", index),
+        }
+    }
+}
+
+impl str::FromStr for ChildNumber {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let (index, hardened) = match s.strip_suffix(|c| c == '\'' || c == 'h' || c == 'H') {
+            Some(index) => (index, true),
+            None => (s, false),
+        };
+        // Reject signs and whitespace accepted by `u32::from_str`
+        if index.is_empty() || !index.bytes().all(|c| c.is_ascii_digit()) {
+            return Err(Error::InvalidDerivationPath);
+        }
+        let index = index
+            .parse::<u32>()
+            .map_err(|_| Error::InvalidDerivationPath)?;
+        if hardened {
+            ChildNumber::hardened(index)
+        } else {
+            ChildNumber::normal(index)
+        }
+    }
+}
+
+/// Path of child numbers from a key to one of its descendants, eg: `m/84'/0'/0'/0/1`.
+#[derive(Ord, PartialOrd, Eq, PartialEq, Clone, Debug, Hash, Default)]
+pub struct DerivationPath(Vec<ChildNumber>);
+
+impl DerivationPath {
+    /// Returns the path extended with the child number.
+    pub fn child(&self, child: ChildNumber) -> DerivationPath {
+        let mut path = self.0.clone();
+        path.push(child);
+        DerivationPath(path)
+    }
+}
+
+impl ops::Deref for DerivationPath {
+    type Target = [ChildNumber];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl From<Vec<ChildNumber>> for DerivationPath {
+    fn from(path: Vec<ChildNumber>) -> Self {
+        DerivationPath(path)
+    }
+}
+
+impl fmt::Display for DerivationPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("m")?;
+        for child in &self.0 {
+            write!(f, "/{}", child)?;
+        }
+        Ok(())
+    }
+}
+
+impl str::FromStr for DerivationPath {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let mut parts = s.split('/');
+        if parts.next() != Some("m") {
+            return Err(Error::InvalidDerivationPath);
+        }
+        parts
+            .map(str::parse)
+            .collect::<Result<Vec<ChildNumber>, Error>>()
+            .map(DerivationPath)
+    }
+}
+
+/// Script type of the addresses derived from an extended key, which selects its version bytes.
+#[derive(Ord, PartialOrd, Eq, PartialEq, Copy, Clone, Debug, Default)]
+pub enum ExtendedKeyFormat {
+    /// P2PKH addresses (xpub/xprv, tpub/tprv)
+    #[default]
+    Legacy,
+    /// P2WPKH nested in P2SH addresses (ypub/yprv, upub/uprv)
+    NestedSegwit,
+    /// Native P2WPKH addresses (zpub/zprv, vpub/vprv)
+    NativeSegwit,
+}
+
+impl ExtendedKeyFormat {
+    const ALL: [ExtendedKeyFormat; 3] = [
+        ExtendedKeyFormat::Legacy,
+        ExtendedKeyFormat::NestedSegwit,
+        ExtendedKeyFormat::NativeSegwit,
+    ];
+
+    /// Returns the version bytes of `(public, private)` extended keys of the network.
+    pub fn versions(&self, network: Network) -> ([u8; 4], [u8; 4]) {
+        let params = network.params();
+        match (self, network) {
+            (ExtendedKeyFormat::Legacy, _) => (params.bip32_public, params.bip32_private),
+            (ExtendedKeyFormat::NestedSegwit, Network::Mainnet) => {
+                ([0x04, 0x9d, 0x7c, 0xb2], [0x04, 0x9d, 0x78, 0x78])
+            }
+            (ExtendedKeyFormat::NestedSegwit, _) => {
+                ([0x04, 0x4a, 0x52, 0x62], [0x04, 0x4a, 0x4e, 0x28])
+            }
+            (ExtendedKeyFormat::NativeSegwit, Network::Mainnet) => {
+                ([0x04, 0xb2, 0x47, 0x46], [0x04, 0xb2, 0x43, 0x0c])
+            }
+            (ExtendedKeyFormat::NativeSegwit, _) => {
+                ([0x04, 0x5f, 0x1c, 0xf6], [0x04, 0x5f, 0x18, 0xbc])
+            }
+        }
+    }
+
+    /// Finds the first network and format using the version bytes.
+    fn from_version(version: &[u8], private: bool) -> Result<(Network, Self), Error> {
+        Network::ALL
+            .iter()
+            .flat_map(|network| Self::ALL.iter().map(move |format| (*network, *format)))
+            .find(|(network, format)| {
+                let (public_version, private_version) = format.versions(*network);
+                let expected = if private {
+                    private_version
+                } else {
+                    public_version
+                };
+                expected[..] == *version
+            })
+            .ok_or(Error::InvalidExtendedKey)
+    }
+}
+
+/// Computes `HMAC-SHA512(chain_code, data || index)` and splits it into the tweak and the child
+/// chain code.
+fn child_hmac(chain_code: &ChainCode, data: &[u8], child: ChildNumber) -> (Secret, ChainCode) {
+    let mut input = Vec::with_capacity(data.len() + 4);
+    input.extend_from_slice(data);
+    input.extend_from_slice(&child.to_u32().to_be_bytes());
+    let i = hmac_sha512(chain_code.as_bytes(), &input);
+    (
+        Secret::from_slice(&i[..32]),
+        ChainCode::from_slice(&i[32..]),
+    )
+}
+
+/// Serializes the common fields of extended keys, followed by the checksum.
+fn extended_key_layout(
+    version: [u8; 4],
+    depth: u8,
+    parent_fingerprint: &Fingerprint,
+    child_number: ChildNumber,
+    chain_code: &ChainCode,
+    key: &[u8; 33],
+) -> Vec<u8> {
+    let mut result = Vec::with_capacity(EXTENDED_KEY_SIZE);
+    result.extend_from_slice(&version);
+    result.push(depth);
+    result.extend_from_slice(parent_fingerprint.as_bytes());
+    result.extend_from_slice(&child_number.to_u32().to_be_bytes());
+    result.extend_from_slice(chain_code.as_bytes());
+    result.extend_from_slice(key);
+    let cs = checksum(&result);
+    result.extend_from_slice(cs.as_bytes());
+    result
+}
+
+/// Checks the size and checksum of a serialized extended key, and that master keys have no
+/// parent fingerprint nor child number.
+fn check_extended_key_layout(data: &[u8]) -> Result<(), Error> {
+    if data.len() != EXTENDED_KEY_SIZE {
+        return Err(Error::InvalidExtendedKey);
+    }
+    let cs = checksum(&data[..EXTENDED_KEY_SIZE - 4]);
+    if &data[EXTENDED_KEY_SIZE - 4..] != cs.as_bytes() {
+        return Err(Error::InvalidChecksum);
+    }
+    if data[4] == 0 && data[5..13] != [0u8; 8] {
+        return Err(Error::InvalidExtendedKey);
+    }
+    Ok(())
+}
+
+fn child_number_from_slice(data: &[u8]) -> ChildNumber {
+    let mut index = [0u8; 4];
+    index.copy_from_slice(data);
+    ChildNumber::from(u32::from_be_bytes(index))
+}
+
+/// Extended private key
+#[derive(Ord, PartialOrd, Eq, PartialEq, Copy, Clone, Debug, Default)]
+pub struct ExtendedPrivKey {
+    /// The network on which this key should be used.
+    pub network: Network,
+    /// The script type of derived addresses.
+    pub format: ExtendedKeyFormat,
+    /// Number of derivations from the master key.
+    pub depth: u8,
+    /// Fingerprint of the parent key, zero for the master key.
+    pub parent_fingerprint: Fingerprint,
+    /// Index of this key in its parent.
+    pub child_number: ChildNumber,
+    /// Chain code.
+    pub chain_code: ChainCode,
+    /// ECDSA key.
+    pub secret: Secret,
+}
+
+impl fmt::Display for ExtendedPrivKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        bs58::encode(self.layout().as_slice()).into_string().fmt(f)
+    }
+}
+
+impl str::FromStr for ExtendedPrivKey {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let hex = bs58::decode(s)
+            .into_vec()
+            .map_err(|_| Error::InvalidExtendedKey)?;
+        ExtendedPrivKey::from_layout(&hex)
+    }
+}
+
+impl ExtendedPrivKey {
+    /// Creates the master key from a seed.
+    pub fn new_master(network: Network, seed: &[u8]) -> Result<Self, Error> {
+        let i = hmac_sha512(b"Bitcoin seed", seed);
+        let secret = Secret::from_slice(&i[..32]);
+        secp256k1::SecretKey::parse(secret.as_fixed_bytes())?;
+        Ok(ExtendedPrivKey {
+            network,
+            format: ExtendedKeyFormat::Legacy,
+            depth: 0,
+            parent_fingerprint: Fingerprint::zero(),
+            child_number: ChildNumber::Normal(0),
+            chain_code: ChainCode::from_slice(&i[32..]),
+            secret,
+        })
+    }
+
+    /// Returns the (compressed) private key.
+    pub fn private(&self) -> Private {
+        Private {
+            network: self.network,
+            secret: self.secret,
+            compressed: true,
+        }
+    }
+
+    /// Returns the compressed public key.
+    pub fn public(&self) -> Result<Public, Error> {
+        let secret_key = secp256k1::SecretKey::parse(self.secret.as_fixed_bytes())?;
+        let public = secp256k1::PublicKey::from_secret_key(&secret_key);
+        Ok(Public::Compressed(H264::from(
+            public.serialize_compressed(),
+        )))
+    }
+
+    /// Returns the identifier of the key (`hash160` of its public key).
+    pub fn identifier(&self) -> Result<AddressHash, Error> {
+        Ok(self.public()?.address_hash())
+    }
+
+    /// Returns the fingerprint of the key.
+    pub fn fingerprint(&self) -> Result<Fingerprint, Error> {
+        Ok(Fingerprint::from_slice(&self.identifier()?[..4]))
+    }
+
+    /// Derives the child key of the given index.
+    pub fn derive_child(&self, child: ChildNumber) -> Result<Self, Error> {
+        let (tweak, chain_code) = if child.is_hardened() {
+            let mut data = [0u8; 33];
+            data[1..].copy_from_slice(self.secret.as_bytes());
+            child_hmac(&self.chain_code, &data, child)
+        } else {
+            child_hmac(&self.chain_code, &self.public()?, child)
+        };
+
+        let tweak = secp256k1::SecretKey::parse(tweak.as_fixed_bytes())?;
+        let mut secret_key = secp256k1::SecretKey::parse(self.secret.as_fixed_bytes())?;
+        secret_key.tweak_add_assign(&tweak)?;
+
+        Ok(ExtendedPrivKey {
+            network: self.network,
+            format: self.format,
+            depth: self
+                .depth
+                .checked_add(1)
+                .ok_or(Error::InvalidDerivationPath)?,
+            parent_fingerprint: self.fingerprint()?,
+            child_number: child,
+            chain_code,
+            secret: Secret::from(secret_key.serialize()),
+        })
+    }
+
+    /// Derives the descendant key at the given path.
+    pub fn derive(&self, path: &DerivationPath) -> Result<Self, Error> {
+        path.iter()
+            .try_fold(*self, |key, child| key.derive_child(*child))
+    }
+}
+
+impl DisplayLayout for ExtendedPrivKey {
+    type Target = Vec<u8>;
+
+    fn layout(&self) -> Self::Target {
+        let mut key = [0u8; 33];
+        key[1..].copy_from_slice(self.secret.as_bytes());
+        let (_, version) = self.format.versions(self.network);
+        extended_key_layout(
+            version,
+            self.depth,
+            &self.parent_fingerprint,
+            self.child_number,
+            &self.chain_code,
+            &key,
+        )
+    }
+
+    fn from_layout(data: &[u8]) -> Result<Self, Error>
+    where
+        Self: Sized,
+    {
+        check_extended_key_layout(data)?;
+        let (network, format) = ExtendedKeyFormat::from_version(&data[0..4], true)?;
+        if data[45] != 0 {
+            return Err(Error::InvalidExtendedKey);
+        }
+        let secret = Secret::from_slice(&data[46..78]);
+        secp256k1::SecretKey::parse(secret.as_fixed_bytes())?;
+
+        Ok(ExtendedPrivKey {
+            network,
+            format,
+            depth: data[4],
+            parent_fingerprint: Fingerprint::from_slice(&data[5..9]),
+            child_number: child_number_from_slice(&data[9..13]),
+            chain_code: ChainCode::from_slice(&data[13..45]),
+            secret,
+        })
+    }
+}
+
+/// Extended public key
+#[derive(Ord, PartialOrd, Eq, PartialEq, Copy, Clone, Debug, Default)]
+pub struct ExtendedPubKey {
+    /// The network on which this key should be used.
+    pub network: Network,
+    /// The script type of derived addresses.
+    pub format: ExtendedKeyFormat,
+    /// Number of derivations from the master key.
+    pub depth: u8,
+    /// Fingerprint of the parent key, zero for the master key.
+    pub parent_fingerprint: Fingerprint,
+    /// Index of this key in its parent.
+    pub child_number: ChildNumber,
+    /// Chain code.
+    pub chain_code: ChainCode,
+    /// Compressed public key.
+    pub public: Public,
+}
+
+impl fmt::Display for ExtendedPubKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        bs58::encode(self.layout().as_slice()).into_string().fmt(f)
+    }
+}
+
+impl str::FromStr for ExtendedPubKey {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let hex = bs58::decode(s)
+            .into_vec()
+            .map_err(|_| Error::InvalidExtendedKey)?;
+        ExtendedPubKey::from_layout(&hex)
+    }
+}
+
+impl ExtendedPubKey {
+    /// Returns the extended public key of the extended private key.
+    pub fn from_private(xprv: &ExtendedPrivKey) -> Result<Self, Error> {
+        Ok(ExtendedPubKey {
+            network: xprv.network,
+            format: xprv.format,
+            depth: xprv.depth,
+            parent_fingerprint: xprv.parent_fingerprint,
+            child_number: xprv.child_number,
+            chain_code: xprv.chain_code,
+            public: xprv.public()?,
+        })
+    }
+
+    /// Returns the identifier of the key (`hash160` of its public key).
+    pub fn identifier(&self) -> AddressHash {
+        self.public.address_hash()
+    }
+
+    /// Returns the fingerprint of the key.
+    pub fn fingerprint(&self) -> Fingerprint {
+        Fingerprint::from_slice(&self.identifier()[..4])
+    }
+
+    /// Derives the child key of the given index. Hardened keys can't be derived from public keys.
+    pub fn derive_child(&self, child: ChildNumber) -> Result<Self, Error> {
+        if child.is_hardened() {
+            return Err(Error::InvalidDerivationPath);
+        }
+
+        let (tweak, chain_code) = child_hmac(&self.chain_code, &self.public, child);
+        let tweak = secp256k1::SecretKey::parse(tweak.as_fixed_bytes())?;
+        let mut public = secp256k1::PublicKey::parse_slice(&self.public, None)?;
+        public.tweak_add_assign(&tweak)?;
+
+        Ok(ExtendedPubKey {
+            network: self.network,
+            format: self.format,
+            depth: self
+                .depth
+                .checked_add(1)
+                .ok_or(Error::InvalidDerivationPath)?,
+            parent_fingerprint: self.fingerprint(),
+            child_number: child,
+            chain_code,
+            public: Public::Compressed(H264::from(public.serialize_compressed())),
+        })
+    }
+
+    /// Derives the descendant key at the given path.
+    pub fn derive(&self, path: &DerivationPath) -> Result<Self, Error> {
+        path.iter()
+            .try_fold(*self, |key, child| key.derive_child(*child))
+    }
+
+    /// Returns the address of the key, of the type given by the key format.
+    pub fn address(&self) -> Address {
+        let hash = self.identifier();
+        let (kind, hash) = match self.format {
+            ExtendedKeyFormat::Legacy => (Type::P2PKH, AddressTypes::Legacy(hash)),
+            ExtendedKeyFormat::NestedSegwit => {
+                // P2SH of the P2WPKH script `OP_0 <hash>`
+                let mut redeem_script = vec![0x00, 0x14];
+                redeem_script.extend_from_slice(hash.as_bytes());
+                (Type::P2SH, AddressTypes::Legacy(dhash160(&redeem_script)))
+            }
+            ExtendedKeyFormat::NativeSegwit => (Type::P2WPKH, AddressTypes::WitnessV0KeyHash(hash)),
+        };
+        Address {
+            kind,
+            network: self.network,
+            hash,
+        }
+    }
+}
+
+impl DisplayLayout for ExtendedPubKey {
+    type Target = Vec<u8>;
+
+    fn layout(&self) -> Self::Target {
+        let mut key = [0u8; 33];
+        key.copy_from_slice(&self.public);
+        let (version, _) = self.format.versions(self.network);
+        extended_key_layout(
+            version,
+            self.depth,
+            &self.parent_fingerprint,
+            self.child_number,
+            &self.chain_code,
+            &key,
+        )
+    }
+
+    fn from_layout(data: &[u8]) -> Result<Self, Error>
+    where
+        Self: Sized,
+    {
+        check_extended_key_layout(data)?;
+        let (network, format) = ExtendedKeyFormat::from_version(&data[0..4], false)?;
+        let public = Public::Compressed(H264::from_slice(&data[45..78]));
+        secp256k1::PublicKey::parse_slice(&public, None)?;
+
+        Ok(ExtendedPubKey {
+            network,
+            format,
+            depth: data[4],
+            parent_fingerprint: Fingerprint::from_slice(&data[5..9]),
+            child_number: child_number_from_slice(&data[9..13]),
+            chain_code: ChainCode::from_slice(&data[13..45]),
+            public,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // https://github.com/bitcoin/bips/blob/master/bip-0032.mediawiki#test-vector-1
+    #[test]
+    fn test_derive_bip32_vector_1() {
+        let seed = hex::decode("000102030405060708090a0b0c0d0e0f").unwrap();
+        let master = ExtendedPrivKey::new_master(Network::Mainnet, &seed).unwrap();
+        let vectors = [
+            (
+                "m",
+                "xpub661MyMwAqRbcFtXgS5sYJABqqG9YLmC4Q1Rdap9gSE8NqtwybGhePY2gZ29ESFjqJoCu1Rupje8YtGqsefD265TMg7usUDFdp6W1EGMcet8",
+                "xprv9s21ZrQH143K3QTDL4LXw2F7HEK3wJUD2nW2nRk4stbPy6cq3jPPqjiChkVvvNKmPGJxWUtg6LnF5kejMRNNU3TGtRBeJgk33yuGBxrMPHi",
+            ),
+            (
+                "m/0'",
+                "xpub68Gmy5EdvgibQVfPdqkBBCHxA5htiqg55crXYuXoQRKfDBFA1WEjWgP6LHhwBZeNK1VTsfTFUHCdrfp1bgwQ9xv5ski8PX9rL2dZXvgGDnw",
+                "xprv9uHRZZhk6KAJC1avXpDAp4MDc3sQKNxDiPvvkX8Br5ngLNv1TxvUxt4cV1rGL5hj6KCesnDYUhd7oWgT11eZG7XnxHrnYeSvkzY7d2bhkJ7",
+            ),
+            (
+                "m/0'/1",
+                "xpub6ASuArnXKPbfEwhqN6e3mwBcDTgzisQN1wXN9BJcM47sSikHjJf3UFHKkNAWbWMiGj7Wf5uMash7SyYq527Hqck2AxYysAA7xmALppuCkwQ",
+                "xprv9wTYmMFdV23N2TdNG573QoEsfRrWKQgWeibmLntzniatZvR9BmLnvSxqu53Kw1UmYPxLgboyZQaXwTCg8MSY3H2EU4pWcQDnRnrVA1xe8fs",
+            ),
+            (
+                "m/0'/1/2'",
+                "xpub6D4BDPcP2GT577Vvch3R8wDkScZWzQzMMUm3PWbmWvVJrZwQY4VUNgqFJPMM3No2dFDFGTsxxpG5uJh7n7epu4trkrX7x7DogT5Uv6fcLW5",
+                "xprv9z4pot5VBttmtdRTWfWQmoH1taj2axGVzFqSb8C9xaxKymcFzXBDptWmT7FwuEzG3ryjH4ktypQSAewRiNMjANTtpgP4mLTj34bhnZX7UiM",
+            ),
+            (
+                "m/0'/1/2'/2/1000000000",
+                "xpub6H1LXWLaKsWFhvm6RVpEL9P4KfRZSW7abD2ttkWP3SSQvnyA8FSVqNTEcYFgJS2UaFcxupHiYkro49S8yGasTvXEYBVPamhGW6cFJodrTHy",
+                "xprvA41z7zogVVwxVSgdKUHDy1SKmdb533PjDz7J6N6mV6uS3ze1ai8FHa8kmHScGpWmj4WggLyQjgPie1rFSruoUihUZREPSL39UNdE3BBDu76",
+            ),
+        ];
+        for (path, xpub, xprv) in vectors.iter() {
+            let path: DerivationPath = path.parse().unwrap();
+            let key = master.derive(&path).unwrap();
+            assert_eq!(key.to_string(), *xprv);
+            assert_eq!(xprv.parse::<ExtendedPrivKey>(), Ok(key));
+
+            let public = ExtendedPubKey::from_private(&key).unwrap();
+            assert_eq!(public.to_string(), *xpub);
+            assert_eq!(xpub.parse::<ExtendedPubKey>(), Ok(public));
+        }
+    }
+
+    // https://github.com/bitcoin/bips/blob/master/bip-0032.mediawiki#test-vector-2
+    #[test]
+    fn test_derive_bip32_vector_2() {
+        let seed = hex::decode("fffcf9f6f3f0edeae7e4e1dedbd8d5d2cfccc9c6c3c0bdbab7b4b1aeaba8a5a29f9c999693908d8a8784817e7b7875726f6c696663605d5a5754514e4b484542").unwrap();
+        let master = ExtendedPrivKey::new_master(Network::Mainnet, &seed).unwrap();
+        let vectors = [
+            (
+                "m",
+                "xpub661MyMwAqRbcFW31YEwpkMuc5THy2PSt5bDMsktWQcFF8syAmRUapSCGu8ED9W6oDMSgv6Zz8idoc4a6mr8BDzTJY47LJhkJ8UB7WEGuduB",
+                "xprv9s21ZrQH143K31xYSDQpPDxsXRTUcvj2iNHm5NUtrGiGG5e2DtALGdso3pGz6ssrdK4PFmM8NSpSBHNqPqm55Qn3LqFtT2emdEXVYsCzC2U",
+            ),
+            (
+                "m/0",
+                "xpub69H7F5d8KSRgmmdJg2KhpAK8SR3DjMwAdkxj3ZuxV27CprR9LgpeyGmXUbC6wb7ERfvrnKZjXoUmmDznezpbZb7ap6r1D3tgFxHmwMkQTPH",
+                "xprv9vHkqa6EV4sPZHYqZznhT2NPtPCjKuDKGY38FBWLvgaDx45zo9WQRUT3dKYnjwih2yJD9mkrocEZXo1ex8G81dwSM1fwqWpWkeS3v86pgKt",
+            ),
+            (
+                "m/0/2147483647'",
+                "xpub6ASAVgeehLbnwdqV6UKMHVzgqAG8Gr6riv3Fxxpj8ksbH9ebxaEyBLZ85ySDhKiLDBrQSARLq1uNRts8RuJiHjaDMBU4Zn9h8LZNnBC5y4a",
+                "xprv9wSp6B7kry3Vj9m1zSnLvN3xH8RdsPP1Mh7fAaR7aRLcQMKTR2vidYEeEg2mUCTAwCd6vnxVrcjfy2kRgVsFawNzmjuHc2YmYRmagcEPdU9",
+            ),
+            (
+                "m/0/2147483647'/1",
+                "xpub6DF8uhdarytz3FWdA8TvFSvvAh8dP3283MY7p2V4SeE2wyWmG5mg5EwVvmdMVCQcoNJxGoWaU9DCWh89LojfZ537wTfunKau47EL2dhHKon",
+                "xprv9zFnWC6h2cLgpmSA46vutJzBcfJ8yaJGg8cX1e5StJh45BBciYTRXSd25UEPVuesF9yog62tGAQtHjXajPPdbRCHuWS6T8XA2ECKADdw4Ef",
+            ),
+            (
+                "m/0/2147483647'/1/2147483646'",
+                "xpub6ERApfZwUNrhLCkDtcHTcxd75RbzS1ed54G1LkBUHQVHQKqhMkhgbmJbZRkrgZw4koxb5JaHWkY4ALHY2grBGRjaDMzQLcgJvLJuZZvRcEL",
+                "xprvA1RpRA33e1JQ7ifknakTFpgNXPmW2YvmhqLQYMmrj4xJXXWYpDPS3xz7iAxn8L39njGVyuoseXzU6rcxFLJ8HFsTjSyQbLYnMpCqE2VbFWc",
+            ),
+            (
+                "m/0/2147483647'/1/2147483646'/2",
+                "xpub6FnCn6nSzZAw5Tw7cgR9bi15UV96gLZhjDstkXXxvCLsUXBGXPdSnLFbdpq8p9HmGsApME5hQTZ3emM2rnY5agb9rXpVGyy3bdW6EEgAtqt",
+                "xprvA2nrNbFZABcdryreWet9Ea4LvTJcGsqrMzxHx98MMrotbir7yrKCEXw7nadnHM8Dq38EGfSh6dqA9QWTyefMLEcBYJUuekgW4BYPJcr9E7j",
+            ),
+        ];
+        for (path, xpub, xprv) in vectors.iter() {
+            let path: DerivationPath = path.parse().unwrap();
+            let key = master.derive(&path).unwrap();
+            assert_eq!(key.to_string(), *xprv);
+            assert_eq!(xprv.parse::<ExtendedPrivKey>(), Ok(key));
+
+            let public = ExtendedPubKey::from_private(&key).unwrap();
+            assert_eq!(public.to_string(), *xpub);
+            assert_eq!(xpub.parse::<ExtendedPubKey>(), Ok(public));
+        }
+    }
+
+    // https://github.com/bitcoin/bips/blob/master/bip-0032.mediawiki#test-vector-3
+    #[test]
+    fn test_derive_bip32_vector_3() {
+        // Retention of leading zeros of the private key
+        let seed = hex::decode("4b381541583be4423346c643850da4b320e46a87ae3d2a4e6da11eba819cd4acba45d239319ac14f863b8d5ab5a0d0c64d2e8a1e7d1457df2e5a3c51c73235be").unwrap();
+        let master = ExtendedPrivKey::new_master(Network::Mainnet, &seed).unwrap();
+        let vectors = [
+            (
+                "m",
+                "xpub661MyMwAqRbcEZVB4dScxMAdx6d4nFc9nvyvH3v4gJL378CSRZiYmhRoP7mBy6gSPSCYk6SzXPTf3ND1cZAceL7SfJ1Z3GC8vBgp2epUt13",
+                "xprv9s21ZrQH143K25QhxbucbDDuQ4naNntJRi4KUfWT7xo4EKsHt2QJDu7KXp1A3u7Bi1j8ph3EGsZ9Xvz9dGuVrtHHs7pXeTzjuxBrCmmhgC6",
+            ),
+            (
+                "m/0'",
+                "xpub68NZiKmJWnxxS6aaHmn81bvJeTESw724CRDs6HbuccFQN9Ku14VQrADWgqbhhTHBaohPX4CjNLf9fq9MYo6oDaPPLPxSb7gwQN3ih19Zm4Y",
+                "xprv9uPDJpEQgRQfDcW7BkF7eTya6RPxXeJCqCJGHuCJ4GiRVLzkTXBAJMu2qaMWPrS7AANYqdq6vcBcBUdJCVVFceUvJFjaPdGZ2y9WACViL4L",
+            ),
+        ];
+        for (path, xpub, xprv) in vectors.iter() {
+            let path: DerivationPath = path.parse().unwrap();
+            let key = master.derive(&path).unwrap();
+            assert_eq!(key.to_string(), *xprv);
+            assert_eq!(xprv.parse::<ExtendedPrivKey>(), Ok(key));
+
+            let public = ExtendedPubKey::from_private(&key).unwrap();
+            assert_eq!(public.to_string(), *xpub);
+            assert_eq!(xpub.parse::<ExtendedPubKey>(), Ok(public));
+        }
+    }
+
+    // https://github.com/bitcoin/bips/blob/master/bip-0032.mediawiki#test-vector-5
+    #[test]
+    fn test_invalid_bip32_vector_5() {
+        let invalid_public = [
+            // Private key with a public key version
+            "xpub661MyMwAqRbcEYS8w7XLSVeEsBXy79zSzH1J8vCdxAZningWLdN3zgtU6LBpB85b3D2yc8sfvZU521AAwdZafEz7mnzBBsz4wKY5fTtTQBm",
+            // Public key prefixes 04 and 01
+            "xpub661MyMwAqRbcEYS8w7XLSVeEsBXy79zSzH1J8vCdxAZningWLdN3zgtU6Txnt3siSujt9RCVYsx4qHZGc62TG4McvMGcAUjeuwZdduYEvFn",
+            "xpub661MyMwAqRbcEYS8w7XLSVeEsBXy79zSzH1J8vCdxAZningWLdN3zgtU6N8ZMMXctdiCjxTNq964yKkwrkBJJwpzZS4HS2fxvyYUA4q2Xe4",
+            // Zero depth with a non-zero parent fingerprint
+            "xpub661no6RGEX3uJkY4bNnPcw4URcQTrSibUZ4NqJEw5eBkv7ovTwgiT91XX27VbEXGENhYRCf7hyEbWrR3FewATdCEebj6znwMfQkhRYHRLpJ",
+            // Zero depth with a non-zero index
+            "xpub661MyMwAuDcm6CRQ5N4qiHKrJ39Xe1R1NyfouMKTTWcguwVcfrZJaNvhpebzGerh7gucBvzEQWRugZDuDXjNDRmXzSZe4c7mnTK97pTvGS8",
+            // Unknown version
+            "DMwo58pR1QLEFihHiXPVykYB6fJmsTeHvyTp7hRThAtCX8CvYzgPcn8XnmdfHGMQzT7ayAmfo4z3gY5KfbrZWZ6St24UVf2Qgo6oujFktLHdHY4",
+            // Public key not on the curve
+            "xpub661MyMwAqRbcEYS8w7XLSVeEsBXy79zSzH1J8vCdxAZningWLdN3zgtU6Q5JXayek4PRsn35jii4veMimro1xefsM58PgBMrvdYre8QyULY",
+        ];
+        for xpub in invalid_public.iter() {
+            assert!(xpub.parse::<ExtendedPubKey>().is_err(), "{}", xpub);
+        }
+
+        let invalid_private = [
+            // Public key with a private key version
+            "xprv9s21ZrQH143K24Mfq5zL5MhWK9hUhhGbd45hLXo2Pq2oqzMMo63oStZzFGTQQD3dC4H2D5GBj7vWvSQaaBv5cxi9gafk7NF3pnBju6dwKvH",
+            // Private key prefixes 04 and 01
+            "xprv9s21ZrQH143K24Mfq5zL5MhWK9hUhhGbd45hLXo2Pq2oqzMMo63oStZzFGpWnsj83BHtEy5Zt8CcDr1UiRXuWCmTQLxEK9vbz5gPstX92JQ",
+            "xprv9s21ZrQH143K24Mfq5zL5MhWK9hUhhGbd45hLXo2Pq2oqzMMo63oStZzFAzHGBP2UuGCqWLTAPLcMtD9y5gkZ6Eq3Rjuahrv17fEQ3Qen6J",
+            // Zero depth with a non-zero parent fingerprint
+            "xprv9s2SPatNQ9Vc6GTbVMFPFo7jsaZySyzk7L8n2uqKXJen3KUmvQNTuLh3fhZMBoG3G4ZW1N2kZuHEPY53qmbZzCHshoQnNf4GvELZfqTUrcv",
+            // Zero depth with a non-zero index
+            "xprv9s21ZrQH4r4TsiLvyLXqM9P7k1K3EYhA1kkD6xuquB5i39AU8KF42acDyL3qsDbU9NmZn6MsGSUYZEsuoePmjzsB3eFKSUEh3Gu1N3cqVUN",
+            // Private keys out of the 1..n-1 range
+            "xprv9s21ZrQH143K24Mfq5zL5MhWK9hUhhGbd45hLXo2Pq2oqzMMo63oStZzF93Y5wvzdUayhgkkFoicQZcP3y52uPPxFnfoLZB21Teqt1VvEHx",
+            "xprv9s21ZrQH143K24Mfq5zL5MhWK9hUhhGbd45hLXo2Pq2oqzMMo63oStZzFAzHGBP2UuGCqWLTAPLcMtD9y5gkZ6Eq3Rjuahrv17fENZ3QzxW",
+        ];
+        for xprv in invalid_private.iter() {
+            assert!(xprv.parse::<ExtendedPrivKey>().is_err(), "{}", xprv);
+        }
+
+        assert_eq!(
+            "xpub661no6RGEX3uJkY4bNnPcw4URcQTrSibUZ4NqJEw5eBkv7ovTwgiT91XX27VbEXGENhYRCf7hyEbWrR3FewATdCEebj6znwMfQkhRYHRLpJ"
+                .parse::<ExtendedPubKey>(),
+            Err(Error::InvalidExtendedKey)
+        );
+        assert_eq!(
+            "xprv9s21ZrQH4r4TsiLvyLXqM9P7k1K3EYhA1kkD6xuquB5i39AU8KF42acDyL3qsDbU9NmZn6MsGSUYZEsuoePmjzsB3eFKSUEh3Gu1N3cqVUN"
+                .parse::<ExtendedPrivKey>(),
+            Err(Error::InvalidExtendedKey)
+        );
+        assert_eq!(
+            "xprv9s21ZrQH143K3QTDL4LXw2F7HEK3wJUD2nW2nRk4stbPy6cq3jPPqjiChkVvvNKmPGJxWUtg6LnF5kejMRNNU3TGtRBeJgk33yuGBxrMPHL"
+                .parse::<ExtendedPrivKey>(),
+            Err(Error::InvalidChecksum)
+        );
+    }
+
+    #[test]
+    fn test_derive_public() {
+        let seed = hex::decode("000102030405060708090a0b0c0d0e0f").unwrap();
+        let master = ExtendedPrivKey::new_master(Network::Mainnet, &seed).unwrap();
+        let account = master.derive(&"m/0'/1".parse().unwrap()).unwrap();
+        let account_public = ExtendedPubKey::from_private(&account).unwrap();
+
+        let path: DerivationPath = "m/2/1000000000".parse().unwrap();
+        assert_eq!(
+            account_public.derive(&path),
+            ExtendedPubKey::from_private(&account.derive(&path).unwrap())
+        );
+        assert_eq!(
+            account_public.derive_child(ChildNumber::Hardened(0)),
+            Err(Error::InvalidDerivationPath)
+        );
+        assert_eq!(
+            account_public.parent_fingerprint,
+            master
+                .derive_child(ChildNumber::Hardened(0))
+                .unwrap()
+                .fingerprint()
+                .unwrap()
+        );
+    }
+
+    // https://github.com/bitcoin/bips/blob/master/bip-0084.mediawiki#test-vectors
+    #[test]
+    fn test_zpub_addresses() {
+        let zpub = "zpub6rFR7y4Q2AijBEqTUquhVz398htDFrtymD9xYYfG1m4wAcvPhXNfE3EfH1r1ADqtfSdVCToUG868RvUUkgDKf31mGDtKsAYz2oz2AGutZYs";
+        let account: ExtendedPubKey = zpub.parse().unwrap();
+        assert_eq!(account.network, Network::Mainnet);
+        assert_eq!(account.format, ExtendedKeyFormat::NativeSegwit);
+        assert_eq!(account.depth, 3);
+        assert_eq!(account.to_string(), zpub);
+
+        let address = account.derive(&"m/0/0".parse().unwrap()).unwrap().address();
+        assert_eq!(
+            address.to_string(),
+            "bc1qcr8te4kr609gcawutmrza0j4xv80jy8z306fyu"
+        );
+        let address = account.derive(&"m/1/0".parse().unwrap()).unwrap().address();
+        assert_eq!(
+            address.to_string(),
+            "bc1q8c6fshw2dlwun7ekn9qwf37cu2rn755upcp6el"
+        );
+    }
+
+    #[test]
+    fn test_derivation_path() {
+        let path: DerivationPath = "m/84'/0'/0h/0/1".parse().unwrap();
+        assert_eq!(
+            *path,
+            [
+                ChildNumber::Hardened(84),
+                ChildNumber::Hardened(0),
+                ChildNumber::Hardened(0),
+                ChildNumber::Normal(0),
+                ChildNumber::Normal(1),
+            ]
+        );
+        assert_eq!(path.to_string(), "m/84'/0'/0'/0/1");
+        assert_eq!("m".parse::<DerivationPath>(), Ok(DerivationPath::default()));
+
+        assert!("".parse::<DerivationPath>().is_err());
+        assert!("84'/0'".parse::<DerivationPath>().is_err());
+        assert!("m/".parse::<DerivationPath>().is_err());
+        assert!("m/+1".parse::<DerivationPath>().is_err());
+        assert!("m/2147483648".parse::<DerivationPath>().is_err());
+        assert_eq!(
+            "m/2147483647'".parse::<DerivationPath>().unwrap()[0].to_u32(),
+            u32::MAX
+        );
+    }
+}
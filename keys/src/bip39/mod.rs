@@ -0,0 +1,210 @@
+//! BIP39 mnemonic codes for generating deterministic keys.
+//!
+//! https://github.com/bitcoin/bips/blob/master/bip-0039.mediawiki
+
+mod english;
+
+#[cfg(not(feature = "std"))]
+use alloc::{format, vec, vec::Vec};
+use core::{fmt, str};
+
+use light_bitcoin_crypto::{pbkdf2_hmac_sha512, sha256};
+use light_bitcoin_primitives::H512;
+
+use crate::error::Error;
+
+use self::english::WORDS;
+
+/// Number of PBKDF2 rounds of the seed derivation.
+const PBKDF2_ROUNDS: u32 = 2048;
+
+/// Number of bits encoded by a single word.
+const BITS_PER_WORD: usize = 11;
+
+/// Mnemonic sentence encoding 128 to 256 bits of entropy.
+#[derive(Ord, PartialOrd, Eq, PartialEq, Clone, Debug)]
+pub struct Mnemonic {
+    entropy: Vec<u8>,
+}
+
+impl Mnemonic {
+    /// Creates the mnemonic of the entropy, which must be 16, 20, 24, 28 or 32 bytes long.
+    pub fn from_entropy(entropy: &[u8]) -> Result<Self, Error> {
+        if entropy.len() < 16 || entropy.len() > 32 || !entropy.len().is_multiple_of(4) {
+            return Err(Error::InvalidMnemonic);
+        }
+        Ok(Mnemonic {
+            entropy: entropy.to_vec(),
+        })
+    }
+
+    /// Returns the entropy encoded by the mnemonic.
+    pub fn entropy(&self) -> &[u8] {
+        &self.entropy
+    }
+
+    /// Returns the words of the mnemonic sentence.
+    pub fn words(&self) -> impl Iterator<Item = &'static str> + '_ {
+        // The checksum is the first `ENT / 32` bits of `sha256(entropy)`
+        let checksum = sha256(&self.entropy);
+        let bits = self.entropy.len() * 8 + self.entropy.len() / 4;
+        let bit = move |i: usize| {
+            let byte = if i < self.entropy.len() * 8 {
+                self.entropy[i / 8]
+            } else {
+                checksum[(i - self.entropy.len() * 8) / 8]
+            };
+            (byte >> (7 - i % 8)) & 1 == 1
+        };
+        (0..bits / BITS_PER_WORD).map(move |word| {
+            let index = (0..BITS_PER_WORD).fold(0usize, |index, i| {
+                (index << 1) | bit(word * BITS_PER_WORD + i) as usize
+            });
+            WORDS[index]
+        })
+    }
+
+    /// Derives the 64-byte seed of the mnemonic, which is the input of `ExtendedPrivKey::new_master`.
+    ///
+    /// The passphrase is used as given, non-ASCII passphrases must be NFKD normalized by the
+    /// caller.
+    pub fn to_seed(&self, passphrase: &str) -> H512 {
+        let sentence = self.words().collect::<Vec<_>>().join(" ");
+        let salt = format!("mnemonic{}", passphrase);
+        pbkdf2_hmac_sha512(sentence.as_bytes(), salt.as_bytes(), PBKDF2_ROUNDS)
+    }
+}
+
+impl fmt::Display for Mnemonic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, word) in self.words().enumerate() {
+            if i > 0 {
+                f.write_str(" ")?;
+            }
+            f.write_str(word)?;
+        }
+        Ok(())
+    }
+}
+
+impl str::FromStr for Mnemonic {
+    type Err = Error;
+
+    /// Parses a mnemonic sentence, checking its length, words and checksum.
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let indexes = s
+            .split_whitespace()
+            .map(|word| {
+                WORDS
+                    .binary_search(&word)
+                    .map_err(|_| Error::InvalidMnemonic)
+            })
+            .collect::<Result<Vec<usize>, Error>>()?;
+        if indexes.len() < 12 || indexes.len() > 24 || !indexes.len().is_multiple_of(3) {
+            return Err(Error::InvalidMnemonic);
+        }
+
+        let bits = indexes.len() * BITS_PER_WORD;
+        let entropy_bits = bits * 32 / 33;
+        let mut data = vec![0u8; bits.div_ceil(8)];
+        for (i, index) in indexes.iter().enumerate() {
+            for j in 0..BITS_PER_WORD {
+                if (index >> (BITS_PER_WORD - 1 - j)) & 1 == 1 {
+                    let bit = i * BITS_PER_WORD + j;
+                    data[bit / 8] |= 1 << (7 - bit % 8);
+                }
+            }
+        }
+
+        let mnemonic = Mnemonic::from_entropy(&data[..entropy_bits / 8])?;
+        // The checksum bits follow the entropy, and fit in the last byte
+        let checksum_bits = bits - entropy_bits;
+        let expected = sha256(mnemonic.entropy())[0] >> (8 - checksum_bits);
+        let checksum = data[entropy_bits / 8] >> (8 - checksum_bits);
+        if checksum != expected {
+            return Err(Error::InvalidChecksum);
+        }
+        Ok(mnemonic)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use light_bitcoin_primitives::h512;
+
+    use super::*;
+    use crate::address::Network;
+    use crate::bip32::{ExtendedKeyFormat, ExtendedPrivKey, ExtendedPubKey};
+
+    // https://github.com/trezor/python-mnemonic/blob/master/vectors.json
+    #[test]
+    fn test_mnemonic_vectors() {
+        let vectors = [
+            (
+                "00000000000000000000000000000000",
+                "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about",
+                "c55257c360c07c72029aebc1b53c05ed0362ada38ead3e3e9efa3708e53495531f09a6987599d18264c1e1c92f2cf141630c7a3c4ab7c81b2f001698e7463b04",
+            ),
+            (
+                "7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f7f",
+                "legal winner thank year wave sausage worth useful legal winner thank yellow",
+                "2e8905819b8723fe2c1d161860e5ee1830318dbf49a83bd451cfb8440c28bd6fa457fe1296106559a3c80937a1c1069be3a3a5bd381ee6260e8d9739fce1f607",
+            ),
+            (
+                "ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+                "zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo zoo vote",
+                "dd48c104698c30cfe2b6142103248622fb7bb0ff692eebb00089b32d22484e1613912f0a5b694407be899ffd31ed3992c456cdf60f5d4564b8ba3f05a69890ad",
+            ),
+        ];
+        for (entropy, sentence, seed) in vectors.iter() {
+            let mnemonic = Mnemonic::from_entropy(&hex::decode(entropy).unwrap()).unwrap();
+            assert_eq!(mnemonic.to_string(), *sentence);
+            assert_eq!(sentence.parse::<Mnemonic>(), Ok(mnemonic.clone()));
+            assert_eq!(mnemonic.to_seed("TREZOR"), h512(seed));
+        }
+    }
+
+    #[test]
+    fn test_invalid_mnemonic() {
+        assert_eq!(
+            Mnemonic::from_entropy(&[0; 15]),
+            Err(Error::InvalidMnemonic)
+        );
+        assert_eq!(
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon"
+                .parse::<Mnemonic>(),
+            Err(Error::InvalidChecksum)
+        );
+        assert_eq!(
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about"
+                .parse::<Mnemonic>(),
+            Err(Error::InvalidMnemonic)
+        );
+        assert_eq!(
+            "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon bitcoin"
+                .parse::<Mnemonic>(),
+            Err(Error::InvalidMnemonic)
+        );
+    }
+
+    // https://github.com/bitcoin/bips/blob/master/bip-0084.mediawiki#test-vectors
+    #[test]
+    fn test_seed_to_extended_key() {
+        let mnemonic: Mnemonic = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about"
+            .parse()
+            .unwrap();
+        let seed = mnemonic.to_seed("");
+        let mut master = ExtendedPrivKey::new_master(Network::Mainnet, seed.as_bytes()).unwrap();
+        master.format = ExtendedKeyFormat::NativeSegwit;
+        assert_eq!(
+            master.to_string(),
+            "zprvAWgYBBk7JR8Gjrh4UJQ2uJdG1r3WNRRfURiABBE3RvMXYSrRJL62XuezvGdPvG6GFBZduosCc1YP5wixPox7zhZLfiUm8aunE96BBa4Kei5"
+        );
+
+        let account = master.derive(&"m/84'/0'/0'".parse().unwrap()).unwrap();
+        assert_eq!(
+            ExtendedPubKey::from_private(&account).unwrap().to_string(),
+            "zpub6rFR7y4Q2AijBEqTUquhVz398htDFrtymD9xYYfG1m4wAcvPhXNfE3EfH1r1ADqtfSdVCToUG868RvUUkgDKf31mGDtKsAYz2oz2AGutZYs"
+        );
+    }
+}
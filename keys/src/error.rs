@@ -8,6 +8,10 @@ pub enum Error {
     InvalidChecksum,
     InvalidPrivate,
     InvalidAddress,
+    InvalidTweak,
+    InvalidDerivationPath,
+    InvalidExtendedKey,
+    InvalidMnemonic,
     FailedKeyGeneration,
 }
 
@@ -25,6 +29,10 @@ impl core::fmt::Display for Error {
             Error::InvalidChecksum => "Invalid Checksum",
             Error::InvalidPrivate => "Invalid Private",
             Error::InvalidAddress => "Invalid Address",
+            Error::InvalidTweak => "Invalid Tweak",
+            Error::InvalidDerivationPath => "Invalid Derivation Path",
+            Error::InvalidExtendedKey => "Invalid Extended Key",
+            Error::InvalidMnemonic => "Invalid Mnemonic",
             Error::FailedKeyGeneration => "Key generation failed",
         };
 
@@ -6,22 +6,33 @@
 extern crate alloc;
 
 mod address;
+mod bech32;
+mod bip32;
+mod bip39;
 mod display;
 mod error;
 mod keypair;
+mod network;
 mod private;
 mod public;
+mod schnorr;
 mod signature;
 
 use light_bitcoin_primitives::*;
 
-pub use self::address::{Address, Network, Type};
+pub use self::address::{Address, AddressTypes, Network, Type};
+pub use self::bip32::{
+    ChainCode, ChildNumber, DerivationPath, ExtendedKeyFormat, ExtendedPrivKey, ExtendedPubKey,
+    Fingerprint,
+};
+pub use self::bip39::Mnemonic;
 pub use self::display::DisplayLayout;
 pub use self::error::Error;
 pub use self::keypair::KeyPair;
+pub use self::network::NetworkParams;
 pub use self::private::Private;
 pub use self::public::Public;
-pub use self::signature::{CompactSignature, Signature};
+pub use self::signature::{CompactSignature, SchnorrSignature, Signature};
 
 /// 20 bytes long hash derived from public `ripemd160(sha256(public))`
 pub type AddressHash = H160;
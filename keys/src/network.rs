@@ -0,0 +1,181 @@
+//! Consensus and encoding parameters of the bitcoin networks.
+
+use light_bitcoin_primitives::{H256, U256};
+
+use crate::address::Network;
+
+/// Parameters of a bitcoin network.
+#[derive(Eq, PartialEq, Copy, Clone, Debug)]
+pub struct NetworkParams {
+    /// Base58 version byte of P2PKH addresses.
+    pub p2pkh_prefix: u8,
+    /// Base58 version byte of P2SH addresses.
+    pub p2sh_prefix: u8,
+    /// Human-readable part of Bech32/Bech32m segwit addresses.
+    pub bech32_hrp: &'static str,
+    /// Base58 version byte of WIF private keys.
+    pub wif_prefix: u8,
+    /// BIP32 version bytes of extended public keys.
+    pub bip32_public: [u8; 4],
+    /// BIP32 version bytes of extended private keys.
+    pub bip32_private: [u8; 4],
+    /// Hash of the genesis block.
+    pub genesis_hash: H256,
+    /// Magic bytes starting every P2P message.
+    pub magic: [u8; 4],
+    /// Maximum proof-of-work target.
+    pub pow_limit: U256,
+}
+
+impl NetworkParams {
+    /// Bitcoin mainnet
+    pub const MAINNET: NetworkParams = NetworkParams {
+        p2pkh_prefix: 0,
+        p2sh_prefix: 5,
+        bech32_hrp: "bc",
+        wif_prefix: 128,
+        bip32_public: [0x04, 0x88, 0xb2, 0x1e],
+        bip32_private: [0x04, 0x88, 0xad, 0xe4],
+        // 000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f
+        genesis_hash: H256([
+            0x6f, 0xe2, 0x8c, 0x0a, 0xb6, 0xf1, 0xb3, 0x72, 0xc1, 0xa6, 0xa2, 0x46, 0xae, 0x63,
+            0xf7, 0x4f, 0x93, 0x1e, 0x83, 0x65, 0xe1, 0x5a, 0x08, 0x9c, 0x68, 0xd6, 0x19, 0x00,
+            0x00, 0x00, 0x00, 0x00,
+        ]),
+        magic: [0xf9, 0xbe, 0xb4, 0xd9],
+        // 00000000ffffffffffffffffffffffffffffffffffffffffffffffffffffffff
+        pow_limit: U256([u64::MAX, u64::MAX, u64::MAX, 0x0000_0000_ffff_ffff]),
+    };
+
+    /// Bitcoin testnet (version 3)
+    pub const TESTNET: NetworkParams = NetworkParams {
+        p2pkh_prefix: 111,
+        p2sh_prefix: 196,
+        bech32_hrp: "tb",
+        wif_prefix: 239,
+        bip32_public: [0x04, 0x35, 0x87, 0xcf],
+        bip32_private: [0x04, 0x35, 0x83, 0x94],
+        // 000000000933ea01ad0ee984209779baaec3ced90fa3f408719526f8d77f4943
+        genesis_hash: H256([
+            0x43, 0x49, 0x7f, 0xd7, 0xf8, 0x26, 0x95, 0x71, 0x08, 0xf4, 0xa3, 0x0f, 0xd9, 0xce,
+            0xc3, 0xae, 0xba, 0x79, 0x97, 0x20, 0x84, 0xe9, 0x0e, 0xad, 0x01, 0xea, 0x33, 0x09,
+            0x00, 0x00, 0x00, 0x00,
+        ]),
+        magic: [0x0b, 0x11, 0x09, 0x07],
+        // 00000000ffffffffffffffffffffffffffffffffffffffffffffffffffffffff
+        pow_limit: U256([u64::MAX, u64::MAX, u64::MAX, 0x0000_0000_ffff_ffff]),
+    };
+
+    /// Bitcoin regression test network
+    pub const REGTEST: NetworkParams = NetworkParams {
+        p2pkh_prefix: 111,
+        p2sh_prefix: 196,
+        bech32_hrp: "bcrt",
+        wif_prefix: 239,
+        bip32_public: [0x04, 0x35, 0x87, 0xcf],
+        bip32_private: [0x04, 0x35, 0x83, 0x94],
+        // 0f9188f13cb7b2c71f2a335e3a4fc328bf5beb436012afca590b1a11466e2206
+        genesis_hash: H256([
+            0x06, 0x22, 0x6e, 0x46, 0x11, 0x1a, 0x0b, 0x59, 0xca, 0xaf, 0x12, 0x60, 0x43, 0xeb,
+            0x5b, 0xbf, 0x28, 0xc3, 0x4f, 0x3a, 0x5e, 0x33, 0x2a, 0x1f, 0xc7, 0xb2, 0xb7, 0x3c,
+            0xf1, 0x88, 0x91, 0x0f,
+        ]),
+        magic: [0xfa, 0xbf, 0xb5, 0xda],
+        // 7fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff
+        pow_limit: U256([u64::MAX, u64::MAX, u64::MAX, 0x7fff_ffff_ffff_ffff]),
+    };
+
+    /// Bitcoin default signet (BIP325)
+    pub const SIGNET: NetworkParams = NetworkParams {
+        p2pkh_prefix: 111,
+        p2sh_prefix: 196,
+        bech32_hrp: "tb",
+        wif_prefix: 239,
+        bip32_public: [0x04, 0x35, 0x87, 0xcf],
+        bip32_private: [0x04, 0x35, 0x83, 0x94],
+        // 00000008819873e925422c1ff0f99f7cc9bbb232af63a077a480a3633bee1ef6
+        genesis_hash: H256([
+            0xf6, 0x1e, 0xee, 0x3b, 0x63, 0xa3, 0x80, 0xa4, 0x77, 0xa0, 0x63, 0xaf, 0x32, 0xb2,
+            0xbb, 0xc9, 0x7c, 0x9f, 0xf9, 0xf0, 0x1f, 0x2c, 0x42, 0x25, 0xe9, 0x73, 0x98, 0x81,
+            0x08, 0x00, 0x00, 0x00,
+        ]),
+        magic: [0x0a, 0x03, 0xcf, 0x40],
+        // 00000377ae000000000000000000000000000000000000000000000000000000
+        pow_limit: U256([0, 0, 0, 0x0000_0377_ae00_0000]),
+    };
+}
+
+impl Network {
+    /// All known networks. Networks sharing an encoding prefix are listed in order of precedence
+    /// when decoding.
+    pub const ALL: [Network; 4] = [
+        Network::Mainnet,
+        Network::Testnet,
+        Network::Regtest,
+        Network::Signet,
+    ];
+
+    /// Returns the parameters of the network.
+    pub fn params(&self) -> &'static NetworkParams {
+        match self {
+            Network::Mainnet => &NetworkParams::MAINNET,
+            Network::Testnet => &NetworkParams::TESTNET,
+            Network::Regtest => &NetworkParams::REGTEST,
+            Network::Signet => &NetworkParams::SIGNET,
+        }
+    }
+
+    /// Finds the first network whose parameters satisfy the predicate.
+    pub(crate) fn find<F>(predicate: F) -> Option<Network>
+    where
+        F: Fn(&NetworkParams) -> bool,
+    {
+        Network::ALL
+            .iter()
+            .copied()
+            .find(|network| predicate(network.params()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use light_bitcoin_primitives::{h256_rev, Compact};
+
+    use super::*;
+
+    #[test]
+    fn test_genesis_hash() {
+        assert_eq!(
+            Network::Mainnet.params().genesis_hash,
+            h256_rev("000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f")
+        );
+        assert_eq!(
+            Network::Testnet.params().genesis_hash,
+            h256_rev("000000000933ea01ad0ee984209779baaec3ced90fa3f408719526f8d77f4943")
+        );
+        assert_eq!(
+            Network::Regtest.params().genesis_hash,
+            h256_rev("0f9188f13cb7b2c71f2a335e3a4fc328bf5beb436012afca590b1a11466e2206")
+        );
+        assert_eq!(
+            Network::Signet.params().genesis_hash,
+            h256_rev("00000008819873e925422c1ff0f99f7cc9bbb232af63a077a480a3633bee1ef6")
+        );
+    }
+
+    #[test]
+    fn test_pow_limit() {
+        assert_eq!(
+            Compact::from_u256(Network::Mainnet.params().pow_limit),
+            Compact::new(0x1d00_ffff)
+        );
+        assert_eq!(
+            Compact::from_u256(Network::Regtest.params().pow_limit),
+            Compact::new(0x207f_ffff)
+        );
+        assert_eq!(
+            Compact::from_u256(Network::Signet.params().pow_limit),
+            Compact::new(0x1e03_77ae)
+        );
+    }
+}
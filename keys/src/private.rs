@@ -5,12 +5,14 @@ use alloc::{vec, vec::Vec};
 use core::{fmt, str};
 
 use light_bitcoin_crypto::checksum;
-use light_bitcoin_primitives::H520;
+use light_bitcoin_primitives::{H256, H512, H520};
 
 use crate::address::Network;
 use crate::display::DisplayLayout;
 use crate::error::Error;
-use crate::signature::{CompactSignature, Signature};
+use crate::public::Public;
+use crate::schnorr;
+use crate::signature::{CompactSignature, SchnorrSignature, Signature};
 use crate::{Message, Secret};
 
 /// Secret with additional network identifier and format type
@@ -65,6 +67,42 @@ impl Private {
         compact_signature[1..65].copy_from_slice(&data);
         Ok(H520::from(compact_signature).into())
     }
+
+    /// Creates a BIP340 signature with all-zero auxiliary randomness.
+    pub fn sign_schnorr(&self, message: &Message) -> Result<SchnorrSignature, Error> {
+        self.sign_schnorr_with_aux_rand(message, &H256::zero())
+    }
+
+    /// Creates a BIP340 signature using the given auxiliary randomness.
+    pub fn sign_schnorr_with_aux_rand(
+        &self,
+        message: &Message,
+        aux_rand: &H256,
+    ) -> Result<SchnorrSignature, Error> {
+        let signature = schnorr::sign(
+            self.secret.as_fixed_bytes(),
+            message.as_fixed_bytes(),
+            aux_rand.as_fixed_bytes(),
+        )?;
+        Ok(H512::from(signature).into())
+    }
+
+    /// Returns the x-only public key (BIP340) of this secret.
+    pub fn x_only_public(&self) -> Result<Public, Error> {
+        let x = schnorr::x_only_public(self.secret.as_fixed_bytes())?;
+        Ok(Public::XOnly(H256::from(x)))
+    }
+
+    /// Tweaks the secret so that it signs for `Public::tweak_add_x_only` of its x-only public key.
+    pub fn tweak_add_x_only(&self, tweak: &H256) -> Result<Private, Error> {
+        let secret =
+            schnorr::tweak_add_secret(self.secret.as_fixed_bytes(), tweak.as_fixed_bytes())?;
+        Ok(Private {
+            network: self.network,
+            secret: H256::from(secret),
+            compressed: true,
+        })
+    }
 }
 
 impl DisplayLayout for Private {
@@ -72,12 +110,7 @@ impl DisplayLayout for Private {
 
     fn layout(&self) -> Self::Target {
         let mut result = vec![];
-        let network_byte = match self.network {
-            Network::Mainnet => 128,
-            Network::Testnet => 239,
-        };
-
-        result.push(network_byte);
+        result.push(self.network.params().wif_prefix);
         result.extend(self.secret.as_bytes());
         if self.compressed {
             result.push(1);
@@ -106,11 +139,8 @@ impl DisplayLayout for Private {
             return Err(Error::InvalidChecksum);
         }
 
-        let network = match data[0] {
-            128 => Network::Mainnet,
-            239 => Network::Testnet,
-            _ => return Err(Error::InvalidPrivate),
-        };
+        let network =
+            Network::find(|params| params.wif_prefix == data[0]).ok_or(Error::InvalidPrivate)?;
 
         let secret = Secret::from_slice(&data[1..33]);
 
@@ -124,7 +154,7 @@ impl DisplayLayout for Private {
 
 #[cfg(test)]
 mod tests {
-    use light_bitcoin_primitives::h256_rev;
+    use light_bitcoin_primitives::{h256, h256_rev};
 
     use super::*;
 
@@ -157,4 +187,80 @@ mod tests {
                 .unwrap()
         );
     }
+
+    #[test]
+    fn test_sign_schnorr_bip340_vectors() {
+        // https://github.com/bitcoin/bips/blob/master/bip-0340/test-vectors.csv
+        let private = Private {
+            network: Network::Mainnet,
+            secret: h256("0000000000000000000000000000000000000000000000000000000000000003"),
+            compressed: true,
+        };
+        let public = private.x_only_public().unwrap();
+        assert_eq!(
+            public,
+            Public::XOnly(h256(
+                "f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9"
+            ))
+        );
+        let message = H256::zero();
+        let signature = private.sign_schnorr(&message).unwrap();
+        assert_eq!(
+            signature,
+            "e907831f80848d1069a5371b402410364bdf1c5f8307b0084c55f1ce2dca821525f66a4a85ea8b71e482a74f382d2ce5ebeee8fdb2172f477df4900d310536c0"
+                .parse()
+                .unwrap()
+        );
+        assert_eq!(public.verify_schnorr(&message, &signature), Ok(true));
+
+        let private = Private {
+            network: Network::Mainnet,
+            secret: h256("b7e151628aed2a6abf7158809cf4f3c762e7160f38b4da56a784d9045190cfef"),
+            compressed: true,
+        };
+        let public = private.x_only_public().unwrap();
+        assert_eq!(
+            public,
+            Public::XOnly(h256(
+                "dff1d77f2a671c5f36183726db2341be58feae1da2deced843240f7b502ba659"
+            ))
+        );
+        let message = h256("243f6a8885a308d313198a2e03707344a4093822299f31d0082efa98ec4e6c89");
+        let aux_rand = h256("0000000000000000000000000000000000000000000000000000000000000001");
+        let signature = private
+            .sign_schnorr_with_aux_rand(&message, &aux_rand)
+            .unwrap();
+        assert_eq!(
+            signature,
+            "6896bd60eeae296db48a229ff71dfe071bde413e6d43f917dc8dcf8c78de33418906d11ac976abccb20b091292bff4ea897efcb639ea871cfa95f6de339e4b0a"
+                .parse()
+                .unwrap()
+        );
+        assert_eq!(public.verify_schnorr(&message, &signature), Ok(true));
+        assert_eq!(public.verify_schnorr(&H256::zero(), &signature), Ok(false));
+    }
+
+    #[test]
+    fn test_tweak_add_x_only() {
+        let private = Private {
+            network: Network::Mainnet,
+            secret: h256("b7e151628aed2a6abf7158809cf4f3c762e7160f38b4da56a784d9045190cfef"),
+            compressed: true,
+        };
+        let tweak = h256("243f6a8885a308d313198a2e03707344a4093822299f31d0082efa98ec4e6c89");
+        let (tweaked_public, _) = private
+            .x_only_public()
+            .unwrap()
+            .tweak_add_x_only(&tweak)
+            .unwrap();
+        let tweaked_private = private.tweak_add_x_only(&tweak).unwrap();
+        assert_eq!(tweaked_private.x_only_public(), Ok(tweaked_public));
+
+        let message = h256("0000000000000000000000000000000000000000000000000000000000000001");
+        let signature = tweaked_private.sign_schnorr(&message).unwrap();
+        assert_eq!(
+            tweaked_public.verify_schnorr(&message, &signature),
+            Ok(true)
+        );
+    }
 }
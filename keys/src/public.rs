@@ -1,14 +1,15 @@
 use core::{fmt, ops};
 
 use light_bitcoin_crypto::dhash160;
-use light_bitcoin_primitives::{H264, H512, H520};
+use light_bitcoin_primitives::{H256, H264, H512, H520};
 
 use codec::{Decode, Encode};
 #[cfg(feature = "std")]
 use serde::{Deserialize, Serialize};
 
 use crate::error::Error;
-use crate::signature::{CompactSignature, Signature};
+use crate::schnorr;
+use crate::signature::{CompactSignature, SchnorrSignature, Signature};
 use crate::{AddressHash, Message};
 
 /// Secret public key
@@ -21,6 +22,8 @@ pub enum Public {
     Normal(H520),
     /// Compressed version of public key
     Compressed(H264),
+    /// X-only version of public key (BIP340)
+    XOnly(H256),
 }
 
 impl fmt::Debug for Public {
@@ -28,6 +31,7 @@ impl fmt::Debug for Public {
         match self {
             Public::Normal(hash) => write!(f, "{:?}", hash),
             Public::Compressed(hash) => write!(f, "{:?}", hash),
+            Public::XOnly(hash) => write!(f, "{:?}", hash),
         }
     }
 }
@@ -37,6 +41,7 @@ impl fmt::Display for Public {
         match self {
             Public::Normal(hash) => write!(f, "{}", hash),
             Public::Compressed(hash) => write!(f, "{}", hash),
+            Public::XOnly(hash) => write!(f, "{}", hash),
         }
     }
 }
@@ -48,6 +53,7 @@ impl ops::Deref for Public {
         match self {
             Public::Normal(hash) => hash.as_bytes(),
             Public::Compressed(hash) => hash.as_bytes(),
+            Public::XOnly(hash) => hash.as_bytes(),
         }
     }
 }
@@ -59,6 +65,7 @@ impl Default for Public {
 }
 
 impl Public {
+    /// Creates a compressed or uncompressed public key from its SEC serialization.
     pub fn from_slice(data: &[u8]) -> Result<Self, Error> {
         match data.len() {
             33 => Ok(Public::Compressed(H264::from_slice(data))),
@@ -67,6 +74,14 @@ impl Public {
         }
     }
 
+    /// Creates an x-only public key (BIP340) from its 32 bytes.
+    pub fn from_x_only(data: &[u8]) -> Result<Self, Error> {
+        match data.len() {
+            32 => Ok(Public::XOnly(H256::from_slice(data))),
+            _ => Err(Error::InvalidPublic),
+        }
+    }
+
     pub fn address_hash(&self) -> AddressHash {
         dhash160(self)
     }
@@ -77,6 +92,7 @@ impl Public {
             Public::Compressed(pubkey) => {
                 secp256k1::PublicKey::parse_compressed(pubkey.as_fixed_bytes())?
             }
+            Public::XOnly(_) => return Err(Error::InvalidPublic),
         };
         let mut signature = secp256k1::Signature::parse_der_lax(&**signature)?;
         signature.normalize_s();
@@ -90,12 +106,46 @@ impl Public {
             Public::Compressed(pubkey) => {
                 secp256k1::PublicKey::parse_compressed(pubkey.as_fixed_bytes())?
             }
+            Public::XOnly(_) => return Err(Error::InvalidPublic),
         };
         let signature = secp256k1::Signature::parse(signature);
         let message = secp256k1::Message::parse(message.as_fixed_bytes());
         Ok(secp256k1::verify(&message, &signature, &public))
     }
 
+    /// Returns the x coordinate of the public key.
+    pub fn x_only(&self) -> H256 {
+        match self {
+            Public::Normal(pubkey) => H256::from_slice(&pubkey[1..33]),
+            Public::Compressed(pubkey) => H256::from_slice(&pubkey[1..33]),
+            Public::XOnly(pubkey) => *pubkey,
+        }
+    }
+
+    /// Verifies a BIP340 signature using the x coordinate of the public key.
+    pub fn verify_schnorr(
+        &self,
+        message: &Message,
+        signature: &SchnorrSignature,
+    ) -> Result<bool, Error> {
+        let mut sig = [0u8; 64];
+        sig.copy_from_slice(signature);
+        Ok(schnorr::verify(
+            self.x_only().as_fixed_bytes(),
+            message.as_fixed_bytes(),
+            &sig,
+        ))
+    }
+
+    /// Tweaks the x-only public key `P` as `P + tweak * G` (BIP341).
+    ///
+    /// Returns the tweaked x-only public key and whether its y coordinate is odd.
+    pub fn tweak_add_x_only(&self, tweak: &H256) -> Result<(Public, bool), Error> {
+        let (x, odd) =
+            schnorr::tweak_add_public(self.x_only().as_fixed_bytes(), tweak.as_fixed_bytes())?;
+        Ok((Public::XOnly(H256::from(x)), odd))
+    }
+
     pub fn recover_compact(message: &Message, signature: &CompactSignature) -> Result<Self, Error> {
         let recovery_id = (signature[0] - 27) & 3;
         let compressed = (signature[0] - 27) & 4 != 0;
@@ -131,3 +181,34 @@ fn test_serde_public() {
     let de = serde_json::from_str::<Test>(&ser).unwrap();
     assert_eq!(de, pubkey);
 }
+
+#[test]
+fn test_verify_schnorr_public_not_on_curve() {
+    // BIP340 test vector 5
+    let public = Public::from_x_only(
+        &hex::decode("eefdea4cdb677750a420fee807eacf21eb9898ae79b9768766e4faa04a2d4a34").unwrap(),
+    )
+    .unwrap();
+    let message = light_bitcoin_primitives::h256(
+        "243f6a8885a308d313198a2e03707344a4093822299f31d0082efa98ec4e6c89",
+    );
+    let signature = "6cff5c3ba86c69ea4b7376f31a9bcb4f74c1976089b2d9963da2e5543e17776969e89b4c5564d00349106b8497785dd7d1d713a8ae82b32fa79d5f7fc407d39b"
+        .parse()
+        .unwrap();
+    assert_eq!(public.verify_schnorr(&message, &signature), Ok(false));
+}
+
+#[test]
+fn test_public_from_slice() {
+    let x_only = [2u8; 32];
+    assert_eq!(Public::from_slice(&x_only), Err(Error::InvalidPublic));
+    assert_eq!(
+        Public::from_x_only(&x_only),
+        Ok(Public::XOnly(H256::from(x_only)))
+    );
+    assert_eq!(Public::from_x_only(&[2u8; 33]), Err(Error::InvalidPublic));
+    assert_eq!(
+        Public::from_slice(&[2u8; 33]),
+        Ok(Public::Compressed(H264::from([2u8; 33])))
+    );
+}
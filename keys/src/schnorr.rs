@@ -0,0 +1,181 @@
+//! BIP340 Schnorr signatures and x-only key tweaking on top of the secp256k1 curve primitives.
+
+use light_bitcoin_crypto::tagged_hash;
+use secp256k1::curve::{Affine, Field, Jacobian, Scalar, ECMULT_CONTEXT, ECMULT_GEN_CONTEXT};
+
+use crate::error::Error;
+
+/// Lifts an x coordinate to the curve point with an even y coordinate.
+fn lift_x(x: &[u8; 32]) -> Option<Affine> {
+    let mut fx = Field::default();
+    if !fx.set_b32(x) {
+        return None;
+    }
+    let mut point = Affine::default();
+    if !point.set_xo_var(&fx, false) {
+        return None;
+    }
+    Some(point)
+}
+
+fn x_bytes(point: &Affine) -> [u8; 32] {
+    let mut x = point.x.clone();
+    x.normalize();
+    x.b32()
+}
+
+fn has_even_y(point: &Affine) -> bool {
+    let mut y = point.y.clone();
+    y.normalize();
+    !y.is_odd()
+}
+
+/// Parses a scalar, failing on values not lower than the curve order.
+fn parse_scalar(bytes: &[u8; 32]) -> Option<Scalar> {
+    let mut scalar = Scalar::default();
+    if bool::from(scalar.set_b32(bytes)) {
+        return None;
+    }
+    Some(scalar)
+}
+
+/// Parses a scalar reducing it modulo the curve order.
+fn reduce_scalar(bytes: &[u8; 32]) -> Scalar {
+    let mut scalar = Scalar::default();
+    let _ = scalar.set_b32(bytes);
+    scalar
+}
+
+fn mul_generator(scalar: &Scalar) -> Affine {
+    let mut point = Jacobian::default();
+    ECMULT_GEN_CONTEXT.ecmult_gen(&mut point, scalar);
+    Affine::from_gej(&point)
+}
+
+fn challenge(r: &[u8], public: &[u8], message: &[u8]) -> Scalar {
+    let mut data = [0u8; 96];
+    data[..32].copy_from_slice(r);
+    data[32..64].copy_from_slice(public);
+    data[64..].copy_from_slice(message);
+    reduce_scalar(tagged_hash(b"BIP0340/challenge", &data).as_fixed_bytes())
+}
+
+/// Parses the secret key and negates it if its public key has an odd y coordinate.
+fn even_y_secret(secret: &[u8; 32]) -> Result<(Scalar, Affine), Error> {
+    let secret = parse_scalar(secret).ok_or(Error::InvalidSecret)?;
+    if secret.is_zero() {
+        return Err(Error::InvalidSecret);
+    }
+    let public = mul_generator(&secret);
+    if has_even_y(&public) {
+        Ok((secret, public))
+    } else {
+        Ok((-secret, public))
+    }
+}
+
+/// Creates a BIP340 signature of the 32-byte message.
+pub fn sign(secret: &[u8; 32], message: &[u8; 32], aux_rand: &[u8; 32]) -> Result<[u8; 64], Error> {
+    let (secret, public) = even_y_secret(secret)?;
+    let public = x_bytes(&public);
+
+    let aux_hash = tagged_hash(b"BIP0340/aux", aux_rand);
+    let mut data = [0u8; 96];
+    for (t, (d, a)) in data[..32]
+        .iter_mut()
+        .zip(secret.b32().iter().zip(aux_hash.as_bytes()))
+    {
+        *t = d ^ a;
+    }
+    data[32..64].copy_from_slice(&public);
+    data[64..].copy_from_slice(message);
+
+    let nonce = reduce_scalar(tagged_hash(b"BIP0340/nonce", &data).as_fixed_bytes());
+    if nonce.is_zero() {
+        return Err(Error::InvalidSignature);
+    }
+    let r = mul_generator(&nonce);
+    let nonce = if has_even_y(&r) { nonce } else { -nonce };
+    let r = x_bytes(&r);
+
+    let e = challenge(&r, &public, message);
+    let s = nonce + e * secret;
+
+    let mut signature = [0u8; 64];
+    signature[..32].copy_from_slice(&r);
+    signature[32..].copy_from_slice(&s.b32());
+    Ok(signature)
+}
+
+/// Verifies a BIP340 signature of the 32-byte message against the x-only public key.
+pub fn verify(public: &[u8; 32], message: &[u8; 32], signature: &[u8; 64]) -> bool {
+    let point = match lift_x(public) {
+        Some(point) => point,
+        None => return false,
+    };
+
+    let mut r_bytes = [0u8; 32];
+    r_bytes.copy_from_slice(&signature[..32]);
+    let mut r = Field::default();
+    if !r.set_b32(&r_bytes) {
+        return false;
+    }
+
+    let mut s_bytes = [0u8; 32];
+    s_bytes.copy_from_slice(&signature[32..]);
+    let s = match parse_scalar(&s_bytes) {
+        Some(s) => s,
+        None => return false,
+    };
+
+    let e = challenge(&r_bytes, public, message);
+
+    // R = s * G - e * P
+    let mut rj = Jacobian::default();
+    ECMULT_CONTEXT.ecmult(&mut rj, &Jacobian::from_ge(&point), &-e, &s);
+    if rj.is_infinity() {
+        return false;
+    }
+
+    let r_point = Affine::from_gej(&rj);
+    has_even_y(&r_point) && x_bytes(&r_point) == r_bytes
+}
+
+/// Computes `P + tweak * G` for the x-only public key `P`.
+///
+/// Returns the x-only result together with the parity of its y coordinate (`true` if odd).
+pub fn tweak_add_public(public: &[u8; 32], tweak: &[u8; 32]) -> Result<([u8; 32], bool), Error> {
+    let point = lift_x(public).ok_or(Error::InvalidPublic)?;
+    let tweak = parse_scalar(tweak).ok_or(Error::InvalidTweak)?;
+
+    let mut qj = Jacobian::default();
+    ECMULT_CONTEXT.ecmult(
+        &mut qj,
+        &Jacobian::from_ge(&point),
+        &Scalar::from_int(1),
+        &tweak,
+    );
+    if qj.is_infinity() {
+        return Err(Error::InvalidTweak);
+    }
+
+    let q = Affine::from_gej(&qj);
+    Ok((x_bytes(&q), !has_even_y(&q)))
+}
+
+/// Tweaks the secret key so that it matches `tweak_add_public` applied to its x-only public key.
+pub fn tweak_add_secret(secret: &[u8; 32], tweak: &[u8; 32]) -> Result<[u8; 32], Error> {
+    let (secret, _) = even_y_secret(secret)?;
+    let tweak = parse_scalar(tweak).ok_or(Error::InvalidTweak)?;
+    let tweaked = secret + tweak;
+    if tweaked.is_zero() {
+        return Err(Error::InvalidTweak);
+    }
+    Ok(tweaked.b32())
+}
+
+/// Returns the x-only public key of the secret key.
+pub fn x_only_public(secret: &[u8; 32]) -> Result<[u8; 32], Error> {
+    let (_, public) = even_y_secret(secret)?;
+    Ok(x_bytes(&public))
+}
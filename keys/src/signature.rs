@@ -6,7 +6,7 @@
 use alloc::vec::Vec;
 use core::{fmt, ops, str};
 
-use light_bitcoin_primitives::H520;
+use light_bitcoin_primitives::{H512, H520};
 
 use crate::error::Error;
 
@@ -60,8 +60,12 @@ impl From<Signature> for Vec<u8> {
 }
 
 impl Signature {
+    /// Checks that the (lax DER encoded) signature has an `S` value in the lower half of the curve order.
     pub fn check_low_s(&self) -> bool {
-        unimplemented!();
+        match secp256k1::Signature::parse_der_lax(&self.0) {
+            Ok(signature) => !signature.s.is_high(),
+            Err(_) => false,
+        }
     }
 }
 
@@ -126,3 +130,53 @@ impl From<CompactSignature> for H520 {
         s.0
     }
 }
+
+/// BIP340 Schnorr signature (64 bytes)
+#[derive(Ord, PartialOrd, Eq, PartialEq, Copy, Clone, Default)]
+pub struct SchnorrSignature(H512);
+
+impl fmt::Debug for SchnorrSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self.0)
+    }
+}
+
+impl fmt::Display for SchnorrSignature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl ops::Deref for SchnorrSignature {
+    type Target = [u8];
+
+    fn deref(&self) -> &Self::Target {
+        self.0.as_bytes()
+    }
+}
+
+// mainly use for test
+impl str::FromStr for SchnorrSignature {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let s = s.strip_prefix("0x").unwrap_or(s);
+        if s.len() != H512::len_bytes() * 2 {
+            return Err(Error::InvalidSignature);
+        }
+        let bytes = hex::decode(s).map_err(|_| Error::InvalidSignature)?;
+        Ok(SchnorrSignature(H512::from_slice(&bytes)))
+    }
+}
+
+impl From<H512> for SchnorrSignature {
+    fn from(h: H512) -> Self {
+        SchnorrSignature(h)
+    }
+}
+
+impl From<SchnorrSignature> for H512 {
+    fn from(s: SchnorrSignature) -> Self {
+        s.0
+    }
+}
@@ -9,7 +9,8 @@ extern crate alloc;
 use alloc::{string::String, vec, vec::Vec};
 use core::fmt;
 
-use light_bitcoin_chain::merkle_node_hash;
+use codec::{Decode, Encode};
+use light_bitcoin_chain::{merkle_node_hash, Block, BlockHeader, Transaction};
 use light_bitcoin_primitives::{hash_rev, io, H256};
 use light_bitcoin_serialization::{
     deserialize, serialize, Deserializable, Reader, Serializable, Stream,
@@ -28,6 +29,8 @@ pub enum Error {
     NoTransactions,
     /// When there are too many transactions
     TooManyTransactions,
+    /// When the transaction index is out of the transactions range
+    IndexOutOfRange,
     /// General format error
     BadFormat(String),
 }
@@ -41,6 +44,7 @@ impl core::fmt::Display for Error {
             Error::MerkleRootMismatch => f.write_str("header merkle root don't match to the root calculated from the partial merkle tree"),
             Error::NoTransactions => f.write_str("partial merkle tree contains no transactions"),
             Error::TooManyTransactions => f.write_str("there are too many transactions"),
+            Error::IndexOutOfRange => f.write_str("transaction index is out of range"),
             Error::BadFormat(err) => f.write_str(err)
         }
     }
@@ -365,39 +369,240 @@ impl codec::Decode for PartialMerkleTree {
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use hashbrown::HashSet;
+/// Data structure that represents a block header paired to a partial merkle tree.
+///
+/// It is the payload of the `merkleblock` message and the proof returned by the
+/// `gettxoutproof` rpc call.
+///
+/// The serialization format:
+///  - block header (80 bytes)
+///  - partial merkle tree
+#[derive(PartialEq, Eq, Clone, Debug, Default, Serializable, Deserializable)]
+pub struct MerkleBlock {
+    /// The block header
+    pub header: BlockHeader,
+    /// Transactions making up a partial merkle tree
+    pub txn: PartialMerkleTree,
+}
 
-    use light_bitcoin_chain::{merkle_root, Block, BlockHeader};
-    use light_bitcoin_primitives::{h256_rev, H256};
-    use light_bitcoin_serialization::{deserialize, serialize, Deserializable, Serializable};
-    use rand::prelude::*;
+impl MerkleBlock {
+    /// Create a MerkleBlock from a block, that contains proofs for all txids
+    /// for which the `match_txid` function returns true.
+    ///
+    /// Returns an error when the block has no transactions
+    pub fn from_block<F>(block: &Block, match_txid: F) -> Result<Self, Error>
+    where
+        F: Fn(&H256) -> bool,
+    {
+        let txids = block
+            .transactions
+            .iter()
+            .map(|tx| tx.hash())
+            .collect::<Vec<_>>();
+        Self::from_header_txids(&block.header, &txids, match_txid)
+    }
 
-    use super::PartialMerkleTree;
+    /// Create a MerkleBlock from the block header and the txids of the block,
+    /// that contains proofs for all txids for which the `match_txid` function returns true.
+    ///
+    /// Returns an error when `txids` is empty
+    pub fn from_header_txids<F>(
+        header: &BlockHeader,
+        txids: &[H256],
+        match_txid: F,
+    ) -> Result<Self, Error>
+    where
+        F: Fn(&H256) -> bool,
+    {
+        if txids.is_empty() {
+            return Err(Error::NoTransactions);
+        }
+        let matches = txids.iter().map(match_txid).collect::<Vec<_>>();
+        let txn = PartialMerkleTree::from_txids(txids, &matches);
+        Ok(MerkleBlock {
+            header: *header,
+            txn,
+        })
+    }
 
-    #[derive(PartialEq, Eq, Clone, Debug, Serializable, Deserializable)]
-    struct MerkleBlock {
-        header: BlockHeader,
-        pmt: PartialMerkleTree,
+    /// Extract the matching txid's represented by this partial merkle tree
+    /// and their respective indices within the partial tree,
+    /// checking that the merkle root matches the one of the header.
+    pub fn extract_matches(
+        &self,
+        matches: &mut Vec<H256>,
+        indexes: &mut Vec<u32>,
+    ) -> Result<(), Error> {
+        let merkle_root = self.txn.extract_matches(matches, indexes)?;
+        if merkle_root != self.header.merkle_root_hash {
+            return Err(Error::MerkleRootMismatch);
+        }
+        Ok(())
     }
+}
 
-    impl MerkleBlock {
-        fn from_block(block: &Block, match_txids: &HashSet<H256>) -> Self {
-            let header = block.header;
+impl codec::Encode for MerkleBlock {
+    fn encode(&self) -> Vec<u8> {
+        let value = serialize::<MerkleBlock>(self);
+        value.encode()
+    }
+}
+
+impl codec::EncodeLike for MerkleBlock {}
+
+impl codec::Decode for MerkleBlock {
+    fn decode<I: codec::Input>(value: &mut I) -> Result<Self, codec::Error> {
+        let value: Vec<u8> = codec::Decode::decode(value)?;
+        deserialize(Reader::new(&value)).map_err(|_| "deserialize MerkleBlock error".into())
+    }
+}
 
-            let mut matches = Vec::with_capacity(block.transactions.len());
-            let mut hashes = Vec::with_capacity(block.transactions.len());
+/// Data structure that represents the merkle branch of a single transaction.
+///
+/// It holds the hashes of the siblings of the transaction and of its ancestors, from the leaf
+/// level up, so that the merkle root can be recomputed from the txid. The bits of `index` tell
+/// at each height whether the node is a right child. Nodes without sibling, at the end of odd
+/// width levels, are hashed with themselves and have no stored hash.
+///
+/// A branch of a single transaction is much smaller than a partial merkle tree, its SCALE
+/// encoding is:
+///  - compact    total_transactions (1-5 bytes)
+///  - compact    index              (1-5 bytes)
+///  - compact    number of hashes   (1 byte)
+///  - uint256[]  sibling hashes from the leaf level up (<= 32*tree_height bytes)
+///
+/// The number of transactions is not authenticated by the merkle root; it has to match the
+/// block (for instance with the branch of the coinbase transaction) for the index to be
+/// meaningful.
+#[derive(PartialEq, Eq, Clone, Debug, Default, Encode, Decode)]
+pub struct MerkleBranch {
+    /// The total number of transactions in the block
+    #[codec(compact)]
+    pub tx_count: u32,
+    /// Index of the transaction in the block, the node-is-right-child bits
+    #[codec(compact)]
+    pub index: u32,
+    /// Sibling hashes
+    pub hashes: Vec<H256>,
+}
 
-            for hash in block.transactions.iter().map(|tx| tx.hash()) {
-                matches.push(match_txids.contains(&hash));
-                hashes.push(hash);
+impl MerkleBranch {
+    /// Construct the merkle branch of the transaction at `index` in the block.
+    ///
+    /// Returns an error when `index` is out of the `txids` range
+    pub fn from_txids(txids: &[H256], index: u32) -> Result<Self, Error> {
+        if index as usize >= txids.len() {
+            return Err(Error::IndexOutOfRange);
+        }
+
+        let mut hashes = vec![];
+        let mut level = txids.to_vec();
+        let mut pos = index as usize;
+        while level.len() > 1 {
+            if let Some(sibling) = level.get(pos ^ 1) {
+                hashes.push(*sibling);
             }
-            let pmt = PartialMerkleTree::from_txids(&hashes, &matches);
-            MerkleBlock { header, pmt }
+            level = level
+                .chunks(2)
+                .map(|pair| merkle_node_hash(&pair[0], pair.get(1).unwrap_or(&pair[0])))
+                .collect();
+            pos >>= 1;
         }
+        Ok(MerkleBranch {
+            tx_count: txids.len() as u32,
+            index,
+            hashes,
+        })
+    }
+
+    /// Construct the merkle branch of the transaction at `index` in the block.
+    ///
+    /// Returns an error when `index` is out of the transactions range
+    pub fn from_block(block: &Block, index: u32) -> Result<Self, Error> {
+        let txids = block
+            .transactions
+            .iter()
+            .map(|tx| tx.hash())
+            .collect::<Vec<_>>();
+        Self::from_txids(&txids, index)
     }
 
+    /// Compute the merkle root of the branch for the txid.
+    ///
+    /// Returns an error if the branch doesn't match the shape of a tree of `tx_count`
+    /// transactions, or if it hashes two identical siblings together, which only happens
+    /// with duplicated transactions (CVE-2012-2459).
+    pub fn merkle_root(&self, txid: &H256) -> Result<H256, Error> {
+        if self.tx_count == 0 {
+            return Err(Error::NoTransactions);
+        }
+        if self.tx_count > MAX_BLOCK_WEIGHT / MIN_TRANSACTION_WEIGHT {
+            return Err(Error::TooManyTransactions);
+        }
+        if self.index >= self.tx_count {
+            return Err(Error::IndexOutOfRange);
+        }
+
+        let mut hashes = self.hashes.iter();
+        let mut hash = *txid;
+        let mut pos = self.index;
+        let mut width = self.tx_count;
+        while width > 1 {
+            hash = if pos ^ 1 < width {
+                let sibling = hashes
+                    .next()
+                    .ok_or("Branch contains less hashes than the tree height")?;
+                if *sibling == hash {
+                    return Err("Found identical transaction hashes".into());
+                }
+                if pos & 1 == 0 {
+                    merkle_node_hash(&hash, sibling)
+                } else {
+                    merkle_node_hash(sibling, &hash)
+                }
+            } else {
+                merkle_node_hash(&hash, &hash)
+            };
+            pos >>= 1;
+            width = (width + 1) >> 1;
+        }
+        if hashes.next().is_some() {
+            return Err("Branch contains more hashes than the tree height".into());
+        }
+        Ok(hash)
+    }
+
+    /// Verify that the branch proves the inclusion of the txid under the merkle root.
+    pub fn verify(&self, txid: &H256, merkle_root: &H256) -> Result<(), Error> {
+        if self.merkle_root(txid)? != *merkle_root {
+            return Err(Error::MerkleRootMismatch);
+        }
+        Ok(())
+    }
+
+    /// Verify that the branch proves the inclusion of the transaction under the merkle root.
+    ///
+    /// 64 bytes transactions are rejected, their serialization could also be the two children
+    /// hashes of an inner node of the tree.
+    pub fn verify_transaction(&self, tx: &Transaction, merkle_root: &H256) -> Result<(), Error> {
+        if serialize(tx).len() == 64 {
+            return Err("Transaction of 64 bytes is ambiguous with an inner node".into());
+        }
+        self.verify(&tx.hash(), merkle_root)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hashbrown::HashSet;
+
+    use light_bitcoin_chain::{merkle_root, Block, Transaction};
+    use light_bitcoin_primitives::{h256_rev, H256};
+    use light_bitcoin_serialization::{deserialize, serialize};
+    use rand::prelude::*;
+
+    use super::{Error, MerkleBlock, MerkleBranch, PartialMerkleTree};
+
     impl PartialMerkleTree {
         /// Flip one bit in one of the hashes - this should break the authentication
         fn damage(&mut self, rng: &mut ThreadRng) {
@@ -519,8 +724,16 @@ mod tests {
         let mb: MerkleBlock = deserialize(data.as_slice()).unwrap();
         assert_eq!(get_block_13b8a().hash(), mb.header.hash());
 
-        let merkle_root = mb.pmt.extract_matches(&mut vec![], &mut vec![]).unwrap();
-        assert_eq!(merkle_root, mb.header.merkle_root_hash);
+        let mut matches = vec![];
+        let mut indexes = vec![];
+        mb.extract_matches(&mut matches, &mut indexes).unwrap();
+        assert_eq!(
+            matches,
+            vec![h256_rev(
+                "220ebc64e21abece964927322cba69180ed853bb187fbc6923bac7d010b9d87a"
+            )]
+        );
+        assert_eq!(indexes, vec![3]);
 
         // Serialize again and check that it matches the original bytes
         assert_eq!(data, *serialize(&mb));
@@ -533,19 +746,17 @@ mod tests {
 
         let txid1 = h256_rev("74d681e0e03bafa802c8aa084379aa98d9fcd632ddc2ed9782b586ec87451f20");
         let txid2 = h256_rev("f9fc751cb7dc372406a9f8d738d5e6f8f63bab71986a39cf36ee70ee17036d07");
-        let txids = vec![txid1, txid2].into_iter().collect();
+        let txids: HashSet<H256> = vec![txid1, txid2].into_iter().collect();
 
-        let merkle_block = MerkleBlock::from_block(&block, &txids);
+        let merkle_block = MerkleBlock::from_block(&block, |txid| txids.contains(txid)).unwrap();
         assert_eq!(merkle_block.header.hash(), block.hash());
 
         let mut matches: Vec<H256> = vec![];
         let mut indexes: Vec<u32> = vec![];
-        let merkle_root = merkle_block
-            .pmt
+        merkle_block
             .extract_matches(&mut matches, &mut indexes)
             .unwrap();
 
-        assert_eq!(merkle_root, block.header.merkle_root_hash);
         assert_eq!(matches.len(), 2);
 
         // Ordered by occurrence in depth-first tree traversal.
@@ -559,28 +770,157 @@ mod tests {
     #[test]
     fn merkle_block_construct_from_txids_not_found() {
         let block = get_block_13b8a();
-        let txids = vec![h256_rev(
+        let txids: HashSet<H256> = vec![h256_rev(
             "c0ffee00003bafa802c8aa084379aa98d9fcd632ddc2ed9782b586ec87451f20",
         )]
         .into_iter()
         .collect();
 
-        let merkle_block = MerkleBlock::from_block(&block, &txids);
+        let merkle_block = MerkleBlock::from_block(&block, |txid| txids.contains(txid)).unwrap();
 
         assert_eq!(merkle_block.header.hash(), block.hash());
 
         let mut matches: Vec<H256> = vec![];
         let mut indexes: Vec<u32> = vec![];
-        let merkle_root = merkle_block
-            .pmt
+        merkle_block
             .extract_matches(&mut matches, &mut indexes)
             .unwrap();
 
-        assert_eq!(merkle_root, block.header.merkle_root_hash);
         assert_eq!(matches.len(), 0);
         assert_eq!(indexes.len(), 0);
     }
 
+    /// A MerkleBlock can't be created without transactions
+    #[test]
+    fn merkle_block_construct_from_no_txids() {
+        let block = get_block_13b8a();
+        let result = MerkleBlock::from_header_txids(&block.header, &[], |_| true);
+        assert!(matches!(result, Err(Error::NoTransactions)));
+    }
+
+    /// A MerkleBlock whose tree doesn't commit to the merkle root of the header is rejected
+    #[test]
+    fn merkle_block_merkle_root_mismatch() {
+        let block = get_block_13b8a();
+        let txid = h256_rev("74d681e0e03bafa802c8aa084379aa98d9fcd632ddc2ed9782b586ec87451f20");
+
+        let mut merkle_block = MerkleBlock::from_block(&block, |hash| *hash == txid).unwrap();
+        merkle_block.header.merkle_root_hash = H256::repeat_byte(1);
+        let result = merkle_block.extract_matches(&mut vec![], &mut vec![]);
+        assert!(matches!(result, Err(Error::MerkleRootMismatch)));
+
+        // The codec encoding wraps the wire serialization
+        let merkle_block = MerkleBlock::from_block(&block, |hash| *hash == txid).unwrap();
+        let encoded = codec::Encode::encode(&merkle_block);
+        let decoded: MerkleBlock = codec::Decode::decode(&mut encoded.as_slice()).unwrap();
+        assert_eq!(serialize(&decoded), serialize(&merkle_block));
+    }
+
+    #[test]
+    fn merkle_branch_tests() {
+        for tx_count in [1u32, 2, 3, 7, 8, 17, 100].iter() {
+            let txids = (1..=*tx_count)
+                .map(|i| h256_rev(&format!("{:064x}", i)))
+                .collect::<Vec<_>>();
+            let root = merkle_root(&txids);
+            for index in 0..*tx_count {
+                let txid = &txids[index as usize];
+                let branch = MerkleBranch::from_txids(&txids, index).unwrap();
+                assert_eq!(branch.merkle_root(txid).unwrap(), root);
+                branch.verify(txid, &root).unwrap();
+
+                // Compact SCALE encoding
+                let encoded = codec::Encode::encode(&branch);
+                assert!(encoded.len() <= 3 + 32 * branch.hashes.len() + 2);
+                let decoded: MerkleBranch = codec::Decode::decode(&mut encoded.as_slice()).unwrap();
+                assert_eq!(decoded, branch);
+
+                // Another txid or another position don't match the root
+                if *tx_count > 1 {
+                    let other = &txids[(index as usize + 1) % txids.len()];
+                    assert!(branch.verify(other, &root).is_err());
+                    let mut moved = branch.clone();
+                    moved.index ^= 1;
+                    assert!(moved.verify(txid, &root).is_err());
+                }
+            }
+        }
+
+        let block = get_block_13b8a();
+        let tx = &block.transactions[5];
+        let branch = MerkleBranch::from_block(&block, 5).unwrap();
+        assert_eq!(branch.hashes.len(), 4);
+        branch
+            .verify_transaction(tx, &block.header.merkle_root_hash)
+            .unwrap();
+        assert!(matches!(
+            MerkleBranch::from_block(&block, 9),
+            Err(Error::IndexOutOfRange)
+        ));
+    }
+
+    #[test]
+    fn merkle_branch_malformed() {
+        let txids = (1..=5)
+            .map(|i| h256_rev(&format!("{:064x}", i)))
+            .collect::<Vec<_>>();
+        let root = merkle_root(&txids);
+        let branch = MerkleBranch::from_txids(&txids, 1).unwrap();
+        assert!(matches!(
+            MerkleBranch::from_txids(&txids, 5),
+            Err(Error::IndexOutOfRange)
+        ));
+        assert!(matches!(
+            MerkleBranch::from_txids(&[], 0),
+            Err(Error::IndexOutOfRange)
+        ));
+
+        let mut out_of_range = branch.clone();
+        out_of_range.index = 5;
+        assert!(matches!(
+            out_of_range.merkle_root(&txids[1]),
+            Err(Error::IndexOutOfRange)
+        ));
+        let mut short = branch.clone();
+        short.hashes.pop();
+        assert!(short.merkle_root(&txids[1]).is_err());
+        let mut long = branch.clone();
+        long.hashes.push(root);
+        assert!(long.merkle_root(&txids[1]).is_err());
+        let empty = MerkleBranch::default();
+        assert!(matches!(
+            empty.merkle_root(&txids[0]),
+            Err(Error::NoTransactions)
+        ));
+    }
+
+    #[test]
+    fn merkle_branch_malleability() {
+        // [1, 2, 3] and [1, 2, 3, 3] have the same merkle root
+        let txids = (1..=3)
+            .map(|i| h256_rev(&format!("{:064x}", i)))
+            .collect::<Vec<_>>();
+        let root = merkle_root(&txids);
+        let mut duplicated = txids.clone();
+        duplicated.push(txids[2]);
+        assert_eq!(merkle_root(&duplicated), root);
+
+        let branch = MerkleBranch::from_txids(&duplicated, 3).unwrap();
+        assert!(branch.verify(&txids[2], &root).is_err());
+        MerkleBranch::from_txids(&txids, 2)
+            .unwrap()
+            .verify(&txids[2], &root)
+            .unwrap();
+
+        // A 64 bytes transaction could be an inner node of the tree
+        let tx: Transaction = "010000000100000000000000000000000000000000000000000000000000000000000000000000000000ffffffff010000000000000000045151515100000000"
+            .parse().unwrap();
+        assert_eq!(serialize(&tx).len(), 64);
+        let branch = MerkleBranch::from_txids(&[tx.hash()], 0).unwrap();
+        assert!(branch.verify_transaction(&tx, &tx.hash()).is_err());
+        branch.verify(&tx.hash(), &tx.hash()).unwrap();
+    }
+
     // Block 100,002 (0000000000013b8ab2cd513b0261a14096412195a72a0c4827d229dcc7e0f7af) with 9 txs.
     // https://blockchain.info/rawblock/0000000000013b8ab2cd513b0261a14096412195a72a0c4827d229dcc7e0f7af
     // https://blockchain.info/rawblock/0000000000013b8ab2cd513b0261a14096412195a72a0c4827d229dcc7e0f7af?format=hex
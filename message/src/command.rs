@@ -0,0 +1,111 @@
+//! Message command
+
+use core::{fmt, str};
+
+use light_bitcoin_primitives::io;
+use light_bitcoin_serialization::{Deserializable, Reader, Serializable, Stream};
+
+/// Size of the command in the message header.
+pub const COMMAND_SIZE: usize = 12;
+
+/// Name of a message, an ASCII string padded with zeros.
+#[derive(Eq, PartialEq, Ord, PartialOrd, Hash, Copy, Clone, Default)]
+pub struct Command([u8; COMMAND_SIZE]);
+
+impl Command {
+    /// Returns the command of the name, or `None` if the name is longer than 12 bytes or not
+    /// printable ASCII.
+    pub fn new(name: &str) -> Option<Self> {
+        if name.len() > COMMAND_SIZE || !name.bytes().all(|b| b.is_ascii_graphic()) {
+            return None;
+        }
+        let mut command = [0u8; COMMAND_SIZE];
+        command[..name.len()].copy_from_slice(name.as_bytes());
+        Some(Command(command))
+    }
+
+    /// Returns the name of the command.
+    pub fn as_str(&self) -> &str {
+        let len = self.0.iter().position(|b| *b == 0).unwrap_or(COMMAND_SIZE);
+        str::from_utf8(&self.0[..len]).expect("commands are ASCII; qed")
+    }
+}
+
+impl From<&'static str> for Command {
+    fn from(name: &'static str) -> Self {
+        Command::new(name).expect("static commands are valid")
+    }
+}
+
+impl PartialEq<str> for Command {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl fmt::Display for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl fmt::Debug for Command {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serializable for Command {
+    fn serialize(&self, stream: &mut Stream) {
+        stream.append_slice(&self.0);
+    }
+
+    #[inline]
+    fn serialized_size(&self) -> usize {
+        COMMAND_SIZE
+    }
+}
+
+impl Deserializable for Command {
+    fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, io::Error>
+    where
+        Self: Sized,
+        T: io::Read,
+    {
+        let mut command = [0u8; COMMAND_SIZE];
+        reader.read_slice(&mut command)?;
+        // The name is followed by zeros only
+        let len = command.iter().position(|b| *b == 0).unwrap_or(COMMAND_SIZE);
+        if !command[..len].iter().all(|b| b.is_ascii_graphic())
+            || command[len..].iter().any(|b| *b != 0)
+        {
+            return Err(io::Error::ReadMalformedData);
+        }
+        Ok(Command(command))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use light_bitcoin_serialization::{deserialize, serialize};
+
+    use super::*;
+
+    #[test]
+    fn test_command() {
+        let command: Command = "version".into();
+        assert_eq!(command.as_str(), "version");
+        assert_eq!(&*serialize(&command), b"version\0\0\0\0\0".as_ref());
+        assert_eq!(
+            deserialize::<_, Command>(b"version\0\0\0\0\0".as_ref()),
+            Ok(command)
+        );
+        assert_eq!(
+            Command::new("AndrewAndrew").unwrap().as_str(),
+            "AndrewAndrew"
+        );
+        assert_eq!(Command::new("AndrewAndrewA"), None);
+        assert!(deserialize::<_, Command>(b"version\0\0\0\0".as_ref()).is_err());
+        assert!(deserialize::<_, Command>(b"ver\0sion\0\0\0\0".as_ref()).is_err());
+    }
+}
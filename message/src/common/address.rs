@@ -0,0 +1,425 @@
+//! Network addresses of the `version`, `addr` and `addrv2` messages
+
+#[cfg(not(feature = "std"))]
+use alloc::vec;
+
+use light_bitcoin_primitives::{io, Bytes};
+use light_bitcoin_serialization::{CompactInteger, Deserializable, Reader, Serializable, Stream};
+
+/// Maximum size of a BIP155 address.
+pub const MAX_ADDRV2_SIZE: usize = 512;
+
+/// Prefix of the IPv4-mapped IPv6 addresses.
+const IPV4_MAPPED_PREFIX: [u8; 12] = [0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0xff, 0xff];
+/// Prefix of the OnionCat IPv6 addresses (Tor v2).
+const ONION_CAT_PREFIX: [u8; 6] = [0xfd, 0x87, 0xd8, 0x7e, 0xeb, 0x43];
+
+/// Services supported by a node, as a bit field.
+#[derive(Eq, PartialEq, Hash, Copy, Clone, Debug, Default)]
+pub struct Services(pub u64);
+
+impl Services {
+    /// Serves the full block chain
+    pub const NETWORK: Services = Services(1);
+    /// Answers `getutxo` requests (BIP64)
+    pub const GETUTXO: Services = Services(1 << 1);
+    /// Supports bloom filters (BIP111)
+    pub const BLOOM: Services = Services(1 << 2);
+    /// Serves witnesses (BIP144)
+    pub const WITNESS: Services = Services(1 << 3);
+    /// Serves compact block filters (BIP157)
+    pub const COMPACT_FILTERS: Services = Services(1 << 6);
+    /// Serves the last 288 blocks only (BIP159)
+    pub const NETWORK_LIMITED: Services = Services(1 << 10);
+
+    /// Returns true if all the services of `other` are supported.
+    pub fn has(&self, other: Services) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// Returns the services with the services of `other` added.
+    pub fn with(self, other: Services) -> Services {
+        Services(self.0 | other.0)
+    }
+}
+
+impl From<u64> for Services {
+    fn from(services: u64) -> Self {
+        Services(services)
+    }
+}
+
+impl From<Services> for u64 {
+    fn from(services: Services) -> Self {
+        services.0
+    }
+}
+
+impl Serializable for Services {
+    fn serialize(&self, stream: &mut Stream) {
+        stream.append(&self.0);
+    }
+
+    #[inline]
+    fn serialized_size(&self) -> usize {
+        8
+    }
+}
+
+impl Deserializable for Services {
+    fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, io::Error>
+    where
+        Self: Sized,
+        T: io::Read,
+    {
+        reader.read().map(Services)
+    }
+}
+
+/// Network address of a node, as in the `version` message.
+#[derive(Eq, PartialEq, Hash, Copy, Clone, Debug, Default)]
+pub struct NetAddress {
+    /// Services of the node
+    pub services: Services,
+    /// IPv6 address, or IPv4-mapped IPv6 address
+    pub ip: [u8; 16],
+    /// Port, in big-endian on the wire
+    pub port: u16,
+}
+
+impl NetAddress {
+    /// Creates the address of an IPv4 node.
+    pub fn ipv4(services: Services, ip: [u8; 4], port: u16) -> Self {
+        let mut mapped = [0u8; 16];
+        mapped[..12].copy_from_slice(&IPV4_MAPPED_PREFIX);
+        mapped[12..].copy_from_slice(&ip);
+        NetAddress {
+            services,
+            ip: mapped,
+            port,
+        }
+    }
+
+    /// Returns the IPv4 address if the address is IPv4-mapped.
+    pub fn as_ipv4(&self) -> Option<[u8; 4]> {
+        if self.ip[..12] != IPV4_MAPPED_PREFIX {
+            return None;
+        }
+        let mut ip = [0u8; 4];
+        ip.copy_from_slice(&self.ip[12..]);
+        Some(ip)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<(Services, std::net::SocketAddr)> for NetAddress {
+    fn from((services, address): (Services, std::net::SocketAddr)) -> Self {
+        match address {
+            std::net::SocketAddr::V4(address) => {
+                NetAddress::ipv4(services, address.ip().octets(), address.port())
+            }
+            std::net::SocketAddr::V6(address) => NetAddress {
+                services,
+                ip: address.ip().octets(),
+                port: address.port(),
+            },
+        }
+    }
+}
+
+impl Serializable for NetAddress {
+    fn serialize(&self, stream: &mut Stream) {
+        stream
+            .append(&self.services)
+            .append_slice(&self.ip)
+            .append_slice(&self.port.to_be_bytes());
+    }
+
+    #[inline]
+    fn serialized_size(&self) -> usize {
+        26
+    }
+}
+
+impl Deserializable for NetAddress {
+    fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, io::Error>
+    where
+        Self: Sized,
+        T: io::Read,
+    {
+        let services = reader.read()?;
+        let mut ip = [0u8; 16];
+        reader.read_slice(&mut ip)?;
+        let mut port = [0u8; 2];
+        reader.read_slice(&mut port)?;
+        Ok(NetAddress {
+            services,
+            ip,
+            port: u16::from_be_bytes(port),
+        })
+    }
+}
+
+/// Entry of the `addr` message: an address with the time it was last seen.
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Default)]
+#[derive(Serializable, Deserializable)]
+pub struct AddressEntry {
+    /// Time the node was last seen
+    pub timestamp: u32,
+    /// Address of the node
+    pub address: NetAddress,
+}
+
+/// BIP155 network address.
+///
+/// https://github.com/bitcoin/bips/blob/master/bip-0155.mediawiki
+#[derive(Eq, PartialEq, Hash, Clone, Debug)]
+pub enum AddressV2 {
+    /// IPv4 address
+    Ipv4([u8; 4]),
+    /// IPv6 address, neither IPv4-mapped nor OnionCat
+    Ipv6([u8; 16]),
+    /// Tor v2 onion service
+    TorV2([u8; 10]),
+    /// Tor v3 onion service public key
+    TorV3([u8; 32]),
+    /// I2P destination hash
+    I2p([u8; 32]),
+    /// CJDNS address
+    Cjdns([u8; 16]),
+    /// Address of an unknown network
+    Unknown(u8, Bytes),
+}
+
+impl AddressV2 {
+    /// Returns the BIP155 network id of the address.
+    pub fn network_id(&self) -> u8 {
+        match self {
+            AddressV2::Ipv4(_) => 1,
+            AddressV2::Ipv6(_) => 2,
+            AddressV2::TorV2(_) => 3,
+            AddressV2::TorV3(_) => 4,
+            AddressV2::I2p(_) => 5,
+            AddressV2::Cjdns(_) => 6,
+            AddressV2::Unknown(network, _) => *network,
+        }
+    }
+
+    /// Returns the raw address.
+    pub fn as_bytes(&self) -> &[u8] {
+        match self {
+            AddressV2::Ipv4(addr) => addr,
+            AddressV2::Ipv6(addr) => addr,
+            AddressV2::TorV2(addr) => addr,
+            AddressV2::TorV3(addr) => addr,
+            AddressV2::I2p(addr) => addr,
+            AddressV2::Cjdns(addr) => addr,
+            AddressV2::Unknown(_, addr) => addr,
+        }
+    }
+}
+
+impl Serializable for AddressV2 {
+    fn serialize(&self, stream: &mut Stream) {
+        let addr = self.as_bytes();
+        stream
+            .append(&self.network_id())
+            .append(&CompactInteger::from(addr.len()))
+            .append_slice(addr);
+    }
+}
+
+fn read_array<T: io::Read, A: AsMut<[u8]> + Default>(
+    reader: &mut Reader<T>,
+    len: usize,
+) -> Result<A, io::Error> {
+    let mut addr = A::default();
+    if addr.as_mut().len() != len {
+        return Err(io::Error::ReadMalformedData);
+    }
+    reader.read_slice(addr.as_mut())?;
+    Ok(addr)
+}
+
+impl Deserializable for AddressV2 {
+    fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, io::Error>
+    where
+        Self: Sized,
+        T: io::Read,
+    {
+        let network: u8 = reader.read()?;
+        let len: usize = reader.read::<CompactInteger>()?.into();
+        if len > MAX_ADDRV2_SIZE {
+            return Err(io::Error::ReadMalformedData);
+        }
+        Ok(match network {
+            1 => AddressV2::Ipv4(read_array(reader, len)?),
+            2 => {
+                let addr: [u8; 16] = read_array(reader, len)?;
+                if addr[..12] == IPV4_MAPPED_PREFIX || addr[..6] == ONION_CAT_PREFIX {
+                    return Err(io::Error::ReadMalformedData);
+                }
+                AddressV2::Ipv6(addr)
+            }
+            3 => AddressV2::TorV2(read_array(reader, len)?),
+            4 => AddressV2::TorV3(read_array(reader, len)?),
+            5 => AddressV2::I2p(read_array(reader, len)?),
+            6 => {
+                let addr: [u8; 16] = read_array(reader, len)?;
+                if addr[0] != 0xfc {
+                    return Err(io::Error::ReadMalformedData);
+                }
+                AddressV2::Cjdns(addr)
+            }
+            _ => {
+                let mut addr = vec![0u8; len];
+                reader.read_slice(&mut addr)?;
+                AddressV2::Unknown(network, addr.into())
+            }
+        })
+    }
+}
+
+/// Entry of the `addrv2` message.
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct AddressV2Entry {
+    /// Time the node was last seen
+    pub timestamp: u32,
+    /// Services of the node, compact size encoded
+    pub services: Services,
+    /// Address of the node
+    pub address: AddressV2,
+    /// Port, in big-endian on the wire
+    pub port: u16,
+}
+
+impl Serializable for AddressV2Entry {
+    fn serialize(&self, stream: &mut Stream) {
+        stream
+            .append(&self.timestamp)
+            .append(&CompactInteger::from(self.services.0))
+            .append(&self.address)
+            .append_slice(&self.port.to_be_bytes());
+    }
+}
+
+impl Deserializable for AddressV2Entry {
+    fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, io::Error>
+    where
+        Self: Sized,
+        T: io::Read,
+    {
+        let timestamp = reader.read()?;
+        let services: u64 = reader.read::<CompactInteger>()?.into();
+        let address = reader.read()?;
+        let mut port = [0u8; 2];
+        reader.read_slice(&mut port)?;
+        Ok(AddressV2Entry {
+            timestamp,
+            services: Services(services),
+            address,
+            port: u16::from_be_bytes(port),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use light_bitcoin_serialization::{deserialize, serialize};
+
+    use super::*;
+
+    // https://github.com/bitcoin/bitcoin/blob/master/src/test/net_tests.cpp
+    #[test]
+    fn test_address_v2() {
+        let vectors = [
+            ("010401020304", AddressV2::Ipv4([1, 2, 3, 4])),
+            (
+                "02101a1b2a2b3a3b4a4b5a5b6a6b7a7b8a8b",
+                AddressV2::Ipv6([
+                    0x1a, 0x1b, 0x2a, 0x2b, 0x3a, 0x3b, 0x4a, 0x4b, 0x5a, 0x5b, 0x6a, 0x6b, 0x7a,
+                    0x7b, 0x8a, 0x8b,
+                ]),
+            ),
+            (
+                "030af1f2f3f4f5f6f7f8f9fa",
+                AddressV2::TorV2([0xf1, 0xf2, 0xf3, 0xf4, 0xf5, 0xf6, 0xf7, 0xf8, 0xf9, 0xfa]),
+            ),
+            (
+                "aa0401020304",
+                AddressV2::Unknown(0xaa, vec![1, 2, 3, 4].into()),
+            ),
+        ];
+        for (raw, address) in vectors.iter() {
+            let raw = hex::decode(raw).unwrap();
+            assert_eq!(
+                &deserialize::<_, AddressV2>(raw.as_slice()).unwrap(),
+                address
+            );
+            assert_eq!(&*serialize(address), raw.as_slice());
+        }
+
+        for invalid in [
+            // Invalid IPv4 length
+            "01050102030405",
+            // IPv4-mapped IPv6 address
+            "021000000000000000000000ffff01020304",
+            // OnionCat IPv6 address
+            "0210fd87d87eeb430102030405060708090a",
+            // CJDNS address without the fc prefix
+            "0610aa000000000000000000000000000001",
+            // Too long address
+            "aafd0102",
+        ]
+        .iter()
+        {
+            let raw = hex::decode(invalid).unwrap();
+            assert!(deserialize::<_, AddressV2>(raw.as_slice()).is_err());
+        }
+    }
+
+    #[test]
+    fn test_address_v2_entries() {
+        let raw = hex::decode("0261bc6649019902abab208d79627683fd4804010409090909208d").unwrap();
+        let mut reader = Reader::new(&raw);
+        let entries: Vec<AddressV2Entry> = reader.read_list().unwrap();
+        assert!(reader.is_finished());
+        assert_eq!(
+            entries,
+            vec![
+                AddressV2Entry {
+                    timestamp: 0x4966_bc61,
+                    services: Services(1),
+                    address: AddressV2::Unknown(0x99, vec![0xab, 0xab].into()),
+                    port: 8333,
+                },
+                AddressV2Entry {
+                    timestamp: 0x8376_6279,
+                    services: Services(0x448),
+                    address: AddressV2::Ipv4([9, 9, 9, 9]),
+                    port: 8333,
+                },
+            ]
+        );
+        assert_eq!(
+            &*light_bitcoin_serialization::serialize_list(&entries),
+            raw.as_slice()
+        );
+    }
+
+    #[test]
+    fn test_net_address() {
+        let address = NetAddress::ipv4(Services::NETWORK, [10, 0, 0, 1], 8333);
+        assert_eq!(
+            hex::encode(&*serialize(&address)),
+            "010000000000000000000000000000000000ffff0a000001208d"
+        );
+        assert_eq!(address.as_ipv4(), Some([10, 0, 0, 1]));
+        assert_eq!(
+            deserialize::<_, NetAddress>(serialize(&address).as_ref()),
+            Ok(address)
+        );
+        assert!(Services(9).has(Services::WITNESS));
+        assert!(!Services::NETWORK.has(Services::WITNESS));
+    }
+}
@@ -0,0 +1,717 @@
+//! Compact blocks (BIP152)
+//!
+//! https://github.com/bitcoin/bips/blob/master/bip-0152.mediawiki
+
+#[cfg(not(feature = "std"))]
+use alloc::{collections::BTreeMap, vec, vec::Vec};
+#[cfg(feature = "std")]
+use std::collections::BTreeMap;
+
+use light_bitcoin_chain::{Block, BlockHeader, IndexedBlock, Transaction};
+use light_bitcoin_crypto::{sha256, siphash24};
+use light_bitcoin_primitives::{io, H256};
+use light_bitcoin_serialization::{CompactInteger, Deserializable, Reader, Serializable, Stream};
+
+use crate::error::Error;
+use crate::header::MAX_PAYLOAD_SIZE;
+
+/// Maximum number of short ids of a `cmpctblock` payload, each taking 6 bytes.
+const MAX_SHORT_IDS: usize = MAX_PAYLOAD_SIZE as usize / 6;
+/// Maximum number of transactions of a `blocktxn` payload, a transaction taking at least
+/// 10 bytes (version, input and output counts, and lock time).
+const MAX_BLOCK_TRANSACTIONS: usize = MAX_PAYLOAD_SIZE as usize / 10;
+
+/// Checks the compact block version: 1 for txids, 2 for wtxids.
+fn check_version(version: u64) -> Result<(), Error> {
+    match version {
+        1 | 2 => Ok(()),
+        _ => Err(Error::UnknownCompactVersion),
+    }
+}
+
+/// Returns the hash identifying the transaction in compact blocks of the version.
+fn compact_hash(transaction: &Transaction, version: u64) -> H256 {
+    if version == 1 {
+        transaction.hash()
+    } else {
+        transaction.witness_hash()
+    }
+}
+
+/// Short transaction id: the 6 lower bytes of a SipHash-2-4 of the txid or wtxid.
+#[derive(Eq, PartialEq, Ord, PartialOrd, Hash, Copy, Clone, Debug, Default)]
+pub struct ShortTxId(pub [u8; 6]);
+
+impl Serializable for ShortTxId {
+    fn serialize(&self, stream: &mut Stream) {
+        stream.append_slice(&self.0);
+    }
+
+    #[inline]
+    fn serialized_size(&self) -> usize {
+        6
+    }
+}
+
+impl Deserializable for ShortTxId {
+    fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, io::Error>
+    where
+        Self: Sized,
+        T: io::Read,
+    {
+        let mut short_id = [0u8; 6];
+        reader.read_slice(&mut short_id)?;
+        Ok(ShortTxId(short_id))
+    }
+}
+
+impl ShortTxId {
+    /// Computes the SipHash-2-4 keys of a compact block: the first two little-endian 64-bit
+    /// integers of the SHA-256 of the header followed by the nonce.
+    pub fn siphash_keys(header: &BlockHeader, nonce: u64) -> (u64, u64) {
+        let mut stream = Stream::new();
+        stream.append(header).append(&nonce);
+        let hash = sha256(&stream.out());
+        let mut key0 = [0u8; 8];
+        let mut key1 = [0u8; 8];
+        key0.copy_from_slice(&hash[..8]);
+        key1.copy_from_slice(&hash[8..16]);
+        (u64::from_le_bytes(key0), u64::from_le_bytes(key1))
+    }
+
+    /// Computes the short id of a txid or wtxid with the keys of the compact block.
+    pub fn new(keys: (u64, u64), hash: &H256) -> Self {
+        let hash = siphash24(keys.0, keys.1, hash.as_bytes()).to_le_bytes();
+        let mut short_id = [0u8; 6];
+        short_id.copy_from_slice(&hash[..6]);
+        ShortTxId(short_id)
+    }
+}
+
+/// Transaction sent in full in a compact block.
+#[derive(Eq, PartialEq, Clone, Debug, Default)]
+pub struct PrefilledTransaction {
+    /// Index of the transaction in the block
+    pub index: u16,
+    /// The transaction
+    pub transaction: Transaction,
+}
+
+/// Payload of the `cmpctblock` message: a block header with the short ids of the transactions.
+#[derive(Eq, PartialEq, Clone, Debug, Default)]
+pub struct HeaderAndShortIDs {
+    /// The block header
+    pub header: BlockHeader,
+    /// Nonce of the short id computation
+    pub nonce: u64,
+    /// Short ids of the transactions which are not prefilled
+    pub short_ids: Vec<ShortTxId>,
+    /// Transactions sent in full, ordered by index
+    pub prefilled_transactions: Vec<PrefilledTransaction>,
+}
+
+impl HeaderAndShortIDs {
+    /// Creates the compact block of the version (1 for txids, 2 for wtxids).
+    ///
+    /// The coinbase and the transactions at the ordered `prefill` indexes are sent in full,
+    /// without their witness for version 1.
+    pub fn from_block(
+        block: &IndexedBlock,
+        nonce: u64,
+        version: u64,
+        prefill: &[usize],
+    ) -> Result<Self, Error> {
+        check_version(version)?;
+        let keys = ShortTxId::siphash_keys(&block.header.raw, nonce);
+        let mut prefill = prefill.iter().peekable();
+        let mut short_ids = Vec::new();
+        let mut prefilled_transactions = Vec::new();
+        for (index, transaction) in block.transactions.iter().enumerate() {
+            let prefilled = prefill.next_if_eq(&&index).is_some() || index == 0;
+            if !prefilled {
+                let hash = match version {
+                    // The txid of indexed transactions is already computed
+                    1 => transaction.hash,
+                    _ => compact_hash(&transaction.raw, version),
+                };
+                short_ids.push(ShortTxId::new(keys, &hash));
+                continue;
+            }
+            if index > usize::from(u16::MAX) {
+                return Err(Error::InvalidPrefill);
+            }
+            let mut transaction = transaction.raw.clone();
+            if version == 1 {
+                for input in transaction.inputs.iter_mut() {
+                    input.script_witness.clear();
+                }
+            }
+            prefilled_transactions.push(PrefilledTransaction {
+                index: index as u16,
+                transaction,
+            });
+        }
+        if prefill.next().is_some() {
+            return Err(Error::InvalidPrefill);
+        }
+        Ok(HeaderAndShortIDs {
+            header: block.header.raw,
+            nonce,
+            short_ids,
+            prefilled_transactions,
+        })
+    }
+}
+
+impl Serializable for HeaderAndShortIDs {
+    /// Prefilled transaction indexes are encoded as the difference with the previous index.
+    fn serialize(&self, stream: &mut Stream) {
+        stream
+            .append(&self.header)
+            .append(&self.nonce)
+            .append_list(&self.short_ids)
+            .append(&CompactInteger::from(self.prefilled_transactions.len()));
+        let mut next = 0u32;
+        for prefilled in &self.prefilled_transactions {
+            let index = u32::from(prefilled.index);
+            stream
+                .append(&CompactInteger::from(index.wrapping_sub(next)))
+                .append(&prefilled.transaction);
+            next = index + 1;
+        }
+    }
+}
+
+impl Deserializable for HeaderAndShortIDs {
+    fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, io::Error>
+    where
+        Self: Sized,
+        T: io::Read,
+    {
+        let header = reader.read()?;
+        let nonce = reader.read()?;
+        let short_ids = reader.read_list_max(MAX_SHORT_IDS)?;
+        let len: usize = reader.read::<CompactInteger>()?.into();
+        let mut prefilled_transactions = Vec::new();
+        let mut next = 0;
+        for _ in 0..len {
+            prefilled_transactions.push(PrefilledTransaction {
+                index: read_differential_index(reader, &mut next)?,
+                transaction: reader.read()?,
+            });
+        }
+        Ok(HeaderAndShortIDs {
+            header,
+            nonce,
+            short_ids,
+            prefilled_transactions,
+        })
+    }
+}
+
+/// Writes indexes as the differences with the previous indexes.
+fn write_differential_indexes(stream: &mut Stream, indexes: impl ExactSizeIterator<Item = u16>) {
+    stream.append(&CompactInteger::from(indexes.len()));
+    let mut next = 0u32;
+    for index in indexes {
+        let index = u32::from(index);
+        stream.append(&CompactInteger::from(index.wrapping_sub(next)));
+        next = index + 1;
+    }
+}
+
+/// Reads indexes encoded as the differences with the previous indexes.
+fn read_differential_index<T: io::Read>(
+    reader: &mut Reader<T>,
+    next: &mut u64,
+) -> Result<u16, io::Error> {
+    let diff: u64 = reader.read::<CompactInteger>()?.into();
+    // Indexes are bounded by the maximum number of transactions of a block
+    let index = next
+        .checked_add(diff)
+        .filter(|index| *index <= u64::from(u16::MAX))
+        .ok_or(io::Error::ReadMalformedData)?;
+    *next = index + 1;
+    Ok(index as u16)
+}
+
+/// Payload of the `getblocktxn` message, requesting the transactions missing to reconstruct a
+/// compact block.
+#[derive(Eq, PartialEq, Clone, Debug, Default)]
+pub struct BlockTransactionsRequest {
+    /// Hash of the block
+    pub block_hash: H256,
+    /// Ordered indexes of the requested transactions
+    pub indexes: Vec<u16>,
+}
+
+impl Serializable for BlockTransactionsRequest {
+    /// Indexes are encoded as the difference with the previous index.
+    fn serialize(&self, stream: &mut Stream) {
+        stream.append(&self.block_hash);
+        write_differential_indexes(stream, self.indexes.iter().cloned());
+    }
+}
+
+impl Deserializable for BlockTransactionsRequest {
+    fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, io::Error>
+    where
+        Self: Sized,
+        T: io::Read,
+    {
+        let block_hash = reader.read()?;
+        let len: usize = reader.read::<CompactInteger>()?.into();
+        let mut indexes = Vec::new();
+        let mut next = 0;
+        for _ in 0..len {
+            indexes.push(read_differential_index(reader, &mut next)?);
+        }
+        Ok(BlockTransactionsRequest {
+            block_hash,
+            indexes,
+        })
+    }
+}
+
+/// Payload of the `blocktxn` message, answering a `getblocktxn` message.
+#[derive(Eq, PartialEq, Clone, Debug, Default)]
+#[derive(Serializable)]
+pub struct BlockTransactions {
+    /// Hash of the block
+    pub block_hash: H256,
+    /// The requested transactions, in the order of the request
+    pub transactions: Vec<Transaction>,
+}
+
+impl Deserializable for BlockTransactions {
+    fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, io::Error>
+    where
+        Self: Sized,
+        T: io::Read,
+    {
+        Ok(BlockTransactions {
+            block_hash: reader.read()?,
+            transactions: reader.read_list_max(MAX_BLOCK_TRANSACTIONS)?,
+        })
+    }
+}
+
+impl BlockTransactions {
+    /// Answers the request with the transactions of the block.
+    pub fn from_request(
+        request: &BlockTransactionsRequest,
+        block: &IndexedBlock,
+    ) -> Result<Self, Error> {
+        let transactions = request
+            .indexes
+            .iter()
+            .map(|index| {
+                block
+                    .transactions
+                    .get(usize::from(*index))
+                    .map(|transaction| transaction.raw.clone())
+                    .ok_or(Error::TransactionIndexOutOfRange)
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(BlockTransactions {
+            block_hash: request.block_hash,
+            transactions,
+        })
+    }
+}
+
+/// Block reconstructed from a compact block and the known transactions.
+///
+/// The transactions which are still missing are requested from the peer with a `getblocktxn`
+/// message, and the `blocktxn` answer completes the block.
+#[derive(Clone, Debug)]
+pub struct PartiallyDownloadedBlock {
+    header: BlockHeader,
+    hash: H256,
+    transactions: Vec<Option<Transaction>>,
+}
+
+impl PartiallyDownloadedBlock {
+    /// Places the prefilled transactions of the compact block, and the transactions of the
+    /// source (e.g. the mempool) matching its short ids.
+    ///
+    /// Transactions of the source sharing a short id are left out, the transaction being
+    /// requested from the peer instead.
+    pub fn new<'a, I>(compact: &HeaderAndShortIDs, version: u64, source: I) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = &'a Transaction>,
+    {
+        check_version(version)?;
+        let count = compact.short_ids.len() + compact.prefilled_transactions.len();
+        if count == 0 || count > usize::from(u16::MAX) + 1 {
+            return Err(Error::InvalidCompactBlock);
+        }
+        let mut transactions = vec![None; count];
+        for prefilled in &compact.prefilled_transactions {
+            match transactions.get_mut(usize::from(prefilled.index)) {
+                Some(slot @ None) => *slot = Some(prefilled.transaction.clone()),
+                _ => return Err(Error::InvalidCompactBlock),
+            }
+        }
+
+        // The short ids are the ones of the transactions which are not prefilled, in order
+        let mut slots = BTreeMap::new();
+        let empty_slots = transactions
+            .iter()
+            .enumerate()
+            .filter(|(_, transaction)| transaction.is_none());
+        for ((index, _), short_id) in empty_slots.zip(&compact.short_ids) {
+            if slots.insert(*short_id, index).is_some() {
+                return Err(Error::ShortIdCollision);
+            }
+        }
+
+        let keys = ShortTxId::siphash_keys(&compact.header, compact.nonce);
+        let mut collided = vec![false; count];
+        for transaction in source {
+            let short_id = ShortTxId::new(keys, &compact_hash(transaction, version));
+            let index = match slots.get(&short_id) {
+                Some(index) if !collided[*index] => *index,
+                _ => continue,
+            };
+            match &transactions[index] {
+                None => transactions[index] = Some(transaction.clone()),
+                Some(found) if found != transaction => {
+                    transactions[index] = None;
+                    collided[index] = true;
+                }
+                Some(_) => (),
+            }
+        }
+
+        Ok(PartiallyDownloadedBlock {
+            header: compact.header,
+            hash: compact.header.hash(),
+            transactions,
+        })
+    }
+
+    /// Returns the block header.
+    pub fn header(&self) -> &BlockHeader {
+        &self.header
+    }
+
+    /// Returns true if no transaction is missing.
+    pub fn is_complete(&self) -> bool {
+        self.transactions.iter().all(Option::is_some)
+    }
+
+    /// Returns the indexes of the missing transactions.
+    pub fn missing(&self) -> Vec<u16> {
+        self.transactions
+            .iter()
+            .enumerate()
+            .filter(|(_, transaction)| transaction.is_none())
+            .map(|(index, _)| index as u16)
+            .collect()
+    }
+
+    /// Returns the request of the missing transactions.
+    pub fn request(&self) -> BlockTransactionsRequest {
+        BlockTransactionsRequest {
+            block_hash: self.hash,
+            indexes: self.missing(),
+        }
+    }
+
+    /// Places the transactions answering the request of the missing transactions.
+    pub fn fill(&mut self, response: &BlockTransactions) -> Result<(), Error> {
+        let missing = self.missing();
+        if response.block_hash != self.hash || response.transactions.len() != missing.len() {
+            return Err(Error::UnexpectedBlockTransactions);
+        }
+        for (index, transaction) in missing.into_iter().zip(&response.transactions) {
+            self.transactions[usize::from(index)] = Some(transaction.clone());
+        }
+        Ok(())
+    }
+
+    /// Returns the reconstructed block, checking its merkle root.
+    ///
+    /// A mismatch means a short id matched another transaction; the full block should then be
+    /// requested.
+    pub fn into_block(self) -> Result<IndexedBlock, Error> {
+        let transactions = self
+            .transactions
+            .into_iter()
+            .collect::<Option<Vec<_>>>()
+            .ok_or(Error::IncompleteBlock)?;
+        let block = IndexedBlock::from(Block::new(self.header, transactions));
+        if block.merkle_root() != self.header.merkle_root_hash {
+            return Err(Error::MerkleRootMismatch);
+        }
+        Ok(block)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use light_bitcoin_chain::{merkle_root, OutPoint, TransactionInput, TransactionOutput};
+    use light_bitcoin_serialization::{deserialize, serialize};
+
+    use super::*;
+
+    fn dummy_transaction(nonce: u8) -> Transaction {
+        Transaction {
+            version: 1,
+            inputs: vec![TransactionInput {
+                previous_output: OutPoint::new(H256::repeat_byte(nonce), 0),
+                ..Default::default()
+            }],
+            outputs: vec![TransactionOutput {
+                value: u64::from(nonce),
+                script_pubkey: Default::default(),
+            }],
+            lock_time: 2,
+        }
+    }
+
+    fn dummy_block(transactions: usize) -> IndexedBlock {
+        let transactions: Vec<_> = (0..transactions as u8).map(dummy_transaction).collect();
+        let hashes: Vec<_> = transactions.iter().map(Transaction::hash).collect();
+        let header = BlockHeader {
+            version: 1,
+            merkle_root_hash: merkle_root(&hashes),
+            time: 2,
+            ..Default::default()
+        };
+        Block::new(header, transactions).into()
+    }
+
+    #[test]
+    fn test_header_and_short_ids_serialization() {
+        // https://github.com/rust-bitcoin/rust-bitcoin/blob/master/bitcoin/src/p2p/message.rs
+        let raw = hex::decode(
+            "00000030d923ad36ff2d955abab07f8a0a6e813bc6e066b973e780c5e36674cad5d1cd1f6e265f2a17a0d35cbe701fe9d06e2c6324cfe135f6233e8b767bfa3fb4479b71115dc562ffff7f2006000000000000000000000000010002000000010000000000000000000000000000000000000000000000000000000000000000ffffffff0302ee00ffffffff0100f9029500000000015100000000",
+        )
+        .unwrap();
+        let compact: HeaderAndShortIDs = deserialize(raw.as_slice()).unwrap();
+        assert!(compact.short_ids.is_empty());
+        assert_eq!(compact.prefilled_transactions.len(), 1);
+        assert_eq!(compact.prefilled_transactions[0].index, 0);
+        assert!(compact.prefilled_transactions[0].transaction.is_coinbase());
+        assert_eq!(&*serialize(&compact), raw.as_slice());
+    }
+
+    // https://github.com/rust-bitcoin/rust-bitcoin/blob/master/bitcoin/src/bip152.rs
+    #[test]
+    fn test_compact_block_from_block() {
+        let block: Block = deserialize(
+            hex::decode("000000206c750a364035aefd5f81508a08769975116d9195312ee4520dceac39e1fdc62c4dc67473b8e354358c1e610afeaff7410858bd45df43e2940f8a62bd3d5e3ac943c2975cffff7f200000000002020000000001010000000000000000000000000000000000000000000000000000000000000000ffffffff04016b0101ffffffff020006062a0100000001510000000000000000266a24aa21a9ed4a3d9f3343dafcc0d6f6d4310f2ee5ce273ed34edca6c75db3a73e7f368734200120000000000000000000000000000000000000000000000000000000000000000000000000020000000001021fc20ba2bd745507b8e00679e3b362558f9457db374ca28ffa5243f4c23a4d5f00000000171600147c9dea14ffbcaec4b575e03f05ceb7a81cd3fcbffdffffff915d689be87b43337f42e26033df59807b768223368f189a023d0242d837768900000000171600147c9dea14ffbcaec4b575e03f05ceb7a81cd3fcbffdffffff0200cdf5050000000017a9146803c72d9154a6a20f404bed6d3dcee07986235a8700e1f5050000000017a9144e6a4c7cb5b5562904843bdf816342f4db9f5797870247304402205e9bf6e70eb0e4b495bf483fd8e6e02da64900f290ef8aaa64bb32600d973c450220670896f5d0e5f33473e5f399ab680cc1d25c2d2afd15abd722f04978f28be887012103e4e4d9312b2261af508b367d8ba9be4f01b61d6d6e78bec499845b4f410bcf2702473044022045ac80596a6ac9c8c572f94708709adaf106677221122e08daf8b9741a04f66a022003ccd52a3b78f8fd08058fc04fc0cffa5f4c196c84eae9e37e2a85babe731b57012103e4e4d9312b2261af508b367d8ba9be4f01b61d6d6e78bec499845b4f410bcf276a000000")
+            .unwrap()
+            .as_slice(),
+        )
+        .unwrap();
+        let expected: HeaderAndShortIDs = deserialize(
+            hex::decode("000000206c750a364035aefd5f81508a08769975116d9195312ee4520dceac39e1fdc62c4dc67473b8e354358c1e610afeaff7410858bd45df43e2940f8a62bd3d5e3ac943c2975cffff7f2000000000a4df3c3744da89fa010a6979e971450100020000000001010000000000000000000000000000000000000000000000000000000000000000ffffffff04016b0101ffffffff020006062a0100000001510000000000000000266a24aa21a9ed4a3d9f3343dafcc0d6f6d4310f2ee5ce273ed34edca6c75db3a73e7f368734200120000000000000000000000000000000000000000000000000000000000000000000000000")
+            .unwrap()
+            .as_slice(),
+        )
+        .unwrap();
+        let compact =
+            HeaderAndShortIDs::from_block(&block.into(), 18053200567810711460, 2, &[]).unwrap();
+        assert_eq!(compact, expected);
+    }
+
+    #[test]
+    fn test_oversized_counts() {
+        // Header and nonce followed by the short id count
+        let mut raw = vec![0u8; 80 + 8];
+        raw.extend(&[0xff; 9]);
+        assert_eq!(
+            deserialize::<_, HeaderAndShortIDs>(raw.as_slice()),
+            Err(io::Error::ReadMalformedData)
+        );
+
+        let mut raw = vec![0u8; 32];
+        raw.extend(&[0xff; 9]);
+        assert_eq!(
+            deserialize::<_, BlockTransactions>(raw.as_slice()),
+            Err(io::Error::ReadMalformedData)
+        );
+    }
+
+    #[test]
+    fn test_prefill() {
+        let block = dummy_block(3);
+        let indexes = |compact: HeaderAndShortIDs| {
+            compact
+                .prefilled_transactions
+                .iter()
+                .map(|prefilled| prefilled.index)
+                .collect::<Vec<_>>()
+        };
+        let compact = HeaderAndShortIDs::from_block(&block, 42, 2, &[]).unwrap();
+        assert_eq!(compact.short_ids.len(), 2);
+        assert_eq!(indexes(compact), vec![0]);
+        let compact = HeaderAndShortIDs::from_block(&block, 42, 2, &[0, 1, 2]).unwrap();
+        assert!(compact.short_ids.is_empty());
+        assert_eq!(indexes(compact), vec![0, 1, 2]);
+        let compact = HeaderAndShortIDs::from_block(&block, 42, 1, &[2]).unwrap();
+        assert_eq!(indexes(compact), vec![0, 2]);
+
+        assert_eq!(
+            HeaderAndShortIDs::from_block(&block, 42, 2, &[2, 1]),
+            Err(Error::InvalidPrefill)
+        );
+        assert_eq!(
+            HeaderAndShortIDs::from_block(&block, 42, 2, &[3]),
+            Err(Error::InvalidPrefill)
+        );
+        assert_eq!(
+            HeaderAndShortIDs::from_block(&block, 42, 3, &[]),
+            Err(Error::UnknownCompactVersion)
+        );
+    }
+
+    #[test]
+    fn test_block_transactions_request_serialization() {
+        let vectors = [
+            (vec![4, 0, 5, 1, 10], vec![0, 6, 8, 19]),
+            (vec![1, 0], vec![0]),
+            (vec![5, 0, 0, 0, 0, 0], vec![0, 1, 2, 3, 4]),
+            (vec![3, 1, 1, 1], vec![1, 3, 5]),
+            (vec![3, 0, 0, 253, 0, 1], vec![0, 1, 258]),
+        ];
+        for (raw, indexes) in vectors.iter() {
+            let mut expected = vec![0u8; 32];
+            expected.extend(raw);
+            let request = BlockTransactionsRequest {
+                block_hash: H256::default(),
+                indexes: indexes.clone(),
+            };
+            assert_eq!(&*serialize(&request), expected.as_slice());
+            assert_eq!(deserialize(expected.as_slice()), Ok(request));
+        }
+
+        for invalid in [
+            // 65536
+            vec![1, 0xfe, 0x00, 0x00, 0x01, 0x00],
+            // 65535 followed by another index
+            vec![2, 0xfd, 0xff, 0xff, 0x00],
+            vec![1, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff],
+        ]
+        .iter()
+        {
+            let mut raw = vec![0u8; 32];
+            raw.extend(invalid);
+            assert!(deserialize::<_, BlockTransactionsRequest>(raw.as_slice()).is_err());
+        }
+    }
+
+    #[test]
+    fn test_reconstruction() {
+        let block = dummy_block(5);
+        let compact = HeaderAndShortIDs::from_block(&block, 7, 2, &[3]).unwrap();
+        let compact: HeaderAndShortIDs = deserialize(serialize(&compact).as_ref()).unwrap();
+
+        let mempool = vec![
+            dummy_transaction(4),
+            dummy_transaction(9),
+            dummy_transaction(1),
+        ];
+        let mut partial = PartiallyDownloadedBlock::new(&compact, 2, &mempool).unwrap();
+        assert!(!partial.is_complete());
+        assert_eq!(partial.missing(), vec![2]);
+        assert_eq!(partial.clone().into_block(), Err(Error::IncompleteBlock));
+
+        let request = partial.request();
+        assert_eq!(request.block_hash, *block.hash());
+        let response = BlockTransactions::from_request(&request, &block).unwrap();
+        assert_eq!(response.transactions, vec![dummy_transaction(2)]);
+        assert_eq!(
+            partial.fill(&BlockTransactions::default()),
+            Err(Error::UnexpectedBlockTransactions)
+        );
+        partial.fill(&response).unwrap();
+        assert!(partial.is_complete());
+        let reconstructed = partial.into_block().unwrap();
+        assert_eq!(reconstructed.hash(), block.hash());
+        assert_eq!(reconstructed.transactions, block.transactions);
+
+        // A wrong transaction is detected by the merkle root
+        let mut partial = PartiallyDownloadedBlock::new(&compact, 2, &mempool).unwrap();
+        partial
+            .fill(&BlockTransactions {
+                block_hash: request.block_hash,
+                transactions: vec![dummy_transaction(9)],
+            })
+            .unwrap();
+        assert_eq!(partial.into_block(), Err(Error::MerkleRootMismatch));
+
+        assert_eq!(
+            BlockTransactions::from_request(
+                &BlockTransactionsRequest {
+                    block_hash: request.block_hash,
+                    indexes: vec![5],
+                },
+                &block
+            ),
+            Err(Error::TransactionIndexOutOfRange)
+        );
+    }
+
+    #[test]
+    fn test_invalid_compact_blocks() {
+        let block = dummy_block(3);
+        let compact = HeaderAndShortIDs::from_block(&block, 7, 2, &[]).unwrap();
+        let mempool: Vec<Transaction> = vec![];
+
+        let mut duplicated = compact.clone();
+        duplicated.short_ids[1] = duplicated.short_ids[0];
+        assert_eq!(
+            PartiallyDownloadedBlock::new(&duplicated, 2, &mempool).err(),
+            Some(Error::ShortIdCollision)
+        );
+        let mut out_of_range = compact.clone();
+        out_of_range.prefilled_transactions[0].index = 3;
+        assert_eq!(
+            PartiallyDownloadedBlock::new(&out_of_range, 2, &mempool).err(),
+            Some(Error::InvalidCompactBlock)
+        );
+        assert_eq!(
+            PartiallyDownloadedBlock::new(&HeaderAndShortIDs::default(), 2, &mempool).err(),
+            Some(Error::InvalidCompactBlock)
+        );
+        assert_eq!(
+            PartiallyDownloadedBlock::new(&compact, 3, &mempool).err(),
+            Some(Error::UnknownCompactVersion)
+        );
+    }
+
+    #[test]
+    fn test_differential_indexes() {
+        let transaction = Transaction {
+            inputs: vec![Default::default()],
+            ..Default::default()
+        };
+        let tx_len = serialize(&transaction).len();
+        let compact = HeaderAndShortIDs {
+            short_ids: vec![ShortTxId([1, 2, 3, 4, 5, 6])],
+            prefilled_transactions: [0, 2, 3]
+                .iter()
+                .map(|index| PrefilledTransaction {
+                    index: *index,
+                    transaction: transaction.clone(),
+                })
+                .collect(),
+            ..Default::default()
+        };
+        let serialized = serialize(&compact);
+        // Indexes 0, 2 and 3 are encoded as 0, 1 and 0
+        let prefilled = &serialized[80 + 8 + 1 + 6..];
+        assert_eq!(prefilled[0], 3);
+        assert_eq!(prefilled[1], 0);
+        assert_eq!(prefilled[2 + tx_len], 1);
+        assert_eq!(prefilled[2 + tx_len + 1 + tx_len], 0);
+        assert_eq!(
+            deserialize::<_, HeaderAndShortIDs>(serialized.as_ref()),
+            Ok(compact)
+        );
+    }
+}
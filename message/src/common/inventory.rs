@@ -0,0 +1,127 @@
+//! Inventory of the `inv`, `getdata` and `notfound` messages
+
+use light_bitcoin_primitives::{io, H256};
+use light_bitcoin_serialization::{Deserializable, Reader, Serializable, Stream};
+
+/// Flag of the inventory types requesting witnesses (BIP144).
+pub const MSG_WITNESS_FLAG: u32 = 1 << 30;
+
+/// Type of an inventory vector.
+#[derive(Eq, PartialEq, Hash, Copy, Clone, Debug)]
+pub enum InventoryType {
+    /// Data to be ignored
+    Error,
+    /// Transaction
+    Tx,
+    /// Block
+    Block,
+    /// Merkle block of the transactions matching the bloom filter (BIP37)
+    FilteredBlock,
+    /// Compact block (BIP152)
+    CompactBlock,
+    /// Transaction with its witness (BIP144)
+    WitnessTx,
+    /// Block with the witnesses of its transactions (BIP144)
+    WitnessBlock,
+    /// Merkle block with the witnesses of the matched transactions (BIP144)
+    WitnessFilteredBlock,
+    /// Unknown type
+    Unknown(u32),
+}
+
+impl From<u32> for InventoryType {
+    fn from(inv_type: u32) -> Self {
+        match inv_type {
+            0 => InventoryType::Error,
+            1 => InventoryType::Tx,
+            2 => InventoryType::Block,
+            3 => InventoryType::FilteredBlock,
+            4 => InventoryType::CompactBlock,
+            0x4000_0001 => InventoryType::WitnessTx,
+            0x4000_0002 => InventoryType::WitnessBlock,
+            0x4000_0003 => InventoryType::WitnessFilteredBlock,
+            other => InventoryType::Unknown(other),
+        }
+    }
+}
+
+impl From<InventoryType> for u32 {
+    fn from(inv_type: InventoryType) -> Self {
+        match inv_type {
+            InventoryType::Error => 0,
+            InventoryType::Tx => 1,
+            InventoryType::Block => 2,
+            InventoryType::FilteredBlock => 3,
+            InventoryType::CompactBlock => 4,
+            InventoryType::WitnessTx => MSG_WITNESS_FLAG | 1,
+            InventoryType::WitnessBlock => MSG_WITNESS_FLAG | 2,
+            InventoryType::WitnessFilteredBlock => MSG_WITNESS_FLAG | 3,
+            InventoryType::Unknown(other) => other,
+        }
+    }
+}
+
+impl Serializable for InventoryType {
+    fn serialize(&self, stream: &mut Stream) {
+        stream.append(&u32::from(*self));
+    }
+
+    #[inline]
+    fn serialized_size(&self) -> usize {
+        4
+    }
+}
+
+impl Deserializable for InventoryType {
+    fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, io::Error>
+    where
+        Self: Sized,
+        T: io::Read,
+    {
+        reader.read::<u32>().map(Into::into)
+    }
+}
+
+/// Type and hash of an announced or requested object.
+#[derive(Eq, PartialEq, Hash, Copy, Clone, Debug)]
+#[derive(Serializable, Deserializable)]
+pub struct InventoryVector {
+    /// Type of the object
+    pub inv_type: InventoryType,
+    /// Hash of the object
+    pub hash: H256,
+}
+
+impl InventoryVector {
+    /// Inventory of a transaction.
+    pub fn tx(hash: H256) -> Self {
+        InventoryVector {
+            inv_type: InventoryType::Tx,
+            hash,
+        }
+    }
+
+    /// Inventory of a transaction with its witness.
+    pub fn witness_tx(hash: H256) -> Self {
+        InventoryVector {
+            inv_type: InventoryType::WitnessTx,
+            hash,
+        }
+    }
+
+    /// Inventory of a block.
+    pub fn block(hash: H256) -> Self {
+        InventoryVector {
+            inv_type: InventoryType::Block,
+            hash,
+        }
+    }
+
+    /// Inventory of a block with its witnesses.
+    pub fn witness_block(hash: H256) -> Self {
+        InventoryVector {
+            inv_type: InventoryType::WitnessBlock,
+            hash,
+        }
+    }
+}
@@ -0,0 +1,14 @@
+//! Structures shared by several messages
+
+mod address;
+mod compact_block;
+mod inventory;
+
+pub use self::address::{
+    AddressEntry, AddressV2, AddressV2Entry, NetAddress, Services, MAX_ADDRV2_SIZE,
+};
+pub use self::compact_block::{
+    BlockTransactions, BlockTransactionsRequest, HeaderAndShortIDs, PartiallyDownloadedBlock,
+    PrefilledTransaction, ShortTxId,
+};
+pub use self::inventory::{InventoryType, InventoryVector, MSG_WITNESS_FLAG};
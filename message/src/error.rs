@@ -0,0 +1,55 @@
+//! Network message errors
+
+/// Network message errors
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Error {
+    /// The message starts with the magic of another network.
+    InvalidMagic,
+    /// The command is not the one of the expected payload.
+    UnexpectedCommand,
+    /// The payload doesn't match the checksum of the header.
+    InvalidChecksum,
+    /// The payload is bigger than `MAX_PAYLOAD_SIZE`.
+    OversizedPayload,
+    /// The payload can't be deserialized.
+    Deserialize,
+    /// The compact block version is neither 1 nor 2.
+    UnknownCompactVersion,
+    /// The indexes of the transactions to prefill are not ordered or out of the block.
+    InvalidPrefill,
+    /// The compact block has no transaction or prefilled indexes out of the block.
+    InvalidCompactBlock,
+    /// Several transactions of the compact block have the same short id.
+    ShortIdCollision,
+    /// A requested transaction index is out of the block.
+    TransactionIndexOutOfRange,
+    /// The block transactions are not the ones of the missing transactions.
+    UnexpectedBlockTransactions,
+    /// Transactions of the block are still missing.
+    IncompleteBlock,
+    /// The merkle root of the transactions doesn't match the block header.
+    MerkleRootMismatch,
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Error::InvalidMagic => "Invalid network magic".fmt(f),
+            Error::UnexpectedCommand => "Unexpected message command".fmt(f),
+            Error::InvalidChecksum => "Invalid payload checksum".fmt(f),
+            Error::OversizedPayload => "Payload is too big".fmt(f),
+            Error::Deserialize => "Invalid payload".fmt(f),
+            Error::UnknownCompactVersion => "Unknown compact block version".fmt(f),
+            Error::InvalidPrefill => "Invalid prefilled transaction indexes".fmt(f),
+            Error::InvalidCompactBlock => "Invalid compact block".fmt(f),
+            Error::ShortIdCollision => "Short transaction id collision".fmt(f),
+            Error::TransactionIndexOutOfRange => "Transaction index out of range".fmt(f),
+            Error::UnexpectedBlockTransactions => "Unexpected block transactions".fmt(f),
+            Error::IncompleteBlock => "Block transactions are missing".fmt(f),
+            Error::MerkleRootMismatch => "Merkle root mismatch".fmt(f),
+        }
+    }
+}
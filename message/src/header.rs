@@ -0,0 +1,69 @@
+//! Message header
+
+use light_bitcoin_crypto::checksum;
+use light_bitcoin_primitives::{io, H32};
+use light_bitcoin_serialization::{Deserializable, Reader, Serializable, Stream};
+
+use crate::command::Command;
+
+/// Size of the message header.
+pub const MESSAGE_HEADER_SIZE: usize = 24;
+/// Maximum size of a message payload (4,000,000 bytes).
+pub const MAX_PAYLOAD_SIZE: u32 = 4_000_000;
+
+/// Header of every message of the peer-to-peer protocol.
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Default)]
+pub struct MessageHeader {
+    /// Magic bytes of the network
+    pub magic: [u8; 4],
+    /// Command identifying the payload
+    pub command: Command,
+    /// Size of the payload
+    pub len: u32,
+    /// First 4 bytes of the double SHA-256 of the payload
+    pub checksum: H32,
+}
+
+impl MessageHeader {
+    /// Creates the header of the payload.
+    pub fn for_payload(magic: [u8; 4], command: Command, payload: &[u8]) -> Self {
+        MessageHeader {
+            magic,
+            command,
+            len: payload.len() as u32,
+            checksum: checksum(payload),
+        }
+    }
+}
+
+impl Serializable for MessageHeader {
+    fn serialize(&self, stream: &mut Stream) {
+        stream
+            .append_slice(&self.magic)
+            .append(&self.command)
+            .append(&self.len)
+            .append(&self.checksum);
+    }
+
+    #[inline]
+    fn serialized_size(&self) -> usize {
+        MESSAGE_HEADER_SIZE
+    }
+}
+
+impl Deserializable for MessageHeader {
+    fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, io::Error>
+    where
+        Self: Sized,
+        T: io::Read,
+    {
+        let mut magic = [0u8; 4];
+        reader.read_slice(&mut magic)?;
+        Ok(MessageHeader {
+            magic,
+            command: reader.read()?,
+            len: reader.read()?,
+            checksum: reader.read()?,
+        })
+    }
+}
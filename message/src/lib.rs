@@ -0,0 +1,21 @@
+//! Messages of the Bitcoin peer-to-peer protocol.
+//!
+//! https://en.bitcoin.it/wiki/Protocol_documentation
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(not(feature = "std"))]
+extern crate alloc;
+
+mod command;
+mod error;
+mod header;
+mod message;
+
+pub mod common;
+pub mod types;
+
+pub use self::command::{Command, COMMAND_SIZE};
+pub use self::error::Error;
+pub use self::header::{MessageHeader, MAX_PAYLOAD_SIZE, MESSAGE_HEADER_SIZE};
+pub use self::message::{Message, NetworkMessage, Payload};
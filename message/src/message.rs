@@ -0,0 +1,329 @@
+//! Message framing and decoding
+
+#[cfg(not(feature = "std"))]
+use alloc::vec;
+
+use light_bitcoin_chain::{Block, Transaction};
+use light_bitcoin_merkle::MerkleBlock;
+use light_bitcoin_primitives::{io, Bytes};
+use light_bitcoin_serialization::{
+    deserialize, serialize_with_flags, Deserializable, Reader, Serializable, Stream,
+    SERIALIZE_TRANSACTION_WITNESS,
+};
+
+use crate::command::Command;
+use crate::common::{BlockTransactions, BlockTransactionsRequest, HeaderAndShortIDs};
+use crate::error::Error;
+use crate::header::{MessageHeader, MAX_PAYLOAD_SIZE};
+use crate::types::{
+    Addr, AddrV2, FeeFilter, GetBlocks, GetCFilters, GetData, GetHeaders, Headers, Inv, NotFound,
+    Ping, Pong, SendAddrV2, SendCompact, SendHeaders, Verack, Version,
+};
+
+/// Payload of a message, identified by its command.
+pub trait Payload: Serializable + Deserializable {
+    /// Command of the messages carrying the payload
+    const COMMAND: &'static str;
+}
+
+/// Message of the peer-to-peer protocol: the header followed by the serialized payload.
+#[derive(Eq, PartialEq, Clone, Debug, Default)]
+pub struct Message {
+    /// The message header
+    pub header: MessageHeader,
+    /// The serialized payload
+    pub payload: Bytes,
+}
+
+impl Message {
+    /// Creates the message of the payload, serializing transactions with their witness.
+    pub fn new<T: Payload>(magic: [u8; 4], payload: &T) -> Self {
+        let payload = serialize_with_flags(payload, SERIALIZE_TRANSACTION_WITNESS);
+        Message::from_raw(magic, T::COMMAND.into(), payload)
+    }
+
+    /// Creates the message of an already serialized payload.
+    pub fn from_raw(magic: [u8; 4], command: Command, payload: Bytes) -> Self {
+        Message {
+            header: MessageHeader::for_payload(magic, command, &payload),
+            payload,
+        }
+    }
+
+    /// Returns the command of the message.
+    pub fn command(&self) -> Command {
+        self.header.command
+    }
+
+    /// Checks the magic of the message and its header against the payload.
+    pub fn check(&self, magic: [u8; 4]) -> Result<(), Error> {
+        if self.header.magic != magic {
+            return Err(Error::InvalidMagic);
+        }
+        if self.payload.len() > MAX_PAYLOAD_SIZE as usize {
+            return Err(Error::OversizedPayload);
+        }
+        if self.header.len as usize != self.payload.len()
+            || self.header.checksum != light_bitcoin_crypto::checksum(&self.payload)
+        {
+            return Err(Error::InvalidChecksum);
+        }
+        Ok(())
+    }
+
+    /// Deserializes the payload, which must be the one of the command of the message.
+    pub fn payload<T: Payload>(&self) -> Result<T, Error> {
+        if self.header.command != *T::COMMAND {
+            return Err(Error::UnexpectedCommand);
+        }
+        deserialize(self.payload.as_ref()).map_err(|_| Error::Deserialize)
+    }
+}
+
+impl Serializable for Message {
+    fn serialize(&self, stream: &mut Stream) {
+        stream.append(&self.header).append_slice(&self.payload);
+    }
+}
+
+impl Deserializable for Message {
+    /// Reads the header and the payload of its length, without checking the checksum.
+    fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, io::Error>
+    where
+        Self: Sized,
+        T: io::Read,
+    {
+        let header: MessageHeader = reader.read()?;
+        if header.len > MAX_PAYLOAD_SIZE {
+            return Err(io::Error::ReadMalformedData);
+        }
+        let mut payload = vec![0u8; header.len as usize];
+        reader.read_slice(&mut payload)?;
+        Ok(Message {
+            header,
+            payload: payload.into(),
+        })
+    }
+}
+
+macro_rules! network_messages {
+    ($($(#[$doc:meta])* $variant:ident($payload:ty),)*) => {
+        /// Message of the peer-to-peer protocol decoded according to its command.
+        #[derive(Eq, PartialEq, Clone, Debug)]
+        pub enum NetworkMessage {
+            $($(#[$doc])* $variant($payload),)*
+            /// Message of an unknown command, kept serialized
+            Unknown {
+                /// The command of the message
+                command: Command,
+                /// The serialized payload
+                payload: Bytes,
+            },
+        }
+
+        impl NetworkMessage {
+            /// Returns the command of the message.
+            pub fn command(&self) -> Command {
+                match self {
+                    $(NetworkMessage::$variant(_) => <$payload as Payload>::COMMAND.into(),)*
+                    NetworkMessage::Unknown { command, .. } => *command,
+                }
+            }
+
+            /// Decodes the payload of the message according to its command.
+            ///
+            /// The message is expected to be checked with `Message::check`.
+            pub fn from_message(message: &Message) -> Result<Self, Error> {
+                $(
+                    if message.header.command == *<$payload as Payload>::COMMAND {
+                        return message.payload().map(NetworkMessage::$variant);
+                    }
+                )*
+                Ok(NetworkMessage::Unknown {
+                    command: message.header.command,
+                    payload: message.payload.clone(),
+                })
+            }
+
+            /// Creates the message with the magic of the network.
+            pub fn to_message(&self, magic: [u8; 4]) -> Message {
+                match self {
+                    $(NetworkMessage::$variant(payload) => Message::new(magic, payload),)*
+                    NetworkMessage::Unknown { command, payload } => {
+                        Message::from_raw(magic, *command, payload.clone())
+                    }
+                }
+            }
+        }
+    };
+}
+
+network_messages! {
+    /// `version`
+    Version(Version),
+    /// `verack`
+    Verack(Verack),
+    /// `ping`
+    Ping(Ping),
+    /// `pong`
+    Pong(Pong),
+    /// `inv`
+    Inv(Inv),
+    /// `getdata`
+    GetData(GetData),
+    /// `notfound`
+    NotFound(NotFound),
+    /// `getheaders`
+    GetHeaders(GetHeaders),
+    /// `headers`
+    Headers(Headers),
+    /// `getblocks`
+    GetBlocks(GetBlocks),
+    /// `block`
+    Block(Block),
+    /// `tx`
+    Tx(Transaction),
+    /// `merkleblock`
+    MerkleBlock(MerkleBlock),
+    /// `sendheaders`
+    SendHeaders(SendHeaders),
+    /// `feefilter`
+    FeeFilter(FeeFilter),
+    /// `addr`
+    Addr(Addr),
+    /// `addrv2`
+    AddrV2(AddrV2),
+    /// `sendaddrv2`
+    SendAddrV2(SendAddrV2),
+    /// `sendcmpct`
+    SendCompact(SendCompact),
+    /// `cmpctblock`
+    CompactBlock(HeaderAndShortIDs),
+    /// `getblocktxn`
+    GetBlockTransactions(BlockTransactionsRequest),
+    /// `blocktxn`
+    BlockTransactions(BlockTransactions),
+    /// `getcfilters`
+    GetCFilters(GetCFilters),
+}
+
+#[cfg(test)]
+mod tests {
+    use light_bitcoin_serialization::serialize;
+
+    use super::*;
+    use crate::common::{InventoryVector, Services};
+
+    const MAINNET: [u8; 4] = [0xf9, 0xbe, 0xb4, 0xd9];
+
+    // https://github.com/rust-bitcoin/rust-bitcoin/blob/master/bitcoin/src/p2p/message.rs
+    #[test]
+    fn test_message_serialization() {
+        assert_eq!(
+            hex::encode(&*serialize(&Message::new(MAINNET, &Verack))),
+            "f9beb4d976657261636b000000000000000000005df6e0e2"
+        );
+        assert_eq!(
+            hex::encode(&*serialize(&Message::new(MAINNET, &Ping { nonce: 100 }))),
+            "f9beb4d970696e670000000000000000080000002467f11d6400000000000000"
+        );
+    }
+
+    #[test]
+    fn test_version_message() {
+        let raw = hex::decode("f9beb4d976657273696f6e000000000066000000be61b8277f1101000d04000000000000f00f4d5c00000000000000000000000000000000000000000000ffff5bf08c80b4bd0d04000000000000000000000000000000000000000000000000faa99559cc68a1c1102f5361746f7368693a302e31372e312f938c080001").unwrap();
+        let message: Message = deserialize(raw.as_slice()).unwrap();
+        assert_eq!(message.check(MAINNET), Ok(()));
+        let version = match NetworkMessage::from_message(&message).unwrap() {
+            NetworkMessage::Version(version) => version,
+            _ => panic!("Wrong message type"),
+        };
+        assert_eq!(version.version, 70015);
+        assert_eq!(
+            version.services,
+            Services::NETWORK
+                .with(Services::BLOOM)
+                .with(Services::WITNESS)
+                .with(Services::NETWORK_LIMITED)
+        );
+        assert_eq!(version.timestamp, 1548554224);
+        assert_eq!(version.nonce, 13952548347456104954);
+        assert_eq!(version.user_agent, "/Satoshi:0.17.1/");
+        assert_eq!(version.start_height, 560275);
+        assert!(version.relay);
+        assert_eq!(
+            &*serialize(&NetworkMessage::Version(version).to_message(MAINNET)),
+            raw.as_slice()
+        );
+    }
+
+    #[test]
+    fn test_message_check() {
+        let message = Message::new(
+            MAINNET,
+            &Inv {
+                inventory: vec![InventoryVector::block(Default::default())],
+            },
+        );
+        assert_eq!(message.check(MAINNET), Ok(()));
+        assert_eq!(
+            message.check([0x0b, 0x11, 0x09, 0x07]),
+            Err(Error::InvalidMagic)
+        );
+        assert_eq!(message.payload::<GetData>(), Err(Error::UnexpectedCommand));
+
+        let mut corrupted = message.clone();
+        corrupted.payload[0] ^= 1;
+        assert_eq!(corrupted.check(MAINNET), Err(Error::InvalidChecksum));
+        let mut truncated = message.clone();
+        truncated.payload = truncated.payload[..10].into();
+        assert_eq!(truncated.check(MAINNET), Err(Error::InvalidChecksum));
+        let mut invalid = message;
+        invalid.payload = vec![0xfe].into();
+        invalid.header = MessageHeader::for_payload(MAINNET, "inv".into(), &invalid.payload);
+        assert_eq!(invalid.check(MAINNET), Ok(()));
+        assert_eq!(
+            NetworkMessage::from_message(&invalid),
+            Err(Error::Deserialize)
+        );
+
+        // The length of the header is bounded
+        let mut raw: Vec<u8> = serialize(&Message::new(MAINNET, &Verack)).into();
+        raw[16..20].copy_from_slice(&(MAX_PAYLOAD_SIZE + 1).to_le_bytes());
+        assert!(deserialize::<_, Message>(raw.as_slice()).is_err());
+    }
+
+    #[test]
+    fn test_network_message_roundtrip() {
+        let messages = vec![
+            NetworkMessage::Verack(Verack),
+            NetworkMessage::Pong(Pong { nonce: 7 }),
+            NetworkMessage::SendHeaders(SendHeaders),
+            NetworkMessage::FeeFilter(FeeFilter { fee_rate: 1000 }),
+            NetworkMessage::SendCompact(SendCompact {
+                announce: true,
+                version: 2,
+            }),
+            NetworkMessage::GetCFilters(GetCFilters {
+                filter_type: 0,
+                start_height: 100,
+                stop_hash: Default::default(),
+            }),
+            NetworkMessage::Tx(Transaction {
+                inputs: vec![Default::default()],
+                ..Default::default()
+            }),
+            NetworkMessage::Unknown {
+                command: "mempool".into(),
+                payload: Bytes::new(),
+            },
+        ];
+        for network_message in messages {
+            let raw = serialize(&network_message.to_message(MAINNET));
+            let message: Message = deserialize(raw.as_ref()).unwrap();
+            assert_eq!(message.check(MAINNET), Ok(()));
+            assert_eq!(message.command(), network_message.command());
+            assert_eq!(NetworkMessage::from_message(&message), Ok(network_message));
+        }
+    }
+}
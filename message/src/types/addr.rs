@@ -0,0 +1,78 @@
+//! `addr`, `addrv2` and `sendaddrv2` messages
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use light_bitcoin_primitives::io;
+use light_bitcoin_serialization::{Deserializable, Reader, Serializable, Stream};
+
+use super::empty_payload;
+use crate::common::{AddressEntry, AddressV2Entry};
+use crate::message::Payload;
+
+/// Maximum number of addresses of an address message.
+pub const MAX_ADDR_SIZE: usize = 1_000;
+
+/// Payload of the `addr` message, announcing the addresses of nodes.
+#[derive(Eq, PartialEq, Clone, Debug, Default)]
+pub struct Addr {
+    /// The addresses
+    pub addresses: Vec<AddressEntry>,
+}
+
+impl Payload for Addr {
+    const COMMAND: &'static str = "addr";
+}
+
+impl Serializable for Addr {
+    fn serialize(&self, stream: &mut Stream) {
+        stream.append_list(&self.addresses);
+    }
+}
+
+impl Deserializable for Addr {
+    fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, io::Error>
+    where
+        Self: Sized,
+        T: io::Read,
+    {
+        Ok(Addr {
+            addresses: reader.read_list_max(MAX_ADDR_SIZE)?,
+        })
+    }
+}
+
+/// Payload of the `addrv2` message, announcing the addresses of nodes of any network (BIP155).
+#[derive(Eq, PartialEq, Clone, Debug, Default)]
+pub struct AddrV2 {
+    /// The addresses
+    pub addresses: Vec<AddressV2Entry>,
+}
+
+impl Payload for AddrV2 {
+    const COMMAND: &'static str = "addrv2";
+}
+
+impl Serializable for AddrV2 {
+    fn serialize(&self, stream: &mut Stream) {
+        stream.append_list(&self.addresses);
+    }
+}
+
+impl Deserializable for AddrV2 {
+    fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, io::Error>
+    where
+        Self: Sized,
+        T: io::Read,
+    {
+        Ok(AddrV2 {
+            addresses: reader.read_list_max(MAX_ADDR_SIZE)?,
+        })
+    }
+}
+
+empty_payload!(
+    /// Payload of the `sendaddrv2` message, asking for addresses in `addrv2` messages (BIP155).
+    SendAddrV2,
+    "sendaddrv2"
+);
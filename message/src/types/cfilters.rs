@@ -0,0 +1,23 @@
+//! `getcfilters` message
+
+use light_bitcoin_primitives::H256;
+use light_bitcoin_serialization::{Deserializable, Serializable};
+
+use crate::message::Payload;
+
+/// Payload of the `getcfilters` message, requesting the compact filters of a range of blocks
+/// (BIP157).
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Default)]
+#[derive(Serializable, Deserializable)]
+pub struct GetCFilters {
+    /// Type of the filters, 0 for basic filters
+    pub filter_type: u8,
+    /// Height of the first block
+    pub start_height: u32,
+    /// Hash of the last block
+    pub stop_hash: H256,
+}
+
+impl Payload for GetCFilters {
+    const COMMAND: &'static str = "getcfilters";
+}
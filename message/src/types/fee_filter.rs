@@ -0,0 +1,18 @@
+//! `feefilter` message
+
+use light_bitcoin_serialization::{Deserializable, Serializable};
+
+use crate::message::Payload;
+
+/// Payload of the `feefilter` message, asking not to announce transactions paying a lower fee
+/// rate (BIP133).
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Default)]
+#[derive(Serializable, Deserializable)]
+pub struct FeeFilter {
+    /// Minimum fee rate, in satoshis per kilobyte
+    pub fee_rate: u64,
+}
+
+impl Payload for FeeFilter {
+    const COMMAND: &'static str = "feefilter";
+}
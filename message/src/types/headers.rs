@@ -0,0 +1,161 @@
+//! `getheaders`, `headers`, `getblocks` and `sendheaders` messages
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use light_bitcoin_chain::BlockHeader;
+use light_bitcoin_primitives::{io, H256};
+use light_bitcoin_serialization::{CompactInteger, Deserializable, Reader, Serializable, Stream};
+
+use super::empty_payload;
+use crate::message::Payload;
+
+/// Maximum number of headers of the `headers` message.
+pub const MAX_HEADERS_SIZE: usize = 2_000;
+/// Maximum number of hashes of a block locator.
+const MAX_LOCATOR_SIZE: usize = 101;
+
+macro_rules! locator_payload {
+    ($(#[$doc:meta])* $name:ident, $command:expr) => {
+        $(#[$doc])*
+        #[derive(Eq, PartialEq, Clone, Debug, Default)]
+        pub struct $name {
+            /// Protocol version of the node
+            pub version: u32,
+            /// Hashes of the best chain of the node, from the tip with growing steps
+            pub block_locator_hashes: Vec<H256>,
+            /// Hash of the last requested block, or zero for as many as possible
+            pub hash_stop: H256,
+        }
+
+        impl Payload for $name {
+            const COMMAND: &'static str = $command;
+        }
+
+        impl Serializable for $name {
+            fn serialize(&self, stream: &mut Stream) {
+                stream
+                    .append(&self.version)
+                    .append_list(&self.block_locator_hashes)
+                    .append(&self.hash_stop);
+            }
+        }
+
+        impl Deserializable for $name {
+            fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, io::Error>
+            where
+                Self: Sized,
+                T: io::Read,
+            {
+                Ok($name {
+                    version: reader.read()?,
+                    block_locator_hashes: reader.read_list_max(MAX_LOCATOR_SIZE)?,
+                    hash_stop: reader.read()?,
+                })
+            }
+        }
+    };
+}
+
+locator_payload!(
+    /// Payload of the `getheaders` message, requesting the headers following the locator.
+    GetHeaders,
+    "getheaders"
+);
+
+locator_payload!(
+    /// Payload of the `getblocks` message, requesting the inventory of the blocks following the
+    /// locator.
+    GetBlocks,
+    "getblocks"
+);
+
+/// Payload of the `headers` message.
+///
+/// Every header is followed by an empty transaction count.
+#[derive(Eq, PartialEq, Clone, Debug, Default)]
+pub struct Headers {
+    /// The headers
+    pub headers: Vec<BlockHeader>,
+}
+
+impl Payload for Headers {
+    const COMMAND: &'static str = "headers";
+}
+
+impl Serializable for Headers {
+    fn serialize(&self, stream: &mut Stream) {
+        stream.append(&CompactInteger::from(self.headers.len()));
+        for header in &self.headers {
+            stream.append(header).append(&CompactInteger::from(0u8));
+        }
+    }
+}
+
+impl Deserializable for Headers {
+    fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, io::Error>
+    where
+        Self: Sized,
+        T: io::Read,
+    {
+        let len: usize = reader.read::<CompactInteger>()?.into();
+        if len > MAX_HEADERS_SIZE {
+            return Err(io::Error::ReadMalformedData);
+        }
+        let mut headers = Vec::with_capacity(len);
+        for _ in 0..len {
+            headers.push(reader.read()?);
+            let tx_count: u64 = reader.read::<CompactInteger>()?.into();
+            if tx_count != 0 {
+                return Err(io::Error::ReadMalformedData);
+            }
+        }
+        Ok(Headers { headers })
+    }
+}
+
+empty_payload!(
+    /// Payload of the `sendheaders` message, asking for new blocks to be announced with
+    /// `headers` (BIP130).
+    SendHeaders,
+    "sendheaders"
+);
+
+#[cfg(test)]
+mod tests {
+    use light_bitcoin_primitives::h256_rev;
+    use light_bitcoin_serialization::{deserialize, serialize};
+
+    use super::*;
+
+    // https://github.com/rust-bitcoin/rust-bitcoin/blob/master/bitcoin/src/p2p/message_blockdata.rs
+    #[test]
+    fn test_getblocks_serialization() {
+        let raw = hex::decode("72110100014a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b0000000000000000000000000000000000000000000000000000000000000000").unwrap();
+        let getblocks: GetBlocks = deserialize(raw.as_slice()).unwrap();
+        assert_eq!(getblocks.version, 70002);
+        assert_eq!(
+            getblocks.block_locator_hashes,
+            vec![h256_rev(
+                "3ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a"
+            )]
+        );
+        assert_eq!(getblocks.hash_stop, H256::default());
+        assert_eq!(&*serialize(&getblocks), raw.as_slice());
+    }
+
+    #[test]
+    fn test_headers_serialization() {
+        let headers = Headers {
+            headers: vec![BlockHeader::default(); 2],
+        };
+        let serialized = serialize(&headers);
+        assert_eq!(serialized.len(), 1 + 2 * 81);
+        assert_eq!(deserialize::<_, Headers>(serialized.as_ref()), Ok(headers));
+
+        // A header followed by transactions
+        let mut raw: Vec<u8> = serialized.into();
+        raw[81] = 1;
+        assert!(deserialize::<_, Headers>(raw.as_slice()).is_err());
+    }
+}
@@ -0,0 +1,64 @@
+//! `inv`, `getdata` and `notfound` messages
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use light_bitcoin_primitives::io;
+use light_bitcoin_serialization::{Deserializable, Reader, Serializable, Stream};
+
+use crate::common::InventoryVector;
+use crate::message::Payload;
+
+/// Maximum number of entries of an inventory message.
+pub const MAX_INV_SIZE: usize = 50_000;
+
+macro_rules! inventory_payload {
+    ($(#[$doc:meta])* $name:ident, $command:expr) => {
+        $(#[$doc])*
+        #[derive(Eq, PartialEq, Clone, Debug, Default)]
+        pub struct $name {
+            /// The inventory vectors
+            pub inventory: Vec<InventoryVector>,
+        }
+
+        impl Payload for $name {
+            const COMMAND: &'static str = $command;
+        }
+
+        impl Serializable for $name {
+            fn serialize(&self, stream: &mut Stream) {
+                stream.append_list(&self.inventory);
+            }
+        }
+
+        impl Deserializable for $name {
+            fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, io::Error>
+            where
+                Self: Sized,
+                T: io::Read,
+            {
+                Ok($name {
+                    inventory: reader.read_list_max(MAX_INV_SIZE)?,
+                })
+            }
+        }
+    };
+}
+
+inventory_payload!(
+    /// Payload of the `inv` message, announcing transactions and blocks.
+    Inv,
+    "inv"
+);
+
+inventory_payload!(
+    /// Payload of the `getdata` message, requesting transactions and blocks.
+    GetData,
+    "getdata"
+);
+
+inventory_payload!(
+    /// Payload of the `notfound` message, answering the `getdata` entries the peer doesn't have.
+    NotFound,
+    "notfound"
+);
@@ -0,0 +1,85 @@
+//! Payloads of the messages
+
+mod addr;
+mod cfilters;
+mod fee_filter;
+mod headers;
+mod inv;
+mod ping;
+mod send_compact;
+mod version;
+
+use light_bitcoin_chain::{Block, Transaction};
+use light_bitcoin_merkle::MerkleBlock;
+
+use crate::common::{BlockTransactions, BlockTransactionsRequest, HeaderAndShortIDs};
+use crate::message::Payload;
+
+pub use self::addr::{Addr, AddrV2, SendAddrV2, MAX_ADDR_SIZE};
+pub use self::cfilters::GetCFilters;
+pub use self::fee_filter::FeeFilter;
+pub use self::headers::{GetBlocks, GetHeaders, Headers, SendHeaders, MAX_HEADERS_SIZE};
+pub use self::inv::{GetData, Inv, NotFound, MAX_INV_SIZE};
+pub use self::ping::{Ping, Pong};
+pub use self::send_compact::SendCompact;
+pub use self::version::{Verack, Version};
+
+/// Defines a payload without content.
+macro_rules! empty_payload {
+    ($(#[$doc:meta])* $name:ident, $command:expr) => {
+        $(#[$doc])*
+        #[derive(Eq, PartialEq, Copy, Clone, Debug, Default)]
+        pub struct $name;
+
+        impl light_bitcoin_serialization::Serializable for $name {
+            fn serialize(&self, _stream: &mut light_bitcoin_serialization::Stream) {}
+
+            #[inline]
+            fn serialized_size(&self) -> usize {
+                0
+            }
+        }
+
+        impl light_bitcoin_serialization::Deserializable for $name {
+            fn deserialize<T>(
+                _reader: &mut light_bitcoin_serialization::Reader<T>,
+            ) -> Result<Self, light_bitcoin_primitives::io::Error>
+            where
+                Self: Sized,
+                T: light_bitcoin_primitives::io::Read,
+            {
+                Ok($name)
+            }
+        }
+
+        impl $crate::message::Payload for $name {
+            const COMMAND: &'static str = $command;
+        }
+    };
+}
+
+use empty_payload;
+
+impl Payload for Block {
+    const COMMAND: &'static str = "block";
+}
+
+impl Payload for Transaction {
+    const COMMAND: &'static str = "tx";
+}
+
+impl Payload for MerkleBlock {
+    const COMMAND: &'static str = "merkleblock";
+}
+
+impl Payload for HeaderAndShortIDs {
+    const COMMAND: &'static str = "cmpctblock";
+}
+
+impl Payload for BlockTransactionsRequest {
+    const COMMAND: &'static str = "getblocktxn";
+}
+
+impl Payload for BlockTransactions {
+    const COMMAND: &'static str = "blocktxn";
+}
@@ -0,0 +1,29 @@
+//! `ping` and `pong` messages
+
+use light_bitcoin_serialization::{Deserializable, Serializable};
+
+use crate::message::Payload;
+
+/// Payload of the `ping` message, checking the connection is alive.
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Default)]
+#[derive(Serializable, Deserializable)]
+pub struct Ping {
+    /// Nonce to send back in the `pong` message
+    pub nonce: u64,
+}
+
+impl Payload for Ping {
+    const COMMAND: &'static str = "ping";
+}
+
+/// Payload of the `pong` message, the answer to a `ping` message.
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Default)]
+#[derive(Serializable, Deserializable)]
+pub struct Pong {
+    /// Nonce of the `ping` message
+    pub nonce: u64,
+}
+
+impl Payload for Pong {
+    const COMMAND: &'static str = "pong";
+}
@@ -0,0 +1,19 @@
+//! `sendcmpct` message
+
+use light_bitcoin_serialization::{Deserializable, Serializable};
+
+use crate::message::Payload;
+
+/// Payload of the `sendcmpct` message, negotiating compact block relay (BIP152).
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Default)]
+#[derive(Serializable, Deserializable)]
+pub struct SendCompact {
+    /// Whether new blocks should be announced with `cmpctblock` messages
+    pub announce: bool,
+    /// Version of the compact blocks: 1 for txids, 2 for wtxids
+    pub version: u64,
+}
+
+impl Payload for SendCompact {
+    const COMMAND: &'static str = "sendcmpct";
+}
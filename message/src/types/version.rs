@@ -0,0 +1,110 @@
+//! `version` and `verack` messages
+
+#[cfg(not(feature = "std"))]
+use alloc::string::String;
+
+use light_bitcoin_primitives::io;
+use light_bitcoin_serialization::{Deserializable, Reader, Serializable, Stream};
+
+use super::empty_payload;
+use crate::common::{NetAddress, Services};
+use crate::message::Payload;
+
+/// Payload of the `version` message, the first message sent to a peer.
+#[derive(Eq, PartialEq, Clone, Debug, Default)]
+pub struct Version {
+    /// Protocol version of the node
+    pub version: u32,
+    /// Services of the node
+    pub services: Services,
+    /// Current time of the node
+    pub timestamp: i64,
+    /// Address of the peer
+    pub receiver: NetAddress,
+    /// Address of the node
+    pub sender: NetAddress,
+    /// Random nonce detecting connections to self
+    pub nonce: u64,
+    /// User agent of the node (BIP14)
+    pub user_agent: String,
+    /// Height of the best chain of the node
+    pub start_height: i32,
+    /// Whether the peer should announce transactions before a `filterload` (BIP37)
+    pub relay: bool,
+}
+
+impl Payload for Version {
+    const COMMAND: &'static str = "version";
+}
+
+impl Serializable for Version {
+    fn serialize(&self, stream: &mut Stream) {
+        stream
+            .append(&self.version)
+            .append(&self.services)
+            .append(&self.timestamp)
+            .append(&self.receiver)
+            .append(&self.sender)
+            .append(&self.nonce)
+            .append(&self.user_agent)
+            .append(&self.start_height)
+            .append(&self.relay);
+    }
+}
+
+impl Deserializable for Version {
+    /// The relay flag is optional and defaults to true.
+    fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, io::Error>
+    where
+        Self: Sized,
+        T: io::Read,
+    {
+        Ok(Version {
+            version: reader.read()?,
+            services: reader.read()?,
+            timestamp: reader.read()?,
+            receiver: reader.read()?,
+            sender: reader.read()?,
+            nonce: reader.read()?,
+            user_agent: reader.read()?,
+            start_height: reader.read()?,
+            relay: if reader.is_finished() {
+                true
+            } else {
+                reader.read()?
+            },
+        })
+    }
+}
+
+empty_payload!(
+    /// Payload of the `verack` message, acknowledging the `version` message of the peer.
+    Verack,
+    "verack"
+);
+
+#[cfg(test)]
+mod tests {
+    use light_bitcoin_serialization::{deserialize, serialize};
+
+    use super::*;
+
+    // https://github.com/rust-bitcoin/rust-bitcoin/blob/master/bitcoin/src/p2p/message_network.rs
+    #[test]
+    fn test_version_serialization() {
+        let raw = hex::decode("721101000100000000000000e6e0845300000000010000000000000000000000000000000000ffff0000000000000100000000000000fd87d87eeb4364f22cf54dca59412db7208d47d920cffce83ee8102f5361746f7368693a302e392e39392f2c9f040001").unwrap();
+        let version: Version = deserialize(raw.as_slice()).unwrap();
+        assert_eq!(version.version, 70002);
+        assert_eq!(version.services, Services::NETWORK);
+        assert_eq!(version.timestamp, 1401217254);
+        assert_eq!(version.nonce, 16735069437859780935);
+        assert_eq!(version.user_agent, "/Satoshi:0.9.99/");
+        assert_eq!(version.start_height, 302892);
+        assert!(version.relay);
+        assert_eq!(&*serialize(&version), raw.as_slice());
+
+        // Without the relay flag
+        let version: Version = deserialize(&raw[..raw.len() - 1]).unwrap();
+        assert!(version.relay);
+    }
+}
@@ -68,6 +68,37 @@ impl Compact {
         }
     }
 
+    /// Returns the target, or `None` if the encoding is negative, overflows or is zero.
+    pub fn target(self) -> Option<U256> {
+        match self.to_u256() {
+            Ok(target) if !target.is_zero() => Some(target),
+            _ => None,
+        }
+    }
+
+    /// Returns true if the value lands in [0, target].
+    ///
+    /// Invalid encodings (see `target`) are never met, unlike the raw `Ord` of the encoding
+    /// which doesn't order targets.
+    pub fn is_met_by(self, value: &U256) -> bool {
+        match self.target() {
+            Some(target) => *value <= target,
+            None => false,
+        }
+    }
+
+    /// Returns the expected number of hashes needed to meet the target: `2^256 / (target + 1)`.
+    ///
+    /// Invalid encodings have no work.
+    pub fn work(self) -> U256 {
+        match self.target() {
+            // 2^256 doesn't fit, but `2^256 / (t + 1) == (!t / (t + 1)) + 1`
+            Some(target) if target != U256::max_value() => (!target / (target + 1)) + 1,
+            Some(_) => 1.into(),
+            None => U256::zero(),
+        }
+    }
+
     pub fn from_u256(val: U256) -> Self {
         let mut size = (val.bits() + 7) / 8;
         let mut compact = if size <= 3 {
@@ -117,6 +148,24 @@ mod tests {
         assert_eq!(Compact::new(0x04123456).to_u256(), Ok(0x12345600u64.into()));
     }
 
+    #[test]
+    fn test_target_and_work() {
+        let compact = Compact::new(0x1d00ffff);
+        let target = U256::from(0xffff) << 208;
+        assert_eq!(compact.target(), Some(target));
+        assert_eq!(compact.work(), U256::from(0x0001_0001_0001u64));
+        assert!(compact.is_met_by(&target));
+        assert!(!compact.is_met_by(&(target + 1)));
+
+        // negative, overflowing and zero targets are never met
+        for bits in [0x04923456, 0xff123456, 0x01003456, 0x00000000].iter() {
+            let compact = Compact::new(*bits);
+            assert_eq!(compact.target(), None);
+            assert!(!compact.is_met_by(&U256::zero()));
+            assert_eq!(compact.work(), U256::zero());
+        }
+    }
+
     #[test]
     fn test_from_u256() {
         let test1 = U256::from(1000u64);
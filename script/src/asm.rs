@@ -0,0 +1,366 @@
+//! Human-readable script assembly.
+//!
+//! The format is the one of Bitcoin Core's `ParseScript`, the tokens being separated by
+//! whitespace:
+//!
+//! - opcode names, with or without the `OP_` prefix (`OP_DUP`, `DUP`, `OP_PUSHDATA1`);
+//! - decimal numbers in [-0xffffffff, 0xffffffff], pushed as script numbers (`-1`, `0` and `1`
+//!   to `16` are `OP_1NEGATE`, `OP_0` and `OP_1` to `OP_16`);
+//! - `0x`-prefixed hex, inserted as raw bytes (`0x14 0x89ab...`);
+//! - single-quoted strings without whitespace, pushed as data (`'Abc'`).
+//!
+//! `Script::to_asm` prints the assembly of Core's `ScriptToAsmStr` (the `asm` of `decodescript`),
+//! which writes the data of pushes without their push opcodes and so can't always be parsed back.
+//! `Script::to_parseable_asm` keeps every byte so that `Script::from_asm` gives back the same
+//! script.
+
+#[cfg(not(feature = "std"))]
+use alloc::{format, string::String, vec};
+use core::fmt::{self, Write};
+
+use crate::builder::Builder;
+use crate::num::Num;
+use crate::opcode::Opcode;
+use crate::script::Script;
+
+/// Maximum absolute value of the decimal numbers of the assembly.
+const MAX_ASM_NUMBER: i64 = 0xffff_ffff;
+
+/// Errors of the script assembler, with the byte position of the invalid token in the input.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum AsmError {
+    /// The token is not an opcode name, a number, hex bytes or a quoted string.
+    UnknownToken(usize),
+    /// The decimal number is out of the [-0xffffffff, 0xffffffff] range.
+    NumberOutOfRange(usize),
+    /// The `0x`-prefixed bytes are not an even number of hex digits.
+    InvalidHex(usize),
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for AsmError {}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AsmError::UnknownToken(position) => write!(f, "Unknown token at {}", position),
+            AsmError::NumberOutOfRange(position) => {
+                write!(f, "Number out of range at {}", position)
+            }
+            AsmError::InvalidHex(position) => write!(f, "Invalid hex at {}", position),
+        }
+    }
+}
+
+impl Script {
+    /// Parses the human-readable assembly of a script.
+    pub fn from_asm(asm: &str) -> Result<Script, AsmError> {
+        let mut builder = Builder::default();
+        for (position, token) in tokens(asm) {
+            builder = parse_token(builder, position, token)?;
+        }
+        Ok(builder.into_script())
+    }
+
+    /// Returns the human-readable assembly of the script, as Bitcoin Core's `ScriptToAsmStr`.
+    ///
+    /// Pushes of up to 4 bytes are written as decimal numbers, even when they are not minimally
+    /// encoded, and longer pushes as their bare hex data. `OP_0`, `OP_1NEGATE` and `OP_1` to
+    /// `OP_16` are written as `0`, `-1` and `1` to `16`. Undefined opcodes are written as
+    /// `OP_UNKNOWN`, and a truncated push ends the assembly with `[error]`.
+    pub fn to_asm(&self) -> String {
+        let mut asm = String::new();
+        let mut pc = 0;
+        while pc < self.len() {
+            if !asm.is_empty() {
+                asm.push(' ');
+            }
+            let instruction = match self.get_instruction(pc) {
+                Ok(instruction) => instruction,
+                Err(_) if Opcode::from_u8(self[pc]).is_none() => {
+                    asm.push_str("OP_UNKNOWN");
+                    pc += 1;
+                    continue;
+                }
+                Err(_) => {
+                    asm.push_str("[error]");
+                    break;
+                }
+            };
+
+            let opcode = instruction.opcode;
+            match instruction.data {
+                Some(data) if data.len() <= 4 => {
+                    let number =
+                        Num::from_slice(data, false, 4).expect("data is up to 4 bytes; qed");
+                    let _ = write!(asm, "{}", i64::from(number));
+                }
+                Some(data) => write_hex(&mut asm, data),
+                None if opcode == Opcode::OP_1NEGATE => asm.push_str("-1"),
+                None if opcode.is_within_op_n() => {
+                    let _ = write!(asm, "{}", opcode.decode_op_n());
+                }
+                // Core knows 0xba as the tapscript opcode only
+                None if opcode == Opcode::OP_CHECKSIGADD => asm.push_str("OP_CHECKSIGADD"),
+                None if opcode > Opcode::OP_CHECKSIGADD => asm.push_str("OP_UNKNOWN"),
+                None => {
+                    let _ = write!(asm, "{:?}", opcode);
+                }
+            }
+            pc += instruction.step;
+        }
+        asm
+    }
+
+    /// Returns the human-readable assembly of the script, which `Script::from_asm` parses back.
+    ///
+    /// Data pushes are written as their hex push opcode and data (`0x02 0xabcd`), or after the
+    /// `OP_PUSHDATA*` name and the hex length (`OP_PUSHDATA1 0x4c 0xabcd...`). Pushes of minimally
+    /// encoded numbers of up to 4 bytes, other than -1 and 1 to 16, are written in decimal.
+    /// Unknown opcodes and truncated pushes are written as hex.
+    pub fn to_parseable_asm(&self) -> String {
+        let mut asm = String::new();
+        let mut pc = 0;
+        while pc < self.len() {
+            if !asm.is_empty() {
+                asm.push(' ');
+            }
+            let instruction = match self.get_instruction(pc) {
+                Ok(instruction) => instruction,
+                Err(_) => {
+                    // Unknown opcodes are skipped, a truncated push ends the script
+                    let end = match Opcode::from_u8(self[pc]) {
+                        None => pc + 1,
+                        Some(_) => self.len(),
+                    };
+                    write_prefixed_hex(&mut asm, &self[pc..end]);
+                    pc = end;
+                    continue;
+                }
+            };
+
+            let opcode = instruction.opcode;
+            match instruction.data {
+                Some(data) if opcode >= Opcode::OP_PUSHDATA1 && opcode <= Opcode::OP_PUSHDATA4 => {
+                    let _ = write!(asm, "{:?} ", opcode);
+                    write_prefixed_hex(&mut asm, &self[pc + 1..pc + instruction.step - data.len()]);
+                    if !data.is_empty() {
+                        asm.push(' ');
+                        write_prefixed_hex(&mut asm, data);
+                    }
+                }
+                Some(data) if opcode != Opcode::OP_0 => match asm_number(data) {
+                    Some(number) => {
+                        let _ = write!(asm, "{}", number);
+                    }
+                    None => {
+                        write_prefixed_hex(&mut asm, &[opcode as u8]);
+                        asm.push(' ');
+                        write_prefixed_hex(&mut asm, data);
+                    }
+                },
+                _ if opcode == Opcode::OP_0 => asm.push('0'),
+                _ if opcode == Opcode::OP_1NEGATE => asm.push_str("-1"),
+                _ if opcode.is_within_op_n() => {
+                    let _ = write!(asm, "{}", opcode.decode_op_n());
+                }
+                _ => {
+                    let _ = write!(asm, "{:?}", opcode);
+                }
+            }
+            pc += instruction.step;
+        }
+        asm
+    }
+}
+
+/// Splits the assembly into tokens, with their byte position.
+fn tokens(asm: &str) -> impl Iterator<Item = (usize, &str)> {
+    asm.split(|c: char| c.is_ascii_whitespace())
+        .filter(|token| !token.is_empty())
+        .map(move |token| (token.as_ptr() as usize - asm.as_ptr() as usize, token))
+}
+
+/// Appends the token at the position to the script.
+fn parse_token(builder: Builder, position: usize, token: &str) -> Result<Builder, AsmError> {
+    let digits = token.strip_prefix('-').unwrap_or(token);
+    if !digits.is_empty() && digits.bytes().all(|c| c.is_ascii_digit()) {
+        let number = token
+            .parse::<i64>()
+            .ok()
+            .filter(|number| number.abs() <= MAX_ASM_NUMBER)
+            .ok_or(AsmError::NumberOutOfRange(position))?;
+        return Ok(match number {
+            -1 => builder.push_opcode(Opcode::OP_1NEGATE),
+            0 => builder.push_opcode(Opcode::OP_0),
+            1..=16 => builder.push_opcode(
+                Opcode::from_u8(Opcode::OP_1 as u8 + number as u8 - 1)
+                    .expect("number is within [1; 16]; qed"),
+            ),
+            _ => builder.push_num(Num::from(number)),
+        });
+    }
+
+    if let Some(hex) = token.strip_prefix("0x") {
+        let mut bytes = vec![0u8; hex.len() / 2];
+        if hex.is_empty() || hex::decode_to_slice(hex, &mut bytes).is_err() {
+            return Err(AsmError::InvalidHex(position));
+        }
+        return Ok(builder.push_raw(&bytes));
+    }
+
+    if token.len() >= 2 && token.starts_with('\'') && token.ends_with('\'') {
+        return Ok(builder.push_data(&token.as_bytes()[1..token.len() - 1]));
+    }
+
+    opcode_by_name(token)
+        .map(|opcode| builder.push_opcode(opcode))
+        .ok_or(AsmError::UnknownToken(position))
+}
+
+/// Returns the opcode of the name, with or without the `OP_` prefix.
+fn opcode_by_name(name: &str) -> Option<Opcode> {
+    let name = name.strip_prefix("OP_").unwrap_or(name);
+    match name {
+        "NOP2" => return Some(Opcode::OP_CHECKLOCKTIMEVERIFY),
+        "NOP3" => return Some(Opcode::OP_CHECKSEQUENCEVERIFY),
+        "CHECKSIGADD" => return Some(Opcode::OP_CHECKSIGADD),
+        "TRUE" => return Some(Opcode::OP_1),
+        "FALSE" => return Some(Opcode::OP_0),
+        _ => {}
+    }
+    (0..=u8::MAX)
+        .filter_map(Opcode::from_u8)
+        .find(|opcode| format!("{:?}", opcode)[3..] == *name)
+}
+
+/// Returns the number pushed by the data if it is written in decimal.
+fn asm_number(data: &[u8]) -> Option<i64> {
+    let number = i64::from(Num::from_slice(data, true, 4).ok()?);
+    match number {
+        -1 | 1..=16 => None,
+        _ => Some(number),
+    }
+}
+
+/// Appends the hex of the bytes.
+fn write_hex(asm: &mut String, bytes: &[u8]) {
+    for byte in bytes {
+        let _ = write!(asm, "{:02x}", byte);
+    }
+}
+
+/// Appends the `0x`-prefixed hex of the bytes.
+fn write_prefixed_hex(asm: &mut String, bytes: &[u8]) {
+    asm.push_str("0x");
+    write_hex(asm, bytes);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // https://github.com/bitcoin/bitcoin/blob/master/src/test/data/script_tests.json
+    #[test]
+    fn test_from_asm() {
+        let vectors = [
+            ("0", "00"),
+            ("-1 1 16", "4f5160"),
+            ("17", "0111"),
+            ("-2", "0182"),
+            ("2147483647", "04ffffff7f"),
+            ("4294967295", "05ffffffff00"),
+            ("'Az'", "02417a"),
+            ("0x4c 0x01 0x07", "4c0107"),
+            ("OP_PUSHDATA2 0x0100 0x07", "4d010007"),
+            (
+                "DUP HASH160 0x14 0x89abcdefabbaabbaabbaabbaabbaabbaabbaabba EQUALVERIFY CHECKSIG",
+                "76a91489abcdefabbaabbaabbaabbaabbaabbaabbaabba88ac",
+            ),
+            ("NOP2 OP_NOP3 OP_CHECKLOCKTIMEVERIFY", "b1b2b1"),
+            ("  OP_1\t\nOP_RETURN  ", "516a"),
+            ("", ""),
+        ];
+        for (asm, hex) in vectors.iter() {
+            let script = Script::from_asm(asm).unwrap();
+            assert_eq!(script, hex.parse().unwrap(), "{}", asm);
+        }
+
+        assert_eq!(Script::from_asm("DUP FOO"), Err(AsmError::UnknownToken(4)));
+        assert_eq!(
+            Script::from_asm("1 4294967296"),
+            Err(AsmError::NumberOutOfRange(2))
+        );
+        assert_eq!(
+            Script::from_asm("1 -4294967296"),
+            Err(AsmError::NumberOutOfRange(2))
+        );
+        assert_eq!(Script::from_asm("0x"), Err(AsmError::InvalidHex(0)));
+        assert_eq!(Script::from_asm("1 0xabc"), Err(AsmError::InvalidHex(2)));
+        assert_eq!(Script::from_asm("0xzz"), Err(AsmError::InvalidHex(0)));
+        assert_eq!(Script::from_asm("'a"), Err(AsmError::UnknownToken(0)));
+    }
+
+    // https://github.com/bitcoin/bitcoin/blob/master/src/core_write.cpp
+    #[test]
+    fn test_to_asm() {
+        let vectors = [
+            ("", ""),
+            ("00", "0"),
+            ("4f5160", "-1 1 16"),
+            // Short pushes are numbers, whether minimally encoded or not
+            ("0105", "5"),
+            ("0180", "0"),
+            ("0182", "-2"),
+            ("04ffffff7f", "2147483647"),
+            ("04ffffffff", "-2147483647"),
+            ("4c00", "0"),
+            ("4c0107", "7"),
+            ("05ffffffff00", "ffffffff00"),
+            (
+                "76a91489abcdefabbaabbaabbaabbaabbaabbaabbaabba88ac",
+                "OP_DUP OP_HASH160 89abcdefabbaabbaabbaabbaabbaabbaabbaabba OP_EQUALVERIFY OP_CHECKSIG",
+            ),
+            ("b1b2", "OP_CHECKLOCKTIMEVERIFY OP_CHECKSEQUENCEVERIFY"),
+            ("ba", "OP_CHECKSIGADD"),
+            // Unknown opcodes and truncated push
+            ("bbff51", "OP_UNKNOWN OP_UNKNOWN 1"),
+            ("51030102", "1 [error]"),
+        ];
+        for (hex, asm) in vectors.iter() {
+            let script: Script = hex.parse().unwrap();
+            assert_eq!(script.to_asm(), *asm, "{}", hex);
+        }
+    }
+
+    #[test]
+    fn test_to_parseable_asm_roundtrip() {
+        let vectors = [
+            ("", ""),
+            ("00", "0"),
+            ("4f5160", "-1 1 16"),
+            // Pushes of -1 and 1 to 16 are not minimal
+            ("0105", "0x01 0x05"),
+            ("0111", "17"),
+            ("0180", "0x01 0x80"),
+            ("04ffffff7f", "2147483647"),
+            ("05ffffffff00", "0x05 0xffffffff00"),
+            ("4c00", "OP_PUSHDATA1 0x00"),
+            ("4c0107", "OP_PUSHDATA1 0x01 0x07"),
+            ("4e0100000007", "OP_PUSHDATA4 0x01000000 0x07"),
+            (
+                "76a91489abcdefabbaabbaabbaabbaabbaabbaabbaabba88ac",
+                "OP_DUP OP_HASH160 0x14 0x89abcdefabbaabbaabbaabbaabbaabbaabbaabba OP_EQUALVERIFY OP_CHECKSIG",
+            ),
+            ("b1b2", "OP_CHECKLOCKTIMEVERIFY OP_CHECKSEQUENCEVERIFY"),
+            // Unknown opcode and truncated push
+            ("ff51", "0xff 1"),
+            ("51030102", "1 0x030102"),
+        ];
+        for (hex, asm) in vectors.iter() {
+            let script: Script = hex.parse().unwrap();
+            assert_eq!(script.to_parseable_asm(), *asm);
+            assert_eq!(Script::from_asm(asm), Ok(script));
+        }
+    }
+}
@@ -0,0 +1,331 @@
+//! BIP37 bloom filters
+//!
+//! https://github.com/bitcoin/bips/blob/master/bip-0037.mediawiki
+
+#[cfg(not(feature = "std"))]
+use alloc::{vec, vec::Vec};
+
+use light_bitcoin_chain::{OutPoint, Transaction};
+use light_bitcoin_crypto::murmur3;
+use light_bitcoin_primitives::io;
+use light_bitcoin_serialization::{serialize, Deserializable, Reader, Serializable, Stream};
+
+use crate::script::{Script, ScriptType};
+
+/// Maximum size of the filter in bytes (36,000 bytes).
+pub const MAX_BLOOM_FILTER_SIZE: usize = 36_000;
+/// Maximum number of hash functions of the filter.
+pub const MAX_HASH_FUNCS: u32 = 50;
+
+/// The filter is not updated when an output matches.
+pub const BLOOM_UPDATE_NONE: u8 = 0;
+/// The outpoint of every matched output is added to the filter.
+pub const BLOOM_UPDATE_ALL: u8 = 1;
+/// Only the outpoints of matched pay-to-pubkey and multisig outputs are added to the filter.
+pub const BLOOM_UPDATE_P2PUBKEY_ONLY: u8 = 2;
+/// Bits of the flags holding the update mode.
+pub const BLOOM_UPDATE_MASK: u8 = 3;
+
+/// Multiplier of the hash function index in the murmur3 seed.
+const SEED_MULTIPLIER: u32 = 0xfba4_c795;
+
+/// BIP37 bloom filter, the payload of the `filterload` message.
+///
+/// Peers relay only the transactions relevant to the filter, and build merkle blocks of the
+/// matched transactions.
+#[derive(Eq, PartialEq, Clone, Debug, Default)]
+pub struct BloomFilter {
+    /// Bit field of the filter
+    pub data: Vec<u8>,
+    /// Number of hash functions
+    pub hash_funcs: u32,
+    /// Random value added to the seeds of the hash functions
+    pub tweak: u32,
+    /// `BLOOM_UPDATE_*` flags
+    pub flags: u8,
+}
+
+impl BloomFilter {
+    /// Creates the filter of the optimal size for the number of elements and the false positive
+    /// rate, within the protocol limits.
+    #[cfg(feature = "std")]
+    pub fn new(elements: u32, fp_rate: f64, tweak: u32, flags: u8) -> Self {
+        let ln2 = core::f64::consts::LN_2;
+        let elements = f64::from(elements.max(1));
+        let size = (-1.0 / (ln2 * ln2) * elements * fp_rate.ln() / 8.0)
+            .clamp(1.0, MAX_BLOOM_FILTER_SIZE as f64) as usize;
+        let hash_funcs = ((size * 8) as f64 / elements * ln2).clamp(1.0, MAX_HASH_FUNCS as f64);
+        BloomFilter {
+            data: vec![0; size],
+            hash_funcs: hash_funcs as u32,
+            tweak,
+            flags,
+        }
+    }
+
+    /// Creates an empty filter of `size` bytes and `hash_funcs` hash functions.
+    pub fn with_size(size: usize, hash_funcs: u32, tweak: u32, flags: u8) -> Self {
+        BloomFilter {
+            data: vec![0; size],
+            hash_funcs,
+            tweak,
+            flags,
+        }
+    }
+
+    /// Returns true if the filter respects the size limits of the protocol.
+    pub fn is_within_size_constraints(&self) -> bool {
+        self.data.len() <= MAX_BLOOM_FILTER_SIZE && self.hash_funcs <= MAX_HASH_FUNCS
+    }
+
+    fn bit_index(&self, n: u32, data: &[u8]) -> usize {
+        let seed = n.wrapping_mul(SEED_MULTIPLIER).wrapping_add(self.tweak);
+        murmur3(seed, data) as usize % (self.data.len() * 8)
+    }
+
+    /// Adds the data to the filter.
+    pub fn insert(&mut self, data: &[u8]) {
+        if self.data.is_empty() {
+            return;
+        }
+        for n in 0..self.hash_funcs {
+            let index = self.bit_index(n, data);
+            self.data[index >> 3] |= 1 << (index & 7);
+        }
+    }
+
+    /// Returns true if the data may have been added to the filter.
+    ///
+    /// An empty filter matches everything.
+    pub fn contains(&self, data: &[u8]) -> bool {
+        if self.data.is_empty() {
+            return true;
+        }
+        (0..self.hash_funcs).all(|n| {
+            let index = self.bit_index(n, data);
+            self.data[index >> 3] & (1 << (index & 7)) != 0
+        })
+    }
+
+    /// Adds the serialized outpoint to the filter.
+    pub fn insert_outpoint(&mut self, outpoint: &OutPoint) {
+        self.insert(&serialize(outpoint));
+    }
+
+    /// Returns true if the serialized outpoint may have been added to the filter.
+    pub fn contains_outpoint(&self, outpoint: &OutPoint) -> bool {
+        self.contains(&serialize(outpoint))
+    }
+
+    /// Returns true if the transaction matches the filter, adding the outpoints of the matched
+    /// outputs according to the update flags.
+    ///
+    /// The transaction matches if the filter contains its txid, a data push of one of its output
+    /// scripts, the outpoint spent by one of its inputs or a data push of one of its input
+    /// scripts.
+    pub fn is_relevant_and_update(&mut self, tx: &Transaction) -> bool {
+        if self.data.is_empty() {
+            return true;
+        }
+        let txid = tx.hash();
+        let mut found = self.contains(txid.as_bytes());
+
+        for (index, output) in tx.outputs.iter().enumerate() {
+            let script: Script = output.script_pubkey.clone().into();
+            if !self.contains_push(&script) {
+                continue;
+            }
+            found = true;
+            let update = match self.flags & BLOOM_UPDATE_MASK {
+                BLOOM_UPDATE_ALL => true,
+                BLOOM_UPDATE_P2PUBKEY_ONLY => matches!(
+                    script.script_type(),
+                    ScriptType::PubKey | ScriptType::Multisig
+                ),
+                _ => false,
+            };
+            if update {
+                self.insert_outpoint(&OutPoint {
+                    txid,
+                    index: index as u32,
+                });
+            }
+        }
+        if found {
+            return true;
+        }
+
+        tx.inputs.iter().any(|input| {
+            self.contains_outpoint(&input.previous_output)
+                || self.contains_push(&input.script_sig.clone().into())
+        })
+    }
+
+    /// Returns true if the filter contains one of the non-empty data pushes of the script.
+    fn contains_push(&self, script: &Script) -> bool {
+        script
+            .iter()
+            .map_while(Result::ok)
+            .filter_map(|instruction| instruction.data)
+            .any(|data| !data.is_empty() && self.contains(data))
+    }
+}
+
+impl Serializable for BloomFilter {
+    fn serialize(&self, stream: &mut Stream) {
+        stream
+            .append_list(&self.data)
+            .append(&self.hash_funcs)
+            .append(&self.tweak)
+            .append(&self.flags);
+    }
+}
+
+impl Deserializable for BloomFilter {
+    fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, io::Error>
+    where
+        Self: Sized,
+        T: io::Read,
+    {
+        Ok(BloomFilter {
+            data: reader.read_list()?,
+            hash_funcs: reader.read()?,
+            tweak: reader.read()?,
+            flags: reader.read()?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use light_bitcoin_chain::{TransactionInput, TransactionOutput};
+    use light_bitcoin_keys::{KeyPair, Private};
+    use light_bitcoin_primitives::{h256_rev, H256};
+    use light_bitcoin_serialization::deserialize;
+
+    use super::*;
+    use crate::builder::Builder;
+
+    // https://github.com/bitcoin/bitcoin/blob/master/src/test/bloom_tests.cpp
+    #[test]
+    fn test_bloom_create_insert_serialize() {
+        for (tweak, expected) in [
+            (0, "03614e9b050000000000000001"),
+            (2_147_483_649, "03ce4299050000000100008001"),
+        ]
+        .iter()
+        {
+            let mut filter = BloomFilter::new(3, 0.01, *tweak, BLOOM_UPDATE_ALL);
+            assert!(filter.is_within_size_constraints());
+
+            let first = hex::decode("99108ad8ed9bb6274d3980bab5a85c048f0950c8").unwrap();
+            filter.insert(&first);
+            assert!(filter.contains(&first));
+            // One bit different in the first byte
+            let other = hex::decode("19108ad8ed9bb6274d3980bab5a85c048f0950c8").unwrap();
+            assert!(!filter.contains(&other));
+            let second = hex::decode("b5a2c786d9ef4658287ced5914b37a1b4aa32eee").unwrap();
+            filter.insert(&second);
+            assert!(filter.contains(&second));
+            let third = hex::decode("b9300670b4c5366e95b2699e8b18bc75e5f729c5").unwrap();
+            filter.insert(&third);
+            assert!(filter.contains(&third));
+
+            let serialized = serialize(&filter);
+            assert_eq!(hex::encode(&*serialized), *expected);
+            let deserialized: BloomFilter = deserialize(serialized.as_ref()).unwrap();
+            assert_eq!(deserialized, filter);
+        }
+
+        let empty = BloomFilter::default();
+        assert!(empty.contains(b"anything"));
+    }
+
+    fn payment(previous_output: OutPoint, script_pubkey: Script) -> Transaction {
+        Transaction {
+            version: 1,
+            inputs: vec![TransactionInput {
+                previous_output,
+                script_sig: Builder::default()
+                    .push_data(&[0x30; 71])
+                    .into_script()
+                    .to_bytes(),
+                sequence: 0xffff_ffff,
+                script_witness: vec![],
+            }],
+            outputs: vec![TransactionOutput {
+                value: 50_000,
+                script_pubkey: script_pubkey.to_bytes(),
+            }],
+            lock_time: 0,
+        }
+    }
+
+    #[test]
+    fn test_bloom_match_and_update() {
+        let keypair = KeyPair::from_private(Private {
+            network: Default::default(),
+            secret: H256::repeat_byte(1),
+            compressed: true,
+        })
+        .unwrap();
+        let public = keypair.public();
+        let p2pkh = Builder::build_p2pkh(&public.address_hash());
+        let p2pk = Builder::default()
+            .push_data(public)
+            .push_opcode(crate::Opcode::OP_CHECKSIG)
+            .into_script();
+
+        let funding_outpoint = OutPoint {
+            txid: h256_rev("0000000000000000000000000000000000000000000000000000000000000001"),
+            index: 0,
+        };
+        let funding = payment(funding_outpoint, p2pkh.clone());
+        let spending = payment(
+            OutPoint {
+                txid: funding.hash(),
+                index: 0,
+            },
+            Builder::build_nulldata(b"unrelated"),
+        );
+
+        // The txid matches
+        let mut filter = BloomFilter::new(10, 0.000_001, 0, BLOOM_UPDATE_ALL);
+        filter.insert(funding.hash().as_bytes());
+        assert!(filter.is_relevant_and_update(&funding));
+        assert!(!filter.is_relevant_and_update(&spending));
+
+        // The key hash in the output script matches, its outpoint is added
+        let mut filter = BloomFilter::new(10, 0.000_001, 0, BLOOM_UPDATE_ALL);
+        filter.insert(public.address_hash().as_bytes());
+        assert!(filter.is_relevant_and_update(&funding));
+        assert!(filter.is_relevant_and_update(&spending));
+
+        // Only outpoints of pay-to-pubkey outputs are added
+        let mut filter = BloomFilter::new(10, 0.000_001, 0, BLOOM_UPDATE_P2PUBKEY_ONLY);
+        filter.insert(public.address_hash().as_bytes());
+        assert!(filter.is_relevant_and_update(&funding));
+        assert!(!filter.is_relevant_and_update(&spending));
+        filter.insert(public);
+        let funding_p2pk = payment(funding_outpoint, p2pk);
+        assert!(filter.is_relevant_and_update(&funding_p2pk));
+        assert!(filter.contains_outpoint(&OutPoint {
+            txid: funding_p2pk.hash(),
+            index: 0,
+        }));
+
+        // Nothing is added without update
+        let mut filter = BloomFilter::new(10, 0.000_001, 0, BLOOM_UPDATE_NONE);
+        filter.insert(public.address_hash().as_bytes());
+        assert!(filter.is_relevant_and_update(&funding));
+        assert!(!filter.is_relevant_and_update(&spending));
+
+        // The spent outpoint and the input script pushes match
+        let mut filter = BloomFilter::new(10, 0.000_001, 0, BLOOM_UPDATE_NONE);
+        filter.insert_outpoint(&funding_outpoint);
+        assert!(filter.is_relevant_and_update(&funding));
+        let mut filter = BloomFilter::new(10, 0.000_001, 0, BLOOM_UPDATE_NONE);
+        filter.insert(&[0x30; 71]);
+        assert!(filter.is_relevant_and_update(&spending));
+    }
+}
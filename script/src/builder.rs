@@ -1,11 +1,15 @@
 //! Script builder
 
-use light_bitcoin_keys::AddressHash;
-use light_bitcoin_primitives::Bytes;
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
 
+use light_bitcoin_keys::{Address, AddressHash, AddressTypes, Public, Type};
+use light_bitcoin_primitives::{Bytes, H256};
+
+use crate::error::Error;
 use crate::num::Num;
 use crate::opcode::Opcode;
-use crate::script::Script;
+use crate::script::{Script, MAX_PUBKEYS_PER_MULTISIG};
 
 /// Script builder
 #[derive(Ord, PartialOrd, Eq, PartialEq, Clone, Debug, Hash, Default)]
@@ -34,6 +38,77 @@ impl Builder {
             .into_script()
     }
 
+    /// Builds p2wpkh script pubkey
+    pub fn build_p2wpkh(address: &AddressHash) -> Script {
+        Builder::default()
+            .push_opcode(Opcode::OP_0)
+            .push_bytes(address.as_bytes())
+            .into_script()
+    }
+
+    /// Builds p2wsh script pubkey
+    pub fn build_p2wsh(script_hash: &H256) -> Script {
+        Builder::default()
+            .push_opcode(Opcode::OP_0)
+            .push_bytes(script_hash.as_bytes())
+            .into_script()
+    }
+
+    /// Builds p2tr script pubkey from the x-only output key
+    pub fn build_p2tr(output_key: &H256) -> Script {
+        Builder::default()
+            .push_opcode(Opcode::OP_1)
+            .push_bytes(output_key.as_bytes())
+            .into_script()
+    }
+
+    /// Builds the script pubkey paying to the address
+    pub fn build_address(address: &Address) -> Script {
+        match (address.kind, &address.hash) {
+            (Type::P2SH, AddressTypes::Legacy(hash)) => Builder::build_p2sh(hash),
+            (_, AddressTypes::Legacy(hash)) => Builder::build_p2pkh(hash),
+            (_, AddressTypes::WitnessV0KeyHash(hash)) => Builder::build_p2wpkh(hash),
+            (_, AddressTypes::WitnessV0ScriptHash(hash)) => Builder::build_p2wsh(hash),
+            (_, AddressTypes::WitnessV1Taproot(key)) => Builder::build_p2tr(key),
+        }
+    }
+
+    /// Builds m-of-n multisig redeem script `<m> <key_1> ... <key_n> <n> CHECKMULTISIG`
+    ///
+    /// With `sorted`, the keys are sorted by their serialization (BIP67), so that every signer
+    /// builds the same script whatever the order it got the keys in. BIP67 only allows
+    /// compressed keys, and x-only keys can't be used by CHECKMULTISIG at all.
+    pub fn build_multisig(m: u32, keys: &[Public], sorted: bool) -> Result<Script, Error> {
+        let n = keys.len();
+        if n == 0 || n > MAX_PUBKEYS_PER_MULTISIG {
+            return Err(Error::PubkeyCount);
+        }
+        if m == 0 || m as usize > n {
+            return Err(Error::SigCount);
+        }
+        if keys.iter().any(|key| match key {
+            Public::Normal(_) => sorted,
+            Public::Compressed(_) => false,
+            Public::XOnly(_) => true,
+        }) {
+            return Err(Error::PubkeyType);
+        }
+
+        let mut keys = keys.iter().collect::<Vec<_>>();
+        if sorted {
+            keys.sort_by(|a, b| a[..].cmp(&b[..]));
+        }
+        let builder = keys
+            .into_iter()
+            .fold(Builder::default().push_count(m as usize), |builder, key| {
+                builder.push_data(key)
+            });
+        Ok(builder
+            .push_count(n)
+            .push_opcode(Opcode::OP_CHECKMULTISIG)
+            .into_script())
+    }
+
     /// Builds op_return script
     pub fn build_nulldata(bytes: &[u8]) -> Script {
         Builder::default()
@@ -63,6 +138,18 @@ impl Builder {
         self.push_data(&num.to_bytes())
     }
 
+    /// Appends the count of signatures or keys of a multisig, `OP_1` to `OP_16` for the
+    /// small ones
+    fn push_count(self, count: usize) -> Self {
+        match count {
+            1..=16 => self.push_opcode(
+                Opcode::from_u8(Opcode::OP_1 as u8 + count as u8 - 1)
+                    .expect("count is within [1; 16]; qed"),
+            ),
+            _ => self.push_num(Num::from(count as i64)),
+        }
+    }
+
     /// Appends bytes push operation to the end od script
     pub fn push_bytes(mut self, bytes: &[u8]) -> Self {
         let len = bytes.len();
@@ -105,6 +192,12 @@ impl Builder {
         self
     }
 
+    /// Appends raw bytes to the end of script
+    pub fn push_raw(mut self, bytes: &[u8]) -> Self {
+        self.data.extend_from_slice(bytes);
+        self
+    }
+
     /// Appends `OP_RETURN` operation to the end of script
     pub fn return_bytes(mut self, bytes: &[u8]) -> Self {
         let len = bytes.len();
@@ -0,0 +1,70 @@
+//! Descriptor checksum (BIP380)
+//!
+//! A BCH code over the descriptor, read as groups of 5 bits of the position of each character
+//! in `INPUT_CHARSET`, with an extra symbol for the upper bits of every 3 characters.
+
+use super::Error;
+
+/// Characters allowed in descriptors, ordered so that the most common ones differ in the lower
+/// 5 bits of their position.
+const INPUT_CHARSET: &str = "0123456789()[],'/*abcdefgh@:$%{}\
+    IJKLMNOPQRSTUVWXYZ&+-.;<=>?!^_|~\
+    ijklmnopqrstuvwxyzABCDEFGH`#\"\\ ";
+
+/// Characters of the checksum, the bech32 character set.
+const CHECKSUM_CHARSET: &[u8] = b"qpzry9x8gf2tvdw0s3jn54khce6mua7l";
+
+/// Length of the checksum.
+pub(super) const CHECKSUM_LENGTH: usize = 8;
+
+/// Appends the 5 bits value to the checksum state.
+fn polymod(c: u64, value: u64) -> u64 {
+    let c0 = c >> 35;
+    let mut c = ((c & 0x7_ffff_ffff) << 5) ^ value;
+    for (i, generator) in [
+        0xf5_dee5_1989,
+        0xa9_fdca_3312,
+        0x1b_ab10_e32d,
+        0x37_06b1_677a,
+        0x64_4d62_6ffd,
+    ]
+    .iter()
+    .enumerate()
+    {
+        if c0 & (1 << i) != 0 {
+            c ^= generator;
+        }
+    }
+    c
+}
+
+/// Computes the checksum of the descriptor, without its `#`.
+pub(super) fn checksum(desc: &str) -> Result<[u8; CHECKSUM_LENGTH], Error> {
+    let mut c = 1;
+    let mut class = 0;
+    let mut class_count = 0;
+    for ch in desc.chars() {
+        let position = INPUT_CHARSET.find(ch).ok_or(Error::InvalidCharacter)? as u64;
+        c = polymod(c, position & 31);
+        class = class * 3 + (position >> 5);
+        class_count += 1;
+        if class_count == 3 {
+            c = polymod(c, class);
+            class = 0;
+            class_count = 0;
+        }
+    }
+    if class_count > 0 {
+        c = polymod(c, class);
+    }
+    for _ in 0..CHECKSUM_LENGTH {
+        c = polymod(c, 0);
+    }
+    c ^= 1;
+
+    let mut result = [0u8; CHECKSUM_LENGTH];
+    for (i, ch) in result.iter_mut().enumerate() {
+        *ch = CHECKSUM_CHARSET[((c >> (5 * (7 - i))) & 31) as usize];
+    }
+    Ok(result)
+}
@@ -0,0 +1,66 @@
+//! Descriptor errors
+
+use crate::miniscript;
+
+/// Descriptor errors
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Error {
+    /// The descriptor is malformed: unbalanced parentheses, missing or extra arguments.
+    Syntax,
+    /// The descriptor is nested deeper than the maximum depth.
+    MaxDepth,
+    /// The descriptor contains a character outside of the descriptor character set.
+    InvalidCharacter,
+    /// The checksum doesn't match the descriptor.
+    InvalidChecksum,
+    /// The script expression name is unknown.
+    UnknownFunction,
+    /// The script expression isn't allowed at this level, eg: `wpkh` inside `wsh`.
+    InvalidContext,
+    /// The key isn't the hex of a public key allowed at this level.
+    InvalidKey,
+    /// The key origin isn't a fingerprint followed by a derivation path.
+    InvalidOrigin,
+    /// The threshold or the number of keys of a multisig is out of range.
+    InvalidThreshold,
+    /// The address of `addr` is invalid.
+    InvalidAddress,
+    /// The script of `raw` isn't hex.
+    InvalidHex,
+    /// The script of `sh` or `wsh` exceeds its maximum size.
+    ScriptSize,
+    /// The descriptor has no address, eg: `pk` or `raw` of a non-standard script.
+    NoAddress,
+    /// The miniscript of `wsh` is invalid.
+    Miniscript(miniscript::Error),
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Error::Syntax => "Malformed descriptor".fmt(f),
+            Error::MaxDepth => "Descriptor nested too deeply".fmt(f),
+            Error::InvalidCharacter => "Invalid descriptor character".fmt(f),
+            Error::InvalidChecksum => "Invalid descriptor checksum".fmt(f),
+            Error::UnknownFunction => "Unknown script expression".fmt(f),
+            Error::InvalidContext => "Script expression not allowed here".fmt(f),
+            Error::InvalidKey => "Invalid key expression".fmt(f),
+            Error::InvalidOrigin => "Invalid key origin".fmt(f),
+            Error::InvalidThreshold => "Multisig threshold out of range".fmt(f),
+            Error::InvalidAddress => "Invalid address".fmt(f),
+            Error::InvalidHex => "Invalid script hex".fmt(f),
+            Error::ScriptSize => "Script too large".fmt(f),
+            Error::NoAddress => "Descriptor has no address".fmt(f),
+            Error::Miniscript(e) => write!(f, "Invalid miniscript: {}", e),
+        }
+    }
+}
+
+impl From<miniscript::Error> for Error {
+    fn from(e: miniscript::Error) -> Self {
+        Error::Miniscript(e)
+    }
+}
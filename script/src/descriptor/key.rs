@@ -0,0 +1,110 @@
+//! Key expressions of descriptors
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+use core::fmt;
+
+use light_bitcoin_keys::{ChildNumber, DerivationPath, Fingerprint, Public};
+use light_bitcoin_primitives::{H256, H264, H520};
+
+use super::Error;
+use crate::psbt::KeySource;
+
+/// Encodings of the keys allowed by a script expression.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+pub(super) enum KeyContext {
+    /// Compressed and uncompressed keys, in `pk`, `pkh`, `multi` and `sh`.
+    Legacy,
+    /// Compressed keys only, in `wpkh` and `wsh`.
+    Segwit,
+    /// X-only and compressed keys, in `tr`.
+    Taproot,
+}
+
+/// Key expression: a public key with the optional fingerprint and derivation path of the
+/// master key it was derived from, eg: `[d34db33f/84'/0'/0']0279be66...`.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct DescriptorKey {
+    /// Fingerprint of the master key and derivation path of the key.
+    pub origin: Option<KeySource>,
+    /// Public key
+    pub key: Public,
+}
+
+impl From<Public> for DescriptorKey {
+    fn from(key: Public) -> Self {
+        DescriptorKey { origin: None, key }
+    }
+}
+
+impl fmt::Display for DescriptorKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some((fingerprint, path)) = &self.origin {
+            write!(f, "[{:x}", fingerprint)?;
+            for child in path.iter() {
+                write!(f, "/{}", child)?;
+            }
+            f.write_str("]")?;
+        }
+        for byte in self.key.iter() {
+            write!(f, "{:02x}", byte)?;
+        }
+        Ok(())
+    }
+}
+
+impl DescriptorKey {
+    /// Parses the key expression, with the key encodings allowed in the context.
+    pub(super) fn parse(s: &str, context: KeyContext) -> Result<Self, Error> {
+        let (origin, key) = match s.strip_prefix('[') {
+            Some(rest) => {
+                let end = rest.find(']').ok_or(Error::InvalidOrigin)?;
+                (Some(parse_origin(&rest[..end])?), &rest[end + 1..])
+            }
+            None => (None, s),
+        };
+        Ok(DescriptorKey {
+            origin,
+            key: parse_key(key, context)?,
+        })
+    }
+}
+
+/// Parses the fingerprint and derivation path of a key origin, without its brackets.
+fn parse_origin(s: &str) -> Result<KeySource, Error> {
+    let mut parts = s.split('/');
+    let mut fingerprint = [0u8; 4];
+    hex::decode_to_slice(parts.next().unwrap_or(""), &mut fingerprint)
+        .map_err(|_| Error::InvalidOrigin)?;
+    let path = parts
+        .map(str::parse)
+        .collect::<Result<Vec<ChildNumber>, _>>()
+        .map_err(|_| Error::InvalidOrigin)?;
+    Ok((Fingerprint::from(fingerprint), DerivationPath::from(path)))
+}
+
+/// Parses the hex of a public key.
+fn parse_key(s: &str, context: KeyContext) -> Result<Public, Error> {
+    let key = match (s.len(), context) {
+        (64, KeyContext::Taproot) => {
+            let mut bytes = [0u8; 32];
+            hex::decode_to_slice(s, &mut bytes).map_err(|_| Error::InvalidKey)?;
+            return Ok(Public::XOnly(H256::from(bytes)));
+        }
+        (66, _) => {
+            let mut bytes = [0u8; 33];
+            hex::decode_to_slice(s, &mut bytes).map_err(|_| Error::InvalidKey)?;
+            Public::Compressed(H264::from(bytes))
+        }
+        (130, KeyContext::Legacy) => {
+            let mut bytes = [0u8; 65];
+            hex::decode_to_slice(s, &mut bytes).map_err(|_| Error::InvalidKey)?;
+            Public::Normal(H520::from(bytes))
+        }
+        _ => return Err(Error::InvalidKey),
+    };
+    match (&key, key[0]) {
+        (Public::Compressed(_), 2 | 3) | (Public::Normal(_), 4) => Ok(key),
+        _ => Err(Error::InvalidKey),
+    }
+}
@@ -0,0 +1,554 @@
+//! Output script descriptors (BIP380-386).
+//!
+//! https://github.com/bitcoin/bips/blob/master/bip-0380.mediawiki
+//!
+//! - Descriptors are parsed with `str::parse`, checking the checksum after `#` when present, and
+//!   displayed with their checksum, the form imported by `importdescriptors` of bitcoind.
+//! - `Descriptor::script_pubkey` and `Descriptor::address` derive the output of the descriptor,
+//!   `Descriptor::redeem_script` and `Descriptor::witness_script` the scripts spending it.
+//!
+//! Keys are hex public keys with an optional origin: extended keys and ranged descriptors are
+//! not supported. `wsh` also accepts miniscript expressions, without key origins, and the script
+//! trees of `tr` only have `pk` leaves.
+
+mod checksum;
+mod error;
+mod key;
+mod parse;
+
+#[cfg(not(feature = "std"))]
+use alloc::{boxed::Box, string::ToString, vec::Vec};
+use core::{fmt, str};
+
+use light_bitcoin_keys::{Address, AddressHash, AddressTypes, Network, Type};
+use light_bitcoin_primitives::H256;
+
+use crate::builder::Builder;
+use crate::error::Error as ScriptError;
+use crate::miniscript::Miniscript;
+use crate::opcode::Opcode;
+use crate::script::Script;
+use crate::taproot::{tapbranch_hash, tapleaf_hash, taproot_output_key, TAPROOT_LEAF_TAPSCRIPT};
+
+pub use self::error::Error;
+pub use self::key::DescriptorKey;
+
+/// Script expressions, with their string form.
+#[derive(PartialEq, Clone, Debug)]
+pub enum Descriptor {
+    /// `pk(KEY)`: `<KEY> CHECKSIG`.
+    Pk(DescriptorKey),
+    /// `pkh(KEY)`: P2PKH output of the key.
+    Pkh(DescriptorKey),
+    /// `wpkh(KEY)`: P2WPKH output of the compressed key.
+    Wpkh(DescriptorKey),
+    /// `sh(SCRIPT)`: P2SH output of the script.
+    Sh(Box<Descriptor>),
+    /// `wsh(SCRIPT)`: P2WSH output of the script.
+    Wsh(Box<Descriptor>),
+    /// `multi(k,KEY_1,...,KEY_n)`: k-of-n `CHECKMULTISIG` of the keys, in the given order.
+    Multi(usize, Vec<DescriptorKey>),
+    /// `sortedmulti(k,KEY_1,...,KEY_n)`: `multi` of the keys sorted by their encoding (BIP67).
+    SortedMulti(usize, Vec<DescriptorKey>),
+    /// Miniscript expression inside `wsh`.
+    Miniscript(Miniscript),
+    /// `tr(KEY)` or `tr(KEY,TREE)`: P2TR output of the internal key and script tree.
+    Tr(DescriptorKey, Option<TapTree>),
+    /// `addr(ADDR)`: output of the address.
+    Addr(Address),
+    /// `raw(HEX)`: the script.
+    Raw(Script),
+}
+
+/// Script tree of a `tr` descriptor.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum TapTree {
+    /// `pk(KEY)` leaf: `<KEY> CHECKSIG` with the x-only key.
+    Pk(DescriptorKey),
+    /// `{A,B}` branch
+    Branch(Box<TapTree>, Box<TapTree>),
+}
+
+impl TapTree {
+    /// Returns the tapscript of a leaf, `None` for branches.
+    pub fn leaf_script(&self) -> Option<Script> {
+        match self {
+            TapTree::Pk(key) => Some(tapscript_pk(key)),
+            TapTree::Branch(..) => None,
+        }
+    }
+
+    /// Returns the merkle root of the tree.
+    pub fn merkle_root(&self) -> H256 {
+        match self {
+            TapTree::Pk(key) => tapleaf_hash(TAPROOT_LEAF_TAPSCRIPT, &tapscript_pk(key)),
+            TapTree::Branch(a, b) => tapbranch_hash(&a.merkle_root(), &b.merkle_root()),
+        }
+    }
+}
+
+/// Builds `<x-only KEY> CHECKSIG`.
+fn tapscript_pk(key: &DescriptorKey) -> Script {
+    Builder::default()
+        .push_data(key.key.x_only().as_bytes())
+        .push_opcode(Opcode::OP_CHECKSIG)
+        .into_script()
+}
+
+impl fmt::Display for TapTree {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TapTree::Pk(key) => write!(f, "pk({})", key),
+            TapTree::Branch(a, b) => write!(f, "{{{},{}}}", a, b),
+        }
+    }
+}
+
+impl Descriptor {
+    /// Returns the output script of the descriptor.
+    pub fn script_pubkey(&self) -> Result<Script, Error> {
+        let script = match self {
+            Descriptor::Pk(key) => Builder::default()
+                .push_data(&key.key)
+                .push_opcode(Opcode::OP_CHECKSIG)
+                .into_script(),
+            Descriptor::Pkh(key) => Builder::build_p2pkh(&key.key.address_hash()),
+            Descriptor::Wpkh(key) => Builder::build_p2wpkh(&key.key.address_hash()),
+            Descriptor::Sh(inner) => inner.script_pubkey()?.to_p2sh(),
+            Descriptor::Wsh(inner) => inner.script_pubkey()?.to_p2wsh(),
+            Descriptor::Multi(k, keys) => multisig_script(*k, keys, false)?,
+            Descriptor::SortedMulti(k, keys) => multisig_script(*k, keys, true)?,
+            Descriptor::Miniscript(ms) => ms.to_script(),
+            Descriptor::Tr(key, tree) => {
+                let merkle_root = tree.as_ref().map(TapTree::merkle_root);
+                let (output_key, _) = taproot_output_key(&key.key, merkle_root.as_ref())
+                    .map_err(|_| Error::InvalidKey)?;
+                Builder::build_p2tr(&output_key.x_only())
+            }
+            Descriptor::Addr(address) => Builder::build_address(address),
+            Descriptor::Raw(script) => script.clone(),
+        };
+        Ok(script)
+    }
+
+    /// Returns the address of the output script on the network.
+    pub fn address(&self, network: Network) -> Result<Address, Error> {
+        let script = self.script_pubkey()?;
+        let (kind, hash) = if script.is_pay_to_public_key_hash() {
+            let hash = AddressHash::from_slice(&script[3..23]);
+            (Type::P2PKH, AddressTypes::Legacy(hash))
+        } else if script.is_pay_to_script_hash() {
+            let hash = AddressHash::from_slice(&script[2..22]);
+            (Type::P2SH, AddressTypes::Legacy(hash))
+        } else {
+            let (version, program) = script.parse_witness_program().ok_or(Error::NoAddress)?;
+            let hash = AddressTypes::from_witness_program(version, program)
+                .map_err(|_| Error::NoAddress)?;
+            let kind = match hash {
+                AddressTypes::WitnessV0KeyHash(_) => Type::P2WPKH,
+                AddressTypes::WitnessV0ScriptHash(_) => Type::P2WSH,
+                _ => Type::P2TR,
+            };
+            (kind, hash)
+        };
+        Ok(Address {
+            kind,
+            network,
+            hash,
+        })
+    }
+
+    /// Returns the redeem script of `sh` descriptors.
+    pub fn redeem_script(&self) -> Option<Script> {
+        match self {
+            Descriptor::Sh(inner) => inner.script_pubkey().ok(),
+            _ => None,
+        }
+    }
+
+    /// Returns the witness script of `wsh` and `sh(wsh)` descriptors.
+    pub fn witness_script(&self) -> Option<Script> {
+        match self {
+            Descriptor::Sh(inner) => inner.witness_script(),
+            Descriptor::Wsh(inner) => inner.script_pubkey().ok(),
+            _ => None,
+        }
+    }
+
+    /// Writes the string form of the descriptor, without checksum.
+    fn fmt_expr(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fmt_multi =
+            |f: &mut fmt::Formatter<'_>, name: &str, k: usize, keys: &[DescriptorKey]| {
+                write!(f, "{}({}", name, k)?;
+                for key in keys {
+                    write!(f, ",{}", key)?;
+                }
+                f.write_str(")")
+            };
+
+        match self {
+            Descriptor::Pk(key) => write!(f, "pk({})", key),
+            Descriptor::Pkh(key) => write!(f, "pkh({})", key),
+            Descriptor::Wpkh(key) => write!(f, "wpkh({})", key),
+            Descriptor::Sh(inner) => {
+                f.write_str("sh(")?;
+                inner.fmt_expr(f)?;
+                f.write_str(")")
+            }
+            Descriptor::Wsh(inner) => {
+                f.write_str("wsh(")?;
+                inner.fmt_expr(f)?;
+                f.write_str(")")
+            }
+            Descriptor::Multi(k, keys) => fmt_multi(f, "multi", *k, keys),
+            Descriptor::SortedMulti(k, keys) => fmt_multi(f, "sortedmulti", *k, keys),
+            Descriptor::Miniscript(ms) => write!(f, "{}", ms),
+            Descriptor::Tr(key, None) => write!(f, "tr({})", key),
+            Descriptor::Tr(key, Some(tree)) => write!(f, "tr({},{})", key, tree),
+            Descriptor::Addr(address) => write!(f, "addr({})", address),
+            Descriptor::Raw(script) => {
+                f.write_str("raw(")?;
+                for byte in script[..].iter() {
+                    write!(f, "{:02x}", byte)?;
+                }
+                f.write_str(")")
+            }
+        }
+    }
+}
+
+/// String form of a descriptor without checksum.
+struct Expr<'a>(&'a Descriptor);
+
+impl fmt::Display for Expr<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt_expr(f)
+    }
+}
+
+impl fmt::Display for Descriptor {
+    /// Writes the descriptor followed by `#` and its checksum.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let expr = Expr(self).to_string();
+        let checksum = checksum::checksum(&expr).map_err(|_| fmt::Error)?;
+        let checksum = str::from_utf8(&checksum).expect("checksum characters are ascii; qed");
+        write!(f, "{}#{}", expr, checksum)
+    }
+}
+
+impl str::FromStr for Descriptor {
+    type Err = Error;
+
+    /// Parses a descriptor, checking its checksum if present.
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let (expr, expected) = match s.split_once('#') {
+            Some((expr, checksum)) => (expr, Some(checksum)),
+            None => (s, None),
+        };
+        let checksum = checksum::checksum(expr)?;
+        match expected {
+            Some(expected) if expected.as_bytes() != checksum => Err(Error::InvalidChecksum),
+            _ => parse::parse(expr),
+        }
+    }
+}
+
+/// Builds `<k> <KEY_1> ... <KEY_n> <n> CHECKMULTISIG`, with the keys sorted for `sortedmulti`.
+fn multisig_script(k: usize, keys: &[DescriptorKey], sorted: bool) -> Result<Script, Error> {
+    let keys = keys.iter().map(|key| key.key).collect::<Vec<_>>();
+    Builder::build_multisig(k as u32, &keys, sorted).map_err(|err| match err {
+        ScriptError::PubkeyType => Error::InvalidKey,
+        _ => Error::InvalidThreshold,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use light_bitcoin_keys::{KeyPair, Private};
+    use light_bitcoin_primitives::H256;
+
+    use super::*;
+
+    /// Compressed key of the generator point.
+    const G: &str = "0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798";
+
+    fn desc(s: &str) -> Descriptor {
+        s.parse().unwrap()
+    }
+
+    fn script_hex(s: &str) -> String {
+        hex::encode(&*desc(s).script_pubkey().unwrap())
+    }
+
+    fn address(s: &str) -> String {
+        desc(s).address(Network::Mainnet).unwrap().to_string()
+    }
+
+    #[test]
+    fn test_checksum() {
+        // https://github.com/bitcoin/bips/blob/master/bip-0380.mediawiki#test-vectors
+        assert_eq!(desc("raw(deadbeef)").to_string(), "raw(deadbeef)#89f8spxm");
+        assert_eq!(desc("raw(deadbeef)#89f8spxm"), desc("raw(deadbeef)"));
+        for s in &[
+            "raw(deadbeef)#",
+            "raw(deadbeef)#89f8spxmx",
+            "raw(deadbeef)#89f8spx",
+            "raw(deadbeef)#oooooooo",
+            "raw(deedbeef)#89f8spxm",
+            "raw(deadbeef)##9f8spxm",
+        ] {
+            assert_eq!(
+                s.parse::<Descriptor>(),
+                Err(Error::InvalidChecksum),
+                "{}",
+                s
+            );
+        }
+        assert_eq!(
+            "raw(deadbeef)\u{e9}".parse::<Descriptor>(),
+            Err(Error::InvalidCharacter)
+        );
+        // The checksum covers the hardened markers as written
+        let h = desc(&format!("wpkh([d34db33f/84h/0h/0h]{})#5fp02c75", G));
+        let apostrophe = desc(&format!("wpkh([d34db33f/84'/0'/0']{})#n9g43y4k", G));
+        assert_eq!(h, apostrophe);
+        assert_eq!(h.to_string(), apostrophe.to_string());
+    }
+
+    #[test]
+    fn test_key_expressions() {
+        let s = format!("pkh([d34db33f/44'/0'/0']{})", G);
+        let d = desc(&s);
+        match &d {
+            Descriptor::Pkh(DescriptorKey {
+                origin: Some((fingerprint, path)),
+                key,
+            }) => {
+                assert_eq!(format!("{:x}", fingerprint), "d34db33f");
+                assert_eq!(path.to_string(), "m/44'/0'/0'");
+                assert_eq!(hex::encode(&**key), G);
+            }
+            _ => panic!("expected pkh with origin"),
+        }
+        assert_eq!(d.to_string().split('#').next(), Some(&s[..]));
+        assert_eq!(desc(&d.to_string()), d);
+
+        let uncompressed = "04a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd\
+                            5b8dec5235a0fa8722476c7709c02559e3aa73aa03918ba2d492eea75abea235";
+        assert!(format!("pkh({})", uncompressed)
+            .parse::<Descriptor>()
+            .is_ok());
+        let x_only = &G[2..];
+        for s in &[
+            format!("wpkh({})", uncompressed),
+            format!("wsh(pk({}))", uncompressed),
+            format!("pk({})", x_only),
+            format!("pk(05{})", x_only),
+            format!("pk({}00)", G),
+        ] {
+            assert_eq!(s.parse::<Descriptor>(), Err(Error::InvalidKey), "{}", s);
+        }
+        for s in &[
+            format!("pk([d34db33f/44'/0']{}", G),
+            format!("pk([d34db3/44']{})", G),
+            format!("pk([d34db33f/x]{})", G),
+            format!("pk([d34db33f/44']{})", G).replace(']', ""),
+        ] {
+            assert!(s.parse::<Descriptor>().is_err(), "{}", s);
+        }
+    }
+
+    #[test]
+    fn test_script_pubkeys_and_addresses() {
+        // https://github.com/bitcoin/bips/blob/master/bip-0381.mediawiki#test-vectors
+        assert_eq!(script_hex(&format!("pk({})", G)), format!("21{}ac", G));
+        assert_eq!(
+            address(&format!("pkh({})", G)),
+            "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH"
+        );
+        assert_eq!(
+            address(&format!("wpkh({})", G)),
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"
+        );
+        assert_eq!(
+            address(&format!("sh(wpkh({}))", G)),
+            "3JvL6Ymt8MVWiCNHC7oWU6nLeHNJKLZGLN"
+        );
+        // https://github.com/bitcoin/bips/blob/master/bip-0382.mediawiki#test-vectors
+        let pkh = "pkh(02e493dbf1c10d80f3581e4904930b1404cc6c13900ee0758474fa94abe8c4cd13)";
+        assert_eq!(
+            script_hex(&format!("wsh({})", pkh)),
+            "0020fc5acc302aab97f821f9a61e1cc572e7968a603551e95d4ba12b51df6581482f"
+        );
+        let d = desc(&format!("sh(wsh({}))", pkh));
+        assert_eq!(d.witness_script(), Some(desc(pkh).script_pubkey().unwrap()));
+        assert_eq!(
+            d.redeem_script(),
+            Some(desc(&format!("wsh({})", pkh)).script_pubkey().unwrap())
+        );
+
+        // https://github.com/bitcoin/bips/blob/master/bip-0086.mediawiki#test-vectors
+        assert_eq!(
+            address("tr(cc8a4bc64d897bddc5fbc2f670f7a8ba0b386779106cf1223c6fc5d7cd6fc115)"),
+            "bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr"
+        );
+
+        // https://github.com/bitcoin/bips/blob/master/bip-0385.mediawiki#test-vectors
+        for addr in &[
+            "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH",
+            "3JvL6Ymt8MVWiCNHC7oWU6nLeHNJKLZGLN",
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4",
+        ] {
+            assert_eq!(address(&format!("addr({})", addr)), *addr);
+        }
+        assert_eq!(
+            address(&format!("raw({})", script_hex(&format!("sh(wpkh({}))", G)))),
+            "3JvL6Ymt8MVWiCNHC7oWU6nLeHNJKLZGLN"
+        );
+        assert_eq!(
+            desc(&format!("pk({})", G)).address(Network::Mainnet),
+            Err(Error::NoAddress)
+        );
+        assert_eq!(
+            "addr(1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMi)".parse::<Descriptor>(),
+            Err(Error::InvalidAddress)
+        );
+        assert_eq!("raw(dead0)".parse::<Descriptor>(), Err(Error::InvalidHex));
+    }
+
+    #[test]
+    fn test_multi() {
+        // https://github.com/bitcoin/bips/blob/master/bip-0383.mediawiki#test-vectors
+        let a = "022f8bde4d1a07209355b4a7250a5c5128e88b84bddc619ab7cba8d569b240efe4";
+        let b = "025cbdf0646e5db4eaa398f365f2ea7a0e3d419b7e0330e39ce92bddedcac4f9bc";
+        let c = "03acd484e2f0c7f65309ad178a9f559abde09796974c57e714c35f110dfc27ccbe";
+        assert_eq!(
+            script_hex(&format!("multi(1,{},{})", a, b)),
+            format!("5121{}21{}52ae", a, b)
+        );
+        assert_eq!(
+            script_hex(&format!("sortedmulti(1,{},{})", c, a)),
+            format!("5121{}21{}52ae", a, c)
+        );
+        let d = desc(&format!("wsh(sortedmulti(2,{},{},{}))", c, b, a));
+        assert_eq!(
+            d.witness_script().map(|script| hex::encode(&*script)),
+            Some(format!("5221{}21{}21{}53ae", a, b, c))
+        );
+        assert_eq!(desc(&d.to_string()), d);
+
+        for s in &[
+            format!("multi(0,{},{})", a, b),
+            format!("multi(3,{},{})", a, b),
+            format!("multi(+1,{},{})", a, b),
+            format!("multi(1,{},{},{},{})", a, b, c, G),
+        ] {
+            assert_eq!(
+                s.parse::<Descriptor>(),
+                Err(Error::InvalidThreshold),
+                "{}",
+                s
+            );
+        }
+        assert!(format!("sh(multi(1,{},{},{},{}))", a, b, c, G)
+            .parse::<Descriptor>()
+            .is_ok());
+        let keys = [a, b, c, G].repeat(4).join(",");
+        assert_eq!(
+            format!("sh(multi(1,{}))", keys).parse::<Descriptor>(),
+            Err(Error::ScriptSize)
+        );
+        assert!(format!("wsh(multi(1,{}))", keys)
+            .parse::<Descriptor>()
+            .is_ok());
+    }
+
+    #[test]
+    fn test_contexts() {
+        for s in &[
+            format!("wsh(wpkh({}))", G),
+            format!("wsh(wsh(pk({})))", G),
+            format!("sh(sh(pk({})))", G),
+            format!("sh(tr({}))", G),
+            format!("tr({},wpkh({}))", G, G),
+            "sh(raw(deadbeef))".to_string(),
+        ] {
+            assert_eq!(s.parse::<Descriptor>(), Err(Error::InvalidContext), "{}", s);
+        }
+        for s in &[
+            format!("pk({})x", G),
+            format!("pk({}", G),
+            format!("pk({},{})", G, G),
+            format!("tr({},{{pk({})}})", G, G),
+        ] {
+            assert_eq!(s.parse::<Descriptor>(), Err(Error::Syntax), "{}", s);
+        }
+        assert_eq!(
+            format!("foo({})", G).parse::<Descriptor>(),
+            Err(Error::UnknownFunction)
+        );
+
+        // Untrusted input nested too deeply for the parser
+        let s = "sh(".repeat(200_000) + &")".repeat(200_000);
+        assert_eq!(s.parse::<Descriptor>(), Err(Error::MaxDepth));
+    }
+
+    #[test]
+    fn test_tr_script_tree() {
+        // https://github.com/bitcoin/bips/blob/master/bip-0386.mediawiki#test-vectors
+        let internal_key = "a34b99f22c790c4e36b2b3c2c35a36db06226e41c692fc82b8b56ac1c540c5bd";
+        let leaf_key = "669b8afcec803a0d323e9a17f3ea8e68e8abe5a278020a929adbec52421adbd0";
+        assert_eq!(
+            script_hex(&format!("tr({})", internal_key)),
+            "512077aab6e066f8a7419c5ab714c12c67d25007ed55a43cadcacb4d7a970a093f11"
+        );
+        assert_eq!(
+            script_hex(&format!("tr({},pk({}))", internal_key, leaf_key)),
+            "512017cf18db381d836d8923b1bdb246cfcd818da1a9f0e6e7907f187f0b2f937754"
+        );
+
+        // Branches commit to their children in any order
+        let s = format!(
+            "tr({},{{pk({}),{{pk({}),pk({})}}}})",
+            G, G, leaf_key, internal_key
+        );
+        let d = desc(&s);
+        assert_eq!(d.to_string().split('#').next(), Some(&s[..]));
+        let swapped = format!(
+            "tr({},{{{{pk({}),pk({})}},pk({})}})",
+            G, internal_key, leaf_key, G
+        );
+        assert_eq!(d.script_pubkey(), desc(&swapped).script_pubkey());
+    }
+
+    #[test]
+    fn test_wsh_miniscript() {
+        let trustees = (1..=3)
+            .map(|n| {
+                KeyPair::from_private(Private {
+                    network: Default::default(),
+                    secret: H256::repeat_byte(n),
+                    compressed: true,
+                })
+                .unwrap()
+            })
+            .map(|keypair| hex::encode(&**keypair.public()))
+            .collect::<Vec<_>>();
+        let expr = format!(
+            "or_d(multi(2,{},{}),and_v(v:pk({}),older(144)))",
+            trustees[0], trustees[1], trustees[2]
+        );
+        let d = desc(&format!("wsh({})", expr));
+        let ms = expr.parse::<Miniscript>().unwrap();
+        assert_eq!(
+            d,
+            Descriptor::Wsh(Box::new(Descriptor::Miniscript(ms.clone())))
+        );
+        assert_eq!(d.witness_script(), Some(ms.to_script()));
+        assert_eq!(desc(&d.to_string()), d);
+        assert_eq!(
+            format!("wsh(and_v(v:pk({}),older(0)))", trustees[0]).parse::<Descriptor>(),
+            Err(Error::Miniscript(crate::miniscript::Error::InvalidNumber))
+        );
+    }
+}
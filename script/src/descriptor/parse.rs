@@ -0,0 +1,222 @@
+//! String form of descriptors
+
+#[cfg(not(feature = "std"))]
+use alloc::{boxed::Box, vec, vec::Vec};
+
+use super::key::KeyContext;
+use super::{Descriptor, DescriptorKey, Error, TapTree};
+use crate::miniscript::{self, MAX_STANDARD_P2WSH_SCRIPT_SIZE};
+use crate::script::{Script, MAX_PUBKEYS_PER_MULTISIG, MAX_SCRIPT_ELEMENT_SIZE};
+use crate::taproot::TAPROOT_CONTROL_MAX_NODE_COUNT;
+
+/// Names of the script expressions.
+const FUNCTION_NAMES: &[&str] = &[
+    "pk",
+    "pkh",
+    "wpkh",
+    "sh",
+    "wsh",
+    "multi",
+    "sortedmulti",
+    "tr",
+    "addr",
+    "raw",
+];
+
+/// Maximum number of keys of a bare `multi` (policy).
+const MAX_BARE_MULTISIG_KEYS: usize = 3;
+
+/// Maximum depth of the descriptor: `sh(wsh(...))` around a miniscript of the maximum depth.
+///
+/// The descriptor is processed recursively, so its depth must be bounded to parse untrusted
+/// input.
+const MAX_DEPTH: usize = 2 + miniscript::MAX_DEPTH;
+
+/// Level of a script expression, which restricts the expressions and keys it accepts.
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+enum Context {
+    /// Top level
+    Top,
+    /// Inside `sh`
+    P2sh,
+    /// Inside `wsh`
+    P2wsh,
+}
+
+/// Node of the descriptor: a name and its arguments, or a `{A,B}` branch named `{`.
+struct Tree<'a> {
+    name: &'a str,
+    args: Vec<Tree<'a>>,
+    /// The string of the node, with its arguments.
+    span: &'a str,
+}
+
+/// Parses the string form of a descriptor, without checksum.
+pub(super) fn parse(s: &str) -> Result<Descriptor, Error> {
+    match parse_tree(s, 0)? {
+        (tree, "") => from_tree(&tree, Context::Top),
+        _ => Err(Error::Syntax),
+    }
+}
+
+/// Parses the node at the start of the string, returning the rest of the string.
+///
+/// `depth` is the depth of the parent node.
+fn parse_tree(s: &str, depth: usize) -> Result<(Tree<'_>, &str), Error> {
+    let depth = depth + 1;
+    if depth > MAX_DEPTH {
+        return Err(Error::MaxDepth);
+    }
+    let end = s.find(&['(', ')', '{', '}', ','][..]).unwrap_or(s.len());
+    let (name, rest) = s.split_at(end);
+    let (name, close) = match rest.as_bytes().first() {
+        Some(b'(') => (name, b')'),
+        Some(b'{') if name.is_empty() => (&s[..1], b'}'),
+        _ => {
+            let tree = Tree {
+                name,
+                args: Vec::new(),
+                span: name,
+            };
+            return Ok((tree, rest));
+        }
+    };
+
+    let mut args = Vec::new();
+    let mut rest = &rest[1..];
+    loop {
+        let (arg, after) = parse_tree(rest, depth)?;
+        args.push(arg);
+        rest = after.get(1..).ok_or(Error::Syntax)?;
+        match after.as_bytes()[0] {
+            b',' => continue,
+            c if c == close => break,
+            _ => return Err(Error::Syntax),
+        }
+    }
+    let span = &s[..s.len() - rest.len()];
+    Ok((Tree { name, args, span }, rest))
+}
+
+/// Builds the script expression of the node.
+fn from_tree(tree: &Tree, context: Context) -> Result<Descriptor, Error> {
+    let allowed = match (tree.name, context) {
+        ("pk", _) | ("pkh", _) | ("multi", _) | ("sortedmulti", _) => true,
+        ("wpkh", Context::Top) | ("wpkh", Context::P2sh) => true,
+        ("wsh", Context::Top) | ("wsh", Context::P2sh) => true,
+        ("sh", Context::Top) | ("tr", Context::Top) => true,
+        ("addr", Context::Top) | ("raw", Context::Top) => true,
+        (name, _) if FUNCTION_NAMES.contains(&name) => false,
+        (_, Context::P2wsh) => return Ok(Descriptor::Miniscript(tree.span.parse()?)),
+        _ => return Err(Error::UnknownFunction),
+    };
+    if !allowed {
+        return Err(Error::InvalidContext);
+    }
+
+    let key_context = match context {
+        Context::P2wsh => KeyContext::Segwit,
+        _ => KeyContext::Legacy,
+    };
+    let args = &tree.args;
+    let descriptor = match (tree.name, args.len()) {
+        ("pk", 1) => Descriptor::Pk(key(&args[0], key_context)?),
+        ("pkh", 1) => Descriptor::Pkh(key(&args[0], key_context)?),
+        ("wpkh", 1) => Descriptor::Wpkh(key(&args[0], KeyContext::Segwit)?),
+        ("sh", 1) => {
+            let inner = from_tree(&args[0], Context::P2sh)?;
+            if inner.script_pubkey()?.len() > MAX_SCRIPT_ELEMENT_SIZE {
+                return Err(Error::ScriptSize);
+            }
+            Descriptor::Sh(Box::new(inner))
+        }
+        ("wsh", 1) => {
+            let inner = from_tree(&args[0], Context::P2wsh)?;
+            if inner.script_pubkey()?.len() > MAX_STANDARD_P2WSH_SCRIPT_SIZE {
+                return Err(Error::ScriptSize);
+            }
+            Descriptor::Wsh(Box::new(inner))
+        }
+        ("multi", n) if n >= 2 => {
+            let (k, keys) = multi(args, context, key_context)?;
+            Descriptor::Multi(k, keys)
+        }
+        ("sortedmulti", n) if n >= 2 => {
+            let (k, keys) = multi(args, context, key_context)?;
+            Descriptor::SortedMulti(k, keys)
+        }
+        ("tr", 1) => Descriptor::Tr(key(&args[0], KeyContext::Taproot)?, None),
+        ("tr", 2) => {
+            let internal_key = key(&args[0], KeyContext::Taproot)?;
+            Descriptor::Tr(internal_key, Some(tap_tree(&args[1], 0)?))
+        }
+        ("addr", 1) => Descriptor::Addr(
+            terminal(&args[0])?
+                .parse()
+                .map_err(|_| Error::InvalidAddress)?,
+        ),
+        ("raw", 1) => {
+            let hex = terminal(&args[0])?;
+            let mut bytes = vec![0u8; hex.len() / 2];
+            hex::decode_to_slice(hex, &mut bytes).map_err(|_| Error::InvalidHex)?;
+            Descriptor::Raw(Script::from(bytes))
+        }
+        _ => return Err(Error::Syntax),
+    };
+    Ok(descriptor)
+}
+
+/// Parses the threshold and keys of `multi` and `sortedmulti`.
+fn multi(
+    args: &[Tree],
+    context: Context,
+    key_context: KeyContext,
+) -> Result<(usize, Vec<DescriptorKey>), Error> {
+    let k = terminal(&args[0])?;
+    if !k.bytes().all(|c| c.is_ascii_digit()) {
+        return Err(Error::InvalidThreshold);
+    }
+    let k = k.parse::<usize>().map_err(|_| Error::InvalidThreshold)?;
+    let keys = args[1..]
+        .iter()
+        .map(|arg| key(arg, key_context))
+        .collect::<Result<Vec<_>, _>>()?;
+    let max_keys = match context {
+        Context::Top => MAX_BARE_MULTISIG_KEYS,
+        _ => MAX_PUBKEYS_PER_MULTISIG,
+    };
+    if k == 0 || k > keys.len() || keys.len() > max_keys {
+        return Err(Error::InvalidThreshold);
+    }
+    Ok((k, keys))
+}
+
+/// Builds the script tree of `tr`, the node being at the given depth.
+fn tap_tree(tree: &Tree, depth: usize) -> Result<TapTree, Error> {
+    if depth > TAPROOT_CONTROL_MAX_NODE_COUNT {
+        return Err(Error::Syntax);
+    }
+    match (tree.name, tree.args.len()) {
+        ("{", 2) => Ok(TapTree::Branch(
+            Box::new(tap_tree(&tree.args[0], depth + 1)?),
+            Box::new(tap_tree(&tree.args[1], depth + 1)?),
+        )),
+        ("pk", 1) => Ok(TapTree::Pk(key(&tree.args[0], KeyContext::Taproot)?)),
+        ("{", _) | ("pk", _) => Err(Error::Syntax),
+        (name, _) if FUNCTION_NAMES.contains(&name) => Err(Error::InvalidContext),
+        _ => Err(Error::UnknownFunction),
+    }
+}
+
+/// Returns the name of a node without arguments.
+fn terminal<'a>(tree: &Tree<'a>) -> Result<&'a str, Error> {
+    match tree.args.is_empty() {
+        true => Ok(tree.name),
+        false => Err(Error::Syntax),
+    }
+}
+
+/// Parses the key expression of a node.
+fn key(tree: &Tree, context: KeyContext) -> Result<DescriptorKey, Error> {
+    DescriptorKey::parse(terminal(tree)?, context)
+}
@@ -52,10 +52,19 @@ pub enum Error {
     SignatureNullDummy,
     PubkeyType,
     Cleanstack,
+    MinimalIf,
+    SignatureNullFail,
 
     // Softfork safeness
     DiscourageUpgradableNops,
     DiscourageUpgradableWitnessProgram,
+    DiscourageUpgradableTaprootVersion,
+    DiscourageOpSuccess,
+    DiscourageUpgradablePubkeyType,
+
+    // Constant scriptCode
+    OpCodeSeparator,
+    SignatureFindAndDelete,
 
     // SegWit-related errors
     WitnessProgramWrongLength,
@@ -65,6 +74,15 @@ pub enum Error {
     WitnessMalleatedP2SH,
     WitnessUnexpected,
     WitnessPubKeyType,
+
+    // Taproot
+    SchnorrSigSize,
+    SchnorrSigHashtype,
+    SchnorrSig,
+    TaprootWrongControlSize,
+    TapscriptValidationWeight,
+    TapscriptCheckMultisig,
+    TapscriptMinimalIf,
 }
 
 #[cfg(feature = "std")]
@@ -122,12 +140,25 @@ impl core::fmt::Display for Error {
             Error::Cleanstack => {
                 "Only one element is expected to remain at stack at the end of execution".fmt(f)
             }
+            Error::MinimalIf => "OP_IF/NOTIF argument must be minimal".fmt(f),
+            Error::SignatureNullFail => {
+                "Signature must be zero for failed CHECK(MULTI)SIG operation".fmt(f)
+            }
 
             // Softfork safeness
             Error::DiscourageUpgradableNops => "Discourage Upgradable Nops".fmt(f),
             Error::DiscourageUpgradableWitnessProgram => {
                 "Discourage Upgradable Witness Program".fmt(f)
             }
+            Error::DiscourageUpgradableTaprootVersion => {
+                "Discourage Upgradable Taproot Version".fmt(f)
+            }
+            Error::DiscourageOpSuccess => "Discourage OP_SUCCESSx".fmt(f),
+            Error::DiscourageUpgradablePubkeyType => "Discourage Upgradable Pubkey Type".fmt(f),
+
+            // Constant scriptCode
+            Error::OpCodeSeparator => "Using OP_CODESEPARATOR in non-witness script".fmt(f),
+            Error::SignatureFindAndDelete => "Signature is found in scriptCode".fmt(f),
 
             // SegWit-related errors
             Error::WitnessProgramWrongLength => "Witness program has incorrect length".fmt(f),
@@ -139,6 +170,19 @@ impl core::fmt::Display for Error {
             Error::WitnessMalleatedP2SH => "Witness requires only-redeemscript scriptSig".fmt(f),
             Error::WitnessUnexpected => "Witness provided for non-witness script".fmt(f),
             Error::WitnessPubKeyType => "Using non-compressed keys in segwit".fmt(f),
+
+            // Taproot
+            Error::SchnorrSigSize => "Invalid Schnorr signature size".fmt(f),
+            Error::SchnorrSigHashtype => "Invalid Schnorr signature hash type".fmt(f),
+            Error::SchnorrSig => "Invalid Schnorr signature".fmt(f),
+            Error::TaprootWrongControlSize => "Invalid Taproot control block size".fmt(f),
+            Error::TapscriptValidationWeight => {
+                "Too much signature validation relative to witness weight".fmt(f)
+            }
+            Error::TapscriptCheckMultisig => {
+                "OP_CHECKMULTISIG(VERIFY) is not available in tapscript".fmt(f)
+            }
+            Error::TapscriptMinimalIf => "OP_IF/NOTIF argument must be minimal in tapscript".fmt(f),
         }
     }
 }
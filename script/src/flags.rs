@@ -63,9 +63,33 @@ pub struct VerificationFlags {
     /// Support segregated witness
     pub verify_witness: bool,
 
+    /// Segwit script only: Require the argument of OP_IF/NOTIF to be exactly 0x01 or empty vector
+    pub verify_minimalif: bool,
+
+    /// Signature(s) must be empty vector if a CHECK(MULTI)SIG operation failed
+    pub verify_nullfail: bool,
+
     /// Making v1-v16 witness program non-standard
     pub verify_discourage_upgradable_witness_program: bool,
 
+    /// Public keys in segregated witness scripts must be compressed
+    pub verify_witness_pubkeytype: bool,
+
+    /// Taproot/Tapscript validation (BIP341 & BIP342)
+    pub verify_taproot: bool,
+
+    /// Making unknown Taproot leaf versions non-standard
+    pub verify_discourage_upgradable_taproot_version: bool,
+
+    /// Making unknown OP_SUCCESS non-standard
+    pub verify_discourage_op_success: bool,
+
+    /// Making unknown public key versions (in BIP 342 scripts) non-standard
+    pub verify_discourage_upgradable_pubkeytype: bool,
+
+    /// Making OP_CODESEPARATOR and FindAndDelete fail any non-segwit scripts
+    pub verify_const_scriptcode: bool,
+
     /// Support OP_CAT opcode
     pub verify_concat: bool,
 
@@ -144,16 +168,71 @@ impl VerificationFlags {
         self
     }
 
+    pub fn verify_low_s(mut self, value: bool) -> Self {
+        self.verify_low_s = value;
+        self
+    }
+
+    pub fn verify_minimaldata(mut self, value: bool) -> Self {
+        self.verify_minimaldata = value;
+        self
+    }
+
+    pub fn verify_discourage_upgradable_nops(mut self, value: bool) -> Self {
+        self.verify_discourage_upgradable_nops = value;
+        self
+    }
+
     pub fn verify_cleanstack(mut self, value: bool) -> Self {
         self.verify_cleanstack = value;
         self
     }
 
+    pub fn verify_minimalif(mut self, value: bool) -> Self {
+        self.verify_minimalif = value;
+        self
+    }
+
+    pub fn verify_nullfail(mut self, value: bool) -> Self {
+        self.verify_nullfail = value;
+        self
+    }
+
     pub fn verify_discourage_upgradable_witness_program(mut self, value: bool) -> Self {
         self.verify_discourage_upgradable_witness_program = value;
         self
     }
 
+    pub fn verify_witness_pubkeytype(mut self, value: bool) -> Self {
+        self.verify_witness_pubkeytype = value;
+        self
+    }
+
+    pub fn verify_taproot(mut self, value: bool) -> Self {
+        self.verify_taproot = value;
+        self
+    }
+
+    pub fn verify_discourage_upgradable_taproot_version(mut self, value: bool) -> Self {
+        self.verify_discourage_upgradable_taproot_version = value;
+        self
+    }
+
+    pub fn verify_discourage_op_success(mut self, value: bool) -> Self {
+        self.verify_discourage_op_success = value;
+        self
+    }
+
+    pub fn verify_discourage_upgradable_pubkeytype(mut self, value: bool) -> Self {
+        self.verify_discourage_upgradable_pubkeytype = value;
+        self
+    }
+
+    pub fn verify_const_scriptcode(mut self, value: bool) -> Self {
+        self.verify_const_scriptcode = value;
+        self
+    }
+
     pub fn verify_concat(mut self, value: bool) -> Self {
         self.verify_concat = value;
         self
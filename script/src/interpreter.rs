@@ -0,0 +1,3259 @@
+//! Script interpreter
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+use core::mem;
+
+use light_bitcoin_chain::constants::SEQUENCE_LOCKTIME_DISABLE_FLAG;
+use light_bitcoin_crypto::{dhash160, dhash256, ripemd160, sha1, sha256};
+use light_bitcoin_keys::{AddressHash, Public, Signature};
+use light_bitcoin_primitives::{Bytes, H256};
+use light_bitcoin_serialization::{serialized_list_size, Stream};
+
+use crate::builder::Builder;
+use crate::error::Error;
+use crate::flags::VerificationFlags;
+use crate::num::Num;
+use crate::opcode::Opcode;
+use crate::script::{
+    Script, ScriptWitness, MAX_OPS_PER_SCRIPT, MAX_PUBKEYS_PER_MULTISIG, MAX_SCRIPT_ELEMENT_SIZE,
+    MAX_SCRIPT_SIZE, MAX_STACK_SIZE,
+};
+use crate::sign::{ScriptExecutionData, Sighash, SignatureVersion};
+use crate::stack::Stack;
+use crate::taproot::{
+    is_op_success, tapleaf_hash, verify_taproot_commitment, ANNEX_TAG, TAPROOT_CONTROL_BASE_SIZE,
+    TAPROOT_CONTROL_MAX_SIZE, TAPROOT_CONTROL_NODE_SIZE, TAPROOT_LEAF_MASK, TAPROOT_LEAF_TAPSCRIPT,
+    VALIDATION_WEIGHT_OFFSET, VALIDATION_WEIGHT_PER_SIGOP_PASSED,
+};
+use crate::verify::SignatureChecker;
+
+/// Default maximum size of numeric operands.
+const DEFAULT_SCRIPT_NUM_SIZE: usize = 4;
+
+/// Maximum size of numeric operands of `OP_CHECKLOCKTIMEVERIFY` and `OP_CHECKSEQUENCEVERIFY`.
+const LOCKTIME_SCRIPT_NUM_SIZE: usize = 5;
+
+/// Maximum size of numbers which may be re-encoded by `OP_NUM2BIN`.
+const NUM2BIN_SCRIPT_NUM_SIZE: usize = 8;
+
+/// Checks that the DER part of a signature (without the sighash type) is strictly encoded (BIP66).
+///
+/// Format: 0x30 [total-length] 0x02 [R-length] [R] 0x02 [S-length] [S]
+fn is_valid_der_signature_encoding(sig: &[u8]) -> bool {
+    // Minimum and maximum size constraints.
+    if sig.len() < 8 || sig.len() > 72 {
+        return false;
+    }
+
+    // A signature is of type 0x30 (compound).
+    if sig[0] != 0x30 {
+        return false;
+    }
+
+    // Make sure the length covers the entire signature.
+    if sig[1] as usize != sig.len() - 2 {
+        return false;
+    }
+
+    // Extract the length of the R element.
+    let len_r = sig[3] as usize;
+
+    // Make sure the length of the S element is still inside the signature.
+    if 5 + len_r >= sig.len() {
+        return false;
+    }
+
+    // Extract the length of the S element.
+    let len_s = sig[5 + len_r] as usize;
+
+    // Verify that the length of the signature matches the sum of the length of the elements.
+    if len_r + len_s + 6 != sig.len() {
+        return false;
+    }
+
+    // Check whether the R element is an integer.
+    if sig[2] != 0x02 {
+        return false;
+    }
+
+    // Zero-length integers are not allowed for R.
+    if len_r == 0 {
+        return false;
+    }
+
+    // Negative numbers are not allowed for R.
+    if (sig[4] & 0x80) != 0 {
+        return false;
+    }
+
+    // Null bytes at the start of R are not allowed, unless R would otherwise be interpreted as a negative number.
+    if len_r > 1 && sig[4] == 0x00 && (sig[5] & 0x80) == 0 {
+        return false;
+    }
+
+    // Check whether the S element is an integer.
+    if sig[len_r + 4] != 0x02 {
+        return false;
+    }
+
+    // Zero-length integers are not allowed for S.
+    if len_s == 0 {
+        return false;
+    }
+
+    // Negative numbers are not allowed for S.
+    if (sig[len_r + 6] & 0x80) != 0 {
+        return false;
+    }
+
+    // Null bytes at the start of S are not allowed, unless S would otherwise be interpreted as a negative number.
+    if len_s > 1 && (sig[len_r + 6] == 0x00) && (sig[len_r + 7] & 0x80) == 0 {
+        return false;
+    }
+
+    true
+}
+
+/// Checks that a transaction signature (DER signature followed by the sighash type) is strictly encoded.
+fn is_valid_signature_encoding(sig: &[u8]) -> bool {
+    match sig.split_last() {
+        Some((_, der)) => is_valid_der_signature_encoding(der),
+        None => false,
+    }
+}
+
+fn is_low_der_signature(der: &[u8]) -> Result<(), Error> {
+    if !is_valid_der_signature_encoding(der) {
+        return Err(Error::SignatureDer);
+    }
+
+    let signature: Signature = der.into();
+    if !signature.check_low_s() {
+        return Err(Error::SignatureHighS);
+    }
+
+    Ok(())
+}
+
+fn is_defined_hashtype_signature(version: SignatureVersion, sig: &[u8]) -> bool {
+    match sig.last() {
+        Some(hashtype) => Sighash::is_defined(version, u32::from(*hashtype)),
+        None => false,
+    }
+}
+
+fn check_signature_encoding(
+    sig: &[u8],
+    flags: &VerificationFlags,
+    version: SignatureVersion,
+) -> Result<(), Error> {
+    // Empty signature. Not strictly DER encoded, but allowed to provide a
+    // compact way to provide an invalid signature for use with CHECK(MULTI)SIG
+    if sig.is_empty() {
+        return Ok(());
+    }
+
+    if (flags.verify_dersig || flags.verify_low_s || flags.verify_strictenc)
+        && !is_valid_signature_encoding(sig)
+    {
+        return Err(Error::SignatureDer);
+    }
+
+    if flags.verify_low_s {
+        is_low_der_signature(&sig[..sig.len() - 1])?;
+    }
+
+    if flags.verify_strictenc {
+        if !is_defined_hashtype_signature(version, sig) {
+            return Err(Error::SignatureHashtype);
+        }
+
+        let uses_fork_id = sig[sig.len() - 1] & 0x40 != 0;
+        let enabled_fork_id = version == SignatureVersion::ForkId;
+        if uses_fork_id && !enabled_fork_id {
+            return Err(Error::SignatureIllegalForkId);
+        } else if !uses_fork_id && enabled_fork_id {
+            return Err(Error::SignatureMustUseForkId);
+        }
+    }
+
+    Ok(())
+}
+
+/// Same as `check_signature_encoding`, but for signatures without the sighash type (`OP_CHECKDATASIG`).
+fn check_data_signature_encoding(sig: &[u8], flags: &VerificationFlags) -> Result<(), Error> {
+    if sig.is_empty() {
+        return Ok(());
+    }
+
+    if (flags.verify_dersig || flags.verify_low_s || flags.verify_strictenc)
+        && !is_valid_der_signature_encoding(sig)
+    {
+        return Err(Error::SignatureDer);
+    }
+
+    if flags.verify_low_s {
+        is_low_der_signature(sig)?;
+    }
+
+    Ok(())
+}
+
+fn is_public_key(v: &[u8]) -> bool {
+    match v.len() {
+        33 if v[0] == 2 || v[0] == 3 => true,
+        65 if v[0] == 4 => true,
+        _ => false,
+    }
+}
+
+fn is_compressed_public_key(v: &[u8]) -> bool {
+    v.len() == 33 && (v[0] == 2 || v[0] == 3)
+}
+
+fn check_pubkey_encoding(
+    v: &[u8],
+    flags: &VerificationFlags,
+    version: SignatureVersion,
+) -> Result<(), Error> {
+    if flags.verify_strictenc && !is_public_key(v) {
+        return Err(Error::PubkeyType);
+    }
+
+    // Only compressed keys are accepted in segwit
+    if flags.verify_witness_pubkeytype
+        && version == SignatureVersion::WitnessV0
+        && !is_compressed_public_key(v)
+    {
+        return Err(Error::WitnessPubKeyType);
+    }
+
+    Ok(())
+}
+
+fn check_minimal_push(data: &[u8], opcode: Opcode) -> bool {
+    if data.is_empty() {
+        // Could have used OP_0.
+        opcode == Opcode::OP_0
+    } else if data.len() == 1 && data[0] >= 1 && data[0] <= 16 {
+        // Could have used OP_1 .. OP_16.
+        false
+    } else if data.len() == 1 && data[0] == 0x81 {
+        // Could have used OP_1NEGATE.
+        false
+    } else if data.len() <= 75 {
+        // Could have used a direct push (opcode indicating number of bytes pushed + those bytes).
+        opcode as usize == data.len()
+    } else if data.len() <= 255 {
+        // Could have used OP_PUSHDATA.
+        opcode == Opcode::OP_PUSHDATA1
+    } else if data.len() <= 65535 {
+        // Could have used OP_PUSHDATA2.
+        opcode == Opcode::OP_PUSHDATA2
+    } else {
+        true
+    }
+}
+
+/// Interprets a stack element as a boolean. Negative zero is false.
+fn cast_to_bool(data: &[u8]) -> bool {
+    match data.split_last() {
+        None => false,
+        Some((last, rest)) if rest.iter().all(|x| *x == 0) => *last != 0 && *last != 0x80,
+        Some(_) => true,
+    }
+}
+
+fn check_signature(
+    checker: &dyn SignatureChecker,
+    script_sig: &[u8],
+    public: &[u8],
+    script_code: &Script,
+    version: SignatureVersion,
+) -> bool {
+    let public = match Public::from_slice(public) {
+        Ok(public) => public,
+        Err(_) => return false,
+    };
+
+    match script_sig.split_last() {
+        Some((hashtype, sig)) => checker.check_signature(
+            &sig.into(),
+            &public,
+            script_code,
+            u32::from(*hashtype),
+            version,
+        ),
+        None => false,
+    }
+}
+
+fn verify_data_signature(
+    checker: &dyn SignatureChecker,
+    sig: &[u8],
+    public: &[u8],
+    message: &[u8],
+) -> bool {
+    if sig.is_empty() {
+        return false;
+    }
+
+    match Public::from_slice(public) {
+        Ok(public) => checker.verify_signature(&sig.into(), &public, &sha256(message)),
+        Err(_) => false,
+    }
+}
+
+/// Signature check of `OP_CHECKSIG`, `OP_CHECKSIGVERIFY` and `OP_CHECKSIGADD` in tapscript (BIP342).
+///
+/// Returns `Ok(false)` for an empty signature. Any other signature must be valid.
+fn eval_checksig_tapscript(
+    checker: &dyn SignatureChecker,
+    sig: &[u8],
+    pubkey: &[u8],
+    flags: &VerificationFlags,
+    execdata: &mut ScriptExecutionData,
+) -> Result<bool, Error> {
+    let success = !sig.is_empty();
+    if success {
+        // Implement the sigops/witnesssize ratio test.
+        execdata.validation_weight_left -= VALIDATION_WEIGHT_PER_SIGOP_PASSED;
+        if execdata.validation_weight_left < 0 {
+            return Err(Error::TapscriptValidationWeight);
+        }
+    }
+
+    match pubkey.len() {
+        0 => return Err(Error::PubkeyType),
+        32 => {
+            if success {
+                let public = Public::XOnly(H256::from_slice(pubkey));
+                checker.check_schnorr_signature(
+                    sig,
+                    &public,
+                    SignatureVersion::Tapscript,
+                    execdata,
+                )?;
+            }
+        }
+        // Public keys of unknown types are reserved for soft forks and always succeed.
+        _ => {
+            if flags.verify_discourage_upgradable_pubkeytype {
+                return Err(Error::DiscourageUpgradablePubkeyType);
+            }
+        }
+    }
+
+    Ok(success)
+}
+
+/// Verifies that `script_sig` satisfies `script_pubkey`.
+///
+/// The script signature is executed first, then the script pubkey is executed on the
+/// resulting stack. With `verify_p2sh` the redeem script of a P2SH output is executed as well,
+/// and with `verify_witness` native and P2SH-wrapped witness programs are checked against `witness`.
+pub fn verify_script(
+    script_sig: &Script,
+    script_pubkey: &Script,
+    witness: &ScriptWitness,
+    flags: &VerificationFlags,
+    checker: &dyn SignatureChecker,
+) -> Result<(), Error> {
+    if flags.verify_sigpushonly && !script_sig.is_push_only() {
+        return Err(Error::SignaturePushOnly);
+    }
+
+    let mut stack = Stack::new();
+    let mut stack_copy = Stack::new();
+    let mut had_witness = false;
+
+    eval_script(
+        &mut stack,
+        script_sig,
+        flags,
+        checker,
+        SignatureVersion::Base,
+    )?;
+
+    if flags.verify_p2sh {
+        stack_copy = stack.clone();
+    }
+
+    let res = eval_script(
+        &mut stack,
+        script_pubkey,
+        flags,
+        checker,
+        SignatureVersion::Base,
+    )?;
+    if !res {
+        return Err(Error::EvalFalse);
+    }
+
+    // Bare witness programs
+    if flags.verify_witness {
+        if let Some((witness_version, witness_program)) = script_pubkey.parse_witness_program() {
+            had_witness = true;
+
+            // The script_sig must be _exactly_ empty, otherwise we reintroduce malleability.
+            if !script_sig.is_empty() {
+                return Err(Error::WitnessMalleated);
+            }
+
+            verify_witness_program(
+                witness,
+                witness_version,
+                witness_program,
+                flags,
+                checker,
+                false,
+            )?;
+
+            // Bypass the cleanstack check at the end. The actual stack is obviously not clean
+            // for witness programs.
+            stack.truncate(1);
+        }
+    }
+
+    // Additional validation for spend-to-script-hash transactions:
+    if flags.verify_p2sh && script_pubkey.is_pay_to_script_hash() {
+        // script_sig must be literals-only or validation fails
+        if !script_sig.is_push_only() {
+            return Err(Error::SignaturePushOnly);
+        }
+
+        mem::swap(&mut stack, &mut stack_copy);
+
+        // stack cannot be empty here, because if it was the
+        // P2SH  HASH <> EQUAL  scriptPubKey would be evaluated with
+        // an empty stack and the `eval_script` above would return false.
+        assert!(!stack.is_empty());
+
+        let redeem_script: Script = stack.pop()?.into();
+        let res = eval_script(
+            &mut stack,
+            &redeem_script,
+            flags,
+            checker,
+            SignatureVersion::Base,
+        )?;
+        if !res {
+            return Err(Error::EvalFalse);
+        }
+
+        // P2SH witness program
+        if flags.verify_witness {
+            if let Some((witness_version, witness_program)) = redeem_script.parse_witness_program()
+            {
+                had_witness = true;
+
+                // The script_sig must be _exactly_ a single push of the redeem script. Otherwise we
+                // reintroduce malleability.
+                let expected_script_sig =
+                    Builder::default().push_data(&redeem_script).into_script();
+                if *script_sig != expected_script_sig {
+                    return Err(Error::WitnessMalleatedP2SH);
+                }
+
+                verify_witness_program(
+                    witness,
+                    witness_version,
+                    witness_program,
+                    flags,
+                    checker,
+                    true,
+                )?;
+
+                // Bypass the cleanstack check at the end. The actual stack is obviously not clean
+                // for witness programs.
+                stack.truncate(1);
+            }
+        }
+    }
+
+    // The CLEANSTACK check is only performed after potential P2SH evaluation,
+    // as the non-P2SH evaluation of a P2SH script will obviously not result in
+    // a clean stack (the P2SH inputs remain). The same holds for witness evaluation.
+    if flags.verify_cleanstack {
+        // Disallow CLEANSTACK without P2SH, as otherwise a switch
+        // CLEANSTACK->P2SH+CLEANSTACK would be possible, which is not a
+        // softfork (and P2SH should be one).
+        assert!(flags.verify_p2sh);
+        if stack.len() != 1 {
+            return Err(Error::Cleanstack);
+        }
+    }
+
+    if flags.verify_witness {
+        // We can't check for correct unexpected witness data if P2SH was off, so require
+        // that WITNESS implies P2SH. Otherwise, going from WITNESS->P2SH+WITNESS would be
+        // possible, which is not a softfork.
+        assert!(flags.verify_p2sh);
+        if !had_witness && !witness.is_empty() {
+            return Err(Error::WitnessUnexpected);
+        }
+    }
+
+    Ok(())
+}
+
+/// Verifies the witness of a witness program (BIP141).
+///
+/// Version 0 programs are either a 20-byte key hash (P2WPKH) or a 32-byte script hash (P2WSH).
+/// With `verify_taproot`, native version 1 programs of 32 bytes are taproot outputs (BIP341).
+/// Programs of other versions are left for future soft forks and always succeed, unless
+/// `verify_discourage_upgradable_witness_program` is set.
+fn verify_witness_program(
+    witness: &ScriptWitness,
+    witness_version: u8,
+    witness_program: &[u8],
+    flags: &VerificationFlags,
+    checker: &dyn SignatureChecker,
+    is_p2sh: bool,
+) -> Result<(), Error> {
+    if witness_version == 1 && witness_program.len() == 32 && !is_p2sh && flags.verify_taproot {
+        return verify_taproot_program(witness, witness_program, flags, checker);
+    }
+
+    if witness_version != 0 {
+        if flags.verify_discourage_upgradable_witness_program {
+            return Err(Error::DiscourageUpgradableWitnessProgram);
+        }
+        // Higher version witness scripts return true for future softfork compatibility
+        return Ok(());
+    }
+
+    let (stack, script_pubkey): (Stack<Bytes>, Script) = match witness_program.len() {
+        // Version 0 segregated witness program: SHA256(Script) inside the program,
+        // Script + inputs in witness
+        32 => {
+            let (witness_script, witness_stack) = match witness.split_last() {
+                Some(split) => split,
+                None => return Err(Error::WitnessProgramWitnessEmpty),
+            };
+
+            if sha256(witness_script).as_bytes() != witness_program {
+                return Err(Error::WitnessProgramMismatch);
+            }
+
+            (witness_stack.to_vec().into(), witness_script.clone().into())
+        }
+        // Special case for pay-to-pubkeyhash; signature + pubkey in witness
+        20 => {
+            if witness.len() != 2 {
+                return Err(Error::WitnessProgramMismatch);
+            }
+
+            let script_pubkey = Builder::build_p2pkh(&AddressHash::from_slice(witness_program));
+            (witness.clone().into(), script_pubkey)
+        }
+        _ => return Err(Error::WitnessProgramWrongLength),
+    };
+
+    execute_witness_script(
+        stack,
+        &script_pubkey,
+        flags,
+        checker,
+        SignatureVersion::WitnessV0,
+        &mut ScriptExecutionData::default(),
+    )
+}
+
+/// Verifies the witness of a taproot output (BIP341).
+///
+/// A single witness element (besides the optional annex) is a signature for the output key
+/// (key path spending). Otherwise the last two elements are the control block and the leaf
+/// script, which is executed on the remaining elements (script path spending).
+fn verify_taproot_program(
+    witness: &ScriptWitness,
+    witness_program: &[u8],
+    flags: &VerificationFlags,
+    checker: &dyn SignatureChecker,
+) -> Result<(), Error> {
+    let mut execdata = ScriptExecutionData::default();
+    let mut stack: &[Bytes] = witness;
+
+    if stack.is_empty() {
+        return Err(Error::WitnessProgramWitnessEmpty);
+    }
+
+    if stack.len() >= 2 {
+        if let Some((annex, rest)) = stack.split_last() {
+            if annex.first() == Some(&ANNEX_TAG) {
+                // Drop annex (this is non-standard; see IsWitnessStandard)
+                let mut annex_stream = Stream::default();
+                annex_stream.append(annex);
+                execdata.annex_hash = Some(sha256(&annex_stream.out()));
+                stack = rest;
+            }
+        }
+    }
+
+    let (control, rest) = match stack.split_last() {
+        // Key path spending (stack size is 1 after removing optional annex)
+        Some((signature, [])) => {
+            let public = Public::XOnly(H256::from_slice(witness_program));
+            return checker.check_schnorr_signature(
+                signature,
+                &public,
+                SignatureVersion::Taproot,
+                &execdata,
+            );
+        }
+        Some(split) => split,
+        None => return Err(Error::WitnessProgramWitnessEmpty),
+    };
+
+    // Script path spending (stack size is >1 after removing optional annex)
+    let (script, stack) = rest.split_last().ok_or(Error::WitnessProgramWitnessEmpty)?;
+    if control.len() < TAPROOT_CONTROL_BASE_SIZE
+        || control.len() > TAPROOT_CONTROL_MAX_SIZE
+        || !(control.len() - TAPROOT_CONTROL_BASE_SIZE).is_multiple_of(TAPROOT_CONTROL_NODE_SIZE)
+    {
+        return Err(Error::TaprootWrongControlSize);
+    }
+
+    let leaf_version = control[0] & TAPROOT_LEAF_MASK;
+    let script: Script = script.clone().into();
+    let leaf_hash = tapleaf_hash(leaf_version, &script);
+    if !verify_taproot_commitment(control, witness_program, &leaf_hash) {
+        return Err(Error::WitnessProgramMismatch);
+    }
+    execdata.tapleaf_hash = Some(leaf_hash);
+
+    if leaf_version != TAPROOT_LEAF_TAPSCRIPT {
+        // Other leaf versions are left for future soft forks and always succeed
+        if flags.verify_discourage_upgradable_taproot_version {
+            return Err(Error::DiscourageUpgradableTaprootVersion);
+        }
+        return Ok(());
+    }
+
+    execdata.validation_weight_left =
+        serialized_list_size::<Bytes, Bytes>(witness) as i64 + VALIDATION_WEIGHT_OFFSET;
+
+    execute_witness_script(
+        stack.to_vec().into(),
+        &script,
+        flags,
+        checker,
+        SignatureVersion::Tapscript,
+        &mut execdata,
+    )
+}
+
+/// Executes the script of a witness program on the witness stack.
+///
+/// Scripts inside witness implicitly require cleanstack behaviour.
+fn execute_witness_script(
+    mut stack: Stack<Bytes>,
+    script: &Script,
+    flags: &VerificationFlags,
+    checker: &dyn SignatureChecker,
+    version: SignatureVersion,
+    execdata: &mut ScriptExecutionData,
+) -> Result<(), Error> {
+    if version == SignatureVersion::Tapscript {
+        // OP_SUCCESSx processing overrides everything, including stack element size limits
+        let mut pc = 0;
+        while pc < script.len() {
+            if is_op_success(script[pc]) {
+                if flags.verify_discourage_op_success {
+                    return Err(Error::DiscourageOpSuccess);
+                }
+                return Ok(());
+            }
+
+            pc += match script.get_instruction(pc) {
+                Ok(instruction) => instruction.step,
+                // Undefined opcodes only fail when executed.
+                Err(Error::BadOpcode) if Opcode::from_u8(script[pc]).is_none() => 1,
+                Err(err) => return Err(err),
+            };
+        }
+
+        // Tapscript enforces initial stack size limits
+        if stack.len() > MAX_STACK_SIZE {
+            return Err(Error::StackSize);
+        }
+    }
+
+    // Disallow stack item size > MAX_SCRIPT_ELEMENT_SIZE in witness stack
+    if stack
+        .iter()
+        .any(|item| item.len() > MAX_SCRIPT_ELEMENT_SIZE)
+    {
+        return Err(Error::PushSize);
+    }
+
+    eval_script_with_execdata(&mut stack, script, flags, checker, version, execdata)?;
+
+    if stack.len() != 1 {
+        return Err(Error::Cleanstack);
+    }
+
+    if !cast_to_bool(stack.last()?) {
+        return Err(Error::EvalFalse);
+    }
+
+    Ok(())
+}
+
+/// Evaluates the script on the given stack.
+///
+/// Returns `Ok(true)` if the script has been executed successfully and left a true value
+/// on the top of the stack.
+pub fn eval_script(
+    stack: &mut Stack<Bytes>,
+    script: &Script,
+    flags: &VerificationFlags,
+    checker: &dyn SignatureChecker,
+    version: SignatureVersion,
+) -> Result<bool, Error> {
+    eval_script_with_execdata(
+        stack,
+        script,
+        flags,
+        checker,
+        version,
+        &mut ScriptExecutionData::default(),
+    )
+}
+
+fn eval_script_with_execdata(
+    stack: &mut Stack<Bytes>,
+    script: &Script,
+    flags: &VerificationFlags,
+    checker: &dyn SignatureChecker,
+    version: SignatureVersion,
+    execdata: &mut ScriptExecutionData,
+) -> Result<bool, Error> {
+    // Tapscript has no script size and opcode count limits (BIP342)
+    let is_tapscript = version == SignatureVersion::Tapscript;
+    if !is_tapscript && script.len() > MAX_SCRIPT_SIZE {
+        return Err(Error::ScriptSize);
+    }
+
+    let mut pc = 0;
+    let mut opcode_pos = 0u32;
+    let mut op_count = 0;
+    let mut begincode = 0;
+    let mut exec_stack = Vec::<bool>::new();
+    let mut altstack = Stack::<Bytes>::new();
+
+    while pc < script.len() {
+        let executing = exec_stack.iter().all(|x| *x);
+        let instruction = match script.get_instruction(pc) {
+            Ok(i) => i,
+            // Undefined opcodes only fail when executed.
+            Err(Error::BadOpcode) if !executing && Opcode::from_u8(script[pc]).is_none() => {
+                pc += 1;
+                opcode_pos += 1;
+                op_count += 1;
+                if !is_tapscript && op_count > MAX_OPS_PER_SCRIPT {
+                    return Err(Error::OpCount);
+                }
+                continue;
+            }
+            Err(err) => return Err(err),
+        };
+        let opcode = instruction.opcode;
+
+        if let Some(data) = instruction.data {
+            if data.len() > MAX_SCRIPT_ELEMENT_SIZE {
+                return Err(Error::PushSize);
+            }
+        }
+
+        pc += instruction.step;
+        let current_opcode_pos = opcode_pos;
+        opcode_pos += 1;
+
+        if opcode.is_countable() {
+            op_count += 1;
+            if !is_tapscript && op_count > MAX_OPS_PER_SCRIPT {
+                return Err(Error::OpCount);
+            }
+        }
+
+        if opcode.is_disabled(flags) {
+            return Err(Error::DisabledOpcode(opcode));
+        }
+
+        // With SCRIPT_VERIFY_CONST_SCRIPTCODE, OP_CODESEPARATOR in non-segwit script is rejected
+        // even in an unexecuted branch
+        if opcode == Opcode::OP_CODESEPARATOR
+            && version == SignatureVersion::Base
+            && flags.verify_const_scriptcode
+        {
+            return Err(Error::OpCodeSeparator);
+        }
+
+        if !(executing || (Opcode::OP_IF <= opcode && opcode <= Opcode::OP_ENDIF)) {
+            continue;
+        }
+
+        match opcode {
+            Opcode::OP_PUSHDATA1
+            | Opcode::OP_PUSHDATA2
+            | Opcode::OP_PUSHDATA4
+            | Opcode::OP_0
+            | Opcode::OP_PUSHBYTES_1
+            | Opcode::OP_PUSHBYTES_2
+            | Opcode::OP_PUSHBYTES_3
+            | Opcode::OP_PUSHBYTES_4
+            | Opcode::OP_PUSHBYTES_5
+            | Opcode::OP_PUSHBYTES_6
+            | Opcode::OP_PUSHBYTES_7
+            | Opcode::OP_PUSHBYTES_8
+            | Opcode::OP_PUSHBYTES_9
+            | Opcode::OP_PUSHBYTES_10
+            | Opcode::OP_PUSHBYTES_11
+            | Opcode::OP_PUSHBYTES_12
+            | Opcode::OP_PUSHBYTES_13
+            | Opcode::OP_PUSHBYTES_14
+            | Opcode::OP_PUSHBYTES_15
+            | Opcode::OP_PUSHBYTES_16
+            | Opcode::OP_PUSHBYTES_17
+            | Opcode::OP_PUSHBYTES_18
+            | Opcode::OP_PUSHBYTES_19
+            | Opcode::OP_PUSHBYTES_20
+            | Opcode::OP_PUSHBYTES_21
+            | Opcode::OP_PUSHBYTES_22
+            | Opcode::OP_PUSHBYTES_23
+            | Opcode::OP_PUSHBYTES_24
+            | Opcode::OP_PUSHBYTES_25
+            | Opcode::OP_PUSHBYTES_26
+            | Opcode::OP_PUSHBYTES_27
+            | Opcode::OP_PUSHBYTES_28
+            | Opcode::OP_PUSHBYTES_29
+            | Opcode::OP_PUSHBYTES_30
+            | Opcode::OP_PUSHBYTES_31
+            | Opcode::OP_PUSHBYTES_32
+            | Opcode::OP_PUSHBYTES_33
+            | Opcode::OP_PUSHBYTES_34
+            | Opcode::OP_PUSHBYTES_35
+            | Opcode::OP_PUSHBYTES_36
+            | Opcode::OP_PUSHBYTES_37
+            | Opcode::OP_PUSHBYTES_38
+            | Opcode::OP_PUSHBYTES_39
+            | Opcode::OP_PUSHBYTES_40
+            | Opcode::OP_PUSHBYTES_41
+            | Opcode::OP_PUSHBYTES_42
+            | Opcode::OP_PUSHBYTES_43
+            | Opcode::OP_PUSHBYTES_44
+            | Opcode::OP_PUSHBYTES_45
+            | Opcode::OP_PUSHBYTES_46
+            | Opcode::OP_PUSHBYTES_47
+            | Opcode::OP_PUSHBYTES_48
+            | Opcode::OP_PUSHBYTES_49
+            | Opcode::OP_PUSHBYTES_50
+            | Opcode::OP_PUSHBYTES_51
+            | Opcode::OP_PUSHBYTES_52
+            | Opcode::OP_PUSHBYTES_53
+            | Opcode::OP_PUSHBYTES_54
+            | Opcode::OP_PUSHBYTES_55
+            | Opcode::OP_PUSHBYTES_56
+            | Opcode::OP_PUSHBYTES_57
+            | Opcode::OP_PUSHBYTES_58
+            | Opcode::OP_PUSHBYTES_59
+            | Opcode::OP_PUSHBYTES_60
+            | Opcode::OP_PUSHBYTES_61
+            | Opcode::OP_PUSHBYTES_62
+            | Opcode::OP_PUSHBYTES_63
+            | Opcode::OP_PUSHBYTES_64
+            | Opcode::OP_PUSHBYTES_65
+            | Opcode::OP_PUSHBYTES_66
+            | Opcode::OP_PUSHBYTES_67
+            | Opcode::OP_PUSHBYTES_68
+            | Opcode::OP_PUSHBYTES_69
+            | Opcode::OP_PUSHBYTES_70
+            | Opcode::OP_PUSHBYTES_71
+            | Opcode::OP_PUSHBYTES_72
+            | Opcode::OP_PUSHBYTES_73
+            | Opcode::OP_PUSHBYTES_74
+            | Opcode::OP_PUSHBYTES_75 => {
+                if let Some(data) = instruction.data {
+                    if flags.verify_minimaldata && !check_minimal_push(data, opcode) {
+                        return Err(Error::Minimaldata);
+                    }
+                    stack.push(data.into());
+                }
+            }
+            Opcode::OP_1NEGATE
+            | Opcode::OP_1
+            | Opcode::OP_2
+            | Opcode::OP_3
+            | Opcode::OP_4
+            | Opcode::OP_5
+            | Opcode::OP_6
+            | Opcode::OP_7
+            | Opcode::OP_8
+            | Opcode::OP_9
+            | Opcode::OP_10
+            | Opcode::OP_11
+            | Opcode::OP_12
+            | Opcode::OP_13
+            | Opcode::OP_14
+            | Opcode::OP_15
+            | Opcode::OP_16 => {
+                let value = opcode as i32 - (Opcode::OP_1 as i32 - 1);
+                stack.push(Num::from(value).to_bytes());
+            }
+            Opcode::OP_CAT if flags.verify_concat => {
+                let mut value_to_append = stack.pop()?;
+                let value_to_update = stack.last_mut()?;
+                if value_to_update.len() + value_to_append.len() > MAX_SCRIPT_ELEMENT_SIZE {
+                    return Err(Error::PushSize);
+                }
+                value_to_update.append(&mut value_to_append);
+            }
+            // OP_SPLIT replaces OP_SUBSTR
+            Opcode::OP_SUBSTR if flags.verify_split => {
+                let n = Num::from_slice(
+                    &stack.pop()?,
+                    flags.verify_minimaldata,
+                    DEFAULT_SCRIPT_NUM_SIZE,
+                )?;
+                if n.is_negative() {
+                    return Err(Error::InvalidStackOperation);
+                }
+                let n: usize = n.into();
+                let splitted_value = {
+                    let value_to_split = stack.last_mut()?;
+                    if n > value_to_split.len() {
+                        return Err(Error::InvalidSplitRange);
+                    }
+                    value_to_split.split_off(n)
+                };
+                stack.push(splitted_value);
+            }
+            Opcode::OP_AND if flags.verify_and => {
+                let mask = stack.pop()?;
+                let value_to_update = stack.last_mut()?;
+                if mask.len() != value_to_update.len() {
+                    return Err(Error::InvalidOperandSize);
+                }
+                for (byte_to_update, byte_mask) in value_to_update.iter_mut().zip(mask.iter()) {
+                    *byte_to_update &= byte_mask;
+                }
+            }
+            Opcode::OP_OR if flags.verify_or => {
+                let mask = stack.pop()?;
+                let value_to_update = stack.last_mut()?;
+                if mask.len() != value_to_update.len() {
+                    return Err(Error::InvalidOperandSize);
+                }
+                for (byte_to_update, byte_mask) in value_to_update.iter_mut().zip(mask.iter()) {
+                    *byte_to_update |= byte_mask;
+                }
+            }
+            Opcode::OP_XOR if flags.verify_xor => {
+                let mask = stack.pop()?;
+                let value_to_update = stack.last_mut()?;
+                if mask.len() != value_to_update.len() {
+                    return Err(Error::InvalidOperandSize);
+                }
+                for (byte_to_update, byte_mask) in value_to_update.iter_mut().zip(mask.iter()) {
+                    *byte_to_update ^= byte_mask;
+                }
+            }
+            Opcode::OP_DIV if flags.verify_div => {
+                let v1 = Num::from_slice(
+                    &stack.pop()?,
+                    flags.verify_minimaldata,
+                    DEFAULT_SCRIPT_NUM_SIZE,
+                )?;
+                let v2 = Num::from_slice(
+                    &stack.pop()?,
+                    flags.verify_minimaldata,
+                    DEFAULT_SCRIPT_NUM_SIZE,
+                )?;
+                if v1.is_zero() {
+                    return Err(Error::DivisionByZero);
+                }
+                stack.push((v2 / v1).to_bytes());
+            }
+            Opcode::OP_MOD if flags.verify_mod => {
+                let v1 = Num::from_slice(
+                    &stack.pop()?,
+                    flags.verify_minimaldata,
+                    DEFAULT_SCRIPT_NUM_SIZE,
+                )?;
+                let v2 = Num::from_slice(
+                    &stack.pop()?,
+                    flags.verify_minimaldata,
+                    DEFAULT_SCRIPT_NUM_SIZE,
+                )?;
+                if v1.is_zero() {
+                    return Err(Error::DivisionByZero);
+                }
+                stack.push((v2 % v1).to_bytes());
+            }
+            // OP_BIN2NUM replaces OP_RIGHT
+            Opcode::OP_RIGHT if flags.verify_bin2num => {
+                let bin = stack.pop()?;
+                let n = Num::minimally_encode(&bin, DEFAULT_SCRIPT_NUM_SIZE)?;
+                stack.push(n.to_bytes());
+            }
+            // OP_NUM2BIN replaces OP_LEFT
+            Opcode::OP_LEFT if flags.verify_num2bin => {
+                let bin_size = Num::from_slice(
+                    &stack.pop()?,
+                    flags.verify_minimaldata,
+                    DEFAULT_SCRIPT_NUM_SIZE,
+                )?;
+                if bin_size.is_negative() || bin_size > MAX_SCRIPT_ELEMENT_SIZE.into() {
+                    return Err(Error::PushSize);
+                }
+                let bin_size: usize = bin_size.into();
+
+                let num = Num::minimally_encode(&stack.pop()?, NUM2BIN_SCRIPT_NUM_SIZE)?;
+                let mut num = num.to_bytes();
+                if num.len() > bin_size {
+                    return Err(Error::ImpossibleEncoding);
+                }
+
+                if num.len() < bin_size {
+                    // the sign bit is moved to the last byte of the padded value
+                    let sign = match num.last_mut() {
+                        Some(last) => {
+                            let sign = *last & 0x80;
+                            *last &= 0x7f;
+                            sign
+                        }
+                        None => 0,
+                    };
+                    let mut num_vec = num.take();
+                    num_vec.resize(bin_size, 0);
+                    num_vec[bin_size - 1] |= sign;
+                    num = num_vec.into();
+                }
+
+                stack.push(num);
+            }
+            Opcode::OP_CAT
+            | Opcode::OP_SUBSTR
+            | Opcode::OP_LEFT
+            | Opcode::OP_RIGHT
+            | Opcode::OP_INVERT
+            | Opcode::OP_AND
+            | Opcode::OP_OR
+            | Opcode::OP_XOR
+            | Opcode::OP_2MUL
+            | Opcode::OP_2DIV
+            | Opcode::OP_MUL
+            | Opcode::OP_DIV
+            | Opcode::OP_MOD
+            | Opcode::OP_LSHIFT
+            | Opcode::OP_RSHIFT => {
+                return Err(Error::DisabledOpcode(opcode));
+            }
+            Opcode::OP_NOP => {}
+            Opcode::OP_CHECKLOCKTIMEVERIFY => {
+                if flags.verify_locktime {
+                    // Note that elsewhere numeric opcodes are limited to
+                    // operands in the range -2**31+1 to 2**31-1, however it is
+                    // legal for opcodes to produce results exceeding that
+                    // range. This limitation is implemented by `Num`'s
+                    // default 4-byte limit.
+                    //
+                    // If we kept to that limit we'd have a year 2038 problem,
+                    // even though the nLockTime field in transactions
+                    // themselves is uint32 which only becomes meaningless
+                    // after the year 2106.
+                    //
+                    // Thus as a special case we tell `Num` to accept up
+                    // to 5-byte bignums, which are good until 2**39-1, well
+                    // beyond the 2**32-1 limit of the nLockTime field itself.
+                    let lock_time = Num::from_slice(
+                        stack.last()?,
+                        flags.verify_minimaldata,
+                        LOCKTIME_SCRIPT_NUM_SIZE,
+                    )?;
+
+                    // In the rare event that the argument may be < 0 due to
+                    // some arithmetic being done first, you can always use
+                    // 0 MAX CHECKLOCKTIMEVERIFY.
+                    if lock_time.is_negative() {
+                        return Err(Error::NegativeLocktime);
+                    }
+
+                    if !checker.check_lock_time(lock_time) {
+                        return Err(Error::UnsatisfiedLocktime);
+                    }
+                } else if flags.verify_discourage_upgradable_nops {
+                    return Err(Error::DiscourageUpgradableNops);
+                }
+            }
+            Opcode::OP_CHECKSEQUENCEVERIFY => {
+                if flags.verify_checksequence {
+                    let sequence = Num::from_slice(
+                        stack.last()?,
+                        flags.verify_minimaldata,
+                        LOCKTIME_SCRIPT_NUM_SIZE,
+                    )?;
+
+                    if sequence.is_negative() {
+                        return Err(Error::NegativeLocktime);
+                    }
+
+                    // To provide for future soft-fork extensibility, if the
+                    // operand has the disabled lock-time flag set,
+                    // CHECKSEQUENCEVERIFY behaves as a NOP.
+                    if (sequence & Num::from(SEQUENCE_LOCKTIME_DISABLE_FLAG)).is_zero()
+                        && !checker.check_sequence(sequence)
+                    {
+                        return Err(Error::UnsatisfiedLocktime);
+                    }
+                } else if flags.verify_discourage_upgradable_nops {
+                    return Err(Error::DiscourageUpgradableNops);
+                }
+            }
+            Opcode::OP_NOP1
+            | Opcode::OP_NOP4
+            | Opcode::OP_NOP5
+            | Opcode::OP_NOP6
+            | Opcode::OP_NOP7
+            | Opcode::OP_NOP8
+            | Opcode::OP_NOP9
+            | Opcode::OP_NOP10 => {
+                if flags.verify_discourage_upgradable_nops {
+                    return Err(Error::DiscourageUpgradableNops);
+                }
+            }
+            Opcode::OP_IF | Opcode::OP_NOTIF => {
+                let mut exec_value = false;
+                if executing {
+                    let condition = stack.pop().map_err(|_| Error::UnbalancedConditional)?;
+                    // Tapscript requires minimal IF/NOTIF inputs as a consensus rule
+                    if is_tapscript
+                        && (condition.len() > 1 || (condition.len() == 1 && condition[0] != 1))
+                    {
+                        return Err(Error::TapscriptMinimalIf);
+                    }
+                    // Under witness v0 rules it is only a policy rule, enabled through MINIMALIF
+                    if version == SignatureVersion::WitnessV0
+                        && flags.verify_minimalif
+                        && (condition.len() > 1 || (condition.len() == 1 && condition[0] != 1))
+                    {
+                        return Err(Error::MinimalIf);
+                    }
+                    exec_value = cast_to_bool(&condition);
+                    if opcode == Opcode::OP_NOTIF {
+                        exec_value = !exec_value;
+                    }
+                }
+                exec_stack.push(exec_value);
+            }
+            Opcode::OP_ELSE => match exec_stack.last_mut() {
+                Some(last) => *last = !*last,
+                None => return Err(Error::UnbalancedConditional),
+            },
+            Opcode::OP_ENDIF => {
+                if exec_stack.pop().is_none() {
+                    return Err(Error::UnbalancedConditional);
+                }
+            }
+            Opcode::OP_VERIFY => {
+                let exec_value = cast_to_bool(&stack.pop()?);
+                if !exec_value {
+                    return Err(Error::Verify);
+                }
+            }
+            Opcode::OP_RETURN => {
+                return Err(Error::ReturnOpcode);
+            }
+            Opcode::OP_TOALTSTACK => {
+                altstack.push(stack.pop()?);
+            }
+            Opcode::OP_FROMALTSTACK => {
+                stack.push(
+                    altstack
+                        .pop()
+                        .map_err(|_| Error::InvalidAltstackOperation)?,
+                );
+            }
+            Opcode::OP_2DROP => {
+                stack.drop(2)?;
+            }
+            Opcode::OP_2DUP => {
+                stack.dup(2)?;
+            }
+            Opcode::OP_3DUP => {
+                stack.dup(3)?;
+            }
+            Opcode::OP_2OVER => {
+                stack.over(2)?;
+            }
+            Opcode::OP_2ROT => {
+                stack.rot(2)?;
+            }
+            Opcode::OP_2SWAP => {
+                stack.swap(2)?;
+            }
+            Opcode::OP_IFDUP => {
+                if cast_to_bool(stack.last()?) {
+                    stack.dup(1)?;
+                }
+            }
+            Opcode::OP_DEPTH => {
+                let depth = Num::from(stack.len());
+                stack.push(depth.to_bytes());
+            }
+            Opcode::OP_DROP => {
+                stack.pop()?;
+            }
+            Opcode::OP_DUP => {
+                stack.dup(1)?;
+            }
+            Opcode::OP_NIP => {
+                stack.nip()?;
+            }
+            Opcode::OP_OVER => {
+                stack.over(1)?;
+            }
+            Opcode::OP_PICK | Opcode::OP_ROLL => {
+                let n: i64 = Num::from_slice(
+                    &stack.pop()?,
+                    flags.verify_minimaldata,
+                    DEFAULT_SCRIPT_NUM_SIZE,
+                )?
+                .into();
+                if n < 0 || n >= stack.len() as i64 {
+                    return Err(Error::InvalidStackOperation);
+                }
+
+                let v = match opcode {
+                    Opcode::OP_PICK => stack.top(n as usize)?.clone(),
+                    _ => stack.remove(n as usize)?,
+                };
+
+                stack.push(v);
+            }
+            Opcode::OP_ROT => {
+                stack.rot(1)?;
+            }
+            Opcode::OP_SWAP => {
+                stack.swap(1)?;
+            }
+            Opcode::OP_TUCK => {
+                stack.tuck()?;
+            }
+            Opcode::OP_SIZE => {
+                let n = Num::from(stack.last()?.len());
+                stack.push(n.to_bytes());
+            }
+            Opcode::OP_EQUAL => {
+                let v1 = stack.pop()?;
+                let v2 = stack.pop()?;
+                stack.push(Num::from(v1 == v2).to_bytes());
+            }
+            Opcode::OP_EQUALVERIFY => {
+                let equal = stack.pop()? == stack.pop()?;
+                if !equal {
+                    return Err(Error::EqualVerify);
+                }
+            }
+            Opcode::OP_1ADD => {
+                let n = Num::from_slice(
+                    &stack.pop()?,
+                    flags.verify_minimaldata,
+                    DEFAULT_SCRIPT_NUM_SIZE,
+                )? + Num::from(1);
+                stack.push(n.to_bytes());
+            }
+            Opcode::OP_1SUB => {
+                let n = Num::from_slice(
+                    &stack.pop()?,
+                    flags.verify_minimaldata,
+                    DEFAULT_SCRIPT_NUM_SIZE,
+                )? - Num::from(1);
+                stack.push(n.to_bytes());
+            }
+            Opcode::OP_NEGATE => {
+                let n = -Num::from_slice(
+                    &stack.pop()?,
+                    flags.verify_minimaldata,
+                    DEFAULT_SCRIPT_NUM_SIZE,
+                )?;
+                stack.push(n.to_bytes());
+            }
+            Opcode::OP_ABS => {
+                let n = Num::from_slice(
+                    &stack.pop()?,
+                    flags.verify_minimaldata,
+                    DEFAULT_SCRIPT_NUM_SIZE,
+                )?
+                .abs();
+                stack.push(n.to_bytes());
+            }
+            Opcode::OP_NOT => {
+                let n = Num::from_slice(
+                    &stack.pop()?,
+                    flags.verify_minimaldata,
+                    DEFAULT_SCRIPT_NUM_SIZE,
+                )?
+                .is_zero();
+                stack.push(Num::from(n).to_bytes());
+            }
+            Opcode::OP_0NOTEQUAL => {
+                let n = !Num::from_slice(
+                    &stack.pop()?,
+                    flags.verify_minimaldata,
+                    DEFAULT_SCRIPT_NUM_SIZE,
+                )?
+                .is_zero();
+                stack.push(Num::from(n).to_bytes());
+            }
+            Opcode::OP_ADD => {
+                let v1 = Num::from_slice(
+                    &stack.pop()?,
+                    flags.verify_minimaldata,
+                    DEFAULT_SCRIPT_NUM_SIZE,
+                )?;
+                let v2 = Num::from_slice(
+                    &stack.pop()?,
+                    flags.verify_minimaldata,
+                    DEFAULT_SCRIPT_NUM_SIZE,
+                )?;
+                stack.push((v1 + v2).to_bytes());
+            }
+            Opcode::OP_SUB => {
+                let v1 = Num::from_slice(
+                    &stack.pop()?,
+                    flags.verify_minimaldata,
+                    DEFAULT_SCRIPT_NUM_SIZE,
+                )?;
+                let v2 = Num::from_slice(
+                    &stack.pop()?,
+                    flags.verify_minimaldata,
+                    DEFAULT_SCRIPT_NUM_SIZE,
+                )?;
+                stack.push((v2 - v1).to_bytes());
+            }
+            Opcode::OP_BOOLAND => {
+                let v1 = Num::from_slice(
+                    &stack.pop()?,
+                    flags.verify_minimaldata,
+                    DEFAULT_SCRIPT_NUM_SIZE,
+                )?;
+                let v2 = Num::from_slice(
+                    &stack.pop()?,
+                    flags.verify_minimaldata,
+                    DEFAULT_SCRIPT_NUM_SIZE,
+                )?;
+                let v = Num::from(!v1.is_zero() && !v2.is_zero());
+                stack.push(v.to_bytes());
+            }
+            Opcode::OP_BOOLOR => {
+                let v1 = Num::from_slice(
+                    &stack.pop()?,
+                    flags.verify_minimaldata,
+                    DEFAULT_SCRIPT_NUM_SIZE,
+                )?;
+                let v2 = Num::from_slice(
+                    &stack.pop()?,
+                    flags.verify_minimaldata,
+                    DEFAULT_SCRIPT_NUM_SIZE,
+                )?;
+                let v = Num::from(!v1.is_zero() || !v2.is_zero());
+                stack.push(v.to_bytes());
+            }
+            Opcode::OP_NUMEQUAL => {
+                let v1 = Num::from_slice(
+                    &stack.pop()?,
+                    flags.verify_minimaldata,
+                    DEFAULT_SCRIPT_NUM_SIZE,
+                )?;
+                let v2 = Num::from_slice(
+                    &stack.pop()?,
+                    flags.verify_minimaldata,
+                    DEFAULT_SCRIPT_NUM_SIZE,
+                )?;
+                let v = Num::from(v1 == v2);
+                stack.push(v.to_bytes());
+            }
+            Opcode::OP_NUMEQUALVERIFY => {
+                let v1 = Num::from_slice(
+                    &stack.pop()?,
+                    flags.verify_minimaldata,
+                    DEFAULT_SCRIPT_NUM_SIZE,
+                )?;
+                let v2 = Num::from_slice(
+                    &stack.pop()?,
+                    flags.verify_minimaldata,
+                    DEFAULT_SCRIPT_NUM_SIZE,
+                )?;
+                if v1 != v2 {
+                    return Err(Error::NumEqualVerify);
+                }
+            }
+            Opcode::OP_NUMNOTEQUAL => {
+                let v1 = Num::from_slice(
+                    &stack.pop()?,
+                    flags.verify_minimaldata,
+                    DEFAULT_SCRIPT_NUM_SIZE,
+                )?;
+                let v2 = Num::from_slice(
+                    &stack.pop()?,
+                    flags.verify_minimaldata,
+                    DEFAULT_SCRIPT_NUM_SIZE,
+                )?;
+                let v = Num::from(v1 != v2);
+                stack.push(v.to_bytes());
+            }
+            Opcode::OP_LESSTHAN => {
+                let v1 = Num::from_slice(
+                    &stack.pop()?,
+                    flags.verify_minimaldata,
+                    DEFAULT_SCRIPT_NUM_SIZE,
+                )?;
+                let v2 = Num::from_slice(
+                    &stack.pop()?,
+                    flags.verify_minimaldata,
+                    DEFAULT_SCRIPT_NUM_SIZE,
+                )?;
+                let v = Num::from(v2 < v1);
+                stack.push(v.to_bytes());
+            }
+            Opcode::OP_GREATERTHAN => {
+                let v1 = Num::from_slice(
+                    &stack.pop()?,
+                    flags.verify_minimaldata,
+                    DEFAULT_SCRIPT_NUM_SIZE,
+                )?;
+                let v2 = Num::from_slice(
+                    &stack.pop()?,
+                    flags.verify_minimaldata,
+                    DEFAULT_SCRIPT_NUM_SIZE,
+                )?;
+                let v = Num::from(v2 > v1);
+                stack.push(v.to_bytes());
+            }
+            Opcode::OP_LESSTHANOREQUAL => {
+                let v1 = Num::from_slice(
+                    &stack.pop()?,
+                    flags.verify_minimaldata,
+                    DEFAULT_SCRIPT_NUM_SIZE,
+                )?;
+                let v2 = Num::from_slice(
+                    &stack.pop()?,
+                    flags.verify_minimaldata,
+                    DEFAULT_SCRIPT_NUM_SIZE,
+                )?;
+                let v = Num::from(v2 <= v1);
+                stack.push(v.to_bytes());
+            }
+            Opcode::OP_GREATERTHANOREQUAL => {
+                let v1 = Num::from_slice(
+                    &stack.pop()?,
+                    flags.verify_minimaldata,
+                    DEFAULT_SCRIPT_NUM_SIZE,
+                )?;
+                let v2 = Num::from_slice(
+                    &stack.pop()?,
+                    flags.verify_minimaldata,
+                    DEFAULT_SCRIPT_NUM_SIZE,
+                )?;
+                let v = Num::from(v2 >= v1);
+                stack.push(v.to_bytes());
+            }
+            Opcode::OP_MIN => {
+                let v1 = Num::from_slice(
+                    &stack.pop()?,
+                    flags.verify_minimaldata,
+                    DEFAULT_SCRIPT_NUM_SIZE,
+                )?;
+                let v2 = Num::from_slice(
+                    &stack.pop()?,
+                    flags.verify_minimaldata,
+                    DEFAULT_SCRIPT_NUM_SIZE,
+                )?;
+                stack.push(core::cmp::min(v1, v2).to_bytes());
+            }
+            Opcode::OP_MAX => {
+                let v1 = Num::from_slice(
+                    &stack.pop()?,
+                    flags.verify_minimaldata,
+                    DEFAULT_SCRIPT_NUM_SIZE,
+                )?;
+                let v2 = Num::from_slice(
+                    &stack.pop()?,
+                    flags.verify_minimaldata,
+                    DEFAULT_SCRIPT_NUM_SIZE,
+                )?;
+                stack.push(core::cmp::max(v1, v2).to_bytes());
+            }
+            Opcode::OP_WITHIN => {
+                let max = Num::from_slice(
+                    &stack.pop()?,
+                    flags.verify_minimaldata,
+                    DEFAULT_SCRIPT_NUM_SIZE,
+                )?;
+                let min = Num::from_slice(
+                    &stack.pop()?,
+                    flags.verify_minimaldata,
+                    DEFAULT_SCRIPT_NUM_SIZE,
+                )?;
+                let value = Num::from_slice(
+                    &stack.pop()?,
+                    flags.verify_minimaldata,
+                    DEFAULT_SCRIPT_NUM_SIZE,
+                )?;
+                let v = Num::from(min <= value && value < max);
+                stack.push(v.to_bytes());
+            }
+            Opcode::OP_RIPEMD160 => {
+                let v = ripemd160(&stack.pop()?);
+                stack.push(v.as_bytes().into());
+            }
+            Opcode::OP_SHA1 => {
+                let v = sha1(&stack.pop()?);
+                stack.push(v.as_bytes().into());
+            }
+            Opcode::OP_SHA256 => {
+                let v = sha256(&stack.pop()?);
+                stack.push(v.as_bytes().into());
+            }
+            Opcode::OP_HASH160 => {
+                let v = dhash160(&stack.pop()?);
+                stack.push(v.as_bytes().into());
+            }
+            Opcode::OP_HASH256 => {
+                let v = dhash256(&stack.pop()?);
+                stack.push(v.as_bytes().into());
+            }
+            Opcode::OP_CODESEPARATOR => {
+                begincode = pc;
+                execdata.codeseparator_pos = current_opcode_pos;
+            }
+            Opcode::OP_CHECKSIG | Opcode::OP_CHECKSIGVERIFY => {
+                let pubkey = stack.pop()?;
+                let signature = stack.pop()?;
+
+                let success = if is_tapscript {
+                    eval_checksig_tapscript(checker, &signature, &pubkey, flags, execdata)?
+                } else {
+                    // Subset of script starting at the most recent codeseparator
+                    let mut subscript = script.subscript(begincode);
+
+                    // Drop the signature in pre-segwit scripts but not segwit scripts
+                    if version == SignatureVersion::Base {
+                        let signature_script =
+                            Builder::default().push_data(&signature).into_script();
+                        let found = subscript.find_and_delete(&signature_script);
+                        if found.len() != subscript.len() && flags.verify_const_scriptcode {
+                            return Err(Error::SignatureFindAndDelete);
+                        }
+                        subscript = found;
+                    }
+
+                    check_signature_encoding(&signature, flags, version)?;
+                    check_pubkey_encoding(&pubkey, flags, version)?;
+
+                    let success =
+                        check_signature(checker, &signature, &pubkey, &subscript, version);
+                    if !success && flags.verify_nullfail && !signature.is_empty() {
+                        return Err(Error::SignatureNullFail);
+                    }
+                    success
+                };
+                match opcode {
+                    Opcode::OP_CHECKSIG => {
+                        stack.push(Num::from(success).to_bytes());
+                    }
+                    _ => {
+                        if !success {
+                            return Err(Error::CheckSigVerify);
+                        }
+                    }
+                }
+            }
+            Opcode::OP_CHECKMULTISIG | Opcode::OP_CHECKMULTISIGVERIFY => {
+                if is_tapscript {
+                    return Err(Error::TapscriptCheckMultisig);
+                }
+
+                let keys_count = Num::from_slice(
+                    &stack.pop()?,
+                    flags.verify_minimaldata,
+                    DEFAULT_SCRIPT_NUM_SIZE,
+                )?;
+                if keys_count.is_negative() || keys_count > MAX_PUBKEYS_PER_MULTISIG.into() {
+                    return Err(Error::PubkeyCount);
+                }
+
+                let keys_count: usize = keys_count.into();
+                op_count += keys_count as u32;
+                if op_count > MAX_OPS_PER_SCRIPT {
+                    return Err(Error::OpCount);
+                }
+
+                let keys = (0..keys_count)
+                    .map(|_| stack.pop())
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                let sigs_count = Num::from_slice(
+                    &stack.pop()?,
+                    flags.verify_minimaldata,
+                    DEFAULT_SCRIPT_NUM_SIZE,
+                )?;
+                if sigs_count.is_negative() || sigs_count > keys_count.into() {
+                    return Err(Error::SigCount);
+                }
+
+                let sigs_count: usize = sigs_count.into();
+                let sigs = (0..sigs_count)
+                    .map(|_| stack.pop())
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                // Subset of script starting at the most recent codeseparator
+                let mut subscript = script.subscript(begincode);
+
+                // Drop the signatures in pre-segwit scripts but not segwit scripts
+                if version == SignatureVersion::Base {
+                    for signature in &sigs {
+                        let signature_script =
+                            Builder::default().push_data(signature).into_script();
+                        let found = subscript.find_and_delete(&signature_script);
+                        if found.len() != subscript.len() && flags.verify_const_scriptcode {
+                            return Err(Error::SignatureFindAndDelete);
+                        }
+                        subscript = found;
+                    }
+                }
+
+                let mut success = true;
+                let mut k = 0;
+                let mut s = 0;
+                while s < sigs.len() && success {
+                    let key = &keys[k];
+                    let sig = &sigs[s];
+
+                    // Note how this makes the exact order of pubkey/signature evaluation
+                    // distinguishable by CHECKMULTISIG NOT if the STRICTENC flag is set.
+                    check_signature_encoding(sig, flags, version)?;
+                    check_pubkey_encoding(key, flags, version)?;
+
+                    if check_signature(checker, sig, key, &subscript, version) {
+                        s += 1;
+                    }
+                    k += 1;
+
+                    // If there are more signatures left than keys left,
+                    // then too many signatures have failed. Exit early,
+                    // without checking any further signatures.
+                    success = sigs.len() - s <= keys.len() - k;
+                }
+
+                // If the operation failed, we require that all signatures must be empty vector
+                if !success && flags.verify_nullfail && sigs.iter().any(|sig| !sig.is_empty()) {
+                    return Err(Error::SignatureNullFail);
+                }
+
+                // A bug causes CHECKMULTISIG to consume one extra argument
+                // whose contents were not checked in any way.
+                //
+                // Unfortunately this is a potential source of mutability,
+                // so optionally verify it is exactly equal to zero prior
+                // to removing it from the stack.
+                if !stack.pop()?.is_empty() && flags.verify_nulldummy {
+                    return Err(Error::SignatureNullDummy);
+                }
+
+                match opcode {
+                    Opcode::OP_CHECKMULTISIG => {
+                        stack.push(Num::from(success).to_bytes());
+                    }
+                    _ => {
+                        if !success {
+                            return Err(Error::CheckSigVerify);
+                        }
+                    }
+                }
+            }
+            Opcode::OP_CHECKSIGADD if is_tapscript => {
+                // (sig num pubkey -- num)
+                let pubkey = stack.pop()?;
+                let num = Num::from_slice(
+                    &stack.pop()?,
+                    flags.verify_minimaldata,
+                    DEFAULT_SCRIPT_NUM_SIZE,
+                )?;
+                let signature = stack.pop()?;
+
+                let success =
+                    eval_checksig_tapscript(checker, &signature, &pubkey, flags, execdata)?;
+                stack.push((num + Num::from(success)).to_bytes());
+            }
+            Opcode::OP_CHECKDATASIG | Opcode::OP_CHECKDATASIGVERIFY
+                if flags.verify_checkdatasig =>
+            {
+                let pubkey = stack.pop()?;
+                let message = stack.pop()?;
+                let signature = stack.pop()?;
+
+                check_data_signature_encoding(&signature, flags)?;
+                check_pubkey_encoding(&pubkey, flags, version)?;
+
+                let success = verify_data_signature(checker, &signature, &pubkey, &message);
+                match opcode {
+                    Opcode::OP_CHECKDATASIG => {
+                        stack.push(Num::from(success).to_bytes());
+                    }
+                    _ => {
+                        if !success {
+                            return Err(Error::CheckDataSigVerify);
+                        }
+                    }
+                }
+            }
+            Opcode::OP_CHECKDATASIG
+            | Opcode::OP_CHECKDATASIGVERIFY
+            | Opcode::OP_RESERVED
+            | Opcode::OP_VER
+            | Opcode::OP_VERIF
+            | Opcode::OP_VERNOTIF
+            | Opcode::OP_RESERVED1
+            | Opcode::OP_RESERVED2 => {
+                return Err(Error::BadOpcode);
+            }
+        }
+
+        if stack.len() + altstack.len() > MAX_STACK_SIZE {
+            return Err(Error::StackSize);
+        }
+    }
+
+    if !exec_stack.is_empty() {
+        return Err(Error::UnbalancedConditional);
+    }
+
+    let success = !stack.is_empty() && cast_to_bool(stack.last()?);
+    Ok(success)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use light_bitcoin_chain::Transaction;
+    use light_bitcoin_keys::{KeyPair, Private};
+
+    use crate::sign::TransactionInputSigner;
+    use crate::verify::{NoopSignatureChecker, TransactionSignatureChecker};
+
+    fn basic_test_with_flags(
+        script: &Script,
+        flags: &VerificationFlags,
+        expected: Result<bool, Error>,
+        expected_stack: Stack<Bytes>,
+    ) {
+        let checker = NoopSignatureChecker;
+        let version = SignatureVersion::Base;
+        let mut stack = Stack::new();
+        assert_eq!(
+            eval_script(&mut stack, script, flags, &checker, version),
+            expected
+        );
+        if expected.is_ok() {
+            assert_eq!(stack, expected_stack);
+        }
+    }
+
+    fn basic_test(script: &Script, expected: Result<bool, Error>, expected_stack: Stack<Bytes>) {
+        let flags = VerificationFlags::default().verify_p2sh(true);
+        basic_test_with_flags(script, &flags, expected, expected_stack)
+    }
+
+    fn keypair(wif: &str) -> KeyPair {
+        let private: Private = wif.parse().unwrap();
+        KeyPair::from_private(private).unwrap()
+    }
+
+    fn sign(
+        signer: &TransactionInputSigner,
+        keypair: &KeyPair,
+        script_code: &Script,
+        sighash: u32,
+    ) -> Bytes {
+        let hash = signer
+            .signature_hash(0, 0, script_code, SignatureVersion::Base, sighash)
+            .unwrap();
+        let mut signature: Vec<u8> = keypair.private().sign(&hash).unwrap().into();
+        signature.push(sighash as u8);
+        signature.into()
+    }
+
+    fn spending_tx() -> Transaction {
+        "0100000001f5d8ee39a430901c91a5917b9f2dc19d6d1a0e9cea205b009ca73dd04470b9a60000000000ffffffff0100f2052a010000001976a914a09be8040cbf399926aeb1f470c37d1341f3b46588ac00000000".parse().unwrap()
+    }
+
+    #[test]
+    fn test_is_valid_signature_encoding() {
+        let sig = hex::decode("3045022100ff2e6d5c2d8c2f4e1a7a5e3e7a14e8a7f48a9d5b2b2e83a0cc0f1a5a1f2c3d0102204c2a8c0c1f3b1e2d4f6a8b9c0d1e2f3a4b5c6d7e8f9a0b1c2d3e4f5a6b7c8d9e01").unwrap();
+        assert!(is_valid_signature_encoding(&sig));
+        // negative R
+        let mut bad = sig.clone();
+        bad[4] = 0x80;
+        assert!(!is_valid_signature_encoding(&bad));
+        // wrong total length
+        let mut bad = sig.clone();
+        bad[1] = 0x46;
+        assert!(!is_valid_signature_encoding(&bad));
+        // not a compound type
+        let mut bad = sig;
+        bad[0] = 0x31;
+        assert!(!is_valid_signature_encoding(&bad));
+        assert!(!is_valid_signature_encoding(&[]));
+    }
+
+    #[test]
+    fn test_cast_to_bool() {
+        assert!(!cast_to_bool(&[]));
+        assert!(!cast_to_bool(&[0x00]));
+        assert!(!cast_to_bool(&[0x00, 0x00, 0x80]));
+        assert!(cast_to_bool(&[0x00, 0x01, 0x80]));
+        assert!(cast_to_bool(&[0x01]));
+        assert!(cast_to_bool(&[0x80, 0x00]));
+    }
+
+    #[test]
+    fn test_equal() {
+        let script = Builder::default()
+            .push_data(&[0x4])
+            .push_data(&[0x4])
+            .push_opcode(Opcode::OP_EQUAL)
+            .into_script();
+        let stack = vec![vec![1].into()].into();
+        basic_test(&script, Ok(true), stack);
+    }
+
+    #[test]
+    fn test_equal_false() {
+        let script = Builder::default()
+            .push_data(&[0x4])
+            .push_data(&[0x3])
+            .push_opcode(Opcode::OP_EQUAL)
+            .into_script();
+        let stack = vec![Bytes::new()].into();
+        basic_test(&script, Ok(false), stack);
+    }
+
+    #[test]
+    fn test_equal_verify_failed() {
+        let script = Builder::default()
+            .push_data(&[0x4])
+            .push_data(&[0x3])
+            .push_opcode(Opcode::OP_EQUALVERIFY)
+            .into_script();
+        basic_test(&script, Err(Error::EqualVerify), Stack::new());
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        // (2 + 3) - 1 == 4, 4 within [0, 5)
+        let script = Builder::default()
+            .push_opcode(Opcode::OP_2)
+            .push_opcode(Opcode::OP_3)
+            .push_opcode(Opcode::OP_ADD)
+            .push_opcode(Opcode::OP_1SUB)
+            .push_opcode(Opcode::OP_DUP)
+            .push_opcode(Opcode::OP_4)
+            .push_opcode(Opcode::OP_NUMEQUALVERIFY)
+            .push_opcode(Opcode::OP_0)
+            .push_opcode(Opcode::OP_5)
+            .push_opcode(Opcode::OP_WITHIN)
+            .into_script();
+        let stack = vec![vec![1].into()].into();
+        basic_test(&script, Ok(true), stack);
+
+        let script = Builder::default()
+            .push_opcode(Opcode::OP_1NEGATE)
+            .push_opcode(Opcode::OP_ABS)
+            .push_opcode(Opcode::OP_NEGATE)
+            .push_opcode(Opcode::OP_16)
+            .push_opcode(Opcode::OP_MAX)
+            .into_script();
+        let stack = vec![vec![16].into()].into();
+        basic_test(&script, Ok(true), stack);
+    }
+
+    #[test]
+    fn test_arithmetic_number_overflow() {
+        let script = Builder::default()
+            .push_data(&[0x01, 0x02, 0x03, 0x04, 0x05])
+            .push_opcode(Opcode::OP_1ADD)
+            .into_script();
+        basic_test(&script, Err(Error::NumberOverflow), Stack::new());
+    }
+
+    #[test]
+    fn test_pick_and_roll() {
+        let script = Builder::default()
+            .push_data(&[0x11])
+            .push_data(&[0x22])
+            .push_data(&[0x33])
+            .push_opcode(Opcode::OP_2)
+            .push_opcode(Opcode::OP_PICK)
+            .into_script();
+        let stack = vec![
+            vec![0x11].into(),
+            vec![0x22].into(),
+            vec![0x33].into(),
+            vec![0x11].into(),
+        ]
+        .into();
+        basic_test(&script, Ok(true), stack);
+
+        let script = Builder::default()
+            .push_data(&[0x11])
+            .push_data(&[0x22])
+            .push_data(&[0x33])
+            .push_opcode(Opcode::OP_2)
+            .push_opcode(Opcode::OP_ROLL)
+            .into_script();
+        let stack = vec![vec![0x22].into(), vec![0x33].into(), vec![0x11].into()].into();
+        basic_test(&script, Ok(true), stack);
+
+        let script = Builder::default()
+            .push_data(&[0x11])
+            .push_opcode(Opcode::OP_1)
+            .push_opcode(Opcode::OP_PICK)
+            .into_script();
+        basic_test(&script, Err(Error::InvalidStackOperation), Stack::new());
+    }
+
+    #[test]
+    fn test_altstack() {
+        let script = Builder::default()
+            .push_opcode(Opcode::OP_1)
+            .push_opcode(Opcode::OP_TOALTSTACK)
+            .push_opcode(Opcode::OP_DEPTH)
+            .push_opcode(Opcode::OP_FROMALTSTACK)
+            .into_script();
+        let stack = vec![Bytes::new(), vec![1].into()].into();
+        basic_test(&script, Ok(true), stack);
+
+        let script = Builder::default()
+            .push_opcode(Opcode::OP_FROMALTSTACK)
+            .into_script();
+        basic_test(&script, Err(Error::InvalidAltstackOperation), Stack::new());
+    }
+
+    #[test]
+    fn test_if_else_endif() {
+        let script = Builder::default()
+            .push_opcode(Opcode::OP_0)
+            .push_opcode(Opcode::OP_IF)
+            .push_opcode(Opcode::OP_RETURN)
+            .push_opcode(Opcode::OP_ELSE)
+            .push_opcode(Opcode::OP_2)
+            .push_opcode(Opcode::OP_ENDIF)
+            .into_script();
+        let stack = vec![vec![2].into()].into();
+        basic_test(&script, Ok(true), stack);
+
+        let script = Builder::default()
+            .push_opcode(Opcode::OP_1)
+            .push_opcode(Opcode::OP_NOTIF)
+            .push_opcode(Opcode::OP_2)
+            .push_opcode(Opcode::OP_ENDIF)
+            .push_opcode(Opcode::OP_3)
+            .into_script();
+        let stack = vec![vec![3].into()].into();
+        basic_test(&script, Ok(true), stack);
+    }
+
+    #[test]
+    fn test_unbalanced_conditional() {
+        let script = Builder::default()
+            .push_opcode(Opcode::OP_1)
+            .push_opcode(Opcode::OP_IF)
+            .push_opcode(Opcode::OP_1)
+            .into_script();
+        basic_test(&script, Err(Error::UnbalancedConditional), Stack::new());
+
+        let script = Builder::default()
+            .push_opcode(Opcode::OP_1)
+            .push_opcode(Opcode::OP_ENDIF)
+            .into_script();
+        basic_test(&script, Err(Error::UnbalancedConditional), Stack::new());
+
+        let script = Builder::default().push_opcode(Opcode::OP_IF).into_script();
+        basic_test(&script, Err(Error::UnbalancedConditional), Stack::new());
+    }
+
+    #[test]
+    fn test_bad_opcode_in_unexecuted_branch() {
+        let script: Script = "006368ba51".parse().unwrap();
+        // OP_0 OP_IF OP_ENDIF 0xba OP_1: 0xba is executed
+        basic_test(&script, Err(Error::BadOpcode), Stack::new());
+
+        // OP_0 OP_IF 0xff OP_ENDIF OP_1
+        let script: Script = "0063ff6851".parse().unwrap();
+        let stack = vec![vec![1].into()].into();
+        basic_test(&script, Ok(true), stack);
+
+        // OP_0 OP_IF OP_VERIF OP_ENDIF OP_1
+        let script: Script = "0063656851".parse().unwrap();
+        basic_test(&script, Err(Error::BadOpcode), Stack::new());
+    }
+
+    #[test]
+    fn test_return() {
+        let script = Builder::default()
+            .push_opcode(Opcode::OP_1)
+            .push_opcode(Opcode::OP_RETURN)
+            .into_script();
+        basic_test(&script, Err(Error::ReturnOpcode), Stack::new());
+    }
+
+    #[test]
+    fn test_disabled_opcodes() {
+        // disabled opcodes fail even in unexecuted branches
+        let script = Builder::default()
+            .push_opcode(Opcode::OP_0)
+            .push_opcode(Opcode::OP_IF)
+            .push_opcode(Opcode::OP_MUL)
+            .push_opcode(Opcode::OP_ENDIF)
+            .into_script();
+        basic_test(
+            &script,
+            Err(Error::DisabledOpcode(Opcode::OP_MUL)),
+            Stack::new(),
+        );
+
+        let script = Builder::default()
+            .push_data(&[0x1])
+            .push_data(&[0x2])
+            .push_opcode(Opcode::OP_CAT)
+            .into_script();
+        basic_test(
+            &script,
+            Err(Error::DisabledOpcode(Opcode::OP_CAT)),
+            Stack::new(),
+        );
+    }
+
+    #[test]
+    fn test_concat_and_split() {
+        let flags = VerificationFlags::default()
+            .verify_concat(true)
+            .verify_split(true);
+        let script = Builder::default()
+            .push_data(&[0x1, 0x2])
+            .push_data(&[0x3])
+            .push_opcode(Opcode::OP_CAT)
+            .push_opcode(Opcode::OP_1)
+            .push_opcode(Opcode::OP_SUBSTR)
+            .into_script();
+        let stack = vec![vec![0x1].into(), vec![0x2, 0x3].into()].into();
+        basic_test_with_flags(&script, &flags, Ok(true), stack);
+
+        let script = Builder::default()
+            .push_data(&[0x1, 0x2])
+            .push_opcode(Opcode::OP_3)
+            .push_opcode(Opcode::OP_SUBSTR)
+            .into_script();
+        basic_test_with_flags(&script, &flags, Err(Error::InvalidSplitRange), Stack::new());
+    }
+
+    #[test]
+    fn test_div_by_zero() {
+        let flags = VerificationFlags::default().verify_div(true);
+        let script = Builder::default()
+            .push_opcode(Opcode::OP_1)
+            .push_opcode(Opcode::OP_0)
+            .push_opcode(Opcode::OP_DIV)
+            .into_script();
+        basic_test_with_flags(&script, &flags, Err(Error::DivisionByZero), Stack::new());
+    }
+
+    #[test]
+    fn test_num2bin_and_bin2num() {
+        let flags = VerificationFlags::default()
+            .verify_num2bin(true)
+            .verify_bin2num(true);
+        let script = Builder::default()
+            .push_data(&[0x81])
+            .push_opcode(Opcode::OP_4)
+            .push_opcode(Opcode::OP_LEFT)
+            .into_script();
+        let stack = vec![vec![0x01, 0x00, 0x00, 0x80].into()].into();
+        basic_test_with_flags(&script, &flags, Ok(true), stack);
+
+        let script = Builder::default()
+            .push_data(&[0x01, 0x00, 0x00, 0x80])
+            .push_opcode(Opcode::OP_RIGHT)
+            .into_script();
+        let stack = vec![vec![0x81].into()].into();
+        basic_test_with_flags(&script, &flags, Ok(true), stack);
+
+        let script = Builder::default()
+            .push_data(&[0x01, 0x02])
+            .push_opcode(Opcode::OP_1)
+            .push_opcode(Opcode::OP_LEFT)
+            .into_script();
+        basic_test_with_flags(
+            &script,
+            &flags,
+            Err(Error::ImpossibleEncoding),
+            Stack::new(),
+        );
+    }
+
+    #[test]
+    fn test_minimal_data() {
+        let flags = VerificationFlags::default();
+        let minimal_flags = VerificationFlags::default().verify_minimaldata(true);
+
+        // PUSHDATA1 of a single byte
+        let script: Script = "4c0105".parse().unwrap();
+        let stack = vec![vec![5].into()].into();
+        basic_test_with_flags(&script, &flags, Ok(true), stack);
+        basic_test_with_flags(
+            &script,
+            &minimal_flags,
+            Err(Error::Minimaldata),
+            Stack::new(),
+        );
+
+        // non-minimally encoded number
+        let script = Builder::default()
+            .push_data(&[0x01, 0x00])
+            .push_opcode(Opcode::OP_1ADD)
+            .into_script();
+        let stack = vec![vec![2].into()].into();
+        basic_test_with_flags(&script, &flags, Ok(true), stack);
+        basic_test_with_flags(
+            &script,
+            &minimal_flags,
+            Err(Error::NumberNotMinimallyEncoded),
+            Stack::new(),
+        );
+    }
+
+    #[test]
+    fn test_discourage_upgradable_nops() {
+        let script = Builder::default()
+            .push_opcode(Opcode::OP_1)
+            .push_opcode(Opcode::OP_NOP5)
+            .into_script();
+        let stack = vec![vec![1].into()].into();
+        basic_test(&script, Ok(true), stack);
+
+        let flags = VerificationFlags::default().verify_discourage_upgradable_nops(true);
+        basic_test_with_flags(
+            &script,
+            &flags,
+            Err(Error::DiscourageUpgradableNops),
+            Stack::new(),
+        );
+    }
+
+    #[test]
+    fn test_hash_opcodes() {
+        let script = Builder::default()
+            .push_data(b"hello")
+            .push_opcode(Opcode::OP_SHA256)
+            .push_data(
+                &hex::decode("2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824")
+                    .unwrap(),
+            )
+            .push_opcode(Opcode::OP_EQUALVERIFY)
+            .push_data(b"hello")
+            .push_opcode(Opcode::OP_HASH160)
+            .push_data(&hex::decode("b6a9c8c230722b7c748331a8b450f05566dc7d0f").unwrap())
+            .push_opcode(Opcode::OP_EQUAL)
+            .into_script();
+        let stack = vec![vec![1].into()].into();
+        basic_test(&script, Ok(true), stack);
+    }
+
+    #[test]
+    fn test_negative_locktime() {
+        let flags = VerificationFlags::default().verify_locktime(true);
+        let script = Builder::default()
+            .push_opcode(Opcode::OP_1NEGATE)
+            .push_opcode(Opcode::OP_CHECKLOCKTIMEVERIFY)
+            .into_script();
+        basic_test_with_flags(&script, &flags, Err(Error::NegativeLocktime), Stack::new());
+    }
+
+    #[test]
+    fn test_stack_size_limit() {
+        let mut builder = Builder::default();
+        for _ in 0..(MAX_STACK_SIZE / 2) {
+            builder = builder.push_opcode(Opcode::OP_1);
+        }
+        for _ in 0..(MAX_STACK_SIZE / 2 / 3 + 1) {
+            builder = builder.push_opcode(Opcode::OP_3DUP);
+        }
+        let script = builder.into_script();
+        basic_test(&script, Err(Error::StackSize), Stack::new());
+    }
+
+    #[test]
+    fn test_check_locktime_verify() {
+        let tx: Transaction = "0100000001f5d8ee39a430901c91a5917b9f2dc19d6d1a0e9cea205b009ca73dd04470b9a600000000000000000001000000000000000000e8030000".parse().unwrap();
+        let checker = TransactionSignatureChecker {
+            signer: tx.into(),
+            input_index: 0,
+            input_amount: 0,
+        };
+        let flags = VerificationFlags::default().verify_locktime(true);
+
+        let script = Builder::default()
+            .push_num(999.into())
+            .push_opcode(Opcode::OP_CHECKLOCKTIMEVERIFY)
+            .into_script();
+        let mut stack = Stack::new();
+        assert_eq!(
+            eval_script(
+                &mut stack,
+                &script,
+                &flags,
+                &checker,
+                SignatureVersion::Base
+            ),
+            Ok(true)
+        );
+
+        let script = Builder::default()
+            .push_num(1001.into())
+            .push_opcode(Opcode::OP_CHECKLOCKTIMEVERIFY)
+            .into_script();
+        let mut stack = Stack::new();
+        assert_eq!(
+            eval_script(
+                &mut stack,
+                &script,
+                &flags,
+                &checker,
+                SignatureVersion::Base
+            ),
+            Err(Error::UnsatisfiedLocktime)
+        );
+    }
+
+    // https://github.com/bitcoin/bips/blob/master/bip-0143.mediawiki#native-p2wpkh
+    #[test]
+    fn test_verify_p2pk() {
+        let tx: Transaction = "01000000000102fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f00000000494830450221008b9d1dc26ba6a9cb62127b02742fa9d754cd3bebf337f7a55d114c8e5cdd30be022040529b194ba3f9281a99f2b1c0a19c0489bc22ede944ccf4ecbab4cc618ef3ed01eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac000247304402203609e17b84f6a7d30c80bfa610b5b4542f32a8a0d5447a12fb1366d7f01cc44a0220573a954c4518331561406f90300e8f3358f51928d43c212a8caed02de67eebee0121025476c2e83188368da1ff3e292e7acafcdb3566bb0ad253f62fc70f07aeee635711000000".parse().unwrap();
+        let script_sig: Script = tx.inputs[0].script_sig.clone().into();
+        let script_pubkey: Script =
+            "2103c9f4836b9a4f77fc0d81f7bcb01b7f1b35916864b9476c241ce9fc198bd25432ac"
+                .parse()
+                .unwrap();
+        let checker = TransactionSignatureChecker {
+            signer: tx.into(),
+            input_index: 0,
+            input_amount: 625_000_000,
+        };
+        let flags = VerificationFlags::default()
+            .verify_p2sh(true)
+            .verify_dersig(true)
+            .verify_strictenc(true);
+        assert_eq!(
+            verify_script(&script_sig, &script_pubkey, &vec![], &flags, &checker),
+            Ok(())
+        );
+
+        // signature doesn't match another public key
+        let script_pubkey: Script =
+            "21025476c2e83188368da1ff3e292e7acafcdb3566bb0ad253f62fc70f07aeee6357ac"
+                .parse()
+                .unwrap();
+        assert_eq!(
+            verify_script(&script_sig, &script_pubkey, &vec![], &flags, &checker),
+            Err(Error::EvalFalse)
+        );
+    }
+
+    #[test]
+    fn test_verify_p2pkh() {
+        let keypair = keypair("Kwr371tjA9u2rFSMZjTNun2PXXP3WPZu2afRHTcta6KxEUdm1vEw");
+        let tx = spending_tx();
+        let signer: TransactionInputSigner = tx.into();
+        let script_pubkey = Builder::build_p2pkh(&keypair.public().address_hash());
+        let signature = sign(&signer, &keypair, &script_pubkey, 1);
+        let script_sig = Builder::default()
+            .push_data(&signature)
+            .push_data(keypair.public())
+            .into_script();
+        let checker = TransactionSignatureChecker {
+            signer,
+            input_index: 0,
+            input_amount: 0,
+        };
+        let flags = VerificationFlags::default()
+            .verify_p2sh(true)
+            .verify_strictenc(true)
+            .verify_cleanstack(true);
+        assert_eq!(
+            verify_script(&script_sig, &script_pubkey, &vec![], &flags, &checker),
+            Ok(())
+        );
+
+        // undefined sighash type
+        let mut signature = signature.take();
+        *signature.last_mut().unwrap() = 0x05;
+        let script_sig = Builder::default()
+            .push_data(&signature)
+            .push_data(keypair.public())
+            .into_script();
+        assert_eq!(
+            verify_script(&script_sig, &script_pubkey, &vec![], &flags, &checker),
+            Err(Error::SignatureHashtype)
+        );
+    }
+
+    #[test]
+    fn test_verify_p2sh_multisig() {
+        let keypair1 = keypair("Kwr371tjA9u2rFSMZjTNun2PXXP3WPZu2afRHTcta6KxEUdm1vEw");
+        let keypair2 = keypair("L3Hq7a8FEQwJkW1M2GNKDW28546Vp5miewcCzSqUD9kCAXrJdS3g");
+        let keypair3 = keypair("5KC4ejrDjv152FGwP386VD1i2NYc5KkfSMyv1nGy1VGDxGHqVY3");
+        let redeem_script = Builder::default()
+            .push_opcode(Opcode::OP_2)
+            .push_data(keypair1.public())
+            .push_data(keypair2.public())
+            .push_data(keypair3.public())
+            .push_opcode(Opcode::OP_3)
+            .push_opcode(Opcode::OP_CHECKMULTISIG)
+            .into_script();
+        let script_pubkey = Builder::build_p2sh(&dhash160(&redeem_script));
+
+        let tx = spending_tx();
+        let signer: TransactionInputSigner = tx.into();
+        let signature1 = sign(&signer, &keypair1, &redeem_script, 1);
+        let signature3 = sign(&signer, &keypair3, &redeem_script, 1);
+        let checker = TransactionSignatureChecker {
+            signer,
+            input_index: 0,
+            input_amount: 0,
+        };
+        let flags = VerificationFlags::default()
+            .verify_p2sh(true)
+            .verify_nulldummy(true)
+            .verify_cleanstack(true);
+
+        let script_sig = Builder::default()
+            .push_opcode(Opcode::OP_0)
+            .push_data(&signature1)
+            .push_data(&signature3)
+            .push_data(&redeem_script)
+            .into_script();
+        assert_eq!(
+            verify_script(&script_sig, &script_pubkey, &vec![], &flags, &checker),
+            Ok(())
+        );
+
+        // signatures must be in the same order as public keys
+        let script_sig = Builder::default()
+            .push_opcode(Opcode::OP_0)
+            .push_data(&signature3)
+            .push_data(&signature1)
+            .push_data(&redeem_script)
+            .into_script();
+        assert_eq!(
+            verify_script(&script_sig, &script_pubkey, &vec![], &flags, &checker),
+            Err(Error::EvalFalse)
+        );
+
+        // dummy element must be empty
+        let script_sig = Builder::default()
+            .push_opcode(Opcode::OP_1)
+            .push_data(&signature1)
+            .push_data(&signature3)
+            .push_data(&redeem_script)
+            .into_script();
+        assert_eq!(
+            verify_script(&script_sig, &script_pubkey, &vec![], &flags, &checker),
+            Err(Error::SignatureNullDummy)
+        );
+
+        // without P2SH only the hash of the redeem script is checked
+        let script_sig = Builder::default()
+            .push_opcode(Opcode::OP_1)
+            .push_data(&redeem_script)
+            .into_script();
+        assert_eq!(
+            verify_script(
+                &script_sig,
+                &script_pubkey,
+                &vec![],
+                &VerificationFlags::default(),
+                &checker
+            ),
+            Ok(())
+        );
+
+        // script sig of P2SH spends must be push only
+        let script_sig = Builder::default()
+            .push_opcode(Opcode::OP_0)
+            .push_data(&signature1)
+            .push_data(&signature3)
+            .push_opcode(Opcode::OP_NOP)
+            .push_data(&redeem_script)
+            .into_script();
+        assert_eq!(
+            verify_script(&script_sig, &script_pubkey, &vec![], &flags, &checker),
+            Err(Error::SignaturePushOnly)
+        );
+    }
+
+    #[test]
+    fn test_verify_cleanstack() {
+        let script_sig = Builder::default()
+            .push_opcode(Opcode::OP_1)
+            .push_opcode(Opcode::OP_1)
+            .into_script();
+        let script_pubkey = Script::new(vec![].into());
+        let checker = NoopSignatureChecker;
+        let flags = VerificationFlags::default().verify_p2sh(true);
+        assert_eq!(
+            verify_script(&script_sig, &script_pubkey, &vec![], &flags, &checker),
+            Ok(())
+        );
+
+        let flags = flags.verify_cleanstack(true);
+        assert_eq!(
+            verify_script(&script_sig, &script_pubkey, &vec![], &flags, &checker),
+            Err(Error::Cleanstack)
+        );
+    }
+
+    #[test]
+    fn test_nullfail() {
+        let flags = VerificationFlags::default().verify_nullfail(true);
+
+        // a failed CHECKSIG with an empty signature is allowed
+        let script = Builder::default()
+            .push_opcode(Opcode::OP_0)
+            .push_data(&[2; 33])
+            .push_opcode(Opcode::OP_CHECKSIG)
+            .into_script();
+        basic_test_with_flags(&script, &flags, Ok(false), vec![Bytes::new()].into());
+
+        let script = Builder::default()
+            .push_data(&[1])
+            .push_data(&[2; 33])
+            .push_opcode(Opcode::OP_CHECKSIG)
+            .into_script();
+        basic_test_with_flags(
+            &script,
+            &VerificationFlags::default(),
+            Ok(false),
+            vec![Bytes::new()].into(),
+        );
+        basic_test_with_flags(
+            &script,
+            &flags,
+            Err(Error::SignatureNullFail),
+            Stack::default(),
+        );
+
+        let script = Builder::default()
+            .push_opcode(Opcode::OP_0)
+            .push_opcode(Opcode::OP_0)
+            .push_data(&[1])
+            .push_opcode(Opcode::OP_2)
+            .push_data(&[2; 33])
+            .push_data(&[3; 33])
+            .push_opcode(Opcode::OP_2)
+            .push_opcode(Opcode::OP_CHECKMULTISIG)
+            .into_script();
+        basic_test_with_flags(
+            &script,
+            &VerificationFlags::default(),
+            Ok(false),
+            vec![Bytes::new()].into(),
+        );
+        basic_test_with_flags(
+            &script,
+            &flags,
+            Err(Error::SignatureNullFail),
+            Stack::default(),
+        );
+    }
+
+    #[test]
+    fn test_const_scriptcode() {
+        let flags = VerificationFlags::default().verify_const_scriptcode(true);
+
+        // OP_CODESEPARATOR is rejected even if it's not executed
+        let script = Builder::default()
+            .push_opcode(Opcode::OP_0)
+            .push_opcode(Opcode::OP_IF)
+            .push_opcode(Opcode::OP_CODESEPARATOR)
+            .push_opcode(Opcode::OP_ENDIF)
+            .push_opcode(Opcode::OP_1)
+            .into_script();
+        basic_test(&script, Ok(true), vec![vec![1].into()].into());
+        basic_test_with_flags(
+            &script,
+            &flags,
+            Err(Error::OpCodeSeparator),
+            Stack::default(),
+        );
+
+        // but not in segwit scripts
+        let checker = NoopSignatureChecker;
+        let mut stack = Stack::new();
+        assert_eq!(
+            eval_script(
+                &mut stack,
+                &script,
+                &flags,
+                &checker,
+                SignatureVersion::WitnessV0
+            ),
+            Ok(true)
+        );
+
+        // the signature is found in the script code
+        let script = Builder::default()
+            .push_data(&[1])
+            .push_data(&[1])
+            .push_data(&[2; 33])
+            .push_opcode(Opcode::OP_CHECKSIG)
+            .into_script();
+        basic_test(
+            &script,
+            Ok(false),
+            vec![vec![1].into(), Bytes::new()].into(),
+        );
+        basic_test_with_flags(
+            &script,
+            &flags,
+            Err(Error::SignatureFindAndDelete),
+            Stack::default(),
+        );
+
+        let script = Builder::default()
+            .push_opcode(Opcode::OP_0)
+            .push_data(&[1])
+            .push_opcode(Opcode::OP_1)
+            .push_data(&[2; 33])
+            .push_opcode(Opcode::OP_1)
+            .push_opcode(Opcode::OP_CHECKMULTISIG)
+            .into_script();
+        basic_test_with_flags(
+            &script,
+            &flags,
+            Err(Error::SignatureFindAndDelete),
+            Stack::default(),
+        );
+    }
+
+    fn witness_flags() -> VerificationFlags {
+        VerificationFlags::default()
+            .verify_p2sh(true)
+            .verify_witness(true)
+    }
+
+    // https://github.com/bitcoin/bips/blob/master/bip-0143.mediawiki#native-p2wpkh
+    #[test]
+    fn test_verify_p2wpkh() {
+        let tx: Transaction = "01000000000102fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f00000000494830450221008b9d1dc26ba6a9cb62127b02742fa9d754cd3bebf337f7a55d114c8e5cdd30be022040529b194ba3f9281a99f2b1c0a19c0489bc22ede944ccf4ecbab4cc618ef3ed01eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac000247304402203609e17b84f6a7d30c80bfa610b5b4542f32a8a0d5447a12fb1366d7f01cc44a0220573a954c4518331561406f90300e8f3358f51928d43c212a8caed02de67eebee0121025476c2e83188368da1ff3e292e7acafcdb3566bb0ad253f62fc70f07aeee635711000000".parse().unwrap();
+        let witness = tx.inputs[1].script_witness.clone();
+        let script_sig = Script::new(Bytes::new());
+        let script_pubkey: Script = "00141d0f172a0ecb48aee1be1f2687d2963ae33f71a1"
+            .parse()
+            .unwrap();
+        let checker = TransactionSignatureChecker {
+            signer: tx.into(),
+            input_index: 1,
+            input_amount: 600_000_000,
+        };
+        let flags = witness_flags()
+            .verify_cleanstack(true)
+            .verify_witness_pubkeytype(true);
+        assert_eq!(
+            verify_script(&script_sig, &script_pubkey, &witness, &flags, &checker),
+            Ok(())
+        );
+
+        // amount is committed to by the signature
+        let checker = TransactionSignatureChecker {
+            input_amount: 600_000_001,
+            ..checker
+        };
+        assert_eq!(
+            verify_script(&script_sig, &script_pubkey, &witness, &flags, &checker),
+            Err(Error::EvalFalse)
+        );
+
+        // without segwit the output is anyone-can-spend, but witness must not be provided
+        let flags = VerificationFlags::default().verify_p2sh(true);
+        assert_eq!(
+            verify_script(&script_sig, &script_pubkey, &witness, &flags, &checker),
+            Ok(())
+        );
+    }
+
+    // https://github.com/bitcoin/bips/blob/master/bip-0143.mediawiki#p2sh-p2wpkh
+    #[test]
+    fn test_verify_p2sh_p2wpkh() {
+        let tx: Transaction = "01000000000101db6b1b20aa0fd7b23880be2ecbd4a98130974cf4748fb66092ac4d3ceb1a5477010000001716001479091972186c449eb1ded22b78e40d009bdf0089feffffff02b8b4eb0b000000001976a914a457b684d7f0d539a46a45bbc043f35b59d0d96388ac0008af2f000000001976a914fd270b1ee6abcaea97fea7ad0402e8bd8ad6d77c88ac02473044022047ac8e878352d3ebbde1c94ce3a10d057c24175747116f8288e5d794d12d482f0220217f36a485cae903c713331d877c1f64677e3622ad4010726870540656fe9dcb012103ad1d8e89212f0b92c74d23bb710c00662ad1470198ac48c43f7d6f93a2a2687392040000".parse().unwrap();
+        let witness = tx.inputs[0].script_witness.clone();
+        let script_sig: Script = tx.inputs[0].script_sig.clone().into();
+        let script_pubkey: Script = "a9144733f37cf4db86fbc2efed2500b4f4e49f31202387"
+            .parse()
+            .unwrap();
+        let checker = TransactionSignatureChecker {
+            signer: tx.into(),
+            input_index: 0,
+            input_amount: 1_000_000_000,
+        };
+        let flags = witness_flags().verify_cleanstack(true);
+        assert_eq!(
+            verify_script(&script_sig, &script_pubkey, &witness, &flags, &checker),
+            Ok(())
+        );
+
+        // redeem script must be pushed with the minimal push opcode
+        let mut malleated_script_sig = vec![Opcode::OP_PUSHDATA1 as u8];
+        malleated_script_sig.extend_from_slice(&script_sig);
+        let malleated_script_sig: Script = malleated_script_sig.into();
+        assert_eq!(
+            verify_script(
+                &malleated_script_sig,
+                &script_pubkey,
+                &witness,
+                &flags,
+                &checker
+            ),
+            Err(Error::WitnessMalleatedP2SH)
+        );
+    }
+
+    #[test]
+    fn test_verify_p2wsh() {
+        let keypair = keypair("Kwr371tjA9u2rFSMZjTNun2PXXP3WPZu2afRHTcta6KxEUdm1vEw");
+        let witness_script = Builder::default()
+            .push_data(keypair.public())
+            .push_opcode(Opcode::OP_CHECKSIG)
+            .into_script();
+        let script_pubkey = Builder::default()
+            .push_opcode(Opcode::OP_0)
+            .push_data(sha256(&witness_script).as_bytes())
+            .into_script();
+
+        let amount = 100_000;
+        let signer: TransactionInputSigner = spending_tx().into();
+        let hash = signer
+            .signature_hash(0, amount, &witness_script, SignatureVersion::WitnessV0, 1)
+            .unwrap();
+        let mut signature: Vec<u8> = keypair.private().sign(&hash).unwrap().into();
+        signature.push(1);
+        let checker = TransactionSignatureChecker {
+            signer,
+            input_index: 0,
+            input_amount: amount,
+        };
+        let flags = witness_flags().verify_cleanstack(true);
+        let script_sig = Script::new(Bytes::new());
+
+        let witness = vec![signature.clone().into(), witness_script.to_bytes()];
+        assert_eq!(
+            verify_script(&script_sig, &script_pubkey, &witness, &flags, &checker),
+            Ok(())
+        );
+
+        // script sig of native witness programs must be empty
+        let malleated_script_sig = Builder::default().push_opcode(Opcode::OP_0).into_script();
+        assert_eq!(
+            verify_script(
+                &malleated_script_sig,
+                &script_pubkey,
+                &witness,
+                &flags,
+                &checker
+            ),
+            Err(Error::WitnessMalleated)
+        );
+
+        // extra witness items are not allowed to stay on the stack
+        let witness = vec![vec![1].into(), signature.into(), witness_script.to_bytes()];
+        assert_eq!(
+            verify_script(&script_sig, &script_pubkey, &witness, &flags, &checker),
+            Err(Error::Cleanstack)
+        );
+
+        let witness = vec![vec![1].into(), vec![0x51].into()];
+        assert_eq!(
+            verify_script(&script_sig, &script_pubkey, &witness, &flags, &checker),
+            Err(Error::WitnessProgramMismatch)
+        );
+
+        assert_eq!(
+            verify_script(&script_sig, &script_pubkey, &vec![], &flags, &checker),
+            Err(Error::WitnessProgramWitnessEmpty)
+        );
+    }
+
+    #[test]
+    fn test_verify_p2wsh_minimalif() {
+        let witness_script = Builder::default()
+            .push_opcode(Opcode::OP_IF)
+            .push_opcode(Opcode::OP_1)
+            .push_opcode(Opcode::OP_ENDIF)
+            .into_script();
+        let script_pubkey = Builder::default()
+            .push_opcode(Opcode::OP_0)
+            .push_data(sha256(&witness_script).as_bytes())
+            .into_script();
+        let script_sig = Script::new(Bytes::new());
+        let checker = NoopSignatureChecker;
+        let flags = witness_flags();
+
+        let witness = vec![vec![2].into(), witness_script.to_bytes()];
+        assert_eq!(
+            verify_script(&script_sig, &script_pubkey, &witness, &flags, &checker),
+            Ok(())
+        );
+
+        let flags = flags.verify_minimalif(true);
+        assert_eq!(
+            verify_script(&script_sig, &script_pubkey, &witness, &flags, &checker),
+            Err(Error::MinimalIf)
+        );
+
+        let witness = vec![vec![1].into(), witness_script.to_bytes()];
+        assert_eq!(
+            verify_script(&script_sig, &script_pubkey, &witness, &flags, &checker),
+            Ok(())
+        );
+
+        // the rule doesn't apply to legacy scripts
+        basic_test_with_flags(
+            &Builder::default()
+                .push_data(&[2])
+                .push_opcode(Opcode::OP_IF)
+                .push_opcode(Opcode::OP_1)
+                .push_opcode(Opcode::OP_ENDIF)
+                .into_script(),
+            &flags,
+            Ok(true),
+            vec![vec![1].into()].into(),
+        );
+    }
+
+    #[test]
+    fn test_verify_witness_program_errors() {
+        let checker = NoopSignatureChecker;
+        let flags = witness_flags();
+        let script_sig = Script::new(Bytes::new());
+
+        // version 0 programs are either 20 or 32 bytes long
+        let script_pubkey: Script = "0015000102030405060708090a0b0c0d0e0f1011121314"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            verify_script(&script_sig, &script_pubkey, &vec![], &flags, &checker),
+            Err(Error::WitnessProgramWrongLength)
+        );
+
+        // P2WPKH witness holds exactly a signature and a public key
+        let script_pubkey: Script = "00141d0f172a0ecb48aee1be1f2687d2963ae33f71a1"
+            .parse()
+            .unwrap();
+        let witness = vec![vec![1].into()];
+        assert_eq!(
+            verify_script(&script_sig, &script_pubkey, &witness, &flags, &checker),
+            Err(Error::WitnessProgramMismatch)
+        );
+
+        // witness stack elements are limited in size
+        let script_pubkey = Builder::default()
+            .push_opcode(Opcode::OP_0)
+            .push_data(sha256(&[0x51]).as_bytes())
+            .into_script();
+        let witness = vec![
+            vec![0; MAX_SCRIPT_ELEMENT_SIZE + 1].into(),
+            vec![0x51].into(),
+        ];
+        assert_eq!(
+            verify_script(&script_sig, &script_pubkey, &witness, &flags, &checker),
+            Err(Error::PushSize)
+        );
+
+        // exactly one true element must remain on the stack
+        let witness = vec![vec![0x51].into()];
+        assert_eq!(
+            verify_script(&script_sig, &script_pubkey, &witness, &flags, &checker),
+            Ok(())
+        );
+        let witness = vec![vec![].into(), vec![0x51].into()];
+        assert_eq!(
+            verify_script(&script_sig, &script_pubkey, &witness, &flags, &checker),
+            Err(Error::Cleanstack)
+        );
+        let script_pubkey = Builder::default()
+            .push_opcode(Opcode::OP_0)
+            .push_data(sha256(&[0x00]).as_bytes())
+            .into_script();
+        let witness = vec![vec![0x00].into()];
+        assert_eq!(
+            verify_script(&script_sig, &script_pubkey, &witness, &flags, &checker),
+            Err(Error::EvalFalse)
+        );
+        let script_pubkey = Builder::default()
+            .push_opcode(Opcode::OP_0)
+            .push_data(sha256(&[0x75]).as_bytes())
+            .into_script();
+        let witness = vec![vec![1].into(), vec![0x75].into()];
+        assert_eq!(
+            verify_script(&script_sig, &script_pubkey, &witness, &flags, &checker),
+            Err(Error::Cleanstack)
+        );
+
+        // witness for a non-witness output
+        let script_pubkey = Builder::default().push_opcode(Opcode::OP_1).into_script();
+        let witness = vec![vec![1].into()];
+        assert_eq!(
+            verify_script(&script_sig, &script_pubkey, &witness, &flags, &checker),
+            Err(Error::WitnessUnexpected)
+        );
+    }
+
+    #[test]
+    fn test_verify_upgradable_witness_program() {
+        let checker = NoopSignatureChecker;
+        let script_sig = Script::new(Bytes::new());
+        let script_pubkey: Script = "5914000102030405060708090a0b0c0d0e0f10111213"
+            .parse()
+            .unwrap();
+        let witness = vec![vec![1].into()];
+
+        let flags = witness_flags();
+        assert_eq!(
+            verify_script(&script_sig, &script_pubkey, &witness, &flags, &checker),
+            Ok(())
+        );
+
+        let flags = flags.verify_discourage_upgradable_witness_program(true);
+        assert_eq!(
+            verify_script(&script_sig, &script_pubkey, &witness, &flags, &checker),
+            Err(Error::DiscourageUpgradableWitnessProgram)
+        );
+    }
+
+    #[test]
+    fn test_witness_pubkey_type() {
+        let keypair = keypair("5KC4ejrDjv152FGwP386VD1i2NYc5KkfSMyv1nGy1VGDxGHqVY3");
+        let script = Builder::default()
+            .push_data(&[])
+            .push_data(keypair.public())
+            .push_opcode(Opcode::OP_CHECKSIG)
+            .into_script();
+        let flags = witness_flags().verify_witness_pubkeytype(true);
+        let checker = NoopSignatureChecker;
+
+        let mut stack = Stack::new();
+        assert_eq!(
+            eval_script(
+                &mut stack,
+                &script,
+                &flags,
+                &checker,
+                SignatureVersion::Base
+            ),
+            Ok(false)
+        );
+
+        let mut stack = Stack::new();
+        assert_eq!(
+            eval_script(
+                &mut stack,
+                &script,
+                &flags,
+                &checker,
+                SignatureVersion::WitnessV0
+            ),
+            Err(Error::WitnessPubKeyType)
+        );
+    }
+
+    fn taproot_flags() -> VerificationFlags {
+        witness_flags().verify_taproot(true)
+    }
+
+    fn p2tr_script_pubkey(output_key: &Public) -> Script {
+        Builder::default()
+            .push_opcode(Opcode::OP_1)
+            .push_data(output_key)
+            .into_script()
+    }
+
+    /// Checker of the first input of `spending_tx` spending `script_pubkey`.
+    fn taproot_checker(script_pubkey: &Script, amount: u64) -> TransactionSignatureChecker {
+        let spent_output = light_bitcoin_chain::TransactionOutput {
+            value: amount,
+            script_pubkey: script_pubkey.to_bytes(),
+        };
+        let signer =
+            TransactionInputSigner::from(spending_tx()).with_spent_outputs(vec![spent_output]);
+        TransactionSignatureChecker {
+            signer,
+            input_index: 0,
+            input_amount: amount,
+        }
+    }
+
+    /// Signs the first input of `spending_tx` spending the tapscript leaf.
+    fn sign_tapscript(
+        checker: &TransactionSignatureChecker,
+        private: &Private,
+        leaf_hash: &H256,
+    ) -> Bytes {
+        let execdata = ScriptExecutionData {
+            tapleaf_hash: Some(*leaf_hash),
+            ..Default::default()
+        };
+        let hash = checker
+            .signer
+            .signature_hash_schnorr(0, SignatureVersion::Tapscript, 0, &execdata)
+            .unwrap();
+        private.sign_schnorr(&hash).unwrap().to_vec().into()
+    }
+
+    /// Builds the control block of a leaf of the tree given by its merkle path.
+    fn control_block(internal_key: &Public, path: &[H256], merkle_root: &H256) -> Bytes {
+        let (_, odd) = crate::taproot::taproot_output_key(internal_key, Some(merkle_root)).unwrap();
+        let mut control = vec![TAPROOT_LEAF_TAPSCRIPT | odd as u8];
+        control.extend_from_slice(internal_key.x_only().as_bytes());
+        for node in path {
+            control.extend_from_slice(node.as_bytes());
+        }
+        control.into()
+    }
+
+    #[test]
+    fn test_verify_taproot_key_path() {
+        let private = *keypair("Kwr371tjA9u2rFSMZjTNun2PXXP3WPZu2afRHTcta6KxEUdm1vEw").private();
+        let internal_key = private.x_only_public().unwrap();
+        let (output_key, _) = crate::taproot::taproot_output_key(&internal_key, None).unwrap();
+        let tweak = crate::taproot::taproot_tweak_hash(&internal_key.x_only(), None);
+        let tweaked_private = private.tweak_add_x_only(&tweak).unwrap();
+
+        let script_pubkey = p2tr_script_pubkey(&output_key);
+        let checker = taproot_checker(&script_pubkey, 100_000);
+        let flags = taproot_flags();
+        let script_sig = Script::new(Bytes::new());
+
+        let hash = checker
+            .signer
+            .signature_hash_schnorr(
+                0,
+                SignatureVersion::Taproot,
+                0,
+                &ScriptExecutionData::default(),
+            )
+            .unwrap();
+        let signature = tweaked_private.sign_schnorr(&hash).unwrap().to_vec();
+        let witness = vec![signature.clone().into()];
+        assert_eq!(
+            verify_script(&script_sig, &script_pubkey, &witness, &flags, &checker),
+            Ok(())
+        );
+
+        // explicit SIGHASH_ALL commits to a different hash
+        let mut signature_all = signature.clone();
+        signature_all.push(1);
+        assert_eq!(
+            verify_script(
+                &script_sig,
+                &script_pubkey,
+                &vec![signature_all.into()],
+                &flags,
+                &checker
+            ),
+            Err(Error::SchnorrSig)
+        );
+
+        // SIGHASH_DEFAULT must not be explicitly encoded
+        let mut signature_default = signature.clone();
+        signature_default.push(0);
+        assert_eq!(
+            verify_script(
+                &script_sig,
+                &script_pubkey,
+                &vec![signature_default.into()],
+                &flags,
+                &checker
+            ),
+            Err(Error::SchnorrSigHashtype)
+        );
+
+        assert_eq!(
+            verify_script(
+                &script_sig,
+                &script_pubkey,
+                &vec![signature[..63].to_vec().into()],
+                &flags,
+                &checker
+            ),
+            Err(Error::SchnorrSigSize)
+        );
+
+        // the annex is committed to by the signature
+        let witness = vec![signature.into(), vec![ANNEX_TAG].into()];
+        assert_eq!(
+            verify_script(&script_sig, &script_pubkey, &witness, &flags, &checker),
+            Err(Error::SchnorrSig)
+        );
+
+        assert_eq!(
+            verify_script(&script_sig, &script_pubkey, &vec![], &flags, &checker),
+            Err(Error::WitnessProgramWitnessEmpty)
+        );
+
+        // without taproot, v1 witness programs are upgradable
+        assert_eq!(
+            verify_script(
+                &script_sig,
+                &script_pubkey,
+                &vec![],
+                &witness_flags(),
+                &checker
+            ),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_verify_taproot_script_path() {
+        let internal_key = keypair("L3Hq7a8FEQwJkW1M2GNKDW28546Vp5miewcCzSqUD9kCAXrJdS3g")
+            .private()
+            .x_only_public()
+            .unwrap();
+        let private1 = *keypair("Kwr371tjA9u2rFSMZjTNun2PXXP3WPZu2afRHTcta6KxEUdm1vEw").private();
+        let private2 = *keypair("5KC4ejrDjv152FGwP386VD1i2NYc5KkfSMyv1nGy1VGDxGHqVY3").private();
+        let public1 = private1.x_only_public().unwrap();
+        let public2 = private2.x_only_public().unwrap();
+
+        // leaf 0: <public1> OP_CHECKSIG
+        let script1 = Builder::default()
+            .push_data(&public1)
+            .push_opcode(Opcode::OP_CHECKSIG)
+            .into_script();
+        // leaf 1: <public1> OP_CHECKSIG <public2> OP_CHECKSIGADD OP_2 OP_NUMEQUAL
+        let script2 = Builder::default()
+            .push_data(&public1)
+            .push_opcode(Opcode::OP_CHECKSIG)
+            .push_data(&public2)
+            .push_opcode(Opcode::OP_CHECKSIGADD)
+            .push_opcode(Opcode::OP_2)
+            .push_opcode(Opcode::OP_NUMEQUAL)
+            .into_script();
+        let leaf1 = tapleaf_hash(TAPROOT_LEAF_TAPSCRIPT, &script1);
+        let leaf2 = tapleaf_hash(TAPROOT_LEAF_TAPSCRIPT, &script2);
+        let merkle_root = crate::taproot::tapbranch_hash(&leaf1, &leaf2);
+        let (output_key, _) =
+            crate::taproot::taproot_output_key(&internal_key, Some(&merkle_root)).unwrap();
+
+        let script_pubkey = p2tr_script_pubkey(&output_key);
+        let checker = taproot_checker(&script_pubkey, 100_000);
+        let flags = taproot_flags();
+        let script_sig = Script::new(Bytes::new());
+
+        let control1 = control_block(&internal_key, &[leaf2], &merkle_root);
+        let signature = sign_tapscript(&checker, &private1, &leaf1);
+        let witness = vec![signature.clone(), script1.to_bytes(), control1.clone()];
+        assert_eq!(
+            verify_script(&script_sig, &script_pubkey, &witness, &flags, &checker),
+            Ok(())
+        );
+
+        // a signature for another leaf is invalid
+        let witness = vec![
+            sign_tapscript(&checker, &private1, &leaf2),
+            script1.to_bytes(),
+            control1.clone(),
+        ];
+        assert_eq!(
+            verify_script(&script_sig, &script_pubkey, &witness, &flags, &checker),
+            Err(Error::SchnorrSig)
+        );
+
+        // an empty signature fails OP_CHECKSIG without an error
+        let witness = vec![Bytes::new(), script1.to_bytes(), control1.clone()];
+        assert_eq!(
+            verify_script(&script_sig, &script_pubkey, &witness, &flags, &checker),
+            Err(Error::EvalFalse)
+        );
+
+        // the control block must prove the leaf belongs to the output key
+        let witness = vec![signature.clone(), script2.to_bytes(), control1.clone()];
+        assert_eq!(
+            verify_script(&script_sig, &script_pubkey, &witness, &flags, &checker),
+            Err(Error::WitnessProgramMismatch)
+        );
+
+        let witness = vec![
+            signature,
+            script1.to_bytes(),
+            control1[..40].to_vec().into(),
+        ];
+        assert_eq!(
+            verify_script(&script_sig, &script_pubkey, &witness, &flags, &checker),
+            Err(Error::TaprootWrongControlSize)
+        );
+
+        // 2-of-2 with OP_CHECKSIGADD, signatures are consumed from the top
+        let control2 = control_block(&internal_key, &[leaf1], &merkle_root);
+        let signature1 = sign_tapscript(&checker, &private1, &leaf2);
+        let signature2 = sign_tapscript(&checker, &private2, &leaf2);
+        let witness = vec![
+            signature2.clone(),
+            signature1.clone(),
+            script2.to_bytes(),
+            control2.clone(),
+        ];
+        assert_eq!(
+            verify_script(&script_sig, &script_pubkey, &witness, &flags, &checker),
+            Ok(())
+        );
+
+        let witness = vec![Bytes::new(), signature1, script2.to_bytes(), control2];
+        assert_eq!(
+            verify_script(&script_sig, &script_pubkey, &witness, &flags, &checker),
+            Err(Error::EvalFalse)
+        );
+    }
+
+    /// Verifies a single leaf taproot output of `script` with the given witness stack.
+    fn verify_tapscript(
+        script: &Script,
+        mut witness: ScriptWitness,
+        flags: &VerificationFlags,
+    ) -> Result<(), Error> {
+        let internal_key = keypair("L3Hq7a8FEQwJkW1M2GNKDW28546Vp5miewcCzSqUD9kCAXrJdS3g")
+            .private()
+            .x_only_public()
+            .unwrap();
+        let leaf = tapleaf_hash(TAPROOT_LEAF_TAPSCRIPT, script);
+        let (output_key, _) =
+            crate::taproot::taproot_output_key(&internal_key, Some(&leaf)).unwrap();
+        let script_pubkey = p2tr_script_pubkey(&output_key);
+        let checker = taproot_checker(&script_pubkey, 100_000);
+
+        witness.push(script.to_bytes());
+        witness.push(control_block(&internal_key, &[], &leaf));
+        verify_script(
+            &Script::new(Bytes::new()),
+            &script_pubkey,
+            &witness,
+            flags,
+            &checker,
+        )
+    }
+
+    #[test]
+    fn test_verify_tapscript_rules() {
+        let flags = taproot_flags();
+
+        // OP_SUCCESSx makes the script succeed, even if it's never executed
+        let script: Script = vec![0x00, 0x63, 0x50, 0x68].into();
+        assert_eq!(verify_tapscript(&script, vec![], &flags), Ok(()));
+        assert_eq!(
+            verify_tapscript(&script, vec![], &flags.verify_discourage_op_success(true)),
+            Err(Error::DiscourageOpSuccess)
+        );
+
+        // OP_CHECKMULTISIG is disabled
+        let script = Builder::default()
+            .push_opcode(Opcode::OP_0)
+            .push_opcode(Opcode::OP_0)
+            .push_opcode(Opcode::OP_0)
+            .push_opcode(Opcode::OP_CHECKMULTISIG)
+            .into_script();
+        assert_eq!(
+            verify_tapscript(&script, vec![], &flags),
+            Err(Error::TapscriptCheckMultisig)
+        );
+
+        // OP_IF requires minimal arguments
+        let script = Builder::default()
+            .push_opcode(Opcode::OP_IF)
+            .push_opcode(Opcode::OP_1)
+            .push_opcode(Opcode::OP_ENDIF)
+            .into_script();
+        assert_eq!(
+            verify_tapscript(&script, vec![vec![1].into()], &flags),
+            Ok(())
+        );
+        assert_eq!(
+            verify_tapscript(&script, vec![vec![2].into()], &flags),
+            Err(Error::TapscriptMinimalIf)
+        );
+
+        // public keys of unknown types are upgradable
+        let script = Builder::default()
+            .push_data(&[2; 33])
+            .push_opcode(Opcode::OP_CHECKSIG)
+            .into_script();
+        assert_eq!(
+            verify_tapscript(&script, vec![vec![1].into()], &flags),
+            Ok(())
+        );
+        assert_eq!(
+            verify_tapscript(
+                &script,
+                vec![vec![1].into()],
+                &flags.verify_discourage_upgradable_pubkeytype(true)
+            ),
+            Err(Error::DiscourageUpgradablePubkeyType)
+        );
+
+        // every signature check consumes the validation weight budget of the witness
+        let mut builder = Builder::default();
+        for _ in 0..10 {
+            builder = builder
+                .push_opcode(Opcode::OP_DUP)
+                .push_data(&[2; 33])
+                .push_opcode(Opcode::OP_CHECKSIGVERIFY);
+        }
+        let script = builder.into_script();
+        assert_eq!(
+            verify_tapscript(&script, vec![vec![1].into()], &flags),
+            Err(Error::TapscriptValidationWeight)
+        );
+
+        // scripts of unknown leaf versions are upgradable
+        let internal_key = keypair("L3Hq7a8FEQwJkW1M2GNKDW28546Vp5miewcCzSqUD9kCAXrJdS3g")
+            .private()
+            .x_only_public()
+            .unwrap();
+        let script = Builder::default().push_opcode(Opcode::OP_0).into_script();
+        let leaf = tapleaf_hash(0xc2, &script);
+        let (output_key, odd) =
+            crate::taproot::taproot_output_key(&internal_key, Some(&leaf)).unwrap();
+        let mut control = vec![0xc2 | odd as u8];
+        control.extend_from_slice(internal_key.x_only().as_bytes());
+        let script_pubkey = p2tr_script_pubkey(&output_key);
+        let checker = taproot_checker(&script_pubkey, 100_000);
+        let witness = vec![script.to_bytes(), control.into()];
+        let script_sig = Script::new(Bytes::new());
+        assert_eq!(
+            verify_script(&script_sig, &script_pubkey, &witness, &flags, &checker),
+            Ok(())
+        );
+        assert_eq!(
+            verify_script(
+                &script_sig,
+                &script_pubkey,
+                &witness,
+                &flags.verify_discourage_upgradable_taproot_version(true),
+                &checker
+            ),
+            Err(Error::DiscourageUpgradableTaprootVersion)
+        );
+    }
+}
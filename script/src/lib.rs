@@ -3,25 +3,54 @@
 #[cfg(not(feature = "std"))]
 extern crate alloc;
 
+mod asm;
+mod bloom;
 mod builder;
+pub mod descriptor;
 mod error;
 mod flags;
+mod interpreter;
+pub mod miniscript;
 mod num;
 mod opcode;
+pub mod psbt;
 mod script;
 mod sign;
+mod sigops;
+mod stack;
+mod taproot;
+mod transaction_builder;
 mod verify;
 
 pub use light_bitcoin_primitives::*;
 
+pub use self::asm::AsmError;
+pub use self::bloom::{
+    BloomFilter, BLOOM_UPDATE_ALL, BLOOM_UPDATE_MASK, BLOOM_UPDATE_NONE,
+    BLOOM_UPDATE_P2PUBKEY_ONLY, MAX_BLOOM_FILTER_SIZE, MAX_HASH_FUNCS,
+};
 pub use self::builder::Builder;
 pub use self::error::Error;
 pub use self::flags::VerificationFlags;
+pub use self::interpreter::{eval_script, verify_script};
 pub use self::num::Num;
 pub use self::opcode::Opcode;
 pub use self::script::{
     is_witness_commitment_script, Script, ScriptAddress, ScriptType, ScriptWitness,
     MAX_OPS_PER_SCRIPT, MAX_PUBKEYS_PER_MULTISIG, MAX_SCRIPT_ELEMENT_SIZE, MAX_SCRIPT_SIZE,
+    MAX_STACK_SIZE,
+};
+pub use self::sign::{
+    ScriptExecutionData, SignatureVersion, TransactionInputSigner, UnsignedTransactionInput,
+};
+pub use self::sigops::TransactionSigOps;
+pub use self::stack::Stack;
+pub use self::taproot::{
+    compute_taproot_merkle_root, is_op_success, tapbranch_hash, tapleaf_hash, taproot_output_key,
+    taproot_tweak_hash, verify_taproot_commitment, ANNEX_TAG, TAPROOT_CONTROL_BASE_SIZE,
+    TAPROOT_CONTROL_MAX_NODE_COUNT, TAPROOT_CONTROL_MAX_SIZE, TAPROOT_CONTROL_NODE_SIZE,
+    TAPROOT_LEAF_MASK, TAPROOT_LEAF_TAPSCRIPT, VALIDATION_WEIGHT_OFFSET,
+    VALIDATION_WEIGHT_PER_SIGOP_PASSED,
 };
-pub use self::sign::{SignatureVersion, TransactionInputSigner, UnsignedTransactionInput};
+pub use self::transaction_builder::TransactionBuilder;
 pub use self::verify::{NoopSignatureChecker, SignatureChecker, TransactionSignatureChecker};
@@ -0,0 +1,168 @@
+//! Compilation of miniscript expressions to scripts
+
+use light_bitcoin_primitives::H160;
+
+use super::{Fragment, Miniscript};
+use crate::builder::Builder;
+use crate::num::Num;
+use crate::opcode::Opcode;
+use crate::script::Script;
+
+impl Miniscript {
+    /// Returns the script of the expression.
+    pub fn to_script(&self) -> Script {
+        self.compile(Builder::default(), false).into_script()
+    }
+
+    /// Returns the size of the script of the expression.
+    pub fn script_size(&self) -> usize {
+        self.to_script().len()
+    }
+
+    /// Appends the script of the expression.
+    ///
+    /// With `verify`, the expression is the last one of a `v:` wrapper and its final `EQUAL`,
+    /// `CHECKSIG` or `CHECKMULTISIG` is replaced by its `VERIFY` version.
+    fn compile(&self, builder: Builder, verify: bool) -> Builder {
+        let verify_opcode = |opcode: Opcode, verify_opcode: Opcode| match verify {
+            true => verify_opcode,
+            false => opcode,
+        };
+        let equal = verify_opcode(Opcode::OP_EQUAL, Opcode::OP_EQUALVERIFY);
+
+        match &self.fragment {
+            Fragment::False => builder.push_opcode(Opcode::OP_0),
+            Fragment::True => builder.push_opcode(Opcode::OP_1),
+            Fragment::PkK(key) => builder.push_data(key),
+            Fragment::PkH(key) => push_key_hash(builder, &key.address_hash()),
+            Fragment::RawPkH(hash) => push_key_hash(builder, hash),
+            Fragment::Older(n) => {
+                push_int(builder, *n as i64).push_opcode(Opcode::OP_CHECKSEQUENCEVERIFY)
+            }
+            Fragment::After(n) => {
+                push_int(builder, *n as i64).push_opcode(Opcode::OP_CHECKLOCKTIMEVERIFY)
+            }
+            Fragment::Sha256(hash) => {
+                push_hash_check(builder, Opcode::OP_SHA256, hash.as_bytes()).push_opcode(equal)
+            }
+            Fragment::Hash256(hash) => {
+                push_hash_check(builder, Opcode::OP_HASH256, hash.as_bytes()).push_opcode(equal)
+            }
+            Fragment::Ripemd160(hash) => {
+                push_hash_check(builder, Opcode::OP_RIPEMD160, hash.as_bytes()).push_opcode(equal)
+            }
+            Fragment::Hash160(hash) => {
+                push_hash_check(builder, Opcode::OP_HASH160, hash.as_bytes()).push_opcode(equal)
+            }
+            Fragment::AndOr(x, y, z) => {
+                let builder = x.compile(builder, false).push_opcode(Opcode::OP_NOTIF);
+                let builder = z.compile(builder, false).push_opcode(Opcode::OP_ELSE);
+                y.compile(builder, false).push_opcode(Opcode::OP_ENDIF)
+            }
+            Fragment::AndV(x, y) => y.compile(x.compile(builder, false), verify),
+            Fragment::AndB(x, y) => y
+                .compile(x.compile(builder, false), false)
+                .push_opcode(Opcode::OP_BOOLAND),
+            Fragment::OrB(x, z) => z
+                .compile(x.compile(builder, false), false)
+                .push_opcode(Opcode::OP_BOOLOR),
+            Fragment::OrC(x, z) => {
+                let builder = x.compile(builder, false).push_opcode(Opcode::OP_NOTIF);
+                z.compile(builder, false).push_opcode(Opcode::OP_ENDIF)
+            }
+            Fragment::OrD(x, z) => {
+                let builder = x
+                    .compile(builder, false)
+                    .push_opcode(Opcode::OP_IFDUP)
+                    .push_opcode(Opcode::OP_NOTIF);
+                z.compile(builder, false).push_opcode(Opcode::OP_ENDIF)
+            }
+            Fragment::OrI(x, z) => {
+                let builder = x.compile(builder.push_opcode(Opcode::OP_IF), false);
+                let builder = z.compile(builder.push_opcode(Opcode::OP_ELSE), false);
+                builder.push_opcode(Opcode::OP_ENDIF)
+            }
+            Fragment::Thresh(k, subs) => {
+                let builder = subs.iter().enumerate().fold(builder, |builder, (i, sub)| {
+                    let builder = sub.compile(builder, false);
+                    match i {
+                        0 => builder,
+                        _ => builder.push_opcode(Opcode::OP_ADD),
+                    }
+                });
+                push_int(builder, *k as i64).push_opcode(equal)
+            }
+            Fragment::Multi(k, keys) => {
+                let builder = keys
+                    .iter()
+                    .fold(push_int(builder, *k as i64), |builder, key| {
+                        builder.push_data(key)
+                    });
+                push_int(builder, keys.len() as i64).push_opcode(verify_opcode(
+                    Opcode::OP_CHECKMULTISIG,
+                    Opcode::OP_CHECKMULTISIGVERIFY,
+                ))
+            }
+            Fragment::Alt(x) => x
+                .compile(builder.push_opcode(Opcode::OP_TOALTSTACK), false)
+                .push_opcode(Opcode::OP_FROMALTSTACK),
+            Fragment::Swap(x) => x.compile(builder.push_opcode(Opcode::OP_SWAP), verify),
+            Fragment::Check(x) => x.compile(builder, false).push_opcode(verify_opcode(
+                Opcode::OP_CHECKSIG,
+                Opcode::OP_CHECKSIGVERIFY,
+            )),
+            Fragment::DupIf(x) => {
+                let builder = builder
+                    .push_opcode(Opcode::OP_DUP)
+                    .push_opcode(Opcode::OP_IF);
+                x.compile(builder, false).push_opcode(Opcode::OP_ENDIF)
+            }
+            Fragment::Verify(x) => {
+                let builder = x.compile(builder, true);
+                match x.ty.has("x") {
+                    true => builder.push_opcode(Opcode::OP_VERIFY),
+                    false => builder,
+                }
+            }
+            Fragment::NonZero(x) => {
+                let builder = builder
+                    .push_opcode(Opcode::OP_SIZE)
+                    .push_opcode(Opcode::OP_0NOTEQUAL)
+                    .push_opcode(Opcode::OP_IF);
+                x.compile(builder, false).push_opcode(Opcode::OP_ENDIF)
+            }
+            Fragment::ZeroNotEqual(x) => {
+                x.compile(builder, false).push_opcode(Opcode::OP_0NOTEQUAL)
+            }
+        }
+    }
+}
+
+/// Pushes the number, with `OP_0` and `OP_1` to `OP_16` for small numbers.
+fn push_int(builder: Builder, n: i64) -> Builder {
+    match n {
+        0 => builder.push_opcode(Opcode::OP_0),
+        1..=16 => builder.push_opcode(
+            Opcode::from_u8(Opcode::OP_1 as u8 + n as u8 - 1)
+                .expect("number is within [1; 16]; qed"),
+        ),
+        _ => builder.push_num(Num::from(n)),
+    }
+}
+
+/// Appends `DUP HASH160 <hash> EQUALVERIFY`.
+fn push_key_hash(builder: Builder, hash: &H160) -> Builder {
+    builder
+        .push_opcode(Opcode::OP_DUP)
+        .push_opcode(Opcode::OP_HASH160)
+        .push_data(hash.as_bytes())
+        .push_opcode(Opcode::OP_EQUALVERIFY)
+}
+
+/// Appends `SIZE 32 EQUALVERIFY <opcode> <hash>`, the final `EQUAL` being left to the caller.
+fn push_hash_check(builder: Builder, opcode: Opcode, hash: &[u8]) -> Builder {
+    push_int(builder.push_opcode(Opcode::OP_SIZE), 32)
+        .push_opcode(Opcode::OP_EQUALVERIFY)
+        .push_opcode(opcode)
+        .push_data(hash)
+}
@@ -0,0 +1,414 @@
+//! Lifting of scripts to miniscript expressions
+//!
+//! The script is decoded from its end, like Bitcoin Core's `DecodeScript`: every miniscript
+//! fragment is recognized by its last opcodes, and the expressions are built bottom-up on a
+//! stack as their arguments are decoded.
+
+#[cfg(not(feature = "std"))]
+use alloc::{boxed::Box, vec, vec::Vec};
+
+use light_bitcoin_keys::Public;
+use light_bitcoin_primitives::{H160, H256};
+
+use super::{Error, Fragment, Miniscript};
+use crate::num::Num;
+use crate::opcode::Opcode;
+use crate::script::{Script, MAX_PUBKEYS_PER_MULTISIG};
+
+/// Opcode with its pushed data.
+type Token<'a> = (Opcode, &'a [u8]);
+
+/// Decoding steps, run from a stack.
+#[derive(Clone, Copy)]
+enum Step {
+    /// A single expression of type B, K or V, not `and_v`.
+    SingleBkvExpr,
+    /// An expression of type B, K or V, possibly made of `and_v` expressions.
+    BkvExpr,
+    /// An expression of type W.
+    WExpr,
+    /// Another `and_v` argument if the script allows it.
+    MaybeAndV,
+    /// Wraps the last expression in `s:`, after the `SWAP` opcode.
+    Swap,
+    /// Wraps the last expression in `a:`, after the `TOALTSTACK` opcode.
+    Alt,
+    /// Wraps the last expression in `c:`.
+    Check,
+    /// Wraps the last expression in `d:`.
+    DupIf,
+    /// Wraps the last expression in `v:`.
+    Verify,
+    /// Wraps the last expression in `j:`.
+    NonZero,
+    /// Wraps the last expression in `n:`.
+    ZeroNotEqual,
+    /// Combines the last two expressions in `and_v`.
+    AndV,
+    /// Combines the last two expressions in `and_b`.
+    AndB,
+    /// Combines the last two expressions in `or_b`.
+    OrB,
+    /// Combines the last two expressions in `or_c`.
+    OrC,
+    /// Combines the last two expressions in `or_d`.
+    OrD,
+    /// Combines the last three expressions in `andor`.
+    AndOr,
+    /// The next `thresh` argument, with the number of decoded arguments and the threshold.
+    ThreshW(usize, usize),
+    /// Combines the last expressions in `thresh`, with their number and the threshold.
+    ThreshE(usize, usize),
+    /// The fragment ending with the decoded `ENDIF`.
+    EndIf,
+    /// The fragment ending with the decoded `NOTIF ... ENDIF`.
+    EndIfNotIf,
+    /// The fragment ending with the decoded `ELSE ... ENDIF`.
+    EndIfElse,
+}
+
+impl Miniscript {
+    /// Returns the expression whose script is the script.
+    ///
+    /// Keys of `pk_h` fragments are unknown, they are lifted to `expr_raw_pkh`. Fails with
+    /// `Error::InvalidScript` if the script isn't exactly the compilation of an expression.
+    pub fn from_script(script: &Script) -> Result<Miniscript, Error> {
+        let mut tokens = tokenize(script)?;
+        tokens.reverse();
+        let ms = decode(&tokens)?;
+        match ms.to_script() == *script {
+            true => Ok(ms),
+            false => Err(Error::InvalidScript),
+        }
+    }
+}
+
+/// Returns the opcodes of the script, `VERIFY` opcodes being split from the opcode they verify.
+fn tokenize(script: &Script) -> Result<Vec<Token<'_>>, Error> {
+    let mut tokens: Vec<Token> = Vec::new();
+    for instruction in script.iter() {
+        let instruction = instruction.map_err(|_| Error::InvalidScript)?;
+        let data = instruction.data.unwrap_or(&[]);
+        let (opcode, verify) = match instruction.opcode {
+            Opcode::OP_EQUALVERIFY => (Opcode::OP_EQUAL, true),
+            Opcode::OP_NUMEQUALVERIFY => (Opcode::OP_NUMEQUAL, true),
+            Opcode::OP_CHECKSIGVERIFY => (Opcode::OP_CHECKSIG, true),
+            Opcode::OP_CHECKMULTISIGVERIFY => (Opcode::OP_CHECKMULTISIG, true),
+            Opcode::OP_VERIFY => match tokens.last() {
+                // A separate `VERIFY` after an opcode with a `VERIFY` version isn't miniscript
+                Some((
+                    Opcode::OP_EQUAL
+                    | Opcode::OP_NUMEQUAL
+                    | Opcode::OP_CHECKSIG
+                    | Opcode::OP_CHECKMULTISIG,
+                    _,
+                )) => return Err(Error::InvalidScript),
+                _ => (Opcode::OP_VERIFY, false),
+            },
+            opcode => (opcode, false),
+        };
+        tokens.push((opcode, data));
+        if verify {
+            tokens.push((Opcode::OP_VERIFY, &[]));
+        }
+    }
+    Ok(tokens)
+}
+
+/// Returns the number pushed by the token.
+fn parse_number(token: &Token) -> Option<i64> {
+    let (opcode, data) = *token;
+    if opcode == Opcode::OP_0 {
+        return Some(0);
+    }
+    if opcode.is_within_op_n() {
+        return Some(opcode.decode_op_n() as i64);
+    }
+    match data.is_empty() {
+        true => None,
+        false => Num::from_slice(data, true, 4).ok().map(i64::from),
+    }
+}
+
+/// Decodes the reversed opcodes of a script.
+fn decode(tokens: &[Token]) -> Result<Miniscript, Error> {
+    let invalid = || Error::InvalidScript;
+    let mut steps = vec![Step::BkvExpr];
+    let mut constructed: Vec<Miniscript> = Vec::new();
+    let mut pos = 0;
+
+    // Replaces the last expression with the wrapper of it
+    fn wrap(
+        constructed: &mut Vec<Miniscript>,
+        wrapper: fn(Box<Miniscript>) -> Fragment,
+    ) -> Result<(), Error> {
+        let x = constructed.pop().ok_or(Error::InvalidScript)?;
+        constructed.push(Miniscript::new(wrapper(Box::new(x)))?);
+        Ok(())
+    }
+    // Replaces the last two expressions with their combination, the last one being the first
+    // argument
+    fn combine(
+        constructed: &mut Vec<Miniscript>,
+        fragment: fn(Box<Miniscript>, Box<Miniscript>) -> Fragment,
+    ) -> Result<(), Error> {
+        let x = constructed.pop().ok_or(Error::InvalidScript)?;
+        let y = constructed.pop().ok_or(Error::InvalidScript)?;
+        constructed.push(Miniscript::new(fragment(Box::new(x), Box::new(y)))?);
+        Ok(())
+    }
+
+    while let Some(step) = steps.pop() {
+        let input = &tokens[pos..];
+        let opcode = |i: usize| input.get(i).map(|token| token.0);
+        match step {
+            Step::SingleBkvExpr => {
+                let (first, data) = *input.first().ok_or_else(invalid)?;
+                let fragment = if first == Opcode::OP_1 {
+                    pos += 1;
+                    Some(Fragment::True)
+                } else if first == Opcode::OP_0 {
+                    pos += 1;
+                    Some(Fragment::False)
+                } else if data.len() == 33 {
+                    pos += 1;
+                    let key = Public::from_slice(data).map_err(|_| invalid())?;
+                    Some(Fragment::PkK(key))
+                } else if opcode(0) == Some(Opcode::OP_VERIFY)
+                    && opcode(1) == Some(Opcode::OP_EQUAL)
+                    && opcode(3) == Some(Opcode::OP_HASH160)
+                    && opcode(4) == Some(Opcode::OP_DUP)
+                    && input[2].1.len() == 20
+                {
+                    pos += 5;
+                    Some(Fragment::RawPkH(H160::from_slice(input[2].1)))
+                } else if input.len() >= 2
+                    && (first == Opcode::OP_CHECKSEQUENCEVERIFY
+                        || first == Opcode::OP_CHECKLOCKTIMEVERIFY)
+                    && parse_number(&input[1]).is_some()
+                {
+                    pos += 2;
+                    let n = parse_number(&input[1]).expect("checked above; qed");
+                    if n < 1 || n > super::MAX_TIMELOCK as i64 {
+                        return Err(invalid());
+                    }
+                    Some(match first {
+                        Opcode::OP_CHECKSEQUENCEVERIFY => Fragment::Older(n as u32),
+                        _ => Fragment::After(n as u32),
+                    })
+                } else if input.len() >= 7
+                    && first == Opcode::OP_EQUAL
+                    && opcode(3) == Some(Opcode::OP_VERIFY)
+                    && opcode(4) == Some(Opcode::OP_EQUAL)
+                    && parse_number(&input[5]) == Some(32)
+                    && opcode(6) == Some(Opcode::OP_SIZE)
+                {
+                    let hash = input[1].1;
+                    let fragment = match (input[2].0, hash.len()) {
+                        (Opcode::OP_SHA256, 32) => Fragment::Sha256(H256::from_slice(hash)),
+                        (Opcode::OP_HASH256, 32) => Fragment::Hash256(H256::from_slice(hash)),
+                        (Opcode::OP_RIPEMD160, 20) => Fragment::Ripemd160(H160::from_slice(hash)),
+                        (Opcode::OP_HASH160, 20) => Fragment::Hash160(H160::from_slice(hash)),
+                        _ => return Err(invalid()),
+                    };
+                    pos += 7;
+                    Some(fragment)
+                } else if input.len() >= 3 && first == Opcode::OP_CHECKMULTISIG {
+                    let n = parse_number(&input[1]).ok_or_else(invalid)?;
+                    if n < 1 || n > MAX_PUBKEYS_PER_MULTISIG as i64 {
+                        return Err(invalid());
+                    }
+                    let n = n as usize;
+                    if input.len() < 3 + n {
+                        return Err(invalid());
+                    }
+                    let mut keys = input[2..2 + n]
+                        .iter()
+                        .map(|(_, data)| match data.len() {
+                            33 => Public::from_slice(data).map_err(|_| invalid()),
+                            _ => Err(invalid()),
+                        })
+                        .collect::<Result<Vec<_>, _>>()?;
+                    keys.reverse();
+                    let k = parse_number(&input[2 + n]).ok_or_else(invalid)?;
+                    if k < 1 || k > n as i64 {
+                        return Err(invalid());
+                    }
+                    pos += 3 + n;
+                    Some(Fragment::Multi(k as usize, keys))
+                } else {
+                    None
+                };
+                if let Some(fragment) = fragment {
+                    constructed.push(Miniscript::new(fragment)?);
+                    continue;
+                }
+
+                // `and_v` commutes with the wrappers and combinations ending the script, so
+                // their last argument is a single expression
+                match first {
+                    Opcode::OP_CHECKSIG => {
+                        pos += 1;
+                        steps.extend_from_slice(&[Step::Check, Step::SingleBkvExpr]);
+                    }
+                    Opcode::OP_VERIFY => {
+                        pos += 1;
+                        steps.extend_from_slice(&[Step::Verify, Step::SingleBkvExpr]);
+                    }
+                    Opcode::OP_0NOTEQUAL => {
+                        pos += 1;
+                        steps.extend_from_slice(&[Step::ZeroNotEqual, Step::SingleBkvExpr]);
+                    }
+                    Opcode::OP_EQUAL if input.len() >= 3 => {
+                        let k = parse_number(&input[1]).ok_or_else(invalid)?;
+                        if k < 1 {
+                            return Err(invalid());
+                        }
+                        pos += 2;
+                        steps.push(Step::ThreshW(0, k as usize));
+                    }
+                    Opcode::OP_ENDIF => {
+                        pos += 1;
+                        steps.extend_from_slice(&[Step::EndIf, Step::BkvExpr]);
+                    }
+                    Opcode::OP_BOOLAND => {
+                        pos += 1;
+                        steps.extend_from_slice(&[Step::AndB, Step::SingleBkvExpr, Step::WExpr]);
+                    }
+                    Opcode::OP_BOOLOR => {
+                        pos += 1;
+                        steps.extend_from_slice(&[Step::OrB, Step::SingleBkvExpr, Step::WExpr]);
+                    }
+                    _ => return Err(invalid()),
+                }
+            }
+            Step::BkvExpr => steps.extend_from_slice(&[Step::MaybeAndV, Step::SingleBkvExpr]),
+            Step::WExpr => {
+                match opcode(0).ok_or_else(invalid)? {
+                    Opcode::OP_FROMALTSTACK => {
+                        pos += 1;
+                        steps.push(Step::Alt);
+                    }
+                    _ => steps.push(Step::Swap),
+                }
+                steps.push(Step::BkvExpr);
+            }
+            Step::MaybeAndV => {
+                // These opcodes can't end an expression
+                let ends_expression = !matches!(
+                    opcode(0),
+                    None | Some(
+                        Opcode::OP_IF
+                            | Opcode::OP_NOTIF
+                            | Opcode::OP_ELSE
+                            | Opcode::OP_TOALTSTACK
+                            | Opcode::OP_SWAP
+                    )
+                );
+                if ends_expression {
+                    steps.extend_from_slice(&[Step::AndV, Step::BkvExpr]);
+                }
+            }
+            Step::Swap | Step::Alt => {
+                let expected = match step {
+                    Step::Swap => Opcode::OP_SWAP,
+                    _ => Opcode::OP_TOALTSTACK,
+                };
+                if opcode(0) != Some(expected) {
+                    return Err(invalid());
+                }
+                pos += 1;
+                match step {
+                    Step::Swap => wrap(&mut constructed, Fragment::Swap)?,
+                    _ => wrap(&mut constructed, Fragment::Alt)?,
+                }
+            }
+            Step::Check => wrap(&mut constructed, Fragment::Check)?,
+            Step::DupIf => wrap(&mut constructed, Fragment::DupIf)?,
+            Step::Verify => wrap(&mut constructed, Fragment::Verify)?,
+            Step::NonZero => wrap(&mut constructed, Fragment::NonZero)?,
+            Step::ZeroNotEqual => wrap(&mut constructed, Fragment::ZeroNotEqual)?,
+            Step::AndV => combine(&mut constructed, Fragment::AndV)?,
+            Step::AndB => combine(&mut constructed, Fragment::AndB)?,
+            Step::OrB => combine(&mut constructed, Fragment::OrB)?,
+            Step::OrC => combine(&mut constructed, Fragment::OrC)?,
+            Step::OrD => combine(&mut constructed, Fragment::OrD)?,
+            Step::AndOr => {
+                // Decoded in the order Y, Z, X
+                let x = constructed.pop().ok_or_else(invalid)?;
+                let z = constructed.pop().ok_or_else(invalid)?;
+                let y = constructed.pop().ok_or_else(invalid)?;
+                let fragment = Fragment::AndOr(Box::new(x), Box::new(y), Box::new(z));
+                constructed.push(Miniscript::new(fragment)?);
+            }
+            Step::ThreshW(n, k) => match opcode(0).ok_or_else(invalid)? {
+                Opcode::OP_ADD => {
+                    pos += 1;
+                    steps.extend_from_slice(&[Step::ThreshW(n + 1, k), Step::WExpr]);
+                }
+                _ => steps.extend_from_slice(&[Step::ThreshE(n + 1, k), Step::SingleBkvExpr]),
+            },
+            Step::ThreshE(n, k) => {
+                if k > n || constructed.len() < n {
+                    return Err(invalid());
+                }
+                let subs = constructed
+                    .split_off(constructed.len() - n)
+                    .into_iter()
+                    .rev();
+                constructed.push(Miniscript::new(Fragment::Thresh(k, subs.collect()))?);
+            }
+            Step::EndIf => match opcode(0).ok_or_else(invalid)? {
+                Opcode::OP_ELSE => {
+                    pos += 1;
+                    steps.extend_from_slice(&[Step::EndIfElse, Step::BkvExpr]);
+                }
+                Opcode::OP_IF if opcode(1) == Some(Opcode::OP_DUP) => {
+                    pos += 2;
+                    steps.push(Step::DupIf);
+                }
+                Opcode::OP_IF
+                    if opcode(1) == Some(Opcode::OP_0NOTEQUAL)
+                        && opcode(2) == Some(Opcode::OP_SIZE) =>
+                {
+                    pos += 3;
+                    steps.push(Step::NonZero);
+                }
+                Opcode::OP_NOTIF => {
+                    pos += 1;
+                    steps.push(Step::EndIfNotIf);
+                }
+                _ => return Err(invalid()),
+            },
+            Step::EndIfNotIf => {
+                match opcode(0).ok_or_else(invalid)? {
+                    Opcode::OP_IFDUP => {
+                        pos += 1;
+                        steps.push(Step::OrD);
+                    }
+                    _ => steps.push(Step::OrC),
+                }
+                steps.push(Step::SingleBkvExpr);
+            }
+            Step::EndIfElse => match opcode(0).ok_or_else(invalid)? {
+                Opcode::OP_IF => {
+                    pos += 1;
+                    combine(&mut constructed, Fragment::OrI)?;
+                }
+                Opcode::OP_NOTIF => {
+                    pos += 1;
+                    steps.extend_from_slice(&[Step::AndOr, Step::SingleBkvExpr]);
+                }
+                _ => return Err(invalid()),
+            },
+        }
+    }
+
+    match (
+        constructed.pop(),
+        constructed.is_empty() && pos == tokens.len(),
+    ) {
+        (Some(ms), true) => Ok(ms),
+        _ => Err(invalid()),
+    }
+}
@@ -0,0 +1,46 @@
+//! Miniscript errors
+
+/// Miniscript errors
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Error {
+    /// The expression is malformed: unbalanced parentheses, missing or extra arguments.
+    Syntax,
+    /// The expression is nested deeper than the maximum depth.
+    MaxDepth,
+    /// The fragment name is unknown.
+    UnknownFragment,
+    /// The wrapper letter is unknown.
+    UnknownWrapper,
+    /// The key isn't the hex of a compressed public key.
+    InvalidKey,
+    /// The hash isn't hex of the size of the hash function.
+    InvalidHash,
+    /// The number, time lock or threshold is out of range.
+    InvalidNumber,
+    /// The arguments of a fragment don't have the types it requires.
+    TypeCheck,
+    /// The script isn't the compilation of a miniscript.
+    InvalidScript,
+    /// No non-malleable satisfaction is available.
+    Unsatisfiable,
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Error::Syntax => "Malformed miniscript expression".fmt(f),
+            Error::MaxDepth => "Miniscript expression nested too deeply".fmt(f),
+            Error::UnknownFragment => "Unknown miniscript fragment".fmt(f),
+            Error::UnknownWrapper => "Unknown miniscript wrapper".fmt(f),
+            Error::InvalidKey => "Invalid compressed public key".fmt(f),
+            Error::InvalidHash => "Invalid hash".fmt(f),
+            Error::InvalidNumber => "Number out of range".fmt(f),
+            Error::TypeCheck => "Miniscript fragment arguments have invalid types".fmt(f),
+            Error::InvalidScript => "Script is not a miniscript".fmt(f),
+            Error::Unsatisfiable => "No non-malleable satisfaction available".fmt(f),
+        }
+    }
+}
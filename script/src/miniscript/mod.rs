@@ -0,0 +1,756 @@
+//! Miniscript: structured P2WSH scripts that can be analyzed, composed and satisfied.
+//!
+//! http://bitcoin.sipa.be/miniscript/
+//! https://github.com/bitcoin/bips/blob/master/bip-0379.md
+//!
+//! - Expressions are parsed from their string form with `str::parse` and type checked as they
+//!   are built.
+//! - `Miniscript::to_script` compiles an expression, `Miniscript::from_script` lifts a script
+//!   back, failing if the script isn't exactly the compilation of an expression.
+//! - `Miniscript::is_sane` tells if the script only spends the way the expression reads: every
+//!   spend requires a signature, every satisfaction is non-malleable and fits in the opcode and
+//!   witness stack limits, and no spend mixes time and height locks.
+//! - `Miniscript::satisfy` builds the witness stack from the available signatures, preimages
+//!   and time locks, and `Miniscript::max_satisfaction_weight` bounds the size of the P2WSH
+//!   witness for fee estimation.
+//!
+//! Only the segwit v0 context is supported, not tapscript.
+
+mod compile;
+mod decode;
+mod error;
+mod parse;
+mod satisfy;
+mod types;
+
+#[cfg(not(feature = "std"))]
+use alloc::{boxed::Box, collections::BTreeSet, vec, vec::Vec};
+use core::{fmt, str};
+#[cfg(feature = "std")]
+use std::collections::BTreeSet;
+
+use light_bitcoin_keys::Public;
+use light_bitcoin_primitives::{H160, H256};
+
+use crate::script::{MAX_OPS_PER_SCRIPT, MAX_PUBKEYS_PER_MULTISIG};
+
+pub use self::error::Error;
+pub(crate) use self::parse::MAX_DEPTH;
+pub use self::satisfy::Satisfier;
+pub use self::types::Type;
+
+/// Maximum size of a P2WSH script to be relayed (policy).
+pub const MAX_STANDARD_P2WSH_SCRIPT_SIZE: usize = 3600;
+/// Maximum number of stack items of a P2WSH witness to be relayed, without the witness script
+/// (policy).
+pub const MAX_STANDARD_P2WSH_STACK_ITEMS: usize = 100;
+
+/// Largest relative or absolute time lock of `older` and `after`.
+const MAX_TIMELOCK: u32 = 0x7fff_ffff;
+
+/// Miniscript fragments, with their string form.
+///
+/// Wrappers (`a:`, `s:`, ...) are fragments with a single argument. The aliases of the string
+/// form are parsed into their definitions: `pk(K)` is `c:pk_k(K)`, `pkh(K)` is `c:pk_h(K)`,
+/// `and_n(X,Y)` is `andor(X,Y,0)`, `t:X` is `and_v(X,1)`, `l:X` is `or_i(0,X)` and `u:X` is
+/// `or_i(X,0)`.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum Fragment {
+    /// `0`
+    False,
+    /// `1`
+    True,
+    /// `pk_k(K)`: pushes the key.
+    PkK(Public),
+    /// `pk_h(K)`: pushes the key matching the hash of the key.
+    PkH(Public),
+    /// `expr_raw_pkh(H)`: `pk_h` of an unknown key, with the HASH160 of the key.
+    RawPkH(H160),
+    /// `older(n)`: relative time lock.
+    Older(u32),
+    /// `after(n)`: absolute time lock.
+    After(u32),
+    /// `sha256(H)`: the SHA256 preimage of the hash.
+    Sha256(H256),
+    /// `hash256(H)`: the double SHA256 preimage of the hash.
+    Hash256(H256),
+    /// `ripemd160(H)`: the RIPEMD160 preimage of the hash.
+    Ripemd160(H160),
+    /// `hash160(H)`: the HASH160 preimage of the hash.
+    Hash160(H160),
+    /// `andor(X,Y,Z)`: either X and Y, or Z.
+    AndOr(Box<Miniscript>, Box<Miniscript>, Box<Miniscript>),
+    /// `and_v(X,Y)`: X and Y, X being a verify expression.
+    AndV(Box<Miniscript>, Box<Miniscript>),
+    /// `and_b(X,Y)`: X and Y, Y being wrapped.
+    AndB(Box<Miniscript>, Box<Miniscript>),
+    /// `or_b(X,Z)`: X or Z, both dissatisfiable, Z being wrapped.
+    OrB(Box<Miniscript>, Box<Miniscript>),
+    /// `or_c(X,Z)`: X or Z, Z being a verify expression.
+    OrC(Box<Miniscript>, Box<Miniscript>),
+    /// `or_d(X,Z)`: X or Z.
+    OrD(Box<Miniscript>, Box<Miniscript>),
+    /// `or_i(X,Z)`: X or Z, chosen by the satisfier.
+    OrI(Box<Miniscript>, Box<Miniscript>),
+    /// `thresh(k,X1,...,Xn)`: k of the expressions.
+    Thresh(usize, Vec<Miniscript>),
+    /// `multi(k,K1,...,Kn)`: k signatures of the keys, with `CHECKMULTISIG`.
+    Multi(usize, Vec<Public>),
+    /// `a:X`: X on the alt stack.
+    Alt(Box<Miniscript>),
+    /// `s:X`: X under the top element of the stack.
+    Swap(Box<Miniscript>),
+    /// `c:X`: checks the signature of the key pushed by X.
+    Check(Box<Miniscript>),
+    /// `d:X`: X if the top element of the stack is nonzero.
+    DupIf(Box<Miniscript>),
+    /// `v:X`: verifies X.
+    Verify(Box<Miniscript>),
+    /// `j:X`: X if the top element of the stack is nonzero, without consuming a zero.
+    NonZero(Box<Miniscript>),
+    /// `n:X`: X with its result converted to 0 or 1.
+    ZeroNotEqual(Box<Miniscript>),
+}
+
+/// Type checked miniscript expression.
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub struct Miniscript {
+    fragment: Fragment,
+    ty: Type,
+}
+
+impl Miniscript {
+    /// Builds the expression, checking its numbers and keys and the types of its arguments.
+    pub fn new(fragment: Fragment) -> Result<Self, Error> {
+        match &fragment {
+            Fragment::Older(n) | Fragment::After(n) if *n == 0 || *n > MAX_TIMELOCK => {
+                return Err(Error::InvalidNumber)
+            }
+            Fragment::Thresh(k, subs) if *k == 0 || *k > subs.len() => {
+                return Err(Error::InvalidNumber)
+            }
+            Fragment::Multi(k, keys)
+                if *k == 0 || *k > keys.len() || keys.len() > MAX_PUBKEYS_PER_MULTISIG =>
+            {
+                return Err(Error::InvalidNumber)
+            }
+            _ => {}
+        }
+        let compressed = |key: &Public| matches!(key, Public::Compressed(_));
+        let keys_valid = match &fragment {
+            Fragment::PkK(key) | Fragment::PkH(key) => compressed(key),
+            Fragment::Multi(_, keys) => keys.iter().all(compressed),
+            _ => true,
+        };
+        if !keys_valid {
+            return Err(Error::InvalidKey);
+        }
+
+        let ty = types::compute(&fragment);
+        if !ty.is_valid() {
+            return Err(Error::TypeCheck);
+        }
+        Ok(Miniscript { fragment, ty })
+    }
+
+    /// Returns the fragment of the expression.
+    pub fn fragment(&self) -> &Fragment {
+        &self.fragment
+    }
+
+    /// Returns the type of the expression.
+    pub fn ty(&self) -> Type {
+        self.ty
+    }
+
+    /// Returns the arguments of the fragment which are expressions.
+    fn subs(&self) -> Vec<&Miniscript> {
+        match &self.fragment {
+            Fragment::AndOr(x, y, z) => vec![&**x, &**y, &**z],
+            Fragment::AndV(x, y)
+            | Fragment::AndB(x, y)
+            | Fragment::OrB(x, y)
+            | Fragment::OrC(x, y)
+            | Fragment::OrD(x, y)
+            | Fragment::OrI(x, y) => vec![&**x, &**y],
+            Fragment::Thresh(_, subs) => subs.iter().collect(),
+            Fragment::Alt(x)
+            | Fragment::Swap(x)
+            | Fragment::Check(x)
+            | Fragment::DupIf(x)
+            | Fragment::Verify(x)
+            | Fragment::NonZero(x)
+            | Fragment::ZeroNotEqual(x) => vec![&**x],
+            _ => Vec::new(),
+        }
+    }
+
+    /// Returns true if a key appears twice in the expression.
+    fn has_duplicate_keys(&self) -> bool {
+        fn insert_keys<'a>(ms: &'a Miniscript, keys: &mut BTreeSet<&'a Public>) -> bool {
+            let unique = match &ms.fragment {
+                Fragment::PkK(key) | Fragment::PkH(key) => keys.insert(key),
+                Fragment::Multi(_, multi_keys) => multi_keys.iter().all(|key| keys.insert(key)),
+                _ => true,
+            };
+            unique && ms.subs().into_iter().all(|sub| insert_keys(sub, keys))
+        }
+        !insert_keys(self, &mut BTreeSet::new())
+    }
+
+    /// Returns true if the expression can be used as a P2WSH script whose spending conditions
+    /// are the ones the expression reads as.
+    ///
+    /// The expression must be of type `B`, require a signature for every spend (`s`), have only
+    /// non-malleable satisfactions (`m`), not mix time and height locks (`k`), not repeat keys,
+    /// and compile to a standard P2WSH script. Its satisfactions must not exceed the opcode
+    /// limit of the interpreter nor the standard number of witness stack items.
+    pub fn is_sane(&self) -> bool {
+        self.ty.has("Bsmk")
+            && !self.has_duplicate_keys()
+            && self.script_size() <= MAX_STANDARD_P2WSH_SCRIPT_SIZE
+            && !matches!(
+                self.max_satisfaction_ops(),
+                Some(ops) if ops > MAX_OPS_PER_SCRIPT as usize
+            )
+            && !matches!(
+                self.max_satisfaction_stack_items(),
+                Some(items) if items > MAX_STANDARD_P2WSH_STACK_ITEMS
+            )
+    }
+
+    /// Writes the expression, after wrapper letters if `wrapped` is set.
+    fn fmt_wrapped(&self, f: &mut fmt::Formatter<'_>, wrapped: bool) -> fmt::Result {
+        let wrapper = |f: &mut fmt::Formatter<'_>, letter: &str, x: &Miniscript| {
+            f.write_str(letter)?;
+            x.fmt_wrapped(f, true)
+        };
+        match &self.fragment {
+            Fragment::Alt(x) => return wrapper(f, "a", x),
+            Fragment::Swap(x) => return wrapper(f, "s", x),
+            Fragment::DupIf(x) => return wrapper(f, "d", x),
+            Fragment::Verify(x) => return wrapper(f, "v", x),
+            Fragment::NonZero(x) => return wrapper(f, "j", x),
+            Fragment::ZeroNotEqual(x) => return wrapper(f, "n", x),
+            Fragment::Check(x) if !matches!(x.fragment, Fragment::PkK(_) | Fragment::PkH(_)) => {
+                return wrapper(f, "c", x)
+            }
+            Fragment::AndV(x, y) if y.fragment == Fragment::True => return wrapper(f, "t", x),
+            Fragment::OrI(x, z) if x.fragment == Fragment::False => return wrapper(f, "l", z),
+            Fragment::OrI(x, z) if z.fragment == Fragment::False => return wrapper(f, "u", x),
+            _ => {}
+        }
+
+        if wrapped {
+            f.write_str(":")?;
+        }
+        let write_args = |f: &mut fmt::Formatter<'_>, name: &str, args: &[&Miniscript]| {
+            write!(f, "{}(", name)?;
+            for (i, arg) in args.iter().enumerate() {
+                if i > 0 {
+                    f.write_str(",")?;
+                }
+                arg.fmt_wrapped(f, false)?;
+            }
+            f.write_str(")")
+        };
+        match &self.fragment {
+            Fragment::False => f.write_str("0"),
+            Fragment::True => f.write_str("1"),
+            Fragment::PkK(key) => write_hex_arg(f, "pk_k", key),
+            Fragment::PkH(key) => write_hex_arg(f, "pk_h", key),
+            Fragment::RawPkH(hash) => write_hex_arg(f, "expr_raw_pkh", hash.as_bytes()),
+            Fragment::Older(n) => write!(f, "older({})", n),
+            Fragment::After(n) => write!(f, "after({})", n),
+            Fragment::Sha256(hash) => write_hex_arg(f, "sha256", hash.as_bytes()),
+            Fragment::Hash256(hash) => write_hex_arg(f, "hash256", hash.as_bytes()),
+            Fragment::Ripemd160(hash) => write_hex_arg(f, "ripemd160", hash.as_bytes()),
+            Fragment::Hash160(hash) => write_hex_arg(f, "hash160", hash.as_bytes()),
+            Fragment::Check(x) => match &x.fragment {
+                Fragment::PkK(key) => write_hex_arg(f, "pk", key),
+                Fragment::PkH(key) => write_hex_arg(f, "pkh", key),
+                _ => unreachable!("other checks are written as wrappers; qed"),
+            },
+            Fragment::AndOr(x, y, z) if z.fragment == Fragment::False => {
+                write_args(f, "and_n", &[&**x, &**y])
+            }
+            Fragment::AndOr(x, y, z) => write_args(f, "andor", &[&**x, &**y, &**z]),
+            Fragment::AndV(x, y) => write_args(f, "and_v", &[&**x, &**y]),
+            Fragment::AndB(x, y) => write_args(f, "and_b", &[&**x, &**y]),
+            Fragment::OrB(x, z) => write_args(f, "or_b", &[&**x, &**z]),
+            Fragment::OrC(x, z) => write_args(f, "or_c", &[&**x, &**z]),
+            Fragment::OrD(x, z) => write_args(f, "or_d", &[&**x, &**z]),
+            Fragment::OrI(x, z) => write_args(f, "or_i", &[&**x, &**z]),
+            Fragment::Thresh(k, subs) => {
+                write!(f, "thresh({}", k)?;
+                for sub in subs {
+                    f.write_str(",")?;
+                    sub.fmt_wrapped(f, false)?;
+                }
+                f.write_str(")")
+            }
+            Fragment::Multi(k, keys) => {
+                write!(f, "multi({}", k)?;
+                for key in keys {
+                    f.write_str(",")?;
+                    write_hex(f, key)?;
+                }
+                f.write_str(")")
+            }
+            Fragment::Alt(_)
+            | Fragment::Swap(_)
+            | Fragment::DupIf(_)
+            | Fragment::Verify(_)
+            | Fragment::NonZero(_)
+            | Fragment::ZeroNotEqual(_) => unreachable!("wrappers are written above; qed"),
+        }
+    }
+}
+
+impl fmt::Display for Miniscript {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_wrapped(f, false)
+    }
+}
+
+impl str::FromStr for Miniscript {
+    type Err = Error;
+
+    /// Parses the string form of a miniscript expression.
+    fn from_str(s: &str) -> Result<Self, Error> {
+        parse::parse(s)
+    }
+}
+
+/// Writes the bytes in hex.
+fn write_hex(f: &mut fmt::Formatter<'_>, bytes: &[u8]) -> fmt::Result {
+    bytes.iter().try_for_each(|byte| write!(f, "{:02x}", byte))
+}
+
+/// Writes a fragment whose argument is written in hex.
+fn write_hex_arg(f: &mut fmt::Formatter<'_>, name: &str, bytes: &[u8]) -> fmt::Result {
+    write!(f, "{}(", name)?;
+    write_hex(f, bytes)?;
+    f.write_str(")")
+}
+
+#[cfg(test)]
+mod tests {
+    use light_bitcoin_chain::{OutPoint, Transaction, TransactionInput, TransactionOutput};
+    use light_bitcoin_crypto::sha256;
+    use light_bitcoin_keys::{KeyPair, Private};
+    use light_bitcoin_primitives::Bytes;
+
+    use super::*;
+    use crate::builder::Builder;
+    use crate::flags::VerificationFlags;
+    use crate::interpreter::verify_script;
+    use crate::opcode::Opcode;
+    use crate::script::Script;
+    use crate::sign::{SignatureVersion, TransactionInputSigner};
+    use crate::verify::TransactionSignatureChecker;
+
+    fn keypair(n: u8) -> KeyPair {
+        KeyPair::from_private(Private {
+            network: Default::default(),
+            secret: H256::repeat_byte(n),
+            compressed: true,
+        })
+        .unwrap()
+    }
+
+    fn ms(s: &str) -> Miniscript {
+        s.parse().unwrap()
+    }
+
+    // https://github.com/bitcoin/bitcoin/blob/master/src/test/miniscript_tests.cpp
+    const VECTORS: &[(&str, &str)] = &[
+        (
+            "lltvln:after(1231488000)",
+            "6300676300676300670400046749b1926869516868",
+        ),
+        (
+            "uuj:and_v(v:multi(2,03d01115d548e7561b15c38f004d734633687cf4419620095bc5b0f47070afe85a,025601570cb47f238d2b0286db4a990fa0f3ba28d1a319f5e7cf55c2a2444da7cc),after(1231488000))",
+            "6363829263522103d01115d548e7561b15c38f004d734633687cf4419620095bc5b0f47070afe85a21025601570cb47f238d2b0286db4a990fa0f3ba28d1a319f5e7cf55c2a2444da7cc52af0400046749b168670068670068",
+        ),
+        (
+            "or_b(un:multi(2,03daed4f2be3a8bf278e70132fb0beb7522f570e144bf615c07e996d443dee8729,024ce119c96e2fa357200b559b2f7dd5a5f02d5290aff74b03f3e471b273211c97),al:older(16))",
+            "63522103daed4f2be3a8bf278e70132fb0beb7522f570e144bf615c07e996d443dee872921024ce119c96e2fa357200b559b2f7dd5a5f02d5290aff74b03f3e471b273211c9752ae926700686b63006760b2686c9b",
+        ),
+        (
+            "j:and_v(vdv:after(1567547623),older(2016))",
+            "829263766304e7e06e5db169686902e007b268",
+        ),
+        (
+            "t:and_v(vu:hash256(131772552c01444cd81360818376a040b7c3b2b7b0a53550ee3edde216cec61b),v:sha256(ec4916dd28fc4c10d78e287ca5d9cc51ee1ae73cbfde08c6b37324cbfaac8bc5))",
+            "6382012088aa20131772552c01444cd81360818376a040b7c3b2b7b0a53550ee3edde216cec61b876700686982012088a820ec4916dd28fc4c10d78e287ca5d9cc51ee1ae73cbfde08c6b37324cbfaac8bc58851",
+        ),
+        (
+            "t:andor(multi(3,02d7924d4f7d43ea965a465ae3095ff41131e5946f3c85f79e44adbcf8e27e080e,03fff97bd5755eeea420453a14355235d382f6472f8568a18b2f057a1460297556,02e493dbf1c10d80f3581e4904930b1404cc6c13900ee0758474fa94abe8c4cd13),v:older(4194305),v:sha256(9267d3dbed802941483f1afa2a6bc68de5f653128aca9bf1461c5d0a3ad36ed2))",
+            "532102d7924d4f7d43ea965a465ae3095ff41131e5946f3c85f79e44adbcf8e27e080e2103fff97bd5755eeea420453a14355235d382f6472f8568a18b2f057a14602975562102e493dbf1c10d80f3581e4904930b1404cc6c13900ee0758474fa94abe8c4cd1353ae6482012088a8209267d3dbed802941483f1afa2a6bc68de5f653128aca9bf1461c5d0a3ad36ed2886703010040b2696851",
+        ),
+        (
+            "or_d(multi(1,02f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f9),or_b(multi(3,022f01e5e15cca351daff3843fb70f3c2f0a1bdd05e5af888a67784ef3e10a2a01,032fa2104d6b38d11b0230010559879124e42ab8dfeff5ff29dc9cdadd4ecacc3f,03d01115d548e7561b15c38f004d734633687cf4419620095bc5b0f47070afe85a),su:after(500000)))",
+            "512102f9308a019258c31049344f85f89d5229b531c845836f99b08601f113bce036f951ae73645321022f01e5e15cca351daff3843fb70f3c2f0a1bdd05e5af888a67784ef3e10a2a0121032fa2104d6b38d11b0230010559879124e42ab8dfeff5ff29dc9cdadd4ecacc3f2103d01115d548e7561b15c38f004d734633687cf4419620095bc5b0f47070afe85a53ae7c630320a107b16700689b68",
+        ),
+        (
+            "or_d(sha256(38df1c1f64a24a77b23393bca50dff872e31edc4f3b5aa3b90ad0b82f4f089b6),and_n(un:after(499999999),older(4194305)))",
+            "82012088a82038df1c1f64a24a77b23393bca50dff872e31edc4f3b5aa3b90ad0b82f4f089b68773646304ff64cd1db19267006864006703010040b26868",
+        ),
+    ];
+
+    #[test]
+    fn test_compile_and_lift() {
+        for (expression, hex) in VECTORS {
+            let miniscript = ms(expression);
+            assert_eq!(miniscript.to_string(), *expression);
+            let script: Script = hex.parse().unwrap();
+            assert_eq!(miniscript.to_script(), script, "{}", expression);
+            assert_eq!(miniscript.script_size(), script.len());
+            assert_eq!(
+                Miniscript::from_script(&script).unwrap().to_script(),
+                script
+            );
+        }
+
+        // Keys of pk_h are lifted to their hash
+        let key = *keypair(1).public();
+        let miniscript = ms(&format!("and_v(v:pkh({}),older(144))", hex::encode(&*key)));
+        let lifted = Miniscript::from_script(&miniscript.to_script()).unwrap();
+        assert_eq!(
+            lifted.to_string(),
+            format!(
+                "and_v(vc:expr_raw_pkh({:x}),older(144))",
+                key.address_hash()
+            )
+        );
+        assert_eq!(lifted.to_script(), miniscript.to_script());
+
+        // Not miniscripts: an unknown fragment, a separate VERIFY and a non-minimal push
+        for asm in [
+            "OP_DUP",
+            "1 OP_EQUAL OP_VERIFY 1",
+            "OP_PUSHDATA1 0x01 0x90 OP_CHECKSEQUENCEVERIFY",
+        ]
+        .iter()
+        {
+            let script = Script::from_asm(asm).unwrap();
+            assert_eq!(Miniscript::from_script(&script), Err(Error::InvalidScript));
+        }
+    }
+
+    #[test]
+    fn test_parse() {
+        let a = hex::encode(&**keypair(1).public());
+        let b = hex::encode(&**keypair(2).public());
+        // Aliases are written in their shortest form
+        let vectors = [
+            (format!("c:pk_k({})", a), format!("pk({})", a)),
+            (format!("c:pk_h({})", a), format!("pkh({})", a)),
+            (format!("and_v(v:pk({}),1)", a), format!("tv:pk({})", a)),
+            (format!("or_i(0,pk({}))", a), format!("l:pk({})", a)),
+            (format!("or_i(pk({}),0)", a), format!("u:pk({})", a)),
+            (
+                format!("andor(pk({}),pk({}),0)", a, b),
+                format!("and_n(pk({}),pk({}))", a, b),
+            ),
+            (
+                format!("thresh(1,pk({}),s:pk({}))", a, b),
+                format!("thresh(1,pk({}),s:pk({}))", a, b),
+            ),
+        ];
+        for (expression, expected) in vectors.iter() {
+            assert_eq!(ms(expression).to_string(), *expected);
+        }
+
+        let errors = [
+            ("pk(", Error::Syntax),
+            ("pk()", Error::InvalidKey),
+            ("older(1))", Error::Syntax),
+            ("older(1,2)", Error::Syntax),
+            (":older(1)", Error::Syntax),
+            ("foo(1)", Error::UnknownFragment),
+            ("x:older(1)", Error::UnknownWrapper),
+            ("older(0)", Error::InvalidNumber),
+            ("older(-1)", Error::InvalidNumber),
+            ("after(2147483648)", Error::InvalidNumber),
+            ("sha256(00)", Error::InvalidHash),
+            (
+                "v:pk_k(02d7924d4f7d43ea965a465ae3095ff41131e5946f3c85f79e44adbcf8e27e080e)",
+                Error::TypeCheck,
+            ),
+            ("and_v(older(1),older(2))", Error::TypeCheck),
+            ("thresh(3,older(1),older(2))", Error::InvalidNumber),
+        ];
+        for (expression, error) in errors.iter() {
+            assert_eq!(
+                expression.parse::<Miniscript>(),
+                Err(*error),
+                "{}",
+                expression
+            );
+        }
+
+        // Wrappers count in the depth of the expression, above `older` and its argument
+        let wrapped = |n: usize| format!("{}:older(1)", "n".repeat(n));
+        assert!(wrapped(parse::MAX_DEPTH - 2).parse::<Miniscript>().is_ok());
+        assert_eq!(
+            wrapped(parse::MAX_DEPTH - 1).parse::<Miniscript>(),
+            Err(Error::MaxDepth)
+        );
+        let nested = "or_i(0,".repeat(200_000) + "1" + &")".repeat(200_000);
+        assert_eq!(nested.parse::<Miniscript>(), Err(Error::MaxDepth));
+
+        let uncompressed = *KeyPair::from_private(Private {
+            network: Default::default(),
+            secret: H256::repeat_byte(1),
+            compressed: false,
+        })
+        .unwrap()
+        .public();
+        assert_eq!(
+            Miniscript::new(Fragment::PkK(uncompressed)),
+            Err(Error::InvalidKey)
+        );
+    }
+
+    #[test]
+    fn test_types_and_sanity() {
+        let a = hex::encode(&**keypair(1).public());
+        let b = hex::encode(&**keypair(2).public());
+        let hash = sha256(b"preimage");
+
+        let vectors = [
+            (format!("pk({})", a), "Bonduesmk", true),
+            ("older(144)".to_string(), "Bzfmxhk", false),
+            (format!("and_v(v:pk({}),older(144))", a), "Bonfsmxhk", true),
+            (format!("or_b(pk({}),s:pk({}))", a, b), "Bduesmxk", true),
+            // Malleable: the hash can be dissatisfied without signature
+            (
+                format!("or_d(sha256({:x}),pk({}))", hash, a),
+                "Bduxk",
+                false,
+            ),
+            // Height and time locks mixed
+            (
+                format!("and_v(v:after(100),and_v(v:after(500000001),pk({})))", a),
+                "Bonusmij",
+                false,
+            ),
+            // Duplicate key
+            (format!("and_v(v:pk({}),pk({}))", a, a), "Bnufsmk", false),
+        ];
+        for (expression, ty, sane) in vectors.iter() {
+            let miniscript = ms(expression);
+            assert_eq!(miniscript.ty().to_string(), *ty, "{}", expression);
+            assert_eq!(miniscript.is_sane(), *sane, "{}", expression);
+        }
+    }
+
+    #[test]
+    fn test_resource_limits() {
+        let keys: Vec<String> = (1..=112)
+            .map(|n| hex::encode(&**keypair(n).public()))
+            .collect();
+        let thresh = |n: usize| {
+            let subs: String = keys[1..=n]
+                .iter()
+                .map(|key| format!(",s:pk({})", key))
+                .collect();
+            ms(&format!("thresh(1,pk({}){})", keys[0], subs))
+        };
+
+        // Every opcode is counted, executed or not: CHECKSIG, then SWAP CHECKSIG ADD for each
+        // s:pk, then EQUAL
+        for n in [66, 67, 111].iter() {
+            let miniscript = thresh(*n);
+            let script = miniscript.to_script();
+            let ops = script
+                .iter()
+                .filter(|instruction| instruction.as_ref().unwrap().opcode > Opcode::OP_16)
+                .count();
+            assert_eq!(ops, 2 + 3 * n);
+            assert_eq!(miniscript.max_satisfaction_ops(), Some(ops));
+            assert_eq!(miniscript.max_satisfaction_stack_items(), Some(n + 1));
+        }
+        assert!(thresh(66).is_sane());
+        assert!(!thresh(67).is_sane());
+        assert!(!thresh(111).is_sane());
+
+        // The keys of CHECKMULTISIG are counted when executed
+        let multi = |k: usize, keys: &[String]| format!("multi({},{})", k, keys.join(","));
+        let miniscript = ms(&format!(
+            "or_d({},{})",
+            multi(1, &keys[..20]),
+            multi(1, &keys[20..40])
+        ));
+        assert_eq!(miniscript.max_satisfaction_ops(), Some(3 + 1 + 20 + 1 + 20));
+
+        // 5 signatures of 20 keys each, with the dummy elements of CHECKMULTISIG
+        let chain = |k: usize| {
+            let multis: Vec<String> = keys[..100].chunks(20).map(|keys| multi(k, keys)).collect();
+            ms(&format!(
+                "and_v(v:{},and_v(v:{},and_v(v:{},and_v(v:{},{}))))",
+                multis[0], multis[1], multis[2], multis[3], multis[4]
+            ))
+        };
+        assert_eq!(chain(19).max_satisfaction_stack_items(), Some(100));
+        assert!(chain(19).is_sane());
+        assert_eq!(chain(20).max_satisfaction_stack_items(), Some(105));
+        assert!(!chain(20).is_sane());
+    }
+
+    /// Spends the P2WSH output of the miniscript with the satisfaction of the keys and preimages.
+    fn spend(
+        miniscript: &Miniscript,
+        keys: &[KeyPair],
+        preimages: &[&[u8]],
+        sequence: u32,
+    ) -> Result<(), Error> {
+        let witness_script = miniscript.to_script();
+        let spent = TransactionOutput {
+            value: 100_000,
+            script_pubkey: Builder::build_p2wsh(&sha256(&witness_script)).to_bytes(),
+        };
+        let mut tx = Transaction {
+            version: 2,
+            inputs: vec![TransactionInput {
+                previous_output: OutPoint::new(H256::repeat_byte(1), 0),
+                script_sig: Bytes::new(),
+                sequence,
+                script_witness: vec![],
+            }],
+            outputs: vec![TransactionOutput {
+                value: 90_000,
+                script_pubkey: Builder::build_nulldata(b"miniscript").to_bytes(),
+            }],
+            lock_time: 0,
+        };
+
+        let signer = TransactionInputSigner::from(tx.clone());
+        let hash = signer
+            .signature_hash(
+                0,
+                spent.value,
+                &witness_script,
+                SignatureVersion::WitnessV0,
+                1,
+            )
+            .unwrap();
+        let mut satisfier = Satisfier {
+            sequence,
+            ..Default::default()
+        };
+        for keypair in keys {
+            let mut signature: Vec<u8> = keypair.private().sign(&hash).unwrap().into();
+            signature.push(1);
+            satisfier
+                .signatures
+                .insert(*keypair.public(), signature.into());
+        }
+        for preimage in preimages {
+            satisfier.add_preimage(preimage.to_vec().into());
+        }
+
+        let mut witness = miniscript.satisfy(&satisfier)?;
+        let size: usize = witness.iter().map(|element| 1 + element.len()).sum();
+        assert!(size <= miniscript.max_satisfaction_size().unwrap());
+        witness.push(witness_script.to_bytes());
+        tx.inputs[0].script_witness = witness;
+
+        let flags = VerificationFlags::default()
+            .verify_p2sh(true)
+            .verify_witness(true)
+            .verify_checksequence(true)
+            .verify_nulldummy(true)
+            .verify_cleanstack(true);
+        let checker = TransactionSignatureChecker {
+            signer: TransactionInputSigner::from(tx.clone()),
+            input_index: 0,
+            input_amount: spent.value,
+        };
+        verify_script(
+            &Script::new(Bytes::new()),
+            &spent.script_pubkey.into(),
+            &tx.inputs[0].script_witness,
+            &flags,
+            &checker,
+        )
+        .expect("satisfaction is valid");
+        Ok(())
+    }
+
+    #[test]
+    fn test_satisfy_trustee_policy() {
+        let keys: Vec<KeyPair> = (1..=4).map(keypair).collect();
+        // 2 of 3 trustees, or the recovery key after 144 blocks
+        let policy = ms(&format!(
+            "or_d(multi(2,{},{},{}),and_v(v:pk({}),older(144)))",
+            hex::encode(&**keys[0].public()),
+            hex::encode(&**keys[1].public()),
+            hex::encode(&**keys[2].public()),
+            hex::encode(&**keys[3].public())
+        ));
+        assert!(policy.is_sane());
+
+        assert_eq!(spend(&policy, &keys[..2], &[], 0), Ok(()));
+        assert_eq!(spend(&policy, &keys[1..3], &[], 0), Ok(()));
+        assert_eq!(
+            spend(&policy, &keys[..1], &[], 0),
+            Err(Error::Unsatisfiable)
+        );
+        assert_eq!(
+            spend(&policy, &keys[3..], &[], 143),
+            Err(Error::Unsatisfiable)
+        );
+        assert_eq!(spend(&policy, &keys[3..], &[], 144), Ok(()));
+        // The recovery key doesn't help the trustees
+        assert_eq!(
+            spend(&policy, &keys[2..], &[], 0),
+            Err(Error::Unsatisfiable)
+        );
+
+        // 2 + 34 + 1 (multi dissatisfaction) + 74 (recovery signature) vs 1 + 2 * 74
+        assert_eq!(policy.max_satisfaction_size(), Some(149));
+        assert_eq!(
+            policy.max_satisfaction_weight(),
+            Some(1 + 149 + 1 + policy.script_size())
+        );
+    }
+
+    #[test]
+    fn test_satisfy_thresh_and_hashes() {
+        let keys: Vec<KeyPair> = (1..=3).map(keypair).collect();
+        let preimage = [7u8; 32];
+        let policy = ms(&format!(
+            "thresh(2,pk({}),s:pk({}),a:and_n(pk({}),sha256({:x})))",
+            hex::encode(&**keys[0].public()),
+            hex::encode(&**keys[1].public()),
+            hex::encode(&**keys[2].public()),
+            sha256(&preimage)
+        ));
+        assert_eq!(
+            Miniscript::from_script(&policy.to_script()),
+            Ok(policy.clone())
+        );
+
+        assert_eq!(spend(&policy, &keys[..2], &[], 0), Ok(()));
+        assert_eq!(spend(&policy, &keys[1..], &[&preimage], 0), Ok(()));
+        assert_eq!(
+            spend(&policy, &keys[1..], &[], 0),
+            Err(Error::Unsatisfiable)
+        );
+        assert_eq!(
+            spend(&policy, &keys[2..], &[&preimage], 0),
+            Err(Error::Unsatisfiable)
+        );
+
+        let hash_only = ms(&format!("sha256({:x})", sha256(&preimage)));
+        assert_eq!(hash_only.max_satisfaction_size(), Some(33));
+        // Satisfactions without signature are malleable
+        let mut satisfier = Satisfier::default();
+        satisfier.add_preimage(preimage.to_vec().into());
+        assert_eq!(hash_only.satisfy(&satisfier), Err(Error::Unsatisfiable));
+        assert_eq!(ms("0").max_satisfaction_size(), None);
+    }
+}
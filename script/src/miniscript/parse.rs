@@ -0,0 +1,194 @@
+//! String form of miniscript expressions
+
+#[cfg(not(feature = "std"))]
+use alloc::{boxed::Box, vec::Vec};
+
+use light_bitcoin_keys::Public;
+use light_bitcoin_primitives::{H160, H256, H264};
+
+use super::{Error, Fragment, Miniscript};
+
+/// Names of the fragments with arguments.
+const FRAGMENT_NAMES: &[&str] = &[
+    "pk_k",
+    "pk_h",
+    "pk",
+    "pkh",
+    "expr_raw_pkh",
+    "older",
+    "after",
+    "sha256",
+    "hash256",
+    "ripemd160",
+    "hash160",
+    "andor",
+    "and_v",
+    "and_b",
+    "and_n",
+    "or_b",
+    "or_c",
+    "or_d",
+    "or_i",
+    "thresh",
+    "multi",
+];
+
+/// Maximum depth of the expression, every wrapper counting as a level.
+///
+/// The expression is processed recursively, so its depth must be bounded to parse untrusted
+/// input.
+pub(crate) const MAX_DEPTH: usize = 402;
+
+/// Node of the expression: a name, with its wrappers, and its arguments.
+struct Tree<'a> {
+    name: &'a str,
+    args: Vec<Tree<'a>>,
+}
+
+/// Parses the string form of an expression.
+pub(super) fn parse(s: &str) -> Result<Miniscript, Error> {
+    match parse_tree(s, 0)? {
+        (tree, "") => from_tree(&tree),
+        _ => Err(Error::Syntax),
+    }
+}
+
+/// Parses the node at the start of the string, returning the rest of the string.
+///
+/// `depth` is the depth of the parent node.
+fn parse_tree(s: &str, depth: usize) -> Result<(Tree<'_>, &str), Error> {
+    let end = s.find(&['(', ',', ')'][..]).unwrap_or(s.len());
+    let (name, mut rest) = s.split_at(end);
+    let depth = depth + 1 + name.find(':').unwrap_or(0);
+    if depth > MAX_DEPTH {
+        return Err(Error::MaxDepth);
+    }
+    let mut args = Vec::new();
+    if let Some(inner) = rest.strip_prefix('(') {
+        rest = inner;
+        loop {
+            let (arg, after) = parse_tree(rest, depth)?;
+            args.push(arg);
+            rest = after.get(1..).ok_or(Error::Syntax)?;
+            match after.as_bytes()[0] {
+                b',' => continue,
+                b')' => break,
+                _ => return Err(Error::Syntax),
+            }
+        }
+    }
+    Ok((Tree { name, args }, rest))
+}
+
+/// Builds the expression of the node, applying its wrappers from the innermost.
+fn from_tree(tree: &Tree) -> Result<Miniscript, Error> {
+    let (wrappers, name) = match tree.name.find(':') {
+        Some(colon) => (&tree.name[..colon], &tree.name[colon + 1..]),
+        None => ("", tree.name),
+    };
+    if name.is_empty() || (tree.name.contains(':') && wrappers.is_empty()) {
+        return Err(Error::Syntax);
+    }
+
+    let mut ms = from_name(name, &tree.args)?;
+    for wrapper in wrappers.chars().rev() {
+        let x = Box::new(ms);
+        ms = Miniscript::new(match wrapper {
+            'a' => Fragment::Alt(x),
+            's' => Fragment::Swap(x),
+            'c' => Fragment::Check(x),
+            'd' => Fragment::DupIf(x),
+            'v' => Fragment::Verify(x),
+            'j' => Fragment::NonZero(x),
+            'n' => Fragment::ZeroNotEqual(x),
+            't' => Fragment::AndV(x, Box::new(Miniscript::new(Fragment::True)?)),
+            'l' => Fragment::OrI(Box::new(Miniscript::new(Fragment::False)?), x),
+            'u' => Fragment::OrI(x, Box::new(Miniscript::new(Fragment::False)?)),
+            _ => return Err(Error::UnknownWrapper),
+        })?;
+    }
+    Ok(ms)
+}
+
+/// Builds the expression of the fragment name and arguments.
+fn from_name(name: &str, args: &[Tree]) -> Result<Miniscript, Error> {
+    let sub = |i: usize| from_tree(&args[i]).map(Box::new);
+    let fragment = match (name, args.len()) {
+        ("0", 0) => Fragment::False,
+        ("1", 0) => Fragment::True,
+        ("pk_k", 1) => Fragment::PkK(key(&args[0])?),
+        ("pk_h", 1) => Fragment::PkH(key(&args[0])?),
+        ("pk", 1) => {
+            let pk_k = Miniscript::new(Fragment::PkK(key(&args[0])?))?;
+            Fragment::Check(Box::new(pk_k))
+        }
+        ("pkh", 1) => {
+            let pk_h = Miniscript::new(Fragment::PkH(key(&args[0])?))?;
+            Fragment::Check(Box::new(pk_h))
+        }
+        ("expr_raw_pkh", 1) => Fragment::RawPkH(H160::from_slice(&hash::<20>(&args[0])?)),
+        ("older", 1) => Fragment::Older(number(&args[0])?),
+        ("after", 1) => Fragment::After(number(&args[0])?),
+        ("sha256", 1) => Fragment::Sha256(H256::from_slice(&hash::<32>(&args[0])?)),
+        ("hash256", 1) => Fragment::Hash256(H256::from_slice(&hash::<32>(&args[0])?)),
+        ("ripemd160", 1) => Fragment::Ripemd160(H160::from_slice(&hash::<20>(&args[0])?)),
+        ("hash160", 1) => Fragment::Hash160(H160::from_slice(&hash::<20>(&args[0])?)),
+        ("andor", 3) => Fragment::AndOr(sub(0)?, sub(1)?, sub(2)?),
+        ("and_v", 2) => Fragment::AndV(sub(0)?, sub(1)?),
+        ("and_b", 2) => Fragment::AndB(sub(0)?, sub(1)?),
+        ("and_n", 2) => Fragment::AndOr(
+            sub(0)?,
+            sub(1)?,
+            Box::new(Miniscript::new(Fragment::False)?),
+        ),
+        ("or_b", 2) => Fragment::OrB(sub(0)?, sub(1)?),
+        ("or_c", 2) => Fragment::OrC(sub(0)?, sub(1)?),
+        ("or_d", 2) => Fragment::OrD(sub(0)?, sub(1)?),
+        ("or_i", 2) => Fragment::OrI(sub(0)?, sub(1)?),
+        ("thresh", n) if n >= 2 => Fragment::Thresh(
+            number(&args[0])? as usize,
+            args[1..].iter().map(from_tree).collect::<Result<_, _>>()?,
+        ),
+        ("multi", n) if n >= 2 => Fragment::Multi(
+            number(&args[0])? as usize,
+            args[1..].iter().map(key).collect::<Result<_, _>>()?,
+        ),
+        (name, _) if FRAGMENT_NAMES.contains(&name) => return Err(Error::Syntax),
+        _ => return Err(Error::UnknownFragment),
+    };
+    Miniscript::new(fragment)
+}
+
+/// Returns the name of a node without arguments.
+fn terminal<'a>(tree: &Tree<'a>) -> Result<&'a str, Error> {
+    match tree.args.is_empty() {
+        true => Ok(tree.name),
+        false => Err(Error::Syntax),
+    }
+}
+
+/// Parses a decimal number.
+fn number(tree: &Tree) -> Result<u32, Error> {
+    let s = terminal(tree)?;
+    if s.is_empty() || !s.bytes().all(|c| c.is_ascii_digit()) {
+        return Err(Error::InvalidNumber);
+    }
+    s.parse().map_err(|_| Error::InvalidNumber)
+}
+
+/// Parses the hex of a compressed public key.
+fn key(tree: &Tree) -> Result<Public, Error> {
+    let mut bytes = [0u8; 33];
+    hex::decode_to_slice(terminal(tree)?, &mut bytes).map_err(|_| Error::InvalidKey)?;
+    match bytes[0] {
+        2 | 3 => Ok(Public::Compressed(H264::from(bytes))),
+        _ => Err(Error::InvalidKey),
+    }
+}
+
+/// Parses the hex of a hash of `N` bytes.
+fn hash<const N: usize>(tree: &Tree) -> Result<[u8; N], Error> {
+    let mut bytes = [0u8; N];
+    hex::decode_to_slice(terminal(tree)?, &mut bytes).map_err(|_| Error::InvalidHash)?;
+    Ok(bytes)
+}
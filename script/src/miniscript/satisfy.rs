@@ -0,0 +1,733 @@
+//! Satisfaction of miniscript expressions
+//!
+//! For every expression, a satisfaction and a dissatisfaction are chosen among the candidate
+//! witness stacks built from those of the arguments, like Bitcoin Core's `ProduceInput`: a
+//! candidate without signature is malleable if another candidate exists, as anyone could
+//! replace it; otherwise the non-malleable and then the smallest candidate is preferred.
+
+#[cfg(not(feature = "std"))]
+use alloc::{collections::BTreeMap, vec, vec::Vec};
+use core::ops;
+#[cfg(feature = "std")]
+use std::collections::BTreeMap;
+
+use light_bitcoin_chain::constants::{
+    LOCKTIME_THRESHOLD, SEQUENCE_LOCKTIME_DISABLE_FLAG, SEQUENCE_LOCKTIME_MASK,
+    SEQUENCE_LOCKTIME_TYPE_FLAG,
+};
+use light_bitcoin_crypto::{dhash160, dhash256, ripemd160, sha256};
+use light_bitcoin_keys::Public;
+use light_bitcoin_primitives::{Bytes, H160, H256};
+
+use super::{Error, Fragment, Miniscript};
+use crate::script::ScriptWitness;
+
+/// Size of a witness element holding a signature, with its length prefix.
+const SIGNATURE_SIZE: usize = 1 + 73;
+/// Size of a witness element holding a compressed public key, with its length prefix.
+const KEY_SIZE: usize = 1 + 33;
+/// Size of a witness element holding a hash preimage, with its length prefix.
+const PREIMAGE_SIZE: usize = 1 + 32;
+
+/// Signatures, preimages and time locks available to satisfy an expression.
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct Satisfier {
+    /// Signatures of the spending transaction, with their sighash type, by public key.
+    pub signatures: BTreeMap<Public, Bytes>,
+    /// Preimages of `sha256` fragments
+    pub sha256_preimages: BTreeMap<H256, Bytes>,
+    /// Preimages of `hash256` fragments
+    pub hash256_preimages: BTreeMap<H256, Bytes>,
+    /// Preimages of `ripemd160` fragments
+    pub ripemd160_preimages: BTreeMap<H160, Bytes>,
+    /// Preimages of `hash160` fragments
+    pub hash160_preimages: BTreeMap<H160, Bytes>,
+    /// Sequence number of the spending input, checked by `older` fragments.
+    pub sequence: u32,
+    /// Lock time of the spending transaction, checked by `after` fragments.
+    pub lock_time: u32,
+}
+
+impl Satisfier {
+    /// Adds the preimage to the map of each hash function.
+    pub fn add_preimage(&mut self, preimage: Bytes) {
+        self.sha256_preimages
+            .insert(sha256(&preimage), preimage.clone());
+        self.hash256_preimages
+            .insert(dhash256(&preimage), preimage.clone());
+        self.ripemd160_preimages
+            .insert(ripemd160(&preimage), preimage.clone());
+        self.hash160_preimages.insert(dhash160(&preimage), preimage);
+    }
+
+    /// Returns true if the sequence number satisfies the relative time lock (BIP112).
+    fn check_older(&self, n: u32) -> bool {
+        if self.sequence & SEQUENCE_LOCKTIME_DISABLE_FLAG != 0 {
+            return false;
+        }
+        let mask = SEQUENCE_LOCKTIME_TYPE_FLAG | SEQUENCE_LOCKTIME_MASK;
+        let (sequence, n) = (self.sequence & mask, n & mask);
+        (sequence & SEQUENCE_LOCKTIME_TYPE_FLAG) == (n & SEQUENCE_LOCKTIME_TYPE_FLAG)
+            && sequence >= n
+    }
+
+    /// Returns true if the lock time satisfies the absolute time lock (BIP65).
+    fn check_after(&self, n: u32) -> bool {
+        (self.lock_time < LOCKTIME_THRESHOLD) == (n < LOCKTIME_THRESHOLD) && self.lock_time >= n
+    }
+
+    /// Returns the stack of the signature of the key.
+    fn signature(&self, key: &Public) -> Stack {
+        match self.signatures.get(key) {
+            Some(signature) => Stack::push(signature.clone()).with_signature(),
+            None => Stack::invalid(),
+        }
+    }
+
+    /// Returns the stack of the preimage of the hash.
+    fn preimage<H: Ord>(preimages: &BTreeMap<H, Bytes>, hash: &H) -> Stack {
+        match preimages.get(hash) {
+            Some(preimage) if preimage.len() == 32 => Stack::push(preimage.clone()),
+            _ => Stack::invalid(),
+        }
+    }
+}
+
+/// Candidate witness stack.
+#[derive(Clone)]
+struct Stack {
+    /// The stack can be built from the satisfier.
+    available: bool,
+    /// The stack contains a signature.
+    has_signature: bool,
+    /// Third parties can change the stack into another valid one.
+    malleable: bool,
+    /// The stack isn't a canonical (dis)satisfaction.
+    non_canonical: bool,
+    /// Serialized size of the elements.
+    size: usize,
+    /// Elements, from the bottom of the stack.
+    elements: Vec<Bytes>,
+}
+
+impl Stack {
+    /// The empty stack
+    fn empty() -> Self {
+        Stack {
+            available: true,
+            has_signature: false,
+            malleable: false,
+            non_canonical: false,
+            size: 0,
+            elements: Vec::new(),
+        }
+    }
+
+    /// A stack that can't be built
+    fn invalid() -> Self {
+        Stack {
+            available: false,
+            ..Stack::empty()
+        }
+    }
+
+    /// The stack of a single element
+    fn push(element: Bytes) -> Self {
+        Stack {
+            size: element_size(element.len()),
+            elements: vec![element],
+            ..Stack::empty()
+        }
+    }
+
+    /// The stack of an empty element
+    fn zero() -> Self {
+        Stack::push(Bytes::new())
+    }
+
+    /// The stack of a 1 element
+    fn one() -> Self {
+        Stack::push(vec![1].into())
+    }
+
+    fn with_signature(mut self) -> Self {
+        self.has_signature = true;
+        self
+    }
+
+    fn malleable(mut self) -> Self {
+        self.malleable = true;
+        self
+    }
+
+    fn non_canonical(mut self) -> Self {
+        self.non_canonical = true;
+        self
+    }
+}
+
+impl ops::Add for Stack {
+    type Output = Stack;
+
+    /// Concatenates the stacks, `other` on top of `self`.
+    fn add(mut self, other: Stack) -> Stack {
+        self.available &= other.available;
+        self.has_signature |= other.has_signature;
+        self.malleable |= other.malleable;
+        self.non_canonical |= other.non_canonical;
+        self.size += other.size;
+        self.elements.extend(other.elements);
+        self
+    }
+}
+
+impl ops::BitOr for Stack {
+    type Output = Stack;
+
+    /// Chooses between the stacks.
+    fn bitor(mut self, mut other: Stack) -> Stack {
+        if !self.available {
+            return other;
+        }
+        if !other.available {
+            return self;
+        }
+        // A stack without signature can always be used by third parties
+        match (self.has_signature, other.has_signature) {
+            (false, true) => return self,
+            (true, false) => return other,
+            (false, false) => {
+                self.malleable = true;
+                other.malleable = true;
+            }
+            (true, true) => {
+                if self.malleable != other.malleable {
+                    return if self.malleable { other } else { self };
+                }
+            }
+        }
+        if self.size <= other.size {
+            self
+        } else {
+            other
+        }
+    }
+}
+
+/// Returns the serialized size of a witness element.
+fn element_size(len: usize) -> usize {
+    let prefix = match len {
+        0..=0xfc => 1,
+        0xfd..=0xffff => 3,
+        _ => 5,
+    };
+    prefix + len
+}
+
+/// Adds the sizes, if both exist.
+fn add_size(a: Option<usize>, b: Option<usize>) -> Option<usize> {
+    Some(a? + b?)
+}
+
+/// Returns the largest of the sizes which exist.
+fn max_size(a: Option<usize>, b: Option<usize>) -> Option<usize> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.max(b)),
+        _ => a.or(b),
+    }
+}
+
+impl Miniscript {
+    /// Returns the witness stack satisfying the expression, without the witness script.
+    ///
+    /// Fails with `Error::Unsatisfiable` if the satisfier can't satisfy the expression, or only
+    /// with a malleable witness or a witness without signature.
+    pub fn satisfy(&self, satisfier: &Satisfier) -> Result<ScriptWitness, Error> {
+        let (_, sat) = self.produce(satisfier);
+        match sat.available && sat.has_signature && !sat.malleable {
+            true => Ok(sat.elements),
+            false => Err(Error::Unsatisfiable),
+        }
+    }
+
+    /// Returns the dissatisfaction and satisfaction stacks.
+    fn produce(&self, satisfier: &Satisfier) -> (Stack, Stack) {
+        match &self.fragment {
+            Fragment::False => (Stack::empty(), Stack::invalid()),
+            Fragment::True => (Stack::invalid(), Stack::empty()),
+            Fragment::PkK(key) => (Stack::zero(), satisfier.signature(key)),
+            Fragment::PkH(key) => {
+                let key_stack = Stack::push(key.to_vec().into());
+                (
+                    Stack::zero() + key_stack.clone(),
+                    satisfier.signature(key) + key_stack,
+                )
+            }
+            Fragment::RawPkH(hash) => {
+                match satisfier
+                    .signatures
+                    .keys()
+                    .find(|key| key.address_hash() == *hash)
+                {
+                    Some(key) => {
+                        let key_stack = Stack::push(key.to_vec().into());
+                        (
+                            Stack::zero() + key_stack.clone(),
+                            satisfier.signature(key) + key_stack,
+                        )
+                    }
+                    None => (Stack::invalid(), Stack::invalid()),
+                }
+            }
+            Fragment::Older(n) => match satisfier.check_older(*n) {
+                true => (Stack::invalid(), Stack::empty()),
+                false => (Stack::invalid(), Stack::invalid()),
+            },
+            Fragment::After(n) => match satisfier.check_after(*n) {
+                true => (Stack::invalid(), Stack::empty()),
+                false => (Stack::invalid(), Stack::invalid()),
+            },
+            Fragment::Sha256(hash) => (
+                zero32(),
+                Satisfier::preimage(&satisfier.sha256_preimages, hash),
+            ),
+            Fragment::Hash256(hash) => (
+                zero32(),
+                Satisfier::preimage(&satisfier.hash256_preimages, hash),
+            ),
+            Fragment::Ripemd160(hash) => (
+                zero32(),
+                Satisfier::preimage(&satisfier.ripemd160_preimages, hash),
+            ),
+            Fragment::Hash160(hash) => (
+                zero32(),
+                Satisfier::preimage(&satisfier.hash160_preimages, hash),
+            ),
+            Fragment::AndOr(x, y, z) => {
+                let ((x_nsat, x_sat), (y_nsat, y_sat), (z_nsat, z_sat)) = (
+                    x.produce(satisfier),
+                    y.produce(satisfier),
+                    z.produce(satisfier),
+                );
+                (
+                    (y_nsat + x_sat.clone()).non_canonical() | (z_nsat + x_nsat.clone()),
+                    (y_sat + x_sat) | (z_sat + x_nsat),
+                )
+            }
+            Fragment::AndV(x, y) => {
+                let ((_, x_sat), (y_nsat, y_sat)) = (x.produce(satisfier), y.produce(satisfier));
+                ((y_nsat + x_sat.clone()).non_canonical(), y_sat + x_sat)
+            }
+            Fragment::AndB(x, y) => {
+                let ((x_nsat, x_sat), (y_nsat, y_sat)) =
+                    (x.produce(satisfier), y.produce(satisfier));
+                (
+                    (y_nsat.clone() + x_nsat.clone())
+                        | (y_sat.clone() + x_nsat).malleable().non_canonical()
+                        | (y_nsat + x_sat.clone()).malleable().non_canonical(),
+                    y_sat + x_sat,
+                )
+            }
+            Fragment::OrB(x, z) => {
+                let ((x_nsat, x_sat), (z_nsat, z_sat)) =
+                    (x.produce(satisfier), z.produce(satisfier));
+                (
+                    z_nsat.clone() + x_nsat.clone(),
+                    (z_nsat + x_sat.clone())
+                        | (z_sat.clone() + x_nsat)
+                        | (z_sat + x_sat).malleable().non_canonical(),
+                )
+            }
+            Fragment::OrC(x, z) => {
+                let ((x_nsat, x_sat), (_, z_sat)) = (x.produce(satisfier), z.produce(satisfier));
+                (Stack::invalid(), x_sat | (z_sat + x_nsat))
+            }
+            Fragment::OrD(x, z) => {
+                let ((x_nsat, x_sat), (z_nsat, z_sat)) =
+                    (x.produce(satisfier), z.produce(satisfier));
+                (z_nsat + x_nsat.clone(), x_sat | (z_sat + x_nsat))
+            }
+            Fragment::OrI(x, z) => {
+                let ((x_nsat, x_sat), (z_nsat, z_sat)) =
+                    (x.produce(satisfier), z.produce(satisfier));
+                (
+                    (x_nsat + Stack::one()) | (z_nsat + Stack::zero()),
+                    (x_sat + Stack::one()) | (z_sat + Stack::zero()),
+                )
+            }
+            Fragment::Thresh(k, subs) => {
+                // sats[i]: the best stack of the arguments processed so far with i satisfied,
+                // the last argument being at the bottom
+                let mut sats = vec![Stack::empty()];
+                for sub in subs.iter().rev() {
+                    let (nsat, sat) = sub.produce(satisfier);
+                    let mut next = vec![sats[0].clone() + nsat.clone()];
+                    for j in 1..sats.len() {
+                        next.push(
+                            (sats[j].clone() + nsat.clone()) | (sats[j - 1].clone() + sat.clone()),
+                        );
+                    }
+                    next.push(sats[sats.len() - 1].clone() + sat);
+                    sats = next;
+                }
+                let mut nsat = Stack::invalid();
+                for (i, stack) in sats.iter().enumerate() {
+                    match i {
+                        i if i == *k => continue,
+                        // Dissatisfying some arguments only is overcomplete, so malleable
+                        0 => nsat = nsat | stack.clone(),
+                        _ => nsat = nsat | stack.clone().malleable().non_canonical(),
+                    }
+                }
+                (nsat, sats.swap_remove(*k))
+            }
+            Fragment::Multi(k, keys) => {
+                // sats[i]: the best stack with i signatures of the keys processed so far
+                let mut sats = vec![Stack::zero()];
+                for key in keys {
+                    let signature = satisfier.signature(key);
+                    let mut next = vec![sats[0].clone()];
+                    for j in 1..sats.len() {
+                        next.push(sats[j].clone() | (sats[j - 1].clone() + signature.clone()));
+                    }
+                    next.push(sats[sats.len() - 1].clone() + signature);
+                    sats = next;
+                }
+                let nsat = (0..*k).fold(Stack::zero(), |nsat, _| nsat + Stack::zero());
+                (nsat, sats.swap_remove(*k))
+            }
+            Fragment::Alt(x)
+            | Fragment::Swap(x)
+            | Fragment::Check(x)
+            | Fragment::ZeroNotEqual(x) => x.produce(satisfier),
+            Fragment::DupIf(x) => {
+                let (_, sat) = x.produce(satisfier);
+                (Stack::zero(), sat + Stack::one())
+            }
+            Fragment::Verify(x) => {
+                let (_, sat) = x.produce(satisfier);
+                (Stack::invalid(), sat)
+            }
+            Fragment::NonZero(x) => {
+                let (nsat, sat) = x.produce(satisfier);
+                // The dissatisfaction of X, if it starts with a nonzero element, can be used too
+                let nsat_malleable = nsat.available && !nsat.has_signature;
+                let zero = match nsat_malleable {
+                    true => Stack::zero().malleable(),
+                    false => Stack::zero(),
+                };
+                (zero, sat)
+            }
+        }
+    }
+
+    /// Returns the maximum size of the witness stack satisfying the expression: the elements
+    /// with their length prefixes, without the count of elements and the witness script.
+    ///
+    /// Signatures are counted with 73 bytes. Returns `None` if the expression can't be
+    /// satisfied.
+    pub fn max_satisfaction_size(&self) -> Option<usize> {
+        self.max_sizes().1
+    }
+
+    /// Returns the maximum weight of the P2WSH witness satisfying the expression: the count of
+    /// elements, the satisfaction and the witness script.
+    pub fn max_satisfaction_weight(&self) -> Option<usize> {
+        let script_size = self.script_size();
+        // Standard witnesses have at most 100 elements, counted on a single byte
+        Some(1 + self.max_satisfaction_size()? + element_size(script_size))
+    }
+
+    /// Returns the maximum sizes of the dissatisfaction and satisfaction stacks.
+    fn max_sizes(&self) -> (Option<usize>, Option<usize>) {
+        let zero = element_size(0);
+        let one = element_size(1);
+        match &self.fragment {
+            Fragment::False => (Some(0), None),
+            Fragment::True => (None, Some(0)),
+            Fragment::PkK(_) => (Some(zero), Some(SIGNATURE_SIZE)),
+            Fragment::PkH(_) | Fragment::RawPkH(_) => {
+                (Some(zero + KEY_SIZE), Some(SIGNATURE_SIZE + KEY_SIZE))
+            }
+            Fragment::Older(_) | Fragment::After(_) => (None, Some(0)),
+            Fragment::Sha256(_)
+            | Fragment::Hash256(_)
+            | Fragment::Ripemd160(_)
+            | Fragment::Hash160(_) => (Some(PREIMAGE_SIZE), Some(PREIMAGE_SIZE)),
+            Fragment::AndOr(x, y, z) => {
+                let ((x_nsat, x_sat), (_, y_sat), (z_nsat, z_sat)) =
+                    (x.max_sizes(), y.max_sizes(), z.max_sizes());
+                (
+                    add_size(x_nsat, z_nsat),
+                    max_size(add_size(x_sat, y_sat), add_size(x_nsat, z_sat)),
+                )
+            }
+            Fragment::AndV(x, y) => {
+                let ((_, x_sat), (y_nsat, y_sat)) = (x.max_sizes(), y.max_sizes());
+                (add_size(x_sat, y_nsat), add_size(x_sat, y_sat))
+            }
+            Fragment::AndB(x, y) => {
+                let ((x_nsat, x_sat), (y_nsat, y_sat)) = (x.max_sizes(), y.max_sizes());
+                (add_size(x_nsat, y_nsat), add_size(x_sat, y_sat))
+            }
+            Fragment::OrB(x, z) => {
+                let ((x_nsat, x_sat), (z_nsat, z_sat)) = (x.max_sizes(), z.max_sizes());
+                (
+                    add_size(x_nsat, z_nsat),
+                    max_size(add_size(x_sat, z_nsat), add_size(x_nsat, z_sat)),
+                )
+            }
+            Fragment::OrC(x, z) => {
+                let ((x_nsat, x_sat), (_, z_sat)) = (x.max_sizes(), z.max_sizes());
+                (None, max_size(x_sat, add_size(x_nsat, z_sat)))
+            }
+            Fragment::OrD(x, z) => {
+                let ((x_nsat, x_sat), (z_nsat, z_sat)) = (x.max_sizes(), z.max_sizes());
+                (
+                    add_size(x_nsat, z_nsat),
+                    max_size(x_sat, add_size(x_nsat, z_sat)),
+                )
+            }
+            Fragment::OrI(x, z) => {
+                let ((x_nsat, x_sat), (z_nsat, z_sat)) = (x.max_sizes(), z.max_sizes());
+                (
+                    max_size(add_size(x_nsat, Some(one)), add_size(z_nsat, Some(zero))),
+                    max_size(add_size(x_sat, Some(one)), add_size(z_sat, Some(zero))),
+                )
+            }
+            Fragment::Thresh(k, subs) => thresh_max(*k, subs.iter().map(Miniscript::max_sizes)),
+            Fragment::Multi(k, _) => (Some(zero * (k + 1)), Some(zero + SIGNATURE_SIZE * k)),
+            Fragment::Alt(x)
+            | Fragment::Swap(x)
+            | Fragment::Check(x)
+            | Fragment::ZeroNotEqual(x) => x.max_sizes(),
+            Fragment::DupIf(x) => (Some(zero), add_size(x.max_sizes().1, Some(one))),
+            Fragment::Verify(x) => (None, x.max_sizes().1),
+            Fragment::NonZero(x) => (Some(zero), x.max_sizes().1),
+        }
+    }
+}
+
+impl Miniscript {
+    /// Returns the maximum number of non-push opcodes counted by the interpreter when the
+    /// satisfaction is executed, with the keys of `CHECKMULTISIG` (Bitcoin Core's `GetOps`).
+    ///
+    /// Returns `None` if the expression can't be satisfied.
+    pub fn max_satisfaction_ops(&self) -> Option<usize> {
+        let (count, _, sat) = self.max_ops();
+        Some(count + sat?)
+    }
+
+    /// Returns the maximum number of elements of the witness stack satisfying the expression,
+    /// without the witness script (Bitcoin Core's `GetStackSize`).
+    ///
+    /// Returns `None` if the expression can't be satisfied.
+    pub fn max_satisfaction_stack_items(&self) -> Option<usize> {
+        self.max_stack_items().1
+    }
+
+    /// Returns the number of non-push opcodes of the script, and the maximum number of opcodes
+    /// counted on top of them when the dissatisfaction and satisfaction are executed: the keys of
+    /// `CHECKMULTISIG`.
+    fn max_ops(&self) -> (usize, Option<usize>, Option<usize>) {
+        match &self.fragment {
+            Fragment::False => (0, Some(0), None),
+            Fragment::True => (0, None, Some(0)),
+            Fragment::PkK(_) => (0, Some(0), Some(0)),
+            Fragment::PkH(_) | Fragment::RawPkH(_) => (3, Some(0), Some(0)),
+            Fragment::Older(_) | Fragment::After(_) => (1, None, Some(0)),
+            Fragment::Sha256(_)
+            | Fragment::Hash256(_)
+            | Fragment::Ripemd160(_)
+            | Fragment::Hash160(_) => (4, None, Some(0)),
+            Fragment::AndOr(x, y, z) => {
+                let ((x_count, x_nsat, x_sat), (y_count, _, y_sat), (z_count, z_nsat, z_sat)) =
+                    (x.max_ops(), y.max_ops(), z.max_ops());
+                (
+                    3 + x_count + y_count + z_count,
+                    add_size(x_nsat, z_nsat),
+                    max_size(add_size(x_sat, y_sat), add_size(x_nsat, z_sat)),
+                )
+            }
+            Fragment::AndV(x, y) => {
+                let ((x_count, _, x_sat), (y_count, _, y_sat)) = (x.max_ops(), y.max_ops());
+                (x_count + y_count, None, add_size(x_sat, y_sat))
+            }
+            Fragment::AndB(x, y) => {
+                let ((x_count, x_nsat, x_sat), (y_count, y_nsat, y_sat)) =
+                    (x.max_ops(), y.max_ops());
+                (
+                    1 + x_count + y_count,
+                    add_size(x_nsat, y_nsat),
+                    add_size(x_sat, y_sat),
+                )
+            }
+            Fragment::OrB(x, z) => {
+                let ((x_count, x_nsat, x_sat), (z_count, z_nsat, z_sat)) =
+                    (x.max_ops(), z.max_ops());
+                (
+                    1 + x_count + z_count,
+                    add_size(x_nsat, z_nsat),
+                    max_size(add_size(x_sat, z_nsat), add_size(x_nsat, z_sat)),
+                )
+            }
+            Fragment::OrC(x, z) => {
+                let ((x_count, x_nsat, x_sat), (z_count, _, z_sat)) = (x.max_ops(), z.max_ops());
+                (
+                    2 + x_count + z_count,
+                    None,
+                    max_size(x_sat, add_size(x_nsat, z_sat)),
+                )
+            }
+            Fragment::OrD(x, z) => {
+                let ((x_count, x_nsat, x_sat), (z_count, z_nsat, z_sat)) =
+                    (x.max_ops(), z.max_ops());
+                (
+                    3 + x_count + z_count,
+                    add_size(x_nsat, z_nsat),
+                    max_size(x_sat, add_size(x_nsat, z_sat)),
+                )
+            }
+            Fragment::OrI(x, z) => {
+                let ((x_count, x_nsat, x_sat), (z_count, z_nsat, z_sat)) =
+                    (x.max_ops(), z.max_ops());
+                (
+                    3 + x_count + z_count,
+                    max_size(x_nsat, z_nsat),
+                    max_size(x_sat, z_sat),
+                )
+            }
+            Fragment::Thresh(k, subs) => {
+                // The arguments are followed by ADD, and the last one by EQUAL
+                let count = subs.iter().map(|sub| sub.max_ops().0 + 1).sum();
+                let (nsat, sat) = thresh_max(
+                    *k,
+                    subs.iter().map(|sub| {
+                        let (_, nsat, sat) = sub.max_ops();
+                        (nsat, sat)
+                    }),
+                );
+                (count, nsat, sat)
+            }
+            Fragment::Multi(_, keys) => (1, Some(keys.len()), Some(keys.len())),
+            Fragment::Swap(x) | Fragment::Check(x) | Fragment::ZeroNotEqual(x) => {
+                let (count, nsat, sat) = x.max_ops();
+                (1 + count, nsat, sat)
+            }
+            Fragment::Alt(x) => {
+                let (count, nsat, sat) = x.max_ops();
+                (2 + count, nsat, sat)
+            }
+            Fragment::DupIf(x) => {
+                let (count, _, sat) = x.max_ops();
+                (3 + count, Some(0), sat)
+            }
+            Fragment::NonZero(x) => {
+                let (count, _, sat) = x.max_ops();
+                (4 + count, Some(0), sat)
+            }
+            Fragment::Verify(x) => {
+                let (count, _, sat) = x.max_ops();
+                // VERIFY is merged into the last opcode, except after `x` expressions
+                (count + x.ty.has("x") as usize, None, sat)
+            }
+        }
+    }
+
+    /// Returns the maximum numbers of elements of the dissatisfaction and satisfaction stacks.
+    fn max_stack_items(&self) -> (Option<usize>, Option<usize>) {
+        match &self.fragment {
+            Fragment::False => (Some(0), None),
+            Fragment::True | Fragment::Older(_) | Fragment::After(_) => (None, Some(0)),
+            Fragment::PkK(_) => (Some(1), Some(1)),
+            Fragment::PkH(_) | Fragment::RawPkH(_) => (Some(2), Some(2)),
+            Fragment::Sha256(_)
+            | Fragment::Hash256(_)
+            | Fragment::Ripemd160(_)
+            | Fragment::Hash160(_) => (None, Some(1)),
+            Fragment::AndOr(x, y, z) => {
+                let ((x_nsat, x_sat), (_, y_sat), (z_nsat, z_sat)) = (
+                    x.max_stack_items(),
+                    y.max_stack_items(),
+                    z.max_stack_items(),
+                );
+                (
+                    add_size(x_nsat, z_nsat),
+                    max_size(add_size(x_sat, y_sat), add_size(x_nsat, z_sat)),
+                )
+            }
+            Fragment::AndV(x, y) => {
+                let ((_, x_sat), (_, y_sat)) = (x.max_stack_items(), y.max_stack_items());
+                (None, add_size(x_sat, y_sat))
+            }
+            Fragment::AndB(x, y) => {
+                let ((x_nsat, x_sat), (y_nsat, y_sat)) = (x.max_stack_items(), y.max_stack_items());
+                (add_size(x_nsat, y_nsat), add_size(x_sat, y_sat))
+            }
+            Fragment::OrB(x, z) => {
+                let ((x_nsat, x_sat), (z_nsat, z_sat)) = (x.max_stack_items(), z.max_stack_items());
+                (
+                    add_size(x_nsat, z_nsat),
+                    max_size(add_size(x_sat, z_nsat), add_size(x_nsat, z_sat)),
+                )
+            }
+            Fragment::OrC(x, z) => {
+                let ((x_nsat, x_sat), (_, z_sat)) = (x.max_stack_items(), z.max_stack_items());
+                (None, max_size(x_sat, add_size(x_nsat, z_sat)))
+            }
+            Fragment::OrD(x, z) => {
+                let ((x_nsat, x_sat), (z_nsat, z_sat)) = (x.max_stack_items(), z.max_stack_items());
+                (
+                    add_size(x_nsat, z_nsat),
+                    max_size(x_sat, add_size(x_nsat, z_sat)),
+                )
+            }
+            Fragment::OrI(x, z) => {
+                let ((x_nsat, x_sat), (z_nsat, z_sat)) = (x.max_stack_items(), z.max_stack_items());
+                (
+                    add_size(max_size(x_nsat, z_nsat), Some(1)),
+                    add_size(max_size(x_sat, z_sat), Some(1)),
+                )
+            }
+            Fragment::Thresh(k, subs) => {
+                thresh_max(*k, subs.iter().map(Miniscript::max_stack_items))
+            }
+            Fragment::Multi(k, _) => (Some(k + 1), Some(k + 1)),
+            Fragment::Alt(x)
+            | Fragment::Swap(x)
+            | Fragment::Check(x)
+            | Fragment::ZeroNotEqual(x) => x.max_stack_items(),
+            Fragment::DupIf(x) => (Some(1), add_size(x.max_stack_items().1, Some(1))),
+            Fragment::Verify(x) => (None, x.max_stack_items().1),
+            Fragment::NonZero(x) => (Some(1), x.max_stack_items().1),
+        }
+    }
+}
+
+/// Returns the maximum dissatisfaction and satisfaction of `thresh(k,...)`, from those of its
+/// arguments: the dissatisfaction of every argument, and the largest combination of `k`
+/// satisfactions and dissatisfactions of the others.
+fn thresh_max(
+    k: usize,
+    subs: impl Iterator<Item = (Option<usize>, Option<usize>)>,
+) -> (Option<usize>, Option<usize>) {
+    // sats[j]: maximum with j satisfied arguments among the first ones
+    let mut sats = vec![Some(0)];
+    for (nsat, sat) in subs {
+        let mut next = vec![add_size(sats[0], nsat)];
+        for j in 1..sats.len() {
+            next.push(max_size(
+                add_size(sats[j], nsat),
+                add_size(sats[j - 1], sat),
+            ));
+        }
+        next.push(add_size(sats[sats.len() - 1], sat));
+        sats = next;
+    }
+    (sats[0], sats[k])
+}
+
+/// The stack of a 32 bytes element which isn't a preimage, malleable.
+fn zero32() -> Stack {
+    Stack::push(vec![0; 32].into()).malleable()
+}
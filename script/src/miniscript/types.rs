@@ -0,0 +1,295 @@
+//! Type system of miniscript
+//!
+//! Every expression has exactly one basic type and a set of properties, computed from the types
+//! of its arguments with the rules of the specification (and of Bitcoin Core's `ComputeType`):
+//!
+//! - `B` (base): pushes nonzero on satisfaction and an exact 0 on dissatisfaction.
+//! - `V` (verify): pushes nothing on satisfaction and can't be dissatisfied.
+//! - `K` (key): pushes a public key whose signature satisfies the expression.
+//! - `W` (wrapped): takes its inputs under the top element of the stack.
+//! - `z` / `o` / `n`: consumes no / exactly one / at least one nonzero stack element.
+//! - `d` / `u`: has a dissatisfaction / pushes exactly 1 on satisfaction.
+//! - `e` / `f`: the dissatisfaction is unique and non-malleable / there is no dissatisfaction
+//!   without a signature.
+//! - `s`: every satisfaction requires a signature.
+//! - `m`: a non-malleable satisfaction exists.
+//! - `x`: the last opcode is not `EQUAL`, `CHECKSIG` or `CHECKMULTISIG`, so `v:` adds `VERIFY`.
+//! - `g` / `h` / `i` / `j`: contains a relative time / relative height / absolute time /
+//!   absolute height lock.
+//! - `k`: no satisfaction requires time and height locks of the same kind at once.
+
+use core::{fmt, ops};
+
+use light_bitcoin_chain::constants::{LOCKTIME_THRESHOLD, SEQUENCE_LOCKTIME_TYPE_FLAG};
+
+use super::Fragment;
+
+/// Letters of the properties, by bit.
+const LETTERS: &[u8] = b"BVKWzondufesmxghijk";
+
+/// Type of a miniscript expression: its basic type and properties.
+#[derive(PartialEq, Eq, Clone, Copy, Default)]
+pub struct Type(u32);
+
+impl Type {
+    /// Returns the type with the properties of the letters.
+    const fn from_letters(letters: &str) -> Type {
+        let letters = letters.as_bytes();
+        let mut bits = 0;
+        let mut i = 0;
+        while i < letters.len() {
+            let mut bit = 0;
+            while LETTERS[bit] != letters[i] {
+                bit += 1;
+            }
+            bits |= 1 << bit;
+            i += 1;
+        }
+        Type(bits)
+    }
+
+    /// Returns true if the type has every property of the letters.
+    pub fn has(self, letters: &str) -> bool {
+        let other = Type::from_letters(letters);
+        self.0 & other.0 == other.0
+    }
+
+    /// Returns true if the type has exactly one basic type.
+    pub fn is_valid(self) -> bool {
+        (self & Type::from_letters("BVKW")).0.count_ones() == 1
+    }
+
+    /// Returns the type if the condition holds, the empty type otherwise.
+    fn when(self, condition: bool) -> Type {
+        if condition {
+            self
+        } else {
+            Type::default()
+        }
+    }
+}
+
+impl ops::BitOr for Type {
+    type Output = Type;
+
+    fn bitor(self, other: Type) -> Type {
+        Type(self.0 | other.0)
+    }
+}
+
+impl ops::BitAnd for Type {
+    type Output = Type;
+
+    fn bitand(self, other: Type) -> Type {
+        Type(self.0 & other.0)
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (bit, letter) in LETTERS.iter().enumerate() {
+            if self.0 & (1 << bit) != 0 {
+                write!(f, "{}", *letter as char)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Type {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self, f)
+    }
+}
+
+/// Shorthand of `Type::from_letters`.
+const fn t(letters: &str) -> Type {
+    Type::from_letters(letters)
+}
+
+/// Returns true if satisfying both types may require time and height locks of the same kind.
+fn timelocks_conflict(x: Type, y: Type) -> bool {
+    (x.has("g") && y.has("h"))
+        || (x.has("h") && y.has("g"))
+        || (x.has("i") && y.has("j"))
+        || (x.has("j") && y.has("i"))
+}
+
+/// Computes the type of the fragment from the types of its arguments.
+///
+/// The returned type is not valid if the arguments don't have the types the fragment requires.
+pub(super) fn compute(fragment: &Fragment) -> Type {
+    match fragment {
+        Fragment::False => t("Bzudemsxk"),
+        Fragment::True => t("Bzufmxk"),
+        Fragment::PkK(_) => t("Konudemsxk"),
+        Fragment::PkH(_) | Fragment::RawPkH(_) => t("Knudemsxk"),
+        Fragment::Older(n) => {
+            let time = n & SEQUENCE_LOCKTIME_TYPE_FLAG != 0;
+            t("g").when(time) | t("h").when(!time) | t("Bzfmxk")
+        }
+        Fragment::After(n) => {
+            let time = *n >= LOCKTIME_THRESHOLD;
+            t("i").when(time) | t("j").when(!time) | t("Bzfmxk")
+        }
+        Fragment::Sha256(_)
+        | Fragment::Hash256(_)
+        | Fragment::Ripemd160(_)
+        | Fragment::Hash160(_) => t("Bonudmk"),
+        Fragment::Multi(..) => t("Bnudemsk"),
+        Fragment::Alt(x) => {
+            let x = x.ty();
+            t("W").when(x.has("B")) | (x & t("ghijkudfems")) | t("x")
+        }
+        Fragment::Swap(x) => {
+            let x = x.ty();
+            t("W").when(x.has("Bo")) | (x & t("ghijkudfemsx"))
+        }
+        Fragment::Check(x) => {
+            let x = x.ty();
+            t("B").when(x.has("K")) | (x & t("ghijkondfem")) | t("us")
+        }
+        Fragment::DupIf(x) => {
+            let x = x.ty();
+            t("B").when(x.has("Vz"))
+                | t("o").when(x.has("z"))
+                | t("e").when(x.has("f"))
+                | (x & t("ghijkms"))
+                | t("ndx")
+        }
+        Fragment::Verify(x) => {
+            let x = x.ty();
+            t("V").when(x.has("B")) | (x & t("ghijkzonms")) | t("fx")
+        }
+        Fragment::NonZero(x) => {
+            let x = x.ty();
+            t("B").when(x.has("Bn")) | t("e").when(x.has("f")) | (x & t("ghijkoums")) | t("ndx")
+        }
+        Fragment::ZeroNotEqual(x) => {
+            let x = x.ty();
+            (x & t("ghijkBzondfems")) | t("ux")
+        }
+        Fragment::AndV(x, y) => {
+            let (x, y) = (x.ty(), y.ty());
+            (y & t("KVB")).when(x.has("V"))
+                | (x & t("n"))
+                | (y & t("n")).when(x.has("z"))
+                | ((x | y) & t("o")).when((x | y).has("z"))
+                | (x & y & t("dmz"))
+                | ((x | y) & t("s"))
+                | t("f").when(y.has("f") || x.has("s"))
+                | (y & t("ux"))
+                | ((x | y) & t("ghij"))
+                | t("k").when((x & y).has("k") && !timelocks_conflict(x, y))
+        }
+        Fragment::AndB(x, y) => {
+            let (x, y) = (x.ty(), y.ty());
+            (x & t("B")).when(y.has("W"))
+                | ((x | y) & t("o")).when((x | y).has("z"))
+                | (x & t("n"))
+                | (y & t("n")).when(x.has("z"))
+                | (x & y & t("e")).when((x & y).has("s"))
+                | (x & y & t("dzm"))
+                | t("f").when((x & y).has("f") || x.has("sf") || y.has("sf"))
+                | ((x | y) & t("s"))
+                | t("ux")
+                | ((x | y) & t("ghij"))
+                | t("k").when((x & y).has("k") && !timelocks_conflict(x, y))
+        }
+        Fragment::OrB(x, z) => {
+            let (x, z) = (x.ty(), z.ty());
+            t("B").when(x.has("Bd") && z.has("Wd"))
+                | ((x | z) & t("o")).when((x | z).has("z"))
+                | (x & z & t("m")).when((x | z).has("s") && (x & z).has("e"))
+                | (x & z & t("zse"))
+                | t("dux")
+                | ((x | z) & t("ghij"))
+                | (x & z & t("k"))
+        }
+        Fragment::OrC(x, z) => {
+            let (x, z) = (x.ty(), z.ty());
+            (z & t("V")).when(x.has("Bdu"))
+                | (x & t("o")).when(z.has("z"))
+                | (x & z & t("m")).when(x.has("e") && (x | z).has("s"))
+                | (x & z & t("zs"))
+                | t("fx")
+                | ((x | z) & t("ghij"))
+                | (x & z & t("k"))
+        }
+        Fragment::OrD(x, z) => {
+            let (x, z) = (x.ty(), z.ty());
+            (z & t("B")).when(x.has("Bdu"))
+                | (x & t("o")).when(z.has("z"))
+                | (x & z & t("m")).when(x.has("e") && (x | z).has("s"))
+                | (x & z & t("zse"))
+                | (z & t("ufd"))
+                | t("x")
+                | ((x | z) & t("ghij"))
+                | (x & z & t("k"))
+        }
+        Fragment::OrI(x, z) => {
+            let (x, z) = (x.ty(), z.ty());
+            (x & z & t("VBKufs"))
+                | t("o").when((x & z).has("z"))
+                | ((x | z) & t("e")).when((x | z).has("f"))
+                | (x & z & t("m")).when((x | z).has("s"))
+                | ((x | z) & t("d"))
+                | t("x")
+                | ((x | z) & t("ghij"))
+                | (x & z & t("k"))
+        }
+        Fragment::AndOr(x, y, z) => {
+            let (x, y, z) = (x.ty(), y.ty(), z.ty());
+            (y & z & t("BKV")).when(x.has("Bdu"))
+                | (x & y & z & t("z"))
+                | ((x | (y & z)) & t("o")).when((x | (y & z)).has("z"))
+                | (y & z & t("u"))
+                | (z & t("f")).when(x.has("s") || y.has("f"))
+                | (z & t("d"))
+                | (x & z & t("e")).when(x.has("s") || y.has("f"))
+                | (x & y & z & t("m")).when(x.has("e") && (x | y | z).has("s"))
+                | (z & (x | y) & t("s"))
+                | t("x")
+                | ((x | y | z) & t("ghij"))
+                | t("k").when((x & y & z).has("k") && !timelocks_conflict(x, y))
+        }
+        Fragment::Thresh(k, subs) => {
+            let mut all_e = true;
+            let mut all_m = true;
+            let mut args = 0;
+            let mut num_s = 0;
+            let mut timelocks = t("k");
+            for (i, sub) in subs.iter().enumerate() {
+                let sub = sub.ty();
+                if !sub.has(if i == 0 { "Bdu" } else { "Wdu" }) {
+                    return Type::default();
+                }
+                all_e &= sub.has("e");
+                all_m &= sub.has("m");
+                if sub.has("s") {
+                    num_s += 1;
+                }
+                args += if sub.has("z") {
+                    0
+                } else if sub.has("o") {
+                    1
+                } else {
+                    2
+                };
+                timelocks = ((timelocks | sub) & t("ghij"))
+                    | t("k").when(
+                        (timelocks & sub).has("k")
+                            && (*k <= 1 || !timelocks_conflict(timelocks, sub)),
+                    );
+            }
+            let n = subs.len();
+            t("Bdu")
+                | t("z").when(args == 0)
+                | t("o").when(args == 1)
+                | t("e").when(all_e && num_s == n)
+                | t("m").when(all_e && all_m && num_s + k >= n)
+                | t("s").when(num_s + k > n)
+                | timelocks
+        }
+    }
+}
@@ -228,6 +228,9 @@ impl fmt::Display for Opcode {
 }
 
 impl Opcode {
+    /// Tapscript `OP_CHECKSIGADD` (BIP342) shares its value with `OP_CHECKDATASIG`.
+    pub const OP_CHECKSIGADD: Opcode = Opcode::OP_CHECKDATASIG;
+
     pub fn from_u8(u: u8) -> Option<Self> {
         use self::Opcode::*;
         match u {
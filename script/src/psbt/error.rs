@@ -0,0 +1,88 @@
+//! PSBT errors
+
+use light_bitcoin_keys::Error as KeysError;
+use light_bitcoin_primitives::io;
+
+/// PSBT errors
+#[derive(Debug, PartialEq)]
+pub enum Error {
+    /// The data doesn't start with the `psbt 0xff` magic bytes.
+    InvalidMagic,
+    /// The data isn't valid base64.
+    InvalidBase64,
+    /// The data is truncated or malformed.
+    Io(io::Error),
+    /// A key is malformed or not allowed in the PSBT version.
+    InvalidKey,
+    /// A key appears twice in the same map.
+    DuplicateKey,
+    /// A value is malformed.
+    InvalidValue,
+    /// The PSBT version is unknown, or a field required by the version is missing.
+    InvalidVersion,
+    /// The unsigned transaction is missing or contains signatures.
+    InvalidUnsignedTx,
+    /// The input or output index doesn't exist.
+    InvalidIndex,
+    /// The output spent by an input is unknown.
+    MissingUtxo,
+    /// The non-witness UTXO doesn't match the previous output of the input.
+    UtxoMismatch,
+    /// The redeem or witness script required to spend an input is missing or doesn't match.
+    InvalidScript,
+    /// The PSBTs being combined don't spend the same transaction.
+    MismatchedTransaction,
+    /// Inputs or outputs can't be added to the PSBT.
+    NotModifiable,
+    /// The inputs require incompatible lock times.
+    LocktimeConflict,
+    /// The signature hash type is not allowed.
+    InvalidSighashType,
+    /// The input script can't be finalized with the collected signatures.
+    MissingSignature,
+    /// An input isn't finalized.
+    NotFinalized,
+    /// Key error
+    Keys(KeysError),
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Error::InvalidMagic => "Invalid PSBT magic bytes".fmt(f),
+            Error::InvalidBase64 => "Invalid base64 encoding".fmt(f),
+            Error::Io(e) => write!(f, "Malformed PSBT data: {:?}", e),
+            Error::InvalidKey => "Invalid PSBT key".fmt(f),
+            Error::DuplicateKey => "Duplicate PSBT key".fmt(f),
+            Error::InvalidValue => "Invalid PSBT value".fmt(f),
+            Error::InvalidVersion => "Invalid PSBT version or missing required field".fmt(f),
+            Error::InvalidUnsignedTx => "Invalid unsigned transaction".fmt(f),
+            Error::InvalidIndex => "Input or output index out of range".fmt(f),
+            Error::MissingUtxo => "Spent output is unknown".fmt(f),
+            Error::UtxoMismatch => "Non-witness UTXO does not match the previous output".fmt(f),
+            Error::InvalidScript => "Redeem or witness script is missing or mismatched".fmt(f),
+            Error::MismatchedTransaction => "PSBTs spend different transactions".fmt(f),
+            Error::NotModifiable => "Inputs or outputs are not modifiable".fmt(f),
+            Error::LocktimeConflict => "Inputs require incompatible lock times".fmt(f),
+            Error::InvalidSighashType => "Invalid signature hash type".fmt(f),
+            Error::MissingSignature => "Not enough signatures to finalize the input".fmt(f),
+            Error::NotFinalized => "Input is not finalized".fmt(f),
+            Error::Keys(e) => write!(f, "Key error: {}", e),
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+impl From<KeysError> for Error {
+    fn from(e: KeysError) -> Self {
+        Error::Keys(e)
+    }
+}
@@ -0,0 +1,216 @@
+//! Finalizer role of the PSBT
+
+#[cfg(not(feature = "std"))]
+use alloc::{collections::BTreeMap, vec, vec::Vec};
+#[cfg(feature = "std")]
+use std::collections::BTreeMap;
+
+use light_bitcoin_keys::Public;
+use light_bitcoin_primitives::{Bytes, H256};
+
+use super::{taproot_output_key, Error, Input, Psbt, SpentScriptKind};
+use crate::builder::Builder;
+use crate::num::Num;
+use crate::opcode::Opcode;
+use crate::script::{Script, ScriptType, ScriptWitness};
+use crate::taproot::{tapleaf_hash, TAPROOT_LEAF_TAPSCRIPT};
+
+impl Psbt {
+    /// Finalizes every input.
+    pub fn finalize(&mut self) -> Result<(), Error> {
+        for index in 0..self.inputs.len() {
+            self.finalize_input(index)?;
+        }
+        Ok(())
+    }
+
+    /// Builds the final script sig and witness of the input from its signatures (Finalizer).
+    ///
+    /// Single key, multisig and tapscript `multi_a` scripts are supported. Taproot inputs prefer
+    /// the key path, then the satisfiable leaf with the shortest control block. Once finalized,
+    /// only the UTXOs, the final scripts and the fields of the unsigned transaction are kept.
+    pub fn finalize_input(&mut self, index: usize) -> Result<(), Error> {
+        let input = self.inputs.get(index).ok_or(Error::InvalidIndex)?;
+        if input.is_finalized() {
+            return Ok(());
+        }
+        let script_pubkey: Script = self.spent_output(index)?.script_pubkey.into();
+
+        let (script_sig, witness) = if taproot_output_key(&script_pubkey).is_some() {
+            (None, Some(satisfy_taproot(input)?))
+        } else {
+            let spent = self.spent_script(index, &script_pubkey)?;
+            let witness = match spent.kind {
+                SpentScriptKind::Legacy => None,
+                SpentScriptKind::WitnessKeyHash => {
+                    Some(satisfy(&spent.script_code, &input.partial_sigs)?)
+                }
+                SpentScriptKind::WitnessScript => {
+                    let mut stack = satisfy(&spent.script_code, &input.partial_sigs)?;
+                    stack.push(spent.script_code.to_bytes());
+                    Some(stack)
+                }
+            };
+            let script_sig = match (&witness, spent.redeem_script) {
+                (None, redeem_script) => {
+                    let stack = satisfy(&spent.script_code, &input.partial_sigs)?;
+                    let builder = stack
+                        .iter()
+                        .fold(Builder::default(), |builder, data| builder.push_data(data));
+                    let builder = match redeem_script {
+                        Some(redeem_script) => builder.push_data(&redeem_script),
+                        None => builder,
+                    };
+                    Some(builder.into_script())
+                }
+                // Nested segwit programs are pushed by the script sig
+                (Some(_), Some(redeem_script)) => {
+                    Some(Builder::default().push_data(&redeem_script).into_script())
+                }
+                (Some(_), None) => None,
+            };
+            (script_sig, witness)
+        };
+
+        let input = core::mem::take(&mut self.inputs[index]);
+        self.inputs[index] = Input {
+            non_witness_utxo: input.non_witness_utxo,
+            witness_utxo: input.witness_utxo,
+            final_script_sig: script_sig,
+            final_script_witness: witness,
+            previous_txid: input.previous_txid,
+            output_index: input.output_index,
+            sequence: input.sequence,
+            required_time_locktime: input.required_time_locktime,
+            required_height_locktime: input.required_height_locktime,
+            proprietary: input.proprietary,
+            unknown: input.unknown,
+            ..Default::default()
+        };
+        Ok(())
+    }
+}
+
+/// Returns the stack satisfying a single key or multisig script with the partial signatures.
+fn satisfy(script: &Script, partial_sigs: &BTreeMap<Public, Bytes>) -> Result<Vec<Bytes>, Error> {
+    match script.script_type() {
+        ScriptType::PubKey => {
+            let public = Public::from_slice(&script[1..script.len() - 1])?;
+            let signature = partial_sigs.get(&public).ok_or(Error::MissingSignature)?;
+            Ok(vec![signature.clone()])
+        }
+        ScriptType::PubKeyHash => partial_sigs
+            .iter()
+            .find(|(public, _)| public.address_hash().as_bytes() == &script[3..23])
+            .map(|(public, signature)| vec![signature.clone(), public.to_vec().into()])
+            .ok_or(Error::MissingSignature),
+        ScriptType::Multisig => {
+            let (keys, m, _) = script.parse_redeem_script().ok_or(Error::InvalidScript)?;
+            // Signatures are checked in the order of the keys
+            let signatures = keys
+                .iter()
+                .filter_map(|key| {
+                    let public = Public::from_slice(key).ok()?;
+                    partial_sigs.get(&public).cloned()
+                })
+                .take(m as usize)
+                .collect::<Vec<_>>();
+            if signatures.len() < m as usize {
+                return Err(Error::MissingSignature);
+            }
+            // Dummy element consumed by OP_CHECKMULTISIG
+            let mut stack = vec![Bytes::new()];
+            stack.extend(signatures);
+            Ok(stack)
+        }
+        _ => Err(Error::InvalidScript),
+    }
+}
+
+/// Returns the witness of the taproot input, using the key path when possible.
+fn satisfy_taproot(input: &Input) -> Result<ScriptWitness, Error> {
+    if let Some(signature) = &input.tap_key_sig {
+        return Ok(vec![signature.clone()]);
+    }
+
+    let mut best: Option<ScriptWitness> = None;
+    for (control, (script, leaf_version)) in &input.tap_scripts {
+        if *leaf_version != TAPROOT_LEAF_TAPSCRIPT {
+            continue;
+        }
+        if matches!(&best, Some(witness) if witness[witness.len() - 1].len() <= control.len()) {
+            continue;
+        }
+        let leaf_hash = tapleaf_hash(*leaf_version, script);
+        if let Some(mut stack) = satisfy_tapscript(script, &leaf_hash, &input.tap_script_sigs) {
+            stack.push(script.to_bytes());
+            stack.push(control.clone());
+            best = Some(stack);
+        }
+    }
+    best.ok_or(Error::MissingSignature)
+}
+
+/// Returns the stack satisfying a `<key> OP_CHECKSIG` or `multi_a` tapscript.
+fn satisfy_tapscript(
+    script: &Script,
+    leaf_hash: &H256,
+    signatures: &BTreeMap<(H256, H256), Bytes>,
+) -> Option<Vec<Bytes>> {
+    let instructions = script.iter().collect::<Result<Vec<_>, _>>().ok()?;
+    // `multi_a` scripts are `<key> OP_CHECKSIG (<key> OP_CHECKSIGADD)* <k> OP_NUMEQUAL`
+    let (threshold, keys) = match instructions.as_slice() {
+        [key, checksig] if checksig.opcode == Opcode::OP_CHECKSIG => (1, vec![key.data?]),
+        [pairs @ .., threshold, numequal]
+            if numequal.opcode == Opcode::OP_NUMEQUAL
+                && !pairs.is_empty()
+                && pairs.len().is_multiple_of(2) =>
+        {
+            let threshold: usize = match threshold.data {
+                Some(data) => Num::from_slice(data, true, 4).ok()?.into(),
+                None if threshold.opcode.is_within_op_n() => {
+                    threshold.opcode.decode_op_n() as usize
+                }
+                None => return None,
+            };
+            let keys = pairs
+                .chunks(2)
+                .enumerate()
+                .map(|(i, pair)| {
+                    let expected = if i == 0 {
+                        Opcode::OP_CHECKSIG
+                    } else {
+                        Opcode::OP_CHECKSIGADD
+                    };
+                    pair[0].data.filter(|_| pair[1].opcode == expected)
+                })
+                .collect::<Option<Vec<_>>>()?;
+            (threshold, keys)
+        }
+        _ => return None,
+    };
+
+    let mut count = 0;
+    let mut stack = Vec::with_capacity(keys.len());
+    for key in &keys {
+        if key.len() != 32 {
+            return None;
+        }
+        let signature = signatures
+            .get(&(H256::from_slice(key), *leaf_hash))
+            .filter(|_| count < threshold);
+        match signature {
+            Some(signature) => {
+                count += 1;
+                stack.push(signature.clone());
+            }
+            None => stack.push(Bytes::new()),
+        }
+    }
+    if count < threshold || threshold == 0 {
+        return None;
+    }
+    // The signature of the first key is checked first, so it must be on top of the stack
+    stack.reverse();
+    Some(stack)
+}
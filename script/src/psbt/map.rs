@@ -0,0 +1,960 @@
+//! Global, input and output maps of a PSBT
+
+#[cfg(not(feature = "std"))]
+use alloc::{collections::BTreeMap, vec::Vec};
+#[cfg(feature = "std")]
+use std::collections::BTreeMap;
+
+use light_bitcoin_chain::{Transaction, TransactionOutput};
+use light_bitcoin_crypto::{checksum, dhash160, dhash256, ripemd160, sha256};
+use light_bitcoin_keys::{DisplayLayout, ExtendedPubKey, Public};
+use light_bitcoin_primitives::{io, Bytes, H160, H256};
+use light_bitcoin_serialization::{
+    serialize, serialize_with_flags, CompactInteger, Reader, Serializable, Stream,
+    SERIALIZE_TRANSACTION_WITNESS,
+};
+
+use super::error::Error;
+use super::raw::{
+    deserialize_key_source, deserialize_value, serialize_key_source, Key, KeySource, Pair,
+    ProprietaryKey, PSBT_PROPRIETARY,
+};
+use crate::script::{Script, ScriptWitness};
+use crate::taproot::{
+    TAPROOT_CONTROL_BASE_SIZE, TAPROOT_CONTROL_MAX_SIZE, TAPROOT_CONTROL_NODE_SIZE,
+};
+
+const PSBT_GLOBAL_UNSIGNED_TX: u64 = 0x00;
+const PSBT_GLOBAL_XPUB: u64 = 0x01;
+const PSBT_GLOBAL_TX_VERSION: u64 = 0x02;
+const PSBT_GLOBAL_FALLBACK_LOCKTIME: u64 = 0x03;
+const PSBT_GLOBAL_INPUT_COUNT: u64 = 0x04;
+const PSBT_GLOBAL_OUTPUT_COUNT: u64 = 0x05;
+const PSBT_GLOBAL_TX_MODIFIABLE: u64 = 0x06;
+const PSBT_GLOBAL_VERSION: u64 = 0xfb;
+
+const PSBT_IN_NON_WITNESS_UTXO: u64 = 0x00;
+const PSBT_IN_WITNESS_UTXO: u64 = 0x01;
+const PSBT_IN_PARTIAL_SIG: u64 = 0x02;
+const PSBT_IN_SIGHASH_TYPE: u64 = 0x03;
+const PSBT_IN_REDEEM_SCRIPT: u64 = 0x04;
+const PSBT_IN_WITNESS_SCRIPT: u64 = 0x05;
+const PSBT_IN_BIP32_DERIVATION: u64 = 0x06;
+const PSBT_IN_FINAL_SCRIPTSIG: u64 = 0x07;
+const PSBT_IN_FINAL_SCRIPTWITNESS: u64 = 0x08;
+const PSBT_IN_RIPEMD160: u64 = 0x0a;
+const PSBT_IN_SHA256: u64 = 0x0b;
+const PSBT_IN_HASH160: u64 = 0x0c;
+const PSBT_IN_HASH256: u64 = 0x0d;
+const PSBT_IN_PREVIOUS_TXID: u64 = 0x0e;
+const PSBT_IN_OUTPUT_INDEX: u64 = 0x0f;
+const PSBT_IN_SEQUENCE: u64 = 0x10;
+const PSBT_IN_REQUIRED_TIME_LOCKTIME: u64 = 0x11;
+const PSBT_IN_REQUIRED_HEIGHT_LOCKTIME: u64 = 0x12;
+const PSBT_IN_TAP_KEY_SIG: u64 = 0x13;
+const PSBT_IN_TAP_SCRIPT_SIG: u64 = 0x14;
+const PSBT_IN_TAP_LEAF_SCRIPT: u64 = 0x15;
+const PSBT_IN_TAP_BIP32_DERIVATION: u64 = 0x16;
+const PSBT_IN_TAP_INTERNAL_KEY: u64 = 0x17;
+const PSBT_IN_TAP_MERKLE_ROOT: u64 = 0x18;
+
+const PSBT_OUT_REDEEM_SCRIPT: u64 = 0x00;
+const PSBT_OUT_WITNESS_SCRIPT: u64 = 0x01;
+const PSBT_OUT_BIP32_DERIVATION: u64 = 0x02;
+const PSBT_OUT_AMOUNT: u64 = 0x03;
+const PSBT_OUT_SCRIPT: u64 = 0x04;
+const PSBT_OUT_TAP_INTERNAL_KEY: u64 = 0x05;
+const PSBT_OUT_TAP_TREE: u64 = 0x06;
+const PSBT_OUT_TAP_BIP32_DERIVATION: u64 = 0x07;
+
+/// Size of a serialized extended key, without the base58 checksum.
+const EXTENDED_KEY_SIZE: usize = 78;
+
+/// Leaf hashes and key source of a taproot key.
+pub type TapKeyOrigin = (Vec<H256>, KeySource);
+
+/// Global map
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct Global {
+    /// The transaction being signed (version 0 only). Its inputs have empty scripts and witnesses.
+    pub unsigned_tx: Option<Transaction>,
+    /// Extended public keys used by the inputs and outputs, with their key source.
+    pub xpubs: BTreeMap<ExtendedPubKey, KeySource>,
+    /// Version of the transaction (version 2 only).
+    pub tx_version: Option<i32>,
+    /// Lock time used when no input requires one (version 2 only).
+    pub fallback_locktime: Option<u32>,
+    /// Flags allowing to add inputs (bit 0) or outputs (bit 1) and indicating that an input is
+    /// signed with `SIGHASH_SINGLE` (bit 2) (version 2 only).
+    pub tx_modifiable: Option<u8>,
+    /// Version of the PSBT, 0 (BIP174) or 2 (BIP370).
+    pub version: u32,
+    /// Proprietary entries.
+    pub proprietary: BTreeMap<ProprietaryKey, Bytes>,
+    /// Unknown entries.
+    pub unknown: BTreeMap<Key, Bytes>,
+}
+
+/// Input map
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct Input {
+    /// The transaction of the output spent by the input.
+    pub non_witness_utxo: Option<Transaction>,
+    /// The output spent by the input, for segwit inputs.
+    pub witness_utxo: Option<TransactionOutput>,
+    /// ECDSA signatures (including the sighash type byte) of the public keys.
+    pub partial_sigs: BTreeMap<Public, Bytes>,
+    /// Signature hash type to sign the input with.
+    pub sighash_type: Option<u32>,
+    /// Redeem script of P2SH inputs.
+    pub redeem_script: Option<Script>,
+    /// Witness script of P2WSH inputs.
+    pub witness_script: Option<Script>,
+    /// Public keys needed to sign the input, with their key source.
+    pub bip32_derivation: BTreeMap<Public, KeySource>,
+    /// Finalized script sig.
+    pub final_script_sig: Option<Script>,
+    /// Finalized script witness.
+    pub final_script_witness: Option<ScriptWitness>,
+    /// Preimages of `OP_RIPEMD160` hashes.
+    pub ripemd160_preimages: BTreeMap<H160, Bytes>,
+    /// Preimages of `OP_SHA256` hashes.
+    pub sha256_preimages: BTreeMap<H256, Bytes>,
+    /// Preimages of `OP_HASH160` hashes.
+    pub hash160_preimages: BTreeMap<H160, Bytes>,
+    /// Preimages of `OP_HASH256` hashes.
+    pub hash256_preimages: BTreeMap<H256, Bytes>,
+    /// Hash of the transaction of the spent output (version 2 only).
+    pub previous_txid: Option<H256>,
+    /// Index of the spent output (version 2 only).
+    pub output_index: Option<u32>,
+    /// Sequence number of the input, `0xffffffff` if absent (version 2 only).
+    pub sequence: Option<u32>,
+    /// Minimum time based lock time required by the input (version 2 only).
+    pub required_time_locktime: Option<u32>,
+    /// Minimum height based lock time required by the input (version 2 only).
+    pub required_height_locktime: Option<u32>,
+    /// Taproot key path signature (including the sighash type byte unless `SIGHASH_DEFAULT`).
+    pub tap_key_sig: Option<Bytes>,
+    /// Taproot script path signatures of the x-only public keys for the leaf hashes.
+    pub tap_script_sigs: BTreeMap<(H256, H256), Bytes>,
+    /// Taproot leaf scripts with their leaf version, by control block.
+    pub tap_scripts: BTreeMap<Bytes, (Script, u8)>,
+    /// X-only public keys needed to sign the input, with the leaf hashes they're used in and their
+    /// key source.
+    pub tap_key_origins: BTreeMap<H256, TapKeyOrigin>,
+    /// Taproot internal key.
+    pub tap_internal_key: Option<H256>,
+    /// Merkle root of the taproot script tree.
+    pub tap_merkle_root: Option<H256>,
+    /// Proprietary entries.
+    pub proprietary: BTreeMap<ProprietaryKey, Bytes>,
+    /// Unknown entries.
+    pub unknown: BTreeMap<Key, Bytes>,
+}
+
+/// Output map
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct Output {
+    /// Redeem script of P2SH outputs.
+    pub redeem_script: Option<Script>,
+    /// Witness script of P2WSH outputs.
+    pub witness_script: Option<Script>,
+    /// Public keys of the output, with their key source.
+    pub bip32_derivation: BTreeMap<Public, KeySource>,
+    /// Value of the output (version 2 only).
+    pub amount: Option<u64>,
+    /// Script of the output (version 2 only).
+    pub script: Option<Script>,
+    /// Taproot internal key.
+    pub tap_internal_key: Option<H256>,
+    /// Leaves of the taproot script tree in depth-first order: depth, leaf version and script.
+    pub tap_tree: Vec<(u8, u8, Script)>,
+    /// X-only public keys of the output, with the leaf hashes they're used in and their key source.
+    pub tap_key_origins: BTreeMap<H256, TapKeyOrigin>,
+    /// Proprietary entries.
+    pub proprietary: BTreeMap<ProprietaryKey, Bytes>,
+    /// Unknown entries.
+    pub unknown: BTreeMap<Key, Bytes>,
+}
+
+impl Global {
+    /// Reads the global map. Returns the map with the number of inputs and outputs.
+    pub(crate) fn read<T>(reader: &mut Reader<T>) -> Result<(Global, usize, usize), Error>
+    where
+        T: io::Read,
+    {
+        let mut global = Global::default();
+        let mut version = None;
+        let mut input_count = None;
+        let mut output_count = None;
+        while let Some(pair) = Pair::read(reader)? {
+            let Pair { key, value } = pair;
+            match key.type_value {
+                PSBT_GLOBAL_UNSIGNED_TX => {
+                    let tx: Transaction = deserialize_value(no_key(&key, &value)?)?;
+                    if tx
+                        .inputs
+                        .iter()
+                        .any(|input| !input.script_sig.is_empty() || input.has_witness())
+                    {
+                        return Err(Error::InvalidUnsignedTx);
+                    }
+                    set_once(&mut global.unsigned_tx, tx)?;
+                }
+                PSBT_GLOBAL_XPUB => {
+                    if key.key.len() != EXTENDED_KEY_SIZE {
+                        return Err(Error::InvalidKey);
+                    }
+                    let mut layout = key.key.to_vec();
+                    layout.extend_from_slice(checksum(&key.key).as_bytes());
+                    let xpub =
+                        ExtendedPubKey::from_layout(&layout).map_err(|_| Error::InvalidKey)?;
+                    insert_once(&mut global.xpubs, xpub, deserialize_key_source(&value)?)?;
+                }
+                PSBT_GLOBAL_TX_VERSION => {
+                    set_once(
+                        &mut global.tx_version,
+                        deserialize_value(no_key(&key, &value)?)?,
+                    )?;
+                }
+                PSBT_GLOBAL_FALLBACK_LOCKTIME => {
+                    set_once(
+                        &mut global.fallback_locktime,
+                        deserialize_value(no_key(&key, &value)?)?,
+                    )?;
+                }
+                PSBT_GLOBAL_INPUT_COUNT => {
+                    let count: CompactInteger = deserialize_value(no_key(&key, &value)?)?;
+                    set_once(&mut input_count, usize::from(count))?;
+                }
+                PSBT_GLOBAL_OUTPUT_COUNT => {
+                    let count: CompactInteger = deserialize_value(no_key(&key, &value)?)?;
+                    set_once(&mut output_count, usize::from(count))?;
+                }
+                PSBT_GLOBAL_TX_MODIFIABLE => {
+                    set_once(
+                        &mut global.tx_modifiable,
+                        deserialize_value(no_key(&key, &value)?)?,
+                    )?;
+                }
+                PSBT_GLOBAL_VERSION => {
+                    set_once(&mut version, deserialize_value(no_key(&key, &value)?)?)?;
+                }
+                PSBT_PROPRIETARY => {
+                    insert_once(
+                        &mut global.proprietary,
+                        ProprietaryKey::from_key(&key)?,
+                        value,
+                    )?;
+                }
+                _ => insert_once(&mut global.unknown, key, value)?,
+            }
+        }
+
+        global.version = version.unwrap_or(0);
+        match global.version {
+            0 => {
+                if global.tx_version.is_some()
+                    || global.fallback_locktime.is_some()
+                    || global.tx_modifiable.is_some()
+                    || input_count.is_some()
+                    || output_count.is_some()
+                {
+                    return Err(Error::InvalidKey);
+                }
+                let tx = global
+                    .unsigned_tx
+                    .as_ref()
+                    .ok_or(Error::InvalidUnsignedTx)?;
+                let (inputs, outputs) = (tx.inputs.len(), tx.outputs.len());
+                Ok((global, inputs, outputs))
+            }
+            2 => {
+                if global.unsigned_tx.is_some() {
+                    return Err(Error::InvalidKey);
+                }
+                match (global.tx_version, input_count, output_count) {
+                    (Some(_), Some(inputs), Some(outputs)) => Ok((global, inputs, outputs)),
+                    _ => Err(Error::InvalidVersion),
+                }
+            }
+            _ => Err(Error::InvalidVersion),
+        }
+    }
+
+    /// Returns the pairs of the map, in serialization order.
+    pub(crate) fn pairs(&self, input_count: usize, output_count: usize) -> Vec<Pair> {
+        let mut pairs = Vec::new();
+        if let Some(tx) = &self.unsigned_tx {
+            push_pair(&mut pairs, PSBT_GLOBAL_UNSIGNED_TX, &[], serialize(tx));
+        }
+        for (xpub, source) in &self.xpubs {
+            let layout = xpub.layout();
+            push_pair(
+                &mut pairs,
+                PSBT_GLOBAL_XPUB,
+                &layout[..EXTENDED_KEY_SIZE],
+                serialize_key_source(source),
+            );
+        }
+        if let Some(tx_version) = &self.tx_version {
+            push_pair(
+                &mut pairs,
+                PSBT_GLOBAL_TX_VERSION,
+                &[],
+                serialize(tx_version),
+            );
+        }
+        if let Some(locktime) = &self.fallback_locktime {
+            push_pair(
+                &mut pairs,
+                PSBT_GLOBAL_FALLBACK_LOCKTIME,
+                &[],
+                serialize(locktime),
+            );
+        }
+        if self.version >= 2 {
+            let count = CompactInteger::from(input_count);
+            push_pair(&mut pairs, PSBT_GLOBAL_INPUT_COUNT, &[], serialize(&count));
+            let count = CompactInteger::from(output_count);
+            push_pair(&mut pairs, PSBT_GLOBAL_OUTPUT_COUNT, &[], serialize(&count));
+        }
+        if let Some(modifiable) = &self.tx_modifiable {
+            push_pair(
+                &mut pairs,
+                PSBT_GLOBAL_TX_MODIFIABLE,
+                &[],
+                serialize(modifiable),
+            );
+        }
+        if self.version > 0 {
+            push_pair(
+                &mut pairs,
+                PSBT_GLOBAL_VERSION,
+                &[],
+                serialize(&self.version),
+            );
+        }
+        push_extra_pairs(&mut pairs, &self.proprietary, &self.unknown);
+        pairs
+    }
+
+    /// Merges the entries of another global map of the same transaction.
+    pub(crate) fn combine(&mut self, other: Global) {
+        merge(&mut self.xpubs, other.xpubs);
+        // Modifiable flags cleared by any signer stay cleared
+        self.tx_modifiable = match (self.tx_modifiable, other.tx_modifiable) {
+            (Some(a), Some(b)) => Some((a & b & 0b011) | ((a | b) & 0b100)),
+            (a, b) => a.or(b),
+        };
+        merge(&mut self.proprietary, other.proprietary);
+        merge(&mut self.unknown, other.unknown);
+    }
+}
+
+impl Input {
+    /// Reads an input map of a PSBT of the given version.
+    pub(crate) fn read<T>(reader: &mut Reader<T>, version: u32) -> Result<Input, Error>
+    where
+        T: io::Read,
+    {
+        let mut input = Input::default();
+        while let Some(pair) = Pair::read(reader)? {
+            input.insert(pair)?;
+        }
+
+        let v2_fields = input.previous_txid.is_some()
+            || input.output_index.is_some()
+            || input.sequence.is_some()
+            || input.required_time_locktime.is_some()
+            || input.required_height_locktime.is_some();
+        match version {
+            0 if v2_fields => Err(Error::InvalidKey),
+            2 if input.previous_txid.is_none() || input.output_index.is_none() => {
+                Err(Error::InvalidVersion)
+            }
+            _ => Ok(input),
+        }
+    }
+
+    fn insert(&mut self, pair: Pair) -> Result<(), Error> {
+        let Pair { key, value } = pair;
+        match key.type_value {
+            PSBT_IN_NON_WITNESS_UTXO => {
+                let tx = deserialize_value(no_key(&key, &value)?)?;
+                set_once(&mut self.non_witness_utxo, tx)
+            }
+            PSBT_IN_WITNESS_UTXO => {
+                let output = deserialize_value(no_key(&key, &value)?)?;
+                set_once(&mut self.witness_utxo, output)
+            }
+            PSBT_IN_PARTIAL_SIG => {
+                if value.is_empty() {
+                    return Err(Error::InvalidValue);
+                }
+                insert_once(&mut self.partial_sigs, ecdsa_public(&key)?, value)
+            }
+            PSBT_IN_SIGHASH_TYPE => {
+                let sighash_type = deserialize_value(no_key(&key, &value)?)?;
+                set_once(&mut self.sighash_type, sighash_type)
+            }
+            PSBT_IN_REDEEM_SCRIPT => {
+                let script = no_key(&key, &value)?.to_vec().into();
+                set_once(&mut self.redeem_script, script)
+            }
+            PSBT_IN_WITNESS_SCRIPT => {
+                let script = no_key(&key, &value)?.to_vec().into();
+                set_once(&mut self.witness_script, script)
+            }
+            PSBT_IN_BIP32_DERIVATION => insert_once(
+                &mut self.bip32_derivation,
+                ecdsa_public(&key)?,
+                deserialize_key_source(&value)?,
+            ),
+            PSBT_IN_FINAL_SCRIPTSIG => {
+                let script = no_key(&key, &value)?.to_vec().into();
+                set_once(&mut self.final_script_sig, script)
+            }
+            PSBT_IN_FINAL_SCRIPTWITNESS => {
+                let mut reader = Reader::new(no_key(&key, &value)?);
+                let witness = reader
+                    .read_list_max(value.len())
+                    .map_err(|_| Error::InvalidValue)?;
+                if !reader.is_finished() {
+                    return Err(Error::InvalidValue);
+                }
+                set_once(&mut self.final_script_witness, witness)
+            }
+            PSBT_IN_RIPEMD160 => {
+                let hash = preimage_hash(&key, &value, 20, ripemd160(&value).as_bytes())?;
+                insert_once(&mut self.ripemd160_preimages, H160::from_slice(hash), value)
+            }
+            PSBT_IN_SHA256 => {
+                let hash = preimage_hash(&key, &value, 32, sha256(&value).as_bytes())?;
+                insert_once(&mut self.sha256_preimages, H256::from_slice(hash), value)
+            }
+            PSBT_IN_HASH160 => {
+                let hash = preimage_hash(&key, &value, 20, dhash160(&value).as_bytes())?;
+                insert_once(&mut self.hash160_preimages, H160::from_slice(hash), value)
+            }
+            PSBT_IN_HASH256 => {
+                let hash = preimage_hash(&key, &value, 32, dhash256(&value).as_bytes())?;
+                insert_once(&mut self.hash256_preimages, H256::from_slice(hash), value)
+            }
+            // Version 2 fields were unknown keys of earlier PSBTs, keys with data still are
+            PSBT_IN_PREVIOUS_TXID if key.key.is_empty() => {
+                let txid = deserialize_value(no_key(&key, &value)?)?;
+                set_once(&mut self.previous_txid, txid)
+            }
+            PSBT_IN_OUTPUT_INDEX if key.key.is_empty() => {
+                let index = deserialize_value(no_key(&key, &value)?)?;
+                set_once(&mut self.output_index, index)
+            }
+            PSBT_IN_SEQUENCE if key.key.is_empty() => {
+                let sequence = deserialize_value(no_key(&key, &value)?)?;
+                set_once(&mut self.sequence, sequence)
+            }
+            PSBT_IN_REQUIRED_TIME_LOCKTIME if key.key.is_empty() => {
+                let locktime: u32 = deserialize_value(no_key(&key, &value)?)?;
+                if locktime < 500_000_000 {
+                    return Err(Error::InvalidValue);
+                }
+                set_once(&mut self.required_time_locktime, locktime)
+            }
+            PSBT_IN_REQUIRED_HEIGHT_LOCKTIME if key.key.is_empty() => {
+                let locktime: u32 = deserialize_value(no_key(&key, &value)?)?;
+                if locktime == 0 || locktime >= 500_000_000 {
+                    return Err(Error::InvalidValue);
+                }
+                set_once(&mut self.required_height_locktime, locktime)
+            }
+            PSBT_IN_TAP_KEY_SIG => {
+                let signature = schnorr_signature(no_key(&key, &value)?)?;
+                set_once(&mut self.tap_key_sig, signature)
+            }
+            PSBT_IN_TAP_SCRIPT_SIG => {
+                if key.key.len() != 64 {
+                    return Err(Error::InvalidKey);
+                }
+                let x_only = H256::from_slice(&key.key[..32]);
+                let leaf_hash = H256::from_slice(&key.key[32..]);
+                let signature = schnorr_signature(&value)?;
+                insert_once(&mut self.tap_script_sigs, (x_only, leaf_hash), signature)
+            }
+            PSBT_IN_TAP_LEAF_SCRIPT => {
+                let control = &key.key;
+                if control.len() < TAPROOT_CONTROL_BASE_SIZE
+                    || control.len() > TAPROOT_CONTROL_MAX_SIZE
+                    || !(control.len() - TAPROOT_CONTROL_BASE_SIZE)
+                        .is_multiple_of(TAPROOT_CONTROL_NODE_SIZE)
+                {
+                    return Err(Error::InvalidKey);
+                }
+                let (leaf_version, script) = value.split_last().ok_or(Error::InvalidValue)?;
+                let leaf = (script.to_vec().into(), *leaf_version);
+                insert_once(&mut self.tap_scripts, key.key, leaf)
+            }
+            PSBT_IN_TAP_BIP32_DERIVATION => {
+                let origin = tap_key_origin(&value)?;
+                insert_once(&mut self.tap_key_origins, x_only_public(&key)?, origin)
+            }
+            PSBT_IN_TAP_INTERNAL_KEY => {
+                let key = x_only_value(no_key(&key, &value)?)?;
+                set_once(&mut self.tap_internal_key, key)
+            }
+            PSBT_IN_TAP_MERKLE_ROOT => {
+                let root = x_only_value(no_key(&key, &value)?)?;
+                set_once(&mut self.tap_merkle_root, root)
+            }
+            PSBT_PROPRIETARY => insert_once(
+                &mut self.proprietary,
+                ProprietaryKey::from_key(&key)?,
+                value,
+            ),
+            _ => insert_once(&mut self.unknown, key, value),
+        }
+    }
+
+    /// Returns the pairs of the map, in serialization order.
+    pub(crate) fn pairs(&self) -> Vec<Pair> {
+        let mut pairs = Vec::new();
+        if let Some(tx) = &self.non_witness_utxo {
+            let value = serialize_with_flags(tx, SERIALIZE_TRANSACTION_WITNESS);
+            push_pair(&mut pairs, PSBT_IN_NON_WITNESS_UTXO, &[], value);
+        }
+        if let Some(output) = &self.witness_utxo {
+            push_pair(&mut pairs, PSBT_IN_WITNESS_UTXO, &[], serialize(output));
+        }
+        for (public, signature) in &self.partial_sigs {
+            push_pair(&mut pairs, PSBT_IN_PARTIAL_SIG, public, signature.clone());
+        }
+        if let Some(sighash_type) = &self.sighash_type {
+            push_pair(
+                &mut pairs,
+                PSBT_IN_SIGHASH_TYPE,
+                &[],
+                serialize(sighash_type),
+            );
+        }
+        if let Some(script) = &self.redeem_script {
+            push_pair(&mut pairs, PSBT_IN_REDEEM_SCRIPT, &[], script.to_bytes());
+        }
+        if let Some(script) = &self.witness_script {
+            push_pair(&mut pairs, PSBT_IN_WITNESS_SCRIPT, &[], script.to_bytes());
+        }
+        for (public, source) in &self.bip32_derivation {
+            let value = serialize_key_source(source);
+            push_pair(&mut pairs, PSBT_IN_BIP32_DERIVATION, public, value);
+        }
+        for (hash, preimage) in &self.ripemd160_preimages {
+            push_pair(
+                &mut pairs,
+                PSBT_IN_RIPEMD160,
+                hash.as_bytes(),
+                preimage.clone(),
+            );
+        }
+        for (hash, preimage) in &self.sha256_preimages {
+            push_pair(
+                &mut pairs,
+                PSBT_IN_SHA256,
+                hash.as_bytes(),
+                preimage.clone(),
+            );
+        }
+        for (hash, preimage) in &self.hash160_preimages {
+            push_pair(
+                &mut pairs,
+                PSBT_IN_HASH160,
+                hash.as_bytes(),
+                preimage.clone(),
+            );
+        }
+        for (hash, preimage) in &self.hash256_preimages {
+            push_pair(
+                &mut pairs,
+                PSBT_IN_HASH256,
+                hash.as_bytes(),
+                preimage.clone(),
+            );
+        }
+        if let Some(txid) = &self.previous_txid {
+            push_pair(&mut pairs, PSBT_IN_PREVIOUS_TXID, &[], serialize(txid));
+        }
+        if let Some(index) = &self.output_index {
+            push_pair(&mut pairs, PSBT_IN_OUTPUT_INDEX, &[], serialize(index));
+        }
+        if let Some(sequence) = &self.sequence {
+            push_pair(&mut pairs, PSBT_IN_SEQUENCE, &[], serialize(sequence));
+        }
+        if let Some(locktime) = &self.required_time_locktime {
+            push_pair(
+                &mut pairs,
+                PSBT_IN_REQUIRED_TIME_LOCKTIME,
+                &[],
+                serialize(locktime),
+            );
+        }
+        if let Some(locktime) = &self.required_height_locktime {
+            let value = serialize(locktime);
+            push_pair(&mut pairs, PSBT_IN_REQUIRED_HEIGHT_LOCKTIME, &[], value);
+        }
+        if let Some(signature) = &self.tap_key_sig {
+            push_pair(&mut pairs, PSBT_IN_TAP_KEY_SIG, &[], signature.clone());
+        }
+        for ((x_only, leaf_hash), signature) in &self.tap_script_sigs {
+            let mut key = x_only.as_bytes().to_vec();
+            key.extend_from_slice(leaf_hash.as_bytes());
+            push_pair(&mut pairs, PSBT_IN_TAP_SCRIPT_SIG, &key, signature.clone());
+        }
+        for (control, (script, leaf_version)) in &self.tap_scripts {
+            let mut value = script.to_vec();
+            value.push(*leaf_version);
+            push_pair(&mut pairs, PSBT_IN_TAP_LEAF_SCRIPT, control, value.into());
+        }
+        for (x_only, origin) in &self.tap_key_origins {
+            let value = serialize_tap_key_origin(origin);
+            push_pair(
+                &mut pairs,
+                PSBT_IN_TAP_BIP32_DERIVATION,
+                x_only.as_bytes(),
+                value,
+            );
+        }
+        if let Some(key) = &self.tap_internal_key {
+            push_pair(&mut pairs, PSBT_IN_TAP_INTERNAL_KEY, &[], serialize(key));
+        }
+        if let Some(root) = &self.tap_merkle_root {
+            push_pair(&mut pairs, PSBT_IN_TAP_MERKLE_ROOT, &[], serialize(root));
+        }
+        if let Some(script) = &self.final_script_sig {
+            push_pair(&mut pairs, PSBT_IN_FINAL_SCRIPTSIG, &[], script.to_bytes());
+        }
+        if let Some(witness) = &self.final_script_witness {
+            let mut stream = Stream::default();
+            stream.append_list(witness);
+            push_pair(&mut pairs, PSBT_IN_FINAL_SCRIPTWITNESS, &[], stream.out());
+        }
+        push_extra_pairs(&mut pairs, &self.proprietary, &self.unknown);
+        pairs
+    }
+
+    /// Returns true if the input has a final script sig or script witness.
+    pub fn is_finalized(&self) -> bool {
+        self.final_script_sig.is_some() || self.final_script_witness.is_some()
+    }
+
+    /// Merges the entries of the same input of another PSBT.
+    pub(crate) fn combine(&mut self, other: Input) {
+        combine_option(&mut self.non_witness_utxo, other.non_witness_utxo);
+        combine_option(&mut self.witness_utxo, other.witness_utxo);
+        merge(&mut self.partial_sigs, other.partial_sigs);
+        combine_option(&mut self.sighash_type, other.sighash_type);
+        combine_option(&mut self.redeem_script, other.redeem_script);
+        combine_option(&mut self.witness_script, other.witness_script);
+        merge(&mut self.bip32_derivation, other.bip32_derivation);
+        combine_option(&mut self.final_script_sig, other.final_script_sig);
+        combine_option(&mut self.final_script_witness, other.final_script_witness);
+        merge(&mut self.ripemd160_preimages, other.ripemd160_preimages);
+        merge(&mut self.sha256_preimages, other.sha256_preimages);
+        merge(&mut self.hash160_preimages, other.hash160_preimages);
+        merge(&mut self.hash256_preimages, other.hash256_preimages);
+        combine_option(&mut self.sequence, other.sequence);
+        combine_option(
+            &mut self.required_time_locktime,
+            other.required_time_locktime,
+        );
+        combine_option(
+            &mut self.required_height_locktime,
+            other.required_height_locktime,
+        );
+        combine_option(&mut self.tap_key_sig, other.tap_key_sig);
+        merge(&mut self.tap_script_sigs, other.tap_script_sigs);
+        merge(&mut self.tap_scripts, other.tap_scripts);
+        merge(&mut self.tap_key_origins, other.tap_key_origins);
+        combine_option(&mut self.tap_internal_key, other.tap_internal_key);
+        combine_option(&mut self.tap_merkle_root, other.tap_merkle_root);
+        merge(&mut self.proprietary, other.proprietary);
+        merge(&mut self.unknown, other.unknown);
+    }
+}
+
+impl Output {
+    /// Reads an output map of a PSBT of the given version.
+    pub(crate) fn read<T>(reader: &mut Reader<T>, version: u32) -> Result<Output, Error>
+    where
+        T: io::Read,
+    {
+        let mut output = Output::default();
+        while let Some(pair) = Pair::read(reader)? {
+            output.insert(pair)?;
+        }
+
+        match version {
+            0 if output.amount.is_some() || output.script.is_some() => Err(Error::InvalidKey),
+            2 if output.amount.is_none() || output.script.is_none() => Err(Error::InvalidVersion),
+            _ => Ok(output),
+        }
+    }
+
+    fn insert(&mut self, pair: Pair) -> Result<(), Error> {
+        let Pair { key, value } = pair;
+        match key.type_value {
+            PSBT_OUT_REDEEM_SCRIPT => {
+                let script = no_key(&key, &value)?.to_vec().into();
+                set_once(&mut self.redeem_script, script)
+            }
+            PSBT_OUT_WITNESS_SCRIPT => {
+                let script = no_key(&key, &value)?.to_vec().into();
+                set_once(&mut self.witness_script, script)
+            }
+            PSBT_OUT_BIP32_DERIVATION => insert_once(
+                &mut self.bip32_derivation,
+                ecdsa_public(&key)?,
+                deserialize_key_source(&value)?,
+            ),
+            // Version 2 fields were unknown keys of earlier PSBTs, keys with data still are
+            PSBT_OUT_AMOUNT if key.key.is_empty() => {
+                let amount = deserialize_value(no_key(&key, &value)?)?;
+                set_once(&mut self.amount, amount)
+            }
+            PSBT_OUT_SCRIPT if key.key.is_empty() => {
+                let script = no_key(&key, &value)?.to_vec().into();
+                set_once(&mut self.script, script)
+            }
+            PSBT_OUT_TAP_INTERNAL_KEY => {
+                let key = x_only_value(no_key(&key, &value)?)?;
+                set_once(&mut self.tap_internal_key, key)
+            }
+            PSBT_OUT_TAP_TREE => {
+                if !self.tap_tree.is_empty() {
+                    return Err(Error::DuplicateKey);
+                }
+                let mut reader = Reader::new(no_key(&key, &value)?);
+                if reader.is_finished() {
+                    return Err(Error::InvalidValue);
+                }
+                while !reader.is_finished() {
+                    let leaf = read_tap_leaf(&mut reader).map_err(|_| Error::InvalidValue)?;
+                    self.tap_tree.push(leaf);
+                }
+                Ok(())
+            }
+            PSBT_OUT_TAP_BIP32_DERIVATION => {
+                let origin = tap_key_origin(&value)?;
+                insert_once(&mut self.tap_key_origins, x_only_public(&key)?, origin)
+            }
+            PSBT_PROPRIETARY => insert_once(
+                &mut self.proprietary,
+                ProprietaryKey::from_key(&key)?,
+                value,
+            ),
+            _ => insert_once(&mut self.unknown, key, value),
+        }
+    }
+
+    /// Returns the pairs of the map, in serialization order.
+    pub(crate) fn pairs(&self) -> Vec<Pair> {
+        let mut pairs = Vec::new();
+        if let Some(script) = &self.redeem_script {
+            push_pair(&mut pairs, PSBT_OUT_REDEEM_SCRIPT, &[], script.to_bytes());
+        }
+        if let Some(script) = &self.witness_script {
+            push_pair(&mut pairs, PSBT_OUT_WITNESS_SCRIPT, &[], script.to_bytes());
+        }
+        for (public, source) in &self.bip32_derivation {
+            let value = serialize_key_source(source);
+            push_pair(&mut pairs, PSBT_OUT_BIP32_DERIVATION, public, value);
+        }
+        if let Some(amount) = &self.amount {
+            push_pair(&mut pairs, PSBT_OUT_AMOUNT, &[], serialize(amount));
+        }
+        if let Some(script) = &self.script {
+            push_pair(&mut pairs, PSBT_OUT_SCRIPT, &[], script.to_bytes());
+        }
+        if let Some(key) = &self.tap_internal_key {
+            push_pair(&mut pairs, PSBT_OUT_TAP_INTERNAL_KEY, &[], serialize(key));
+        }
+        if !self.tap_tree.is_empty() {
+            let mut stream = Stream::default();
+            for (depth, leaf_version, script) in &self.tap_tree {
+                stream
+                    .append(depth)
+                    .append(leaf_version)
+                    .append(&script.to_bytes());
+            }
+            push_pair(&mut pairs, PSBT_OUT_TAP_TREE, &[], stream.out());
+        }
+        for (x_only, origin) in &self.tap_key_origins {
+            let value = serialize_tap_key_origin(origin);
+            push_pair(
+                &mut pairs,
+                PSBT_OUT_TAP_BIP32_DERIVATION,
+                x_only.as_bytes(),
+                value,
+            );
+        }
+        push_extra_pairs(&mut pairs, &self.proprietary, &self.unknown);
+        pairs
+    }
+
+    /// Merges the entries of the same output of another PSBT.
+    pub(crate) fn combine(&mut self, other: Output) {
+        combine_option(&mut self.redeem_script, other.redeem_script);
+        combine_option(&mut self.witness_script, other.witness_script);
+        merge(&mut self.bip32_derivation, other.bip32_derivation);
+        combine_option(&mut self.tap_internal_key, other.tap_internal_key);
+        if self.tap_tree.is_empty() {
+            self.tap_tree = other.tap_tree;
+        }
+        merge(&mut self.tap_key_origins, other.tap_key_origins);
+        merge(&mut self.proprietary, other.proprietary);
+        merge(&mut self.unknown, other.unknown);
+    }
+}
+
+fn push_pair(pairs: &mut Vec<Pair>, type_value: u64, key: &[u8], value: Bytes) {
+    pairs.push(Pair {
+        key: Key::new(type_value, key.to_vec().into()),
+        value,
+    });
+}
+
+fn push_extra_pairs(
+    pairs: &mut Vec<Pair>,
+    proprietary: &BTreeMap<ProprietaryKey, Bytes>,
+    unknown: &BTreeMap<Key, Bytes>,
+) {
+    for (key, value) in proprietary {
+        pairs.push(Pair {
+            key: key.to_key(),
+            value: value.clone(),
+        });
+    }
+    for (key, value) in unknown {
+        pairs.push(Pair {
+            key: key.clone(),
+            value: value.clone(),
+        });
+    }
+}
+
+fn set_once<T>(field: &mut Option<T>, value: T) -> Result<(), Error> {
+    if field.is_some() {
+        return Err(Error::DuplicateKey);
+    }
+    *field = Some(value);
+    Ok(())
+}
+
+fn insert_once<K: Ord, V>(map: &mut BTreeMap<K, V>, key: K, value: V) -> Result<(), Error> {
+    if map.insert(key, value).is_some() {
+        return Err(Error::DuplicateKey);
+    }
+    Ok(())
+}
+
+fn combine_option<T>(field: &mut Option<T>, other: Option<T>) {
+    if field.is_none() {
+        *field = other;
+    }
+}
+
+fn merge<K: Ord, V>(map: &mut BTreeMap<K, V>, other: BTreeMap<K, V>) {
+    for (key, value) in other {
+        map.entry(key).or_insert(value);
+    }
+}
+
+/// Returns the value of a key which must have no key data.
+fn no_key<'a>(key: &Key, value: &'a Bytes) -> Result<&'a [u8], Error> {
+    if !key.key.is_empty() {
+        return Err(Error::InvalidKey);
+    }
+    Ok(value)
+}
+
+fn ecdsa_public(key: &Key) -> Result<Public, Error> {
+    match key.key.len() {
+        33 | 65 => Public::from_slice(&key.key).map_err(|_| Error::InvalidKey),
+        _ => Err(Error::InvalidKey),
+    }
+}
+
+fn x_only_public(key: &Key) -> Result<H256, Error> {
+    if key.key.len() != 32 {
+        return Err(Error::InvalidKey);
+    }
+    Ok(H256::from_slice(&key.key))
+}
+
+fn x_only_value(value: &[u8]) -> Result<H256, Error> {
+    if value.len() != 32 {
+        return Err(Error::InvalidValue);
+    }
+    Ok(H256::from_slice(value))
+}
+
+fn schnorr_signature(value: &[u8]) -> Result<Bytes, Error> {
+    match value.len() {
+        64 | 65 => Ok(value.to_vec().into()),
+        _ => Err(Error::InvalidValue),
+    }
+}
+
+/// Checks that the key is the hash of the preimage.
+fn preimage_hash<'a>(
+    key: &'a Key,
+    preimage: &[u8],
+    size: usize,
+    hash: &[u8],
+) -> Result<&'a [u8], Error> {
+    if key.key.len() != size {
+        return Err(Error::InvalidKey);
+    }
+    if *key.key != *hash || preimage.is_empty() {
+        return Err(Error::InvalidValue);
+    }
+    Ok(&key.key)
+}
+
+fn tap_key_origin(value: &[u8]) -> Result<TapKeyOrigin, Error> {
+    let mut reader = Reader::new(value);
+    let count: usize = reader
+        .read::<CompactInteger>()
+        .map_err(|_| Error::InvalidValue)?
+        .into();
+    let offset = CompactInteger::from(count).serialized_size();
+    if count > (value.len() - offset) / 32 {
+        return Err(Error::InvalidValue);
+    }
+    let end = offset + count * 32;
+    let leaf_hashes = value[offset..end]
+        .chunks(32)
+        .map(H256::from_slice)
+        .collect();
+    let source = deserialize_key_source(&value[end..])?;
+    Ok((leaf_hashes, source))
+}
+
+fn read_tap_leaf<T>(reader: &mut Reader<T>) -> Result<(u8, u8, Script), io::Error>
+where
+    T: io::Read,
+{
+    let depth = reader.read()?;
+    if depth > 128 {
+        return Err(io::Error::ReadMalformedData);
+    }
+    let leaf_version = reader.read()?;
+    let script: Bytes = reader.read()?;
+    Ok((depth, leaf_version, script.into()))
+}
+
+fn serialize_tap_key_origin(origin: &TapKeyOrigin) -> Bytes {
+    let (leaf_hashes, source) = origin;
+    let mut stream = Stream::default();
+    stream.append_list(leaf_hashes);
+    stream.append_slice(&serialize_key_source(source));
+    stream.out()
+}
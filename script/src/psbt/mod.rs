@@ -0,0 +1,957 @@
+//! Partially signed bitcoin transactions (BIP174, BIP370).
+//!
+//! https://github.com/bitcoin/bips/blob/master/bip-0174.mediawiki
+//! https://github.com/bitcoin/bips/blob/master/bip-0370.mediawiki
+//!
+//! The roles of the format map onto `Psbt` methods:
+//!
+//! - Creator: `Psbt::from_unsigned_tx` (version 0) and `Psbt::new_v2` (version 2),
+//!   completed by `add_input` and `add_output` (the Constructor of version 2).
+//! - Updater: `set_non_witness_utxo`, `set_witness_utxo` and the public fields of the maps.
+//! - Signer: `sign`, `sign_input` and `sign_with_xpriv`.
+//! - Combiner: `combine`.
+//! - Finalizer: `finalize` and `finalize_input`.
+//! - Extractor: `extract_tx`.
+
+mod error;
+mod finalize;
+mod map;
+mod raw;
+mod sign;
+
+#[cfg(not(feature = "std"))]
+use alloc::{string::String, vec::Vec};
+use core::{fmt, str};
+
+use light_bitcoin_chain::constants::SEQUENCE_FINAL;
+use light_bitcoin_chain::{OutPoint, Transaction, TransactionInput, TransactionOutput};
+use light_bitcoin_crypto::{dhash160, sha256};
+use light_bitcoin_primitives::{io, H160, H256};
+use light_bitcoin_serialization::{serialize, Deserializable, Reader, Serializable, Stream};
+
+use crate::builder::Builder;
+use crate::script::Script;
+
+pub use self::error::Error;
+pub use self::map::{Global, Input, Output, TapKeyOrigin};
+pub use self::raw::{Key, KeySource, Pair, ProprietaryKey};
+
+/// Magic bytes starting every PSBT: `psbt` followed by `0xff`.
+const PSBT_MAGIC: [u8; 5] = [0x70, 0x73, 0x62, 0x74, 0xff];
+
+/// Bit of `Global::tx_modifiable` allowing to add inputs.
+const TX_MODIFIABLE_INPUTS: u8 = 0b001;
+/// Bit of `Global::tx_modifiable` allowing to add outputs.
+const TX_MODIFIABLE_OUTPUTS: u8 = 0b010;
+/// Bit of `Global::tx_modifiable` set when an input is signed with `SIGHASH_SINGLE`.
+const TX_MODIFIABLE_SIGHASH_SINGLE: u8 = 0b100;
+
+/// Partially signed transaction
+#[derive(PartialEq, Clone, Debug, Default)]
+pub struct Psbt {
+    /// Global map
+    pub global: Global,
+    /// Input maps, one per transaction input.
+    pub inputs: Vec<Input>,
+    /// Output maps, one per transaction output.
+    pub outputs: Vec<Output>,
+}
+
+impl fmt::Display for Psbt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        base64::encode(serialize(self)).fmt(f)
+    }
+}
+
+impl str::FromStr for Psbt {
+    type Err = Error;
+
+    /// Parses a base64 encoded PSBT.
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let data = base64::decode(s).map_err(|_| Error::InvalidBase64)?;
+        Psbt::from_bytes(&data)
+    }
+}
+
+impl Serializable for Psbt {
+    fn serialize(&self, stream: &mut Stream) {
+        stream.append_slice(&PSBT_MAGIC);
+        let global = self.global.pairs(self.inputs.len(), self.outputs.len());
+        let inputs = self.inputs.iter().map(Input::pairs);
+        let outputs = self.outputs.iter().map(Output::pairs);
+        for pairs in Some(global).into_iter().chain(inputs).chain(outputs) {
+            for pair in &pairs {
+                stream.append(pair);
+            }
+            // Separator ending the map
+            stream.append(&0u8);
+        }
+    }
+}
+
+impl Deserializable for Psbt {
+    fn deserialize<T>(reader: &mut Reader<T>) -> Result<Self, io::Error>
+    where
+        Self: Sized,
+        T: io::Read,
+    {
+        Psbt::read(reader).map_err(|e| match e {
+            Error::Io(e) => e,
+            _ => io::Error::ReadMalformedData,
+        })
+    }
+}
+
+impl Psbt {
+    /// Parses a binary PSBT.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, Error> {
+        let mut reader = Reader::new(data);
+        let psbt = Psbt::read(&mut reader)?;
+        if !reader.is_finished() {
+            return Err(Error::Io(io::Error::UnreadData));
+        }
+        Ok(psbt)
+    }
+
+    fn read<T>(reader: &mut Reader<T>) -> Result<Self, Error>
+    where
+        T: io::Read,
+    {
+        let mut magic = [0u8; 5];
+        reader.read_slice(&mut magic)?;
+        if magic != PSBT_MAGIC {
+            return Err(Error::InvalidMagic);
+        }
+
+        let (global, input_count, output_count) = Global::read(reader)?;
+        let version = global.version;
+        let inputs = (0..input_count)
+            .map(|_| Input::read(reader, version))
+            .collect::<Result<Vec<_>, _>>()?;
+        let outputs = (0..output_count)
+            .map(|_| Output::read(reader, version))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Psbt {
+            global,
+            inputs,
+            outputs,
+        })
+    }
+
+    /// Creates a version 0 PSBT of the transaction (Creator).
+    ///
+    /// The inputs of the transaction must not have script sigs or witnesses.
+    pub fn from_unsigned_tx(tx: Transaction) -> Result<Self, Error> {
+        if tx
+            .inputs
+            .iter()
+            .any(|input| !input.script_sig.is_empty() || input.has_witness())
+        {
+            return Err(Error::InvalidUnsignedTx);
+        }
+        Ok(Psbt {
+            inputs: tx.inputs.iter().map(|_| Input::default()).collect(),
+            outputs: tx.outputs.iter().map(|_| Output::default()).collect(),
+            global: Global {
+                unsigned_tx: Some(tx),
+                ..Default::default()
+            },
+        })
+    }
+
+    /// Creates an empty version 2 PSBT, whose inputs and outputs can be added (Creator).
+    pub fn new_v2(tx_version: i32, fallback_locktime: Option<u32>) -> Self {
+        Psbt {
+            global: Global {
+                tx_version: Some(tx_version),
+                fallback_locktime,
+                tx_modifiable: Some(TX_MODIFIABLE_INPUTS | TX_MODIFIABLE_OUTPUTS),
+                version: 2,
+                ..Default::default()
+            },
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+        }
+    }
+
+    /// Adds an input to a version 2 PSBT (Constructor).
+    ///
+    /// The input must set its previous output, and its lock time requirement must be compatible
+    /// with the other inputs.
+    pub fn add_input(&mut self, input: Input) -> Result<(), Error> {
+        if self.global.version != 2 {
+            return Err(Error::InvalidVersion);
+        }
+        if self.global.tx_modifiable.unwrap_or(0) & TX_MODIFIABLE_INPUTS == 0 {
+            return Err(Error::NotModifiable);
+        }
+        if input.previous_txid.is_none() || input.output_index.is_none() {
+            return Err(Error::InvalidVersion);
+        }
+        self.inputs.push(input);
+        if let Err(e) = self.locktime() {
+            self.inputs.pop();
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Adds an output to a version 2 PSBT (Constructor).
+    ///
+    /// The output must set its amount and script.
+    pub fn add_output(&mut self, output: Output) -> Result<(), Error> {
+        if self.global.version != 2 {
+            return Err(Error::InvalidVersion);
+        }
+        if self.global.tx_modifiable.unwrap_or(0) & TX_MODIFIABLE_OUTPUTS == 0 {
+            return Err(Error::NotModifiable);
+        }
+        if output.amount.is_none() || output.script.is_none() {
+            return Err(Error::InvalidVersion);
+        }
+        self.outputs.push(output);
+        Ok(())
+    }
+
+    /// Returns the unsigned transaction of the PSBT.
+    ///
+    /// For version 2 PSBTs the transaction is built from the fields of the maps.
+    pub fn unsigned_tx(&self) -> Result<Transaction, Error> {
+        if self.global.version != 2 {
+            return self
+                .global
+                .unsigned_tx
+                .clone()
+                .ok_or(Error::InvalidUnsignedTx);
+        }
+
+        let inputs = self
+            .inputs
+            .iter()
+            .map(|input| match (input.previous_txid, input.output_index) {
+                (Some(txid), Some(index)) => Ok(TransactionInput {
+                    previous_output: OutPoint::new(txid, index),
+                    script_sig: Default::default(),
+                    sequence: input.sequence.unwrap_or(SEQUENCE_FINAL),
+                    script_witness: Vec::new(),
+                }),
+                _ => Err(Error::InvalidVersion),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let outputs = self
+            .outputs
+            .iter()
+            .map(|output| match (output.amount, &output.script) {
+                (Some(value), Some(script)) => Ok(TransactionOutput {
+                    value,
+                    script_pubkey: script.to_bytes(),
+                }),
+                _ => Err(Error::InvalidVersion),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Transaction {
+            version: self.global.tx_version.ok_or(Error::InvalidVersion)?,
+            inputs,
+            outputs,
+            lock_time: self.locktime()?,
+        })
+    }
+
+    /// Determines the lock time of a version 2 PSBT from the requirements of its inputs (BIP370).
+    fn locktime(&self) -> Result<u32, Error> {
+        let mut required = false;
+        let (mut time, mut height) = (None, None);
+        let (mut time_allowed, mut height_allowed) = (true, true);
+        for input in &self.inputs {
+            let (input_time, input_height) =
+                (input.required_time_locktime, input.required_height_locktime);
+            if input_time.is_none() && input_height.is_none() {
+                continue;
+            }
+            required = true;
+            time_allowed &= input_time.is_some();
+            height_allowed &= input_height.is_some();
+            time = time.max(input_time);
+            height = height.max(input_height);
+        }
+
+        if !required {
+            return Ok(self.global.fallback_locktime.unwrap_or(0));
+        }
+        // Height based lock times take precedence when both are possible
+        match (height_allowed, time_allowed) {
+            (true, _) => height.ok_or(Error::LocktimeConflict),
+            (false, true) => time.ok_or(Error::LocktimeConflict),
+            (false, false) => Err(Error::LocktimeConflict),
+        }
+    }
+
+    /// Returns the identifier of the transaction being signed, which doesn't depend on the
+    /// sequence numbers of version 2 PSBTs.
+    pub fn unique_id(&self) -> Result<H256, Error> {
+        let mut tx = self.unsigned_tx()?;
+        if self.global.version == 2 {
+            for input in tx.inputs.iter_mut() {
+                input.sequence = 0;
+            }
+        }
+        Ok(tx.hash())
+    }
+
+    /// Returns the output spent by the input, from its witness or non-witness UTXO.
+    pub fn spent_output(&self, index: usize) -> Result<TransactionOutput, Error> {
+        let input = self.inputs.get(index).ok_or(Error::InvalidIndex)?;
+        if let Some(output) = &input.witness_utxo {
+            return Ok(output.clone());
+        }
+        let tx = input.non_witness_utxo.as_ref().ok_or(Error::MissingUtxo)?;
+        let previous_output = self.unsigned_tx()?.inputs[index].previous_output;
+        if tx.hash() != previous_output.txid {
+            return Err(Error::UtxoMismatch);
+        }
+        tx.outputs
+            .get(previous_output.index as usize)
+            .cloned()
+            .ok_or(Error::UtxoMismatch)
+    }
+
+    /// Sets the transaction of the output spent by the input (Updater).
+    pub fn set_non_witness_utxo(&mut self, index: usize, tx: Transaction) -> Result<(), Error> {
+        let previous_output = self
+            .unsigned_tx()?
+            .inputs
+            .get(index)
+            .ok_or(Error::InvalidIndex)?
+            .previous_output;
+        if tx.hash() != previous_output.txid || tx.outputs.len() <= previous_output.index as usize {
+            return Err(Error::UtxoMismatch);
+        }
+        self.inputs[index].non_witness_utxo = Some(tx);
+        Ok(())
+    }
+
+    /// Sets the output spent by a segwit input (Updater).
+    pub fn set_witness_utxo(
+        &mut self,
+        index: usize,
+        output: TransactionOutput,
+    ) -> Result<(), Error> {
+        let input = self.inputs.get_mut(index).ok_or(Error::InvalidIndex)?;
+        input.witness_utxo = Some(output);
+        Ok(())
+    }
+
+    /// Merges another PSBT of the same transaction into this one (Combiner).
+    ///
+    /// Entries already present are kept.
+    pub fn combine(&mut self, other: Psbt) -> Result<(), Error> {
+        if self.global.version != other.global.version
+            || self.inputs.len() != other.inputs.len()
+            || self.outputs.len() != other.outputs.len()
+            || self.unique_id()? != other.unique_id()?
+        {
+            return Err(Error::MismatchedTransaction);
+        }
+
+        self.global.combine(other.global);
+        for (input, other) in self.inputs.iter_mut().zip(other.inputs) {
+            input.combine(other);
+        }
+        for (output, other) in self.outputs.iter_mut().zip(other.outputs) {
+            output.combine(other);
+        }
+        Ok(())
+    }
+
+    /// Returns the signed transaction of a finalized PSBT (Extractor).
+    pub fn extract_tx(&self) -> Result<Transaction, Error> {
+        let mut tx = self.unsigned_tx()?;
+        for (tx_input, input) in tx.inputs.iter_mut().zip(&self.inputs) {
+            if !input.is_finalized() {
+                return Err(Error::NotFinalized);
+            }
+            if let Some(script_sig) = &input.final_script_sig {
+                tx_input.script_sig = script_sig.to_bytes();
+            }
+            if let Some(witness) = &input.final_script_witness {
+                tx_input.script_witness = witness.clone();
+            }
+        }
+        Ok(tx)
+    }
+}
+
+/// Script spent by an input, after unwrapping P2SH and segwit v0 programs.
+struct SpentScript {
+    /// Redeem script of P2SH inputs.
+    redeem_script: Option<Script>,
+    /// Kind of the unwrapped script.
+    kind: SpentScriptKind,
+    /// Script committed to by the signatures.
+    script_code: Script,
+}
+
+#[derive(PartialEq)]
+enum SpentScriptKind {
+    /// Script pubkey or redeem script
+    Legacy,
+    /// P2WPKH program, whose script code is the P2PKH script of the key hash
+    WitnessKeyHash,
+    /// P2WSH witness script
+    WitnessScript,
+}
+
+impl Psbt {
+    /// Unwraps the script spent by an ECDSA input, checking its redeem and witness scripts.
+    fn spent_script(&self, index: usize, script_pubkey: &Script) -> Result<SpentScript, Error> {
+        let input = &self.inputs[index];
+        let redeem_script = if script_pubkey.is_pay_to_script_hash() {
+            let redeem_script = input.redeem_script.clone().ok_or(Error::InvalidScript)?;
+            if dhash160(&redeem_script).as_bytes() != &script_pubkey[2..22] {
+                return Err(Error::InvalidScript);
+            }
+            Some(redeem_script)
+        } else {
+            None
+        };
+        let script = redeem_script.as_ref().unwrap_or(script_pubkey);
+
+        let (kind, script_code) = if script.is_pay_to_witness_key_hash() {
+            let hash = &script[2..22];
+            let script_code = Builder::build_p2pkh(&H160::from_slice(hash));
+            (SpentScriptKind::WitnessKeyHash, script_code)
+        } else if script.is_pay_to_witness_script_hash() {
+            let witness_script = input.witness_script.clone().ok_or(Error::InvalidScript)?;
+            if sha256(&witness_script).as_bytes() != &script[2..34] {
+                return Err(Error::InvalidScript);
+            }
+            (SpentScriptKind::WitnessScript, witness_script)
+        } else {
+            (SpentScriptKind::Legacy, script.clone())
+        };
+
+        Ok(SpentScript {
+            redeem_script,
+            kind,
+            script_code,
+        })
+    }
+}
+
+/// Returns the output key of a taproot script pubkey.
+fn taproot_output_key(script_pubkey: &Script) -> Option<H256> {
+    match script_pubkey.parse_witness_program() {
+        Some((1, program)) if program.len() == 32 && !script_pubkey.is_pay_to_script_hash() => {
+            Some(H256::from_slice(program))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use light_bitcoin_keys::{DerivationPath, ExtendedPrivKey, KeyPair, Network, Private};
+    use light_bitcoin_primitives::{h256, h256_rev};
+    use light_bitcoin_serialization::deserialize;
+
+    use super::*;
+    use crate::builder::Builder;
+    use crate::flags::VerificationFlags;
+    use crate::interpreter::verify_script;
+    use crate::opcode::Opcode;
+    use crate::script::ScriptType;
+    use crate::sign::TransactionInputSigner;
+    use crate::verify::TransactionSignatureChecker;
+
+    fn psbt_roundtrip(data: &str) -> Psbt {
+        let psbt = Psbt::from_bytes(&hex::decode(data).unwrap()).unwrap();
+        assert_eq!(Psbt::from_bytes(&serialize(&psbt)), Ok(psbt.clone()));
+        psbt
+    }
+
+    fn keypair(secret: &str) -> KeyPair {
+        KeyPair::from_private(Private {
+            network: Network::Mainnet,
+            secret: h256(secret),
+            compressed: true,
+        })
+        .unwrap()
+    }
+
+    fn verify_input(tx: &Transaction, index: usize, spent_outputs: &[TransactionOutput]) {
+        let signer =
+            TransactionInputSigner::from(tx.clone()).with_spent_outputs(spent_outputs.to_vec());
+        let checker = TransactionSignatureChecker {
+            signer,
+            input_index: index,
+            input_amount: spent_outputs[index].value,
+        };
+        let flags = VerificationFlags::default()
+            .verify_p2sh(true)
+            .verify_witness(true)
+            .verify_taproot(true)
+            .verify_nulldummy(true)
+            .verify_cleanstack(true);
+        let input = &tx.inputs[index];
+        assert_eq!(
+            verify_script(
+                &input.script_sig.clone().into(),
+                &spent_outputs[index].script_pubkey.clone().into(),
+                &input.script_witness,
+                &flags,
+                &checker,
+            ),
+            Ok(())
+        );
+    }
+
+    // https://github.com/bitcoin/bips/blob/master/bip-0174.mediawiki#test-vectors
+    #[test]
+    fn test_valid_vectors() {
+        // P2PKH input with a non-witness UTXO
+        let data = "70736274ff0100750200000001268171371edff285e937adeea4b37b78000c0566cbb3ad64641713ca42171bf60000000000feffffff02d3dff505000000001976a914d0c59903c5bac2868760e90fd521a4665aa7652088ac00e1f5050000000017a9143545e6e33b832c47050f24d3eeb93c9c03948bc787b32e1300000100fda5010100000000010289a3c71eab4d20e0371bbba4cc698fa295c9463afa2e397f8533ccb62f9567e50100000017160014be18d152a9b012039daf3da7de4f53349eecb985ffffffff86f8aa43a71dff1448893a530a7237ef6b4608bbb2dd2d0171e63aec6a4890b40100000017160014fe3e9ef1a745e974d902c4355943abcb34bd5353ffffffff0200c2eb0b000000001976a91485cff1097fd9e008bb34af709c62197b38978a4888ac72fef84e2c00000017a914339725ba21efd62ac753a9bcd067d6c7a6a39d05870247304402202712be22e0270f394f568311dc7ca9a68970b8025fdd3b240229f07f8a5f3a240220018b38d7dcd314e734c9276bd6fb40f673325bc4baa144c800d2f2f02db2765c012103d2e15674941bad4a996372cb87e1856d3652606d98562fe39c5e9e7e413f210502483045022100d12b852d85dcd961d2f5f4ab660654df6eedcc794c0c33ce5cc309ffb5fce58d022067338a8e0e1725c197fb1a88af59f51e44e4255b20167c8684031c05d1f2592a01210223b72beef0965d10be0778efecd61fcac6f79a4ea169393380734464f84f2ab300000000000000";
+        let psbt = psbt_roundtrip(data);
+        assert_eq!(serialize(&psbt), hex::decode(data).unwrap().into());
+        assert_eq!(
+            psbt.to_string(),
+            "cHNidP8BAHUCAAAAASaBcTce3/KF6Tet7qSze3gADAVmy7OtZGQXE8pCFxv2AAAAAAD+////AtPf9QUAAAAAGXapFNDFmQPFusKGh2DpD9UhpGZap2UgiKwA4fUFAAAAABepFDVF5uM7gyxHBQ8k0+65PJwDlIvHh7MuEwAAAQD9pQEBAAAAAAECiaPHHqtNIOA3G7ukzGmPopXJRjr6Ljl/hTPMti+VZ+UBAAAAFxYAFL4Y0VKpsBIDna89p95PUzSe7LmF/////4b4qkOnHf8USIk6UwpyN+9rRgi7st0tAXHmOuxqSJC0AQAAABcWABT+Pp7xp0XpdNkCxDVZQ6vLNL1TU/////8CAMLrCwAAAAAZdqkUhc/xCX/Z4Ai7NK9wnGIZeziXikiIrHL++E4sAAAAF6kUM5cluiHv1irHU6m80GfWx6ajnQWHAkcwRAIgJxK+IuAnDzlPVoMR3HyppolwuAJf3TskAinwf4pfOiQCIAGLONfc0xTnNMkna9b7QPZzMlvEuqFEyADS8vAtsnZcASED0uFWdJQbrUqZY3LLh+GFbTZSYG2YVi/jnF6efkE/IQUCSDBFAiEA0SuFLYXc2WHS9fSrZgZU327tzHlMDDPOXMMJ/7X85Y0CIGczio4OFyXBl/saiK9Z9R5E5CVbIBZ8hoQDHAXR8lkqASECI7cr7vCWXRC+B3jv7NYfysb3mk6haTkzgHNEZPhPKrMAAAAAAAAA"
+        );
+        assert_eq!(psbt.to_string().parse::<Psbt>(), Ok(psbt.clone()));
+
+        // Finalized P2PKH input and P2SH-P2WPKH input with a redeem script
+        let psbt = psbt_roundtrip("70736274ff0100a00200000002ab0949a08c5af7c49b8212f417e2f15ab3f5c33dcf153821a8139f877a5b7be40000000000feffffffab0949a08c5af7c49b8212f417e2f15ab3f5c33dcf153821a8139f877a5b7be40100000000feffffff02603bea0b000000001976a914768a40bbd740cbe81d988e71de2a4d5c71396b1d88ac8e240000000000001976a9146f4620b553fa095e721b9ee0efe9fa039cca459788ac000000000001076a47304402204759661797c01b036b25928948686218347d89864b719e1f7fcf57d1e511658702205309eabf56aa4d8891ffd111fdf1336f3a29da866d7f8486d75546ceedaf93190121035cdc61fc7ba971c0b501a646a2a83b102cb43881217ca682dc86e2d73fa882920001012000e1f5050000000017a9143545e6e33b832c47050f24d3eeb93c9c03948bc787010416001485d13537f2e265405a34dbafa9e3dda01fb82308000000");
+        assert!(psbt.inputs[0].is_finalized());
+        assert!(!psbt.inputs[1].is_finalized());
+        assert!(psbt.inputs[1]
+            .redeem_script
+            .as_ref()
+            .unwrap()
+            .is_pay_to_witness_key_hash());
+
+        // Sighash type of the input
+        let psbt = psbt_roundtrip("70736274ff0100750200000001268171371edff285e937adeea4b37b78000c0566cbb3ad64641713ca42171bf60000000000feffffff02d3dff505000000001976a914d0c59903c5bac2868760e90fd521a4665aa7652088ac00e1f5050000000017a9143545e6e33b832c47050f24d3eeb93c9c03948bc787b32e1300000100fda5010100000000010289a3c71eab4d20e0371bbba4cc698fa295c9463afa2e397f8533ccb62f9567e50100000017160014be18d152a9b012039daf3da7de4f53349eecb985ffffffff86f8aa43a71dff1448893a530a7237ef6b4608bbb2dd2d0171e63aec6a4890b40100000017160014fe3e9ef1a745e974d902c4355943abcb34bd5353ffffffff0200c2eb0b000000001976a91485cff1097fd9e008bb34af709c62197b38978a4888ac72fef84e2c00000017a914339725ba21efd62ac753a9bcd067d6c7a6a39d05870247304402202712be22e0270f394f568311dc7ca9a68970b8025fdd3b240229f07f8a5f3a240220018b38d7dcd314e734c9276bd6fb40f673325bc4baa144c800d2f2f02db2765c012103d2e15674941bad4a996372cb87e1856d3652606d98562fe39c5e9e7e413f210502483045022100d12b852d85dcd961d2f5f4ab660654df6eedcc794c0c33ce5cc309ffb5fce58d022067338a8e0e1725c197fb1a88af59f51e44e4255b20167c8684031c05d1f2592a01210223b72beef0965d10be0778efecd61fcac6f79a4ea169393380734464f84f2ab30000000001030401000000000000");
+        assert_eq!(psbt.inputs[0].sighash_type, Some(1));
+        let tx = psbt.unsigned_tx().unwrap();
+        assert_eq!(
+            psbt.spent_output(0).unwrap(),
+            psbt.inputs[0].non_witness_utxo.as_ref().unwrap().outputs
+                [tx.inputs[0].previous_output.index as usize]
+        );
+
+        // BIP32 derivations of the outputs
+        let psbt = psbt_roundtrip("70736274ff0100a00200000002ab0949a08c5af7c49b8212f417e2f15ab3f5c33dcf153821a8139f877a5b7be40000000000feffffffab0949a08c5af7c49b8212f417e2f15ab3f5c33dcf153821a8139f877a5b7be40100000000feffffff02603bea0b000000001976a914768a40bbd740cbe81d988e71de2a4d5c71396b1d88ac8e240000000000001976a9146f4620b553fa095e721b9ee0efe9fa039cca459788ac00000000000100df0200000001268171371edff285e937adeea4b37b78000c0566cbb3ad64641713ca42171bf6000000006a473044022070b2245123e6bf474d60c5b50c043d4c691a5d2435f09a34a7662a9dc251790a022001329ca9dacf280bdf30740ec0390422422c81cb45839457aeb76fc12edd95b3012102657d118d3357b8e0f4c2cd46db7b39f6d9c38d9a70abcb9b2de5dc8dbfe4ce31feffffff02d3dff505000000001976a914d0c59903c5bac2868760e90fd521a4665aa7652088ac00e1f5050000000017a9143545e6e33b832c47050f24d3eeb93c9c03948bc787b32e13000001012000e1f5050000000017a9143545e6e33b832c47050f24d3eeb93c9c03948bc787010416001485d13537f2e265405a34dbafa9e3dda01fb8230800220202ead596687ca806043edc3de116cdf29d5e9257c196cd055cf698c8d02bf24e9910b4a6ba670000008000000080020000800022020394f62be9df19952c5587768aeb7698061ad2c4a25c894f47d8c162b4d7213d0510b4a6ba6700000080010000800200008000");
+        let (fingerprint, path) = psbt.outputs[0].bip32_derivation.values().next().unwrap();
+        assert_eq!(fingerprint.as_bytes(), &[0xb4, 0xa6, 0xba, 0x67]);
+        assert_eq!(path.len(), 3);
+
+        // P2SH-P2WSH input with a witness script
+        let psbt = psbt_roundtrip("70736274ff0100550200000001279a2323a5dfb51fc45f220fa58b0fc13e1e3342792a85d7e36cd6333b5cbc390000000000ffffffff01a05aea0b000000001976a914ffe9c0061097cc3b636f2cb0460fa4fc427d2b4588ac0000000000010120955eea0b0000000017a9146345200f68d189e1adc0df1c4d16ea8f14c0dbeb87220203b1341ccba7683b6af4f1238cd6e97e7167d569fac47f1e48d47541844355bd4646304302200424b58effaaa694e1559ea5c93bbfd4a89064224055cdf070b6771469442d07021f5c8eb0fea6516d60b8acb33ad64ede60e8785bfb3aa94b99bdf86151db9a9a010104220020771fd18ad459666dd49f3d564e3dbc42f4c84774e360ada16816a8ed488d5681010547522103b1341ccba7683b6af4f1238cd6e97e7167d569fac47f1e48d47541844355bd462103de55d1e1dac805e3f8a58c1fbf9b94c02f3dbaafe127fefca4995f26f82083bd52ae220603b1341ccba7683b6af4f1238cd6e97e7167d569fac47f1e48d47541844355bd4610b4a6ba67000000800000008004000080220603de55d1e1dac805e3f8a58c1fbf9b94c02f3dbaafe127fefca4995f26f82083bd10b4a6ba670000008000000080050000800000");
+        assert!(psbt.inputs[0]
+            .redeem_script
+            .as_ref()
+            .unwrap()
+            .is_pay_to_witness_script_hash());
+        assert_eq!(
+            psbt.inputs[0]
+                .witness_script
+                .as_ref()
+                .unwrap()
+                .script_type(),
+            ScriptType::Multisig
+        );
+
+        // Unknown keys in the input map
+        let psbt = psbt_roundtrip("70736274ff01003f0200000001ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff0000000000ffffffff010000000000000000036a010000000000000a0f0102030405060708090f0102030405060708090a0b0c0d0e0f0000");
+        assert_eq!(
+            psbt.unique_id(),
+            Ok(h256_rev(
+                "75c5c9665a570569ad77dd1279e6fd4628a093c4dcbf8d41532614044c14c115"
+            ))
+        );
+        let key = Key::new(0x0f, hex::decode("010203040506070809").unwrap().into());
+        assert_eq!(
+            psbt.inputs[0].unknown.get(&key),
+            Some(
+                &hex::decode("0102030405060708090a0b0c0d0e0f")
+                    .unwrap()
+                    .into()
+            )
+        );
+    }
+
+    // https://github.com/bitcoin/bips/blob/master/bip-0371.mediawiki#test-vectors
+    #[test]
+    fn test_taproot_vectors() {
+        // Script path spend with signatures
+        let psbt = psbt_roundtrip("70736274ff01005e02000000019bd48765230bf9a72e662001f972556e54f0c6f97feb56bcb5600d817f6995260100000000ffffffff0148e6052a0100000022512083698e458c6664e1595d75da2597de1e22ee97d798e706c4c0a4b5a9823cd743000000000001012b00f2052a01000000225120c2247efbfd92ac47f6f40b8d42d169175a19fa9fa10e4a25d7f35eb4dd85b69241142cb13ac68248de806aa6a3659cf3c03eb6821d09c8114a4e868febde865bb6d2cd970e15f53fc0c82f950fd560ffa919b76172be017368a89913af074f400b0940bf818d9757d6ffeb538ba057fb4c1fc4e0f5ef186e765beb564791e02af5fd3d5e2551d4e34e33d86f276b82c99c79aed3f0395a081efcd2cc2c65dd7e693d7941144320b0bf16f011b53ea7be615924aa7f27e5d29ad20ea1155d848676c3bad1b2115f2e490af7cc45c4f78511f36057ce5c5a5c56325a29fb44dfc203f356e1f840e1f1ab6fabfa26b236f21833719dc1d428ab768d80f91f9988d8abef47bfb863bb1f2a529f768c15f00ce34ec283cdc07e88f8428be28f6ef64043c32911811a4114fa0f7a3cef3b1d0c0a6ce7d26e17ada0b2e5c92d19efad48b41859cb8a451ca96f7d62059e9497a1a4a267569d9876da60101aff38e3529b9b939ce7f91ae97040ec1f0379206461c83342285423326708ab031f0da4a253ee45aafa5b8c92034d8b605490f8cd13e00f989989b97e215faa36f12dee3693d2daccf3781c1757f66215c150929b74c1a04954b78b4b6035e97a5e078a5a0f28ec96d547bfee9ace803ac06f7d62059e9497a1a4a267569d9876da60101aff38e3529b9b939ce7f91ae970115f2e490af7cc45c4f78511f36057ce5c5a5c56325a29fb44dfc203f356e1f823202cb13ac68248de806aa6a3659cf3c03eb6821d09c8114a4e868febde865bb6d2acc04215c150929b74c1a04954b78b4b6035e97a5e078a5a0f28ec96d547bfee9ace803ac097c6e6fea5ff714ff5724499990810e406e98aa10f5bf7e5f6784bc1d0a9a6ce23204320b0bf16f011b53ea7be615924aa7f27e5d29ad20ea1155d848676c3bad1b2acc06215c150929b74c1a04954b78b4b6035e97a5e078a5a0f28ec96d547bfee9ace803ac0cd970e15f53fc0c82f950fd560ffa919b76172be017368a89913af074f400b09115f2e490af7cc45c4f78511f36057ce5c5a5c56325a29fb44dfc203f356e1f82320fa0f7a3cef3b1d0c0a6ce7d26e17ada0b2e5c92d19efad48b41859cb8a451ca9acc021162cb13ac68248de806aa6a3659cf3c03eb6821d09c8114a4e868febde865bb6d23901cd970e15f53fc0c82f950fd560ffa919b76172be017368a89913af074f400b09772b2da7560000800100008002000080000000000000000021164320b0bf16f011b53ea7be615924aa7f27e5d29ad20ea1155d848676c3bad1b23901115f2e490af7cc45c4f78511f36057ce5c5a5c56325a29fb44dfc203f356e1f8772b2da75600008001000080010000800000000000000000211650929b74c1a04954b78b4b6035e97a5e078a5a0f28ec96d547bfee9ace803ac005007c461e5d2116fa0f7a3cef3b1d0c0a6ce7d26e17ada0b2e5c92d19efad48b41859cb8a451ca939016f7d62059e9497a1a4a267569d9876da60101aff38e3529b9b939ce7f91ae970772b2da7560000800100008003000080000000000000000001172050929b74c1a04954b78b4b6035e97a5e078a5a0f28ec96d547bfee9ace803ac0011820f0362e2f75a6f420a5bde3eb221d96ae6720cf25f81890c95b1d775acb515e65000105201124da7aec92ccd06c954562647f437b138b95721a84be2bf2276bbddab3e67121071124da7aec92ccd06c954562647f437b138b95721a84be2bf2276bbddab3e6711900772b2da7560000800100008000000080000000000500000000");
+        let input = &psbt.inputs[0];
+        assert!(input.tap_internal_key.is_some());
+        assert!(input.tap_merkle_root.is_some());
+        assert!(!input.tap_scripts.is_empty());
+        assert!(!input.tap_script_sigs.is_empty());
+        assert!(!input.tap_key_origins.is_empty());
+
+        // Output taproot tree
+        let psbt = psbt_roundtrip("70736274ff01005e020000000127744ababf3027fe0d6cf23a96eee2efb188ef52301954585883e69b6624b2420000000000ffffffff0148e6052a010000002251200a8cbdc86de1ce1c0f9caeb22d6df7ced3683fe423e05d1e402a879341d6f6f5000000000001012b00f2052a010000002251205a2c2cf5b52cf31f83ad2e8da63ff03183ecd8f609c7510ae8a48e03910a07572116fe349064c98d6e2a853fa3c9b12bd8b304a19c195c60efa7ee2393046d3fa2321900772b2da75600008001000080000000800100000000000000011720fe349064c98d6e2a853fa3c9b12bd8b304a19c195c60efa7ee2393046d3fa2320001052050929b74c1a04954b78b4b6035e97a5e078a5a0f28ec96d547bfee9ace803ac001066f02c02220736e572900fe1252589a2143c8f3c79f71a0412d2353af755e9701c782694a02ac02c02220631c5f3b5832b8fbdebfb19704ceeb323c21f40f7a24f43d68ef0cc26b125969ac01c0222044faa49a0338de488c8dfffecdfb6f329f380bd566ef20c8df6d813eab1c4273ac210744faa49a0338de488c8dfffecdfb6f329f380bd566ef20c8df6d813eab1c42733901f06b798b92a10ed9a9d0bbfd3af173a53b1617da3a4159ca008216cd856b2e0e772b2da75600008001000080010000800000000003000000210750929b74c1a04954b78b4b6035e97a5e078a5a0f28ec96d547bfee9ace803ac005007c461e5d2107631c5f3b5832b8fbdebfb19704ceeb323c21f40f7a24f43d68ef0cc26b125969390118ace409889785e0ea70ceebb8e1ca892a7a78eaede0f2e296cf435961a8f4ca772b2da756000080010000800200008000000000030000002107736e572900fe1252589a2143c8f3c79f71a0412d2353af755e9701c782694a02390129a5b4915090162d759afd3fe0f93fa3326056d0b4088cb933cae7826cb8d82c772b2da7560000800100008003000080000000000300000000");
+        let output = &psbt.outputs[0];
+        assert!(output.tap_internal_key.is_some());
+        assert!(!output.tap_key_origins.is_empty());
+        assert!(!output.tap_tree.is_empty());
+    }
+
+    #[test]
+    fn test_invalid_vectors() {
+        let invalid = [
+            // Network transaction instead of a PSBT
+            ("0200000001268171371edff285e937adeea4b37b78000c0566cbb3ad64641713ca42171bf6000000006a473044022070b2245123e6bf474d60c5b50c043d4c691a5d2435f09a34a7662a9dc251790a022001329ca9dacf280bdf30740ec0390422422c81cb45839457aeb76fc12edd95b3012102657d118d3357b8e0f4c2cd46db7b39f6d9c38d9a70abcb9b2de5dc8dbfe4ce31feffffff02d3dff505000000001976a914d0c59903c5bac2868760e90fd521a4665aa7652088ac00e1f5050000000017a9143545e6e33b832c47050f24d3eeb93c9c03948bc787b32e1300", Error::InvalidMagic),
+            // Unsigned transaction with a script sig
+            ("70736274ff0100fd0a010200000002ab0949a08c5af7c49b8212f417e2f15ab3f5c33dcf153821a8139f877a5b7be4000000006a47304402204759661797c01b036b25928948686218347d89864b719e1f7fcf57d1e511658702205309eabf56aa4d8891ffd111fdf1336f3a29da866d7f8486d75546ceedaf93190121035cdc61fc7ba971c0b501a646a2a83b102cb43881217ca682dc86e2d73fa88292feffffffab0949a08c5af7c49b8212f417e2f15ab3f5c33dcf153821a8139f877a5b7be40100000000feffffff02603bea0b000000001976a914768a40bbd740cbe81d988e71de2a4d5c71396b1d88ac8e240000000000001976a9146f4620b553fa095e721b9ee0efe9fa039cca459788ac00000000000001012000e1f5050000000017a9143545e6e33b832c47050f24d3eeb93c9c03948bc787010416001485d13537f2e265405a34dbafa9e3dda01fb82308000000", Error::InvalidUnsignedTx),
+            // Missing unsigned transaction
+            ("70736274ff000100fda5010100000000010289a3c71eab4d20e0371bbba4cc698fa295c9463afa2e397f8533ccb62f9567e50100000017160014be18d152a9b012039daf3da7de4f53349eecb985ffffffff86f8aa43a71dff1448893a530a7237ef6b4608bbb2dd2d0171e63aec6a4890b40100000017160014fe3e9ef1a745e974d902c4355943abcb34bd5353ffffffff0200c2eb0b000000001976a91485cff1097fd9e008bb34af709c62197b38978a4888ac72fef84e2c00000017a914339725ba21efd62ac753a9bcd067d6c7a6a39d05870247304402202712be22e0270f394f568311dc7ca9a68970b8025fdd3b240229f07f8a5f3a240220018b38d7dcd314e734c9276bd6fb40f673325bc4baa144c800d2f2f02db2765c012103d2e15674941bad4a996372cb87e1856d3652606d98562fe39c5e9e7e413f210502483045022100d12b852d85dcd961d2f5f4ab660654df6eedcc794c0c33ce5cc309ffb5fce58d022067338a8e0e1725c197fb1a88af59f51e44e4255b20167c8684031c05d1f2592a01210223b72beef0965d10be0778efecd61fcac6f79a4ea169393380734464f84f2ab30000000000", Error::InvalidUnsignedTx),
+        ];
+        for (data, error) in invalid {
+            assert_eq!(Psbt::from_bytes(&hex::decode(data).unwrap()), Err(error));
+        }
+
+        // Malformed taproot keys, signatures and control blocks
+        let invalid = [
+            "70736274ff010071020000000127744ababf3027fe0d6cf23a96eee2efb188ef52301954585883e69b6624b2420000000000ffffffff02787c01000000000016001483a7e34bd99ff03a4962ef8a1a101bb295461ece606b042a010000001600147ac369df1b20e033d6116623957b0ac49f3c52e8000000000001012b00f2052a010000002251205a2c2cf5b52cf31f83ad2e8da63ff03183ecd8f609c7510ae8a48e03910a075701172102fe349064c98d6e2a853fa3c9b12bd8b304a19c195c60efa7ee2393046d3fa232000000",
+            "70736274ff010071020000000127744ababf3027fe0d6cf23a96eee2efb188ef52301954585883e69b6624b2420000000000ffffffff02787c01000000000016001483a7e34bd99ff03a4962ef8a1a101bb295461ece606b042a010000001600147ac369df1b20e033d6116623957b0ac49f3c52e8000000000001012b00f2052a010000002251205a2c2cf5b52cf31f83ad2e8da63ff03183ecd8f609c7510ae8a48e03910a0757011342173bb3d36c074afb716fec6307a069a2e450b995f3c82785945ab8df0e24260dcd703b0cbf34de399184a9481ac2b3586db6601f026a77f7e4938481bc34751701aa000000",
+            "70736274ff010071020000000127744ababf3027fe0d6cf23a96eee2efb188ef52301954585883e69b6624b2420000000000ffffffff02787c01000000000016001483a7e34bd99ff03a4962ef8a1a101bb295461ece606b042a010000001600147ac369df1b20e033d6116623957b0ac49f3c52e8000000000001012b00f2052a010000002251205a2c2cf5b52cf31f83ad2e8da63ff03183ecd8f609c7510ae8a48e03910a0757221602fe349064c98d6e2a853fa3c9b12bd8b304a19c195c60efa7ee2393046d3fa2321900772b2da75600008001000080000000800100000000000000000000",
+            "70736274ff01007d020000000127744ababf3027fe0d6cf23a96eee2efb188ef52301954585883e69b6624b2420000000000ffffffff02887b0100000000001600142382871c7e8421a00093f754d91281e675874b9f606b042a010000002251205a2c2cf5b52cf31f83ad2e8da63ff03183ecd8f609c7510ae8a48e03910a0757000000000001012b00f2052a010000002251205a2c2cf5b52cf31f83ad2e8da63ff03183ecd8f609c7510ae8a48e03910a0757000001052102fe349064c98d6e2a853fa3c9b12bd8b304a19c195c60efa7ee2393046d3fa23200",
+            "70736274ff01007d020000000127744ababf3027fe0d6cf23a96eee2efb188ef52301954585883e69b6624b2420000000000ffffffff02887b0100000000001600142382871c7e8421a00093f754d91281e675874b9f606b042a010000002251205a2c2cf5b52cf31f83ad2e8da63ff03183ecd8f609c7510ae8a48e03910a0757000000000001012b00f2052a010000002251205a2c2cf5b52cf31f83ad2e8da63ff03183ecd8f609c7510ae8a48e03910a07570000220702fe349064c98d6e2a853fa3c9b12bd8b304a19c195c60efa7ee2393046d3fa2321900772b2da7560000800100008000000080010000000000000000",
+            "70736274ff01005e02000000019bd48765230bf9a72e662001f972556e54f0c6f97feb56bcb5600d817f6995260100000000ffffffff0148e6052a01000000225120030da4fce4f7db28c2cb2951631e003713856597fe963882cb500e68112cca63000000000001012b00f2052a01000000225120c2247efbfd92ac47f6f40b8d42d169175a19fa9fa10e4a25d7f35eb4dd85b6924214022cb13ac68248de806aa6a3659cf3c03eb6821d09c8114a4e868febde865bb6d2cd970e15f53fc0c82f950fd560ffa919b76172be017368a89913af074f400b094089756aa3739ccc689ec0fcf3a360be32cc0b59b16e93a1e8bb4605726b2ca7a3ff706c4176649632b2cc68e1f912b8a578e3719ce7710885c7a966f49bcd43cb0000",
+            "70736274ff01005e02000000019bd48765230bf9a72e662001f972556e54f0c6f97feb56bcb5600d817f6995260100000000ffffffff0148e6052a01000000225120030da4fce4f7db28c2cb2951631e003713856597fe963882cb500e68112cca63000000000001012b00f2052a01000000225120c2247efbfd92ac47f6f40b8d42d169175a19fa9fa10e4a25d7f35eb4dd85b69241142cb13ac68248de806aa6a3659cf3c03eb6821d09c8114a4e868febde865bb6d2cd970e15f53fc0c82f950fd560ffa919b76172be017368a89913af074f400b094289756aa3739ccc689ec0fcf3a360be32cc0b59b16e93a1e8bb4605726b2ca7a3ff706c4176649632b2cc68e1f912b8a578e3719ce7710885c7a966f49bcd43cb01010000",
+            "70736274ff01005e02000000019bd48765230bf9a72e662001f972556e54f0c6f97feb56bcb5600d817f6995260100000000ffffffff0148e6052a01000000225120030da4fce4f7db28c2cb2951631e003713856597fe963882cb500e68112cca63000000000001012b00f2052a01000000225120c2247efbfd92ac47f6f40b8d42d169175a19fa9fa10e4a25d7f35eb4dd85b69241142cb13ac68248de806aa6a3659cf3c03eb6821d09c8114a4e868febde865bb6d2cd970e15f53fc0c82f950fd560ffa919b76172be017368a89913af074f400b093989756aa3739ccc689ec0fcf3a360be32cc0b59b16e93a1e8bb4605726b2ca7a3ff706c4176649632b2cc68e1f912b8a578e3719ce7710885c7a966f49bcd43cb0000",
+            "70736274ff01005e02000000019bd48765230bf9a72e662001f972556e54f0c6f97feb56bcb5600d817f6995260100000000ffffffff0148e6052a01000000225120030da4fce4f7db28c2cb2951631e003713856597fe963882cb500e68112cca63000000000001012b00f2052a01000000225120c2247efbfd92ac47f6f40b8d42d169175a19fa9fa10e4a25d7f35eb4dd85b6926315c150929b74c1a04954b78b4b6035e97a5e078a5a0f28ec96d547bfee9ace803ac06f7d62059e9497a1a4a267569d9876da60101aff38e3529b9b939ce7f91ae970115f2e490af7cc45c4f78511f36057ce5c5a5c56325a29fb44dfc203f356e1f80023202cb13ac68248de806aa6a3659cf3c03eb6821d09c8114a4e868febde865bb6d2acc00000",
+            "70736274ff01005e02000000019bd48765230bf9a72e662001f972556e54f0c6f97feb56bcb5600d817f6995260100000000ffffffff0148e6052a01000000225120030da4fce4f7db28c2cb2951631e003713856597fe963882cb500e68112cca63000000000001012b00f2052a01000000225120c2247efbfd92ac47f6f40b8d42d169175a19fa9fa10e4a25d7f35eb4dd85b6926115c150929b74c1a04954b78b4b6035e97a5e078a5a0f28ec96d547bfee9ace803ac06f7d62059e9497a1a4a267569d9876da60101aff38e3529b9b939ce7f91ae970115f2e490af7cc45c4f78511f36057ce5c5a5c56325a29fb44dfc203f356e123202cb13ac68248de806aa6a3659cf3c03eb6821d09c8114a4e868febde865bb6d2acc00000",
+        ];
+        for data in invalid.iter() {
+            assert!(Psbt::from_bytes(&hex::decode(data).unwrap()).is_err());
+        }
+
+        // Counts larger than the values hold, in the maps of an input and of an output
+        let global = "70736274ff01003f0200000001ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff0000000000ffffffff010000000000000000036a01000000000000";
+        let x_only = "2116fe349064c98d6e2a853fa3c9b12bd8b304a19c195c60efa7ee2393046d3fa232";
+        let out_x_only = "2107fe349064c98d6e2a853fa3c9b12bd8b304a19c195c60efa7ee2393046d3fa232";
+        let invalid = [
+            // Witness items
+            format!("{}010809ffffffffffffffffff0000", global),
+            format!("{}010805feffffffff0000", global),
+            format!("{}01080204000000", global),
+            // Leaf hashes
+            format!("{}{}09ffffffffffffffffff0000", global, x_only),
+            format!("{}{}05feffffff7f0000", global, x_only),
+            format!("{}00{}09ffffffffffffffffff00", global, out_x_only),
+        ];
+        for data in invalid.iter() {
+            assert_eq!(
+                Psbt::from_bytes(&hex::decode(data).unwrap()),
+                Err(Error::InvalidValue),
+                "{}",
+                data
+            );
+        }
+
+        assert_eq!("cHNidP8=!".parse::<Psbt>(), Err(Error::InvalidBase64));
+        assert_eq!(
+            deserialize::<_, Psbt>(&b"psbt\xff"[..]),
+            Err(io::Error::UnexpectedEof)
+        );
+    }
+
+    fn spending_tx(outputs: &[TransactionOutput]) -> Transaction {
+        Transaction {
+            version: 2,
+            inputs: outputs
+                .iter()
+                .enumerate()
+                .map(|(index, _)| TransactionInput {
+                    previous_output: OutPoint::new(
+                        h256("9bd48765230bf9a72e662001f972556e54f0c6f97feb56bcb5600d817f699526"),
+                        index as u32,
+                    ),
+                    script_sig: Default::default(),
+                    sequence: SEQUENCE_FINAL,
+                    script_witness: vec![],
+                })
+                .collect(),
+            outputs: vec![TransactionOutput {
+                value: 90_000,
+                script_pubkey: Builder::build_p2wpkh(&[0x11; 20].into()).to_bytes(),
+            }],
+            lock_time: 0,
+        }
+    }
+
+    #[test]
+    fn test_multisig_trustees() {
+        let keypairs = [
+            keypair("0000000000000000000000000000000000000000000000000000000000000001"),
+            keypair("0000000000000000000000000000000000000000000000000000000000000002"),
+            keypair("0000000000000000000000000000000000000000000000000000000000000003"),
+        ];
+        let multisig = keypairs
+            .iter()
+            .fold(
+                Builder::default().push_opcode(Opcode::OP_2),
+                |builder, keypair| builder.push_data(keypair.public()),
+            )
+            .push_opcode(Opcode::OP_3)
+            .push_opcode(Opcode::OP_CHECKMULTISIG)
+            .into_script();
+        let p2wsh = Builder::build_p2wsh(&sha256(&multisig));
+        let p2sh_p2wsh = Builder::build_p2sh(&dhash160(&p2wsh));
+        let p2sh = Builder::build_p2sh(&dhash160(&multisig));
+        let spent_outputs = [&p2wsh, &p2sh_p2wsh, &p2sh]
+            .iter()
+            .map(|script| TransactionOutput {
+                value: 50_000,
+                script_pubkey: script.to_bytes(),
+            })
+            .collect::<Vec<_>>();
+
+        // Creator and updater
+        let mut psbt = Psbt::from_unsigned_tx(spending_tx(&spent_outputs)).unwrap();
+        for (index, input) in psbt.inputs.iter_mut().enumerate() {
+            input.witness_utxo = Some(spent_outputs[index].clone());
+        }
+        psbt.inputs[0].witness_script = Some(multisig.clone());
+        psbt.inputs[1].redeem_script = Some(p2wsh.clone());
+        psbt.inputs[1].witness_script = Some(multisig.clone());
+        psbt.inputs[2].redeem_script = Some(multisig.clone());
+        let encoded = psbt.to_string();
+
+        // Each trustee signs its own copy
+        let mut first: Psbt = encoded.parse().unwrap();
+        assert_eq!(first.sign(&keypairs[0]), Ok(3));
+        let mut third: Psbt = encoded.parse().unwrap();
+        assert_eq!(third.sign(&keypairs[2]), Ok(3));
+        assert_eq!(third.finalize(), Err(Error::MissingSignature));
+
+        // Combiner, finalizer and extractor
+        let mut combined: Psbt = first.to_string().parse().unwrap();
+        combined.combine(third).unwrap();
+        assert_eq!(combined.inputs[0].partial_sigs.len(), 2);
+        assert_eq!(combined.extract_tx(), Err(Error::NotFinalized));
+        combined.finalize().unwrap();
+        assert!(combined.inputs[0].partial_sigs.is_empty());
+        assert!(combined.inputs[0].witness_utxo.is_some());
+        let tx = combined.extract_tx().unwrap();
+        assert_eq!(tx.inputs[0].script_witness.len(), 4);
+        assert!(tx.inputs[0].script_sig.is_empty());
+        assert_eq!(tx.inputs[1].script_witness.len(), 4);
+        assert_eq!(
+            tx.inputs[1].script_sig,
+            Builder::default().push_data(&p2wsh).into_bytes()
+        );
+        assert!(tx.inputs[2].script_witness.is_empty());
+        for index in 0..tx.inputs.len() {
+            verify_input(&tx, index, &spent_outputs);
+        }
+
+        // PSBTs of other transactions can't be combined
+        let mut other = Psbt::from_unsigned_tx(spending_tx(&spent_outputs[..1])).unwrap();
+        other.inputs[0].witness_utxo = Some(spent_outputs[0].clone());
+        assert_eq!(combined.combine(other), Err(Error::MismatchedTransaction));
+    }
+
+    #[test]
+    fn test_sign_single_key_inputs() {
+        let keypair = keypair("b7e151628aed2a6abf7158809cf4f3c762e7160f38b4da56a784d9045190cfef");
+        let hash = keypair.public().address_hash();
+        let p2wpkh = Builder::build_p2wpkh(&hash);
+        let spent_outputs = vec![
+            TransactionOutput {
+                value: 50_000,
+                script_pubkey: Builder::build_p2pkh(&hash).to_bytes(),
+            },
+            TransactionOutput {
+                value: 50_000,
+                script_pubkey: p2wpkh.to_bytes(),
+            },
+            TransactionOutput {
+                value: 50_000,
+                script_pubkey: Builder::build_p2sh(&dhash160(&p2wpkh)).to_bytes(),
+            },
+        ];
+        let tx = spending_tx(&spent_outputs);
+        let previous_tx = Transaction {
+            version: 1,
+            inputs: vec![TransactionInput::coinbase(vec![0x51].into())],
+            outputs: spent_outputs.clone(),
+            lock_time: 0,
+        };
+
+        let mut psbt = Psbt::from_unsigned_tx(tx.clone()).unwrap();
+        // The non-witness UTXO must be the transaction of the spent output
+        assert_eq!(
+            psbt.set_non_witness_utxo(0, previous_tx.clone()),
+            Err(Error::UtxoMismatch)
+        );
+        let mut tx = tx;
+        for (index, input) in tx.inputs.iter_mut().enumerate() {
+            input.previous_output = OutPoint::new(previous_tx.hash(), index as u32);
+        }
+        let mut psbt = Psbt::from_unsigned_tx(tx).unwrap();
+        psbt.set_non_witness_utxo(0, previous_tx).unwrap();
+        psbt.set_witness_utxo(1, spent_outputs[1].clone()).unwrap();
+        psbt.set_witness_utxo(2, spent_outputs[2].clone()).unwrap();
+        assert_eq!(psbt.sign_input(2, &keypair), Err(Error::InvalidScript));
+        psbt.inputs[2].redeem_script = Some(p2wpkh);
+        assert_eq!(psbt.sign(&keypair), Ok(3));
+
+        psbt.finalize().unwrap();
+        let tx = psbt.extract_tx().unwrap();
+        for index in 0..tx.inputs.len() {
+            verify_input(&tx, index, &spent_outputs);
+        }
+    }
+
+    #[test]
+    fn test_taproot_signer() {
+        let leaf_keypair =
+            keypair("0000000000000000000000000000000000000000000000000000000000000003");
+        let keypair = keypair("b7e151628aed2a6abf7158809cf4f3c762e7160f38b4da56a784d9045190cfef");
+        let internal_key = keypair.private().x_only_public().unwrap();
+        let leaf_key = leaf_keypair.private().x_only_public().unwrap();
+        let leaf = Builder::default()
+            .push_data(&leaf_key)
+            .push_opcode(Opcode::OP_CHECKSIG)
+            .into_script();
+        let leaf_hash = crate::taproot::tapleaf_hash(0xc0, &leaf);
+        let (output_key, odd) =
+            crate::taproot::taproot_output_key(&internal_key, Some(&leaf_hash)).unwrap();
+        let mut control = vec![0xc0 | odd as u8];
+        control.extend_from_slice(&internal_key);
+        let spent_outputs = vec![
+            TransactionOutput {
+                value: 50_000,
+                script_pubkey: Builder::build_p2tr(&output_key.x_only()).to_bytes(),
+            };
+            2
+        ];
+
+        let mut psbt = Psbt::from_unsigned_tx(spending_tx(&spent_outputs)).unwrap();
+        for input in psbt.inputs.iter_mut() {
+            input.witness_utxo = Some(spent_outputs[0].clone());
+            input.tap_internal_key = Some(internal_key.x_only());
+            input.tap_merkle_root = Some(leaf_hash);
+            input
+                .tap_scripts
+                .insert(control.clone().into(), (leaf.clone(), 0xc0));
+        }
+        // Key path spend of the first input
+        let mut key_path = psbt.clone();
+        key_path.inputs.truncate(1);
+        assert_eq!(psbt.sign_input(0, &keypair), Ok(1));
+        // Script path spend of the second input
+        assert_eq!(psbt.sign_input(1, &leaf_keypair), Ok(1));
+        assert_eq!(psbt.inputs[1].tap_key_sig, None);
+        assert!(psbt.inputs[1]
+            .tap_script_sigs
+            .contains_key(&(leaf_key.x_only(), leaf_hash)));
+
+        psbt.finalize().unwrap();
+        let tx = psbt.extract_tx().unwrap();
+        assert_eq!(tx.inputs[0].script_witness.len(), 1);
+        assert_eq!(tx.inputs[1].script_witness.len(), 3);
+        for index in 0..tx.inputs.len() {
+            verify_input(&tx, index, &spent_outputs);
+        }
+    }
+
+    #[test]
+    fn test_sign_with_xpriv() {
+        let master = ExtendedPrivKey::new_master(Network::Mainnet, &[0x42; 32]).unwrap();
+        let path: DerivationPath = "m/84'/0'/0'/0/1".parse().unwrap();
+        let public = *KeyPair::from_private(master.derive(&path).unwrap().private())
+            .unwrap()
+            .public();
+        let spent_outputs = vec![TransactionOutput {
+            value: 50_000,
+            script_pubkey: Builder::build_p2wpkh(&public.address_hash()).to_bytes(),
+        }];
+
+        let mut psbt = Psbt::from_unsigned_tx(spending_tx(&spent_outputs)).unwrap();
+        psbt.inputs[0].witness_utxo = Some(spent_outputs[0].clone());
+        psbt.inputs[0]
+            .bip32_derivation
+            .insert(public, (master.fingerprint().unwrap(), path));
+        let other = ExtendedPrivKey::new_master(Network::Mainnet, &[0x43; 32]).unwrap();
+        assert_eq!(psbt.sign_with_xpriv(&other), Ok(0));
+        assert_eq!(psbt.sign_with_xpriv(&master), Ok(1));
+        assert!(psbt.inputs[0].partial_sigs.contains_key(&public));
+
+        psbt.finalize().unwrap();
+        verify_input(&psbt.extract_tx().unwrap(), 0, &spent_outputs);
+    }
+
+    #[test]
+    fn test_v2_constructor() {
+        let mut psbt = Psbt::new_v2(2, Some(100));
+        let txid = h256("9bd48765230bf9a72e662001f972556e54f0c6f97feb56bcb5600d817f699526");
+        psbt.add_input(Input {
+            previous_txid: Some(txid),
+            output_index: Some(0),
+            required_height_locktime: Some(650_000),
+            ..Default::default()
+        })
+        .unwrap();
+        psbt.add_input(Input {
+            previous_txid: Some(txid),
+            output_index: Some(1),
+            required_time_locktime: Some(1_657_048_460),
+            required_height_locktime: Some(650_001),
+            ..Default::default()
+        })
+        .unwrap();
+        // Only time based lock times are incompatible with the first input
+        assert_eq!(
+            psbt.add_input(Input {
+                previous_txid: Some(txid),
+                output_index: Some(2),
+                required_time_locktime: Some(1_657_048_460),
+                ..Default::default()
+            }),
+            Err(Error::LocktimeConflict)
+        );
+        assert_eq!(
+            psbt.add_output(Output::default()),
+            Err(Error::InvalidVersion)
+        );
+        psbt.add_output(Output {
+            amount: Some(90_000),
+            script: Some(Builder::build_p2wpkh(&[0x11; 20].into())),
+            ..Default::default()
+        })
+        .unwrap();
+
+        let tx = psbt.unsigned_tx().unwrap();
+        assert_eq!(tx.lock_time, 650_001);
+        assert_eq!(tx.inputs.len(), 2);
+        assert_eq!(tx.inputs[1].previous_output, OutPoint::new(txid, 1));
+        let decoded: Psbt = psbt.to_string().parse().unwrap();
+        assert_eq!(decoded, psbt);
+
+        // Signing with SIGHASH_ALL freezes the inputs and outputs
+        let keypair = keypair("0000000000000000000000000000000000000000000000000000000000000001");
+        let mut signed = psbt.clone();
+        for input in signed.inputs.iter_mut() {
+            input.witness_utxo = Some(TransactionOutput {
+                value: 50_000,
+                script_pubkey: Builder::build_p2wpkh(&keypair.public().address_hash()).to_bytes(),
+            });
+        }
+        assert_eq!(signed.sign(&keypair), Ok(2));
+        assert_eq!(signed.global.tx_modifiable, Some(0));
+        assert_eq!(
+            signed.add_output(Output {
+                amount: Some(1),
+                script: Some(Builder::build_p2wpkh(&[0x11; 20].into())),
+                ..Default::default()
+            }),
+            Err(Error::NotModifiable)
+        );
+    }
+}
@@ -0,0 +1,186 @@
+//! Raw key-value pairs of the PSBT maps
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use light_bitcoin_keys::{ChildNumber, DerivationPath, Fingerprint};
+use light_bitcoin_primitives::{io, Bytes};
+use light_bitcoin_serialization::{CompactInteger, Deserializable, Reader, Serializable, Stream};
+
+use super::error::Error;
+
+/// Key type of proprietary keys, shared by all maps.
+pub const PSBT_PROPRIETARY: u64 = 0xfc;
+
+/// Master key fingerprint and derivation path of a key.
+pub type KeySource = (Fingerprint, DerivationPath);
+
+/// Key of a map entry: its type followed by the key data.
+#[derive(Ord, PartialOrd, Eq, PartialEq, Clone, Debug, Default)]
+pub struct Key {
+    /// Type of the key.
+    pub type_value: u64,
+    /// Key data, following the type.
+    pub key: Bytes,
+}
+
+impl Key {
+    pub fn new(type_value: u64, key: Bytes) -> Self {
+        Key { type_value, key }
+    }
+}
+
+impl Serializable for Key {
+    fn serialize(&self, stream: &mut Stream) {
+        let type_value = CompactInteger::from(self.type_value);
+        stream
+            .append(&CompactInteger::from(
+                type_value.serialized_size() + self.key.len(),
+            ))
+            .append(&type_value)
+            .append_slice(&self.key);
+    }
+}
+
+/// Key-value pair of a map.
+#[derive(Ord, PartialOrd, Eq, PartialEq, Clone, Debug, Default)]
+pub struct Pair {
+    pub key: Key,
+    pub value: Bytes,
+}
+
+impl Serializable for Pair {
+    fn serialize(&self, stream: &mut Stream) {
+        stream.append(&self.key).append(&self.value);
+    }
+}
+
+impl Pair {
+    /// Reads the next pair of a map, or `None` at the separator ending the map.
+    pub fn read<T>(reader: &mut Reader<T>) -> Result<Option<Pair>, io::Error>
+    where
+        T: io::Read,
+    {
+        let len: usize = reader.read::<CompactInteger>()?.into();
+        if len == 0 {
+            return Ok(None);
+        }
+        let mut data = Bytes::new_with_len(len);
+        reader.read_slice(&mut data)?;
+
+        let mut key_reader = Reader::new(&data);
+        let type_value = key_reader.read::<CompactInteger>()?;
+        let key = data[type_value.serialized_size()..].to_vec().into();
+        Ok(Some(Pair {
+            key: Key::new(type_value.into(), key),
+            value: reader.read()?,
+        }))
+    }
+}
+
+/// Key of a proprietary entry (type `0xfc`): an identifier prefix, a subtype and key data.
+#[derive(Ord, PartialOrd, Eq, PartialEq, Clone, Debug, Default)]
+pub struct ProprietaryKey {
+    /// Identifier of the owner of the key.
+    pub prefix: Bytes,
+    /// Type of the key, defined by its owner.
+    pub subtype: u64,
+    /// Key data, following the subtype.
+    pub key: Bytes,
+}
+
+impl ProprietaryKey {
+    /// Parses the key data of a proprietary key.
+    pub fn from_key(key: &Key) -> Result<Self, Error> {
+        if key.type_value != PSBT_PROPRIETARY {
+            return Err(Error::InvalidKey);
+        }
+        let mut reader = Reader::new(&key.key);
+        let prefix: Bytes = reader.read().map_err(|_| Error::InvalidKey)?;
+        let subtype = reader
+            .read::<CompactInteger>()
+            .map_err(|_| Error::InvalidKey)?;
+        let offset = prefix.serialized_size() + subtype.serialized_size();
+        Ok(ProprietaryKey {
+            prefix,
+            subtype: subtype.into(),
+            key: key.key[offset..].to_vec().into(),
+        })
+    }
+
+    /// Returns the raw key of the proprietary key.
+    pub fn to_key(&self) -> Key {
+        let mut stream = Stream::default();
+        stream
+            .append(&self.prefix)
+            .append(&CompactInteger::from(self.subtype))
+            .append_slice(&self.key);
+        Key::new(PSBT_PROPRIETARY, stream.out())
+    }
+}
+
+/// Serializes a key source as the fingerprint followed by the little-endian path indexes.
+pub fn serialize_key_source(source: &KeySource) -> Bytes {
+    let (fingerprint, path) = source;
+    let mut stream = Stream::default();
+    stream.append(fingerprint);
+    for child in path.iter() {
+        stream.append(&child.to_u32());
+    }
+    stream.out()
+}
+
+/// Deserializes a key source.
+pub fn deserialize_key_source(data: &[u8]) -> Result<KeySource, Error> {
+    if data.len() < 4 || !(data.len() - 4).is_multiple_of(4) {
+        return Err(Error::InvalidValue);
+    }
+    let fingerprint = Fingerprint::from_slice(&data[..4]);
+    let path = data[4..]
+        .chunks(4)
+        .map(|index| {
+            let mut bytes = [0u8; 4];
+            bytes.copy_from_slice(index);
+            ChildNumber::from(u32::from_le_bytes(bytes))
+        })
+        .collect::<Vec<_>>();
+    Ok((fingerprint, path.into()))
+}
+
+/// Deserializes a value that must be consumed entirely.
+pub fn deserialize_value<T>(data: &[u8]) -> Result<T, Error>
+where
+    T: Deserializable,
+{
+    light_bitcoin_serialization::deserialize(data).map_err(|_| Error::InvalidValue)
+}
+
+#[cfg(test)]
+mod tests {
+    use light_bitcoin_serialization::serialize;
+
+    use super::*;
+
+    #[test]
+    fn test_pair_roundtrip() {
+        let data = hex::decode("020100020304").unwrap();
+        let mut reader = Reader::new(&data);
+        let pair = Pair::read(&mut reader).unwrap().unwrap();
+        assert_eq!(pair.key, Key::new(1, vec![0].into()));
+        assert_eq!(pair.value, vec![3, 4].into());
+        assert_eq!(serialize(&pair), data.into());
+        assert_eq!(Pair::read(&mut Reader::new(&[0])), Ok(None));
+    }
+
+    #[test]
+    fn test_proprietary_key() {
+        let key = ProprietaryKey {
+            prefix: b"ABC".to_vec().into(),
+            subtype: 2,
+            key: vec![0xff].into(),
+        };
+        let raw = key.to_key();
+        assert_eq!(raw.key, hex::decode("0341424302ff").unwrap().into());
+        assert_eq!(ProprietaryKey::from_key(&raw), Ok(key));
+    }
+}
@@ -0,0 +1,228 @@
+//! Signer role of the PSBT
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use light_bitcoin_keys::{ExtendedPrivKey, KeyPair};
+use light_bitcoin_primitives::H256;
+
+use super::{
+    taproot_output_key, Error, Psbt, SpentScriptKind, TX_MODIFIABLE_INPUTS, TX_MODIFIABLE_OUTPUTS,
+    TX_MODIFIABLE_SIGHASH_SINGLE,
+};
+use crate::script::Script;
+use crate::sign::{ScriptExecutionData, Sighash, SignatureVersion, TransactionInputSigner};
+use crate::taproot::{
+    tapleaf_hash, taproot_tweak_hash, verify_taproot_commitment, TAPROOT_CONTROL_BASE_SIZE,
+    TAPROOT_CONTROL_MAX_SIZE, TAPROOT_CONTROL_NODE_SIZE, TAPROOT_LEAF_TAPSCRIPT,
+};
+
+/// Signature hash type of ECDSA signatures when the input doesn't set one.
+const SIGHASH_ALL: u32 = 1;
+/// Signature hash type of schnorr signatures when the input doesn't set one.
+const SIGHASH_DEFAULT: u32 = 0;
+/// Signature hash type flag committing to the current input only.
+const SIGHASH_ANYONECANPAY: u32 = 0x80;
+/// Base signature hash type committing to none of the outputs.
+const SIGHASH_NONE: u32 = 2;
+/// Base signature hash type committing to the output of the same index.
+const SIGHASH_SINGLE: u32 = 3;
+
+impl Psbt {
+    /// Signs every input the key can sign for, returning the number of new signatures.
+    pub fn sign(&mut self, keypair: &KeyPair) -> Result<usize, Error> {
+        let mut count = 0;
+        for index in 0..self.inputs.len() {
+            count += self.sign_input(index, keypair)?;
+        }
+        Ok(count)
+    }
+
+    /// Signs every input with the keys derived from the master key according to the BIP32
+    /// derivations of the inputs, returning the number of new signatures.
+    pub fn sign_with_xpriv(&mut self, master: &ExtendedPrivKey) -> Result<usize, Error> {
+        let fingerprint = master.fingerprint()?;
+        let mut count = 0;
+        for index in 0..self.inputs.len() {
+            let input = &self.inputs[index];
+            let paths = input
+                .bip32_derivation
+                .values()
+                .chain(input.tap_key_origins.values().map(|(_, source)| source))
+                .filter(|(key_fingerprint, _)| *key_fingerprint == fingerprint)
+                .map(|(_, path)| path.clone())
+                .collect::<Vec<_>>();
+            for path in paths {
+                let keypair = KeyPair::from_private(master.derive(&path)?.private())?;
+                count += self.sign_input(index, &keypair)?;
+            }
+        }
+        Ok(count)
+    }
+
+    /// Signs the input if the key can sign for it, returning the number of new signatures.
+    ///
+    /// Taproot inputs are signed with the key path when the key is the internal key, and with
+    /// every tapscript leaf containing the key otherwise. Other inputs are signed when their
+    /// script (after unwrapping P2SH and segwit v0 programs) contains the key or its hash.
+    pub fn sign_input(&mut self, index: usize, keypair: &KeyPair) -> Result<usize, Error> {
+        let input = self.inputs.get(index).ok_or(Error::InvalidIndex)?;
+        if input.is_finalized() {
+            return Ok(0);
+        }
+        let spent_output = self.spent_output(index)?;
+        let script_pubkey: Script = spent_output.script_pubkey.clone().into();
+        let (count, sighash) = match taproot_output_key(&script_pubkey) {
+            Some(output_key) => self.sign_taproot_input(index, &output_key, keypair)?,
+            None => self.sign_ecdsa_input(index, spent_output.value, &script_pubkey, keypair)?,
+        };
+
+        // Signatures of version 2 PSBTs restrict the inputs and outputs that can be added
+        if count > 0 && self.global.version == 2 {
+            let mut modifiable = self.global.tx_modifiable.unwrap_or(0);
+            if sighash & SIGHASH_ANYONECANPAY == 0 {
+                modifiable &= !TX_MODIFIABLE_INPUTS;
+            }
+            match sighash & 0x1f {
+                SIGHASH_NONE => {}
+                SIGHASH_SINGLE => modifiable |= TX_MODIFIABLE_SIGHASH_SINGLE,
+                _ => modifiable &= !TX_MODIFIABLE_OUTPUTS,
+            }
+            self.global.tx_modifiable = Some(modifiable);
+        }
+        Ok(count)
+    }
+
+    fn sign_ecdsa_input(
+        &mut self,
+        index: usize,
+        amount: u64,
+        script_pubkey: &Script,
+        keypair: &KeyPair,
+    ) -> Result<(usize, u32), Error> {
+        let spent = self.spent_script(index, script_pubkey)?;
+        let sigversion = match spent.kind {
+            SpentScriptKind::Legacy => SignatureVersion::Base,
+            _ => SignatureVersion::WitnessV0,
+        };
+        let sighash = self.inputs[index].sighash_type.unwrap_or(SIGHASH_ALL);
+        if !Sighash::is_defined(sigversion, sighash) {
+            return Err(Error::InvalidSighashType);
+        }
+        let public = keypair.public();
+        if !contains_push(&spent.script_code, public)
+            && !contains_push(&spent.script_code, public.address_hash().as_bytes())
+        {
+            return Ok((0, sighash));
+        }
+
+        let signer = TransactionInputSigner::from(self.unsigned_tx()?);
+        let hash = signer
+            .signature_hash(index, amount, &spent.script_code, sigversion, sighash)
+            .ok_or(Error::InvalidSighashType)?;
+        let mut signature: Vec<u8> = keypair.private().sign(&hash)?.into();
+        signature.push(sighash as u8);
+
+        let partial_sigs = &mut self.inputs[index].partial_sigs;
+        let count = if partial_sigs.contains_key(public) {
+            0
+        } else {
+            1
+        };
+        partial_sigs.insert(*public, signature.into());
+        Ok((count, sighash))
+    }
+
+    fn sign_taproot_input(
+        &mut self,
+        index: usize,
+        output_key: &H256,
+        keypair: &KeyPair,
+    ) -> Result<(usize, u32), Error> {
+        let sighash = self.inputs[index].sighash_type.unwrap_or(SIGHASH_DEFAULT);
+        if !Sighash::is_defined(SignatureVersion::Taproot, sighash) {
+            return Err(Error::InvalidSighashType);
+        }
+        // Taproot signatures commit to all the spent outputs
+        let spent_outputs = (0..self.inputs.len())
+            .map(|i| self.spent_output(i))
+            .collect::<Result<Vec<_>, _>>()?;
+        let signer =
+            TransactionInputSigner::from(self.unsigned_tx()?).with_spent_outputs(spent_outputs);
+        let private = keypair.private();
+        let x_only = private.x_only_public()?.x_only();
+        let input = &mut self.inputs[index];
+        let mut count = 0;
+
+        if input.tap_internal_key == Some(x_only) {
+            let tweak = taproot_tweak_hash(&x_only, input.tap_merkle_root.as_ref());
+            let tweaked = private.tweak_add_x_only(&tweak)?;
+            if tweaked.x_only_public()?.x_only() == *output_key {
+                let hash = signer
+                    .signature_hash_schnorr(
+                        index,
+                        SignatureVersion::Taproot,
+                        sighash,
+                        &ScriptExecutionData::default(),
+                    )
+                    .ok_or(Error::InvalidSighashType)?;
+                let signature = schnorr_signature(&tweaked.sign_schnorr(&hash)?, sighash);
+                if input.tap_key_sig.is_none() {
+                    count += 1;
+                }
+                input.tap_key_sig = Some(signature.into());
+            }
+        }
+
+        for (control, (script, leaf_version)) in &input.tap_scripts {
+            if *leaf_version != TAPROOT_LEAF_TAPSCRIPT
+                || !is_valid_control_size(control.len())
+                || !contains_push(script, x_only.as_bytes())
+            {
+                continue;
+            }
+            let leaf_hash = tapleaf_hash(*leaf_version, script);
+            if !verify_taproot_commitment(control, output_key.as_bytes(), &leaf_hash) {
+                continue;
+            }
+            let execdata = ScriptExecutionData {
+                tapleaf_hash: Some(leaf_hash),
+                ..Default::default()
+            };
+            let hash = signer
+                .signature_hash_schnorr(index, SignatureVersion::Tapscript, sighash, &execdata)
+                .ok_or(Error::InvalidSighashType)?;
+            let signature = schnorr_signature(&private.sign_schnorr(&hash)?, sighash);
+            if input
+                .tap_script_sigs
+                .insert((x_only, leaf_hash), signature.into())
+                .is_none()
+            {
+                count += 1;
+            }
+        }
+        Ok((count, sighash))
+    }
+}
+
+/// Returns true if the script pushes the data.
+fn contains_push(script: &Script, data: &[u8]) -> bool {
+    script
+        .iter()
+        .any(|instruction| matches!(instruction, Ok(i) if i.data == Some(data)))
+}
+
+/// Returns true if a control block of the size can be valid.
+fn is_valid_control_size(len: usize) -> bool {
+    (TAPROOT_CONTROL_BASE_SIZE..=TAPROOT_CONTROL_MAX_SIZE).contains(&len)
+        && (len - TAPROOT_CONTROL_BASE_SIZE).is_multiple_of(TAPROOT_CONTROL_NODE_SIZE)
+}
+
+/// Appends the hash type to a schnorr signature, unless it is `SIGHASH_DEFAULT`.
+fn schnorr_signature(signature: &[u8], sighash: u32) -> Vec<u8> {
+    let mut signature = signature.to_vec();
+    if sighash != SIGHASH_DEFAULT {
+        signature.push(sighash as u8);
+    }
+    signature
+}
@@ -4,9 +4,11 @@
 use alloc::{vec, vec::Vec};
 use core::{fmt, ops, str};
 
-use light_bitcoin_keys::{self as keys, AddressHash, Public};
+use light_bitcoin_crypto::{dhash160, sha256};
+use light_bitcoin_keys::{self as keys, Address, AddressHash, AddressTypes, Network, Public};
 use light_bitcoin_primitives::Bytes;
 
+use crate::builder::Builder;
 use crate::error::Error;
 use crate::opcode::Opcode;
 
@@ -22,6 +24,9 @@ pub const MAX_PUBKEYS_PER_MULTISIG: usize = 20;
 /// Maximum script length in bytes
 pub const MAX_SCRIPT_SIZE: usize = 10000;
 
+/// Maximum number of elements on the stack and altstack combined
+pub const MAX_STACK_SIZE: usize = 1000;
+
 /// Classified script type
 #[derive(PartialEq, Debug)]
 pub enum ScriptType {
@@ -511,17 +516,55 @@ impl Script {
             return 0;
         }
 
-        let script: Script = self
+        // Small integers like OP_1 push no data, their redeem script is empty
+        match self
             .iter()
             .last()
-            .expect("self.data.is_empty() == false; qed")
-            .expect("self.data.is_push_only()")
-            .data
-            .expect("self.data.is_push_only()")
-            .to_vec()
-            .into();
+            .and_then(Result::ok)
+            .and_then(|instruction| instruction.data)
+        {
+            Some(data) => Script::from(data.to_vec()).sigops_count(checkdatasig_active, true),
+            None => 0,
+        }
+    }
+
+    /// Returns the P2SH scriptPubKey paying to this redeem script.
+    pub fn to_p2sh(&self) -> Script {
+        Builder::build_p2sh(&dhash160(&self.data))
+    }
 
-        script.sigops_count(checkdatasig_active, true)
+    /// Returns the P2WSH scriptPubKey paying to this witness script.
+    pub fn to_p2wsh(&self) -> Script {
+        Builder::build_p2wsh(&sha256(&self.data))
+    }
+
+    /// Returns the P2SH scriptPubKey paying to the P2WSH program of this witness script, the
+    /// P2WSH program being the redeem script.
+    pub fn to_p2sh_p2wsh(&self) -> Script {
+        self.to_p2wsh().to_p2sh()
+    }
+
+    /// Returns the P2SH address of this redeem script.
+    pub fn to_p2sh_address(&self, network: Network) -> Address {
+        Address {
+            kind: keys::Type::P2SH,
+            network,
+            hash: AddressTypes::Legacy(dhash160(&self.data)),
+        }
+    }
+
+    /// Returns the P2WSH address of this witness script.
+    pub fn to_p2wsh_address(&self, network: Network) -> Address {
+        Address {
+            kind: keys::Type::P2WSH,
+            network,
+            hash: AddressTypes::WitnessV0ScriptHash(sha256(&self.data)),
+        }
+    }
+
+    /// Returns the P2SH address of the P2WSH program of this witness script.
+    pub fn to_p2sh_p2wsh_address(&self, network: Network) -> Address {
+        self.to_p2wsh().to_p2sh_address(network)
     }
 
     // ============================================================================================
@@ -713,6 +756,7 @@ pub fn is_witness_commitment_script(script: &[u8]) -> bool {
 #[cfg(test)]
 mod tests {
     use light_bitcoin_keys::{Address, Public};
+    use light_bitcoin_primitives::H256;
 
     use super::*;
     use crate::builder::Builder;
@@ -754,6 +798,38 @@ mod tests {
         assert!(!script2.is_pay_to_witness_script_hash());
     }
 
+    #[test]
+    fn test_build_segwit_address_scripts() {
+        let address = "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"
+            .parse::<Address>()
+            .unwrap();
+        let script = Builder::build_p2wpkh(&AddressHash::from_slice(&address.hash));
+        assert!(script.is_pay_to_witness_key_hash());
+        assert_eq!(
+            script.to_bytes(),
+            "0014751e76e8199196d454941c45d1b3a323f1433bd6"
+                .parse()
+                .unwrap()
+        );
+
+        let address = "bc1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3qccfmv3"
+            .parse::<Address>()
+            .unwrap();
+        let script = Builder::build_p2wsh(&H256::from_slice(&address.hash));
+        assert!(script.is_pay_to_witness_script_hash());
+
+        let address = "bc1p0xlxvlhemja6c4dqv22uapctqupfhlxm9h8z3k2e72q4k9hcz7vqzk5jj0"
+            .parse::<Address>()
+            .unwrap();
+        let script = Builder::build_p2tr(&H256::from_slice(&address.hash));
+        assert_eq!(
+            script.to_bytes(),
+            "512079be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798"
+                .parse()
+                .unwrap()
+        );
+    }
+
     #[test]
     fn test_script_debug() {
         let script = Builder::default()
@@ -918,10 +994,12 @@ OP_ADD
 
     #[test]
     fn test_extract_destinations_pub_key_hash() {
-        let address = "13NMTpfNVVJQTNH4spP4UeqBGqLdqDo27S"
-            .parse::<Address>()
-            .unwrap()
-            .hash;
+        let address = AddressHash::from_slice(
+            &"13NMTpfNVVJQTNH4spP4UeqBGqLdqDo27S"
+                .parse::<Address>()
+                .unwrap()
+                .hash,
+        );
         let script = Builder::build_p2pkh(&address);
         assert_eq!(script.script_type(), ScriptType::PubKeyHash);
         assert_eq!(
@@ -932,10 +1010,12 @@ OP_ADD
 
     #[test]
     fn test_extract_destinations_script_hash() {
-        let address = "13NMTpfNVVJQTNH4spP4UeqBGqLdqDo27S"
-            .parse::<Address>()
-            .unwrap()
-            .hash;
+        let address = AddressHash::from_slice(
+            &"13NMTpfNVVJQTNH4spP4UeqBGqLdqDo27S"
+                .parse::<Address>()
+                .unwrap()
+                .hash,
+        );
         let script = Builder::build_p2sh(&address);
         assert_eq!(script.script_type(), ScriptType::ScriptHash);
         assert_eq!(
@@ -1061,5 +1141,114 @@ OP_ADD
         assert_eq!(keylen, 3);
         assert_eq!(keys.len(), 3);
     }
+
+    #[test]
+    fn build_multisig_round_trip() {
+        let (keys, _, _) = REDEEM
+            .parse::<Script>()
+            .unwrap()
+            .parse_redeem_script()
+            .unwrap();
+        let keys = keys
+            .iter()
+            .map(|key| Public::from_slice(key).unwrap())
+            .collect::<Vec<_>>();
+        let script = Builder::build_multisig(2, &keys, false).unwrap();
+        assert_eq!(script, REDEEM.parse().unwrap());
+
+        assert_eq!(
+            Builder::build_multisig(0, &keys, false),
+            Err(Error::SigCount)
+        );
+        assert_eq!(
+            Builder::build_multisig(4, &keys, false),
+            Err(Error::SigCount)
+        );
+        assert_eq!(
+            Builder::build_multisig(1, &[], false),
+            Err(Error::PubkeyCount)
+        );
+        let keys = vec![keys[0]; MAX_PUBKEYS_PER_MULTISIG + 1];
+        assert_eq!(
+            Builder::build_multisig(1, &keys, false),
+            Err(Error::PubkeyCount)
+        );
+
+        // counts above 16 are pushed as numbers
+        let script = Builder::build_multisig(17, &keys[1..], false).unwrap();
+        assert_eq!(&script[..2], &[0x01, 17]);
+        assert_eq!(&script[script.len() - 3..], &[0x01, 20, 0xae]);
+    }
+
+    #[test]
+    fn build_sorted_multisig() {
+        // BIP67 test vector
+        let keys = [
+            "02ff12471208c14bd580709cb2358d98975247d8765f92bc25eab3b2763ed605f8",
+            "02fe6f0a5a297eb38c391581c4413e084773ea23954d93f7753db7dc0adc188b2f",
+        ]
+        .iter()
+        .map(|key| Public::from_slice(&key.parse::<Bytes>().unwrap()).unwrap())
+        .collect::<Vec<_>>();
+        let script = Builder::build_multisig(2, &keys, true).unwrap();
+        let expected: Script = "522102fe6f0a5a297eb38c391581c4413e084773ea23954d93f7753db7dc0adc188b2f2102ff12471208c14bd580709cb2358d98975247d8765f92bc25eab3b2763ed605f852ae"
+            .parse()
+            .unwrap();
+        assert_eq!(script, expected);
+        assert_eq!(
+            script.to_p2sh_address(Network::Mainnet).to_string(),
+            "39bgKC7RFbpoCRbtD5KEdkYKtNyhpsNa3Z"
+        );
+
+        let reversed = keys.iter().rev().copied().collect::<Vec<_>>();
+        assert_eq!(Builder::build_multisig(2, &reversed, true).unwrap(), script);
+        assert_ne!(Builder::build_multisig(2, &keys, false).unwrap(), script);
+
+        // BIP67 only allows compressed keys
+        let uncompressed = Public::from_slice(&"04ae1a62fe09c5f51b13905f07f06b99a2f7159b2225f374cd378d71302fa28414e7aab37397f554a7df5f142c21c1b7303b8a0626f1baded5c72a704f7e6cd84c".parse::<Bytes>().unwrap()).unwrap();
+        let keys = vec![keys[0], uncompressed];
+        assert!(Builder::build_multisig(2, &keys, false).is_ok());
+        assert_eq!(
+            Builder::build_multisig(2, &keys, true),
+            Err(Error::PubkeyType)
+        );
+    }
+
+    #[test]
+    fn build_multisig_x_only_key() {
+        let key = Public::XOnly(H256::repeat_byte(1));
+        assert_eq!(
+            Builder::build_multisig(1, &[key], false),
+            Err(Error::PubkeyType)
+        );
+        assert_eq!(
+            Builder::build_multisig(1, &[key], true),
+            Err(Error::PubkeyType)
+        );
+    }
+
+    #[test]
+    fn redeem_script_outputs() {
+        let script: Script = REDEEM.parse().unwrap();
+
+        let p2sh = script.to_p2sh();
+        assert!(p2sh.is_pay_to_script_hash());
+        assert_eq!(
+            Builder::build_address(&script.to_p2sh_address(Network::Mainnet)),
+            p2sh
+        );
+
+        let p2wsh = script.to_p2wsh();
+        assert!(p2wsh.is_pay_to_witness_script_hash());
+        let address = script.to_p2wsh_address(Network::Mainnet);
+        assert_eq!(address.kind, keys::Type::P2WSH);
+        assert_eq!(Builder::build_address(&address), p2wsh);
+
+        let nested = script.to_p2sh_p2wsh();
+        assert_eq!(nested, p2wsh.to_p2sh());
+        let address = script.to_p2sh_p2wsh_address(Network::Testnet);
+        assert_eq!(address.kind, keys::Type::P2SH);
+        assert_eq!(Builder::build_address(&address), nested);
+    }
     // ============================================================================================
 }
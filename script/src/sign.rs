@@ -4,19 +4,24 @@
 use alloc::{vec, vec::Vec};
 
 use light_bitcoin_chain::{OutPoint, Transaction, TransactionInput, TransactionOutput};
-use light_bitcoin_crypto::dhash256;
-use light_bitcoin_keys::KeyPair;
+use light_bitcoin_crypto::{dhash256, sha256, tagged_hash};
+use light_bitcoin_keys::{self as keys, KeyPair};
 use light_bitcoin_primitives::{Bytes, H256};
 use light_bitcoin_serialization::Stream;
 
 use crate::builder::Builder;
 use crate::script::Script;
+use crate::taproot::{tapleaf_hash, TAPROOT_LEAF_TAPSCRIPT};
 
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum SignatureVersion {
     Base,
     WitnessV0,
     ForkId,
+    /// Taproot key path spending (BIP341)
+    Taproot,
+    /// Tapscript (BIP342)
+    Tapscript,
 }
 
 #[derive(Debug, PartialEq, Clone, Copy)]
@@ -69,6 +74,8 @@ impl Sighash {
         // reset anyone_can_pay && fork_id (if applicable) bits
         let u = match version {
             SignatureVersion::ForkId => u & !(0x40 | 0x80),
+            // SIGHASH_DEFAULT is only defined for taproot signatures
+            SignatureVersion::Taproot | SignatureVersion::Tapscript if u == 0 => return true,
             _ => u & !(0x80),
         };
 
@@ -106,12 +113,38 @@ impl From<TransactionInput> for UnsignedTransactionInput {
     }
 }
 
+/// Data about the script being executed which is committed to by taproot signatures (BIP341/BIP342).
+#[derive(Debug, PartialEq, Clone)]
+pub struct ScriptExecutionData {
+    /// Hash of the annex (`sha256(compact_size(annex) || annex)`), if present.
+    pub annex_hash: Option<H256>,
+    /// Hash of the tapleaf being executed (script path spending only).
+    pub tapleaf_hash: Option<H256>,
+    /// Opcode position of the last executed `OP_CODESEPARATOR`, `0xffffffff` if none.
+    pub codeseparator_pos: u32,
+    /// Remaining signature validation budget (BIP342).
+    pub validation_weight_left: i64,
+}
+
+impl Default for ScriptExecutionData {
+    fn default() -> Self {
+        ScriptExecutionData {
+            annex_hash: None,
+            tapleaf_hash: None,
+            codeseparator_pos: 0xffff_ffff,
+            validation_weight_left: 0,
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct TransactionInputSigner {
     pub version: i32,
     pub inputs: Vec<UnsignedTransactionInput>,
     pub outputs: Vec<TransactionOutput>,
     pub lock_time: u32,
+    /// Outputs spent by `inputs` (in the same order). Required for taproot signature hashes only.
+    pub spent_outputs: Vec<TransactionOutput>,
 }
 
 /// Used for resigning and loading test transactions
@@ -122,11 +155,24 @@ impl From<Transaction> for TransactionInputSigner {
             inputs: t.inputs.into_iter().map(Into::into).collect(),
             outputs: t.outputs,
             lock_time: t.lock_time,
+            spent_outputs: vec![],
         }
     }
 }
 
 impl TransactionInputSigner {
+    /// Sets the outputs spent by the transaction inputs.
+    pub fn with_spent_outputs(mut self, spent_outputs: Vec<TransactionOutput>) -> Self {
+        self.spent_outputs = spent_outputs;
+        self
+    }
+
+    /// Computes the signature hash of the input.
+    ///
+    /// For `SignatureVersion::Tapscript` the `script_pubkey` is the executed leaf script (of leaf
+    /// version `0xc0`). Taproot signature hashes require `spent_outputs` to be set and are `None`
+    /// if they can't be computed (see `signature_hash_schnorr`). The other signature hashes are
+    /// always computed.
     pub fn signature_hash(
         &self,
         input_index: usize,
@@ -134,9 +180,9 @@ impl TransactionInputSigner {
         script_pubkey: &Script,
         sigversion: SignatureVersion,
         sighashtype: u32,
-    ) -> H256 {
+    ) -> Option<H256> {
         let sighash = Sighash::from_u32(sigversion, sighashtype);
-        match sigversion {
+        let hash = match sigversion {
             SignatureVersion::ForkId if sighash.fork_id => self.signature_hash_fork_id(
                 input_index,
                 input_amount,
@@ -154,9 +200,138 @@ impl TransactionInputSigner {
                 sighashtype,
                 sighash,
             ),
+            SignatureVersion::Taproot => {
+                return self.signature_hash_schnorr(
+                    input_index,
+                    sigversion,
+                    sighashtype,
+                    &ScriptExecutionData::default(),
+                )
+            }
+            SignatureVersion::Tapscript => {
+                let execdata = ScriptExecutionData {
+                    tapleaf_hash: Some(tapleaf_hash(TAPROOT_LEAF_TAPSCRIPT, script_pubkey)),
+                    ..Default::default()
+                };
+                return self.signature_hash_schnorr(
+                    input_index,
+                    sigversion,
+                    sighashtype,
+                    &execdata,
+                );
+            }
+        };
+        Some(hash)
+    }
+
+    /// Computes the taproot signature hash of the input (BIP341).
+    ///
+    /// Returns `None` if the hash type is not defined, the input doesn't exist, `spent_outputs`
+    /// doesn't match the inputs, or `SIGHASH_SINGLE` is used without a corresponding output.
+    pub fn signature_hash_schnorr(
+        &self,
+        input_index: usize,
+        sigversion: SignatureVersion,
+        sighashtype: u32,
+        execdata: &ScriptExecutionData,
+    ) -> Option<H256> {
+        let message =
+            self.signature_message_schnorr(input_index, sigversion, sighashtype, execdata)?;
+        Some(tagged_hash(b"TapSighash", &message))
+    }
+
+    /// Serializes the epoch and the message hashed by `signature_hash_schnorr` (BIP341).
+    fn signature_message_schnorr(
+        &self,
+        input_index: usize,
+        sigversion: SignatureVersion,
+        sighashtype: u32,
+        execdata: &ScriptExecutionData,
+    ) -> Option<Bytes> {
+        let ext_flag = match sigversion {
+            SignatureVersion::Taproot => 0u8,
+            SignatureVersion::Tapscript => 1u8,
+            _ => return None,
+        };
+
+        if !Sighash::is_defined(sigversion, sighashtype)
+            || input_index >= self.inputs.len()
+            || self.spent_outputs.len() != self.inputs.len()
+        {
+            return None;
+        }
+
+        let hash_type = sighashtype as u8;
+        let output_type = if hash_type == 0 { 1 } else { hash_type & 3 };
+        let anyone_can_pay = hash_type & 0x80 != 0;
+
+        let mut stream = Stream::default();
+        // Epoch
+        stream.append(&0u8);
+        // Hash type
+        stream.append(&hash_type);
+        // Transaction level data
+        stream.append(&self.version);
+        stream.append(&self.lock_time);
+        if !anyone_can_pay {
+            let mut prevouts = Stream::default();
+            let mut amounts = Stream::default();
+            let mut script_pubkeys = Stream::default();
+            let mut sequences = Stream::default();
+            for (input, spent_output) in self.inputs.iter().zip(&self.spent_outputs) {
+                prevouts.append(&input.previous_output);
+                amounts.append(&spent_output.value);
+                script_pubkeys.append(&spent_output.script_pubkey);
+                sequences.append(&input.sequence);
+            }
+            stream.append(&sha256(&prevouts.out()));
+            stream.append(&sha256(&amounts.out()));
+            stream.append(&sha256(&script_pubkeys.out()));
+            stream.append(&sha256(&sequences.out()));
         }
+        if output_type == SighashBase::All as u8 {
+            let mut outputs = Stream::default();
+            for output in &self.outputs {
+                outputs.append(output);
+            }
+            stream.append(&sha256(&outputs.out()));
+        }
+        // Data about this input
+        let spend_type = (ext_flag << 1) + execdata.annex_hash.is_some() as u8;
+        stream.append(&spend_type);
+        if anyone_can_pay {
+            let input = &self.inputs[input_index];
+            let spent_output = &self.spent_outputs[input_index];
+            stream.append(&input.previous_output);
+            stream.append(spent_output);
+            stream.append(&input.sequence);
+        } else {
+            stream.append(&(input_index as u32));
+        }
+        if let Some(annex_hash) = &execdata.annex_hash {
+            stream.append(annex_hash);
+        }
+        // Data about this output
+        if output_type == SighashBase::Single as u8 {
+            let output = self.outputs.get(input_index)?;
+            let mut single = Stream::default();
+            single.append(output);
+            stream.append(&sha256(&single.out()));
+        }
+        // Data about the script being executed
+        if sigversion == SignatureVersion::Tapscript {
+            stream.append(&execdata.tapleaf_hash?);
+            // Key version
+            stream.append(&0u8);
+            stream.append(&execdata.codeseparator_pos);
+        }
+
+        Some(stream.out())
     }
 
+    /// Signs the input and returns it with its script sig: the signature, followed by the
+    /// public key if `script_pubkey` is P2PKH.
+    ///
     /// input_index - index of input to sign
     /// script_pubkey - script_pubkey of input's previous_output pubkey
     pub fn signed_input(
@@ -167,29 +342,31 @@ impl TransactionInputSigner {
         script_pubkey: &Script,
         sigversion: SignatureVersion,
         sighash: u32,
-    ) -> TransactionInput {
-        let hash = self.signature_hash(
-            input_index,
-            input_amount,
-            script_pubkey,
-            sigversion,
-            sighash,
-        );
+    ) -> Result<TransactionInput, keys::Error> {
+        let hash = self
+            .signature_hash(
+                input_index,
+                input_amount,
+                script_pubkey,
+                sigversion,
+                sighash,
+            )
+            .ok_or(keys::Error::InvalidMessage)?;
 
-        let mut signature: Vec<u8> = keypair.private().sign(&hash).unwrap().into();
+        let mut signature: Vec<u8> = keypair.private().sign(&hash)?.into();
         signature.push(sighash as u8);
-        let script_sig = Builder::default()
-            .push_data(&signature)
-            //.push_data(keypair.public())
-            .into_script();
+        let mut builder = Builder::default().push_data(&signature);
+        if script_pubkey.is_pay_to_public_key_hash() {
+            builder = builder.push_data(keypair.public());
+        }
 
         let unsigned_input = &self.inputs[input_index];
-        TransactionInput {
+        Ok(TransactionInput {
             previous_output: unsigned_input.previous_output,
             sequence: unsigned_input.sequence,
-            script_sig: script_sig.to_bytes(),
+            script_sig: builder.into_script().to_bytes(),
             script_witness: vec![],
-        }
+        })
     }
 
     pub fn signature_hash_original(
@@ -379,10 +556,13 @@ fn compute_hash_outputs(
 
 #[cfg(test)]
 mod tests {
-    use light_bitcoin_keys::{Address, Private};
+    use light_bitcoin_keys::{Address, Network, Private};
     use light_bitcoin_primitives::{h256, h256_rev};
 
     use super::*;
+    use crate::flags::VerificationFlags;
+    use crate::interpreter::verify_script;
+    use crate::verify::TransactionSignatureChecker;
 
     // http://www.righto.com/2014/02/bitcoins-hard-way-using-raw-bitcoin.html
     // https://blockchain.info/rawtx/81b4c832d70cb56ff957589752eb4125a4cab78a25a8fc52d6a09e5bd4404d48
@@ -410,7 +590,7 @@ mod tests {
         // this is irrelevant
         let kp = KeyPair::from_private(private).unwrap();
         assert_eq!(kp.address(), from);
-        assert_eq!(&current_output[3..23], to.hash.as_bytes());
+        assert_eq!(&current_output[3..23], &*to.hash);
 
         let unsigned_input = UnsignedTransactionInput {
             sequence: 0xffff_ffff,
@@ -430,6 +610,7 @@ mod tests {
             lock_time: 0,
             inputs: vec![unsigned_input],
             outputs: vec![output],
+            spent_outputs: vec![],
         };
 
         let hash = input_signer.signature_hash(
@@ -439,7 +620,182 @@ mod tests {
             SignatureVersion::Base,
             SighashBase::All.into(),
         );
-        assert_eq!(hash, expected_signature_hash);
+        assert_eq!(hash, Some(expected_signature_hash));
+    }
+
+    #[test]
+    fn test_signed_input_p2pkh() {
+        let keypair = KeyPair::from_private(Private {
+            network: Network::Mainnet,
+            secret: H256::repeat_byte(1),
+            compressed: true,
+        })
+        .unwrap();
+        let script_pubkey = Builder::build_p2pkh(&keypair.public().address_hash());
+        let mut tx = Transaction {
+            version: 2,
+            inputs: vec![TransactionInput {
+                previous_output: OutPoint::new(H256::repeat_byte(2), 0),
+                script_sig: Bytes::new(),
+                sequence: 0xffff_ffff,
+                script_witness: vec![],
+            }],
+            outputs: vec![TransactionOutput {
+                value: 40_000,
+                script_pubkey: script_pubkey.to_bytes(),
+            }],
+            lock_time: 0,
+        };
+        let signer = TransactionInputSigner::from(tx.clone());
+        tx.inputs[0] = signer
+            .signed_input(
+                &keypair,
+                0,
+                50_000,
+                &script_pubkey,
+                SignatureVersion::Base,
+                SighashBase::All.into(),
+            )
+            .unwrap();
+
+        let checker = TransactionSignatureChecker {
+            signer,
+            input_index: 0,
+            input_amount: 50_000,
+        };
+        let script_sig: Script = tx.inputs[0].script_sig.clone().into();
+        assert_eq!(
+            verify_script(
+                &script_sig,
+                &script_pubkey,
+                &vec![],
+                &VerificationFlags::default().verify_p2sh(true),
+                &checker,
+            ),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_signature_hash_taproot_undefined() {
+        let keypair = KeyPair::from_private(Private {
+            network: Network::Mainnet,
+            secret: H256::repeat_byte(1),
+            compressed: true,
+        })
+        .unwrap();
+        let script_pubkey = Builder::build_p2tr(&keypair.public().x_only());
+        let signer = TransactionInputSigner {
+            version: 2,
+            inputs: vec![UnsignedTransactionInput {
+                previous_output: OutPoint::new(H256::repeat_byte(2), 0),
+                sequence: 0xffff_ffff,
+            }],
+            outputs: vec![],
+            lock_time: 0,
+            spent_outputs: vec![],
+        };
+
+        // No spent outputs
+        let hash = signer.signature_hash(0, 0, &script_pubkey, SignatureVersion::Taproot, 0);
+        assert_eq!(hash, None);
+        let signed =
+            signer.signed_input(&keypair, 0, 0, &script_pubkey, SignatureVersion::Taproot, 0);
+        assert_eq!(signed, Err(keys::Error::InvalidMessage));
+
+        // SIGHASH_SINGLE without corresponding output, undefined hash type
+        let signer = signer.with_spent_outputs(vec![TransactionOutput {
+            value: 50_000,
+            script_pubkey: script_pubkey.to_bytes(),
+        }]);
+        let hash = signer.signature_hash(0, 0, &script_pubkey, SignatureVersion::Taproot, 0);
+        assert!(hash.is_some());
+        let hash = signer.signature_hash(0, 0, &script_pubkey, SignatureVersion::Taproot, 3);
+        assert_eq!(hash, None);
+        let hash = signer.signature_hash(0, 0, &script_pubkey, SignatureVersion::Taproot, 4);
+        assert_eq!(hash, None);
+    }
+
+    // https://github.com/bitcoin/bips/blob/master/bip-0341/wallet-test-vectors.json
+    #[test]
+    fn test_signature_hash_schnorr_bip341() {
+        let tx: Transaction = "02000000097de20cbff686da83a54981d2b9bab3586f4ca7e48f57f5b55963115f3b334e9c010000000000000000d7b7cab57b1393ace2d064f4d4a2cb8af6def61273e127517d44759b6dafdd990000000000fffffffff8e1f583384333689228c5d28eac13366be082dc57441760d957275419a418420000000000fffffffff0689180aa63b30cb162a73c6d2a38b7eeda2a83ece74310fda0843ad604853b0100000000feffffffaa5202bdf6d8ccd2ee0f0202afbbb7461d9264a25e5bfd3c5a52ee1239e0ba6c0000000000feffffff956149bdc66faa968eb2be2d2faa29718acbfe3941215893a2a3446d32acd050000000000000000000e664b9773b88c09c32cb70a2a3e4da0ced63b7ba3b22f848531bbb1d5d5f4c94010000000000000000e9aa6b8e6c9de67619e6a3924ae25696bb7b694bb677a632a74ef7eadfd4eabf0000000000ffffffffa778eb6a263dc090464cd125c466b5a99667720b1c110468831d058aa1b82af10100000000ffffffff0200ca9a3b000000001976a91406afd46bcdfd22ef94ac122aa11f241244a37ecc88ac807840cb0000000020ac9a87f5594be208f8532db38cff670c450ed2fea8fcdefcc9a663f78bab962b0065cd1d"
+            .parse()
+            .unwrap();
+        let spent_outputs = [
+            (
+                "512053a1f6e454df1aa2776a2814a721372d6258050de330b3c6d10ee8f4e0dda343",
+                420000000,
+            ),
+            (
+                "5120147c9c57132f6e7ecddba9800bb0c4449251c92a1e60371ee77557b6620f3ea3",
+                462000000,
+            ),
+            (
+                "76a914751e76e8199196d454941c45d1b3a323f1433bd688ac",
+                294000000,
+            ),
+            (
+                "5120e4d810fd50586274face62b8a807eb9719cef49c04177cc6b76a9a4251d5450e",
+                504000000,
+            ),
+            (
+                "512091b64d5324723a985170e4dc5a0f84c041804f2cd12660fa5dec09fc21783605",
+                630000000,
+            ),
+            ("00147dd65592d0ab2fe0d0257d571abf032cd9db93dc", 378000000),
+            (
+                "512075169f4001aa68f15bbed28b218df1d0a62cbbcf1188c6665110c293c907b831",
+                672000000,
+            ),
+            (
+                "5120712447206d7a5238acc7ff53fbe94a3b64539ad291c7cdbc490b7577e4b17df5",
+                546000000,
+            ),
+            (
+                "512077e30a5522dd9f894c3f8b8bd4c4b2cf82ca7da8a3ea6a239655c39c050ab220",
+                588000000,
+            ),
+        ]
+        .iter()
+        .map(|(script_pubkey, value)| TransactionOutput {
+            value: *value,
+            script_pubkey: script_pubkey.parse().unwrap(),
+        })
+        .collect();
+        let signer = TransactionInputSigner::from(tx).with_spent_outputs(spent_outputs);
+
+        // (input index, hash type, sigMsg, sigHash)
+        let vectors = [
+            (0, 3, "0003020000000065cd1de3b33bb4ef3a52ad1fffb555c0d82828eb22737036eaeb02a235d82b909c4c3f58a6964a4f5f8f0b642ded0a8a553be7622a719da71d1f5befcefcdee8e0fde623ad0f61ad2bca5ba6a7693f50fce988e17c3780bf2b1e720cfbb38fbdd52e2118959c7221ab5ce9e26c3cd67b22c24f8baa54bac281d8e6b05e400e6c3a957e0000000000d0418f0e9a36245b9a50ec87f8bf5be5bcae434337b87139c3a5b1f56e33cba0", "2514a6272f85cfa0f45eb907fcb0d121b808ed37c6ea160a5a9046ed5526d555"),
+            (1, 131, "0083020000000065cd1d00d7b7cab57b1393ace2d064f4d4a2cb8af6def61273e127517d44759b6dafdd9900000000808f891b00000000225120147c9c57132f6e7ecddba9800bb0c4449251c92a1e60371ee77557b6620f3ea3ffffffffffcef8fb4ca7efc5433f591ecfc57391811ce1e186a3793024def5c884cba51d", "325a644af47e8a5a2591cda0ab0723978537318f10e6a63d4eed783b96a71a4d"),
+            (3, 1, "0001020000000065cd1de3b33bb4ef3a52ad1fffb555c0d82828eb22737036eaeb02a235d82b909c4c3f58a6964a4f5f8f0b642ded0a8a553be7622a719da71d1f5befcefcdee8e0fde623ad0f61ad2bca5ba6a7693f50fce988e17c3780bf2b1e720cfbb38fbdd52e2118959c7221ab5ce9e26c3cd67b22c24f8baa54bac281d8e6b05e400e6c3a957ea2e6dab7c1f0dcd297c8d61647fd17d821541ea69c3cc37dcbad7f90d4eb4bc50003000000", "bf013ea93474aa67815b1b6cc441d23b64fa310911d991e713cd34c7f5d46669"),
+            (4, 0, "0000020000000065cd1de3b33bb4ef3a52ad1fffb555c0d82828eb22737036eaeb02a235d82b909c4c3f58a6964a4f5f8f0b642ded0a8a553be7622a719da71d1f5befcefcdee8e0fde623ad0f61ad2bca5ba6a7693f50fce988e17c3780bf2b1e720cfbb38fbdd52e2118959c7221ab5ce9e26c3cd67b22c24f8baa54bac281d8e6b05e400e6c3a957ea2e6dab7c1f0dcd297c8d61647fd17d821541ea69c3cc37dcbad7f90d4eb4bc50004000000", "4f900a0bae3f1446fd48490c2958b5a023228f01661cda3496a11da502a7f7ef"),
+            (6, 2, "0002020000000065cd1de3b33bb4ef3a52ad1fffb555c0d82828eb22737036eaeb02a235d82b909c4c3f58a6964a4f5f8f0b642ded0a8a553be7622a719da71d1f5befcefcdee8e0fde623ad0f61ad2bca5ba6a7693f50fce988e17c3780bf2b1e720cfbb38fbdd52e2118959c7221ab5ce9e26c3cd67b22c24f8baa54bac281d8e6b05e400e6c3a957e0006000000", "15f25c298eb5cdc7eb1d638dd2d45c97c4c59dcaec6679cfc16ad84f30876b85"),
+            (7, 130, "0082020000000065cd1d00e9aa6b8e6c9de67619e6a3924ae25696bb7b694bb677a632a74ef7eadfd4eabf00000000804c8b2000000000225120712447206d7a5238acc7ff53fbe94a3b64539ad291c7cdbc490b7577e4b17df5ffffffff", "cd292de50313804dabe4685e83f923d2969577191a3e1d2882220dca88cbeb10"),
+            (8, 129, "0081020000000065cd1da2e6dab7c1f0dcd297c8d61647fd17d821541ea69c3cc37dcbad7f90d4eb4bc500a778eb6a263dc090464cd125c466b5a99667720b1c110468831d058aa1b82af101000000002b0c230000000022512077e30a5522dd9f894c3f8b8bd4c4b2cf82ca7da8a3ea6a239655c39c050ab220ffffffff", "cccb739eca6c13a8a89e6e5cd317ffe55669bbda23f2fd37b0f18755e008edd2"),
+        ];
+        let execdata = ScriptExecutionData::default();
+        for (index, hash_type, message, hash) in vectors.iter() {
+            assert_eq!(
+                signer.signature_message_schnorr(
+                    *index,
+                    SignatureVersion::Taproot,
+                    *hash_type,
+                    &execdata
+                ),
+                Some(message.parse().unwrap())
+            );
+            assert_eq!(
+                signer.signature_hash_schnorr(
+                    *index,
+                    SignatureVersion::Taproot,
+                    *hash_type,
+                    &execdata
+                ),
+                Some(h256(hash))
+            );
+        }
     }
 
     fn run_test_sighash(tx: &str, script: &str, input_index: usize, hash_type: i32, result: &str) {
@@ -0,0 +1,256 @@
+//! Signature operations cost (BIP141)
+
+use light_bitcoin_chain::{constants::WITNESS_SCALE_FACTOR, Transaction, TransactionOutput};
+use light_bitcoin_primitives::Bytes;
+
+use crate::flags::VerificationFlags;
+use crate::script::Script;
+
+/// Counts of the signature operations of a transaction.
+///
+/// `spent_outputs` are the outputs spent by the inputs, in the same order.
+pub trait TransactionSigOps {
+    /// Counts the signature operations of the input and output scripts, without looking into
+    /// P2SH redeem scripts and witnesses.
+    fn legacy_sigops_count(&self) -> usize;
+
+    /// Counts the signature operations of the redeem scripts of the inputs spending P2SH
+    /// outputs.
+    fn p2sh_sigops_count(&self, spent_outputs: &[TransactionOutput]) -> usize;
+
+    /// Computes the sigop cost of the transaction: the legacy and P2SH signature operations
+    /// count four times, the witness signature operations once.
+    fn sigop_cost(&self, spent_outputs: &[TransactionOutput], flags: &VerificationFlags) -> usize;
+}
+
+impl TransactionSigOps for Transaction {
+    fn legacy_sigops_count(&self) -> usize {
+        let inputs = self
+            .inputs
+            .iter()
+            .map(|input| Script::from(input.script_sig.clone()).sigops_count(false, false));
+        let outputs = self
+            .outputs
+            .iter()
+            .map(|output| Script::from(output.script_pubkey.clone()).sigops_count(false, false));
+        inputs.chain(outputs).sum()
+    }
+
+    fn p2sh_sigops_count(&self, spent_outputs: &[TransactionOutput]) -> usize {
+        if self.is_coinbase() {
+            return 0;
+        }
+        self.inputs
+            .iter()
+            .zip(spent_outputs)
+            .map(|(input, spent_output)| {
+                let script_sig: Script = input.script_sig.clone().into();
+                script_sig
+                    .pay_to_script_hash_sigops(false, &spent_output.script_pubkey.clone().into())
+            })
+            .sum()
+    }
+
+    fn sigop_cost(&self, spent_outputs: &[TransactionOutput], flags: &VerificationFlags) -> usize {
+        let mut cost = self.legacy_sigops_count() * WITNESS_SCALE_FACTOR;
+        if self.is_coinbase() {
+            return cost;
+        }
+        if flags.verify_p2sh {
+            cost += self.p2sh_sigops_count(spent_outputs) * WITNESS_SCALE_FACTOR;
+        }
+        if flags.verify_witness {
+            cost += self
+                .inputs
+                .iter()
+                .zip(spent_outputs)
+                .map(|(input, spent_output)| {
+                    witness_sigops(
+                        &input.script_sig.clone().into(),
+                        &spent_output.script_pubkey.clone().into(),
+                        &input.script_witness,
+                    )
+                })
+                .sum::<usize>();
+        }
+        cost
+    }
+}
+
+/// Counts the signature operations of the witness spending a native or P2SH-wrapped witness
+/// program.
+fn witness_sigops(script_sig: &Script, script_pubkey: &Script, witness: &[Bytes]) -> usize {
+    if let Some((version, program)) = script_pubkey.parse_witness_program() {
+        return witness_program_sigops(version, program, witness);
+    }
+    if script_pubkey.is_pay_to_script_hash() && script_sig.is_push_only() {
+        let redeem_script: Option<Script> = script_sig
+            .iter()
+            .last()
+            .and_then(Result::ok)
+            .and_then(|instruction| instruction.data)
+            .map(|data| data.to_vec().into());
+        if let Some((version, program)) = redeem_script
+            .as_ref()
+            .and_then(Script::parse_witness_program)
+        {
+            return witness_program_sigops(version, program, witness);
+        }
+    }
+    0
+}
+
+/// Counts the signature operations of a version 0 witness program; the other versions have
+/// none.
+fn witness_program_sigops(version: u8, program: &[u8], witness: &[Bytes]) -> usize {
+    match (version, program.len()) {
+        // P2WPKH
+        (0, 20) => 1,
+        // P2WSH, the witness script is the last item
+        (0, 32) => witness
+            .last()
+            .map(|script| Script::from(script.clone()).sigops_count(false, true))
+            .unwrap_or(0),
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use light_bitcoin_chain::{OutPoint, TransactionInput};
+    use light_bitcoin_crypto::{dhash160, sha256};
+    use light_bitcoin_primitives::H256;
+
+    use super::*;
+    use crate::builder::Builder;
+    use crate::opcode::Opcode;
+
+    fn multisig() -> Script {
+        let mut builder = Builder::default().push_opcode(Opcode::OP_2);
+        for key in 2..5 {
+            builder = builder.push_data(&[key; 33]);
+        }
+        builder
+            .push_opcode(Opcode::OP_3)
+            .push_opcode(Opcode::OP_CHECKMULTISIG)
+            .into_script()
+    }
+
+    fn spending(script_sig: Script, script_witness: Vec<Bytes>) -> Transaction {
+        Transaction {
+            version: 2,
+            inputs: vec![TransactionInput {
+                previous_output: OutPoint::new(H256::repeat_byte(1), 0),
+                script_sig: script_sig.to_bytes(),
+                sequence: 0xffff_ffff,
+                script_witness,
+            }],
+            outputs: vec![TransactionOutput {
+                value: 1000,
+                script_pubkey: Builder::build_p2pkh(&Default::default()).to_bytes(),
+            }],
+            lock_time: 0,
+        }
+    }
+
+    fn spent(script_pubkey: Script) -> Vec<TransactionOutput> {
+        vec![TransactionOutput {
+            value: 2000,
+            script_pubkey: script_pubkey.to_bytes(),
+        }]
+    }
+
+    #[test]
+    fn test_sigop_cost() {
+        let flags = VerificationFlags::default()
+            .verify_p2sh(true)
+            .verify_witness(true);
+        let multisig = multisig();
+        let dummy_signature = [0x30; 72];
+
+        // P2PKH: the checksig of the output
+        let tx = spending(
+            Builder::default()
+                .push_data(&dummy_signature)
+                .push_data(&[2; 33])
+                .into_script(),
+            vec![],
+        );
+        let prevouts = spent(Builder::build_p2pkh(&Default::default()));
+        assert_eq!(tx.legacy_sigops_count(), 1);
+        assert_eq!(tx.sigop_cost(&prevouts, &flags), 4);
+
+        // P2SH multisig: the keys of the redeem script
+        let tx = spending(
+            Builder::default()
+                .push_opcode(Opcode::OP_0)
+                .push_data(&dummy_signature)
+                .push_data(&dummy_signature)
+                .push_data(&multisig.to_bytes())
+                .into_script(),
+            vec![],
+        );
+        let prevouts = spent(Builder::build_p2sh(&dhash160(&multisig)));
+        assert_eq!(tx.p2sh_sigops_count(&prevouts), 3);
+        assert_eq!(tx.sigop_cost(&prevouts, &flags), 4 + 3 * 4);
+        assert_eq!(tx.sigop_cost(&prevouts, &VerificationFlags::default()), 4);
+
+        // A redeem script pushed by a small integer opcode is empty
+        for opcode in [
+            Opcode::OP_1,
+            Opcode::OP_16,
+            Opcode::OP_1NEGATE,
+            Opcode::OP_RESERVED,
+        ]
+        .iter()
+        {
+            let tx = spending(
+                Builder::default().push_opcode(*opcode).into_script(),
+                vec![],
+            );
+            assert_eq!(tx.p2sh_sigops_count(&prevouts), 0);
+            assert_eq!(tx.sigop_cost(&prevouts, &flags), 4);
+        }
+
+        // P2WPKH
+        let tx = spending(
+            Script::new(Bytes::new()),
+            vec![dummy_signature.to_vec().into(), vec![2; 33].into()],
+        );
+        let prevouts = spent(Builder::build_p2wpkh(&Default::default()));
+        assert_eq!(tx.sigop_cost(&prevouts, &flags), 4 + 1);
+        assert_eq!(
+            tx.sigop_cost(&prevouts, &VerificationFlags::default().verify_p2sh(true)),
+            4
+        );
+
+        // P2WSH multisig
+        let witness: Vec<Bytes> = vec![
+            Bytes::new(),
+            dummy_signature.to_vec().into(),
+            dummy_signature.to_vec().into(),
+            multisig.to_bytes(),
+        ];
+        let tx = spending(Script::new(Bytes::new()), witness.clone());
+        let prevouts = spent(Builder::build_p2wsh(&sha256(&multisig)));
+        assert_eq!(tx.sigop_cost(&prevouts, &flags), 4 + 3);
+
+        // P2SH-wrapped P2WSH multisig
+        let redeem_script = Builder::build_p2wsh(&sha256(&multisig));
+        let tx = spending(
+            Builder::default()
+                .push_data(&redeem_script.to_bytes())
+                .into_script(),
+            witness,
+        );
+        let prevouts = spent(Builder::build_p2sh(&dhash160(&redeem_script)));
+        assert_eq!(tx.sigop_cost(&prevouts, &flags), 4 + 3);
+
+        // The coinbase only counts the legacy signature operations
+        let coinbase = Transaction {
+            inputs: vec![TransactionInput::coinbase(Bytes::new())],
+            ..spending(Script::new(Bytes::new()), vec![])
+        };
+        assert_eq!(coinbase.sigop_cost(&[], &flags), 4);
+    }
+}
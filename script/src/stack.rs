@@ -0,0 +1,195 @@
+//! Script stack
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+use core::slice;
+
+use crate::error::Error;
+
+/// Stack used by the script interpreter.
+///
+/// Positions passed to the accessors are counted from the top of the stack,
+/// so `top(0)` is the last pushed element.
+#[derive(Default, Debug, PartialEq, Clone)]
+pub struct Stack<T> {
+    data: Vec<T>,
+}
+
+impl<T> From<Vec<T>> for Stack<T> {
+    fn from(data: Vec<T>) -> Self {
+        Stack { data }
+    }
+}
+
+impl<T> From<Stack<T>> for Vec<T> {
+    fn from(stack: Stack<T>) -> Self {
+        stack.data
+    }
+}
+
+impl<T> Stack<T> {
+    pub fn new() -> Self {
+        Stack { data: Vec::new() }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.data.len()
+    }
+
+    pub fn iter(&self) -> slice::Iter<'_, T> {
+        self.data.iter()
+    }
+
+    pub fn clear(&mut self) {
+        self.data.clear()
+    }
+
+    /// Keeps `len` bottom elements, dropping the rest.
+    pub fn truncate(&mut self, len: usize) {
+        self.data.truncate(len)
+    }
+
+    /// Fails with `InvalidStackOperation` if the stack holds less than `len` elements.
+    pub fn require(&self, len: usize) -> Result<(), Error> {
+        if self.data.len() < len {
+            return Err(Error::InvalidStackOperation);
+        }
+        Ok(())
+    }
+
+    pub fn last(&self) -> Result<&T, Error> {
+        self.data.last().ok_or(Error::InvalidStackOperation)
+    }
+
+    pub fn last_mut(&mut self) -> Result<&mut T, Error> {
+        self.data.last_mut().ok_or(Error::InvalidStackOperation)
+    }
+
+    pub fn pop(&mut self) -> Result<T, Error> {
+        self.data.pop().ok_or(Error::InvalidStackOperation)
+    }
+
+    pub fn push(&mut self, value: T) {
+        self.data.push(value)
+    }
+
+    /// Returns the `i`-th element counted from the top.
+    pub fn top(&self, i: usize) -> Result<&T, Error> {
+        self.require(i + 1)?;
+        let pos = self.data.len() - i - 1;
+        Ok(&self.data[pos])
+    }
+
+    /// Removes and returns the `i`-th element counted from the top.
+    pub fn remove(&mut self, i: usize) -> Result<T, Error> {
+        self.require(i + 1)?;
+        let pos = self.data.len() - i - 1;
+        Ok(self.data.remove(pos))
+    }
+
+    /// Drops `i` top elements (`OP_DROP`, `OP_2DROP`).
+    pub fn drop(&mut self, i: usize) -> Result<(), Error> {
+        self.require(i)?;
+        let len = self.data.len();
+        self.data.truncate(len - i);
+        Ok(())
+    }
+
+    /// Duplicates `i` top elements (`OP_DUP`, `OP_2DUP`, `OP_3DUP`).
+    pub fn dup(&mut self, i: usize) -> Result<(), Error>
+    where
+        T: Clone,
+    {
+        self.require(i)?;
+        let start = self.data.len() - i;
+        for j in 0..i {
+            let value = self.data[start + j].clone();
+            self.data.push(value);
+        }
+        Ok(())
+    }
+
+    /// Copies `i` elements lying under the `i` top elements (`OP_OVER`, `OP_2OVER`).
+    pub fn over(&mut self, i: usize) -> Result<(), Error>
+    where
+        T: Clone,
+    {
+        self.require(i * 2)?;
+        let start = self.data.len() - i * 2;
+        for j in 0..i {
+            let value = self.data[start + j].clone();
+            self.data.push(value);
+        }
+        Ok(())
+    }
+
+    /// Moves `i` elements from the depth of `3 * i` to the top (`OP_ROT`, `OP_2ROT`).
+    pub fn rot(&mut self, i: usize) -> Result<(), Error> {
+        self.require(i * 3)?;
+        let pos = self.data.len() - i * 3;
+        for _ in 0..i {
+            let value = self.data.remove(pos);
+            self.data.push(value);
+        }
+        Ok(())
+    }
+
+    /// Swaps `i` top elements with the `i` elements below them (`OP_SWAP`, `OP_2SWAP`).
+    pub fn swap(&mut self, i: usize) -> Result<(), Error> {
+        self.require(i * 2)?;
+        let len = self.data.len();
+        for j in 0..i {
+            self.data.swap(len - i * 2 + j, len - i + j);
+        }
+        Ok(())
+    }
+
+    /// Removes the second-to-top element (`OP_NIP`).
+    pub fn nip(&mut self) -> Result<(), Error> {
+        self.remove(1).map(|_| ())
+    }
+
+    /// Copies the top element below the second-to-top element (`OP_TUCK`).
+    pub fn tuck(&mut self) -> Result<(), Error>
+    where
+        T: Clone,
+    {
+        self.require(2)?;
+        let len = self.data.len();
+        let value = self.data[len - 1].clone();
+        self.data.insert(len - 2, value);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stack_operations() {
+        let mut stack: Stack<u8> = vec![1, 2, 3, 4, 5, 6].into();
+        stack.rot(2).unwrap();
+        assert_eq!(stack, vec![3, 4, 5, 6, 1, 2].into());
+        stack.swap(2).unwrap();
+        assert_eq!(stack, vec![3, 4, 1, 2, 5, 6].into());
+        stack.over(2).unwrap();
+        assert_eq!(stack, vec![3, 4, 1, 2, 5, 6, 1, 2].into());
+        stack.drop(4).unwrap();
+        assert_eq!(stack, vec![3, 4, 1, 2].into());
+        stack.rot(1).unwrap();
+        assert_eq!(stack, vec![3, 1, 2, 4].into());
+        stack.tuck().unwrap();
+        assert_eq!(stack, vec![3, 1, 4, 2, 4].into());
+        stack.nip().unwrap();
+        assert_eq!(stack, vec![3, 1, 4, 4].into());
+        assert_eq!(stack.top(3), Ok(&3));
+        assert_eq!(stack.remove(2), Ok(1));
+        assert_eq!(stack.top(3), Err(Error::InvalidStackOperation));
+        assert_eq!(stack.dup(4), Err(Error::InvalidStackOperation));
+    }
+}
@@ -0,0 +1,141 @@
+//! Taproot commitments (BIP341) and tapscript helpers (BIP342)
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use light_bitcoin_crypto::tagged_hash;
+use light_bitcoin_keys::{Error as KeysError, Public};
+use light_bitcoin_primitives::H256;
+use light_bitcoin_serialization::Stream;
+
+use crate::script::Script;
+
+/// Mask of the leaf version in the first byte of a control block.
+pub const TAPROOT_LEAF_MASK: u8 = 0xfe;
+/// Leaf version of tapscript (BIP342).
+pub const TAPROOT_LEAF_TAPSCRIPT: u8 = 0xc0;
+/// Size of the control block without the merkle path (leaf version + internal key).
+pub const TAPROOT_CONTROL_BASE_SIZE: usize = 33;
+/// Size of a single merkle path node in the control block.
+pub const TAPROOT_CONTROL_NODE_SIZE: usize = 32;
+/// Maximum number of merkle path nodes in the control block.
+pub const TAPROOT_CONTROL_MAX_NODE_COUNT: usize = 128;
+/// Maximum size of the control block.
+pub const TAPROOT_CONTROL_MAX_SIZE: usize =
+    TAPROOT_CONTROL_BASE_SIZE + TAPROOT_CONTROL_NODE_SIZE * TAPROOT_CONTROL_MAX_NODE_COUNT;
+/// First byte of the annex (the optional last witness element).
+pub const ANNEX_TAG: u8 = 0x50;
+/// Validation weight consumed by each executed signature check in tapscript.
+pub const VALIDATION_WEIGHT_PER_SIGOP_PASSED: i64 = 50;
+/// Validation weight budget added on top of the serialized witness size.
+pub const VALIDATION_WEIGHT_OFFSET: i64 = 50;
+
+/// Computes the hash of a tapleaf: `tagged_hash("TapLeaf", leaf_version || compact_size(script) || script)`.
+pub fn tapleaf_hash(leaf_version: u8, script: &Script) -> H256 {
+    let mut stream = Stream::default();
+    stream.append(&(leaf_version & TAPROOT_LEAF_MASK));
+    stream.append_list(script);
+    tagged_hash(b"TapLeaf", &stream.out())
+}
+
+/// Computes the hash of a tapbranch from the hashes of its children (in any order).
+pub fn tapbranch_hash(a: &H256, b: &H256) -> H256 {
+    let (left, right) = if a < b { (a, b) } else { (b, a) };
+    let mut data = Vec::with_capacity(64);
+    data.extend_from_slice(left.as_bytes());
+    data.extend_from_slice(right.as_bytes());
+    tagged_hash(b"TapBranch", &data)
+}
+
+/// Computes the tweak of the internal key: `tagged_hash("TapTweak", internal_key || merkle_root)`.
+///
+/// Outputs without a script tree commit to the internal key only.
+pub fn taproot_tweak_hash(internal_key: &H256, merkle_root: Option<&H256>) -> H256 {
+    let mut data = Vec::with_capacity(64);
+    data.extend_from_slice(internal_key.as_bytes());
+    if let Some(merkle_root) = merkle_root {
+        data.extend_from_slice(merkle_root.as_bytes());
+    }
+    tagged_hash(b"TapTweak", &data)
+}
+
+/// Computes the output key committing to the internal key and the optional script tree.
+///
+/// Returns the x-only output key and whether its y coordinate is odd.
+pub fn taproot_output_key(
+    internal_key: &Public,
+    merkle_root: Option<&H256>,
+) -> Result<(Public, bool), KeysError> {
+    let tweak = taproot_tweak_hash(&internal_key.x_only(), merkle_root);
+    internal_key.tweak_add_x_only(&tweak)
+}
+
+/// Computes the merkle root of the script tree from the control block and the leaf hash.
+pub fn compute_taproot_merkle_root(control: &[u8], tapleaf_hash: &H256) -> H256 {
+    control[TAPROOT_CONTROL_BASE_SIZE..]
+        .chunks(TAPROOT_CONTROL_NODE_SIZE)
+        .fold(*tapleaf_hash, |k, node| {
+            tapbranch_hash(&k, &H256::from_slice(node))
+        })
+}
+
+/// Checks that the witness program commits to the tapleaf using the control block.
+///
+/// The size of the control block must have been checked by the caller.
+pub fn verify_taproot_commitment(control: &[u8], program: &[u8], tapleaf_hash: &H256) -> bool {
+    let internal_key = Public::XOnly(H256::from_slice(&control[1..TAPROOT_CONTROL_BASE_SIZE]));
+    let merkle_root = compute_taproot_merkle_root(control, tapleaf_hash);
+    match taproot_output_key(&internal_key, Some(&merkle_root)) {
+        Ok((output_key, odd)) => &*output_key == program && odd == (control[0] & 1 == 1),
+        Err(_) => false,
+    }
+}
+
+/// Returns true if the opcode is one of the `OP_SUCCESSx` opcodes of tapscript (BIP342).
+pub fn is_op_success(opcode: u8) -> bool {
+    matches!(
+        opcode,
+        80 | 98 | 126..=129 | 131..=134 | 137..=138 | 141..=142 | 149..=153 | 187..=254
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use light_bitcoin_primitives::h256;
+
+    use super::*;
+
+    #[test]
+    fn test_tapbranch_hash_is_sorted() {
+        let a = h256("0000000000000000000000000000000000000000000000000000000000000001");
+        let b = h256("0000000000000000000000000000000000000000000000000000000000000002");
+        assert_eq!(tapbranch_hash(&a, &b), tapbranch_hash(&b, &a));
+        assert_ne!(tapbranch_hash(&a, &b), tapbranch_hash(&a, &a));
+    }
+
+    // https://github.com/bitcoin/bips/blob/master/bip-0341/wallet-test-vectors.json
+    #[test]
+    fn test_taproot_output_key_without_scripts() {
+        let internal_key = Public::XOnly(h256(
+            "d6889cb081036e0faefa3a35157ad71086b123b2b144b649798b494c300a961d",
+        ));
+        let (output_key, _) = taproot_output_key(&internal_key, None).unwrap();
+        assert_eq!(
+            output_key,
+            Public::XOnly(h256(
+                "53a1f6e454df1aa2776a2814a721372d6258050de330b3c6d10ee8f4e0dda343"
+            ))
+        );
+    }
+
+    #[test]
+    fn test_is_op_success() {
+        assert!(is_op_success(0x50));
+        assert!(is_op_success(0x7e));
+        assert!(is_op_success(0xbb));
+        assert!(is_op_success(0xfe));
+        assert!(!is_op_success(0xba));
+        assert!(!is_op_success(0xac));
+        assert!(!is_op_success(0xff));
+    }
+}
@@ -0,0 +1,326 @@
+//! Transaction builder
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use light_bitcoin_chain::constants::SEQUENCE_FINAL;
+use light_bitcoin_chain::{OutPoint, Transaction, TransactionInput, TransactionOutput};
+use light_bitcoin_crypto::dhash160;
+use light_bitcoin_keys::{Address, KeyPair};
+
+use crate::builder::Builder;
+use crate::psbt::{Error, Psbt};
+use crate::script::Script;
+
+/// Input of the built transaction, with the output it spends.
+#[derive(PartialEq, Clone, Debug)]
+struct BuilderInput {
+    previous_output: OutPoint,
+    sequence: u32,
+    spent_output: TransactionOutput,
+    redeem_script: Option<Script>,
+    witness_script: Option<Script>,
+}
+
+/// Builds and signs transactions spending standard outputs.
+///
+/// P2PK, P2PKH, P2SH multisig, P2WPKH, P2SH-P2WPKH and P2WSH inputs are signed with
+/// `SIGHASH_ALL`. The redeem script of P2SH inputs and the witness script of P2WSH inputs must
+/// be given with the input, except for P2SH-P2WPKH inputs whose redeem script is derived from the
+/// signing key.
+///
+/// The transaction goes through a PSBT to be signed and finalized, so signing errors are PSBT
+/// errors.
+#[derive(PartialEq, Clone, Debug)]
+pub struct TransactionBuilder {
+    version: i32,
+    lock_time: u32,
+    inputs: Vec<BuilderInput>,
+    outputs: Vec<TransactionOutput>,
+}
+
+impl Default for TransactionBuilder {
+    fn default() -> Self {
+        TransactionBuilder {
+            version: 2,
+            lock_time: 0,
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+        }
+    }
+}
+
+impl TransactionBuilder {
+    /// Sets the version of the transaction, 2 by default.
+    pub fn version(mut self, version: i32) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Sets the lock time of the transaction, 0 by default.
+    pub fn lock_time(mut self, lock_time: u32) -> Self {
+        self.lock_time = lock_time;
+        self
+    }
+
+    /// Adds an input spending the output.
+    pub fn add_input(self, previous_output: OutPoint, spent_output: TransactionOutput) -> Self {
+        self.add_input_with_scripts(previous_output, spent_output, None, None)
+    }
+
+    /// Adds an input spending the output, with the redeem script of P2SH outputs and the witness
+    /// script of P2WSH outputs.
+    pub fn add_input_with_scripts(
+        mut self,
+        previous_output: OutPoint,
+        spent_output: TransactionOutput,
+        redeem_script: Option<Script>,
+        witness_script: Option<Script>,
+    ) -> Self {
+        self.inputs.push(BuilderInput {
+            previous_output,
+            sequence: SEQUENCE_FINAL,
+            spent_output,
+            redeem_script,
+            witness_script,
+        });
+        self
+    }
+
+    /// Sets the sequence number of the last added input, `0xffffffff` by default.
+    pub fn sequence(mut self, sequence: u32) -> Self {
+        if let Some(input) = self.inputs.last_mut() {
+            input.sequence = sequence;
+        }
+        self
+    }
+
+    /// Adds an output paying the value to the address.
+    pub fn add_output(self, address: &Address, value: u64) -> Self {
+        self.add_output_script(Builder::build_address(address), value)
+    }
+
+    /// Adds an output paying the value to the script.
+    pub fn add_output_script(mut self, script_pubkey: Script, value: u64) -> Self {
+        self.outputs.push(TransactionOutput {
+            value,
+            script_pubkey: script_pubkey.to_bytes(),
+        });
+        self
+    }
+
+    /// Returns the transaction without script sigs and witnesses.
+    pub fn unsigned_transaction(&self) -> Transaction {
+        Transaction {
+            version: self.version,
+            inputs: self
+                .inputs
+                .iter()
+                .map(|input| TransactionInput {
+                    previous_output: input.previous_output,
+                    script_sig: Default::default(),
+                    sequence: input.sequence,
+                    script_witness: Vec::new(),
+                })
+                .collect(),
+            outputs: self.outputs.clone(),
+            lock_time: self.lock_time,
+        }
+    }
+
+    /// Signs every input with the keys and returns the signed transaction.
+    ///
+    /// Fails with `Error::MissingSignature` if the keys can't sign an input, and with
+    /// `Error::InvalidScript` if an input doesn't spend a supported script or its redeem or
+    /// witness script doesn't match the spent output.
+    pub fn sign(&self, keypairs: &[KeyPair]) -> Result<Transaction, Error> {
+        let mut psbt = Psbt::from_unsigned_tx(self.unsigned_transaction())?;
+        for (index, input) in self.inputs.iter().enumerate() {
+            let script_pubkey: Script = input.spent_output.script_pubkey.clone().into();
+            let redeem_script = input.redeem_script.clone().or_else(|| {
+                if script_pubkey.is_pay_to_script_hash() {
+                    nested_witness_key_hash(&script_pubkey, keypairs)
+                } else {
+                    None
+                }
+            });
+            let psbt_input = &mut psbt.inputs[index];
+            psbt_input.witness_utxo = Some(input.spent_output.clone());
+            psbt_input.redeem_script = redeem_script;
+            psbt_input.witness_script = input.witness_script.clone();
+        }
+        for keypair in keypairs {
+            psbt.sign(keypair)?;
+        }
+        psbt.finalize()?;
+        psbt.extract_tx()
+    }
+}
+
+/// Returns the P2WPKH redeem script of the key whose P2SH-P2WPKH script is the script pubkey.
+fn nested_witness_key_hash(script_pubkey: &Script, keypairs: &[KeyPair]) -> Option<Script> {
+    keypairs
+        .iter()
+        .map(|keypair| Builder::build_p2wpkh(&keypair.public().address_hash()))
+        .find(|redeem_script| dhash160(redeem_script).as_bytes() == &script_pubkey[2..22])
+}
+
+#[cfg(test)]
+mod tests {
+    use light_bitcoin_crypto::sha256;
+    use light_bitcoin_keys::{AddressTypes, Network, Private, Type};
+    use light_bitcoin_primitives::H256;
+
+    use super::*;
+    use crate::flags::VerificationFlags;
+    use crate::interpreter::verify_script;
+    use crate::opcode::Opcode;
+    use crate::sign::TransactionInputSigner;
+    use crate::verify::TransactionSignatureChecker;
+
+    fn keypair(n: u8) -> KeyPair {
+        KeyPair::from_private(Private {
+            network: Network::Mainnet,
+            secret: H256::repeat_byte(n),
+            compressed: true,
+        })
+        .unwrap()
+    }
+
+    fn output(script_pubkey: &Script) -> TransactionOutput {
+        TransactionOutput {
+            value: 100_000,
+            script_pubkey: script_pubkey.to_bytes(),
+        }
+    }
+
+    fn multisig(keypairs: &[KeyPair]) -> Script {
+        keypairs
+            .iter()
+            .fold(
+                Builder::default().push_opcode(Opcode::OP_2),
+                |builder, keypair| builder.push_data(keypair.public()),
+            )
+            .push_opcode(Opcode::OP_3)
+            .push_opcode(Opcode::OP_CHECKMULTISIG)
+            .into_script()
+    }
+
+    fn verify(tx: &Transaction, spent_outputs: &[TransactionOutput]) {
+        let flags = VerificationFlags::default()
+            .verify_p2sh(true)
+            .verify_witness(true)
+            .verify_nulldummy(true)
+            .verify_cleanstack(true);
+        for (index, (input, spent_output)) in tx.inputs.iter().zip(spent_outputs).enumerate() {
+            let checker = TransactionSignatureChecker {
+                signer: TransactionInputSigner::from(tx.clone()),
+                input_index: index,
+                input_amount: spent_output.value,
+            };
+            assert_eq!(
+                verify_script(
+                    &input.script_sig.clone().into(),
+                    &spent_output.script_pubkey.clone().into(),
+                    &input.script_witness,
+                    &flags,
+                    &checker,
+                ),
+                Ok(())
+            );
+        }
+    }
+
+    #[test]
+    fn test_sign_standard_inputs() {
+        let keys = [keypair(1), keypair(2), keypair(3)];
+        let public = keys[0].public();
+        let multisig = multisig(&keys);
+        let p2wpkh = Builder::build_p2wpkh(&public.address_hash());
+
+        let p2pk = Builder::default()
+            .push_data(public)
+            .push_opcode(Opcode::OP_CHECKSIG)
+            .into_script();
+        let spent_outputs = [
+            output(&p2pk),
+            output(&Builder::build_p2pkh(&public.address_hash())),
+            output(&Builder::build_p2sh(&dhash160(&multisig))),
+            output(&p2wpkh),
+            output(&Builder::build_p2sh(&dhash160(&p2wpkh))),
+            output(&Builder::build_p2wsh(&sha256(&multisig))),
+        ];
+
+        let address = Address {
+            kind: Type::P2WPKH,
+            network: Network::Mainnet,
+            hash: AddressTypes::WitnessV0KeyHash(keys[1].public().address_hash()),
+        };
+        let builder = spent_outputs
+            .iter()
+            .enumerate()
+            .fold(TransactionBuilder::default(), |builder, (index, spent)| {
+                let outpoint = OutPoint::new(H256::repeat_byte(1), index as u32);
+                match index {
+                    2 => builder.add_input_with_scripts(
+                        outpoint,
+                        spent.clone(),
+                        Some(multisig.clone()),
+                        None,
+                    ),
+                    5 => builder.add_input_with_scripts(
+                        outpoint,
+                        spent.clone(),
+                        None,
+                        Some(multisig.clone()),
+                    ),
+                    _ => builder.add_input(outpoint, spent.clone()),
+                }
+            })
+            .add_output(&address, 500_000);
+
+        let tx = builder.sign(&keys[..2]).unwrap();
+        assert_eq!(
+            tx.outputs[0].script_pubkey,
+            Builder::build_p2wpkh(&keys[1].public().address_hash()).to_bytes()
+        );
+        // Legacy inputs have no witness, native segwit inputs no script sig
+        assert!(!tx.inputs[0].has_witness() && !tx.inputs[2].has_witness());
+        assert!(tx.inputs[3].script_sig.is_empty() && tx.inputs[5].script_sig.is_empty());
+        assert_eq!(tx.inputs[3].script_witness.len(), 2);
+        assert_eq!(tx.inputs[5].script_witness.len(), 4);
+        verify(&tx, &spent_outputs);
+    }
+
+    #[test]
+    fn test_sign_errors() {
+        let keys = [keypair(1), keypair(2), keypair(3)];
+        let multisig = multisig(&keys);
+        let p2sh = output(&Builder::build_p2sh(&dhash160(&multisig)));
+        let outpoint = OutPoint::new(H256::repeat_byte(1), 0);
+
+        let builder = TransactionBuilder::default()
+            .add_input_with_scripts(outpoint, p2sh.clone(), Some(multisig.clone()), None)
+            .sequence(0);
+        assert_eq!(builder.unsigned_transaction().inputs[0].sequence, 0);
+        assert_eq!(builder.sign(&keys[..1]), Err(Error::MissingSignature));
+        assert!(builder.sign(&keys[1..]).is_ok());
+
+        // The redeem script is required and must match
+        let builder = TransactionBuilder::default().add_input(outpoint, p2sh.clone());
+        assert_eq!(builder.sign(&keys), Err(Error::InvalidScript));
+        let builder = TransactionBuilder::default().add_input_with_scripts(
+            outpoint,
+            p2sh,
+            Some(Builder::build_p2wpkh(&keys[0].public().address_hash())),
+            None,
+        );
+        assert_eq!(builder.sign(&keys), Err(Error::InvalidScript));
+
+        let builder = TransactionBuilder::default().add_input(
+            outpoint,
+            output(&Builder::build_p2pkh(&keys[0].public().address_hash())),
+        );
+        assert_eq!(builder.sign(&keys[1..]), Err(Error::MissingSignature));
+    }
+}
@@ -2,11 +2,13 @@ use light_bitcoin_chain::constants::{
     LOCKTIME_THRESHOLD, SEQUENCE_FINAL, SEQUENCE_LOCKTIME_DISABLE_FLAG, SEQUENCE_LOCKTIME_MASK,
     SEQUENCE_LOCKTIME_TYPE_FLAG,
 };
-use light_bitcoin_keys::{Message, Public, Signature};
+use light_bitcoin_keys::{Message, Public, SchnorrSignature, Signature};
+use light_bitcoin_primitives::H512;
 
+use crate::error::Error;
 use crate::num::Num;
 use crate::script::Script;
-use crate::sign::{SignatureVersion, TransactionInputSigner};
+use crate::sign::{ScriptExecutionData, SignatureVersion, TransactionInputSigner};
 
 /// Checks transaction signature
 pub trait SignatureChecker {
@@ -21,6 +23,15 @@ pub trait SignatureChecker {
         version: SignatureVersion,
     ) -> bool;
 
+    /// Checks a BIP340 signature (optionally followed by the hash type) of a taproot input.
+    fn check_schnorr_signature(
+        &self,
+        signature: &[u8],
+        public: &Public,
+        version: SignatureVersion,
+        execdata: &ScriptExecutionData,
+    ) -> Result<(), Error>;
+
     fn check_lock_time(&self, lock_time: Num) -> bool;
 
     fn check_sequence(&self, sequence: Num) -> bool;
@@ -44,6 +55,16 @@ impl SignatureChecker for NoopSignatureChecker {
         false
     }
 
+    fn check_schnorr_signature(
+        &self,
+        _: &[u8],
+        _: &Public,
+        _: SignatureVersion,
+        _: &ScriptExecutionData,
+    ) -> Result<(), Error> {
+        Err(Error::SchnorrSig)
+    }
+
     fn check_lock_time(&self, _: Num) -> bool {
         false
     }
@@ -73,14 +94,43 @@ impl SignatureChecker for TransactionSignatureChecker {
         sighashtype: u32,
         version: SignatureVersion,
     ) -> bool {
-        let hash = self.signer.signature_hash(
+        match self.signer.signature_hash(
             self.input_index,
             self.input_amount,
             script_code,
             version,
             sighashtype,
-        );
-        self.verify_signature(signature, public, &hash)
+        ) {
+            Some(hash) => self.verify_signature(signature, public, &hash),
+            None => false,
+        }
+    }
+
+    fn check_schnorr_signature(
+        &self,
+        signature: &[u8],
+        public: &Public,
+        version: SignatureVersion,
+        execdata: &ScriptExecutionData,
+    ) -> Result<(), Error> {
+        // The signature is either 64 bytes (SIGHASH_DEFAULT) or 65 bytes with an explicit hash type
+        let (signature, sighashtype) = match signature.len() {
+            64 => (signature, 0),
+            65 if signature[64] == 0 => return Err(Error::SchnorrSigHashtype),
+            65 => (&signature[..64], u32::from(signature[64])),
+            _ => return Err(Error::SchnorrSigSize),
+        };
+
+        let hash = self
+            .signer
+            .signature_hash_schnorr(self.input_index, version, sighashtype, execdata)
+            .ok_or(Error::SchnorrSigHashtype)?;
+
+        let signature: SchnorrSignature = H512::from_slice(signature).into();
+        match public.verify_schnorr(&hash, &signature) {
+            Ok(true) => Ok(()),
+            _ => Err(Error::SchnorrSig),
+        }
     }
 
     #[cfg_attr(feature = "cargo-clippy", allow(clippy::nonminimal_bool))]
@@ -4,6 +4,8 @@ pub use light_bitcoin_chain as chain;
 pub use light_bitcoin_crypto as crypto;
 pub use light_bitcoin_keys as keys;
 pub use light_bitcoin_merkle as merkle;
+pub use light_bitcoin_message as message;
 pub use light_bitcoin_primitives as primitives;
 pub use light_bitcoin_script as script;
 pub use light_bitcoin_serialization as serialization;
+pub use light_bitcoin_verification as verification;
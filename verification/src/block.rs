@@ -0,0 +1,308 @@
+//! Context-free block checks
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use light_bitcoin_chain::constants::{
+    MAX_BLOCK_SIGOPS_COST, MAX_BLOCK_WEIGHT, WITNESS_SCALE_FACTOR,
+};
+use light_bitcoin_chain::{merkle_node_hash, IndexedBlock};
+use light_bitcoin_crypto::dhash256;
+use light_bitcoin_primitives::H256;
+use light_bitcoin_script::{is_witness_commitment_script, TransactionSigOps};
+
+use crate::error::BlockError;
+use crate::transaction::check_transaction;
+
+/// Runs the checks of the block which don't depend on the chain (Bitcoin Core's `CheckBlock`),
+/// and checks the witness commitment (BIP141).
+///
+/// The target of the header is not compared to the proof of work limit of the network, nor to
+/// the target expected at its height.
+pub fn check_block(block: &IndexedBlock) -> Result<(), BlockError> {
+    block
+        .header
+        .raw
+        .validate_pow()
+        .map_err(|_| BlockError::InvalidProofOfWork)?;
+
+    let hashes = block
+        .transactions
+        .iter()
+        .map(|tx| tx.hash)
+        .collect::<Vec<_>>();
+    let (merkle_root, mutated) = compute_merkle_root(hashes);
+    if merkle_root != block.header.raw.merkle_root_hash {
+        return Err(BlockError::MerkleRootMismatch);
+    }
+    if mutated {
+        return Err(BlockError::MutatedMerkleTree);
+    }
+
+    if block.transactions.is_empty()
+        || block.transactions.len() * WITNESS_SCALE_FACTOR > MAX_BLOCK_WEIGHT
+        || block.size() * WITNESS_SCALE_FACTOR > MAX_BLOCK_WEIGHT
+    {
+        return Err(BlockError::Size);
+    }
+
+    if !block.transactions[0].raw.is_coinbase() {
+        return Err(BlockError::MissingCoinbase);
+    }
+    if block.transactions[1..]
+        .iter()
+        .any(|tx| tx.raw.is_coinbase())
+    {
+        return Err(BlockError::MultipleCoinbase);
+    }
+
+    for (index, tx) in block.transactions.iter().enumerate() {
+        check_transaction(&tx.raw).map_err(|error| BlockError::Transaction(index, error))?;
+    }
+
+    let sigops = block
+        .transactions
+        .iter()
+        .map(|tx| tx.raw.legacy_sigops_count())
+        .sum::<usize>();
+    if sigops * WITNESS_SCALE_FACTOR > MAX_BLOCK_SIGOPS_COST {
+        return Err(BlockError::Sigops);
+    }
+
+    check_witness_commitment(block)?;
+
+    if block.weight() > MAX_BLOCK_WEIGHT {
+        return Err(BlockError::Size);
+    }
+    Ok(())
+}
+
+/// Checks the witness commitment of the coinbase, the last of its outputs matching the commitment
+/// script, against the witnesses of the transactions.
+///
+/// Without a commitment, no transaction may have a witness.
+fn check_witness_commitment(block: &IndexedBlock) -> Result<(), BlockError> {
+    let coinbase = &block.transactions[0].raw;
+    let commitment = coinbase
+        .outputs
+        .iter()
+        .rev()
+        .find(|output| is_witness_commitment_script(&output.script_pubkey));
+
+    let commitment = match commitment {
+        Some(output) => &output.script_pubkey[6..38],
+        None => {
+            if block.transactions.iter().any(|tx| tx.raw.has_witness()) {
+                return Err(BlockError::UnexpectedWitness);
+            }
+            return Ok(());
+        }
+    };
+
+    let witness = &coinbase.inputs[0].script_witness;
+    if witness.len() != 1 || witness[0].len() != 32 {
+        return Err(BlockError::WitnessNonceSize);
+    }
+
+    let mut data = [0u8; 64];
+    data[..32].copy_from_slice(block.witness_merkle_root().as_bytes());
+    data[32..].copy_from_slice(&witness[0]);
+    if dhash256(&data).as_bytes() != commitment {
+        return Err(BlockError::WitnessMerkleMismatch);
+    }
+    Ok(())
+}
+
+/// Computes the merkle root of the hashes, and whether two identical hashes are hashed together
+/// at some level of the tree, which lets a different list of transactions have the same root
+/// (CVE-2012-2459).
+///
+/// The last hash of an odd level is hashed with itself without being reported.
+fn compute_merkle_root(mut hashes: Vec<H256>) -> (H256, bool) {
+    let mut mutated = false;
+    if hashes.is_empty() {
+        return (H256::zero(), mutated);
+    }
+    while hashes.len() > 1 {
+        mutated |= hashes.chunks_exact(2).any(|pair| pair[0] == pair[1]);
+        hashes = hashes
+            .chunks(2)
+            .map(|pair| merkle_node_hash(&pair[0], pair.get(1).unwrap_or(&pair[0])))
+            .collect();
+    }
+    (hashes[0], mutated)
+}
+
+#[cfg(test)]
+mod tests {
+    use light_bitcoin_chain::{
+        Block, BlockHeader, OutPoint, Transaction, TransactionInput, TransactionOutput,
+    };
+    use light_bitcoin_primitives::{Bytes, Compact};
+
+    use super::*;
+    use crate::error::TransactionError;
+
+    const BLOCK_80000: &str = "01000000ba8b9cda965dd8e536670f9ddec10e53aab14b20bacad27b9137190000000000190760b278fe7b8565fda3b968b918d5fd997f993b23674c0af3b6fde300b38f33a5914ce6ed5b1b01e32f570201000000010000000000000000000000000000000000000000000000000000000000000000ffffffff0704e6ed5b1b014effffffff0100f2052a01000000434104b68a50eaa0287eff855189f949c1c6e5f58b37c88231373d8a59809cbae83059cc6469d65c665ccfd1cfeb75c6e8e19413bba7fbff9bc762419a76d87b16086eac000000000100000001a6b97044d03da79c005b20ea9c0e1a6d9dc12d9f7b91a5911c9030a439eed8f5000000004948304502206e21798a42fae0e854281abd38bacd1aeed3ee3738d9e1446618c4571d1090db022100e2ac980643b0b82c0e88ffdfec6b64e3e6ba35e7ba5fdd7d5d6cc8d25c6b241501ffffffff0100f2052a010000001976a914404371705fa9bd789a2fcd52d2c580b65d35549d88ac00000000";
+
+    fn coinbase() -> Transaction {
+        Transaction {
+            version: 1,
+            inputs: vec![TransactionInput::coinbase(vec![1, 1].into())],
+            outputs: vec![TransactionOutput {
+                value: 50_000,
+                script_pubkey: Bytes::new(),
+            }],
+            lock_time: 0,
+        }
+    }
+
+    fn payment(n: u8) -> Transaction {
+        Transaction {
+            version: 1,
+            inputs: vec![TransactionInput {
+                previous_output: OutPoint::new(H256::repeat_byte(n), 0),
+                ..Default::default()
+            }],
+            outputs: vec![TransactionOutput {
+                value: 50_000,
+                script_pubkey: Bytes::new(),
+            }],
+            lock_time: 0,
+        }
+    }
+
+    /// Builds a block of the transactions with the minimum difficulty, committing to the
+    /// merkle root of the hashes.
+    fn mine(transactions: Vec<Transaction>, hashes: &[H256]) -> IndexedBlock {
+        let mut header = BlockHeader {
+            version: 1,
+            previous_header_hash: H256::zero(),
+            merkle_root_hash: compute_merkle_root(hashes.to_vec()).0,
+            time: 0,
+            bits: Compact::new(0x207f_ffff),
+            nonce: 0,
+        };
+        while header.validate_pow().is_err() {
+            header.nonce += 1;
+        }
+        Block::new(header, transactions).into()
+    }
+
+    fn block(transactions: Vec<Transaction>) -> IndexedBlock {
+        let hashes = transactions.iter().map(|tx| tx.hash()).collect::<Vec<_>>();
+        mine(transactions, &hashes)
+    }
+
+    /// Adds a witness commitment of the transactions to the coinbase.
+    fn commit_witnesses(transactions: &mut [Transaction]) {
+        transactions[0].inputs[0].script_witness = vec![vec![0u8; 32].into()];
+        let block: IndexedBlock = Block::new(Default::default(), transactions.to_vec()).into();
+        let mut data = [0u8; 64];
+        data[..32].copy_from_slice(block.witness_merkle_root().as_bytes());
+        let mut script = vec![0x6a, 0x24, 0xaa, 0x21, 0xa9, 0xed];
+        script.extend_from_slice(dhash256(&data).as_bytes());
+        transactions[0].outputs.push(TransactionOutput {
+            value: 0,
+            script_pubkey: script.into(),
+        });
+    }
+
+    #[test]
+    fn test_check_block() {
+        let block: IndexedBlock = BLOCK_80000.parse().unwrap();
+        assert_eq!(check_block(&block), Ok(()));
+
+        let mut invalid = block.clone();
+        invalid.header.raw.nonce += 1;
+        invalid.header.hash = invalid.header.raw.hash();
+        assert_eq!(check_block(&invalid), Err(BlockError::InvalidProofOfWork));
+
+        assert_eq!(
+            check_block(&self::block(vec![coinbase(), payment(1)])),
+            Ok(())
+        );
+
+        let mut invalid = self::block(vec![coinbase(), payment(1)]);
+        invalid.transactions.pop();
+        assert_eq!(check_block(&invalid), Err(BlockError::MerkleRootMismatch));
+
+        assert_eq!(check_block(&self::block(vec![])), Err(BlockError::Size));
+        assert_eq!(
+            check_block(&self::block(vec![payment(1)])),
+            Err(BlockError::MissingCoinbase)
+        );
+        let mut second = coinbase();
+        second.inputs[0].script_sig = vec![2, 2].into();
+        assert_eq!(
+            check_block(&self::block(vec![coinbase(), second])),
+            Err(BlockError::MultipleCoinbase)
+        );
+
+        let mut empty = payment(2);
+        empty.outputs.clear();
+        assert_eq!(
+            check_block(&self::block(vec![coinbase(), payment(1), empty])),
+            Err(BlockError::Transaction(2, TransactionError::EmptyOutputs))
+        );
+
+        let mut sigops = coinbase();
+        sigops.outputs[0].script_pubkey = vec![0xac; MAX_BLOCK_SIGOPS_COST / 4 + 1].into();
+        assert_eq!(
+            check_block(&self::block(vec![sigops])),
+            Err(BlockError::Sigops)
+        );
+    }
+
+    // https://github.com/bitcoin/bitcoin/blob/master/src/test/merkle_tests.cpp
+    #[test]
+    fn test_mutated_merkle_tree() {
+        // [1, 2, 3] and [1, 2, 3, 3] have the same merkle root
+        let transactions = vec![coinbase(), payment(1), payment(2)];
+        let mut hashes = transactions.iter().map(|tx| tx.hash()).collect::<Vec<_>>();
+        assert!(!compute_merkle_root(hashes.clone()).1);
+        hashes.push(hashes[2]);
+        assert!(compute_merkle_root(hashes.clone()).1);
+        assert_eq!(
+            compute_merkle_root(hashes.clone()).0,
+            compute_merkle_root(hashes[..3].to_vec()).0
+        );
+
+        let mut mutated = transactions.clone();
+        mutated.push(payment(2));
+        assert_eq!(
+            check_block(&mine(mutated, &hashes)),
+            Err(BlockError::MutatedMerkleTree)
+        );
+        assert_eq!(check_block(&mine(transactions, &hashes)), Ok(()));
+    }
+
+    #[test]
+    fn test_witness_commitment() {
+        let mut witness = payment(1);
+        witness.inputs[0].script_witness = vec![vec![1].into()];
+
+        assert_eq!(
+            check_block(&block(vec![coinbase(), witness.clone()])),
+            Err(BlockError::UnexpectedWitness)
+        );
+
+        let mut transactions = vec![coinbase(), witness.clone()];
+        commit_witnesses(&mut transactions);
+        assert_eq!(check_block(&block(transactions.clone())), Ok(()));
+
+        let mut invalid = transactions.clone();
+        invalid[0].inputs[0].script_witness.clear();
+        assert_eq!(
+            check_block(&block(invalid)),
+            Err(BlockError::WitnessNonceSize)
+        );
+
+        let mut invalid = transactions;
+        invalid[1].inputs[0].script_witness = vec![vec![2].into()];
+        assert_eq!(
+            check_block(&block(invalid)),
+            Err(BlockError::WitnessMerkleMismatch)
+        );
+    }
+}
@@ -0,0 +1,217 @@
+//! Consensus and policy errors
+
+use light_bitcoin_script::Error as ScriptError;
+
+/// Errors of the transaction consensus checks
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum TransactionError {
+    /// The transaction has no input.
+    EmptyInputs,
+    /// The transaction has no output.
+    EmptyOutputs,
+    /// The transaction without witness weighs more than a block.
+    Oversize,
+    /// An output value is above `MAX_MONEY`.
+    OutputValueTooLarge,
+    /// The sum of the output values is above `MAX_MONEY`.
+    TotalOutputValueTooLarge,
+    /// Several inputs spend the same output.
+    DuplicateInputs,
+    /// The coinbase script is shorter than 2 bytes or longer than 100 bytes.
+    CoinbaseScriptSize,
+    /// An input of a transaction other than the coinbase spends a null outpoint.
+    NullPrevout,
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TransactionError {}
+
+impl core::fmt::Display for TransactionError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            TransactionError::EmptyInputs => "Transaction has no input".fmt(f),
+            TransactionError::EmptyOutputs => "Transaction has no output".fmt(f),
+            TransactionError::Oversize => "Transaction is too big".fmt(f),
+            TransactionError::OutputValueTooLarge => "Output value is too large".fmt(f),
+            TransactionError::TotalOutputValueTooLarge => "Total output value is too large".fmt(f),
+            TransactionError::DuplicateInputs => "Duplicate inputs".fmt(f),
+            TransactionError::CoinbaseScriptSize => "Invalid coinbase script size".fmt(f),
+            TransactionError::NullPrevout => "Input spends a null outpoint".fmt(f),
+        }
+    }
+}
+
+/// Errors of the block consensus checks
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum BlockError {
+    /// The header hash is above its target.
+    InvalidProofOfWork,
+    /// The merkle root of the transactions doesn't match the header.
+    MerkleRootMismatch,
+    /// The transactions contain a duplicated subtree of the merkle tree (CVE-2012-2459).
+    MutatedMerkleTree,
+    /// The block has no transaction, or weighs too much.
+    Size,
+    /// The first transaction is not a coinbase.
+    MissingCoinbase,
+    /// A transaction other than the first one is a coinbase.
+    MultipleCoinbase,
+    /// The transaction at the index fails the transaction checks.
+    Transaction(usize, TransactionError),
+    /// The legacy signature operations cost more than `MAX_BLOCK_SIGOPS_COST`.
+    Sigops,
+    /// The coinbase witness of a block with a witness commitment is not a single 32-byte value.
+    WitnessNonceSize,
+    /// The witness commitment doesn't match the witnesses of the transactions.
+    WitnessMerkleMismatch,
+    /// A transaction has a witness, but the block has no witness commitment.
+    UnexpectedWitness,
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for BlockError {}
+
+impl core::fmt::Display for BlockError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            BlockError::InvalidProofOfWork => "Invalid proof of work".fmt(f),
+            BlockError::MerkleRootMismatch => "Merkle root mismatch".fmt(f),
+            BlockError::MutatedMerkleTree => "Duplicate transactions in the merkle tree".fmt(f),
+            BlockError::Size => "Invalid block size".fmt(f),
+            BlockError::MissingCoinbase => "First transaction is not a coinbase".fmt(f),
+            BlockError::MultipleCoinbase => "More than one coinbase".fmt(f),
+            BlockError::Transaction(index, error) => {
+                write!(f, "Invalid transaction {}: {}", index, error)
+            }
+            BlockError::Sigops => "Too many signature operations".fmt(f),
+            BlockError::WitnessNonceSize => "Invalid witness reserved value".fmt(f),
+            BlockError::WitnessMerkleMismatch => "Witness commitment mismatch".fmt(f),
+            BlockError::UnexpectedWitness => "Unexpected witness".fmt(f),
+        }
+    }
+}
+
+/// Errors of the checks of the transaction inputs against the unspent outputs
+#[derive(Debug, PartialEq)]
+pub enum InputsError {
+    /// The output spent by the input at the index is missing or already spent.
+    MissingInput(usize),
+    /// The input at the index spends a coinbase output with less than `COINBASE_MATURITY`
+    /// confirmations.
+    PrematureCoinbaseSpend(usize),
+    /// A spent output value or the sum of the spent output values is above `MAX_MONEY`.
+    InputValueOutOfRange,
+    /// The outputs spend more than the inputs.
+    InsufficientInputValue,
+    /// A relative lock-time of the inputs (BIP68) has not expired.
+    SequenceLock,
+    /// The median time past needed by a time-based relative lock-time is unknown.
+    UnknownMedianTimePast,
+    /// The script of the input at the index fails.
+    Script(usize, ScriptError),
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for InputsError {}
+
+impl core::fmt::Display for InputsError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            InputsError::MissingInput(index) => write!(f, "Missing input {}", index),
+            InputsError::PrematureCoinbaseSpend(index) => {
+                write!(f, "Input {} spends an immature coinbase", index)
+            }
+            InputsError::InputValueOutOfRange => "Input value out of range".fmt(f),
+            InputsError::InsufficientInputValue => "Input value below output value".fmt(f),
+            InputsError::SequenceLock => "Relative lock-time not satisfied".fmt(f),
+            InputsError::UnknownMedianTimePast => "Unknown median time past".fmt(f),
+            InputsError::Script(index, error) => {
+                write!(f, "Script of input {} failed: {}", index, error)
+            }
+        }
+    }
+}
+
+/// Violations of the standardness policy
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PolicyError {
+    /// The version is not between 1 and `MAX_STANDARD_TX_VERSION`.
+    Version,
+    /// The transaction weighs more than `MAX_STANDARD_TX_WEIGHT`.
+    TxSize,
+    /// The transaction without witness is smaller than `MIN_STANDARD_TX_NONWITNESS_SIZE`.
+    TxSizeSmall,
+    /// The scriptSig of the input at the index is larger than `MAX_STANDARD_SCRIPTSIG_SIZE`.
+    ScriptSigSize(usize),
+    /// The scriptSig of the input at the index has non-push operations.
+    ScriptSigNotPushOnly(usize),
+    /// The script of the output at the index is not of a standard type.
+    ScriptPubKey(usize),
+    /// The value of the output at the index is below its dust threshold.
+    Dust(usize),
+    /// The transaction has more than one OP_RETURN output.
+    MultiOpReturn,
+    /// The output spent by the input at the index is missing or already spent.
+    MissingInput(usize),
+    /// The input at the index spends a non-standard output, or a P2SH redeem script with more
+    /// than `MAX_P2SH_SIGOPS` signature operations.
+    NonStandardInput(usize),
+    /// The witness of the input at the index exceeds the P2WSH or tapscript limits, or has an
+    /// annex.
+    NonStandardWitness(usize),
+    /// The sigop cost of the transaction is above `MAX_STANDARD_TX_SIGOPS_COST`.
+    Sigops,
+}
+
+impl PolicyError {
+    /// Returns the reject reason of Bitcoin Core for the violation, eg: in the result of
+    /// `testmempoolaccept`.
+    pub fn reason(&self) -> &'static str {
+        match self {
+            PolicyError::Version => "version",
+            PolicyError::TxSize => "tx-size",
+            PolicyError::TxSizeSmall => "tx-size-small",
+            PolicyError::ScriptSigSize(_) => "scriptsig-size",
+            PolicyError::ScriptSigNotPushOnly(_) => "scriptsig-not-pushonly",
+            PolicyError::ScriptPubKey(_) => "scriptpubkey",
+            PolicyError::Dust(_) => "dust",
+            PolicyError::MultiOpReturn => "multi-op-return",
+            PolicyError::MissingInput(_) => "bad-txns-inputs-missingorspent",
+            PolicyError::NonStandardInput(_) => "bad-txns-nonstandard-inputs",
+            PolicyError::NonStandardWitness(_) => "bad-witness-nonstandard",
+            PolicyError::Sigops => "bad-txns-too-many-sigops",
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for PolicyError {}
+
+impl core::fmt::Display for PolicyError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            PolicyError::Version => "Non-standard transaction version".fmt(f),
+            PolicyError::TxSize => "Transaction is too big".fmt(f),
+            PolicyError::TxSizeSmall => "Transaction is too small".fmt(f),
+            PolicyError::ScriptSigSize(index) => {
+                write!(f, "ScriptSig of input {} is too big", index)
+            }
+            PolicyError::ScriptSigNotPushOnly(index) => {
+                write!(f, "ScriptSig of input {} is not push-only", index)
+            }
+            PolicyError::ScriptPubKey(index) => {
+                write!(f, "Script of output {} is non-standard", index)
+            }
+            PolicyError::Dust(index) => write!(f, "Output {} is dust", index),
+            PolicyError::MultiOpReturn => "More than one OP_RETURN output".fmt(f),
+            PolicyError::MissingInput(index) => write!(f, "Missing input {}", index),
+            PolicyError::NonStandardInput(index) => {
+                write!(f, "Input {} spends a non-standard output", index)
+            }
+            PolicyError::NonStandardWitness(index) => {
+                write!(f, "Witness of input {} is non-standard", index)
+            }
+            PolicyError::Sigops => "Too many signature operations".fmt(f),
+        }
+    }
+}
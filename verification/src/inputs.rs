@@ -0,0 +1,344 @@
+//! Contextual transaction checks
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use light_bitcoin_chain::constants::{
+    MAX_MONEY, SEQUENCE_LOCKTIME_DISABLE_FLAG, SEQUENCE_LOCKTIME_MASK, SEQUENCE_LOCKTIME_TYPE_FLAG,
+};
+use light_bitcoin_chain::Transaction;
+use light_bitcoin_script::{
+    verify_script, TransactionInputSigner, TransactionSignatureChecker, VerificationFlags,
+};
+
+use crate::error::InputsError;
+use crate::utxo::{Coin, UtxoView};
+
+/// Number of confirmations of a coinbase before its outputs can be spent.
+pub const COINBASE_MATURITY: u32 = 100;
+
+/// Granularity of the time-based relative lock-times, 2^9 = 512 seconds (BIP68).
+const SEQUENCE_LOCKTIME_GRANULARITY: u32 = 9;
+
+/// Checks the inputs of the transaction against the unspent outputs of the view, for its inclusion
+/// in the block at `height` (Bitcoin Core's `Consensus::CheckTxInputs`, `SequenceLocks` and
+/// `CheckInputScripts`), and returns the fee of the transaction.
+///
+/// The relative lock-times (BIP68) of version 2 transactions are enforced with
+/// `verify_checksequence`. The transaction must not be a coinbase.
+pub fn check_tx_inputs<V: UtxoView + ?Sized>(
+    transaction: &Transaction,
+    view: &V,
+    height: u32,
+    flags: &VerificationFlags,
+) -> Result<u64, InputsError> {
+    let coins = transaction
+        .inputs
+        .iter()
+        .enumerate()
+        .map(|(index, input)| {
+            view.coin(&input.previous_output)
+                .ok_or(InputsError::MissingInput(index))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut value_in = 0u64;
+    for (index, coin) in coins.iter().enumerate() {
+        if coin.is_coinbase && height.saturating_sub(coin.height) < COINBASE_MATURITY {
+            return Err(InputsError::PrematureCoinbaseSpend(index));
+        }
+        if coin.output.value > MAX_MONEY {
+            return Err(InputsError::InputValueOutOfRange);
+        }
+        value_in = value_in
+            .checked_add(coin.output.value)
+            .filter(|value| *value <= MAX_MONEY)
+            .ok_or(InputsError::InputValueOutOfRange)?;
+    }
+
+    let value_out = transaction.total_spends();
+    if value_in < value_out {
+        return Err(InputsError::InsufficientInputValue);
+    }
+
+    if flags.verify_checksequence {
+        check_sequence_locks(transaction, &coins, view, height)?;
+    }
+
+    let spent_outputs = coins
+        .iter()
+        .map(|coin| coin.output.clone())
+        .collect::<Vec<_>>();
+    let mut checker = TransactionSignatureChecker {
+        signer: TransactionInputSigner::from(transaction.clone()).with_spent_outputs(spent_outputs),
+        input_index: 0,
+        input_amount: 0,
+    };
+    for (index, (input, coin)) in transaction.inputs.iter().zip(&coins).enumerate() {
+        checker.input_index = index;
+        checker.input_amount = coin.output.value;
+        verify_script(
+            &input.script_sig.clone().into(),
+            &coin.output.script_pubkey.clone().into(),
+            &input.script_witness,
+            flags,
+            &checker,
+        )
+        .map_err(|error| InputsError::Script(index, error))?;
+    }
+
+    Ok(value_in - value_out)
+}
+
+/// Checks that the relative lock-times of the inputs (BIP68) have expired at `height`.
+fn check_sequence_locks<V: UtxoView + ?Sized>(
+    transaction: &Transaction,
+    coins: &[Coin],
+    view: &V,
+    height: u32,
+) -> Result<(), InputsError> {
+    if transaction.version < 2 {
+        return Ok(());
+    }
+
+    // Last height and time at which the transaction can't be included, -1 without lock
+    let mut min_height = -1i64;
+    let mut min_time = -1i64;
+    for (input, coin) in transaction.inputs.iter().zip(coins) {
+        if input.sequence & SEQUENCE_LOCKTIME_DISABLE_FLAG != 0 {
+            continue;
+        }
+        let value = i64::from(input.sequence & SEQUENCE_LOCKTIME_MASK);
+        if input.sequence & SEQUENCE_LOCKTIME_TYPE_FLAG != 0 {
+            // The lock starts at the median time past of the block before the coin
+            let coin_time = view
+                .median_time_past(coin.height.saturating_sub(1))
+                .ok_or(InputsError::UnknownMedianTimePast)?;
+            min_time =
+                min_time.max(i64::from(coin_time) + (value << SEQUENCE_LOCKTIME_GRANULARITY) - 1);
+        } else {
+            min_height = min_height.max(i64::from(coin.height) + value - 1);
+        }
+    }
+
+    if min_height >= i64::from(height) {
+        return Err(InputsError::SequenceLock);
+    }
+    if min_time >= 0 {
+        let time = view
+            .median_time_past(height.saturating_sub(1))
+            .ok_or(InputsError::UnknownMedianTimePast)?;
+        if min_time >= i64::from(time) {
+            return Err(InputsError::SequenceLock);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use light_bitcoin_chain::{OutPoint, TransactionInput, TransactionOutput};
+    use light_bitcoin_keys::{KeyPair, Network, Private};
+    use light_bitcoin_primitives::{Bytes, H256};
+    use light_bitcoin_script::{Builder, Error as ScriptError, Opcode, SignatureVersion};
+
+    use super::*;
+    use crate::utxo::MemoryUtxoView;
+
+    const SIGHASH_ALL: u32 = 1;
+
+    fn op_true() -> Bytes {
+        vec![Opcode::OP_1 as u8].into()
+    }
+
+    fn coin(value: u64, height: u32, is_coinbase: bool) -> Coin {
+        Coin {
+            output: TransactionOutput {
+                value,
+                script_pubkey: op_true(),
+            },
+            height,
+            is_coinbase,
+        }
+    }
+
+    fn spend(outpoint: OutPoint, value: u64) -> Transaction {
+        Transaction {
+            version: 2,
+            inputs: vec![TransactionInput {
+                previous_output: outpoint,
+                ..Default::default()
+            }],
+            outputs: vec![TransactionOutput {
+                value,
+                script_pubkey: op_true(),
+            }],
+            lock_time: 0,
+        }
+    }
+
+    fn flags() -> VerificationFlags {
+        VerificationFlags::default()
+            .verify_p2sh(true)
+            .verify_witness(true)
+            .verify_checksequence(true)
+    }
+
+    #[test]
+    fn test_check_tx_inputs() {
+        let outpoint = OutPoint::new(H256::repeat_byte(1), 0);
+        let mut view = MemoryUtxoView::default();
+        let tx = spend(outpoint, 40_000);
+        assert_eq!(
+            check_tx_inputs(&tx, &view, 200, &flags()),
+            Err(InputsError::MissingInput(0))
+        );
+
+        view.insert(outpoint, coin(50_000, 100, false));
+        assert_eq!(check_tx_inputs(&tx, &view, 200, &flags()), Ok(10_000));
+        assert_eq!(
+            check_tx_inputs(&spend(outpoint, 50_001), &view, 200, &flags()),
+            Err(InputsError::InsufficientInputValue)
+        );
+
+        view.insert(outpoint, coin(MAX_MONEY + 1, 100, false));
+        assert_eq!(
+            check_tx_inputs(&tx, &view, 200, &flags()),
+            Err(InputsError::InputValueOutOfRange)
+        );
+
+        // The sum of the input values is checked without overflowing
+        let outpoint2 = OutPoint::new(H256::repeat_byte(2), 0);
+        view.insert(outpoint, coin(MAX_MONEY, 100, false));
+        view.insert(outpoint2, coin(u64::MAX, 100, false));
+        let mut tx2 = tx.clone();
+        tx2.inputs.push(TransactionInput {
+            previous_output: outpoint2,
+            ..Default::default()
+        });
+        assert_eq!(
+            check_tx_inputs(&tx2, &view, 200, &flags()),
+            Err(InputsError::InputValueOutOfRange)
+        );
+        view.insert(outpoint2, coin(1, 100, false));
+        assert_eq!(
+            check_tx_inputs(&tx2, &view, 200, &flags()),
+            Err(InputsError::InputValueOutOfRange)
+        );
+
+        // Coinbase outputs mature after 100 blocks
+        view.insert(outpoint, coin(50_000, 100, true));
+        assert_eq!(
+            check_tx_inputs(&tx, &view, 199, &flags()),
+            Err(InputsError::PrematureCoinbaseSpend(0))
+        );
+        assert_eq!(check_tx_inputs(&tx, &view, 200, &flags()), Ok(10_000));
+
+        view.insert(
+            outpoint,
+            Coin {
+                output: TransactionOutput {
+                    value: 50_000,
+                    script_pubkey: vec![Opcode::OP_0 as u8].into(),
+                },
+                height: 100,
+                is_coinbase: false,
+            },
+        );
+        assert_eq!(
+            check_tx_inputs(&tx, &view, 200, &flags()),
+            Err(InputsError::Script(0, ScriptError::EvalFalse))
+        );
+    }
+
+    #[test]
+    fn test_check_tx_inputs_signature() {
+        let keypair = KeyPair::from_private(Private {
+            network: Network::Mainnet,
+            secret: H256::repeat_byte(1),
+            compressed: true,
+        })
+        .unwrap();
+        let script_pubkey = Builder::default()
+            .push_data(keypair.public())
+            .push_opcode(Opcode::OP_CHECKSIG)
+            .into_script();
+
+        let outpoint = OutPoint::new(H256::repeat_byte(1), 0);
+        let mut view = MemoryUtxoView::default();
+        view.insert(
+            outpoint,
+            Coin {
+                output: TransactionOutput {
+                    value: 50_000,
+                    script_pubkey: script_pubkey.to_bytes(),
+                },
+                height: 100,
+                is_coinbase: false,
+            },
+        );
+
+        let mut tx = spend(outpoint, 40_000);
+        tx.inputs[0] = TransactionInputSigner::from(tx.clone())
+            .signed_input(
+                &keypair,
+                0,
+                50_000,
+                &script_pubkey,
+                SignatureVersion::Base,
+                SIGHASH_ALL,
+            )
+            .unwrap();
+        assert_eq!(check_tx_inputs(&tx, &view, 200, &flags()), Ok(10_000));
+
+        tx.outputs[0].value = 30_000;
+        assert_eq!(
+            check_tx_inputs(&tx, &view, 200, &flags()),
+            Err(InputsError::Script(0, ScriptError::EvalFalse))
+        );
+    }
+
+    // https://github.com/bitcoin/bips/blob/master/bip-0068.mediawiki
+    #[test]
+    fn test_sequence_locks() {
+        let outpoint = OutPoint::new(H256::repeat_byte(1), 0);
+        let mut view = MemoryUtxoView::default();
+        view.insert(outpoint, coin(50_000, 100, false));
+
+        // Locked for 10 blocks after the block of the coin
+        let mut tx = spend(outpoint, 40_000);
+        tx.inputs[0].sequence = 10;
+        assert_eq!(
+            check_tx_inputs(&tx, &view, 109, &flags()),
+            Err(InputsError::SequenceLock)
+        );
+        assert_eq!(check_tx_inputs(&tx, &view, 110, &flags()), Ok(10_000));
+
+        // Not enforced for version 1 transactions, or when disabled
+        let mut unlocked = tx.clone();
+        unlocked.version = 1;
+        assert_eq!(check_tx_inputs(&unlocked, &view, 109, &flags()), Ok(10_000));
+        let mut unlocked = tx.clone();
+        unlocked.inputs[0].sequence |= SEQUENCE_LOCKTIME_DISABLE_FLAG;
+        assert_eq!(check_tx_inputs(&unlocked, &view, 109, &flags()), Ok(10_000));
+        assert_eq!(
+            check_tx_inputs(&tx, &view, 109, &flags().verify_checksequence(false)),
+            Ok(10_000)
+        );
+
+        // Locked for 512 seconds after the median time past of the block before the coin
+        tx.inputs[0].sequence = SEQUENCE_LOCKTIME_TYPE_FLAG | 1;
+        assert_eq!(
+            check_tx_inputs(&tx, &view, 110, &flags()),
+            Err(InputsError::UnknownMedianTimePast)
+        );
+        view.set_median_time_past(99, 1_000);
+        view.set_median_time_past(109, 1_511);
+        view.set_median_time_past(110, 1_512);
+        assert_eq!(
+            check_tx_inputs(&tx, &view, 110, &flags()),
+            Err(InputsError::SequenceLock)
+        );
+        assert_eq!(check_tx_inputs(&tx, &view, 111, &flags()), Ok(10_000));
+    }
+}
@@ -0,0 +1,28 @@
+//! Consensus checks of transactions and blocks.
+//!
+//! The context-free checks follow Bitcoin Core's `CheckTransaction` and `CheckBlock`, and reject
+//! malformed transactions and blocks without looking up the chain or the spent outputs. The
+//! inputs of the transactions are then checked against a `UtxoView` of the unspent outputs.
+//!
+//! The `policy` checks tell whether a valid transaction is standard, and will be relayed by
+//! Bitcoin Core nodes with their default settings.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(not(feature = "std"))]
+extern crate alloc;
+
+mod block;
+mod error;
+mod inputs;
+pub mod policy;
+mod transaction;
+mod utxo;
+
+pub use self::block::check_block;
+pub use self::error::{BlockError, InputsError, PolicyError, TransactionError};
+pub use self::inputs::{check_tx_inputs, COINBASE_MATURITY};
+pub use self::transaction::{
+    check_transaction, MAX_COINBASE_SCRIPT_SIZE, MIN_COINBASE_SCRIPT_SIZE,
+};
+pub use self::utxo::{Coin, MemoryUtxoView, UtxoView};
@@ -0,0 +1,584 @@
+//! Standardness policy
+//!
+//! Transactions which are valid but non-standard are not relayed nor mined by Bitcoin Core with
+//! its default settings. The checks follow its `IsStandardTx`, `AreInputsStandard` and
+//! `IsWitnessStandard`, but report every violation instead of the first one.
+
+#[cfg(not(feature = "std"))]
+use alloc::vec::Vec;
+
+use light_bitcoin_chain::constants::{MAX_BLOCK_SIGOPS_COST, WITNESS_SCALE_FACTOR};
+use light_bitcoin_chain::{Transaction, TransactionOutput};
+use light_bitcoin_primitives::Bytes;
+use light_bitcoin_script::miniscript::MAX_STANDARD_P2WSH_SCRIPT_SIZE;
+pub use light_bitcoin_script::miniscript::MAX_STANDARD_P2WSH_STACK_ITEMS;
+use light_bitcoin_script::{
+    eval_script, NoopSignatureChecker, Opcode, Script, ScriptType, SignatureVersion, Stack,
+    TransactionSigOps, VerificationFlags, ANNEX_TAG, MAX_SCRIPT_SIZE, TAPROOT_LEAF_MASK,
+    TAPROOT_LEAF_TAPSCRIPT,
+};
+use light_bitcoin_serialization::serialize;
+
+use crate::error::PolicyError;
+use crate::utxo::UtxoView;
+
+/// Largest standard transaction version.
+pub const MAX_STANDARD_TX_VERSION: i32 = 2;
+/// Maximum weight of a standard transaction.
+pub const MAX_STANDARD_TX_WEIGHT: usize = 400_000;
+/// Minimum size of a standard transaction without witness, so that it can't be mistaken for a
+/// 64-byte inner node of the merkle tree.
+pub const MIN_STANDARD_TX_NONWITNESS_SIZE: usize = 65;
+/// Maximum size of a standard scriptSig, enough for a 15-of-15 P2SH multisig with compressed
+/// keys.
+pub const MAX_STANDARD_SCRIPTSIG_SIZE: usize = 1650;
+/// Maximum size of a standard OP_RETURN output script.
+pub const MAX_OP_RETURN_RELAY: usize = 83;
+/// Maximum number of keys of a standard bare multisig output.
+pub const MAX_STANDARD_BARE_MULTISIG_KEYS: u32 = 3;
+/// Maximum number of signature operations of a standard P2SH redeem script.
+pub const MAX_P2SH_SIGOPS: usize = 15;
+/// Maximum sigop cost of a standard transaction.
+pub const MAX_STANDARD_TX_SIGOPS_COST: usize = MAX_BLOCK_SIGOPS_COST / 5;
+/// Maximum size of the stack items of a standard P2WSH witness, without the witness script.
+pub const MAX_STANDARD_P2WSH_STACK_ITEM_SIZE: usize = 80;
+/// Maximum size of the stack items of a standard tapscript witness.
+pub const MAX_STANDARD_TAPSCRIPT_STACK_ITEM_SIZE: usize = 80;
+/// Fee rate, in satoshis per 1000 bytes, below which spending an output costs more than its
+/// value.
+pub const DUST_RELAY_TX_FEE: u64 = 3000;
+
+/// Returns the script verification flags enforced on every transaction, whose violation makes
+/// it invalid rather than non-standard (Bitcoin Core's `MANDATORY_SCRIPT_VERIFY_FLAGS`).
+pub fn mandatory_script_verify_flags() -> VerificationFlags {
+    VerificationFlags::default()
+        .verify_p2sh(true)
+        .verify_dersig(true)
+        .verify_nulldummy(true)
+        .verify_locktime(true)
+        .verify_checksequence(true)
+        .verify_witness(true)
+        .verify_taproot(true)
+}
+
+/// Returns the script verification flags of standard transactions (Bitcoin Core's
+/// `STANDARD_SCRIPT_VERIFY_FLAGS`).
+pub fn standard_script_verify_flags() -> VerificationFlags {
+    mandatory_script_verify_flags()
+        .verify_strictenc(true)
+        .verify_minimaldata(true)
+        .verify_discourage_upgradable_nops(true)
+        .verify_cleanstack(true)
+        .verify_low_s(true)
+        .verify_minimalif(true)
+        .verify_nullfail(true)
+        .verify_discourage_upgradable_witness_program(true)
+        .verify_witness_pubkeytype(true)
+        .verify_discourage_upgradable_taproot_version(true)
+        .verify_discourage_op_success(true)
+        .verify_discourage_upgradable_pubkeytype(true)
+        .verify_const_scriptcode(true)
+}
+
+/// Returns true if the output script is of a standard type (Bitcoin Core's `IsStandard`).
+///
+/// Bare multisig outputs must have at most 3 keys and OP_RETURN outputs at most
+/// `MAX_OP_RETURN_RELAY` bytes. Witness programs of unknown versions are standard.
+pub fn is_standard_script_pubkey(script: &Script) -> bool {
+    match script.script_type() {
+        ScriptType::Multisig => match script.parse_redeem_script() {
+            Some((_, m, n)) => (1..=MAX_STANDARD_BARE_MULTISIG_KEYS).contains(&n) && m >= 1,
+            None => false,
+        },
+        ScriptType::NullData => script.len() <= MAX_OP_RETURN_RELAY,
+        ScriptType::NonStandard => matches!(
+            script.parse_witness_program(),
+            Some((version, _)) if version != 0
+        ),
+        _ => true,
+    }
+}
+
+/// Returns the value below which the output is dust: spending it would cost more than
+/// `dust_relay_fee` per 1000 bytes times the size of the output and of its spending input.
+pub fn dust_threshold(output: &TransactionOutput, dust_relay_fee: u64) -> u64 {
+    let script: Script = output.script_pubkey.clone().into();
+    if is_unspendable(&script) {
+        return 0;
+    }
+    let mut size = serialize(output).len();
+    size += match script.parse_witness_program() {
+        // Outpoint, empty scriptSig and sequence, with the witness discounted
+        Some(_) => 32 + 4 + 1 + 107 / WITNESS_SCALE_FACTOR + 4,
+        // Outpoint, scriptSig with a signature and a compressed key, and sequence
+        None => 32 + 4 + 1 + 107 + 4,
+    };
+    // Rounded up like the fees of Bitcoin Core's `CFeeRate::GetFee`
+    (dust_relay_fee * size as u64).div_ceil(1000)
+}
+
+/// Returns true if the value of the output is below its dust threshold at `DUST_RELAY_TX_FEE`.
+pub fn is_dust(output: &TransactionOutput) -> bool {
+    output.value < dust_threshold(output, DUST_RELAY_TX_FEE)
+}
+
+/// Checks the transaction against the standardness rules which don't depend on the spent
+/// outputs (Bitcoin Core's `IsStandardTx`).
+pub fn check_standard_tx(transaction: &Transaction) -> Result<(), Vec<PolicyError>> {
+    let mut errors = Vec::new();
+    if !(1..=MAX_STANDARD_TX_VERSION).contains(&transaction.version) {
+        errors.push(PolicyError::Version);
+    }
+    if transaction.weight() > MAX_STANDARD_TX_WEIGHT {
+        errors.push(PolicyError::TxSize);
+    }
+    if transaction.base_size() < MIN_STANDARD_TX_NONWITNESS_SIZE {
+        errors.push(PolicyError::TxSizeSmall);
+    }
+
+    for (index, input) in transaction.inputs.iter().enumerate() {
+        if input.script_sig.len() > MAX_STANDARD_SCRIPTSIG_SIZE {
+            errors.push(PolicyError::ScriptSigSize(index));
+        }
+        if !Script::from(input.script_sig.clone()).is_push_only() {
+            errors.push(PolicyError::ScriptSigNotPushOnly(index));
+        }
+    }
+
+    let mut null_data_count = 0;
+    for (index, output) in transaction.outputs.iter().enumerate() {
+        let script: Script = output.script_pubkey.clone().into();
+        if !is_standard_script_pubkey(&script) {
+            errors.push(PolicyError::ScriptPubKey(index));
+        } else if script.is_null_data_script() {
+            null_data_count += 1;
+        } else if is_dust(output) {
+            errors.push(PolicyError::Dust(index));
+        }
+    }
+    if null_data_count > 1 {
+        errors.push(PolicyError::MultiOpReturn);
+    }
+
+    match errors.is_empty() {
+        true => Ok(()),
+        false => Err(errors),
+    }
+}
+
+/// Checks the inputs of the transaction against the outputs they spend (Bitcoin Core's
+/// `AreInputsStandard` and `IsWitnessStandard`) and, if they are standard, the sigop cost of
+/// the transaction.
+///
+/// The transaction must not be a coinbase.
+pub fn check_standard_inputs<V: UtxoView + ?Sized>(
+    transaction: &Transaction,
+    view: &V,
+) -> Result<(), Vec<PolicyError>> {
+    let mut errors = Vec::new();
+    let mut spent_outputs = Vec::with_capacity(transaction.inputs.len());
+    for (index, input) in transaction.inputs.iter().enumerate() {
+        let coin = match view.coin(&input.previous_output) {
+            Some(coin) => coin,
+            None => {
+                errors.push(PolicyError::MissingInput(index));
+                continue;
+            }
+        };
+        let script_pubkey: Script = coin.output.script_pubkey.clone().into();
+        let script_sig: Script = input.script_sig.clone().into();
+        if !is_standard_input(&script_sig, &script_pubkey) {
+            errors.push(PolicyError::NonStandardInput(index));
+        }
+        if !input.script_witness.is_empty()
+            && !is_standard_witness(&script_sig, &script_pubkey, &input.script_witness)
+        {
+            errors.push(PolicyError::NonStandardWitness(index));
+        }
+        spent_outputs.push(coin.output);
+    }
+
+    // The sigops of non-standard inputs are not counted
+    if !errors.is_empty() {
+        return Err(errors);
+    }
+
+    if transaction.sigop_cost(&spent_outputs, &standard_script_verify_flags())
+        > MAX_STANDARD_TX_SIGOPS_COST
+    {
+        return Err(vec![PolicyError::Sigops]);
+    }
+    Ok(())
+}
+
+/// Returns true if the output can never be spent: OP_RETURN outputs and oversized scripts.
+fn is_unspendable(script: &Script) -> bool {
+    (!script.is_empty() && script[0] == Opcode::OP_RETURN as u8) || script.len() > MAX_SCRIPT_SIZE
+}
+
+/// Returns the stack left by the push-only scriptSig, `None` if it isn't push-only or fails.
+fn eval_push_only(script_sig: &Script) -> Option<Stack<Bytes>> {
+    if !script_sig.is_push_only() {
+        return None;
+    }
+    let mut stack = Stack::new();
+    eval_script(
+        &mut stack,
+        script_sig,
+        &VerificationFlags::default(),
+        &NoopSignatureChecker,
+        SignatureVersion::Base,
+    )
+    .ok()?;
+    Some(stack)
+}
+
+/// Returns true if the input spends a standard output, and a P2SH redeem script with at most
+/// `MAX_P2SH_SIGOPS` signature operations.
+fn is_standard_input(script_sig: &Script, script_pubkey: &Script) -> bool {
+    match script_pubkey.script_type() {
+        // Spending unknown witness versions is non-standard, unlike creating them
+        ScriptType::NonStandard => matches!(
+            script_pubkey.parse_witness_program(),
+            Some((1, program)) if program.len() == 32
+        ),
+        ScriptType::ScriptHash => match eval_push_only(script_sig) {
+            Some(stack) => match stack.last() {
+                Ok(redeem_script) => {
+                    Script::from(redeem_script.clone()).sigops_count(false, true) <= MAX_P2SH_SIGOPS
+                }
+                Err(_) => false,
+            },
+            None => false,
+        },
+        _ => true,
+    }
+}
+
+/// Returns true if the witness of the input is within the limits of the P2WSH or tapscript
+/// witnesses, and has no annex.
+fn is_standard_witness(script_sig: &Script, script_pubkey: &Script, witness: &[Bytes]) -> bool {
+    let (program_script, nested) = match script_pubkey.is_pay_to_script_hash() {
+        true => match eval_push_only(script_sig).and_then(|stack| stack.last().ok().cloned()) {
+            Some(redeem_script) => (Script::from(redeem_script), true),
+            None => return false,
+        },
+        false => (script_pubkey.clone(), false),
+    };
+    let (version, program) = match program_script.parse_witness_program() {
+        Some(program) => program,
+        // Witness of a non-witness output
+        None => return false,
+    };
+
+    match (version, program.len(), witness.split_last()) {
+        (0, 32, Some((witness_script, items))) => {
+            items.len() <= MAX_STANDARD_P2WSH_STACK_ITEMS
+                && witness_script.len() <= MAX_STANDARD_P2WSH_SCRIPT_SIZE
+                && items
+                    .iter()
+                    .all(|item| item.len() <= MAX_STANDARD_P2WSH_STACK_ITEM_SIZE)
+        }
+        (1, 32, Some((last, rest))) if !nested => {
+            if !rest.is_empty() && last.first() == Some(&ANNEX_TAG) {
+                return false;
+            }
+            match rest.split_last() {
+                // Key path spending
+                None => true,
+                Some((_, items)) => match last.first() {
+                    Some(control) if control & TAPROOT_LEAF_MASK == TAPROOT_LEAF_TAPSCRIPT => items
+                        .iter()
+                        .all(|item| item.len() <= MAX_STANDARD_TAPSCRIPT_STACK_ITEM_SIZE),
+                    Some(_) => true,
+                    None => false,
+                },
+            }
+        }
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use light_bitcoin_chain::{OutPoint, TransactionInput};
+    use light_bitcoin_crypto::{dhash160, sha256};
+    use light_bitcoin_primitives::{H160, H256};
+    use light_bitcoin_script::Builder;
+
+    use super::*;
+    use crate::utxo::{Coin, MemoryUtxoView};
+
+    fn output(value: u64, script: Script) -> TransactionOutput {
+        TransactionOutput {
+            value,
+            script_pubkey: script.to_bytes(),
+        }
+    }
+
+    fn transaction(outputs: Vec<TransactionOutput>) -> Transaction {
+        Transaction {
+            version: 2,
+            inputs: vec![TransactionInput {
+                previous_output: OutPoint::new(H256::repeat_byte(1), 0),
+                ..Default::default()
+            }],
+            outputs,
+            lock_time: 0,
+        }
+    }
+
+    fn multisig(m: u8, n: u8) -> Script {
+        let builder = (0..n).fold(
+            Builder::default().push_opcode(Opcode::from_u8(Opcode::OP_1 as u8 + m - 1).unwrap()),
+            |builder, i| builder.push_data(&[2 + i % 2; 33]),
+        );
+        builder
+            .push_opcode(Opcode::from_u8(Opcode::OP_1 as u8 + n - 1).unwrap())
+            .push_opcode(Opcode::OP_CHECKMULTISIG)
+            .into_script()
+    }
+
+    fn nulldata(data: &[u8]) -> Script {
+        Builder::default()
+            .push_opcode(Opcode::OP_RETURN)
+            .push_data(data)
+            .into_script()
+    }
+
+    fn witness_program(version: Opcode, program: &[u8]) -> Script {
+        Builder::default()
+            .push_opcode(version)
+            .push_data(program)
+            .into_script()
+    }
+
+    #[test]
+    fn test_standard_flags() {
+        let mandatory = mandatory_script_verify_flags();
+        let standard = standard_script_verify_flags();
+        assert!(mandatory.verify_p2sh && mandatory.verify_witness && mandatory.verify_taproot);
+        assert!(!mandatory.verify_cleanstack && !mandatory.verify_low_s);
+        assert!(standard.verify_p2sh && standard.verify_witness && standard.verify_taproot);
+        assert!(standard.verify_cleanstack && standard.verify_low_s);
+    }
+
+    #[test]
+    fn test_standard_script_pubkeys() {
+        let hash = H160::repeat_byte(1);
+        for script in &[
+            Builder::build_p2pkh(&hash),
+            Builder::build_p2sh(&hash),
+            Builder::build_p2wpkh(&hash),
+            Builder::build_p2wsh(&H256::repeat_byte(1)),
+            Builder::build_p2tr(&H256::repeat_byte(1)),
+            witness_program(Opcode::OP_2, &[1; 40]),
+            multisig(1, 3),
+            nulldata(&[1; 80]),
+        ] {
+            assert!(is_standard_script_pubkey(script), "{}", script);
+        }
+        for script in &[
+            witness_program(Opcode::OP_0, &[1; 25]),
+            multisig(1, 4),
+            nulldata(&[1; 81]),
+            Builder::default()
+                .push_opcode(Opcode::OP_1)
+                .push_opcode(Opcode::OP_DROP)
+                .into_script(),
+        ] {
+            assert!(!is_standard_script_pubkey(script), "{}", script);
+        }
+    }
+
+    #[test]
+    fn test_dust_thresholds() {
+        let hash = H160::repeat_byte(1);
+        let threshold = |script: Script| dust_threshold(&output(0, script), DUST_RELAY_TX_FEE);
+        assert_eq!(threshold(Builder::build_p2pkh(&hash)), 546);
+        assert_eq!(threshold(Builder::build_p2sh(&hash)), 540);
+        assert_eq!(threshold(Builder::build_p2wpkh(&hash)), 294);
+        assert_eq!(threshold(Builder::build_p2wsh(&H256::repeat_byte(1))), 330);
+        assert_eq!(threshold(Builder::build_p2tr(&H256::repeat_byte(1))), 330);
+        assert_eq!(threshold(Builder::build_nulldata(b"light-bitcoin")), 0);
+
+        // 98 bytes at 1001 sat/kvB cost 98.098 sat, rounded up
+        assert_eq!(
+            dust_threshold(&output(0, Builder::build_p2wpkh(&hash)), 1001),
+            99
+        );
+        assert_eq!(
+            dust_threshold(&output(0, Builder::build_p2wpkh(&hash)), 1),
+            1
+        );
+
+        assert!(is_dust(&output(293, Builder::build_p2wpkh(&hash))));
+        assert!(!is_dust(&output(294, Builder::build_p2wpkh(&hash))));
+    }
+
+    #[test]
+    fn test_check_standard_tx() {
+        let p2wpkh = Builder::build_p2wpkh(&H160::repeat_byte(1));
+        let tx = transaction(vec![output(10_000, p2wpkh.clone())]);
+        assert_eq!(check_standard_tx(&tx), Ok(()));
+
+        // Every violation is reported
+        let mut tx = transaction(vec![
+            output(10_000, p2wpkh.clone()),
+            output(100, p2wpkh),
+            output(0, Builder::build_nulldata(b"light")),
+            output(0, Builder::build_nulldata(b"bitcoin")),
+            output(10_000, multisig(1, 4)),
+        ]);
+        tx.version = 3;
+        tx.inputs[0].script_sig = Builder::default()
+            .push_opcode(Opcode::OP_1)
+            .push_opcode(Opcode::OP_DROP)
+            .into_script()
+            .to_bytes();
+        let errors = check_standard_tx(&tx).unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                PolicyError::Version,
+                PolicyError::ScriptSigNotPushOnly(0),
+                PolicyError::Dust(1),
+                PolicyError::ScriptPubKey(4),
+                PolicyError::MultiOpReturn,
+            ]
+        );
+        assert_eq!(errors[2].reason(), "dust");
+
+        let mut tx = transaction(vec![output(
+            0,
+            Builder::default()
+                .push_opcode(Opcode::OP_RETURN)
+                .into_script(),
+        )]);
+        tx.inputs[0].script_sig = vec![0u8; MAX_STANDARD_SCRIPTSIG_SIZE + 1].into();
+        assert_eq!(
+            check_standard_tx(&tx),
+            Err(vec![PolicyError::ScriptSigSize(0)])
+        );
+        tx.inputs[0].script_sig = Bytes::new();
+        assert_eq!(check_standard_tx(&tx), Err(vec![PolicyError::TxSizeSmall]));
+    }
+
+    #[test]
+    fn test_check_standard_inputs() {
+        let witness_script = Builder::default().push_opcode(Opcode::OP_1).into_script();
+        let p2wsh = Builder::build_p2wsh(&sha256(&witness_script));
+        let redeem_script = (0..=MAX_P2SH_SIGOPS)
+            .fold(Builder::default(), |builder, _| {
+                builder.push_opcode(Opcode::OP_CHECKSIG)
+            })
+            .into_script();
+        let item = Bytes::from(vec![1u8; MAX_STANDARD_P2WSH_STACK_ITEM_SIZE]);
+
+        // Spent scripts, with the scriptSig and witness of their inputs
+        let spends = vec![
+            (
+                p2wsh.clone(),
+                Bytes::new(),
+                vec![item.clone(), witness_script.to_bytes()],
+            ),
+            // P2WSH witnesses are limited to 100 items of 80 bytes
+            (p2wsh.clone(), Bytes::new(), {
+                let mut witness = vec![item; MAX_STANDARD_P2WSH_STACK_ITEMS + 1];
+                witness.push(witness_script.to_bytes());
+                witness
+            }),
+            (
+                p2wsh,
+                Bytes::new(),
+                vec![vec![1u8; 81].into(), witness_script.to_bytes()],
+            ),
+            // P2SH redeem scripts are limited to 15 signature operations
+            (
+                Builder::build_p2sh(&dhash160(&redeem_script)),
+                Builder::default()
+                    .push_data(&redeem_script)
+                    .into_script()
+                    .to_bytes(),
+                vec![],
+            ),
+            // Taproot witnesses have no annex
+            (
+                Builder::build_p2tr(&H256::repeat_byte(1)),
+                Bytes::new(),
+                vec![vec![1u8; 64].into()],
+            ),
+            (
+                Builder::build_p2tr(&H256::repeat_byte(1)),
+                Bytes::new(),
+                vec![vec![1u8; 64].into(), vec![ANNEX_TAG].into()],
+            ),
+            // Unknown witness versions can't be spent
+            (
+                witness_program(Opcode::OP_2, &[1; 32]),
+                Bytes::new(),
+                vec![],
+            ),
+        ];
+
+        let mut view = MemoryUtxoView::default();
+        let mut tx = transaction(vec![output(
+            10_000,
+            Builder::build_p2wpkh(&H160::repeat_byte(1)),
+        )]);
+        tx.inputs.clear();
+        for (index, (script_pubkey, script_sig, script_witness)) in spends.into_iter().enumerate() {
+            let previous_output = OutPoint::new(H256::repeat_byte(1), index as u32);
+            view.insert(
+                previous_output,
+                Coin {
+                    output: output(50_000, script_pubkey),
+                    height: 1,
+                    is_coinbase: false,
+                },
+            );
+            tx.inputs.push(TransactionInput {
+                previous_output,
+                script_sig,
+                script_witness,
+                ..Default::default()
+            });
+        }
+        tx.inputs.push(TransactionInput {
+            previous_output: OutPoint::new(H256::repeat_byte(2), 0),
+            ..Default::default()
+        });
+
+        assert_eq!(
+            check_standard_inputs(&tx, &view),
+            Err(vec![
+                PolicyError::NonStandardWitness(1),
+                PolicyError::NonStandardWitness(2),
+                PolicyError::NonStandardInput(3),
+                PolicyError::NonStandardWitness(5),
+                PolicyError::NonStandardInput(6),
+                PolicyError::MissingInput(7),
+            ])
+        );
+
+        let mut p2sh_spend = tx.inputs[3].clone();
+        tx.inputs = vec![tx.inputs[0].clone(), tx.inputs[4].clone()];
+        assert_eq!(check_standard_inputs(&tx, &view), Ok(()));
+
+        // Redeem scripts pushed by small integer opcodes have no signature operations
+        p2sh_spend.script_sig = Builder::default()
+            .push_opcode(Opcode::OP_1)
+            .into_script()
+            .to_bytes();
+        tx.inputs = vec![p2sh_spend];
+        assert_eq!(check_standard_inputs(&tx, &view), Ok(()));
+        tx.inputs[0].script_sig = Builder::default()
+            .push_opcode(Opcode::OP_RESERVED)
+            .into_script()
+            .to_bytes();
+        assert_eq!(
+            check_standard_inputs(&tx, &view),
+            Err(vec![PolicyError::NonStandardInput(0)])
+        );
+    }
+}
@@ -0,0 +1,149 @@
+//! Context-free transaction checks
+
+#[cfg(not(feature = "std"))]
+use alloc::collections::BTreeSet;
+#[cfg(feature = "std")]
+use std::collections::BTreeSet;
+
+use light_bitcoin_chain::constants::{MAX_BLOCK_WEIGHT, MAX_MONEY, WITNESS_SCALE_FACTOR};
+use light_bitcoin_chain::Transaction;
+
+use crate::error::TransactionError;
+
+/// Minimum size of the coinbase script.
+pub const MIN_COINBASE_SCRIPT_SIZE: usize = 2;
+/// Maximum size of the coinbase script.
+pub const MAX_COINBASE_SCRIPT_SIZE: usize = 100;
+
+/// Runs the checks of the transaction which don't depend on the chain (Bitcoin Core's
+/// `CheckTransaction`).
+pub fn check_transaction(transaction: &Transaction) -> Result<(), TransactionError> {
+    if transaction.inputs.is_empty() {
+        return Err(TransactionError::EmptyInputs);
+    }
+    if transaction.outputs.is_empty() {
+        return Err(TransactionError::EmptyOutputs);
+    }
+    if transaction.base_size() * WITNESS_SCALE_FACTOR > MAX_BLOCK_WEIGHT {
+        return Err(TransactionError::Oversize);
+    }
+
+    let mut total = 0u64;
+    for output in &transaction.outputs {
+        if output.value > MAX_MONEY {
+            return Err(TransactionError::OutputValueTooLarge);
+        }
+        total += output.value;
+        if total > MAX_MONEY {
+            return Err(TransactionError::TotalOutputValueTooLarge);
+        }
+    }
+
+    let mut outpoints = BTreeSet::new();
+    if !transaction
+        .inputs
+        .iter()
+        .all(|input| outpoints.insert(&input.previous_output))
+    {
+        return Err(TransactionError::DuplicateInputs);
+    }
+
+    if transaction.is_coinbase() {
+        let script_size = transaction.inputs[0].script_sig.len();
+        if !(MIN_COINBASE_SCRIPT_SIZE..=MAX_COINBASE_SCRIPT_SIZE).contains(&script_size) {
+            return Err(TransactionError::CoinbaseScriptSize);
+        }
+    } else if transaction
+        .inputs
+        .iter()
+        .any(|input| input.previous_output.is_null())
+    {
+        return Err(TransactionError::NullPrevout);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use light_bitcoin_chain::{OutPoint, TransactionInput, TransactionOutput};
+    use light_bitcoin_primitives::{Bytes, H256};
+
+    use super::*;
+
+    fn transaction() -> Transaction {
+        Transaction {
+            version: 1,
+            inputs: vec![TransactionInput {
+                previous_output: OutPoint::new(H256::repeat_byte(1), 0),
+                ..Default::default()
+            }],
+            outputs: vec![TransactionOutput {
+                value: 50_000,
+                script_pubkey: Bytes::new(),
+            }],
+            lock_time: 0,
+        }
+    }
+
+    // https://github.com/bitcoin/bitcoin/blob/master/src/consensus/tx_check.cpp
+    #[test]
+    fn test_check_transaction() {
+        assert_eq!(check_transaction(&transaction()), Ok(()));
+
+        let mut tx = transaction();
+        tx.inputs.clear();
+        assert_eq!(check_transaction(&tx), Err(TransactionError::EmptyInputs));
+
+        let mut tx = transaction();
+        tx.outputs.clear();
+        assert_eq!(check_transaction(&tx), Err(TransactionError::EmptyOutputs));
+
+        let mut tx = transaction();
+        tx.outputs[0].script_pubkey = vec![0u8; MAX_BLOCK_WEIGHT / 4].into();
+        assert_eq!(check_transaction(&tx), Err(TransactionError::Oversize));
+
+        let mut tx = transaction();
+        tx.outputs[0].value = MAX_MONEY;
+        assert_eq!(check_transaction(&tx), Ok(()));
+        tx.outputs[0].value = MAX_MONEY + 1;
+        assert_eq!(
+            check_transaction(&tx),
+            Err(TransactionError::OutputValueTooLarge)
+        );
+        tx.outputs[0].value = MAX_MONEY;
+        tx.outputs.push(TransactionOutput {
+            value: 1,
+            script_pubkey: Bytes::new(),
+        });
+        assert_eq!(
+            check_transaction(&tx),
+            Err(TransactionError::TotalOutputValueTooLarge)
+        );
+
+        let mut tx = transaction();
+        tx.inputs.push(tx.inputs[0].clone());
+        assert_eq!(
+            check_transaction(&tx),
+            Err(TransactionError::DuplicateInputs)
+        );
+
+        let mut tx = transaction();
+        tx.inputs
+            .push(TransactionInput::coinbase(vec![0; 2].into()));
+        assert_eq!(check_transaction(&tx), Err(TransactionError::NullPrevout));
+    }
+
+    #[test]
+    fn test_check_coinbase() {
+        for (size, valid) in [(1, false), (2, true), (100, true), (101, false)].iter() {
+            let mut tx = transaction();
+            tx.inputs[0] = TransactionInput::coinbase(vec![0; *size].into());
+            let expected = if *valid {
+                Ok(())
+            } else {
+                Err(TransactionError::CoinbaseScriptSize)
+            };
+            assert_eq!(check_transaction(&tx), expected);
+        }
+    }
+}
@@ -0,0 +1,144 @@
+//! Views of the unspent transaction outputs
+
+#[cfg(not(feature = "std"))]
+use alloc::collections::BTreeMap;
+#[cfg(feature = "std")]
+use std::collections::BTreeMap;
+
+use light_bitcoin_chain::{OutPoint, Transaction, TransactionOutput};
+
+/// Unspent transaction output.
+#[derive(Eq, PartialEq, Clone, Debug)]
+pub struct Coin {
+    /// The output, with its value and script
+    pub output: TransactionOutput,
+    /// Height of the block including the transaction of the output
+    pub height: u32,
+    /// Whether the output belongs to a coinbase
+    pub is_coinbase: bool,
+}
+
+/// Access to the unspent outputs of the chain the transactions are checked against.
+///
+/// Runtimes implement it on top of their storage maps; `MemoryUtxoView` keeps everything in
+/// memory.
+pub trait UtxoView {
+    /// Returns the unspent output of the outpoint.
+    fn coin(&self, outpoint: &OutPoint) -> Option<Coin>;
+
+    /// Returns the median time past of the block at the height, used by the time-based relative
+    /// lock-times (BIP68).
+    fn median_time_past(&self, height: u32) -> Option<u32>;
+}
+
+/// In-memory view of the unspent outputs.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryUtxoView {
+    coins: BTreeMap<OutPoint, Coin>,
+    median_times: BTreeMap<u32, u32>,
+}
+
+impl MemoryUtxoView {
+    /// Adds an unspent output.
+    pub fn insert(&mut self, outpoint: OutPoint, coin: Coin) {
+        self.coins.insert(outpoint, coin);
+    }
+
+    /// Removes an output, returning it if it was unspent.
+    pub fn remove(&mut self, outpoint: &OutPoint) -> Option<Coin> {
+        self.coins.remove(outpoint)
+    }
+
+    /// Spends the outputs of the inputs of the transaction and adds its outputs, included in the
+    /// block at the height.
+    pub fn apply_transaction(&mut self, transaction: &Transaction, height: u32) {
+        if !transaction.is_coinbase() {
+            for input in &transaction.inputs {
+                self.coins.remove(&input.previous_output);
+            }
+        }
+        let txid = transaction.hash();
+        for (index, output) in transaction.outputs.iter().enumerate() {
+            self.coins.insert(
+                OutPoint::new(txid, index as u32),
+                Coin {
+                    output: output.clone(),
+                    height,
+                    is_coinbase: transaction.is_coinbase(),
+                },
+            );
+        }
+    }
+
+    /// Sets the median time past of the block at the height.
+    pub fn set_median_time_past(&mut self, height: u32, time: u32) {
+        self.median_times.insert(height, time);
+    }
+
+    /// Returns the number of unspent outputs.
+    pub fn len(&self) -> usize {
+        self.coins.len()
+    }
+
+    /// Returns true if there is no unspent output.
+    pub fn is_empty(&self) -> bool {
+        self.coins.is_empty()
+    }
+}
+
+impl UtxoView for MemoryUtxoView {
+    fn coin(&self, outpoint: &OutPoint) -> Option<Coin> {
+        self.coins.get(outpoint).cloned()
+    }
+
+    fn median_time_past(&self, height: u32) -> Option<u32> {
+        self.median_times.get(&height).cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use light_bitcoin_chain::TransactionInput;
+    use light_bitcoin_primitives::H256;
+
+    use super::*;
+
+    #[test]
+    fn test_apply_transaction() {
+        let coinbase = Transaction {
+            version: 1,
+            inputs: vec![TransactionInput::coinbase(vec![1, 1].into())],
+            outputs: vec![TransactionOutput {
+                value: 50_000,
+                script_pubkey: Default::default(),
+            }],
+            lock_time: 0,
+        };
+        let mut view = MemoryUtxoView::default();
+        view.apply_transaction(&coinbase, 1);
+        let outpoint = OutPoint::new(coinbase.hash(), 0);
+        assert_eq!(
+            view.coin(&outpoint),
+            Some(Coin {
+                output: coinbase.outputs[0].clone(),
+                height: 1,
+                is_coinbase: true,
+            })
+        );
+
+        let spend = Transaction {
+            version: 1,
+            inputs: vec![TransactionInput {
+                previous_output: outpoint,
+                ..Default::default()
+            }],
+            outputs: coinbase.outputs.clone(),
+            lock_time: 0,
+        };
+        view.apply_transaction(&spend, 101);
+        assert_eq!(view.coin(&outpoint), None);
+        assert_eq!(view.len(), 1);
+        assert!(view.coin(&OutPoint::new(spend.hash(), 0)).is_some());
+        assert_eq!(view.coin(&OutPoint::new(H256::zero(), 0)), None);
+    }
+}